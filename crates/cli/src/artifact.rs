@@ -0,0 +1,108 @@
+//! `artifact` command: list, show and remove single artifacts
+
+use anyhow::Result;
+use clap::Subcommand;
+use core_pipeline::scan_set;
+use std::path::Path;
+
+/// Length of the ID prefix shown in listings (enough to be unique)
+const SHORT_ID_LEN: usize = 8;
+
+#[derive(Subcommand)]
+pub enum ArtifactCommand {
+    /// List the artifacts of a scan set
+    List {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
+    },
+
+    /// Show an artifact's metadata and text
+    Show {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
+
+        /// Artifact ID (or unique ID prefix)
+        id: String,
+    },
+
+    /// Remove an artifact and its images from a scan set
+    Remove {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
+
+        /// Artifact ID (or unique ID prefix)
+        id: String,
+    },
+}
+
+/// Run an artifact subcommand
+pub fn run(command: ArtifactCommand) -> Result<()> {
+    match command {
+        ArtifactCommand::List { scan_set } => list_artifacts(&scan_set),
+        ArtifactCommand::Show { scan_set, id } => show_artifact(&scan_set, &id),
+        ArtifactCommand::Remove { scan_set, id } => remove_artifact(&scan_set, &id),
+    }
+}
+
+fn list_artifacts(scan_set_dir: &str) -> Result<()> {
+    let (_, artifacts) = scan_set::load(Path::new(scan_set_dir))?;
+    println!(
+        "{:<8}  {:<16} {:>5} {:>6}  File",
+        "ID", "Kind", "Conf", "Chars"
+    );
+    for artifact in &artifacts {
+        let id = artifact.id.0.to_string();
+        let chars = artifact
+            .content_text
+            .as_deref()
+            .map_or(0, |text| text.chars().count());
+        println!(
+            "{:<8}  {:<16} {:>5.2} {:>6}  {}",
+            &id[..SHORT_ID_LEN],
+            format!("{:?}", artifact.layout_label),
+            artifact.metadata.confidence,
+            chars,
+            artifact
+                .metadata
+                .original_filenames
+                .first()
+                .map_or("", String::as_str)
+        );
+    }
+    println!("{} artifacts", artifacts.len());
+    Ok(())
+}
+
+fn show_artifact(scan_set_dir: &str, id: &str) -> Result<()> {
+    let (_, artifacts) = scan_set::load(Path::new(scan_set_dir))?;
+    let artifact = &artifacts[scan3data::find_artifact(&artifacts, id)?];
+    println!("ID:    {}", artifact.id.0);
+    println!("Kind:  {:?}", artifact.layout_label);
+    println!("Image: {}", artifact.raw_image_path.display());
+    if let Some(processed) = &artifact.processed_image_path {
+        println!("Processed: {}", processed.display());
+    }
+    println!("Metadata:");
+    println!("{}", serde_json::to_string_pretty(&artifact.metadata)?);
+    println!("Text:");
+    match artifact.content_text.as_deref() {
+        Some(text) if !text.trim().is_empty() => println!("{}", text.trim_end()),
+        _ => println!("(none)"),
+    }
+    Ok(())
+}
+
+fn remove_artifact(scan_set_dir: &str, id: &str) -> Result<()> {
+    let removed = scan3data::remove_artifact(Path::new(scan_set_dir), id)?;
+    println!(
+        "🗑️  Removed {:?} artifact {}",
+        removed.artifact.layout_label, removed.artifact.id.0
+    );
+    for file in &removed.files {
+        println!("   Deleted {}", file.display());
+    }
+    Ok(())
+}
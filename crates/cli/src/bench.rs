@@ -0,0 +1,122 @@
+//! `bench` command: OCR error rates of Tesseract and vision models against
+//! ground truth transcriptions
+
+use crate::{end_progress, print_json, progress};
+use anyhow::Result;
+use scan3data::{AnalyzeOptions, BenchReport};
+use std::fs;
+use std::path::Path;
+
+/// Length of the ID prefix shown per artifact
+const SHORT_ID_LEN: usize = 8;
+
+/// Benchmark a scan set and print the error rates of each configuration
+///
+/// Writes the full report as JSON if an output file is given; with `json`
+/// it is printed instead.
+pub async fn bench_scan_set(
+    scan_set_dir: &str,
+    truth_dir: &str,
+    options: &AnalyzeOptions,
+    vision_models: &[String],
+    output_file: Option<&str>,
+    json: bool,
+) -> Result<()> {
+    if !json {
+        println!(
+            "📏 Benchmarking OCR: {} (truth: {})",
+            scan_set_dir, truth_dir
+        );
+        for model in vision_models {
+            println!("👁️  Vision model: {}", model);
+        }
+    }
+
+    let report = scan3data::bench_scan_set(
+        Path::new(scan_set_dir),
+        Path::new(truth_dir),
+        options,
+        vision_models,
+        &mut |p| {
+            progress(
+                json,
+                format_args!("   {:<7} {}/{}", p.phase.as_str(), p.done, p.total),
+            )
+        },
+    )
+    .await?;
+    end_progress(json);
+
+    if let Some(output_file) = output_file {
+        fs::write(output_file, serde_json::to_string_pretty(&report)?)?;
+    }
+    if json {
+        return print_json(&report);
+    }
+
+    if report.artifacts.is_empty() {
+        println!("⚠️  No ground truth transcriptions found in {}", truth_dir);
+        return Ok(());
+    }
+    print_report(&report);
+    if let Some(output_file) = output_file {
+        println!("✅ Report: {}", output_file);
+    }
+    Ok(())
+}
+
+fn print_report(report: &BenchReport) {
+    let width = report
+        .configurations
+        .iter()
+        .map(String::len)
+        .max()
+        .unwrap_or(0);
+
+    println!("   Per artifact (CER / WER):");
+    for artifact in &report.artifacts {
+        println!(
+            "   {}  {}",
+            &artifact.artifact_id[..SHORT_ID_LEN],
+            artifact.image.display()
+        );
+        for configuration in &report.configurations {
+            match (
+                artifact.results.get(configuration),
+                artifact.failures.get(configuration),
+            ) {
+                (Some(accuracy), _) => println!(
+                    "     {:<width$}  {:7.2}% {:7.2}%",
+                    configuration,
+                    accuracy.char_error_rate() * 100.0,
+                    accuracy.word_error_rate() * 100.0,
+                ),
+                (None, Some(failure)) => {
+                    println!("     {:<width$}  failed: {}", configuration, failure)
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    println!(
+        "📊 Overall: {} artifact(s) compared ({} failed, {} without truth)",
+        report.compared,
+        report.artifacts.len() - report.compared,
+        report.without_truth
+    );
+    if report.compared == 0 {
+        return;
+    }
+    println!("     {:<width$}  {:>8} {:>8}", "", "CER", "WER");
+    for configuration in &report.configurations {
+        if let Some(accuracy) = report.totals.get(configuration) {
+            println!(
+                "     {:<width$}  {:7.2}% {:7.2}%",
+                configuration,
+                accuracy.char_error_rate() * 100.0,
+                accuracy.word_error_rate() * 100.0,
+            );
+        }
+    }
+}
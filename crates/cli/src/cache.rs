@@ -0,0 +1,41 @@
+//! `trim-cache` command: delete derived images no artifact refers to, and
+//! cached stage results on request
+
+use anyhow::Result;
+use core_pipeline::derived::DerivedStore;
+use core_pipeline::scan_set;
+use core_pipeline::stage_cache::StageCache;
+use std::path::Path;
+
+/// Delete unreferenced derived images, or all images and cached results of
+/// one stage
+pub fn trim_cache(scan_set_dir: &str, stage: Option<&str>) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (_manifest, mut artifacts) = scan_set::load(scan_set_path)?;
+
+    println!("🧹 Trimming derived images: {}", scan_set_dir);
+
+    let removed = DerivedStore::new(scan_set_path).trim(&mut artifacts, stage)?;
+    if let Some(stage) = stage {
+        // Artifacts no longer refer to the trimmed stage
+        for artifact in &mut artifacts {
+            let dropped = artifact.processed_image_path.as_ref().is_some_and(|path| {
+                !artifact
+                    .metadata
+                    .derived_images
+                    .iter()
+                    .any(|r| &r.path == path)
+            });
+            if dropped {
+                artifact.processed_image_path = None;
+            }
+        }
+        scan_set::save_artifacts(scan_set_path, &artifacts)?;
+
+        let results = StageCache::new(scan_set_path).clear(Some(stage))?;
+        println!("   Stage removed: {} ({} cached result(s))", stage, results);
+    }
+
+    println!("✅ Removed {} derived image(s)", removed);
+    Ok(())
+}
@@ -0,0 +1,64 @@
+//! `diff` command: compare two analyses of the same scans
+
+use anyhow::Result;
+use core_pipeline::diff::{DiffLine, ScanSetDiff};
+use std::path::Path;
+
+/// Compare two scan sets artifact by artifact, as text or as JSON
+pub fn diff_scan_sets(a: &str, b: &str, json: bool) -> Result<()> {
+    let diff = ScanSetDiff::of_scan_sets(Path::new(a), Path::new(b))?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    println!("🔀 Comparing scan sets:");
+    println!("   a: {}", a);
+    println!("   b: {}", b);
+
+    for artifact in diff.changed() {
+        let name = artifact
+            .original_filenames
+            .first()
+            .map_or(artifact.content_hash.as_str(), String::as_str);
+        println!();
+        println!("📄 {}", name);
+        if artifact.kind_changed() {
+            println!(
+                "   Classification: {:?} ({:.2}) -> {:?} ({:.2})",
+                artifact.kind.0, artifact.confidence.0, artifact.kind.1, artifact.confidence.1
+            );
+        }
+        if artifact.text_changed() {
+            println!("   Text: {} character(s) changed", artifact.char_changes);
+            for line in &artifact.lines {
+                match line {
+                    DiffLine::Same(_) => {}
+                    DiffLine::Removed(text) => println!("   - {}", text),
+                    DiffLine::Added(text) => println!("   + {}", text),
+                }
+            }
+        }
+    }
+
+    let text_changed = diff.artifacts.iter().filter(|a| a.text_changed()).count();
+    let kind_changed = diff.artifacts.iter().filter(|a| a.kind_changed()).count();
+    let char_changes: usize = diff.artifacts.iter().map(|a| a.char_changes).sum();
+    println!();
+    println!("📊 Summary:");
+    println!("   Matched artifacts: {}", diff.artifacts.len());
+    println!(
+        "   Text changed: {} ({} characters)",
+        text_changed, char_changes
+    );
+    println!("   Classification changed: {}", kind_changed);
+    if !diff.only_in_a.is_empty() || !diff.only_in_b.is_empty() {
+        println!(
+            "   Unmatched: {} only in a, {} only in b",
+            diff.only_in_a.len(),
+            diff.only_in_b.len()
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,106 @@
+//! `doctor` command: check external dependencies
+
+use crate::DEFAULT_VISION_MODEL;
+use anyhow::{bail, Result};
+use core_pipeline::keypunch::KeypunchModel;
+use core_pipeline::ocr::{check_tesseract, ocr_params};
+use llm_bridge::ollama::has_model;
+use llm_bridge::text::DEFAULT_TEXT_MODEL;
+use llm_bridge::{GeminiClient, OllamaClient, OllamaConfig};
+use scan3data::config::Config;
+
+/// How long to wait for Ollama to answer (it is expected on the local network)
+const OLLAMA_TIMEOUT_SECS: u64 = 5;
+
+/// Probe Tesseract, Ollama and the Gemini API key, with remediation steps
+/// for whatever is missing
+///
+/// Fails if a required dependency (Tesseract) is missing; optional ones
+/// only produce warnings.
+pub async fn run_doctor(config: &Config) -> Result<()> {
+    println!("🩺 Checking dependencies");
+    let mut missing = 0;
+
+    println!();
+    println!("Tesseract (required, OCR):");
+    let (language, _, _) = ocr_params(KeypunchModel::default());
+    match check_tesseract(KeypunchModel::default()) {
+        Ok(()) => println!(
+            "   ✅ Tesseract initialized with '{}' language data",
+            language
+        ),
+        Err(e) => {
+            missing += 1;
+            println!("   ❌ {}", e);
+            println!(
+                "   → Install Tesseract and its '{}' language data:",
+                language
+            );
+            println!("       macOS:  brew install tesseract");
+            println!(
+                "       Debian: sudo apt install tesseract-ocr tesseract-ocr-{}",
+                language
+            );
+            println!("   → If installed elsewhere, set TESSDATA_PREFIX to its tessdata directory");
+        }
+    }
+
+    println!();
+    println!("Ollama (optional, --use-llm):");
+    let ollama_config = OllamaConfig {
+        timeout_secs: OLLAMA_TIMEOUT_SECS,
+        ..config.ollama_config()
+    };
+    let base_url = ollama_config.base_url.clone();
+    match OllamaClient::new(ollama_config)?.list_models().await {
+        Ok(models) => {
+            println!(
+                "   ✅ Reachable at {} ({} model(s) pulled)",
+                base_url,
+                models.len()
+            );
+            let vision = config
+                .models
+                .vision
+                .as_deref()
+                .unwrap_or(DEFAULT_VISION_MODEL);
+            let text = config.models.text.as_deref().unwrap_or(DEFAULT_TEXT_MODEL);
+            for (role, model) in [("Vision", vision), ("Text", text)] {
+                if has_model(&models, model) {
+                    println!("   ✅ {} model {} is pulled", role, model);
+                } else {
+                    println!("   ⚠️  {} model {} is not pulled", role, model);
+                    println!("   → Run: ollama pull {}", model);
+                }
+            }
+        }
+        Err(e) => {
+            println!("   ⚠️  Not reachable at {}: {}", base_url, e);
+            println!("   → Install from https://ollama.com/ and start it with: ollama serve");
+            println!("   → Or set [ollama] base_url in scan3data.toml to where it runs");
+        }
+    }
+
+    println!();
+    println!("Gemini (optional, image cleaning):");
+    match config.gemini_config().and_then(GeminiClient::new) {
+        Ok(client) => match client.check_api_key().await {
+            Ok(()) => println!("   ✅ GEMINI_API_KEY is valid"),
+            Err(e) => {
+                println!("   ⚠️  GEMINI_API_KEY could not be verified: {}", e);
+                println!("   → Check the key at https://ai.google.dev/ and your network access");
+            }
+        },
+        Err(e) => {
+            println!("   ⚠️  {}", e);
+            println!("   → Get a key at https://ai.google.dev/ and: export GEMINI_API_KEY=...");
+        }
+    }
+
+    println!();
+    if missing > 0 {
+        bail!("{} required dependency(ies) missing", missing);
+    }
+    println!("✅ Ready to scan");
+    Ok(())
+}
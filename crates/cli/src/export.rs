@@ -0,0 +1,472 @@
+//! `export` command: write reconstructed documents in emulator formats, or
+//! the scan set as a repository layout, Markdown transcript, metadata
+//! table, IIIF manifest, METS/ALTO package, BagIt bag or source tree
+
+use anyhow::{Context, Result};
+use core_pipeline::derived::{record_derived, DerivedKey, DerivedStore};
+use core_pipeline::export::bagit::write_bag;
+use core_pipeline::export::exporter::{ExporterRegistry, SimhDeckExporter, BUILTIN_EXPORTERS};
+use core_pipeline::export::iiif::{iiif_manifest, IiifOptions};
+use core_pipeline::export::markdown::{plan_transcript, TranscriptLayout, THUMBNAIL_SIZE};
+use core_pipeline::export::metadata::{metadata_table, Delimiter};
+use core_pipeline::export::mets::plan_mets_package;
+use core_pipeline::export::page_ocr::{plan_page_ocr, PageOcrFormat};
+use core_pipeline::export::pdf::{page_lines, PdfImage, SearchablePdf};
+use core_pipeline::export::repository::{plan_repository, write_repository, ImageMode};
+use core_pipeline::export::simh::{deck_text, job_deck, simh_script};
+use core_pipeline::export::sources::plan_source_tree;
+use core_pipeline::image_loader::{load_image, LoadOptions};
+use core_pipeline::ocr::{ocr_cache_key, OcrOutput};
+use core_pipeline::preprocess::{compute_file_hash, preprocess_key};
+use core_pipeline::scan_set;
+use core_pipeline::stage_cache::StageCache;
+use core_pipeline::types::{HighLevelArtifact, PageArtifact, PageId, ScanSetManifest};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Formats exporting the whole scan set rather than documents, handled by
+/// their own functions below
+pub const SCAN_SET_FORMATS: [&str; 12] = [
+    "repository",
+    "markdown",
+    "mdbook",
+    "csv",
+    "tsv",
+    "iiif",
+    "mets",
+    "alto",
+    "hocr",
+    "pdf",
+    "bagit",
+    "sources",
+];
+
+/// Every `--format` value: the registered emulator formats, then the scan
+/// set formats
+pub fn format_names() -> Vec<&'static str> {
+    BUILTIN_EXPORTERS
+        .iter()
+        .map(|exporter| exporter.name())
+        .chain(SCAN_SET_FORMATS)
+        .collect()
+}
+
+/// Output file or directory name of a format when none is given
+///
+/// Emulator formats get their extension from the exporter.
+pub fn default_output_name(format: &str) -> &'static str {
+    match format {
+        "repository" => "repository",
+        "markdown" => "transcript",
+        "mdbook" => "book",
+        "csv" => "metadata.csv",
+        "tsv" => "metadata.tsv",
+        "iiif" => "manifest.json",
+        "mets" => "mets",
+        "alto" => "alto",
+        "hocr" => "hocr",
+        "pdf" => "scans.pdf",
+        "bagit" => "bag",
+        "sources" => "sources",
+        _ => "deck",
+    }
+}
+
+/// Export every reconstructed document the format accepts
+///
+/// Formats come from the registry. With more than one document, outputs
+/// are numbered (`deck_1.json`, ...); an output path without an extension
+/// gets the format's. With `simh` set to a DMS disk image, each card deck
+/// also gets a job stream (`deck.job`) and a simh ibm1130 script
+/// (`deck.ini`) running it. The `simh` binary deck is punched with the scan
+/// set's keypunch models.
+pub fn export_scan_set(
+    registry: &mut ExporterRegistry,
+    scan_set_dir: &str,
+    output_file: &str,
+    format: &str,
+    simh: Option<&str>,
+) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (manifest, _artifacts) = scan_set::load(scan_set_path)?;
+    registry.register(Box::new(SimhDeckExporter::new(manifest.keypunch)));
+
+    let exporter = registry.get(format).with_context(|| {
+        format!(
+            "Unknown export format: {} (use {}, {})",
+            format,
+            registry.names().join(", "),
+            SCAN_SET_FORMATS.join(", ")
+        )
+    })?;
+    if simh.is_some() && format != "card_deck" {
+        anyhow::bail!("--simh needs the card_deck format");
+    }
+
+    let documents: Vec<_> = scan_set::load_high_level(scan_set_path)?
+        .into_iter()
+        .filter(|document| exporter.accepts(document))
+        .collect();
+
+    if documents.is_empty() {
+        anyhow::bail!(
+            "No documents to export as {} in scan set: {}",
+            format,
+            scan_set_dir
+        );
+    }
+
+    println!(
+        "📦 Exporting {} document(s) from {} (format: {})",
+        documents.len(),
+        scan_set_dir,
+        format
+    );
+
+    let mut output_path = PathBuf::from(output_file);
+    if output_path.extension().is_none() {
+        output_path.set_extension(exporter.extension());
+    }
+    for (idx, document) in documents.iter().enumerate() {
+        let path = if documents.len() == 1 {
+            output_path.clone()
+        } else {
+            numbered_path(&output_path, idx + 1)
+        };
+        fs::write(&path, exporter.export(std::slice::from_ref(document))?)
+            .with_context(|| format!("Failed to write export: {}", path.display()))?;
+
+        let HighLevelArtifact::SourceListing(listing) = document else {
+            println!("   {}", path.display());
+            continue;
+        };
+        println!(
+            "   {} ({}, {} lines)",
+            path.display(),
+            listing.language,
+            listing.lines.len()
+        );
+
+        if let Some(dms_disk) = simh {
+            let job_path = path.with_extension("job");
+            let ini_path = path.with_extension("ini");
+            let file_name = |p: &Path| {
+                p.file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned()
+            };
+            fs::write(&job_path, deck_text(&job_deck(listing)))
+                .with_context(|| format!("Failed to write job deck: {}", job_path.display()))?;
+            let script = simh_script(
+                &file_name(&job_path),
+                &file_name(&path.with_extension("lst")),
+                dms_disk,
+            );
+            fs::write(&ini_path, script)
+                .with_context(|| format!("Failed to write simh script: {}", ini_path.display()))?;
+            println!(
+                "   {} (run with: ibm1130 {})",
+                job_path.display(),
+                file_name(&ini_path)
+            );
+        }
+    }
+
+    println!("✅ Export complete!");
+    Ok(())
+}
+
+/// Export every reconstructed document as a directory tree with README
+/// indexes, ready to commit to a preservation repository
+pub fn export_repository(
+    scan_set_dir: &str,
+    output_dir: &str,
+    reference_images: bool,
+) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (manifest, artifacts) = scan_set::load(scan_set_path)?;
+    let documents = scan_set::load_high_level(scan_set_path)?;
+
+    println!("📚 Exporting repository layout from {}", scan_set_dir);
+
+    let images = if reference_images {
+        ImageMode::Reference
+    } else {
+        ImageMode::Copy
+    };
+    let files = plan_repository(&manifest, &artifacts, &documents, images)?;
+    write_repository(scan_set_path, Path::new(output_dir), &files)?;
+
+    println!("✅ Wrote {} file(s) to {}", files.len(), output_dir);
+    Ok(())
+}
+
+/// Export each recovered program as a source file, with a manifest
+pub fn export_sources(scan_set_dir: &str, output_dir: &str) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let manifest = scan_set::load_manifest(scan_set_path)?;
+    let documents = scan_set::load_high_level(scan_set_path)?;
+    let cards = scan_set::load_cards(scan_set_path)?;
+
+    println!("🗃️  Exporting source tree from {}", scan_set_dir);
+
+    let files = plan_source_tree(&manifest, &documents, &cards)?;
+    if files.len() == 1 {
+        anyhow::bail!(
+            "No programs to export in scan set: {} (run `scan3data reconstruct` first)",
+            scan_set_dir
+        );
+    }
+    write_repository(scan_set_path, Path::new(output_dir), &files)?;
+
+    println!(
+        "✅ Wrote {} program(s) and a manifest to {}",
+        files.len() - 1,
+        output_dir
+    );
+    Ok(())
+}
+
+/// Export a Markdown transcript (single file or mdBook) with page thumbnails
+///
+/// Thumbnails are kept as derived images of the scan set, so later
+/// exports reuse them.
+pub fn export_transcript(
+    scan_set_dir: &str,
+    output_dir: &str,
+    layout: TranscriptLayout,
+) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (manifest, mut artifacts) = scan_set::load(scan_set_path)?;
+
+    println!("📝 Exporting Markdown transcript from {}", scan_set_dir);
+
+    let store = DerivedStore::new(scan_set_path);
+    let mut thumbnails = HashMap::new();
+    for artifact in &mut artifacts {
+        let raw_image_path = scan_set_path.join(&artifact.raw_image_path);
+        let source_hash = if artifact.metadata.content_hash.is_empty() {
+            compute_file_hash(&raw_image_path)?
+        } else {
+            artifact.metadata.content_hash.clone()
+        };
+        let key = DerivedKey::new(&source_hash, "thumbnail", &THUMBNAIL_SIZE)?;
+        if !store.contains(&key) {
+            let thumbnail = load_image(&raw_image_path, &LoadOptions::downscaled(THUMBNAIL_SIZE))?;
+            store.store(&key, &thumbnail)?;
+        }
+        let reference = DerivedStore::reference(&key);
+        thumbnails.insert(artifact.id, reference.path.clone());
+        record_derived(artifact, reference);
+    }
+    // Keep the thumbnails referenced so trim-cache leaves them alone
+    scan_set::save_artifacts(scan_set_path, &artifacts)?;
+
+    let files = plan_transcript(&manifest, &artifacts, &thumbnails, layout);
+    write_repository(scan_set_path, Path::new(output_dir), &files)?;
+
+    println!("✅ Wrote {} file(s) to {}", files.len(), output_dir);
+    Ok(())
+}
+
+/// Export one row of metadata per artifact as CSV or TSV
+pub fn export_metadata(scan_set_dir: &str, output_file: &str, delimiter: Delimiter) -> Result<()> {
+    let (_manifest, artifacts) = scan_set::load(Path::new(scan_set_dir))?;
+    fs::write(output_file, metadata_table(&artifacts, delimiter))
+        .with_context(|| format!("Failed to write export: {}", output_file))?;
+    println!(
+        "✅ Exported metadata of {} artifact(s) to {}",
+        artifacts.len(),
+        output_file
+    );
+    Ok(())
+}
+
+/// Export a IIIF Presentation 3.0 manifest with OCR text annotations
+///
+/// Image sizes are read from the image file headers.
+pub fn export_iiif(scan_set_dir: &str, output_file: &str, options: &IiifOptions) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (manifest, artifacts) = scan_set::load(scan_set_path)?;
+
+    let mut dimensions = HashMap::new();
+    for artifact in &artifacts {
+        let path = scan_set_path.join(&artifact.raw_image_path);
+        let size = image::image_dimensions(&path)
+            .with_context(|| format!("Failed to read image header: {}", path.display()))?;
+        dimensions.insert(artifact.id, size);
+    }
+
+    let json =
+        serde_json::to_string_pretty(&iiif_manifest(&manifest, &artifacts, &dimensions, options))?;
+    fs::write(output_file, json)
+        .with_context(|| format!("Failed to write export: {}", output_file))?;
+    println!(
+        "✅ Exported IIIF manifest of {} page(s) to {}",
+        artifacts.len(),
+        output_file
+    );
+    Ok(())
+}
+
+/// Export a METS/ALTO archival package: METS document, page images and
+/// one ALTO file per page
+///
+/// Word coordinates come from the OCR cache of `analyze`; pages analyzed
+/// before it kept word boxes get ALTO text without coordinates.
+pub fn export_mets(scan_set_dir: &str, output_dir: &str) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (manifest, artifacts) = scan_set::load(scan_set_path)?;
+
+    println!("🏛️  Exporting METS/ALTO package from {}", scan_set_dir);
+
+    let (ocr, sizes) = load_word_boxes(scan_set_path, &manifest, &artifacts)?;
+    let files = plan_mets_package(&manifest, &artifacts, &ocr, &sizes);
+    write_repository(scan_set_path, Path::new(output_dir), &files)?;
+
+    println!(
+        "✅ Wrote {} file(s) to {} ({} of {} page(s) with word coordinates)",
+        files.len(),
+        output_dir,
+        ocr.len(),
+        artifacts.len()
+    );
+    Ok(())
+}
+
+/// Export every page image with an ALTO or hOCR file of the same name
+pub fn export_page_ocr(scan_set_dir: &str, output_dir: &str, format: PageOcrFormat) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (manifest, artifacts) = scan_set::load(scan_set_path)?;
+
+    let name = match format {
+        PageOcrFormat::Alto => "ALTO",
+        PageOcrFormat::Hocr => "hOCR",
+    };
+    println!("🗺️  Exporting {} pages from {}", name, scan_set_dir);
+
+    let (ocr, sizes) = load_word_boxes(scan_set_path, &manifest, &artifacts)?;
+    let files = plan_page_ocr(&artifacts, &ocr, &sizes, format);
+    write_repository(scan_set_path, Path::new(output_dir), &files)?;
+
+    println!(
+        "✅ Wrote {} file(s) to {} ({} of {} page(s) with word coordinates)",
+        files.len(),
+        output_dir,
+        ocr.len(),
+        artifacts.len()
+    );
+    Ok(())
+}
+
+/// Image size of each artifact, in pixels
+type ImageSizes = HashMap<PageId, (u32, u32)>;
+
+/// Cached OCR word boxes of the artifacts that have them, and the image
+/// size of every artifact
+fn load_word_boxes(
+    scan_set_path: &Path,
+    manifest: &ScanSetManifest,
+    artifacts: &[PageArtifact],
+) -> Result<(HashMap<PageId, OcrOutput>, ImageSizes)> {
+    let cache = StageCache::new(scan_set_path);
+    let mut ocr = HashMap::new();
+    let mut sizes = HashMap::new();
+    for artifact in artifacts {
+        let path = scan_set_path.join(&artifact.raw_image_path);
+        let size = image::image_dimensions(&path)
+            .with_context(|| format!("Failed to read image header: {}", path.display()))?;
+        sizes.insert(artifact.id, size);
+
+        if artifact.metadata.content_hash.is_empty() {
+            continue;
+        }
+        let key = ocr_cache_key(
+            &preprocess_key(&artifact.metadata.content_hash, &manifest.preprocess)?,
+            manifest.keypunch.model,
+        )?;
+        if let Some(output) = cache.get::<OcrOutput>(&key)? {
+            ocr.insert(artifact.id, output);
+        }
+    }
+    Ok((ocr, sizes))
+}
+
+/// Export the scans as a searchable PDF, with the corrected text as an
+/// invisible layer placed on the OCR lines
+pub fn export_pdf(scan_set_dir: &str, output_file: &str) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (manifest, artifacts) = scan_set::load(scan_set_path)?;
+    if artifacts.is_empty() {
+        anyhow::bail!("Scan set has no artifacts: {}", scan_set_dir);
+    }
+
+    println!("📄 Exporting searchable PDF from {}", scan_set_dir);
+
+    let file = fs::File::create(output_file)
+        .with_context(|| format!("Failed to create {}", output_file))?;
+    let mut pdf = SearchablePdf::new(std::io::BufWriter::new(file))?;
+    let cache = StageCache::new(scan_set_path);
+    let mut positioned = 0;
+    for artifact in &artifacts {
+        let image = PdfImage::from_file(&scan_set_path.join(&artifact.raw_image_path))?;
+        let mut words = Vec::new();
+        if !artifact.metadata.content_hash.is_empty() {
+            let key = ocr_cache_key(
+                &preprocess_key(&artifact.metadata.content_hash, &manifest.preprocess)?,
+                manifest.keypunch.model,
+            )?;
+            if let Some(output) = cache.get::<OcrOutput>(&key)? {
+                words = output.words;
+            }
+        }
+        if !words.is_empty() {
+            positioned += 1;
+        }
+        let text = artifact.content_text.as_deref().unwrap_or_default();
+        let lines = page_lines(text, &words, (image.width, image.height));
+        pdf.add_page(&image, &lines)?;
+    }
+    pdf.finish(&manifest.name)?;
+
+    println!(
+        "✅ Wrote {} page(s) to {} ({} with OCR line positions)",
+        artifacts.len(),
+        output_file,
+        positioned
+    );
+    Ok(())
+}
+
+/// Package the scan set and the given exports as a BagIt bag for archival
+/// deposit
+pub fn export_bag(scan_set_dir: &str, output_dir: &str, include: &[PathBuf]) -> Result<()> {
+    println!("📦 Bagging {} for deposit", scan_set_dir);
+    let date = chrono::Utc::now().format("%Y-%m-%d").to_string();
+    let summary = write_bag(
+        Path::new(scan_set_dir),
+        include,
+        Path::new(output_dir),
+        &date,
+    )?;
+    println!(
+        "✅ Bagged {} file(s), {} bytes, with SHA-256 manifests in {}",
+        summary.files, summary.bytes, output_dir
+    );
+    Ok(())
+}
+
+/// Insert a number before the extension: `deck.json` -> `deck_2.json`
+fn numbered_path(path: &Path, number: usize) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, number, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, number),
+    };
+    path.with_file_name(name)
+}
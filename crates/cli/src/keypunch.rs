@@ -0,0 +1,48 @@
+//! `keypunch` command: set the keypunch model of a scan set or document
+
+use anyhow::{Context, Result};
+use core_pipeline::keypunch::KeypunchModel;
+use core_pipeline::scan_set;
+use std::path::Path;
+
+/// Parse a keypunch model name given on the command line
+pub fn parse_model(name: &str) -> Result<KeypunchModel> {
+    KeypunchModel::parse(name).with_context(|| {
+        format!(
+            "Unknown keypunch model: {} (use 029, 026-fortran or 026-commercial)",
+            name
+        )
+    })
+}
+
+/// Set the keypunch model of the whole scan set, or of one document by
+/// name (the name `reconstruct` prints)
+///
+/// Takes effect on the next `analyze` (OCR whitelist), `validate` and
+/// `reconstruct`.
+pub fn set_keypunch(scan_set_dir: &str, model: &str, document: Option<&str>) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let model = parse_model(model)?;
+    let mut manifest = scan_set::load_manifest(scan_set_path)?;
+
+    match document {
+        Some(name) if model == manifest.keypunch.model => {
+            manifest.keypunch.documents.remove(name);
+            println!(
+                "⌨️  {}: keypunch {} (scan set default)",
+                name,
+                model.as_str()
+            );
+        }
+        Some(name) => {
+            manifest.keypunch.documents.insert(name.to_string(), model);
+            println!("⌨️  {}: keypunch {}", name, model.as_str());
+        }
+        None => {
+            manifest.keypunch.model = model;
+            println!("⌨️  {}: keypunch {}", scan_set_dir, model.as_str());
+        }
+    }
+
+    Ok(scan_set::save_manifest(scan_set_path, &manifest)?)
+}
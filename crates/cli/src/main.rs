@@ -10,17 +10,59 @@ pub mod built_info {
     include!(concat!(env!("OUT_DIR"), "/built.rs"));
 }
 
-use anyhow::{Context, Result};
-use chrono::Utc;
-use clap::{Parser, Subcommand};
-use core_pipeline::ocr::extract_text_tesseract;
-use core_pipeline::preprocess::{
-    compute_image_hash, detect_duplicates, preprocess_image, RgbImage,
+mod artifact;
+mod bench;
+mod cache;
+mod diff;
+mod doctor;
+mod export;
+mod keypunch;
+mod ocr_diff;
+mod profile;
+mod reconstruct;
+mod relabel;
+mod score;
+mod serve;
+mod stats;
+mod validate;
+
+use anyhow::{bail, Context, Result};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use core_pipeline::card_render;
+use core_pipeline::derived::DERIVED_DIR;
+use core_pipeline::export::exporter::{ExporterRegistry, TextDeckExporter};
+use core_pipeline::export::iiif::IiifOptions;
+use core_pipeline::export::markdown::TranscriptLayout;
+use core_pipeline::export::metadata::Delimiter;
+use core_pipeline::export::page_ocr::PageOcrFormat;
+use core_pipeline::export::sidecar::SidecarFormat;
+use core_pipeline::export::simh::DEFAULT_DMS_DISK;
+use core_pipeline::hooks::HookSet;
+use core_pipeline::keypunch::KeypunchModel;
+use core_pipeline::scan_set;
+use core_pipeline::storage::{self, StorageBackend};
+use llm_bridge::GeminiClient;
+use scan3data::{
+    AnalyzeOptions, CleanOptions, CompareOptions, Config, IngestOptions, ReorderOptions,
+    TextDumpFormat, WatchEvent,
 };
-use core_pipeline::types::{PageArtifact, PageId, PageMetadata, ScanSetId, ScanSetManifest};
+use scan3data_server::{DEFAULT_DIST_DIR, DEFAULT_HOST};
 use std::fs;
+use std::ops::ControlFlow;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+
+/// Vision model used when neither `--vision-model` nor the configuration
+/// names one
+const DEFAULT_VISION_MODEL: &str = "llava:latest";
+
+/// Export format used when neither `--format` nor the configuration names
+/// one
+const DEFAULT_EXPORT_FORMAT: &str = "card_deck";
+
+/// Port the web UI is served on by default
+const DEFAULT_PORT: u16 = 7214;
 
 #[derive(Parser)]
 #[command(name = "scan3data")]
@@ -45,54 +87,254 @@ The "3" represents our three-phase pipeline:
   3. Convert - Transform to structured output (emulator formats)
 
 EXAMPLES:
+  # All three phases in one go (export written to ./my_scan_set/export/)
+  scan3data run -i ./scans -o ./my_scan_set --use-vision -f card_deck
+
   # Phase 1: Ingest scans
   scan3data ingest -i ./scans -o ./my_scan_set
 
+  # Cards punched on an 026 with the FORTRAN character set (+ instead of &)
+  scan3data ingest -i ./scans -o ./my_scan_set --keypunch 026-fortran
+
+  # Archival scan set keeping the untouched source files (and EXIF)
+  scan3data ingest -i ./scans -o ./my_scan_set --copy-originals
+
+  # Add another batch of scans to an existing scan set
+  scan3data ingest -i ./more_scans -o ./my_scan_set --append
+
+  # Ingest scans as the document scanner drops them into a folder
+  scan3data ingest -i ~/ScannerInbox -o ./my_scan_set --watch
+
+  # Combine the scan sets of two shipments (analysis results are kept)
+  scan3data merge -a ./shipment1 -b ./shipment2 -o ./combined
+
+  # Share a complete scan set as one zip file, and unpack it elsewhere
+  scan3data pack -s ./my_scan_set -o my_scan_set.zip
+  scan3data unpack -i my_scan_set.zip -o ./their_copy
+
+  # One scan set per classification (split/ListingSource, ...), or a selection
+  scan3data split -s ./my_scan_set -o ./split
+  scan3data split -s ./my_scan_set -o ./payroll --ids 3fa2c1d0,9b7e44aa
+
+  # Ingest a scanned PDF listing, one artifact per page (needs pdftoppm)
+  scan3data ingest -i ./listing.pdf -o ./my_scan_set --pdf-dpi 400
+  scan3data keypunch -s ./my_scan_set --model 029 --document PAYROLL
+
+  # Fix a batch the classifier got wrong: preview, then apply (analyze
+  # keeps the new classification)
+  scan3data relabel -s ./my_scan_set --from Unknown --to CardText \
+    --filter "text-len<90" --dry-run
+
+  # Remove greenbar backgrounds with Gemini, then OCR the cleaned images
+  scan3data clean -s ./my_scan_set --estimate
+  scan3data clean -s ./my_scan_set --max-cost 10
+  scan3data analyze -s ./my_scan_set --prefer-cleaned
+
   # Phase 2: Analyze with vision correction
   scan3data analyze -s ./my_scan_set --use-vision --vision-model llama3.2-vision:11b
 
+  # OCR 8 artifacts at a time, with 2 vision model calls in flight
+  scan3data analyze -s ./my_scan_set --use-vision --jobs 8 --model-jobs 2
+
+  # Run your own corrector or validator scripts after OCR (see hooks.toml)
+  scan3data analyze -s ./my_scan_set --hooks hooks.toml
+
   # Export raw OCR text for inspection
   scan3data text-dump -s ./my_scan_set -o output.txt
+  scan3data text-dump -s ./my_scan_set -o listing.md --format markdown
+  scan3data text-dump -s ./my_scan_set -o ./texts/ --split
 
   # Generate comparison HTML (original vs corrected)
   scan3data compare -s ./my_scan_set -o comparison.html
+  scan3data compare -s ./my_scan_set -o audit.html --raw-ocr
+  scan3data compare -s ./my_scan_set -o drift.html --raw-ocr --column-diff
+  scan3data compare -s ./my_scan_set -o comparison.html --link-images --thumbnail-size 800
+  scan3data compare -s ./my_scan_set -o comparison.html --link-images --per-page 50
+  scan3data compare -s ./my_scan_set -o review.html --only-kind ListingObject --max-confidence 0.6
+
+  # Validate OCR text and write an HTML (or .json) report
+  scan3data validate -s ./my_scan_set -o validation.html
+  scan3data validate -s ./my_scan_set -o validation.json --rules data-deck.toml
+
+  # Score accuracy against reference transcripts (reference/<page>.txt),
+  # then compare a rerun with a new model against the saved baseline
+  scan3data score -s ./my_scan_set -o score.json
+  scan3data score -s ./my_scan_set --baseline score.json
+
+  # XMP sidecars next to the scans, so photo tools show text and class
+  scan3data annotate -s ./my_scan_set --format xmp
+
+  # Small previews (thumbnails/) for the comparison view and web UI
+  scan3data thumbnails -s ./my_scan_set --size 256
+
+  # Check that text lines up with an 80-column grid before column parsing
+  scan3data grid -s ./my_scan_set -o ./grids
+
+  # Draw an exported deck (text, card_deck JSON or simh .dck) as punched
+  # cards, to check it by eye or illustrate it
+  scan3data render-cards -i deck.dck -o ./cards
+
+  # Pick a vision model: error rates of Tesseract alone and with each model
+  # against known-good transcriptions (truth/<page>.txt)
+  scan3data bench -s ./my_scan_set --truth ./truth \
+    --vision-model llava:latest --vision-model llama3.2-vision:11b
+
+  # Check Tesseract, Ollama and the Gemini API key
+  scan3data doctor
+
+  # Enable tab completion of commands, flags and export formats (bash)
+  scan3data completions bash > ~/.local/share/bash-completion/completions/scan3data
+
+  # Which characters the vision model changed in the OCR text (O -> 0, ...)
+  scan3data ocr-diff -s ./my_scan_set
+
+  # Compare two analyses of the same scans (e.g. two vision models)
+  scan3data diff -a ./set_v1 -b ./set_v2
+
+  # Summarize classification, confidence, OCR coverage, duplicates and
+  # missing, repeated or out-of-order pages to rescan
+  scan3data stats -s ./my_scan_set
+  scan3data stats -s ./my_scan_set --json
+
+  # Results as JSON for scripts (ingest, analyze, validate, bench; progress on stderr)
+  scan3data analyze -s ./my_scan_set --json > analysis.json
+
+  # List artifacts, inspect one, or remove a bad scan (ID or unique prefix)
+  scan3data artifact list -s ./my_scan_set
+  scan3data artifact show -s ./my_scan_set 3fa2c1d0
+  scan3data artifact remove -s ./my_scan_set 3fa2c1d0
+
+  # Use pages volunteers already typed in (rows of image,transcription.txt)
+  scan3data import-text -s ./my_scan_set --map mapping.csv
+
+  # Analyze one garbled page again, this time with vision correction
+  scan3data reprocess -s ./my_scan_set --artifact 3fa2c1d0 --use-vision
+
+  # Put shuffled pages in reading order (text model for unnumbered pages)
+  scan3data reorder -s ./my_scan_set --use-llm
+
+  # Order pages by detected page numbers and assemble listings
+  scan3data reconstruct -s ./my_scan_set
+
+  # Delete derived images no artifact refers to any more
+  scan3data trim-cache -s ./my_scan_set
+
+  # Time each pipeline stage (preprocess, OCR, LLM, IO) over 20 artifacts
+  scan3data profile -s ./my_scan_set --sample 20
 
   # Phase 3: Export to emulator format
   scan3data export -s ./my_scan_set -o deck.json -f card_deck
 
+  # Export a deck with a simh ibm1130 script that runs it under DMS
+  scan3data export -s ./my_scan_set -o deck.json -f card_deck --simh
+
+  # Plain 80-column text deck without sequence numbers
+  scan3data export -s ./my_scan_set -o deck.txt -f text --strip-sequence
+
+  # Binary card deck punched on the scan set's keypunch, for simh
+  scan3data export -s ./my_scan_set -o deck.dck -f simh
+
+  # Export documents as a directory tree for a preservation repository
+  scan3data export -s ./my_scan_set -o ./recovered -f repository
+
+  # Publish a Markdown transcript with page thumbnails (or -f mdbook)
+  scan3data export -s ./my_scan_set -o ./transcript -f markdown
+
+  # Catalog in a spreadsheet (id, files, kind, status, page, notes, ...)
+  scan3data export -s ./my_scan_set -o progress.csv -f csv
+
+  # IIIF manifest for image viewers, images served by a IIIF image server
+  scan3data export -s ./my_scan_set -o manifest.json -f iiif \
+    --base-url https://example.org/box3 --image-service https://example.org/iiif/3
+
+  # METS/ALTO archival package with OCR word coordinates
+  scan3data export -s ./my_scan_set -o ./package -f mets
+
+  # ALTO or hOCR file next to each page image, for digital library pipelines
+  scan3data export -s ./my_scan_set -o ./hocr -f hocr
+
+  # Searchable PDF: each scan with its corrected text as an invisible layer
+  scan3data export -s ./my_scan_set -o box3.pdf -f pdf
+
+  # BagIt bundle of the scan set and finished exports for archival deposit
+  scan3data export -s ./my_scan_set -o ./bag -f bagit --include ./recovered
+
+  # One source file per recovered program, with a manifest, for git
+  scan3data export -s ./my_scan_set -o ./sources -f sources
+
   # Serve web UI
   scan3data serve --mode spa --port 8080
 
+  # Serve web UI with the REST API for a scan set
+  scan3data serve --mode api -s ./my_scan_set
+
 AI CODING AGENT INSTRUCTIONS:
 
 This CLI provides a three-phase pipeline for processing IBM 1130 scans:
 
 PHASE 1 - INGEST:
-  Use the 'ingest' command to import scanned images. This command:
+  Use the 'ingest' command to import scanned images or PDFs. This command:
+  - Rasterizes each PDF page (pdftoppm, --pdf-dpi) into its own artifact
   - Detects duplicate images via SHA-256 hashing
+  - Lists look-alike rescans (perceptual hash) in the manifest for review
   - Stores one copy of each unique image
   - Preserves all filenames in metadata for context
   - Creates a scan set directory with artifacts.json manifest
+  Use 'merge' to combine two scan sets, deduplicating across both, and
+  'split' to separate one by classification or selection. 'pack' puts a
+  scan set in a zip file for sharing; 'unpack' restores and verifies it.
 
 PHASE 2 - ANALYZE:
   Use the 'analyze' command to process the scan set. Options:
-  - Default: Tesseract OCR with IBM 1130 character whitelist
+  - Default: Tesseract OCR with the scan set keypunch's character whitelist
   - --use-vision: Apply Ollama vision model for OCR correction
   - --vision-model: Specify model (llama3.2-vision:11b recommended)
+  - --prefer-cleaned: Start from images cleaned by the 'clean' command
   Vision correction preserves column layout and fixes character errors
+  Use 'reprocess' to analyze a single artifact again, and 'import-text'
+  to attach existing transcriptions, which analysis keeps instead of OCR.
+  Then use 'reconstruct' to order pages by their detected page numbers
+  and assemble them into source listings
 
 PHASE 3 - EXPORT:
   Use the 'export' command to generate emulator-ready output:
   - Format: card_deck (punch cards) or listing (printed output)
+  - Format: lst (listing as plain numbered text)
+  - Format: text (one 80-column line per card, --strip-sequence blanks 73-80)
+  - Format: simh (binary deck for the simh ibm1130 card reader, attach -b cr)
+  - card_deck with --simh adds a job deck and simh script to run it
+  - Format: repository (directory per document with README provenance)
+  - Format: markdown or mdbook (transcript with thumbnails, for the web)
+  - Format: csv or tsv (artifact metadata table, for spreadsheets)
+  - Format: iiif (IIIF Presentation manifest with OCR annotations)
+  - Format: mets (METS/ALTO archival package with word coordinates)
+  - Format: alto or hocr (page images, each with its positional OCR file)
+  - Format: pdf (searchable PDF of the scans with an invisible text layer)
+  - Format: sources (one source file per program with a manifest, for git)
+  - Format: bagit (BagIt bag of scan set and exports, with checksums)
   - Output: JSON file for IBM 1130 emulator consumption
 
 UTILITY COMMANDS:
-  - text-dump: Export raw OCR text for manual inspection
+  - text-dump: Export raw OCR text (or Markdown, or a file per artifact) for manual inspection
   - compare: Generate HTML with side-by-side image/text comparison
+  - validate: Check OCR text against format rules, write JSON/HTML report
+  - profile: Report per-stage timing percentiles over sampled artifacts
+  - annotate: Write XMP or JSON sidecars with text and classification
+  - thumbnails: Write small JPEG previews of every scan to thumbnails/
+  - grid: Draw the detected 80-column grid over each processed image
+  - render-cards: Draw the cards of an exported deck with holes and printing
+  - bench: Character and word error rates of OCR configurations vs truth
+  - ocr-diff: Confusion table of characters changed after OCR
+  - diff: Compare text and classification of two analyses of the same scans
+  - doctor: Check Tesseract, Ollama models and GEMINI_API_KEY
+  - stats: Summarize classification, confidence, OCR, duplicates, missing pages
+  - artifact: List, show or remove single artifacts of a scan set
+  - relabel: Fix the classification of many artifacts at once
+  - completions: Print a bash, zsh, fish or PowerShell completion script
   - serve: Start web UI (SPA mode or API mode)
 
 ENVIRONMENT VARIABLES:
-  GEMINI_API_KEY - Required for image cleaning (Gemini 2.5 Flash Image)
+  GEMINI_API_KEY - Required for image cleaning (clean, Gemini 2.5 Flash Image)
   - Get key at: https://ai.google.dev/
   - Cost: $0.039 per image
 
@@ -100,17 +342,31 @@ ENVIRONMENT VARIABLES:
   - Install from: https://ollama.com/
   - Runs at http://localhost:11434
 
+CONFIGURATION:
+  Defaults for the Ollama URL, model names, analyze options, export
+  format and server port are read from scan3data.toml in the working
+  directory, or from the file given with --config. Flags override it.
+
 For more information, see: https://github.com/softwarewrighter/scan3data
 "#)]
 struct Cli {
+    /// Configuration file (default: scan3data.toml if present)
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Print results as JSON on stdout, with progress on stderr (ingest,
+    /// analyze, validate, diff and stats)
+    #[arg(long, global = true)]
+    json: bool,
+
     #[command(subcommand)]
     command: Commands,
 }
 
 #[derive(Subcommand)]
 enum Commands {
-    /// Phase 1: Scan - Ingest scanned images into a scan set
-    Ingest {
+    /// Run all three phases: ingest, analyze, reconstruct and export
+    Run {
         /// Input directory or file
         #[arg(short, long)]
         input: String,
@@ -118,855 +374,1795 @@ enum Commands {
         /// Output directory for scan set
         #[arg(short, long)]
         output: String,
+
+        #[command(flatten)]
+        ingest: IngestArgs,
+
+        #[command(flatten)]
+        analysis: AnalyzeArgs,
+
+        /// Export file or directory (default: in export/ of the scan set)
+        #[arg(short, long)]
+        export_output: Option<String>,
+
+        #[command(flatten)]
+        export: ExportArgs,
     },
 
-    /// Phase 2: Classify & Correct - Analyze a scan set and classify artifacts
-    Analyze {
-        /// Scan set directory
+    /// Phase 1: Scan - Ingest scanned images into a scan set
+    Ingest {
+        /// Input directory or file
         #[arg(short, long)]
-        scan_set: String,
+        input: String,
 
-        /// Use LLM for classification
-        #[arg(long)]
-        use_llm: bool,
+        /// Output directory for scan set
+        #[arg(short, long)]
+        output: String,
 
-        /// Use vision model for OCR correction with layout preservation
+        /// Add the images to the existing scan set in the output directory
+        /// (its keypunch is kept)
         #[arg(long)]
-        use_vision: bool,
+        append: bool,
 
-        /// Vision model to use (default: llava:latest)
-        #[arg(long, default_value = "llava:latest")]
-        vision_model: String,
+        /// Keep watching the input directory and ingest new scans as they
+        /// appear, until interrupted (creates the scan set if needed)
+        #[arg(long, conflicts_with = "append")]
+        watch: bool,
+
+        #[command(flatten)]
+        ingest: IngestArgs,
     },
 
-    /// Phase 3: Convert - Export a scan set to emulator format
-    Export {
+    /// Pack a scan set into a single zip file (with checksums) for sharing
+    Pack {
         /// Scan set directory
         #[arg(short, long)]
         scan_set: String,
 
-        /// Output file
+        /// Zip file to write
         #[arg(short, long)]
         output: String,
-
-        /// Format: card_deck or listing
-        #[arg(short, long, default_value = "card_deck")]
-        format: String,
     },
 
-    /// Export raw OCR text to a text file for inspection
-    TextDump {
-        /// Scan set directory
+    /// Unpack a zip file written by 'pack' into a scan set, verifying it
+    Unpack {
+        /// Zip file to read
         #[arg(short, long)]
-        scan_set: String,
+        input: String,
 
-        /// Output text file
+        /// Output directory for the scan set
         #[arg(short, long)]
         output: String,
     },
 
-    /// Generate HTML comparison view (original image vs corrected text)
-    Compare {
+    /// Split a scan set into new scan sets by classification or selection
+    Split {
         /// Scan set directory
         #[arg(short, long)]
         scan_set: String,
 
-        /// Output HTML file
+        /// Output directory (of one scan set per kind, or of the selection)
         #[arg(short, long)]
         output: String,
 
-        /// Show column grid overlay
-        #[arg(long)]
-        show_grid: bool,
+        /// Split off these artifacts (IDs or unique ID prefixes) instead of
+        /// splitting by kind
+        #[arg(long, value_delimiter = ',')]
+        ids: Vec<String>,
     },
 
-    /// Serve the web UI
-    Serve {
-        /// Port to listen on
-        #[arg(short, long, default_value = "7214")]
-        port: u16,
+    /// Merge two scan sets into a new one, deduplicating images across both
+    Merge {
+        /// First scan set directory (its artifacts come first)
+        #[arg(short)]
+        a: String,
 
-        /// Mode: spa (standalone) or api (with backend)
-        #[arg(short, long, default_value = "spa")]
-        mode: String,
-    },
-}
+        /// Second scan set directory
+        #[arg(short)]
+        b: String,
 
-/// Check if a file is a supported image format
-fn is_supported_image(path: &Path) -> bool {
-    if let Some(ext) = path.extension() {
-        let ext_lower = ext.to_string_lossy().to_lowercase();
-        matches!(
-            ext_lower.as_str(),
-            "jpg" | "jpeg" | "png" | "tif" | "tiff" | "bmp"
-        )
-    } else {
-        false
-    }
-}
+        /// Output directory for the merged scan set
+        #[arg(short, long)]
+        output: String,
 
-/// Collect all image files from input path (file or directory)
-fn collect_image_files(input_path: &str) -> Result<Vec<PathBuf>> {
-    let path = Path::new(input_path);
+        /// List images whose perceptual hashes differ in at most this many
+        /// of 256 bits as suspected near-duplicates (default: 12)
+        #[arg(long)]
+        near_duplicate_distance: Option<u32>,
+    },
 
-    if !path.exists() {
-        anyhow::bail!("Input path does not exist: {}", input_path);
-    }
+    /// Set the keypunch model of a scan set or one of its documents
+    Keypunch {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    let mut image_files = Vec::new();
+        /// Keypunch model: 029, 026-fortran or 026-commercial
+        #[arg(short, long)]
+        model: String,
 
-    if path.is_file() {
-        if is_supported_image(path) {
-            image_files.push(path.to_path_buf());
-        } else {
-            anyhow::bail!("File is not a supported image format: {}", input_path);
-        }
-    } else if path.is_dir() {
-        for entry in WalkDir::new(path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let entry_path = entry.path();
-            if entry_path.is_file() && is_supported_image(entry_path) {
-                image_files.push(entry_path.to_path_buf());
-            }
-        }
-    } else {
-        anyhow::bail!("Input path is neither a file nor directory: {}", input_path);
-    }
+        /// Document name (as printed by reconstruct); the whole scan set if
+        /// omitted
+        #[arg(long)]
+        document: Option<String>,
+    },
 
-    if image_files.is_empty() {
-        anyhow::bail!("No supported image files found in: {}", input_path);
-    }
+    /// Set the classification of every artifact of one kind that matches
+    /// filters, listing each change
+    #[command(after_help = "FILTERS:
+  text-len<90      fewer than 90 characters of text
+  lines>=40        at least 40 non-blank lines
+  confidence<0.5   classified with less than 50% confidence
+  issues>0         with validation issues
+  text~// JOB      text contains \"// JOB\" (text!~ for does not contain)
+
+Numeric fields take <, <=, >, >=, = and !=. Repeat --filter to require
+several conditions.")]
+    Relabel {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    Ok(image_files)
-}
+        /// Only relabel artifacts of this classification (e.g. Unknown);
+        /// any if omitted
+        #[arg(long)]
+        from: Option<String>,
 
-/// Ingest images into a new scan set
-fn ingest_scan_set(input_path: &str, output_dir: &str) -> Result<()> {
-    println!("🔍 Scanning for images in: {}", input_path);
+        /// New classification: CardText, CardObject, CardData,
+        /// ListingSource, ListingObject, RuntimeOutput or Unknown
+        #[arg(long)]
+        to: String,
 
-    // Collect all image files
-    let image_files = collect_image_files(input_path)?;
-    println!("📁 Found {} image file(s)", image_files.len());
+        /// Condition an artifact must meet, e.g. "text-len<90"; repeat for
+        /// several
+        #[arg(long)]
+        filter: Vec<String>,
 
-    // Load images and compute hashes
-    println!("🔢 Computing hashes for duplicate detection...");
-    let mut images_with_data: Vec<(PathBuf, RgbImage)> = Vec::new();
+        /// List the changes without saving them
+        #[arg(long)]
+        dry_run: bool,
+    },
 
-    for (idx, file_path) in image_files.iter().enumerate() {
-        print!("\r   Processing {}/{}", idx + 1, image_files.len());
-        std::io::Write::flush(&mut std::io::stdout()).ok();
+    /// Remove greenbar backgrounds from the scans with Gemini (paid, needs
+    /// GEMINI_API_KEY)
+    Clean {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-        let img = image::open(file_path)
-            .with_context(|| format!("Failed to load image: {}", file_path.display()))?;
-        let rgb_img = img.to_rgb8();
-        images_with_data.push((file_path.clone(), rgb_img));
-    }
-    println!();
+        /// Clean artifacts again that already have a cleaned image
+        #[arg(long)]
+        force: bool,
 
-    // Detect duplicates
-    let duplicate_groups = detect_duplicates(&images_with_data);
-    let unique_count = duplicate_groups.len();
-    let duplicate_count = image_files.len() - unique_count;
-
-    println!("✨ Found {} unique image(s)", unique_count);
-    if duplicate_count > 0 {
-        println!("   ({} duplicate(s) detected)", duplicate_count);
-    }
-
-    // Create scan set directory structure
-    let output_path = Path::new(output_dir);
-    fs::create_dir_all(output_path)
-        .with_context(|| format!("Failed to create output directory: {}", output_dir))?;
-
-    let images_dir = output_path.join("images");
-    let processed_dir = output_path.join("processed");
-    fs::create_dir_all(&images_dir)?;
-    fs::create_dir_all(&processed_dir)?;
-
-    println!("📦 Creating scan set in: {}", output_dir);
-
-    // Generate scan set ID and manifest
-    let scan_set_id = ScanSetId::new();
-    let created_at = Utc::now().to_rfc3339();
-
-    let manifest = ScanSetManifest {
-        scan_set_id,
-        name: Path::new(input_path)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("scan_set")
-            .to_string(),
-        created_at: created_at.clone(),
-        image_count: unique_count,
-        original_file_count: image_files.len(),
-        duplicate_count,
-    };
+        /// Print the projected cost instead of cleaning
+        #[arg(long)]
+        estimate: bool,
 
-    // Save images and create artifacts
-    let mut artifacts: Vec<PageArtifact> = Vec::new();
+        /// Stop before the run spends more than this many USD
+        #[arg(long)]
+        max_cost: Option<f64>,
+    },
 
-    for (idx, group) in duplicate_groups.iter().enumerate() {
-        print!("\r💾 Saving images {}/{}", idx + 1, unique_count);
-        std::io::Write::flush(&mut std::io::stdout()).ok();
+    /// Phase 2: Classify & Correct - Analyze a scan set and classify artifacts
+    Analyze {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-        // Save image with hash as filename
-        let image_filename = format!("{}.jpg", &group.hash[..16]); // Use first 16 chars
-        let image_dest = images_dir.join(&image_filename);
-
-        // Find the image data for this hash
-        let source_image = images_with_data
-            .iter()
-            .find(|(_path, img)| {
-                let hash = compute_image_hash(img);
-                hash == group.hash
-            })
-            .expect("Image data not found for hash");
-
-        // Save the image
-        image::save_buffer(
-            &image_dest,
-            source_image.1.as_raw(),
-            source_image.1.width(),
-            source_image.1.height(),
-            image::ColorType::Rgb8,
-        )?;
-
-        // Create artifact
-        let artifact = PageArtifact {
-            id: PageId::new(),
-            scan_set: scan_set_id,
-            raw_image_path: PathBuf::from("images").join(&image_filename),
-            processed_image_path: None,
-            layout_label: core_pipeline::types::ArtifactKind::Unknown,
-            content_text: None,
-            metadata: PageMetadata {
-                content_hash: group.hash.clone(),
-                original_filenames: group
-                    .filenames
-                    .iter()
-                    .map(|p| p.to_string_lossy().to_string())
-                    .collect(),
-                page_number: None,
-                header: None,
-                footer: None,
-                notes: Vec::new(),
-                confidence: 0.0,
-            },
-        };
+        /// Continue an interrupted run, skipping the artifacts it finished
+        /// (it must have used the same options)
+        #[arg(long)]
+        resume: bool,
 
-        artifacts.push(artifact);
-    }
-    println!();
+        #[command(flatten)]
+        analysis: AnalyzeArgs,
+    },
 
-    // Write manifest.json
-    let manifest_path = output_path.join("manifest.json");
-    let manifest_json = serde_json::to_string_pretty(&manifest)?;
-    fs::write(&manifest_path, manifest_json)
-        .with_context(|| format!("Failed to write manifest: {}", manifest_path.display()))?;
+    /// Attach existing transcriptions (.txt files) to artifacts; analysis
+    /// keeps them and skips OCR
+    ImportText {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    // Write artifacts.json
-    let artifacts_path = output_path.join("artifacts.json");
-    let artifacts_json = serde_json::to_string_pretty(&artifacts)?;
-    fs::write(&artifacts_path, artifacts_json)
-        .with_context(|| format!("Failed to write artifacts: {}", artifacts_path.display()))?;
+        /// CSV file of image (original file name or content hash) and
+        /// transcription file rows, paths relative to it
+        #[arg(short, long)]
+        map: String,
+    },
 
-    println!("✅ Scan set created successfully!");
-    println!("   Scan Set ID: {}", scan_set_id.0);
-    println!("   Manifest: {}", manifest_path.display());
-    println!("   Artifacts: {} page(s)", artifacts.len());
+    /// Analyze a single artifact again (e.g. one that came out garbled)
+    Reprocess {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    Ok(())
-}
+        /// Artifact ID (or unique ID prefix)
+        #[arg(short, long)]
+        artifact: String,
 
-/// Analyze a scan set using OCR and optional LLM classification
-async fn analyze_scan_set(
-    scan_set_dir: &str,
-    use_llm: bool,
-    use_vision: bool,
-    vision_model: &str,
-) -> Result<()> {
-    let scan_set_path = Path::new(scan_set_dir);
+        #[command(flatten)]
+        analysis: AnalyzeArgs,
+    },
 
-    if !scan_set_path.exists() {
-        anyhow::bail!("Scan set directory does not exist: {}", scan_set_dir);
-    }
+    /// Put shuffled pages in reading order (by page number, or by the text
+    /// model for unnumbered pages)
+    Reorder {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    println!("🔬 Analyzing scan set: {}", scan_set_dir);
+        /// Order documents with unnumbered pages with the text model
+        #[arg(long)]
+        use_llm: bool,
 
-    // Load manifest
-    let manifest_path = scan_set_path.join("manifest.json");
-    let manifest_json = fs::read_to_string(&manifest_path)
-        .with_context(|| format!("Failed to read manifest: {}", manifest_path.display()))?;
-    let manifest: ScanSetManifest =
-        serde_json::from_str(&manifest_json).context("Failed to parse manifest.json")?;
+        /// Text model to use (default: qwen2.5:3b)
+        #[arg(long)]
+        text_model: Option<String>,
+    },
 
-    println!("📋 Scan Set ID: {}", manifest.scan_set_id.0);
-    println!("   Images: {}", manifest.image_count);
+    /// Rebuild documents and object decks from scanned pages and cards
+    Reconstruct {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    // Load artifacts
-    let artifacts_path = scan_set_path.join("artifacts.json");
-    let artifacts_json = fs::read_to_string(&artifacts_path)
-        .with_context(|| format!("Failed to read artifacts: {}", artifacts_path.display()))?;
-    let mut artifacts: Vec<PageArtifact> =
-        serde_json::from_str(&artifacts_json).context("Failed to parse artifacts.json")?;
+        /// TOML rule-set file for validation (see 'validate --rules')
+        #[arg(long)]
+        rules: Option<PathBuf>,
+    },
 
-    println!("📄 Processing {} artifact(s)...", artifacts.len());
+    /// Delete derived images (preprocessed, thumbnails, ...) no longer in use
+    TrimCache {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    if use_llm {
-        println!("🤖 LLM mode enabled (not yet implemented)");
-    }
+        /// Also delete every image and cached result of this stage
+        /// (e.g. "preprocess", "ocr", "vision")
+        #[arg(long)]
+        stage: Option<String>,
+    },
 
-    // Initialize vision model if requested
-    let vision_client = if use_vision {
-        println!("👁️  Vision mode enabled (model: {})", vision_model);
-        let client = llm_bridge::OllamaClient::default_client()?;
-        Some(llm_bridge::VisionModel::new(
-            client,
-            vision_model.to_string(),
-        ))
-    } else {
-        None
-    };
+    /// Time each pipeline stage over a sample of artifacts
+    Profile {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    // Process each artifact
-    let processed_dir = scan_set_path.join("processed");
-    let total_artifacts = artifacts.len();
+        /// Number of artifacts to sample (spread evenly over the scan set)
+        #[arg(long, default_value_t = 10)]
+        sample: usize,
 
-    for (idx, artifact) in artifacts.iter_mut().enumerate() {
-        print!("\r   Artifact {}/{}", idx + 1, total_artifacts);
-        std::io::Write::flush(&mut std::io::stdout()).ok();
+        /// Include the LLM classification cross-check
+        #[arg(long)]
+        use_llm: bool,
 
-        // Load the raw image
-        let raw_image_path = scan_set_path.join(&artifact.raw_image_path);
-        let img = image::open(&raw_image_path)
-            .with_context(|| format!("Failed to load image: {}", raw_image_path.display()))?;
-
-        // Preprocess the image
-        let preprocessed = preprocess_image(&img)?;
-
-        // Save preprocessed image
-        let processed_filename = raw_image_path
-            .file_name()
-            .ok_or_else(|| anyhow::anyhow!("Invalid image path"))?;
-        let processed_path = processed_dir.join(processed_filename);
-        preprocessed.save(&processed_path)?;
-
-        // Update artifact with processed image path
-        artifact.processed_image_path = Some(PathBuf::from("processed").join(processed_filename));
-
-        // Run OCR
-        match extract_text_tesseract(&preprocessed) {
-            Ok(text) => {
-                // If vision correction is enabled, correct the OCR text
-                if let Some(ref vision) = vision_client {
-                    // Load original image bytes for vision model
-                    let image_bytes = fs::read(&raw_image_path)?;
-
-                    match vision.correct_ocr_with_layout(&image_bytes, &text).await {
-                        Ok(corrected_text) => {
-                            artifact.content_text = Some(corrected_text);
-                            artifact
-                                .metadata
-                                .notes
-                                .push("Vision-corrected OCR".to_string());
-                        }
-                        Err(e) => {
-                            eprintln!(
-                                "\n   Warning: Vision correction failed for {}: {}",
-                                artifact.raw_image_path.display(),
-                                e
-                            );
-                            // Fall back to raw OCR text
-                            artifact.content_text = Some(text);
-                            artifact
-                                .metadata
-                                .notes
-                                .push(format!("Vision correction failed: {}", e));
-                        }
-                    }
-                } else {
-                    artifact.content_text = Some(text);
-                }
-            }
-            Err(e) => {
-                // Log OCR error but continue processing
-                eprintln!(
-                    "\n   Warning: OCR failed for {}: {}",
-                    artifact.raw_image_path.display(),
-                    e
-                );
-                artifact.metadata.notes.push(format!("OCR failed: {}", e));
-            }
-        }
+        /// Include vision OCR correction with this model
+        #[arg(long)]
+        vision_model: Option<String>,
+    },
 
-        // Basic classification (non-LLM baseline)
-        // TODO: Add more sophisticated heuristics
-        if let Some(ref text) = artifact.content_text {
-            if text.len() > 100 {
-                artifact.layout_label = core_pipeline::types::ArtifactKind::ListingSource;
-                artifact.metadata.confidence = 0.5; // Low confidence for basic heuristic
-            }
-        }
-    }
-    println!();
+    /// Phase 3: Convert - Export a scan set to emulator format
+    Export {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    // Save updated artifacts
-    let updated_artifacts_json = serde_json::to_string_pretty(&artifacts)?;
-    fs::write(&artifacts_path, updated_artifacts_json)
-        .with_context(|| format!("Failed to write artifacts: {}", artifacts_path.display()))?;
+        /// Output file (directory for repository, markdown, mdbook, mets,
+        /// alto, hocr, bagit and sources)
+        #[arg(short, long)]
+        output: String,
 
-    println!("✅ Analysis complete!");
-    println!("   Processed images: {}", processed_dir.display());
-    println!("   Updated artifacts: {}", artifacts_path.display());
-
-    // Show OCR statistics
-    let with_text = artifacts
-        .iter()
-        .filter(|a| a.content_text.is_some())
-        .count();
-    let avg_text_len = artifacts
-        .iter()
-        .filter_map(|a| a.content_text.as_ref())
-        .map(|t| t.len())
-        .sum::<usize>() as f64
-        / with_text.max(1) as f64;
+        #[command(flatten)]
+        export: ExportArgs,
+    },
 
-    println!("📊 OCR Statistics:");
-    println!("   Artifacts with text: {}/{}", with_text, artifacts.len());
-    println!("   Average text length: {:.0} chars", avg_text_len);
+    /// Export raw OCR text to a text file for inspection
+    TextDump {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    Ok(())
-}
+        /// Output text file (a directory with --split)
+        #[arg(short, long)]
+        output: String,
 
-/// Export raw OCR text to a text file for inspection
-fn text_dump_scan_set(scan_set_dir: &str, output_file: &str) -> Result<()> {
-    let scan_set_path = Path::new(scan_set_dir);
+        /// Output format: text, or markdown (a section per artifact with a
+        /// metadata table and fenced text, for GitHub)
+        #[arg(long, default_value = "text", value_parser = ["text", "markdown"])]
+        format: String,
 
-    if !scan_set_path.exists() {
-        anyhow::bail!("Scan set directory does not exist: {}", scan_set_dir);
-    }
+        /// Write one .txt per artifact, named by ingest index and hash
+        /// prefix, into the output directory
+        #[arg(long, conflicts_with = "format")]
+        split: bool,
+    },
 
-    println!("📝 Dumping OCR text from: {}", scan_set_dir);
+    /// Generate HTML comparison view (original image vs corrected text)
+    Compare {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
 
-    // Load manifest
-    let manifest_path = scan_set_path.join("manifest.json");
-    let manifest_json = fs::read_to_string(&manifest_path)
-        .with_context(|| format!("Failed to read manifest: {}", manifest_path.display()))?;
-    let manifest: ScanSetManifest =
-        serde_json::from_str(&manifest_json).context("Failed to parse manifest.json")?;
-
-    // Load artifacts
-    let artifacts_path = scan_set_path.join("artifacts.json");
-    let artifacts_json = fs::read_to_string(&artifacts_path)
-        .with_context(|| format!("Failed to read artifacts: {}", artifacts_path.display()))?;
-    let artifacts: Vec<PageArtifact> =
-        serde_json::from_str(&artifacts_json).context("Failed to parse artifacts.json")?;
-
-    // Build output text
-    let mut output = String::new();
-
-    // Header
-    output.push_str(
-        "================================================================================\n",
-    );
-    output.push_str("SCAN SET OCR TEXT DUMP\n");
-    output.push_str(&format!("Scan Set ID: {}\n", manifest.scan_set_id.0));
-    output.push_str(&format!("Name: {}\n", manifest.name));
-    output.push_str(&format!("Created: {}\n", manifest.created_at));
-    output.push_str(&format!(
-        "Images: {} unique ({} total, {} duplicates)\n",
-        manifest.image_count, manifest.original_file_count, manifest.duplicate_count
-    ));
-    output.push_str(
-        "================================================================================\n\n",
-    );
+        /// Output HTML file
+        #[arg(short, long)]
+        output: String,
 
-    // Process each artifact
-    let mut artifacts_with_text = 0;
-    let mut total_chars = 0;
+        /// Show column grid overlay
+        #[arg(long)]
+        show_grid: bool,
 
-    for (idx, artifact) in artifacts.iter().enumerate() {
-        output.push_str(
-            "================================================================================\n",
-        );
-        output.push_str(&format!("ARTIFACT {}/{}\n", idx + 1, artifacts.len()));
-        output.push_str(
-            "================================================================================\n",
-        );
-        output.push_str(&format!("ID: {}\n", artifact.id.0));
-        output.push_str(&format!("Image: {}\n", artifact.raw_image_path.display()));
+        /// Add a panel of raw OCR text, with the corrections marked
+        #[arg(long)]
+        raw_ocr: bool,
 
-        if let Some(ref processed) = artifact.processed_image_path {
-            output.push_str(&format!("Processed: {}\n", processed.display()));
-        }
+        /// With --raw-ocr, mark characters that differ at the same column,
+        /// showing column drift, rather than the aligned corrections
+        #[arg(long)]
+        column_diff: bool,
 
-        output.push_str(&format!("Classification: {:?}\n", artifact.layout_label));
-        output.push_str(&format!("Confidence: {}\n", artifact.metadata.confidence));
+        /// Link images by relative path instead of embedding them (keep the
+        /// page where it is relative to the scan set)
+        #[arg(long)]
+        link_images: bool,
 
-        // Show original filenames if available
-        if !artifact.metadata.original_filenames.is_empty() {
-            output.push_str("Original Files:\n");
-            for filename in &artifact.metadata.original_filenames {
-                output.push_str(&format!("  - {}\n", filename));
-            }
-        }
+        /// Make thumbnails of at most this many pixels first and show them
+        #[arg(long)]
+        thumbnail_size: Option<u32>,
 
-        output.push_str(
-            "--------------------------------------------------------------------------------\n",
-        );
+        /// Split into pages of this many artifacts (output.html,
+        /// output_2.html, ...)
+        #[arg(long)]
+        per_page: Option<usize>,
 
-        if let Some(ref text) = artifact.content_text {
-            output.push_str("OCR TEXT:\n");
-            output.push_str("--------------------------------------------------------------------------------\n");
-            output.push_str(text);
-            if !text.ends_with('\n') {
-                output.push('\n');
-            }
-            artifacts_with_text += 1;
-            total_chars += text.len();
-        } else {
-            output.push_str("(No OCR text available)\n");
-        }
+        /// Only artifacts of this classification (e.g. ListingObject)
+        #[arg(long)]
+        only_kind: Option<String>,
 
-        output.push_str(
-            "================================================================================\n\n",
-        );
+        /// Only artifacts classified with at least this confidence (0.0-1.0)
+        #[arg(long)]
+        min_confidence: Option<f32>,
+
+        /// Only artifacts classified with at most this confidence (0.0-1.0)
+        #[arg(long)]
+        max_confidence: Option<f32>,
+    },
+
+    /// Validate OCR text against IBM 1130 format rules
+    Validate {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
+
+        /// Report file (.json for machine-readable output, otherwise HTML)
+        #[arg(short, long)]
+        output: String,
+
+        /// TOML rule-set file (enable/disable rules, tune parameters)
+        #[arg(long)]
+        rules: Option<PathBuf>,
+    },
+
+    /// Score OCR accuracy against reference transcripts in the scan set
+    Score {
+        /// Scan set directory (reference transcripts in reference/)
+        #[arg(short, long)]
+        scan_set: String,
+
+        /// Write the report as JSON, for use as a later baseline
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Earlier JSON report to compare the accuracy with
+        #[arg(long)]
+        baseline: Option<String>,
+    },
+
+    /// Write a sidecar next to each scan with its corrected text,
+    /// classification and confidence, for photo and asset management tools
+    Annotate {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
+
+        /// Sidecar format: xmp (<image>.xmp) or json (<image>.json)
+        #[arg(long, default_value = "xmp", value_parser = ["xmp", "json"])]
+        format: String,
+    },
+
+    /// Write small previews of every scan into thumbnails/ of the scan set
+    Thumbnails {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
+
+        /// Longest side of the previews, in pixels
+        #[arg(long, default_value_t = scan3data::thumbnails::DEFAULT_THUMBNAIL_SIZE)]
+        size: u32,
+    },
+
+    /// Draw an 80-column grid, at the detected character pitch, over each
+    /// processed image to check column alignment
+    Grid {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
+
+        /// Output directory for the overlay images
+        #[arg(short, long)]
+        output: String,
+    },
+
+    /// Draw each card of an exported deck as an image, with the
+    /// interpretation printed along the top and the punched holes
+    RenderCards {
+        /// Deck file: text deck, card_deck JSON (.json) or simh binary
+        /// deck (.dck)
+        #[arg(short, long)]
+        input: String,
+
+        /// Output directory for the card images (card-0001.png, ...)
+        #[arg(short, long)]
+        output: String,
+
+        /// Keypunch the deck was punched on: 029, 026-fortran or
+        /// 026-commercial
+        #[arg(long, default_value = "029")]
+        keypunch: String,
+
+        /// Card width in pixels
+        #[arg(long, default_value_t = core_pipeline::card_render::DEFAULT_CARD_WIDTH)]
+        width: u32,
+    },
+
+    /// Compare Tesseract alone and corrected by vision models against
+    /// ground truth transcriptions (character and word error rates)
+    Bench {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
+
+        /// Directory of ground truth transcriptions, named after the images
+        /// (<name>.txt)
+        #[arg(long)]
+        truth: String,
+
+        /// Vision model to compare; repeat to compare several (default:
+        /// llava:latest)
+        #[arg(long)]
+        vision_model: Vec<String>,
+
+        /// Downscale images sent to the vision models to this many pixels
+        /// on the longest side (full resolution if unset)
+        #[arg(long)]
+        vision_max_dimension: Option<u32>,
+
+        /// Number of artifacts to preprocess and OCR in parallel (default: 1)
+        #[arg(short, long)]
+        jobs: Option<usize>,
+
+        /// Read the Gemini-cleaned image of artifacts that have one
+        #[arg(long)]
+        prefer_cleaned: bool,
+
+        /// Write the report as JSON
+        #[arg(short, long)]
+        output: Option<String>,
+    },
+
+    /// Check external dependencies (Tesseract, Ollama, Gemini API key)
+    Doctor,
+
+    /// Show which characters correction changed in the raw OCR text, as a
+    /// confusion table (O -> 0, B -> 8, ...)
+    OcrDiff {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
+
+        /// Number of most frequent changes to show
+        #[arg(long, default_value_t = 30)]
+        top: usize,
+    },
+
+    /// Compare two scan sets artifact by artifact (matched by image content)
+    Diff {
+        /// First scan set directory
+        #[arg(short)]
+        a: String,
+
+        /// Second scan set directory
+        #[arg(short)]
+        b: String,
+    },
+
+    /// Report scan set analytics (classification, confidence, OCR, duplicates,
+    /// page numbering)
+    Stats {
+        /// Scan set directory
+        #[arg(short, long)]
+        scan_set: String,
+    },
+
+    /// Manage single artifacts of a scan set (list, show, remove)
+    Artifact {
+        #[command(subcommand)]
+        action: artifact::ArtifactCommand,
+    },
+
+    /// Print a shell completion script (e.g. for ~/.bash_completion)
+    Completions {
+        /// Shell to complete in
+        shell: Shell,
+    },
+
+    /// Serve the web UI
+    Serve {
+        /// Address to listen on (default: 127.0.0.1; 0.0.0.0 for every
+        /// interface)
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Port to listen on (default: 7214)
+        #[arg(short, long)]
+        port: Option<u16>,
+
+        /// Mode: spa (standalone) or api (with backend)
+        #[arg(short, long, default_value = "spa", value_parser = ["spa", "api"])]
+        mode: String,
+
+        /// Directory of the built frontend (default: dist)
+        #[arg(long)]
+        dist: Option<PathBuf>,
+
+        /// Scan set directory the API serves (api mode)
+        #[arg(short, long)]
+        scan_set: Option<String>,
+    },
+}
+
+/// Options of the ingest phase (`ingest` and `run`)
+#[derive(Args)]
+struct IngestArgs {
+    /// Keypunch the cards were punched on: 029, 026-fortran or
+    /// 026-commercial
+    #[arg(long, default_value = "029")]
+    keypunch: String,
+
+    /// Resolution to rasterize PDF pages at (default: 300)
+    #[arg(long)]
+    pdf_dpi: Option<u32>,
+
+    /// List images whose perceptual hashes differ in at most this many of
+    /// 256 bits as suspected near-duplicates (default: 12)
+    #[arg(long)]
+    near_duplicate_distance: Option<u32>,
+
+    /// Also keep the untouched source files under originals/ in the scan
+    /// set (ingest re-encodes images to JPEG, dropping EXIF)
+    #[arg(long)]
+    copy_originals: bool,
+
+    /// Store the manifest and artifacts as JSON files or in a SQLite
+    /// database (default: json; sqlite needs the sqlite feature)
+    #[arg(
+        long,
+        value_parser = PossibleValuesParser::new(["json", "sqlite"])
+            .map(|name| StorageBackend::parse(&name).unwrap_or_default())
+    )]
+    storage: Option<StorageBackend>,
+}
+
+impl IngestArgs {
+    /// Ingest options, with unset flags taken from the configuration
+    fn options(&self, config: &Config) -> IngestOptions {
+        let settings = &config.ingest;
+        let defaults = IngestOptions::default();
+        IngestOptions {
+            pdf_dpi: self
+                .pdf_dpi
+                .or(settings.pdf_dpi)
+                .unwrap_or(defaults.pdf_dpi),
+            near_duplicate_distance: self
+                .near_duplicate_distance
+                .or(settings.near_duplicate_distance)
+                .unwrap_or(defaults.near_duplicate_distance),
+            copy_originals: self.copy_originals || settings.copy_originals.unwrap_or(false),
+            storage: self.storage.or(settings.storage).unwrap_or_default(),
+        }
+    }
+}
+
+/// Options of the analysis phase (`analyze` and `run`)
+#[derive(Args)]
+struct AnalyzeArgs {
+    /// Use LLM for classification
+    #[arg(long)]
+    use_llm: bool,
+
+    /// Use vision model for OCR correction with layout preservation
+    #[arg(long)]
+    use_vision: bool,
+
+    /// Vision model to use (default: llava:latest)
+    #[arg(long)]
+    vision_model: Option<String>,
+
+    /// Downscale images sent to the vision model to this many pixels
+    /// on the longest side (full resolution if unset)
+    #[arg(long)]
+    vision_max_dimension: Option<u32>,
+
+    /// Automatically fix common OCR confusions (O/0, I/1, S/5, B/8, DC)
+    #[arg(long)]
+    autofix: bool,
+
+    /// Minimum confidence for an auto-fix to be applied (default: 0.9)
+    #[arg(long)]
+    autofix_threshold: Option<f32>,
+
+    /// TOML file of external commands to run after OCR (post-ocr) and
+    /// after correction (post-correct)
+    #[arg(long)]
+    hooks: Option<PathBuf>,
+
+    /// Number of artifacts to preprocess and OCR in parallel (default: 1)
+    #[arg(short, long)]
+    jobs: Option<usize>,
+
+    /// Number of vision or text model calls to run at the same time
+    /// (default: 1)
+    #[arg(long)]
+    model_jobs: Option<usize>,
+
+    /// Analyze the Gemini-cleaned image of artifacts that have one (see
+    /// 'clean')
+    #[arg(long)]
+    prefer_cleaned: bool,
+
+    /// TOML rule-set file for validation (see 'validate --rules')
+    #[arg(long)]
+    rules: Option<PathBuf>,
+}
+
+impl AnalyzeArgs {
+    /// Analysis options, with the hooks file loaded and unset flags taken
+    /// from the configuration
+    fn options(self, config: &Config) -> Result<AnalyzeOptions> {
+        let settings = &config.analyze;
+        let vision_model = self
+            .vision_model
+            .or_else(|| config.models.vision.clone())
+            .unwrap_or_else(|| DEFAULT_VISION_MODEL.to_string());
+        let autofix_threshold = self
+            .autofix_threshold
+            .or(settings.autofix_threshold)
+            .unwrap_or(core_pipeline::autofix::DEFAULT_THRESHOLD);
+        Ok(AnalyzeOptions {
+            ollama: config.ollama_config(),
+            use_llm: self.use_llm,
+            text_model: config.models.text.clone(),
+            vision_model: self.use_vision.then_some(vision_model),
+            vision_max_dimension: self.vision_max_dimension.or(settings.vision_max_dimension),
+            autofix_threshold: self.autofix.then_some(autofix_threshold),
+            hooks: match self.hooks {
+                Some(path) => HookSet::load(&path)?,
+                None => HookSet::default(),
+            },
+            jobs: self.jobs.or(settings.jobs).unwrap_or(1),
+            model_jobs: self.model_jobs.or(settings.model_jobs).unwrap_or(1),
+            prefer_cleaned: self.prefer_cleaned,
+            preprocess: config.preprocess_options(),
+            rules: config.rule_set(self.rules.as_deref())?,
+            resume: false,
+        })
     }
+}
+
+/// Options of the export phase (`export` and `run`)
+#[derive(Args)]
+struct ExportArgs {
+    /// Output format (default: card_deck)
+    #[arg(
+        short,
+        long,
+        value_parser = PossibleValuesParser::new(export::format_names())
+    )]
+    format: Option<String>,
+
+    /// Repository format: list page images by path and hash instead of
+    /// copying them
+    #[arg(long)]
+    reference_images: bool,
+
+    /// IIIF format: URL the manifest and scan set images are published at
+    #[arg(long)]
+    base_url: Option<String>,
+
+    /// IIIF format: IIIF Image API server to serve the images through
+    #[arg(long)]
+    image_service: Option<String>,
+
+    /// Card deck format: also write a DMS job deck and a simh ibm1130
+    /// script that runs it
+    #[arg(long)]
+    simh: bool,
+
+    /// DMS disk image attached by the simh script
+    #[arg(long, default_value = DEFAULT_DMS_DISK)]
+    dms_disk: String,
+
+    /// Text format: blank the sequence columns 73-80 of each card
+    #[arg(long)]
+    strip_sequence: bool,
+
+    /// BagIt format: export file or directory to add to the bag
+    /// (repeatable)
+    #[arg(long)]
+    include: Vec<PathBuf>,
+}
 
-    // Summary footer
-    output.push_str(
-        "================================================================================\n",
+impl ExportArgs {
+    /// Output format: the flag, else the configured format
+    fn format<'a>(&'a self, config: &'a Config) -> &'a str {
+        self.format
+            .as_deref()
+            .or(config.export.format.as_deref())
+            .unwrap_or(DEFAULT_EXPORT_FORMAT)
+    }
+}
+
+/// Ingest images and PDFs into a new scan set
+fn ingest_scan_set(
+    input_path: &str,
+    output_dir: &str,
+    keypunch: KeypunchModel,
+    options: &IngestOptions,
+    json: bool,
+) -> Result<()> {
+    if !json {
+        println!("🔍 Scanning for images in: {}", input_path);
+        println!("📦 Creating scan set in: {}", output_dir);
+    }
+
+    let manifest = scan3data::ingest_scan_set(
+        Path::new(input_path),
+        Path::new(output_dir),
+        keypunch,
+        options,
+        &mut |done, total| progress(json, format_args!("💾 Saving images {}/{}", done, total)),
+    )?;
+    end_progress(json);
+    if json {
+        return print_json(&manifest);
+    }
+
+    println!("📁 Found {} image file(s)", manifest.original_file_count);
+    println!("✨ Found {} unique image(s)", manifest.image_count);
+    if manifest.duplicate_count > 0 {
+        println!("   ({} duplicate(s) detected)", manifest.duplicate_count);
+    }
+    print_near_duplicates(manifest.near_duplicates.len(), output_dir);
+    println!("✅ Scan set created successfully!");
+    println!("   Scan Set ID: {}", manifest.scan_set_id.0);
+    let stored_in = match options.storage {
+        StorageBackend::Json => scan_set::MANIFEST_FILE,
+        StorageBackend::Sqlite => storage::SQLITE_FILE,
+    };
+    println!(
+        "   Manifest: {}",
+        Path::new(output_dir).join(stored_in).display()
     );
-    output.push_str("SUMMARY\n");
-    output.push_str(
-        "================================================================================\n",
+    println!("   Artifacts: {} page(s)", manifest.image_count);
+
+    Ok(())
+}
+
+/// Ingest images and PDFs into an existing scan set
+fn append_scan_set(
+    input_path: &str,
+    scan_set_dir: &str,
+    options: &IngestOptions,
+    json: bool,
+) -> Result<()> {
+    if !json {
+        println!("🔍 Scanning for images in: {}", input_path);
+        println!("📦 Appending to scan set: {}", scan_set_dir);
+    }
+
+    let summary = scan3data::append_to_scan_set(
+        Path::new(input_path),
+        Path::new(scan_set_dir),
+        options,
+        &mut |done, total| progress(json, format_args!("💾 Saving images {}/{}", done, total)),
+    )?;
+    end_progress(json);
+    if json {
+        return print_json(&summary);
+    }
+
+    println!("📁 Found {} image file(s)", summary.files);
+    println!("✨ Added {} new image(s)", summary.added);
+    if summary.files > summary.added {
+        println!(
+            "   ({} duplicate(s) of new or existing images)",
+            summary.files - summary.added
+        );
+    }
+    print_near_duplicates(summary.near_duplicates, scan_set_dir);
+    println!("✅ Scan set updated!");
+    println!("   Artifacts: {} page(s)", summary.manifest.image_count);
+
+    Ok(())
+}
+
+/// Ingest scans dropped into a directory until interrupted
+fn watch_scan_set(
+    input_dir: &str,
+    scan_set_dir: &str,
+    keypunch: KeypunchModel,
+    options: &IngestOptions,
+    json: bool,
+) -> Result<()> {
+    scan3data::watch_folder(
+        Path::new(input_dir),
+        Path::new(scan_set_dir),
+        keypunch,
+        options,
+        &mut |event| {
+            if json {
+                // One JSON object per line, as events happen
+                println!("{}", watch_event_json(&event));
+                return ControlFlow::Continue(());
+            }
+            match event {
+                WatchEvent::Started { manifest, pending } => {
+                    println!("👀 Watching {} (Ctrl-C to stop)", input_dir);
+                    println!(
+                        "📦 Scan set: {} ({} page(s))",
+                        scan_set_dir, manifest.image_count
+                    );
+                    if pending > 0 {
+                        println!("   {} file(s) waiting to be ingested", pending);
+                    }
+                }
+                WatchEvent::Ingested { path, summary } => {
+                    if summary.added > 0 {
+                        println!(
+                            "✨ {}: {} new image(s), {} page(s) in total",
+                            path.display(),
+                            summary.added,
+                            summary.manifest.image_count
+                        );
+                    } else {
+                        println!("   {}: duplicate of an existing image", path.display());
+                    }
+                    print_near_duplicates(summary.near_duplicates, scan_set_dir);
+                }
+                WatchEvent::Failed { path, error } => {
+                    println!("⚠️  {}: {:#}", path.display(), error);
+                }
+            }
+            ControlFlow::Continue(())
+        },
+    )
+}
+
+/// A watch event as a JSON object
+fn watch_event_json(event: &WatchEvent) -> serde_json::Value {
+    match event {
+        WatchEvent::Started { manifest, pending } => serde_json::json!({
+            "event": "started",
+            "scan_set_id": manifest.scan_set_id,
+            "image_count": manifest.image_count,
+            "pending": pending,
+        }),
+        WatchEvent::Ingested { path, summary } => serde_json::json!({
+            "event": "ingested",
+            "path": path,
+            "added": summary.added,
+            "near_duplicates": summary.near_duplicates,
+            "image_count": summary.manifest.image_count,
+        }),
+        WatchEvent::Failed { path, error } => serde_json::json!({
+            "event": "failed",
+            "path": path,
+            "error": format!("{:#}", error),
+        }),
+    }
+}
+
+/// Print a result as pretty JSON on stdout
+fn print_json(value: &impl serde::Serialize) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Show progress on one line, on stderr when stdout carries JSON
+fn progress(json: bool, line: std::fmt::Arguments) {
+    if json {
+        eprint!("\r{}", line);
+        std::io::Write::flush(&mut std::io::stderr()).ok();
+    } else {
+        print!("\r{}", line);
+        std::io::Write::flush(&mut std::io::stdout()).ok();
+    }
+}
+
+/// End the progress line
+fn end_progress(json: bool) {
+    if json {
+        eprintln!();
+    } else {
+        println!();
+    }
+}
+
+/// Write sidecars next to the scans of a scan set
+fn annotate_scan_set(scan_set_dir: &str, format: &str) -> Result<()> {
+    let format = SidecarFormat::parse(format).context("Unknown sidecar format")?;
+    println!(
+        "🏷️  Writing {} sidecars: {}",
+        format.extension(),
+        scan_set_dir
     );
-    output.push_str(&format!("Total artifacts: {}\n", artifacts.len()));
-    output.push_str(&format!("Artifacts with text: {}\n", artifacts_with_text));
-    output.push_str(&format!("Total characters: {}\n", total_chars));
-    if artifacts_with_text > 0 {
-        output.push_str(&format!(
-            "Average characters per artifact: {}\n",
-            total_chars / artifacts_with_text
-        ));
-    }
-    output.push_str(
-        "================================================================================\n",
+
+    let summary =
+        scan3data::annotate_scan_set(Path::new(scan_set_dir), format, &mut |done, total| {
+            progress(false, format_args!("   Image {}/{}", done, total))
+        })?;
+    end_progress(false);
+
+    println!("✅ Sidecars written: {}", summary.annotated);
+    if summary.unanalyzed > 0 {
+        println!(
+            "⚠️  {} artifact(s) without text (run analyze first)",
+            summary.unanalyzed
+        );
+    }
+    Ok(())
+}
+
+/// Write thumbnails of the scans of a scan set
+fn generate_thumbnails(scan_set_dir: &str, size: u32) -> Result<()> {
+    println!("🖼️  Making {} px thumbnails: {}", size, scan_set_dir);
+
+    let summary =
+        scan3data::generate_thumbnails(Path::new(scan_set_dir), size, &mut |done, total| {
+            progress(false, format_args!("   Image {}/{}", done, total))
+        })?;
+    end_progress(false);
+
+    println!("✅ Thumbnails complete!");
+    println!("   Created: {}", summary.created);
+    if summary.existing > 0 {
+        println!("   Already there: {}", summary.existing);
+    }
+
+    Ok(())
+}
+
+/// Draw column grid overlays of the processed images of a scan set
+fn render_grids(scan_set_dir: &str, output: &str) -> Result<()> {
+    println!("📐 Drawing column grids: {}", scan_set_dir);
+
+    let summary = scan3data::render_grids(
+        Path::new(scan_set_dir),
+        Path::new(output),
+        &mut |done, total| progress(false, format_args!("   Image {}/{}", done, total)),
+    )?;
+    end_progress(false);
+
+    for page in &summary.pages {
+        match page.grid {
+            Some(grid) => println!(
+                "   {}: pitch {:.2} px, column 1 at x = {:.0}",
+                page.image.display(),
+                grid.pitch,
+                grid.origin
+            ),
+            None => println!("   {}: no character pitch detected", page.image.display()),
+        }
+    }
+    let drawn = summary.pages.iter().filter(|p| p.output.is_some()).count();
+    println!("✅ Grids drawn: {} image(s) in {}", drawn, output);
+    if summary.unprocessed > 0 {
+        println!(
+            "⚠️  {} artifact(s) without a processed image (run analyze first)",
+            summary.unprocessed
+        );
+    }
+
+    Ok(())
+}
+
+/// Draw the cards of an exported deck as images in an output directory
+fn render_cards(deck: &str, output: &str, keypunch: KeypunchModel, width: u32) -> Result<()> {
+    println!(
+        "🃏 Drawing cards: {} (keypunch {})",
+        deck,
+        keypunch.as_str()
     );
 
-    // Write to file
-    fs::write(output_file, &output)
-        .with_context(|| format!("Failed to write output file: {}", output_file))?;
+    let cards = card_render::read_deck(Path::new(deck), keypunch)?;
+    fs::create_dir_all(output)
+        .with_context(|| format!("Failed to create directory: {}", output))?;
+    for (index, holes) in cards.iter().enumerate() {
+        let path = Path::new(output).join(format!("card-{:04}.png", index + 1));
+        card_render::render_punched_card(holes, keypunch, width)
+            .save(&path)
+            .with_context(|| format!("Failed to write: {}", path.display()))?;
+        progress(false, format_args!("   Card {}/{}", index + 1, cards.len()));
+    }
+    end_progress(false);
+
+    println!("✅ Cards drawn: {} in {}", cards.len(), output);
+    Ok(())
+}
+
+/// Point out suspected near-duplicates to review
+/// Pack a scan set into a zip file
+fn pack_scan_set(scan_set_dir: &str, output: &str) -> Result<()> {
+    println!("📦 Packing scan set: {}", scan_set_dir);
+
+    let summary = scan3data::pack_scan_set(Path::new(scan_set_dir), Path::new(output))?;
+
+    println!("✅ Pack complete!");
+    println!("   Output: {}", output);
+    println!("   Files: {} ({} bytes)", summary.files, summary.bytes);
+
+    Ok(())
+}
+
+/// Unpack a zip file into a scan set, verifying its checksums
+fn unpack_scan_set(input: &str, output: &str) -> Result<()> {
+    println!("📦 Unpacking: {}", input);
+
+    let summary = scan3data::unpack_scan_set(Path::new(input), Path::new(output))?;
+
+    println!("✅ Unpack complete, all checksums match!");
+    println!("   Scan set: {}", output);
+    println!("   Files: {} ({} bytes)", summary.files, summary.bytes);
+
+    Ok(())
+}
+
+/// Split a scan set by classification, or split off a selection
+fn split_scan_set(scan_set_dir: &str, output: &str, ids: &[String]) -> Result<()> {
+    println!("✂️  Splitting scan set: {}", scan_set_dir);
+
+    let parts = if ids.is_empty() {
+        scan3data::split_by_kind(Path::new(scan_set_dir), Path::new(output))?
+    } else {
+        vec![scan3data::split_selection(
+            Path::new(scan_set_dir),
+            ids,
+            Path::new(output),
+        )?]
+    };
+
+    println!("✅ Wrote {} scan set(s):", parts.len());
+    for part in &parts {
+        println!(
+            "   {} ({} page(s))",
+            part.dir.display(),
+            part.manifest.image_count
+        );
+    }
+
+    Ok(())
+}
+
+/// Put the pages of a scan set in reading order
+async fn reorder_scan_set(scan_set_dir: &str, options: &ReorderOptions) -> Result<()> {
+    println!("🔀 Reordering scan set: {}", scan_set_dir);
+
+    let summary = scan3data::reorder_scan_set(Path::new(scan_set_dir), options).await?;
+
+    println!("📄 Documents: {}", summary.documents);
+    println!("   By page number: {}", summary.by_page_number);
+    if options.use_llm {
+        println!("   By text model: {}", summary.by_model);
+    }
+    if summary.model_failures > 0 {
+        println!(
+            "⚠️  Text model failed on {} document(s), ordered by page number instead",
+            summary.model_failures
+        );
+    }
+    println!("✅ Reading order saved!");
+    println!("   Pages moved: {}", summary.moved);
+
+    Ok(())
+}
+
+/// Merge two scan sets into a new one
+fn merge_scan_sets(a: &str, b: &str, output: &str, near_duplicate_distance: u32) -> Result<()> {
+    println!("🔗 Merging scan sets: {} + {}", a, b);
+
+    let summary = scan3data::merge_scan_sets(
+        Path::new(a),
+        Path::new(b),
+        Path::new(output),
+        near_duplicate_distance,
+    )?;
+
+    if summary.duplicates > 0 {
+        println!(
+            "🔁 {} artifact(s) of {} were already in {}",
+            summary.duplicates, b, a
+        );
+    }
+    print_near_duplicates(summary.manifest.near_duplicates.len(), output);
+    println!("✅ Scan set merged!");
+    println!("   Output: {}", output);
+    println!("   Artifacts: {} page(s)", summary.manifest.image_count);
+
+    Ok(())
+}
+
+fn print_near_duplicates(count: usize, scan_set_dir: &str) {
+    if count > 0 {
+        println!(
+            "⚠️  {} suspected near-duplicate pair(s), listed for review in {}",
+            count,
+            Path::new(scan_set_dir)
+                .join(scan_set::MANIFEST_FILE)
+                .display()
+        );
+    }
+}
+
+/// Clean the scans of a scan set with Gemini
+async fn clean_scan_set(scan_set_dir: &str, options: &CleanOptions, config: &Config) -> Result<()> {
+    let client = GeminiClient::new(config.gemini_config()?)?;
+    println!("🧽 Cleaning images of: {}", scan_set_dir);
+
+    let summary = scan3data::clean_scan_set(
+        Path::new(scan_set_dir),
+        &client,
+        options,
+        &mut |done, total| {
+            print!("\r   Cleaned {}/{}", done, total);
+            std::io::Write::flush(&mut std::io::stdout()).ok();
+        },
+    )
+    .await?;
+    println!();
+
+    println!("✅ Cleaning complete!");
+    println!("   Cleaned images: {}", summary.cleaned);
+    if summary.skipped > 0 {
+        println!(
+            "   Skipped: {} artifact(s) already cleaned (--force to redo)",
+            summary.skipped
+        );
+    }
+    if summary.failed > 0 {
+        println!("⚠️  Failed: {} (see artifact notes)", summary.failed);
+    }
+    println!("💰 Cost: ${:.3}", summary.cost_usd);
+    if summary.budget_reached {
+        bail!(
+            "Budget of ${:.2} reached; rerun to clean the remaining images",
+            options.max_cost_usd.unwrap_or_default()
+        );
+    }
+    println!("💡 Tip: analyze --prefer-cleaned to OCR the cleaned images");
+
+    Ok(())
+}
+
+/// Print the projected cost of cleaning a scan set
+fn estimate_clean(scan_set_dir: &str, options: &CleanOptions) -> Result<()> {
+    let estimate = scan3data::estimate_clean(Path::new(scan_set_dir), options)?;
+
+    println!("🧮 Cleaning estimate for: {}", scan_set_dir);
+    println!(
+        "   Images to clean: {} (${:.3} at ${} each)",
+        estimate.images,
+        estimate.cost_usd,
+        llm_bridge::imagen::COST_PER_IMAGE_USD
+    );
+    if estimate.skipped > 0 {
+        println!("   Already cleaned: {}", estimate.skipped);
+    }
+    if let (Some(images), Some(max)) = (estimate.within_budget, options.max_cost_usd) {
+        println!("   Budget ${:.2} covers {} image(s)", max, images);
+    }
+    println!(
+        "   Spent on this scan set so far: ${:.3}",
+        estimate.spent_usd
+    );
+
+    Ok(())
+}
+
+/// Analyze a scan set using OCR and optional LLM classification
+async fn analyze_scan_set(scan_set_dir: &str, options: &AnalyzeOptions, json: bool) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    if !json {
+        println!("🔬 Analyzing scan set: {}", scan_set_dir);
+        if options.use_llm {
+            println!("🤖 LLM mode enabled (cross-checking heuristic classification)");
+        }
+        if let Some(ref model) = options.vision_model {
+            println!("👁️  Vision mode enabled (model: {})", model);
+        }
+        if !options.hooks.is_empty() {
+            println!("🪝 Running {} hook(s)", options.hooks.hooks.len());
+        }
+    }
+
+    let summary = scan3data::analyze_scan_set(scan_set_path, options, &mut |p| {
+        progress(
+            json,
+            format_args!("   {:<7} {}/{}", p.phase.as_str(), p.done, p.total),
+        )
+    })
+    .await?;
+    end_progress(json);
+    if json {
+        return print_json(&summary);
+    }
+
+    println!("✅ Analysis complete!");
+    if summary.resumed > 0 {
+        println!(
+            "   Resumed: {} artifact(s) already analyzed",
+            summary.resumed
+        );
+    }
+    if summary.transcribed > 0 {
+        println!(
+            "   Skipped: {} artifact(s) with a human transcription",
+            summary.transcribed
+        );
+    }
+    if summary.cached_stages > 0 {
+        println!("   Reused {} cached stage result(s)", summary.cached_stages);
+    }
+    println!(
+        "   Processed images: {}",
+        scan_set_path.join(DERIVED_DIR).display()
+    );
+    println!(
+        "   Updated artifacts: {}",
+        scan_set_path.join(scan_set::ARTIFACTS_FILE).display()
+    );
+
+    println!("📊 OCR Statistics:");
+    println!(
+        "   Artifacts with text: {}/{}",
+        summary.with_text, summary.artifacts
+    );
+    println!(
+        "   Average text length: {:.0} chars",
+        summary.average_text_len
+    );
+    println!("🔎 Validation issues: {}", summary.validation_issues);
+
+    Ok(())
+}
+
+/// Attach existing transcriptions to the artifacts of a scan set
+fn import_text(scan_set_dir: &str, mapping_file: &str) -> Result<()> {
+    println!("⌨️  Importing transcriptions into: {}", scan_set_dir);
+
+    let summary =
+        scan3data::import_transcriptions(Path::new(scan_set_dir), Path::new(mapping_file))?;
+
+    println!("✅ Import complete!");
+    println!("   Transcriptions imported: {}", summary.imported);
+    if !summary.unmatched.is_empty() {
+        println!(
+            "⚠️  {} image(s) matched no artifact:",
+            summary.unmatched.len()
+        );
+        for image in &summary.unmatched {
+            println!("   {}", image);
+        }
+    }
+
+    Ok(())
+}
+
+/// Analyze one artifact of a scan set again
+async fn reprocess_artifact(scan_set_dir: &str, id: &str, options: &AnalyzeOptions) -> Result<()> {
+    println!("🔁 Reprocessing artifact {} of: {}", id, scan_set_dir);
+    if let Some(ref model) = options.vision_model {
+        println!("👁️  Vision mode enabled (model: {})", model);
+    }
+
+    let artifact = scan3data::reprocess_artifact(Path::new(scan_set_dir), id, options).await?;
+
+    println!("✅ Reprocessing complete!");
+    println!("   Artifact: {}", artifact.id.0);
+    println!(
+        "   Kind: {:?} (confidence {:.2})",
+        artifact.layout_label, artifact.metadata.confidence
+    );
+    println!(
+        "   Text length: {} chars",
+        artifact.content_text.as_deref().map_or(0, str::len)
+    );
+    println!(
+        "🔎 Validation issues: {}",
+        artifact.metadata.validation_issues.len()
+    );
+
+    Ok(())
+}
+
+/// Export raw OCR text to a text file for inspection
+fn text_dump_scan_set(scan_set_dir: &str, output_file: &str, format: &str) -> Result<()> {
+    let format = TextDumpFormat::parse(format).context("Unknown text dump format")?;
+    println!("📝 Dumping OCR text from: {}", scan_set_dir);
+
+    let summary =
+        scan3data::text_dump_scan_set(Path::new(scan_set_dir), Path::new(output_file), format)?;
 
     println!("✅ Text dump complete!");
     println!("   Output: {}", output_file);
     println!(
         "   Artifacts with text: {}/{}",
-        artifacts_with_text,
-        artifacts.len()
+        summary.with_text, summary.artifacts
     );
-    println!("   Total characters: {}", total_chars);
+    println!("   Total characters: {}", summary.total_chars);
     println!("\n💡 Tip: View with a monospace font to see OCR layout");
 
     Ok(())
 }
 
-/// Generate HTML comparison view of original images vs corrected OCR text
-fn generate_comparison_html(scan_set_dir: &str, output_file: &str, show_grid: bool) -> Result<()> {
-    let scan_set_path = Path::new(scan_set_dir);
+/// Export the OCR text of each artifact to its own file
+fn text_dump_split(scan_set_dir: &str, output_dir: &str) -> Result<()> {
+    println!("📝 Dumping OCR text per artifact from: {}", scan_set_dir);
 
-    if !scan_set_path.exists() {
-        anyhow::bail!("Scan set directory does not exist: {}", scan_set_dir);
-    }
+    let summary = scan3data::text_dump_split(Path::new(scan_set_dir), Path::new(output_dir))?;
+
+    println!("✅ Text dump complete!");
+    println!("   Output: {} ({} files)", output_dir, summary.artifacts);
+    println!(
+        "   Artifacts with text: {}/{}",
+        summary.with_text, summary.artifacts
+    );
+    println!("   Total characters: {}", summary.total_chars);
 
+    Ok(())
+}
+
+/// Generate HTML comparison view of original images vs corrected OCR text
+fn generate_comparison_html(
+    scan_set_dir: &str,
+    output_file: &str,
+    options: &CompareOptions,
+) -> Result<()> {
     println!("📊 Generating comparison view: {}", scan_set_dir);
 
-    // Load manifest and artifacts
-    let manifest_path = scan_set_path.join("manifest.json");
-    let manifest_json = fs::read_to_string(&manifest_path)
-        .with_context(|| format!("Failed to read manifest: {}", manifest_path.display()))?;
-    let _manifest: ScanSetManifest =
-        serde_json::from_str(&manifest_json).context("Failed to parse manifest.json")?;
-
-    let artifacts_path = scan_set_path.join("artifacts.json");
-    let artifacts_json = fs::read_to_string(&artifacts_path)
-        .with_context(|| format!("Failed to read artifacts: {}", artifacts_path.display()))?;
-    let artifacts: Vec<PageArtifact> =
-        serde_json::from_str(&artifacts_json).context("Failed to parse artifacts.json")?;
-
-    println!("📄 Processing {} artifact(s)...", artifacts.len());
-
-    // Build HTML
-    let mut html = String::new();
-
-    // HTML header with CSS
-    html.push_str(&generate_html_header(show_grid));
-
-    // Add each artifact comparison
-    for (idx, artifact) in artifacts.iter().enumerate() {
-        println!("   Artifact {}/{}", idx + 1, artifacts.len());
-
-        // Encode image as base64 data URL
-        let image_path = scan_set_path.join(&artifact.raw_image_path);
-        let image_bytes = fs::read(&image_path)
-            .with_context(|| format!("Failed to read image: {}", image_path.display()))?;
-        let image_b64 =
-            base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &image_bytes);
-        let image_ext = image_path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("jpg");
-        let data_url = format!("data:image/{};base64,{}", image_ext, image_b64);
-
-        // Get corrected text
-        let corrected_text = artifact
-            .content_text
-            .as_deref()
-            .unwrap_or("[No text extracted]");
-
-        // Get metadata
-        let filenames = artifact.metadata.original_filenames.join(", ");
-        let notes = if artifact.metadata.notes.is_empty() {
-            "None".to_string()
-        } else {
-            artifact.metadata.notes.join("; ")
-        };
-
-        // Add comparison section
-        html.push_str(&format!(
-            r#"
-<div class="comparison">
-    <div class="header">
-        <h2>Artifact {}/{}</h2>
-        <div class="metadata">
-            <div><strong>Original files:</strong> {}</div>
-            <div><strong>Processing notes:</strong> {}</div>
-        </div>
-    </div>
-    <div class="side-by-side">
-        <div class="panel">
-            <h3>Original Scan</h3>
-            <div class="image-container">
-                <img src="{}" alt="Original scan" />
-            </div>
-        </div>
-        <div class="panel">
-            <h3>Corrected OCR Text</h3>
-            <div class="text-container">
-                <pre class="ocr-text">{}</pre>
-            </div>
-        </div>
-    </div>
-</div>
-"#,
-            idx + 1,
-            artifacts.len(),
-            html_escape(&filenames),
-            html_escape(&notes),
-            data_url,
-            html_escape(corrected_text)
-        ));
-    }
-
-    // HTML footer
-    html.push_str("</body></html>");
-
-    // Write HTML file
-    fs::write(output_file, &html)
-        .with_context(|| format!("Failed to write HTML file: {}", output_file))?;
+    let artifacts = scan3data::generate_comparison_html(
+        Path::new(scan_set_dir),
+        Path::new(output_file),
+        options,
+    )?;
 
     println!("✅ Comparison view complete!");
     println!("   Output: {}", output_file);
-    println!("   Artifacts: {}", artifacts.len());
+    if let Some(per_page) = options.per_page {
+        let pages = artifacts.div_ceil(per_page.max(1)).max(1);
+        if pages > 1 {
+            let last = scan3data::compare::page_path(Path::new(output_file), pages);
+            println!("   Pages: {} (through {})", pages, last.display());
+        }
+    }
+    println!("   Artifacts: {}", artifacts);
     println!("\n💡 Open {} in a browser to view", output_file);
 
     Ok(())
 }
 
-/// Generate HTML header with CSS styling
-fn generate_html_header(show_grid: bool) -> String {
-    let grid_css = if show_grid {
-        r#"
-        .ocr-text {
-            background-image: repeating-linear-gradient(
-                to right,
-                transparent,
-                transparent 0.6ch,
-                rgba(0, 150, 255, 0.1) 0.6ch,
-                rgba(0, 150, 255, 0.1) 0.61ch
-            );
+/// Export a scan set in the chosen format
+fn export_scan_set(scan_set: &str, output: &str, args: &ExportArgs, config: &Config) -> Result<()> {
+    let format = args.format(config);
+    match format {
+        "repository" => export::export_repository(scan_set, output, args.reference_images)?,
+        "markdown" => export::export_transcript(scan_set, output, TranscriptLayout::SingleFile)?,
+        "mdbook" => export::export_transcript(scan_set, output, TranscriptLayout::MdBook)?,
+        "csv" => export::export_metadata(scan_set, output, Delimiter::Comma)?,
+        "tsv" => export::export_metadata(scan_set, output, Delimiter::Tab)?,
+        "iiif" => {
+            let options = IiifOptions {
+                base_url: args
+                    .base_url
+                    .clone()
+                    .context("The iiif format needs --base-url")?,
+                image_service: args.image_service.clone(),
+            };
+            export::export_iiif(scan_set, output, &options)?
         }
-        "#
-    } else {
-        ""
-    };
-
-    format!(
-        r#"<!DOCTYPE html>
-<html lang="en">
-<head>
-    <meta charset="UTF-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>OCR Comparison View</title>
-    <style>
-        * {{
-            margin: 0;
-            padding: 0;
-            box-sizing: border-box;
-        }}
-        body {{
-            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
-            background: #f5f5f5;
-            padding: 20px;
-        }}
-        .comparison {{
-            background: white;
-            border-radius: 8px;
-            padding: 20px;
-            margin-bottom: 30px;
-            box-shadow: 0 2px 4px rgba(0,0,0,0.1);
-        }}
-        .header {{
-            margin-bottom: 20px;
-            border-bottom: 2px solid #e0e0e0;
-            padding-bottom: 15px;
-        }}
-        .header h2 {{
-            color: #333;
-            margin-bottom: 10px;
-        }}
-        .metadata {{
-            font-size: 14px;
-            color: #666;
-        }}
-        .metadata div {{
-            margin: 5px 0;
-        }}
-        .side-by-side {{
-            display: grid;
-            grid-template-columns: 1fr 1fr;
-            gap: 20px;
-        }}
-        .panel {{
-            border: 1px solid #ddd;
-            border-radius: 4px;
-            overflow: hidden;
-        }}
-        .panel h3 {{
-            background: #f8f8f8;
-            padding: 10px 15px;
-            margin: 0;
-            font-size: 16px;
-            color: #555;
-            border-bottom: 1px solid #ddd;
-        }}
-        .image-container {{
-            padding: 15px;
-            background: #fafafa;
-            display: flex;
-            justify-content: center;
-            align-items: flex-start;
-            overflow: auto;
-            max-height: 800px;
-        }}
-        .image-container img {{
-            max-width: 100%;
-            height: auto;
-            border: 1px solid #ddd;
-            background: white;
-        }}
-        .text-container {{
-            padding: 15px;
-            background: #fafafa;
-            overflow: auto;
-            max-height: 800px;
-        }}
-        .ocr-text {{
-            font-family: "Courier New", Courier, monospace;
-            font-size: 12px;
-            line-height: 1.4;
-            white-space: pre;
-            background: white;
-            padding: 15px;
-            border: 1px solid #ddd;
-            border-radius: 2px;
-            color: #222;
-        }}
-        {}
-        @media (max-width: 1200px) {{
-            .side-by-side {{
-                grid-template-columns: 1fr;
-            }}
-        }}
-    </style>
-</head>
-<body>
-    <h1 style="margin-bottom: 20px; color: #333;">IBM 1130 OCR Comparison View</h1>
-"#,
-        grid_css
-    )
-}
-
-/// Escape HTML special characters
-fn html_escape(text: &str) -> String {
-    text.replace('&', "&amp;")
-        .replace('<', "&lt;")
-        .replace('>', "&gt;")
-        .replace('"', "&quot;")
-        .replace('\'', "&#39;")
+        "mets" => export::export_mets(scan_set, output)?,
+        "alto" => export::export_page_ocr(scan_set, output, PageOcrFormat::Alto)?,
+        "hocr" => export::export_page_ocr(scan_set, output, PageOcrFormat::Hocr)?,
+        "pdf" => export::export_pdf(scan_set, output)?,
+        "bagit" => export::export_bag(scan_set, output, &args.include)?,
+        "sources" => export::export_sources(scan_set, output)?,
+        _ => {
+            let mut registry = ExporterRegistry::default();
+            registry.register(Box::new(TextDeckExporter::new(args.strip_sequence)));
+            export::export_scan_set(
+                &mut registry,
+                scan_set,
+                output,
+                format,
+                args.simh.then_some(args.dms_disk.as_str()),
+            )?
+        }
+    }
+    Ok(())
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
+    // Initialize tracing, on stderr so it never mixes with JSON output
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .init();
 
     let cli = Cli::parse();
+    let config = Config::discover(cli.config.as_deref())?;
+    let json = cli.json;
+    if json
+        && !matches!(
+            cli.command,
+            Commands::Ingest { .. }
+                | Commands::Analyze { .. }
+                | Commands::Validate { .. }
+                | Commands::Diff { .. }
+                | Commands::Stats { .. }
+                | Commands::Bench { .. }
+                | Commands::OcrDiff { .. }
+        )
+    {
+        bail!("--json is supported by ingest, analyze, validate, diff, ocr-diff, stats and bench");
+    }
 
     match cli.command {
-        Commands::Ingest { input, output } => {
-            ingest_scan_set(&input, &output)?;
+        Commands::Run {
+            input,
+            output,
+            ingest,
+            analysis,
+            export_output,
+            export,
+        } => {
+            let keypunch = keypunch::parse_model(&ingest.keypunch)?;
+            let ingest_options = ingest.options(&config);
+            let options = analysis.options(&config)?;
+            let export_output = match export_output {
+                Some(path) => PathBuf::from(path),
+                None => {
+                    let dir = Path::new(&output).join("export");
+                    fs::create_dir_all(&dir).with_context(|| {
+                        format!("Failed to create export directory: {}", dir.display())
+                    })?;
+                    dir.join(export::default_output_name(export.format(&config)))
+                }
+            };
+
+            println!("━━━ Phase 1/3: Scan ━━━");
+            ingest_scan_set(&input, &output, keypunch, &ingest_options, false)?;
+            println!("\n━━━ Phase 2/3: Classify & Correct ━━━");
+            analyze_scan_set(&output, &options, false).await?;
+            println!("\n━━━ Phase 3/3: Convert ━━━");
+            reconstruct::reconstruct_scan_set(&output, &options.rules)?;
+            export_scan_set(&output, &export_output.to_string_lossy(), &export, &config)
+        }
+        Commands::Ingest {
+            input,
+            output,
+            append,
+            watch,
+            ingest,
+        } => {
+            let options = ingest.options(&config);
+            if watch {
+                let keypunch = keypunch::parse_model(&ingest.keypunch)?;
+                watch_scan_set(&input, &output, keypunch, &options, json)?;
+            } else if append {
+                append_scan_set(&input, &output, &options, json)?;
+            } else {
+                let keypunch = keypunch::parse_model(&ingest.keypunch)?;
+                ingest_scan_set(&input, &output, keypunch, &options, json)?;
+            }
+            Ok(())
+        }
+        Commands::Pack { scan_set, output } => {
+            pack_scan_set(&scan_set, &output)?;
+            Ok(())
+        }
+        Commands::Unpack { input, output } => {
+            unpack_scan_set(&input, &output)?;
+            Ok(())
+        }
+        Commands::Split {
+            scan_set,
+            output,
+            ids,
+        } => {
+            split_scan_set(&scan_set, &output, &ids)?;
+            Ok(())
+        }
+        Commands::Merge {
+            a,
+            b,
+            output,
+            near_duplicate_distance,
+        } => {
+            let distance = near_duplicate_distance
+                .or(config.ingest.near_duplicate_distance)
+                .unwrap_or(IngestOptions::default().near_duplicate_distance);
+            merge_scan_sets(&a, &b, &output, distance)?;
+            Ok(())
+        }
+        Commands::Keypunch {
+            scan_set,
+            model,
+            document,
+        } => {
+            keypunch::set_keypunch(&scan_set, &model, document.as_deref())?;
+            Ok(())
+        }
+        Commands::Relabel {
+            scan_set,
+            from,
+            to,
+            filter,
+            dry_run,
+        } => {
+            relabel::relabel_scan_set(&scan_set, from.as_deref(), &to, &filter, dry_run)?;
+            Ok(())
+        }
+        Commands::Clean {
+            scan_set,
+            force,
+            estimate,
+            max_cost,
+        } => {
+            let options = CleanOptions {
+                force,
+                max_cost_usd: max_cost.or(config.clean.max_cost_usd),
+            };
+            if estimate {
+                estimate_clean(&scan_set, &options)?;
+            } else {
+                clean_scan_set(&scan_set, &options, &config).await?;
+            }
             Ok(())
         }
         Commands::Analyze {
             scan_set,
+            resume,
+            analysis,
+        } => {
+            let options = AnalyzeOptions {
+                resume,
+                ..analysis.options(&config)?
+            };
+            analyze_scan_set(&scan_set, &options, json).await?;
+            Ok(())
+        }
+        Commands::ImportText { scan_set, map } => {
+            import_text(&scan_set, &map)?;
+            Ok(())
+        }
+        Commands::Reprocess {
+            scan_set,
+            artifact,
+            analysis,
+        } => {
+            reprocess_artifact(&scan_set, &artifact, &analysis.options(&config)?).await?;
+            Ok(())
+        }
+        Commands::Reorder {
+            scan_set,
+            use_llm,
+            text_model,
+        } => {
+            let options = ReorderOptions {
+                ollama: config.ollama_config(),
+                use_llm,
+                text_model: text_model.or_else(|| config.models.text.clone()),
+            };
+            reorder_scan_set(&scan_set, &options).await?;
+            Ok(())
+        }
+        Commands::Reconstruct { scan_set, rules } => {
+            let rules = config.rule_set(rules.as_deref())?;
+            reconstruct::reconstruct_scan_set(&scan_set, &rules)?;
+            Ok(())
+        }
+        Commands::TrimCache { scan_set, stage } => {
+            cache::trim_cache(&scan_set, stage.as_deref())?;
+            Ok(())
+        }
+        Commands::Profile {
+            scan_set,
+            sample,
             use_llm,
-            use_vision,
             vision_model,
         } => {
-            analyze_scan_set(&scan_set, use_llm, use_vision, &vision_model).await?;
+            profile::profile_scan_set(&scan_set, sample, use_llm, vision_model.as_deref(), &config)
+                .await?;
             Ok(())
         }
         Commands::Export {
+            scan_set,
+            output,
+            export,
+        } => export_scan_set(&scan_set, &output, &export, &config),
+        Commands::TextDump {
             scan_set,
             output,
             format,
+            split,
         } => {
-            println!("Exporting {} -> {} (format: {})", scan_set, output, format);
-            // TODO: Implement export command
-            Ok(())
-        }
-        Commands::TextDump { scan_set, output } => {
-            text_dump_scan_set(&scan_set, &output)?;
+            if split {
+                text_dump_split(&scan_set, &output)?;
+            } else {
+                text_dump_scan_set(&scan_set, &output, &format)?;
+            }
             Ok(())
         }
         Commands::Compare {
             scan_set,
             output,
             show_grid,
+            raw_ocr,
+            column_diff,
+            link_images,
+            thumbnail_size,
+            per_page,
+            only_kind,
+            min_confidence,
+            max_confidence,
+        } => {
+            let options = CompareOptions {
+                show_grid,
+                raw_ocr,
+                column_diff,
+                link_images,
+                thumbnail_size,
+                per_page,
+                only_kind: only_kind.as_deref().map(relabel::parse_kind).transpose()?,
+                min_confidence,
+                max_confidence,
+            };
+            generate_comparison_html(&scan_set, &output, &options)?;
+            Ok(())
+        }
+        Commands::Validate {
+            scan_set,
+            output,
+            rules,
+        } => {
+            let rules_file = rules.or_else(|| config.analyze.rules.clone());
+            validate::validate_scan_set(&scan_set, &output, rules_file.as_deref(), json)?;
+            Ok(())
+        }
+        Commands::Score {
+            scan_set,
+            output,
+            baseline,
+        } => {
+            score::score_scan_set(&scan_set, output.as_deref(), baseline.as_deref())?;
+            Ok(())
+        }
+        Commands::Annotate { scan_set, format } => {
+            annotate_scan_set(&scan_set, &format)?;
+            Ok(())
+        }
+        Commands::Thumbnails { scan_set, size } => {
+            generate_thumbnails(&scan_set, size)?;
+            Ok(())
+        }
+        Commands::Grid { scan_set, output } => {
+            render_grids(&scan_set, &output)?;
+            Ok(())
+        }
+        Commands::RenderCards {
+            input,
+            output,
+            keypunch,
+            width,
         } => {
-            generate_comparison_html(&scan_set, &output, show_grid)?;
+            let keypunch = keypunch::parse_model(&keypunch)?;
+            render_cards(&input, &output, keypunch, width)?;
+            Ok(())
+        }
+        Commands::Bench {
+            scan_set,
+            truth,
+            vision_model,
+            vision_max_dimension,
+            jobs,
+            prefer_cleaned,
+            output,
+        } => {
+            let vision_models = if vision_model.is_empty() {
+                vec![config
+                    .models
+                    .vision
+                    .clone()
+                    .unwrap_or_else(|| DEFAULT_VISION_MODEL.to_string())]
+            } else {
+                vision_model
+            };
+            let options = AnalyzeOptions {
+                ollama: config.ollama_config(),
+                vision_max_dimension: vision_max_dimension.or(config.analyze.vision_max_dimension),
+                jobs: jobs.or(config.analyze.jobs).unwrap_or(1),
+                prefer_cleaned,
+                ..AnalyzeOptions::default()
+            };
+            bench::bench_scan_set(
+                &scan_set,
+                &truth,
+                &options,
+                &vision_models,
+                output.as_deref(),
+                json,
+            )
+            .await?;
+            Ok(())
+        }
+        Commands::Doctor => {
+            doctor::run_doctor(&config).await?;
+            Ok(())
+        }
+        Commands::OcrDiff { scan_set, top } => {
+            ocr_diff::ocr_diff_scan_set(&scan_set, top, json)?;
+            Ok(())
+        }
+        Commands::Diff { a, b } => {
+            diff::diff_scan_sets(&a, &b, json)?;
+            Ok(())
+        }
+        Commands::Stats { scan_set } => {
+            stats::stats_scan_set(&scan_set, json)?;
             Ok(())
         }
-        Commands::Serve { port, mode } => {
-            println!("Serving {} mode on port {}", mode, port);
-            // TODO: Implement serve command
-            // - For "spa" mode: serve static files
-            // - For "api" mode: start REST API server
+        Commands::Artifact { action } => {
+            artifact::run(action)?;
             Ok(())
         }
+        Commands::Completions { shell } => {
+            clap_complete::generate(
+                shell,
+                &mut Cli::command(),
+                "scan3data",
+                &mut std::io::stdout(),
+            );
+            Ok(())
+        }
+        Commands::Serve {
+            host,
+            port,
+            mode,
+            dist,
+            scan_set,
+        } => {
+            let host = host
+                .or_else(|| config.server.host.clone())
+                .unwrap_or_else(|| DEFAULT_HOST.to_string());
+            let port = port.or(config.server.port).unwrap_or(DEFAULT_PORT);
+            let dist = dist
+                .or_else(|| config.server.dist.clone())
+                .unwrap_or_else(|| PathBuf::from(DEFAULT_DIST_DIR));
+            if mode == "api" {
+                serve::serve_api(config, scan_set.as_deref(), dist, &host, port).await
+            } else {
+                serve::serve_spa(&dist, &host, port).await
+            }
+        }
     }
 }
@@ -0,0 +1,64 @@
+//! `ocr-diff` command: characters changed in the raw OCR text, as a
+//! confusion table
+
+use anyhow::Result;
+use core_pipeline::ocr_diff::OcrDiffReport;
+use std::path::Path;
+
+/// Length of the ID prefix shown per artifact
+const SHORT_ID_LEN: usize = 8;
+
+/// Show which characters correction changed in the OCR text of a scan set
+///
+/// Lists the number of changes per artifact and the `top` most frequent
+/// kinds of change; with `json`, the full report is printed instead.
+pub fn ocr_diff_scan_set(scan_set_dir: &str, top: usize, json: bool) -> Result<()> {
+    let report = OcrDiffReport::of_scan_set(Path::new(scan_set_dir))?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!("🔤 OCR changes in scan set: {}", scan_set_dir);
+    if report.compared == 0 {
+        println!("⚠️  No artifact has both cached OCR text and final text (run analyze)");
+        return Ok(());
+    }
+    for artifact in &report.artifacts {
+        println!(
+            "   {}  {}: {} change(s)",
+            &artifact.artifact_id[..SHORT_ID_LEN],
+            artifact.image.display(),
+            artifact.changes.len()
+        );
+    }
+
+    let total: usize = report.confusions.iter().map(|c| c.count).sum();
+    println!(
+        "📊 {} change(s) in {} of {} artifact(s) compared ({} skipped)",
+        total,
+        report.artifacts.len(),
+        report.compared,
+        report.skipped
+    );
+    for confusion in report.confusions.iter().take(top) {
+        println!(
+            "   {:>8} -> {:<8} {:>6}",
+            show_char(confusion.from),
+            show_char(confusion.to),
+            confusion.count
+        );
+    }
+    if report.confusions.len() > top {
+        println!(
+            "   ... {} more kind(s) of change (--top to show more)",
+            report.confusions.len() - top
+        );
+    }
+    Ok(())
+}
+
+/// A character quoted, so spaces show, or "(none)"
+fn show_char(c: Option<char>) -> String {
+    c.map_or_else(|| "(none)".to_string(), |c| format!("{:?}", c))
+}
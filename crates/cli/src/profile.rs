@@ -0,0 +1,145 @@
+//! `profile` command: time the pipeline stages over a sample of artifacts
+
+use anyhow::Result;
+use core_pipeline::classify::{classify_text, Language};
+use core_pipeline::image_loader::{encode_png, load_image, LoadOptions};
+use core_pipeline::ocr::extract_text_tesseract;
+use core_pipeline::preprocess::preprocess_image_timed;
+use core_pipeline::profile::StageTimings;
+use core_pipeline::scan_set;
+use core_pipeline::validate::RuleSet;
+use llm_bridge::text::DEFAULT_TEXT_MODEL;
+use scan3data::Config;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Run the pipeline over up to `sample` artifacts and print per-stage timings
+///
+/// Artifacts are sampled evenly across the scan set. Nothing is written:
+/// caches are bypassed so every stage does its full work. Models run on
+/// the configured Ollama server.
+pub async fn profile_scan_set(
+    scan_set_dir: &str,
+    sample: usize,
+    use_llm: bool,
+    vision_model: Option<&str>,
+    config: &Config,
+) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (manifest, artifacts) = scan_set::load(scan_set_path)?;
+    let keypunch = manifest.keypunch.model;
+
+    let sample = sample.clamp(1, artifacts.len().max(1));
+    let picked: Vec<_> = (0..sample.min(artifacts.len()))
+        .map(|i| &artifacts[i * artifacts.len() / sample])
+        .collect();
+    println!(
+        "⏱️  Profiling {} of {} artifact(s): {}",
+        picked.len(),
+        artifacts.len(),
+        scan_set_dir
+    );
+
+    let text_model = if use_llm {
+        let model = config.models.text.as_deref().unwrap_or(DEFAULT_TEXT_MODEL);
+        Some(llm_bridge::TextModel::new(
+            llm_bridge::OllamaClient::new(config.ollama_config())?,
+            model.to_string(),
+        ))
+    } else {
+        None
+    };
+    let vision = match vision_model {
+        Some(model) => Some(llm_bridge::VisionModel::new(
+            llm_bridge::OllamaClient::new(config.ollama_config())?,
+            model.to_string(),
+        )),
+        None => None,
+    };
+    let preprocess = config.preprocess_options();
+    let rules = RuleSet {
+        keypunch: Some(keypunch),
+        ..RuleSet::default()
+    };
+
+    let mut timings = StageTimings::default();
+    let mut failures = 0;
+    for (idx, artifact) in picked.iter().enumerate() {
+        print!("\r   Artifact {}/{}", idx + 1, picked.len());
+        std::io::Write::flush(&mut std::io::stdout()).ok();
+
+        let raw_image_path = scan_set_path.join(&artifact.raw_image_path);
+        let img = timings.time("io.load", || {
+            load_image(&raw_image_path, &LoadOptions::full())
+        })?;
+        let preprocessed = preprocess_image_timed(&img, &preprocess, &mut timings)?;
+        drop(img);
+
+        let text = match timings.time("ocr", || extract_text_tesseract(&preprocessed, keypunch)) {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!(
+                    "\n   Warning: OCR failed for {}: {}",
+                    artifact.raw_image_path.display(),
+                    e
+                );
+                failures += 1;
+                continue;
+            }
+        };
+
+        if let Some(ref vision) = vision {
+            let image_bytes = timings.time("io.encode", || {
+                encode_png(&image::DynamicImage::ImageLuma8(preprocessed.clone()))
+            })?;
+            let start = Instant::now();
+            let result = vision.correct_ocr_with_layout(&image_bytes, &text).await;
+            timings.record("llm.vision", start.elapsed());
+            if let Err(e) = result {
+                eprintln!("\n   Warning: Vision correction failed: {}", e);
+            }
+        }
+
+        let classification = timings.time("classify", || classify_text(&text));
+        if let Some(ref model) = text_model {
+            let start = Instant::now();
+            let result = model.refine_and_classify(&text).await;
+            timings.record("llm.classify", start.elapsed());
+            if let Err(e) = result {
+                eprintln!("\n   Warning: LLM classification failed: {}", e);
+            }
+        }
+
+        if classification.language == Language::Fortran {
+            timings.time("validate", || rules.validate_fortran(&text));
+        }
+    }
+    println!();
+
+    println!(
+        "   {:<22} {:>5} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "stage", "count", "total", "mean", "p50", "p90", "p99", "max"
+    );
+    for stage in timings.summary() {
+        println!(
+            "   {:<22} {:>5} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            stage.stage,
+            stage.count,
+            format_duration(stage.total),
+            format_duration(stage.mean),
+            format_duration(stage.p50),
+            format_duration(stage.p90),
+            format_duration(stage.p99),
+            format_duration(stage.max),
+        );
+    }
+    if failures > 0 {
+        println!("⚠️  OCR failed on {} artifact(s)", failures);
+    }
+    Ok(())
+}
+
+/// Milliseconds with one decimal, e.g. "12.3ms"
+fn format_duration(duration: Duration) -> String {
+    format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
@@ -0,0 +1,144 @@
+//! `reconstruct` command: rebuild documents and decks from scanned artifacts
+
+use anyhow::Result;
+use core_pipeline::classify::Language;
+use core_pipeline::reconstruct::{
+    build_documents, build_object_decks, extract_headers, group_documents, order_pages,
+    split_logical_pages,
+};
+use core_pipeline::scan_set;
+use core_pipeline::types::HighLevelArtifact;
+use core_pipeline::validate::RuleSet;
+use std::path::Path;
+
+/// Start of the note on pages sharing their page number
+const DUPLICATE_NOTE: &str = "Duplicate page number ";
+
+/// Split scans into logical pages, extract headers, order each document's
+/// pages and flag numbering problems
+///
+/// Artifacts are saved back in reading order. Each document is assembled
+/// into a listing and object cards into decks, saved together as the scan
+/// set's high-level artifacts. Listings are checked against the decks they
+/// match, and disagreements reported, under the given rules with each
+/// document's keypunch model.
+pub fn reconstruct_scan_set(scan_set_dir: &str, rules: &RuleSet) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (manifest, artifacts) = scan_set::load(scan_set_path)?;
+
+    println!("🧩 Reconstructing scan set: {}", scan_set_dir);
+
+    let scans = artifacts.len();
+    let mut artifacts = split_logical_pages(artifacts);
+    if artifacts.len() > scans {
+        println!(
+            "✂️  Split {} scan(s) into {} logical page(s)",
+            scans,
+            artifacts.len()
+        );
+    }
+
+    extract_headers(&mut artifacts);
+    let numbered = artifacts
+        .iter()
+        .filter(|a| a.metadata.page_number.is_some())
+        .count();
+    println!("🔢 Page numbers detected: {}/{}", numbered, artifacts.len());
+
+    // Numbering problems of an earlier run are found again below
+    for artifact in &mut artifacts {
+        artifact
+            .metadata
+            .notes
+            .retain(|note| !note.starts_with(DUPLICATE_NOTE));
+    }
+    let documents = group_documents(&artifacts);
+    let mut order = Vec::with_capacity(artifacts.len());
+    for (doc_idx, document) in documents.iter().enumerate() {
+        let page_order = order_pages(&artifacts, document);
+        println!("📄 Document {}: {} page(s)", doc_idx + 1, document.len());
+
+        if !page_order.gaps.is_empty() {
+            println!("   ⚠️  Missing pages: {:?}", page_order.gaps);
+        }
+        for &number in &page_order.duplicates {
+            println!("   ⚠️  Duplicate page number: {}", number);
+            for &idx in &page_order.order {
+                let metadata = &mut artifacts[idx].metadata;
+                if metadata.page_number == Some(number) {
+                    metadata.notes.push(format!("{}{}", DUPLICATE_NOTE, number));
+                }
+            }
+        }
+
+        order.extend(page_order.order);
+    }
+
+    let mut high_level = build_documents(&artifacts, &documents);
+
+    let cards = scan_set::load_cards(scan_set_path)?;
+    let decks = build_object_decks(&cards);
+    for deck in &decks {
+        println!(
+            "🃏 Object deck: {} ({} cards)",
+            deck.name,
+            deck.object_cards.len()
+        );
+    }
+
+    for artifact in &high_level {
+        if let HighLevelArtifact::SourceListing(listing) = artifact {
+            println!(
+                "📜 Source listing: {} [{}] ({} lines, {} inferred)",
+                listing.name.as_deref().unwrap_or("unnamed"),
+                listing.language,
+                listing.lines.len(),
+                listing.lines.iter().filter(|l| l.inferred).count()
+            );
+
+            let rules = RuleSet {
+                keypunch: Some(manifest.keypunch.for_document(listing.name.as_deref())),
+                ..rules.clone()
+            };
+            let text = listing
+                .lines
+                .iter()
+                .map(|l| l.text.as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            let mut issues = rules.check_keypunch(&text);
+            // Undefined statement numbers usually mean a misread digit
+            if listing.language == Language::Fortran.as_str() {
+                issues.extend(rules.check_statement_labels(&text));
+            }
+            // Object words must match the scanned deck of the same program
+            issues.extend(rules.check_against_decks(&text, &decks));
+            for issue in issues {
+                println!(
+                    "   ⚠️  Line {}: {}{}",
+                    issue.line_number,
+                    issue.description,
+                    issue
+                        .suggestion
+                        .map(|s| format!(" ({})", s))
+                        .unwrap_or_default()
+                );
+            }
+        }
+    }
+    high_level.extend(decks.into_iter().map(HighLevelArtifact::ObjectDeck));
+    scan_set::save_high_level(scan_set_path, &high_level)?;
+
+    let mut slots: Vec<_> = artifacts.into_iter().map(Some).collect();
+    let ordered: Vec<_> = order.iter().filter_map(|&idx| slots[idx].take()).collect();
+    scan_set::save_artifacts(scan_set_path, &ordered)?;
+
+    println!("✅ Reconstruction complete!");
+    println!("   Documents: {}", documents.len());
+    println!(
+        "   High-level artifacts: {}",
+        scan_set_path.join(scan_set::HIGH_LEVEL_FILE).display()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,71 @@
+//! `relabel` command: fix the classification of many artifacts at once
+
+use anyhow::{Context, Result};
+use core_pipeline::filter::ArtifactFilter;
+use core_pipeline::types::ArtifactKind;
+use std::path::Path;
+
+/// Length of the ID prefix shown per artifact
+const SHORT_ID_LEN: usize = 8;
+
+/// Parse a classification given on the command line
+pub(crate) fn parse_kind(name: &str) -> Result<ArtifactKind> {
+    ArtifactKind::parse(name).with_context(|| {
+        let kinds: Vec<String> = ArtifactKind::ALL
+            .iter()
+            .map(|k| format!("{:?}", k))
+            .collect();
+        format!(
+            "Unknown classification: {} (use {})",
+            name,
+            kinds.join(", ")
+        )
+    })
+}
+
+/// Relabel the artifacts of a scan set, listing each change; with
+/// `dry_run` only the list is printed
+pub fn relabel_scan_set(
+    scan_set_dir: &str,
+    from: Option<&str>,
+    to: &str,
+    filters: &[String],
+    dry_run: bool,
+) -> Result<()> {
+    let from = from.map(parse_kind).transpose()?;
+    let to = parse_kind(to)?;
+    let filter = ArtifactFilter::parse(filters)?;
+    println!(
+        "🏷️  Relabeling {} to {:?}: {}",
+        from.map_or("any kind".to_string(), |kind| format!("{:?}", kind)),
+        to,
+        scan_set_dir
+    );
+    for condition in filters {
+        println!("   Filter: {}", condition);
+    }
+
+    let changes = scan3data::relabel_scan_set(Path::new(scan_set_dir), from, to, &filter, dry_run)?;
+    for change in &changes {
+        println!(
+            "   {}  {}  {:?} -> {:?} ({} chars)",
+            &change.artifact_id[..SHORT_ID_LEN],
+            change.image.display(),
+            change.from,
+            to,
+            change.text_len
+        );
+    }
+
+    if changes.is_empty() {
+        println!("✅ No artifacts match");
+    } else if dry_run {
+        println!(
+            "🔍 Would relabel {} artifact(s) (run without --dry-run to apply)",
+            changes.len()
+        );
+    } else {
+        println!("✅ Relabeled {} artifact(s)", changes.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+//! `score` command: accuracy against reference transcripts
+
+use anyhow::Result;
+use core_pipeline::score::{ScoreReport, ScoreStage, REFERENCE_DIR};
+use std::path::Path;
+
+/// Score a scan set against its reference transcripts
+///
+/// Writes the report as JSON if an output file is given, and shows the
+/// change in accuracy since a baseline report if one is given.
+pub fn score_scan_set(
+    scan_set_dir: &str,
+    output_file: Option<&str>,
+    baseline_file: Option<&str>,
+) -> Result<()> {
+    println!("🎯 Scoring scan set: {}", scan_set_dir);
+
+    let report = ScoreReport::score(Path::new(scan_set_dir))?;
+    let baseline = baseline_file
+        .map(|path| ScoreReport::load(Path::new(path)))
+        .transpose()?;
+
+    if report.artifacts.is_empty() {
+        println!(
+            "⚠️  No reference transcripts found in {}",
+            Path::new(scan_set_dir).join(REFERENCE_DIR).display()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "   Scored: {} artifact(s) ({} without reference)",
+        report.artifacts.len(),
+        report.without_reference
+    );
+    println!("📊 Accuracy by stage:");
+    let mut previous: Option<(f64, f64)> = None;
+    for stage in ScoreStage::ALL {
+        let Some(accuracy) = report.totals.get(&stage) else {
+            println!("   {:<6} (no text available)", stage.as_str());
+            continue;
+        };
+        let (chars, lines) = (accuracy.char_accuracy(), accuracy.line_accuracy());
+        print!(
+            "   {:<6} chars {:6.2}%  lines {:6.2}%",
+            stage.as_str(),
+            chars * 100.0,
+            lines * 100.0
+        );
+        if let Some((previous_chars, previous_lines)) = previous {
+            print!(
+                "  (stage {:+.2} / {:+.2})",
+                (chars - previous_chars) * 100.0,
+                (lines - previous_lines) * 100.0
+            );
+        }
+        if let Some((delta_chars, delta_lines)) = baseline
+            .as_ref()
+            .and_then(|baseline| report.delta(baseline, stage))
+        {
+            print!(
+                "  (baseline {:+.2} / {:+.2})",
+                delta_chars * 100.0,
+                delta_lines * 100.0
+            );
+        }
+        println!();
+        previous = Some((chars, lines));
+    }
+
+    if let Some(output_file) = output_file {
+        report.write(Path::new(output_file))?;
+        println!("✅ Report: {}", output_file);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,117 @@
+//! `serve` command: the web UI, alone or with the API
+//!
+//! SPA mode serves the built Yew frontend (`trunk build`, copied to
+//! `dist/` by `scripts/build-wasm.sh`); all processing happens in the
+//! browser. API mode runs the `scan3data-server` router in-process, with
+//! the API reading the given scan set. Either way, paths that are not
+//! files get `index.html`, so client-side routes survive a reload or a
+//! shared link.
+
+use anyhow::{bail, Context, Result};
+use axum::Router;
+use scan3data::Config;
+use scan3data_server::{frontend, router, ServerOptions, DEFAULT_DATA_DIR};
+use std::path::{Path, PathBuf};
+
+/// Serve the frontend in `dist` on `host:port` until interrupted
+pub async fn serve_spa(dist: &Path, host: &str, port: u16) -> Result<()> {
+    if !dist.join("index.html").is_file() {
+        bail!(
+            "No built frontend in {} (run scripts/build-wasm.sh or pass --dist)",
+            dist.display()
+        );
+    }
+    println!("🌐 Serving {}", dist.display());
+    listen(frontend(dist), host, port).await
+}
+
+/// Serve the API for a scan set, and the frontend in `dist` if it is
+/// built, on `host:port` until interrupted
+///
+/// Scan sets created through the API go to the configured data
+/// directory.
+pub async fn serve_api(
+    config: Config,
+    scan_set: Option<&str>,
+    dist: PathBuf,
+    host: &str,
+    port: u16,
+) -> Result<()> {
+    if let Some(dir) = scan_set {
+        // Fail now rather than on the first request
+        core_pipeline::scan_set::load_manifest(Path::new(dir))?;
+        println!("🗂️  Serving API for scan set {}", dir);
+    } else {
+        println!("🗂️  Serving API without a scan set (pass --scan-set)");
+    }
+    if !dist.join("index.html").is_file() {
+        println!("   No built frontend in {}; API only", dist.display());
+    }
+    let data_dir = config
+        .server
+        .data_dir
+        .clone()
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+    println!("   New scan sets go to {}", data_dir.display());
+    if config.api_keys().is_empty() {
+        println!("   No API keys configured; the API is open (set server.api_keys)");
+    } else {
+        println!("   API requests need a key");
+    }
+    let database = config.server.database.clone();
+    if let Some(database) = &database {
+        println!("   Keeping server state in {}", database.display());
+    }
+    let app = router(ServerOptions {
+        config,
+        scan_set: scan_set.map(PathBuf::from),
+        dist,
+        data_dir,
+        database,
+    })?;
+    listen(app, host, port).await
+}
+
+async fn listen(app: Router, host: &str, port: u16) -> Result<()> {
+    let addr = format!("{}:{}", host, port);
+    let listener = tokio::net::TcpListener::bind(&addr)
+        .await
+        .with_context(|| format!("Failed to listen on {}", addr))?;
+    println!("   Listening on http://{} (Ctrl+C to stop)", addr);
+    axum::serve(listener, app).await.context("Server failed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::{Request, StatusCode};
+    use tower::ServiceExt;
+
+    async fn get(app: &Router, uri: &str) -> (StatusCode, String) {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_spa_falls_back_to_index() {
+        let dist = tempfile::tempdir().unwrap();
+        let err = serve_spa(dist.path(), "127.0.0.1", 0).await.unwrap_err();
+        assert!(err.to_string().contains("No built frontend"));
+
+        std::fs::write(dist.path().join("index.html"), "<html>app</html>").unwrap();
+        std::fs::write(dist.path().join("app.js"), "run()").unwrap();
+        let app = frontend(dist.path());
+        assert_eq!(get(&app, "/app.js").await, (StatusCode::OK, "run()".into()));
+        // Client-side routes get the app, to route in the browser
+        for route in ["/", "/scan-sets/3/artifacts"] {
+            assert_eq!(
+                get(&app, route).await,
+                (StatusCode::OK, "<html>app</html>".into())
+            );
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! `stats` command: scan set analytics
+
+use anyhow::Result;
+use core_pipeline::reconstruct::{number_ranges, DocumentNumbering};
+use core_pipeline::stats::{ScanSetStats, CONFIDENCE_BUCKETS};
+use std::path::Path;
+
+/// Width of the longest confidence histogram bar
+const BAR_WIDTH: usize = 40;
+
+/// Report statistics of a scan set, as text or as JSON
+pub fn stats_scan_set(scan_set_dir: &str, json: bool) -> Result<()> {
+    let stats = ScanSetStats::of_scan_set(Path::new(scan_set_dir))?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+        return Ok(());
+    }
+
+    println!("📈 Scan set: {}", scan_set_dir);
+    println!("   Artifacts: {}", stats.artifacts);
+
+    println!("🏷️  Classification:");
+    let mut kinds: Vec<(&String, &usize)> = stats.kinds.iter().collect();
+    kinds.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    for (kind, count) in kinds {
+        println!("   {:<16} {:>6}", kind, count);
+    }
+
+    println!("🎚️  Confidence:");
+    let max = stats.confidence.iter().copied().max().unwrap_or(0).max(1);
+    for (bucket, &count) in stats.confidence.iter().enumerate() {
+        let low = bucket as f64 / CONFIDENCE_BUCKETS as f64;
+        let high = (bucket + 1) as f64 / CONFIDENCE_BUCKETS as f64;
+        let line = format!(
+            "   {:.1}-{:.1} {:>6} {}",
+            low,
+            high,
+            count,
+            "█".repeat(count * BAR_WIDTH / max)
+        );
+        println!("{}", line.trim_end());
+    }
+
+    println!("📝 OCR:");
+    println!(
+        "   Coverage: {}/{} ({:.1}%)",
+        stats.with_text,
+        stats.artifacts,
+        stats.ocr_coverage() * 100.0
+    );
+    println!(
+        "   Average text length: {:.0} chars",
+        stats.average_text_len
+    );
+
+    let notes = stats.top_notes();
+    if !notes.is_empty() {
+        println!("🗒️  Notes:");
+        for (note, count) in notes {
+            println!("   {:>6}  {}", count, note);
+        }
+    }
+
+    let duplicates = &stats.duplicates;
+    println!("🔁 Duplicates:");
+    println!("   Files ingested: {}", duplicates.files);
+    println!("   Duplicate files: {}", duplicates.duplicate_files);
+    println!(
+        "   Artifacts from several files: {}",
+        duplicates.merged_artifacts
+    );
+    println!(
+        "   Suspected near-duplicates: {}",
+        duplicates.near_duplicates
+    );
+
+    println!("📑 Page numbering:");
+    if stats.numbering.is_empty() {
+        println!("   No page numbers detected (run `scan3data reconstruct`)");
+    }
+    for (idx, document) in stats.numbering.iter().enumerate() {
+        let name = match &document.name {
+            Some(name) => format!("listing {}", name),
+            None => format!("document {}", idx + 1),
+        };
+        if document.is_complete() {
+            println!("   ✅ {}: {} page(s), complete", name, document.pages);
+        }
+        for problem in numbering_problems(document) {
+            println!("   ⚠️  {} {}", problem, name);
+        }
+    }
+
+    Ok(())
+}
+
+/// Problems of a document's numbering, to be followed by its name:
+/// "Pages 14-16 missing from", ...
+fn numbering_problems(document: &DocumentNumbering) -> Vec<String> {
+    let mut problems = Vec::new();
+    for (first, last) in number_ranges(&document.missing) {
+        problems.push(if first == last {
+            format!("Page {} missing from", first)
+        } else {
+            format!("Pages {}-{} missing from", first, last)
+        });
+    }
+    for number in &document.duplicates {
+        problems.push(format!("Page {} scanned more than once in", number));
+    }
+    for number in &document.out_of_order {
+        problems.push(format!("Page {} scanned after a later page in", number));
+    }
+    problems
+}
@@ -0,0 +1,98 @@
+//! `validate` command: check OCR text against IBM 1130 format rules
+//!
+//! Besides the report, findings are recorded on the artifacts as
+//! processing notes, replacing those of an earlier run.
+
+use anyhow::Result;
+use core_pipeline::classify::{classify_text, Language};
+use core_pipeline::reconstruct::build_object_decks;
+use core_pipeline::scan_set;
+use core_pipeline::types::ArtifactKind;
+use core_pipeline::validate::{record_notes, RuleSet, Severity, ValidationReport};
+use std::path::Path;
+
+/// Validate every artifact with text and write a JSON or HTML report
+///
+/// Uses the default rules unless a TOML rule-set file is given. FORTRAN
+/// statement rules apply to artifacts classified as FORTRAN; the rest get
+/// the card layout and character set checks, and object listings are
+/// compared with the scan set's object decks. With `json`, a summary of the
+/// findings is printed as JSON.
+pub fn validate_scan_set(
+    scan_set_dir: &str,
+    output_file: &str,
+    rules_file: Option<&Path>,
+    json: bool,
+) -> Result<()> {
+    let scan_set_path = Path::new(scan_set_dir);
+    let (manifest, mut artifacts) = scan_set::load(scan_set_path)?;
+
+    let mut rules = match rules_file {
+        Some(path) => RuleSet::load(path)?,
+        None => RuleSet::default(),
+    };
+    let keypunch = *rules.keypunch.get_or_insert(manifest.keypunch.model);
+    if !json {
+        println!("🔎 Validating scan set: {}", scan_set_dir);
+        if let Some(path) = rules_file {
+            println!("📏 Rule set: {} ({})", rules.name, path.display());
+        }
+        println!("⌨️  Keypunch: {}", keypunch.as_str());
+    }
+
+    // Object listings are compared against the scanned decks they match
+    let decks = build_object_decks(&scan_set::load_cards(scan_set_path)?);
+
+    let mut report = ValidationReport::new(&manifest);
+    for artifact in &mut artifacts {
+        let mut issues = Vec::new();
+        if let Some(text) = artifact.content_text.as_deref() {
+            // Statement rules only for FORTRAN, as in `analyze`
+            issues = if classify_text(text).language == Language::Fortran {
+                rules.validate_fortran(text)
+            } else {
+                rules.check_card(text)
+            };
+            if artifact.layout_label == ArtifactKind::ListingObject {
+                issues.extend(rules.check_object_listing(text));
+                issues.extend(rules.check_against_decks(text, &decks));
+                issues.sort_by_key(|issue| issue.line_number);
+            }
+        }
+        record_notes(&mut artifact.metadata, &issues);
+        report.add_artifact(artifact, issues);
+    }
+
+    report.write(Path::new(output_file))?;
+    scan_set::save_artifacts(scan_set_path, &artifacts)?;
+
+    if json {
+        let summary = serde_json::json!({
+            "report": output_file,
+            "rule_set": rules_file.map(|_| &rules.name),
+            "keypunch": keypunch.as_str(),
+            "artifacts": artifacts.len(),
+            "issues": report.issue_count(),
+            "errors": report.count_severity(Severity::Error),
+            "warnings": report.count_severity(Severity::Warning),
+            "rules": report.rule_counts(),
+        });
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+
+    println!("✅ Validation complete!");
+    println!("   Report: {}", output_file);
+    println!("   Notes recorded on {} artifact(s)", artifacts.len());
+    println!(
+        "   Issues: {} ({} errors, {} warnings)",
+        report.issue_count(),
+        report.count_severity(Severity::Error),
+        report.count_severity(Severity::Warning)
+    );
+    for (rule, count) in report.rule_counts() {
+        println!("   - {}: {}", rule, count);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! The `scan3data` binary run on a small batch of scans
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// Run the binary, failing the test if it fails
+fn scan3data(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_scan3data"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "scan3data {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    output
+}
+
+/// Two copies of one page and another page
+fn write_scans(dir: &Path) {
+    let page = |shade| image::GrayImage::from_pixel(40, 30, image::Luma([shade]));
+    page(200).save(dir.join("p1.png")).unwrap();
+    page(200).save(dir.join("p1-again.png")).unwrap();
+    page(90).save(dir.join("p2.png")).unwrap();
+}
+
+#[test]
+fn test_run_chains_all_phases() {
+    let scans = tempfile::tempdir().unwrap();
+    write_scans(scans.path());
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path().join("set");
+    let (input, output) = (scans.path().to_str().unwrap(), out.to_str().unwrap());
+
+    let stdout = String::from_utf8(scan3data(&["run", "-i", input, "-o", output]).stdout).unwrap();
+    for phase in ["Phase 1/3", "Phase 2/3", "Phase 3/3"] {
+        assert!(stdout.contains(phase), "{phase} missing from:\n{stdout}");
+    }
+    // Ingested, analyzed, reconstructed and exported to the default path
+    let (manifest, artifacts) = core_pipeline::scan_set::load(&out).unwrap();
+    assert_eq!((manifest.image_count, manifest.duplicate_count), (2, 1));
+    assert!(artifacts.iter().all(|a| a.processed_image_path.is_some()));
+    assert!(out.join(core_pipeline::scan_set::HIGH_LEVEL_FILE).is_file());
+    assert!(out.join("export").join("deck.json").is_file());
+}
+
+/// Stdout of a `--json` run, parsed
+fn json(args: &[&str]) -> serde_json::Value {
+    let output = scan3data(&[&["--json"], args].concat());
+    serde_json::from_slice(&output.stdout).unwrap_or_else(|e| {
+        panic!(
+            "scan3data --json {:?} printed more than JSON ({e}):\n{}",
+            args,
+            String::from_utf8_lossy(&output.stdout)
+        )
+    })
+}
+
+#[test]
+fn test_json_output() {
+    let scans = tempfile::tempdir().unwrap();
+    write_scans(scans.path());
+    let out = tempfile::tempdir().unwrap();
+    let set = out.path().join("set");
+    let report = out.path().join("report.json");
+    let (input, set, report) = (
+        scans.path().to_str().unwrap(),
+        set.to_str().unwrap(),
+        report.to_str().unwrap(),
+    );
+
+    let manifest = json(&["ingest", "-i", input, "-o", set]);
+    assert_eq!(manifest["image_count"], 2);
+    assert_eq!(manifest["duplicate_count"], 1);
+
+    let analysis = json(&["analyze", "-s", set]);
+    assert_eq!(analysis["artifacts"], 2);
+    assert_eq!(analysis["resumed"], 0);
+
+    let validation = json(&["validate", "-s", set, "-o", report]);
+    assert_eq!(validation["report"], report);
+    assert_eq!(validation["artifacts"], 2);
+
+    let stats = json(&["stats", "-s", set]);
+    assert!(stats.is_object());
+
+    // Commands without JSON output refuse the flag
+    let refused = Command::new(env!("CARGO_BIN_EXE_scan3data"))
+        .args(["--json", "reconstruct", "-s", set])
+        .output()
+        .unwrap();
+    assert!(!refused.status.success());
+    assert!(String::from_utf8_lossy(&refused.stderr).contains("--json is supported by"));
+}
+
+#[test]
+fn test_reconstruct_twice_keeps_one_duplicate_note() {
+    let scans = tempfile::tempdir().unwrap();
+    write_scans(scans.path());
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path().join("set");
+    let (input, output) = (scans.path().to_str().unwrap(), out.to_str().unwrap());
+    scan3data(&["ingest", "-i", input, "-o", output]);
+
+    // Both pages claim to be page 3
+    let mut artifacts = core_pipeline::scan_set::load_artifacts(&out).unwrap();
+    for artifact in &mut artifacts {
+        artifact.content_text = Some("PAGE 3\n      X = 1\n      Y = 2".to_string());
+    }
+    core_pipeline::scan_set::save_artifacts(&out, &artifacts).unwrap();
+
+    for _ in 0..2 {
+        scan3data(&["reconstruct", "-s", output]);
+    }
+    for artifact in core_pipeline::scan_set::load_artifacts(&out).unwrap() {
+        let notes = &artifact.metadata.notes;
+        assert_eq!(notes, &["Duplicate page number 3"], "{notes:?}");
+    }
+}
+
+#[test]
+fn test_reconstruct_applies_rules_file() {
+    let scans = tempfile::tempdir().unwrap();
+    write_scans(scans.path());
+    let out = tempfile::tempdir().unwrap();
+    let out = out.path().join("set");
+    let (input, output) = (scans.path().to_str().unwrap(), out.to_str().unwrap());
+    scan3data(&["ingest", "-i", input, "-o", output]);
+
+    // '~' cannot be punched on an 029
+    let mut artifacts = core_pipeline::scan_set::load_artifacts(&out).unwrap();
+    for artifact in &mut artifacts {
+        artifact.content_text = Some("      X = 1\n      Y = 2 ~".to_string());
+    }
+    core_pipeline::scan_set::save_artifacts(&out, &artifacts).unwrap();
+
+    let reconstruct = |args: &[&str]| {
+        let stdout = scan3data(&[&["reconstruct", "-s", output], args].concat()).stdout;
+        String::from_utf8(stdout).unwrap()
+    };
+    let stdout = reconstruct(&[]);
+    assert!(stdout.contains("cannot be punched"), "{stdout}");
+
+    let rules = out.join("rules.toml");
+    std::fs::write(&rules, "disabled = [\"keypunch\"]\n").unwrap();
+    let stdout = reconstruct(&["--rules", rules.to_str().unwrap()]);
+    assert!(!stdout.contains("cannot be punched"), "{stdout}");
+}
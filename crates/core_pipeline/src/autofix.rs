@@ -0,0 +1,428 @@
+//! Auto-fix engine for common OCR confusions
+//!
+//! Tesseract routinely confuses visually similar characters on 1130
+//! printouts (O/0, I/1, S/5, B/8) and misreads the `DC` opcode as `Be` or
+//! `oc`. Corrections are proposed word by word, using the word's context:
+//! - Hex fields (location and object words of assembler listings, read
+//!   from their fixed columns): letters become hex digits
+//! - Numeric words: letters become digits
+//! - Alphabetic words: digits become letters when that yields a known keyword
+//! - Opcode position: `Be`/`oc` become `DC`
+//!
+//! Every correction replaces text of the same length, so column layout is
+//! never disturbed. Corrections at or above a confidence threshold are
+//! applied and recorded as revisions; the rest are returned as suggestions.
+
+use crate::classify::{Classification, Language};
+use crate::types::{ArtifactKind, TextRevision};
+use crate::validate::fortran::KEYWORDS;
+use crate::validate::object::listing_fields;
+
+/// Default confidence a correction needs to be applied automatically
+pub const DEFAULT_THRESHOLD: f32 = 0.9;
+
+/// IBM 1130 assembler mnemonics and pseudo-ops
+pub(crate) const MNEMONICS: &[&str] = &[
+    "A", "ABS", "AD", "AND", "B", "BC", "BES", "BN", "BNN", "BNP", "BNZ", "BO", "BOD", "BP", "BSC",
+    "BSI", "BSS", "BZ", "CALL", "D", "DC", "DEC", "DN", "DSA", "DUMP", "EBC", "EJCT", "END", "ENT",
+    "EOR", "EPR", "EQU", "EXIT", "HDNG", "ILS", "ISS", "LD", "LDD", "LDS", "LDX", "LIBF", "LIBR",
+    "LINK", "LIST", "M", "MDX", "NOP", "OR", "ORG", "RTE", "S", "SD", "SKP", "SLA", "SLC", "SLCA",
+    "SLT", "SPAC", "SPR", "SRA", "SRT", "STD", "STO", "STS", "STX", "WAIT", "XIO",
+];
+
+/// A proposed correction to a single word
+#[derive(Debug, Clone, PartialEq)]
+pub struct Correction {
+    /// Line number (1-based)
+    pub line_number: usize,
+    /// Column where the word starts (1-based)
+    pub column: usize,
+    /// Word as read by OCR
+    pub before: String,
+    /// Proposed replacement (always the same length)
+    pub after: String,
+    /// Why the correction is proposed
+    pub reason: String,
+    /// Confidence in the correction (0.0-1.0)
+    pub confidence: f32,
+}
+
+impl Correction {
+    /// Convert an applied correction into a revision record
+    pub fn into_revision(self) -> TextRevision {
+        TextRevision {
+            source: "autofix".to_string(),
+            line_number: self.line_number,
+            column: self.column,
+            before: self.before,
+            after: self.after,
+            reason: self.reason,
+            confidence: self.confidence,
+        }
+    }
+}
+
+/// Result of running the auto-fix engine over a text
+#[derive(Debug, Clone)]
+pub struct AutofixResult {
+    /// Text with confident corrections applied
+    pub text: String,
+    /// Corrections that were applied
+    pub revisions: Vec<TextRevision>,
+    /// Corrections below the threshold, left for review
+    pub suggestions: Vec<Correction>,
+}
+
+/// Propose corrections for every line of a text
+///
+/// Hex fields are only looked for in assembler and object texts, so
+/// FORTRAN such as `CALL LOAD` keeps its letters.
+pub fn propose_corrections(text: &str, classification: &Classification) -> Vec<Correction> {
+    let hex_fields = classification.language == Language::Assembler
+        || matches!(
+            classification.kind,
+            ArtifactKind::CardObject | ArtifactKind::ListingObject
+        );
+    text.split('\n')
+        .enumerate()
+        .flat_map(|(idx, line)| propose_line(idx + 1, line, hex_fields))
+        .collect()
+}
+
+/// Apply corrections at or above `threshold`, recording each as a revision
+pub fn autofix(text: &str, classification: &Classification, threshold: f32) -> AutofixResult {
+    let (applied, suggestions): (Vec<_>, Vec<_>) = propose_corrections(text, classification)
+        .into_iter()
+        .partition(|c| c.confidence >= threshold);
+
+    let fixed_lines: Vec<String> = text
+        .split('\n')
+        .enumerate()
+        .map(|(idx, line)| {
+            let mut chars: Vec<char> = line.chars().collect();
+            for correction in applied.iter().filter(|c| c.line_number == idx + 1) {
+                let start = correction.column - 1;
+                for (offset, c) in correction.after.chars().enumerate() {
+                    chars[start + offset] = c;
+                }
+            }
+            chars.into_iter().collect()
+        })
+        .collect();
+
+    AutofixResult {
+        text: fixed_lines.join("\n"),
+        revisions: applied.into_iter().map(Correction::into_revision).collect(),
+        suggestions,
+    }
+}
+
+/// Propose corrections for a single line
+fn propose_line(line_number: usize, line: &str, listing: bool) -> Vec<Correction> {
+    let words = split_words(line);
+
+    // Object listing lines: location in column 1, then up to two object
+    // words, read from their columns
+    let mut hex_fields = Vec::new();
+    if let Some(fields) = listing.then(|| listing_fields(line)).flatten() {
+        let fields: Vec<usize> = fields
+            .iter()
+            .map_while(|(column, field)| is_hex_candidate(field).then_some(*column))
+            .collect();
+        // The location alone is not enough to call the line a listing line
+        if fields.len() > 1 {
+            hex_fields = words
+                .iter()
+                .enumerate()
+                .filter(|(_, (column, _))| fields.contains(column))
+                .map(|(pos, _)| pos)
+                .collect();
+        }
+    }
+    let opcode_position = hex_fields.last().map(|last| last + 1);
+    let is_format = line.contains("FORMAT");
+
+    words
+        .iter()
+        .enumerate()
+        .filter_map(|(pos, (column, word))| {
+            let fix = if hex_fields.contains(&pos) {
+                fix_hex(word)
+            } else if let Some(fix) = fix_opcode(word, opcode_position == Some(pos)) {
+                Some(fix)
+            } else if is_format {
+                None
+            } else {
+                fix_numeric(word).or_else(|| fix_alphabetic(word))
+            };
+            fix.map(|(after, reason, confidence)| Correction {
+                line_number,
+                column: column + 1,
+                before: word.clone(),
+                after,
+                reason,
+                confidence,
+            })
+        })
+        .collect()
+}
+
+/// Split a line into alphanumeric words with their starting character index
+fn split_words(line: &str) -> Vec<(usize, String)> {
+    let mut words = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+
+    for (idx, c) in line.chars().enumerate() {
+        if c.is_ascii_alphanumeric() || c == '|' {
+            current
+                .get_or_insert_with(|| (idx, String::new()))
+                .1
+                .push(c);
+        } else if let Some(word) = current.take() {
+            words.push(word);
+        }
+    }
+    words.extend(current);
+    words
+}
+
+/// Digit a letter is commonly misread for, with the confidence of the swap
+fn letter_as_digit(c: char) -> Option<(char, f32)> {
+    match c {
+        'O' | 'Q' => Some(('0', 0.95)),
+        'I' | 'L' | 'l' | '|' => Some(('1', 0.95)),
+        'S' => Some(('5', 0.9)),
+        'G' => Some(('6', 0.9)),
+        'Z' => Some(('2', 0.85)),
+        'B' => Some(('8', 0.9)),
+        _ => None,
+    }
+}
+
+/// Letter a digit is commonly misread for
+fn digit_as_letter(c: char) -> Option<char> {
+    match c {
+        '0' => Some('O'),
+        '1' => Some('I'),
+        '2' => Some('Z'),
+        '5' => Some('S'),
+        '6' => Some('G'),
+        '8' => Some('B'),
+        _ => None,
+    }
+}
+
+/// Check whether a word could be a 4-digit hex field after correction
+///
+/// Mnemonics and keywords (`LIBF`, `CALL`) are words, not misread hex.
+fn is_hex_candidate(word: &str) -> bool {
+    word.chars().count() == 4
+        && !MNEMONICS.contains(&word)
+        && !KEYWORDS.contains(&word)
+        && word
+            .chars()
+            .all(|c| c.is_ascii_hexdigit() || (c != 'B' && letter_as_digit(c).is_some()))
+}
+
+/// Replace letters that cannot appear in a hex field
+///
+/// `B` is a valid hex digit, so B/8 confusions cannot be resolved here.
+fn fix_hex(word: &str) -> Option<(String, String, f32)> {
+    let mut confidence = 1.0f32;
+    let fixed: String = word
+        .chars()
+        .map(|c| match letter_as_digit(c) {
+            Some((digit, conf)) if !c.is_ascii_hexdigit() => {
+                confidence = confidence.min(conf);
+                digit
+            }
+            _ => c,
+        })
+        .collect();
+    (fixed != word).then(|| (fixed, "Letter in hex field".to_string(), confidence))
+}
+
+/// Replace letters in a word that is clearly a number
+///
+/// A word is numeric when it starts with a digit or has more digits than
+/// letters, and every letter is a known digit look-alike.
+fn fix_numeric(word: &str) -> Option<(String, String, f32)> {
+    let digits = word.chars().filter(|c| c.is_ascii_digit()).count();
+    let letters = word.chars().count() - digits;
+    let starts_with_digit = word.starts_with(|c: char| c.is_ascii_digit());
+    if letters == 0 || digits == 0 || !(starts_with_digit || digits > letters) {
+        return None;
+    }
+
+    let mut confidence = 1.0f32;
+    let mut fixed = String::new();
+    for c in word.chars() {
+        if c.is_ascii_digit() {
+            fixed.push(c);
+        } else {
+            let (digit, conf) = letter_as_digit(c)?;
+            confidence = confidence.min(conf);
+            fixed.push(digit);
+        }
+    }
+    Some((fixed, "Letter in numeric field".to_string(), confidence))
+}
+
+/// Replace digits in a word that becomes a known keyword or mnemonic
+fn fix_alphabetic(word: &str) -> Option<(String, String, f32)> {
+    let digits = word.chars().filter(|c| c.is_ascii_digit()).count();
+    let letters = word.chars().count() - digits;
+    if digits == 0 || letters <= digits {
+        return None;
+    }
+
+    let fixed: String = word
+        .chars()
+        .map(|c| digit_as_letter(c).unwrap_or(c))
+        .collect();
+    let known = KEYWORDS.contains(&fixed.as_str()) || MNEMONICS.contains(&fixed.as_str());
+    known.then(|| (fixed, "Digit in keyword".to_string(), 0.95))
+}
+
+/// Recognize `DC` misread as `Be`, `oc` and similar
+///
+/// Lowercase never appears on 1130 printouts, so lowercase variants are
+/// fixed anywhere; uppercase variants only in the opcode position of an
+/// object listing line.
+fn fix_opcode(word: &str, at_opcode_position: bool) -> Option<(String, String, f32)> {
+    const MISREADS: &[&str] = &["Be", "be", "bE", "BE", "oc", "Oc", "OC", "0C", "0c", "Dc"];
+    if !MISREADS.contains(&word) {
+        return None;
+    }
+    let has_lowercase = word.chars().any(|c| c.is_ascii_lowercase());
+    let confidence = match (has_lowercase, at_opcode_position) {
+        (true, _) => 0.95,
+        (false, true) => 0.9,
+        (false, false) => return None,
+    };
+    Some((
+        "DC".to_string(),
+        "DC opcode misread".to_string(),
+        confidence,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OBJECT_LISTING: Classification = Classification {
+        language: Language::Assembler,
+        kind: ArtifactKind::ListingObject,
+        confidence: 1.0,
+    };
+
+    const FORTRAN: Classification = Classification {
+        language: Language::Fortran,
+        kind: ArtifactKind::ListingSource,
+        confidence: 1.0,
+    };
+
+    fn fixes(text: &str) -> Vec<(String, String)> {
+        propose_corrections(text, &OBJECT_LISTING)
+            .into_iter()
+            .map(|c| (c.before, c.after))
+            .collect()
+    }
+
+    #[test]
+    fn test_hex_fields_in_object_listing() {
+        let text = "OBFO 0 OO78    Be      123";
+        assert_eq!(
+            fixes(text),
+            vec![
+                ("OBFO".to_string(), "0BF0".to_string()),
+                ("OO78".to_string(), "0078".to_string()),
+                ("Be".to_string(), "DC".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_label_is_not_treated_as_hex_field() {
+        assert!(fixes("LOAD  LD   L X").is_empty());
+    }
+
+    #[test]
+    fn test_hex_fields_need_listing_layout_and_kind() {
+        for classification in [OBJECT_LISTING, FORTRAN] {
+            let result = autofix("      CALL LOAD", &classification, DEFAULT_THRESHOLD);
+            assert_eq!(result.text, "      CALL LOAD");
+            assert!(result.suggestions.is_empty());
+        }
+        assert!(propose_corrections("OBFO 0 OO78", &FORTRAN).is_empty());
+    }
+
+    #[test]
+    fn test_mnemonic_after_one_word_is_not_hex() {
+        for line in ["0102 0 4000      LIBF FLOAT", "0104 0 4000      CALL FILE"] {
+            let result = autofix(line, &OBJECT_LISTING, DEFAULT_THRESHOLD);
+            assert_eq!(result.text, line);
+            assert!(result.revisions.is_empty());
+        }
+        // Only the object columns hold hex fields
+        assert_eq!(
+            fixes("0102 0 4OOO      LIBF FLOAT"),
+            vec![("4OOO".to_string(), "4000".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_numeric_word() {
+        assert_eq!(
+            fixes("   1O CONTINUE"),
+            vec![("1O".to_string(), "10".to_string())]
+        );
+        assert_eq!(
+            fixes("      GOTO 2S"),
+            vec![("2S".to_string(), "25".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_variable_names_left_alone() {
+        assert!(fixes("      X1 = ITEM2 + A").is_empty());
+    }
+
+    #[test]
+    fn test_alphabetic_keyword() {
+        assert_eq!(
+            fixes("      C0NTINUE"),
+            vec![("C0NTINUE".to_string(), "CONTINUE".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_format_statements_skipped() {
+        assert!(fixes("   20 FORMAT (2I5, 1OX)").is_empty());
+    }
+
+    #[test]
+    fn test_uppercase_opcode_only_in_opcode_position() {
+        assert_eq!(
+            fixes("0100 0 0078 OC 123"),
+            vec![("OC".to_string(), "DC".to_string())]
+        );
+        assert!(fixes("* OC IS NOT AN OPCODE HERE").is_empty());
+    }
+
+    #[test]
+    fn test_autofix_applies_above_threshold_and_preserves_columns() {
+        let text = "OBFO 0 OO78    Be      123\n   1Z CONTINUE\n";
+        let result = autofix(text, &OBJECT_LISTING, DEFAULT_THRESHOLD);
+
+        assert_eq!(result.text, "0BF0 0 0078    DC      123\n   1Z CONTINUE\n");
+        assert_eq!(result.revisions.len(), 3);
+        assert_eq!(result.revisions[2].column, 16);
+        assert_eq!(result.revisions[2].source, "autofix");
+
+        // Z -> 2 is below the default threshold
+        assert_eq!(result.suggestions.len(), 1);
+        assert_eq!(result.suggestions[0].after, "12");
+    }
+}
@@ -0,0 +1,335 @@
+//! Punched card images of exported decks
+//!
+//! Draws each card of a deck as it came out of the keypunch: card stock
+//! with the corner cut, the digits 0-9 printed in the rows of every column,
+//! the interpretation printed along the top edge and the Hollerith holes at
+//! the positions [`crate::keypunch`] reads them from scans. Laying the
+//! images of a recovered deck beside the scans checks it by eye, and they
+//! serve as illustrations of the deck.
+//!
+//! Decks are read in the formats `export` writes them in: plain text
+//! (`text`, one card per line), card deck JSON (`card_deck`) and binary
+//! (`simh`, `.dck`). Text is punched on the given keypunch model; the
+//! interpretation is what that model prints for the holes, so a binary
+//! deck shows the characters its keypunch would have printed and columns
+//! the model cannot print are left unprinted.
+//!
+//! Characters are drawn in a built-in 5x7 pixel font scaled to the card
+//! width; below about 500 pixels wide nothing is printed.
+
+use crate::error::{Error, IoContext, ParseContext, Result};
+use crate::export::simh::read_binary_deck;
+use crate::export::CARD_COLUMNS;
+use crate::keypunch::{
+    card_holes, hole_rect, ColumnHoles, KeypunchModel, CARD_HEIGHT, CARD_WIDTH, COLUMN_PITCH,
+    FIRST_COLUMN_X, FIRST_ROW_Y, ROWS, ROW_PITCH,
+};
+use crate::types::EmulatorOutput;
+use image::{Rgb, RgbImage};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// Card width in pixels unless another is asked for (200 dpi)
+pub const DEFAULT_CARD_WIDTH: u32 = 1475;
+
+/// Colors of the card stock, the cut corner, the printing and the holes
+const STOCK: Rgb<u8> = Rgb([240, 226, 180]);
+const CUT_CORNER: Rgb<u8> = Rgb([255, 255, 255]);
+const ROW_DIGITS: Rgb<u8> = Rgb([150, 110, 70]);
+const INTERPRETATION: Rgb<u8> = Rgb([30, 30, 30]);
+const HOLE: Rgb<u8> = Rgb([0, 0, 0]);
+
+/// Size of the corner cut, in inches
+const CORNER_CUT: f32 = 0.25;
+
+/// Center of the interpretation line below the top edge, in inches
+const PRINT_LINE_Y: f32 = 0.11;
+
+/// Font cell width: 5 pixel glyphs and a blank pixel between them
+const GLYPH_WIDTH: u32 = 5;
+const GLYPH_HEIGHT: u32 = 7;
+const CELL_WIDTH: u32 = GLYPH_WIDTH + 1;
+
+/// 5x7 glyphs of every character a keypunch model prints, one row per
+/// byte with the leftmost pixel in bit 4
+const GLYPHS: &[(char, [u8; 7])] = &[
+    ('A', [0x0E, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
+    ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
+    ('D', [0x1C, 0x12, 0x11, 0x11, 0x11, 0x12, 0x1C]),
+    ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
+    ('F', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10]),
+    ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0F]),
+    ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
+    ('I', [0x0E, 0x04, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('J', [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C]),
+    ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
+    ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
+    ('M', [0x11, 0x1B, 0x15, 0x15, 0x11, 0x11, 0x11]),
+    ('N', [0x11, 0x11, 0x19, 0x15, 0x13, 0x11, 0x11]),
+    ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
+    ('Q', [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D]),
+    ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
+    ('S', [0x0F, 0x10, 0x10, 0x0E, 0x01, 0x01, 0x1E]),
+    ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
+    ('V', [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04]),
+    ('W', [0x11, 0x11, 0x11, 0x15, 0x15, 0x15, 0x0A]),
+    ('X', [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11]),
+    ('Y', [0x11, 0x11, 0x11, 0x0A, 0x04, 0x04, 0x04]),
+    ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
+    ('0', [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E]),
+    ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
+    ('2', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x08, 0x1F]),
+    ('3', [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E]),
+    ('4', [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02]),
+    ('5', [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E]),
+    ('6', [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E]),
+    ('7', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08]),
+    ('8', [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E]),
+    ('9', [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C]),
+    ('+', [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00]),
+    ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
+    ('*', [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00]),
+    ('/', [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00]),
+    ('=', [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00]),
+    ('(', [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02]),
+    (')', [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08]),
+    ('.', [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C]),
+    (',', [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08]),
+    (';', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08]),
+    (':', [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x0C, 0x00]),
+    ('$', [0x04, 0x0F, 0x14, 0x0E, 0x05, 0x1E, 0x04]),
+    ('#', [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A]),
+    ('@', [0x0E, 0x11, 0x17, 0x15, 0x17, 0x10, 0x0F]),
+    ('\'', [0x0C, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00]),
+    ('&', [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D]),
+    ('|', [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
+    ('_', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F]),
+    ('<', [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02]),
+    ('>', [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08]),
+    ('?', [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04]),
+    ('!', [0x04, 0x04, 0x04, 0x04, 0x04, 0x00, 0x04]),
+    ('"', [0x0A, 0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00]),
+    ('%', [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03]),
+    ('¤', [0x00, 0x11, 0x0E, 0x0A, 0x0E, 0x11, 0x00]),
+];
+
+/// Card deck JSON: one `card_deck` output or an array of them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum DeckJson {
+    One(EmulatorOutput),
+    Many(Vec<EmulatorOutput>),
+}
+
+/// Hole patterns of the cards of an exported deck
+///
+/// The format is taken from the extension: `.dck` is a binary deck,
+/// `.json` card deck JSON (the cards of every deck in an array, in order)
+/// and anything else a text deck. Text is punched on `model`.
+pub fn read_deck(path: &Path, model: KeypunchModel) -> Result<Vec<[ColumnHoles; CARD_COLUMNS]>> {
+    let bytes = fs::read(path).io_context(|| format!("Failed to read deck: {}", path.display()))?;
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+
+    if extension.eq_ignore_ascii_case("dck") {
+        return read_binary_deck(&bytes);
+    }
+    let cards: Vec<String> = if extension.eq_ignore_ascii_case("json") {
+        let outputs = match serde_json::from_slice(&bytes)
+            .parse_context(|| format!("Failed to parse card deck: {}", path.display()))?
+        {
+            DeckJson::One(output) => vec![output],
+            DeckJson::Many(outputs) => outputs,
+        };
+        let mut cards = Vec::new();
+        for output in outputs {
+            match output {
+                EmulatorOutput::CardDeck { cards: deck, .. } => {
+                    cards.extend(deck.into_iter().map(|card| card.text))
+                }
+                EmulatorOutput::Listing { .. } => {
+                    return Err(Error::invalid(format!(
+                        "{} is a listing, not a card deck",
+                        path.display()
+                    )))
+                }
+            }
+        }
+        cards
+    } else {
+        let text = String::from_utf8(bytes).map_err(|_| {
+            Error::invalid(format!(
+                "{} is not a text deck (binary decks need the .dck extension)",
+                path.display()
+            ))
+        })?;
+        text.lines().map(str::to_string).collect()
+    };
+    Ok(cards.iter().map(|card| card_holes(card, model)).collect())
+}
+
+/// Draw a punched card of `width` pixels: the holes, the interpretation
+/// `model` prints for them and the row digits
+pub fn render_punched_card(
+    holes: &[ColumnHoles; CARD_COLUMNS],
+    model: KeypunchModel,
+    width: u32,
+) -> RgbImage {
+    let height = (width as f32 * CARD_HEIGHT / CARD_WIDTH).round() as u32;
+    let scale = width as f32 / CARD_WIDTH;
+    let cut = (CORNER_CUT * scale) as u32;
+    let mut image = RgbImage::from_fn(
+        width,
+        height,
+        |x, y| {
+            if x + y < cut {
+                CUT_CORNER
+            } else {
+                STOCK
+            }
+        },
+    );
+
+    // Glyphs as large as fit in a column, row digits half that size
+    let print_scale = (COLUMN_PITCH * scale) as u32 / CELL_WIDTH;
+    if print_scale > 0 {
+        let digit_scale = (print_scale / 2).max(1);
+        for (column, &pattern) in holes.iter().enumerate() {
+            let x = (FIRST_COLUMN_X + column as f32 * COLUMN_PITCH) * scale;
+            for (row, &digit) in ROWS.iter().enumerate().skip(2) {
+                let y = (FIRST_ROW_Y + row as f32 * ROW_PITCH) * scale;
+                let digit = char::from(b'0' + digit);
+                draw_glyph(&mut image, digit, x, y, digit_scale, ROW_DIGITS);
+            }
+            if let Some(c) = model.decode(pattern) {
+                let y = PRINT_LINE_Y * scale;
+                draw_glyph(&mut image, c, x, y, print_scale, INTERPRETATION);
+            }
+        }
+    }
+
+    for (column, &pattern) in holes.iter().enumerate() {
+        for row in (0..ROWS.len()).filter(|row| pattern & 1 << row != 0) {
+            let (x, y, w, h) = hole_rect(width, height, column, row);
+            for py in y..y + h {
+                for px in x..x + w {
+                    image.put_pixel(px, py, HOLE);
+                }
+            }
+        }
+    }
+    image
+}
+
+/// Draw a character centered on a point; characters without a glyph
+/// (blanks) draw nothing
+fn draw_glyph(image: &mut RgbImage, c: char, x: f32, y: f32, scale: u32, color: Rgb<u8>) {
+    let Some((_, glyph)) = GLYPHS.iter().find(|(glyph, _)| *glyph == c) else {
+        return;
+    };
+    let left = (x - (GLYPH_WIDTH * scale) as f32 / 2.0).max(0.0) as u32;
+    let top = (y - (GLYPH_HEIGHT * scale) as f32 / 2.0).max(0.0) as u32;
+    for (gy, bits) in glyph.iter().enumerate() {
+        for gx in (0..GLYPH_WIDTH).filter(|gx| bits & 0x10 >> gx != 0) {
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    let px = left + gx * scale + dx;
+                    let py = top + gy as u32 * scale + dy;
+                    if px < image.width() && py < image.height() {
+                        image.put_pixel(px, py, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::simh::binary_deck;
+    use crate::keypunch::detect_holes;
+    use image::DynamicImage;
+    use tempfile::TempDir;
+
+    const IBM029: KeypunchModel = KeypunchModel::Ibm029;
+
+    #[test]
+    fn test_glyphs_cover_charsets() {
+        for model in KeypunchModel::ALL {
+            for c in model.charset().chars().filter(|&c| c != ' ') {
+                assert!(GLYPHS.iter().any(|(glyph, _)| *glyph == c), "{:?}", c);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rendered_holes_read_back() {
+        // The printing must not be taken for holes
+        let holes = card_holes(
+            "      CALL EXIT(1) = 'A/B', X.LT.Y $* #@ 0123456789",
+            IBM029,
+        );
+        let card = render_punched_card(&holes, IBM029, DEFAULT_CARD_WIDTH);
+        assert_eq!(card.width(), DEFAULT_CARD_WIDTH);
+        assert_eq!(
+            detect_holes(&DynamicImage::ImageRgb8(card).to_luma8()),
+            holes
+        );
+
+        // Small cards are left unprinted
+        let blank = render_punched_card(&[0; CARD_COLUMNS], IBM029, 300);
+        assert!(blank
+            .pixels()
+            .all(|&pixel| pixel == STOCK || pixel == CUT_CORNER));
+    }
+
+    #[test]
+    fn test_interpretation_printed() {
+        let print_line = |card: &RgbImage| {
+            let y = (PRINT_LINE_Y * DEFAULT_CARD_WIDTH as f32 / CARD_WIDTH) as u32;
+            (0..card.width())
+                .filter(|&x| *card.get_pixel(x, y) == INTERPRETATION)
+                .count()
+        };
+        let blank = render_punched_card(&[0; CARD_COLUMNS], IBM029, DEFAULT_CARD_WIDTH);
+        assert_eq!(print_line(&blank), 0);
+        let card = render_punched_card(&card_holes("HELLO", IBM029), IBM029, DEFAULT_CARD_WIDTH);
+        assert!(print_line(&card) > 0);
+    }
+
+    #[test]
+    fn test_read_deck_formats() {
+        let dir = TempDir::new().unwrap();
+        let cards = ["// JOB", "      CALL EXIT"];
+        let expected: Vec<_> = cards.iter().map(|card| card_holes(card, IBM029)).collect();
+
+        let text = dir.path().join("deck.txt");
+        fs::write(&text, "// JOB\n      CALL EXIT\n").unwrap();
+        assert_eq!(read_deck(&text, IBM029).unwrap(), expected);
+
+        let json = dir.path().join("deck.json");
+        fs::write(
+            &json,
+            r#"{"type": "card_deck", "machine": "IBM1130", "cards": [
+                {"seq": 1, "text": "// JOB"}, {"seq": 2, "text": "      CALL EXIT"}]}"#,
+        )
+        .unwrap();
+        assert_eq!(read_deck(&json, IBM029).unwrap(), expected);
+
+        let binary = dir.path().join("deck.dck");
+        fs::write(&binary, binary_deck(cards, IBM029)).unwrap();
+        assert_eq!(read_deck(&binary, IBM029).unwrap(), expected);
+
+        let listing = dir.path().join("listing.json");
+        fs::write(
+            &listing,
+            r#"{"type": "listing", "language": "fortran", "lines": []}"#,
+        )
+        .unwrap();
+        assert!(read_deck(&listing, IBM029).is_err());
+    }
+}
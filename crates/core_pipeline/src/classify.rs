@@ -0,0 +1,251 @@
+//! Heuristic (non-LLM) classification
+//!
+//! Detects the language of OCR'd text from keywords and column layout:
+//! - FORTRAN: statements in columns 7-72 starting with a keyword or assignment
+//! - Assembler: 1130 mnemonics near the start of the line, or object listing
+//!   lines with a hex location in columns 1-4
+//! - Forth: colon definitions (`: NAME ... ;`) and common stack words
+//! - Data: lines made up of numbers only
+//!
+//! Fast enough to run on every artifact, this is the default classification
+//! and a cross-check for LLM classification.
+
+use crate::autofix::MNEMONICS;
+use crate::types::ArtifactKind;
+use crate::validate::fortran::is_statement;
+use crate::validate::object::listing_object_words;
+use serde::{Deserialize, Serialize};
+
+/// Common Forth words
+const FORTH_WORDS: &[&str] = &[
+    "DUP", "DROP", "SWAP", "OVER", "ROT", "@", "!", "+!", "C@", "C!",
+];
+
+/// Share of lines with a hex location needed for an object listing
+const OBJECT_LISTING_SHARE: f32 = 0.3;
+
+/// Source language of an artifact
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// IBM 1130 assembler
+    Assembler,
+    /// IBM 1130 FORTRAN
+    Fortran,
+    /// Forth
+    Forth,
+    /// Numeric data
+    Data,
+    /// Could not be determined
+    Unknown,
+}
+
+impl Language {
+    /// Lowercase identifier (e.g. "fortran")
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Language::Assembler => "assembler",
+            Language::Fortran => "fortran",
+            Language::Forth => "forth",
+            Language::Data => "data",
+            Language::Unknown => "unknown",
+        }
+    }
+
+    /// Parse a language name, ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        [
+            Language::Assembler,
+            Language::Fortran,
+            Language::Forth,
+            Language::Data,
+            Language::Unknown,
+        ]
+        .into_iter()
+        .find(|language| language.as_str().eq_ignore_ascii_case(name.trim()))
+    }
+}
+
+/// Result of heuristic classification
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Classification {
+    /// Detected language
+    pub language: Language,
+    /// Artifact kind implied by the language and layout
+    pub kind: ArtifactKind,
+    /// Share of lines supporting the classification (0.0-1.0)
+    pub confidence: f32,
+}
+
+/// Detect the language of a text
+///
+/// Monitor control records (`// FOR`, `// ASM`) decide directly; otherwise
+/// the language whose patterns match the most lines wins. Returns the
+/// language and the share of non-blank lines that matched it.
+pub fn detect_language(text: &str) -> (Language, f32) {
+    for line in text.lines() {
+        let line = line.trim_start();
+        if line.starts_with("// FOR") {
+            return (Language::Fortran, 1.0);
+        }
+        if line.starts_with("// ASM") {
+            return (Language::Assembler, 1.0);
+        }
+    }
+
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).collect();
+    let count = |matches: fn(&str) -> bool| lines.iter().filter(|l| matches(l)).count();
+    let scores = [
+        (Language::Fortran, count(is_statement)),
+        (Language::Assembler, count(is_assembler)),
+        (Language::Forth, count(is_forth)),
+        (Language::Data, count(is_data)),
+    ];
+
+    match scores
+        .iter()
+        .filter(|(_, score)| *score > 0)
+        .max_by_key(|(_, score)| *score)
+    {
+        Some(&(language, score)) => (language, score as f32 / lines.len() as f32),
+        None => (Language::Unknown, 0.0),
+    }
+}
+
+/// Classify a page's text into a language and artifact kind
+pub fn classify_text(text: &str) -> Classification {
+    let (language, confidence) = detect_language(text);
+    let kind = match language {
+        Language::Assembler if is_object_listing(text) => ArtifactKind::ListingObject,
+        Language::Assembler | Language::Fortran | Language::Forth => ArtifactKind::ListingSource,
+        Language::Data => ArtifactKind::CardData,
+        Language::Unknown => ArtifactKind::Unknown,
+    };
+    Classification {
+        language,
+        kind,
+        confidence,
+    }
+}
+
+/// Compare the heuristic language against an LLM-reported one
+///
+/// Returns `None` when either side is unknown (nothing to compare),
+/// otherwise whether the two agree.
+pub fn cross_check(heuristic: Language, llm_language: &str) -> Option<bool> {
+    let llm = Language::parse(llm_language)?;
+    if heuristic == Language::Unknown || llm == Language::Unknown {
+        return None;
+    }
+    Some(heuristic == llm)
+}
+
+/// Check whether a line contains an assembler mnemonic near its start
+fn is_assembler(line: &str) -> bool {
+    // Comment: `*` in column 21, with a blank label and operation field
+    let comment = line
+        .char_indices()
+        .nth(20)
+        .is_some_and(|(at, c)| c == '*' && line[..at].trim().is_empty());
+    comment
+        || line
+            .split_whitespace()
+            .take(4)
+            .any(|token| token.len() >= 2 && MNEMONICS.contains(&token))
+}
+
+/// Check whether a line looks like Forth
+fn is_forth(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.starts_with(": ")
+        || trimmed.ends_with(" ;")
+        || trimmed.split_whitespace().any(|t| FORTH_WORDS.contains(&t))
+}
+
+/// Check whether a line holds only numbers
+fn is_data(line: &str) -> bool {
+    line.chars().any(|c| c.is_ascii_digit())
+        && line
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, ' ' | '.' | ',' | '+' | '-' | 'E'))
+}
+
+/// Check whether enough lines start with a hex location to be an object listing
+fn is_object_listing(text: &str) -> bool {
+    let lines = text.lines().filter(|l| !l.trim().is_empty()).count();
+    let locations = listing_object_words(text)
+        .values()
+        .map(|word| word.line_number)
+        .collect::<std::collections::BTreeSet<_>>()
+        .len();
+    lines > 0 && locations as f32 / lines as f32 >= OBJECT_LISTING_SHARE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        let detect = |text| detect_language(text).0;
+        assert_eq!(detect("// JOB\n// FOR\n      X = 1"), Language::Fortran);
+        assert_eq!(detect("      X = 1\n   10 CONTINUE"), Language::Fortran);
+        assert_eq!(
+            detect("0100 0 C400      START LD   L X\n0102 0 D401            STO  L Y"),
+            Language::Assembler
+        );
+        assert_eq!(detect(": SQUARE DUP * ;"), Language::Forth);
+        assert_eq!(detect("  12.5   -3.0\n  1,000  42"), Language::Data);
+        assert_eq!(detect("HELLO WORLD"), Language::Unknown);
+    }
+
+    #[test]
+    fn test_assembler_comment_after_multibyte_chars() {
+        // Column 21 is past the 20th character, not the 20th byte
+        let comment = format!("{}* NOTE", "\u{a0}".repeat(20));
+        assert!(is_assembler(&comment));
+        assert!(!is_assembler(&format!("{}* NOTE", "é".repeat(20))));
+        // Byte 20 falls inside a character here
+        assert!(!is_assembler(&format!("a{}* NOTE", "é".repeat(19))));
+    }
+
+    #[test]
+    fn test_confidence_is_share_of_matching_lines() {
+        let (language, confidence) = detect_language("      X = 1\n      Y = 2\nGARBAGE ~~\n%%%");
+        assert_eq!(language, Language::Fortran);
+        assert_eq!(confidence, 0.5);
+    }
+
+    #[test]
+    fn test_classify_object_listing() {
+        let text = "0100 0 C400      START LD   L X\n0102 0 D401            STO  L Y";
+        assert_eq!(classify_text(text).kind, ArtifactKind::ListingObject);
+
+        let source = "START LD   L X\n      STO  L Y";
+        let classification = classify_text(source);
+        assert_eq!(classification.language, Language::Assembler);
+        assert_eq!(classification.kind, ArtifactKind::ListingSource);
+    }
+
+    #[test]
+    fn test_classify_data_and_unknown() {
+        assert_eq!(classify_text("1 2 3\n4 5 6").kind, ArtifactKind::CardData);
+        assert_eq!(classify_text("").kind, ArtifactKind::Unknown);
+    }
+
+    #[test]
+    fn test_language_parse() {
+        assert_eq!(Language::parse("FORTRAN"), Some(Language::Fortran));
+        assert_eq!(Language::parse(" Forth "), Some(Language::Forth));
+        assert_eq!(Language::parse("cobol"), None);
+    }
+
+    #[test]
+    fn test_cross_check() {
+        assert_eq!(cross_check(Language::Fortran, "FORTRAN"), Some(true));
+        assert_eq!(cross_check(Language::Fortran, "assembler"), Some(false));
+        assert_eq!(cross_check(Language::Fortran, "unknown"), None);
+        assert_eq!(cross_check(Language::Unknown, "forth"), None);
+        assert_eq!(cross_check(Language::Data, "cobol"), None);
+    }
+}
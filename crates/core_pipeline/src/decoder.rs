@@ -5,30 +5,66 @@
 //! - Compressed label column decoding
 //! - Address field extraction
 //! - Binary data extraction
+//!
+//! Card image layout (80 bytes):
+//! - Bytes 0-71: 36 big-endian 16-bit words
+//!   - Word 1: Load address
+//!   - Word 2: Checksum
+//!   - Word 3: Card type (high byte) and data word count (low byte)
+//!   - Words 4-36: Data words
+//! - Bytes 72-79: Identification (columns 73-76 deck name, 77-80 sequence)
 
+use crate::error::{Error, Result};
 use crate::types::{ObjectCard, ObjectCardType};
-use anyhow::Result;
+
+/// Number of bytes holding words (columns 1-72)
+const WORD_BYTES: usize = 72;
+
+/// Maximum number of data words on one card
+pub const MAX_DATA_WORDS: usize = WORD_BYTES / 2 - 3;
 
 /// Decode an 80-byte object card
 pub fn decode_object_card(data: &[u8]) -> Result<ObjectCard> {
     if data.len() != 80 {
-        anyhow::bail!("Object card must be exactly 80 bytes");
+        return Err(Error::invalid("Object card must be exactly 80 bytes"));
     }
 
-    // TODO: Implement IBM 1130 object card format
-    // - Parse card type indicator
-    // - Extract address fields
-    // - Decode compressed labels
-    // - Extract binary data
+    let word = |idx: usize| u16::from_be_bytes([data[idx * 2], data[idx * 2 + 1]]);
+    let address = word(0);
+    let [type_code, count] = word(2).to_be_bytes();
+
+    let card_type = match type_code {
+        0x01..=0x07 => ObjectCardType::Header,
+        0x0A => ObjectCardType::Text,
+        0x0F => ObjectCardType::End,
+        _ => ObjectCardType::Other,
+    };
+
+    // TODO: Decode compressed labels on subroutine header cards
 
+    let data_words = usize::from(count).min(MAX_DATA_WORDS);
     Ok(ObjectCard {
-        card_type: ObjectCardType::Other,
-        address: None,
-        data: data.to_vec(),
+        card_type,
+        address: (card_type != ObjectCardType::Other).then_some(address),
+        data: data[6..6 + data_words * 2].to_vec(),
         symbols: Vec::new(),
     })
 }
 
+/// Read the identification field (columns 73-80) of an object card
+///
+/// Returns the deck name (columns 73-76) and sequence number (77-80),
+/// each `None` if blank or not printable.
+pub fn card_identification(data: &[u8]) -> (Option<String>, Option<String>) {
+    let field = |range: std::ops::Range<usize>| {
+        let bytes = data.get(range)?;
+        let text: String = bytes.iter().map(|&b| char::from(b)).collect();
+        let text = text.trim();
+        (!text.is_empty() && text.chars().all(|c| c.is_ascii_graphic())).then(|| text.to_string())
+    };
+    (field(72..76), field(76..80))
+}
+
 /// Disassemble IBM 1130 machine code
 pub fn disassemble_1130(_data: &[u8], start_address: u16) -> Result<Vec<String>> {
     // TODO: Implement IBM 1130 disassembler
@@ -60,6 +96,45 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    fn card(type_code: u8, address: u16, words: &[u16], id: &str) -> Vec<u8> {
+        let mut data = vec![0u8; 80];
+        data[0..2].copy_from_slice(&address.to_be_bytes());
+        data[4] = type_code;
+        data[5] = words.len() as u8;
+        for (idx, word) in words.iter().enumerate() {
+            data[6 + idx * 2..8 + idx * 2].copy_from_slice(&word.to_be_bytes());
+        }
+        data[72..72 + id.len()].copy_from_slice(id.as_bytes());
+        data
+    }
+
+    #[test]
+    fn test_decode_text_card() {
+        let data = card(0x0A, 0x0100, &[0xC400, 0x0010], "PROG0002");
+        let decoded = decode_object_card(&data).unwrap();
+        assert_eq!(decoded.card_type, ObjectCardType::Text);
+        assert_eq!(decoded.address, Some(0x0100));
+        assert_eq!(decoded.data, vec![0xC4, 0x00, 0x00, 0x10]);
+    }
+
+    #[test]
+    fn test_decode_card_types() {
+        let decode = |t| decode_object_card(&card(t, 0, &[], "")).unwrap().card_type;
+        assert_eq!(decode(0x01), ObjectCardType::Header);
+        assert_eq!(decode(0x0F), ObjectCardType::End);
+        assert_eq!(decode(0x00), ObjectCardType::Other);
+    }
+
+    #[test]
+    fn test_card_identification() {
+        let data = card(0x0A, 0, &[], "PROG0002");
+        assert_eq!(
+            card_identification(&data),
+            (Some("PROG".to_string()), Some("0002".to_string()))
+        );
+        assert_eq!(card_identification(&[0u8; 80]), (None, None));
+    }
+
     #[test]
     fn test_disassemble_basic() {
         let code = vec![0x00, 0x00, 0x01, 0x00];
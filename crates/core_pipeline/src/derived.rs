@@ -0,0 +1,288 @@
+//! Content-addressed storage for derived images
+//!
+//! Every image derived from a scan (preprocessed, cleaned, thumbnail,
+//! debug overlay) is stored under a path built from the source image hash,
+//! the stage that produced it and a hash of the stage parameters:
+//!
+//! ```text
+//! derived/<source hash>/<stage>-<params hash>.png
+//! ```
+//!
+//! Variants never collide, re-running a stage with the same parameters
+//! finds the existing image, and images no artifact refers to any more can
+//! be trimmed safely.
+
+use crate::error::{Error, IoContext, Result};
+use crate::stage_cache::{hash_json, HASH_PREFIX_LEN};
+use crate::types::PageArtifact;
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Derived image directory within a scan set directory
+pub const DERIVED_DIR: &str = "derived";
+
+/// Identity of a derived image
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DerivedKey {
+    /// Content hash of the source image
+    pub source_hash: String,
+    /// Stage that produced the image (e.g. "preprocess", "thumbnail")
+    pub stage: String,
+    /// Hash of the stage parameters
+    pub params_hash: String,
+}
+
+impl DerivedKey {
+    /// Build a key, hashing the stage parameters' JSON form
+    pub fn new(source_hash: &str, stage: &str, params: &impl Serialize) -> Result<Self> {
+        if source_hash.len() < HASH_PREFIX_LEN {
+            return Err(Error::invalid(format!(
+                "Source hash too short for derived image: '{}'",
+                source_hash
+            )));
+        }
+        if stage.is_empty() || !stage.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(Error::invalid(format!(
+                "Invalid stage name for derived image: '{}'",
+                stage
+            )));
+        }
+        Ok(Self {
+            source_hash: source_hash.to_string(),
+            stage: stage.to_string(),
+            params_hash: hash_json(params)?[..HASH_PREFIX_LEN].to_string(),
+        })
+    }
+
+    /// Unique identifier, usable as the input of a downstream stage
+    pub fn id(&self) -> String {
+        format!("{}-{}-{}", self.source_hash, self.stage, self.params_hash)
+    }
+
+    /// Path relative to the scan set directory
+    pub fn relative_path(&self) -> PathBuf {
+        PathBuf::from(DERIVED_DIR)
+            .join(&self.source_hash[..HASH_PREFIX_LEN])
+            .join(format!("{}-{}.png", self.stage, self.params_hash))
+    }
+}
+
+/// Reference from an artifact to one of its derived images
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivedImageRef {
+    /// Stage that produced the image
+    pub stage: String,
+    /// Hash of the stage parameters
+    pub params_hash: String,
+    /// Path relative to the scan set directory
+    pub path: PathBuf,
+}
+
+/// Derived image store of a scan set
+#[derive(Debug, Clone)]
+pub struct DerivedStore {
+    scan_set_dir: PathBuf,
+}
+
+impl DerivedStore {
+    /// Open the store of a scan set directory
+    pub fn new(scan_set_dir: &Path) -> Self {
+        Self {
+            scan_set_dir: scan_set_dir.to_path_buf(),
+        }
+    }
+
+    /// Absolute path of a derived image
+    pub fn path(&self, key: &DerivedKey) -> PathBuf {
+        self.scan_set_dir.join(key.relative_path())
+    }
+
+    /// Check whether a derived image already exists
+    pub fn contains(&self, key: &DerivedKey) -> bool {
+        self.path(key).exists()
+    }
+
+    /// Load a derived image, if it exists
+    pub fn load(&self, key: &DerivedKey) -> Result<Option<DynamicImage>> {
+        let path = self.path(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let image = image::open(&path).map_err(|source| Error::ImageLoad {
+            path: path.clone(),
+            source,
+        })?;
+        Ok(Some(image))
+    }
+
+    /// Store a derived image, returning the reference to record
+    ///
+    /// Written to a temporary file first, so an interrupted run never
+    /// leaves a truncated image under the final name.
+    pub fn store(&self, key: &DerivedKey, image: &DynamicImage) -> Result<DerivedImageRef> {
+        let path = self.path(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .io_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let tmp = path.with_extension("png.tmp");
+        image
+            .save_with_format(&tmp, image::ImageFormat::Png)
+            .and_then(|_| fs::rename(&tmp, &path).map_err(image::ImageError::IoError))
+            .map_err(|source| Error::ImageEncode {
+                message: format!("Failed to write derived image: {}", path.display()),
+                source,
+            })?;
+        Ok(Self::reference(key))
+    }
+
+    /// Reference for a key (whether or not the image exists)
+    pub fn reference(key: &DerivedKey) -> DerivedImageRef {
+        DerivedImageRef {
+            stage: key.stage.clone(),
+            params_hash: key.params_hash.clone(),
+            path: key.relative_path(),
+        }
+    }
+
+    /// Delete derived images that no artifact refers to
+    ///
+    /// With `stage`, that stage's images are deleted and their references
+    /// removed from the artifacts as well. Returns the number of files
+    /// deleted.
+    pub fn trim(&self, artifacts: &mut [PageArtifact], stage: Option<&str>) -> Result<usize> {
+        if let Some(stage) = stage {
+            for artifact in artifacts.iter_mut() {
+                artifact
+                    .metadata
+                    .derived_images
+                    .retain(|r| r.stage != stage);
+            }
+        }
+        let referenced: HashSet<PathBuf> = artifacts
+            .iter()
+            .flat_map(|a| a.metadata.derived_images.iter())
+            .map(|r| self.scan_set_dir.join(&r.path))
+            .collect();
+
+        let root = self.scan_set_dir.join(DERIVED_DIR);
+        if !root.exists() {
+            return Ok(0);
+        }
+        let mut removed = 0;
+        let read_error = || format!("Failed to read {}", root.display());
+        for source_dir in fs::read_dir(&root).io_context(read_error)? {
+            let source_dir = source_dir.io_context(read_error)?.path();
+            if !source_dir.is_dir() {
+                continue;
+            }
+            let read_error = || format!("Failed to read {}", source_dir.display());
+            for file in fs::read_dir(&source_dir).io_context(read_error)? {
+                let file = file.io_context(read_error)?.path();
+                if !referenced.contains(&file) {
+                    fs::remove_file(&file)
+                        .io_context(|| format!("Failed to remove {}", file.display()))?;
+                    removed += 1;
+                }
+            }
+            if fs::read_dir(&source_dir)
+                .io_context(read_error)?
+                .next()
+                .is_none()
+            {
+                fs::remove_dir(&source_dir)
+                    .io_context(|| format!("Failed to remove {}", source_dir.display()))?;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// Record a derived image on an artifact, replacing one from the same stage
+pub fn record_derived(artifact: &mut PageArtifact, reference: DerivedImageRef) {
+    let derived = &mut artifact.metadata.derived_images;
+    derived.retain(|r| r.stage != reference.stage);
+    derived.push(reference);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageId, PageMetadata, ScanSetId};
+    use image::{GrayImage, Luma};
+
+    const HASH: &str = "0123456789abcdef0123456789abcdef";
+
+    fn artifact() -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::new(),
+            processed_image_path: None,
+            layout_label: ArtifactKind::Unknown,
+            content_text: None,
+            metadata: PageMetadata::default(),
+        }
+    }
+
+    fn image() -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_pixel(4, 4, Luma([128])))
+    }
+
+    #[test]
+    fn test_keys_separate_stages_and_params() {
+        let preprocess = DerivedKey::new(HASH, "preprocess", &1).unwrap();
+        let thumbnail = DerivedKey::new(HASH, "thumbnail", &1).unwrap();
+        let larger = DerivedKey::new(HASH, "thumbnail", &2).unwrap();
+        assert_ne!(preprocess.relative_path(), thumbnail.relative_path());
+        assert_ne!(thumbnail.relative_path(), larger.relative_path());
+        assert!(preprocess
+            .relative_path()
+            .starts_with("derived/0123456789abcdef"));
+        assert!(DerivedKey::new("abc", "preprocess", &1).is_err());
+        assert!(DerivedKey::new(HASH, "../x", &1).is_err());
+    }
+
+    #[test]
+    fn test_store_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DerivedStore::new(dir.path());
+        let key = DerivedKey::new(HASH, "preprocess", &1).unwrap();
+        assert!(store.load(&key).unwrap().is_none());
+
+        let reference = store.store(&key, &image()).unwrap();
+        assert!(store.contains(&key));
+        assert_eq!(reference.path, key.relative_path());
+        assert_eq!(store.load(&key).unwrap().unwrap().width(), 4);
+    }
+
+    #[test]
+    fn test_trim_unreferenced_and_stage() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = DerivedStore::new(dir.path());
+        let kept = DerivedKey::new(HASH, "preprocess", &1).unwrap();
+        let stale = DerivedKey::new(HASH, "preprocess", &2).unwrap();
+        let thumbnail = DerivedKey::new(HASH, "thumbnail", &1).unwrap();
+
+        let mut artifacts = vec![artifact()];
+        record_derived(&mut artifacts[0], store.store(&stale, &image()).unwrap());
+        // Same stage replaces the earlier reference
+        record_derived(&mut artifacts[0], store.store(&kept, &image()).unwrap());
+        record_derived(
+            &mut artifacts[0],
+            store.store(&thumbnail, &image()).unwrap(),
+        );
+        assert_eq!(artifacts[0].metadata.derived_images.len(), 2);
+
+        assert_eq!(store.trim(&mut artifacts, None).unwrap(), 1);
+        assert!(!store.contains(&stale));
+        assert!(store.contains(&kept));
+
+        assert_eq!(store.trim(&mut artifacts, Some("thumbnail")).unwrap(), 1);
+        assert!(!store.contains(&thumbnail));
+        assert_eq!(artifacts[0].metadata.derived_images.len(), 1);
+    }
+}
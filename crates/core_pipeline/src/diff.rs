@@ -0,0 +1,252 @@
+//! Comparison of two analyses of the same scans
+//!
+//! Artifacts of two scan sets are matched by image content hash, so a
+//! scan set re-ingested and analyzed with another vision model lines up
+//! with the original even though artifact ids and image names differ.
+//! For every matched artifact the classification and a line diff of the
+//! text are reported.
+
+use crate::error::Result;
+use crate::reconstruct::stitch::edit_distance;
+use crate::scan_set;
+use crate::score::normalized_lines;
+use crate::types::{ArtifactKind, PageArtifact};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// A line of a text diff
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffLine {
+    /// Line in both texts
+    Same(String),
+    /// Line only in the first text
+    Removed(String),
+    /// Line only in the second text
+    Added(String),
+}
+
+/// Differences of one artifact between the two scan sets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactDiff {
+    /// Image content hash both artifacts share
+    pub content_hash: String,
+    /// Original filenames (from the first scan set)
+    pub original_filenames: Vec<String>,
+    /// Classification in each scan set
+    pub kind: (ArtifactKind, ArtifactKind),
+    /// Classification confidence in each scan set
+    pub confidence: (f32, f32),
+    /// Character insertions, deletions and substitutions between the texts
+    pub char_changes: usize,
+    /// Line diff of the texts (empty if they are the same)
+    pub lines: Vec<DiffLine>,
+}
+
+impl ArtifactDiff {
+    /// Whether the classification changed
+    pub fn kind_changed(&self) -> bool {
+        self.kind.0 != self.kind.1
+    }
+
+    /// Whether the text changed
+    pub fn text_changed(&self) -> bool {
+        self.char_changes > 0
+    }
+}
+
+/// Differences between two scan sets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanSetDiff {
+    /// Name of the first scan set
+    pub a: String,
+    /// Name of the second scan set
+    pub b: String,
+    /// Artifacts found in both scan sets, in the first set's order
+    pub artifacts: Vec<ArtifactDiff>,
+    /// Content hashes of artifacts only in the first scan set
+    pub only_in_a: Vec<String>,
+    /// Content hashes of artifacts only in the second scan set
+    pub only_in_b: Vec<String>,
+}
+
+impl ScanSetDiff {
+    /// Compare two scan set directories
+    pub fn of_scan_sets(a: &Path, b: &Path) -> Result<Self> {
+        let (manifest_a, artifacts_a) = scan_set::load(a)?;
+        let (manifest_b, artifacts_b) = scan_set::load(b)?;
+        Ok(Self::compare(
+            &manifest_a.name,
+            &artifacts_a,
+            &manifest_b.name,
+            &artifacts_b,
+        ))
+    }
+
+    /// Compare the artifacts of two loaded scan sets
+    pub fn compare(
+        a: &str,
+        artifacts_a: &[PageArtifact],
+        b: &str,
+        artifacts_b: &[PageArtifact],
+    ) -> Self {
+        let by_hash: HashMap<&str, &PageArtifact> = artifacts_b
+            .iter()
+            .map(|artifact| (artifact.metadata.content_hash.as_str(), artifact))
+            .collect();
+
+        let mut diff = Self {
+            a: a.to_string(),
+            b: b.to_string(),
+            artifacts: Vec::new(),
+            only_in_a: Vec::new(),
+            only_in_b: Vec::new(),
+        };
+        for artifact_a in artifacts_a {
+            let hash = artifact_a.metadata.content_hash.as_str();
+            let Some(artifact_b) = by_hash.get(hash) else {
+                diff.only_in_a.push(hash.to_string());
+                continue;
+            };
+            let text_a = normalized_lines(artifact_a.content_text.as_deref().unwrap_or(""));
+            let text_b = normalized_lines(artifact_b.content_text.as_deref().unwrap_or(""));
+            let chars_a: Vec<char> = text_a.join("\n").chars().collect();
+            let chars_b: Vec<char> = text_b.join("\n").chars().collect();
+            let char_changes = edit_distance(&chars_a, &chars_b);
+            diff.artifacts.push(ArtifactDiff {
+                content_hash: hash.to_string(),
+                original_filenames: artifact_a.metadata.original_filenames.clone(),
+                kind: (artifact_a.layout_label, artifact_b.layout_label),
+                confidence: (
+                    artifact_a.metadata.confidence,
+                    artifact_b.metadata.confidence,
+                ),
+                char_changes,
+                lines: if char_changes > 0 {
+                    line_diff(&text_a, &text_b)
+                } else {
+                    Vec::new()
+                },
+            });
+        }
+
+        let in_a: HashSet<&str> = artifacts_a
+            .iter()
+            .map(|artifact| artifact.metadata.content_hash.as_str())
+            .collect();
+        diff.only_in_b = artifacts_b
+            .iter()
+            .map(|artifact| artifact.metadata.content_hash.as_str())
+            .filter(|hash| !in_a.contains(hash))
+            .map(str::to_string)
+            .collect();
+        diff
+    }
+
+    /// Matched artifacts whose text or classification changed
+    pub fn changed(&self) -> impl Iterator<Item = &ArtifactDiff> {
+        self.artifacts
+            .iter()
+            .filter(|artifact| artifact.text_changed() || artifact.kind_changed())
+    }
+}
+
+/// Line diff of two texts (longest common subsequence)
+pub fn line_diff(a: &[&str], b: &[&str]) -> Vec<DiffLine> {
+    // common[i][j]: length of the LCS of a[i..] and b[j..]
+    let mut common = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            common[i][j] = if a[i] == b[j] {
+                common[i + 1][j + 1] + 1
+            } else {
+                common[i + 1][j].max(common[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        if i < a.len() && j < b.len() && a[i] == b[j] {
+            lines.push(DiffLine::Same(a[i].to_string()));
+            i += 1;
+            j += 1;
+        } else if j == b.len() || (i < a.len() && common[i + 1][j] >= common[i][j + 1]) {
+            lines.push(DiffLine::Removed(a[i].to_string()));
+            i += 1;
+        } else {
+            lines.push(DiffLine::Added(b[j].to_string()));
+            j += 1;
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PageId, ScanSetId};
+    use std::path::PathBuf;
+
+    fn artifact(hash: &str, kind: ArtifactKind, text: &str) -> PageArtifact {
+        let mut artifact = PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/a.jpg"),
+            processed_image_path: None,
+            layout_label: kind,
+            content_text: Some(text.to_string()),
+            metadata: Default::default(),
+        };
+        artifact.metadata.content_hash = hash.to_string();
+        artifact
+    }
+
+    #[test]
+    fn test_line_diff() {
+        let lines = line_diff(&["A", "B", "C"], &["A", "X", "C", "D"]);
+        assert_eq!(
+            lines,
+            vec![
+                DiffLine::Same("A".to_string()),
+                DiffLine::Removed("B".to_string()),
+                DiffLine::Added("X".to_string()),
+                DiffLine::Same("C".to_string()),
+                DiffLine::Added("D".to_string()),
+            ]
+        );
+        assert!(line_diff(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_compare_matches_by_content_hash() {
+        let a = vec![
+            artifact("h1", ArtifactKind::CardText, "      X = 1\n      END"),
+            artifact("h2", ArtifactKind::Unknown, "SAME"),
+            artifact("h3", ArtifactKind::CardText, "GONE"),
+        ];
+        let b = vec![
+            artifact("h4", ArtifactKind::CardText, "NEW"),
+            artifact("h2", ArtifactKind::ListingSource, "SAME   \n\n"),
+            artifact("h1", ArtifactKind::CardText, "      X = I\n      END"),
+        ];
+
+        let diff = ScanSetDiff::compare("v1", &a, "v2", &b);
+        assert_eq!(diff.only_in_a, vec!["h3"]);
+        assert_eq!(diff.only_in_b, vec!["h4"]);
+        assert_eq!(diff.artifacts.len(), 2);
+
+        let h1 = &diff.artifacts[0];
+        assert_eq!(h1.char_changes, 1);
+        assert!(h1.text_changed() && !h1.kind_changed());
+        assert_eq!(h1.lines[1], DiffLine::Added("      X = I".to_string()));
+
+        // Trailing whitespace does not count as a change
+        let h2 = &diff.artifacts[1];
+        assert!(!h2.text_changed() && h2.kind_changed());
+        assert!(h2.lines.is_empty());
+        assert_eq!(diff.changed().count(), 2);
+    }
+}
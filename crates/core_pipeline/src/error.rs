@@ -0,0 +1,201 @@
+//! Pipeline errors
+//!
+//! Every fallible function in this crate returns [`Error`], so callers
+//! such as the server can tell failure categories apart by matching on the
+//! variant: a missing OCR engine is a setup problem, a corrupt manifest a
+//! data problem, a missing scan set a bad request. The underlying I/O,
+//! image, JSON or database error is kept as the [`source`] of the variant.
+//!
+//! [`source`]: std::error::Error::source
+
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// Result of a pipeline operation
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Failure categories of the pipeline
+#[derive(Debug, Error)]
+pub enum Error {
+    /// A file or directory could not be read, written, created or removed
+    #[error("{message}")]
+    Io {
+        message: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// An image could not be opened or decoded
+    #[error("Failed to load image: {}", path.display())]
+    ImageLoad {
+        path: PathBuf,
+        #[source]
+        source: image::ImageError,
+    },
+
+    /// An image could not be encoded or saved
+    #[error("{message}")]
+    ImageEncode {
+        message: String,
+        #[source]
+        source: image::ImageError,
+    },
+
+    /// Preprocessing could not produce an image
+    #[error("Preprocessing failed: {0}")]
+    Preprocess(String),
+
+    /// Tesseract is not installed or lacks the language data
+    #[error("Failed to initialize Tesseract. Is Tesseract installed?")]
+    OcrEngineMissing {
+        #[source]
+        source: leptess::tesseract::TessInitError,
+    },
+
+    /// Tesseract failed on an image
+    #[error("OCR failed: {0}")]
+    Ocr(String),
+
+    /// The scan set directory does not exist
+    #[error("Scan set directory does not exist: {}", .0.display())]
+    ScanSetNotFound(PathBuf),
+
+    /// A scan set's manifest is not valid JSON for a manifest
+    #[error("Failed to parse manifest: {}", path.display())]
+    ManifestParse {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// Other stored data (artifacts, cards, journal, cache entries) is not
+    /// valid JSON for its type
+    #[error("{message}")]
+    DataParse {
+        message: String,
+        #[source]
+        source: serde_json::Error,
+    },
+
+    /// Data could not be serialized as JSON
+    #[error("Failed to serialize JSON")]
+    Serialize(#[from] serde_json::Error),
+
+    /// Stored data was written by a newer version with a schema this
+    /// version does not know
+    #[error(
+        "{} has schema version {found}, newer than the supported version {supported}",
+        path.display()
+    )]
+    SchemaVersion {
+        path: PathBuf,
+        found: u32,
+        supported: u32,
+    },
+
+    /// A validation rule set is not valid
+    #[error("{message}")]
+    RuleSet {
+        message: String,
+        #[source]
+        source: Box<toml::de::Error>,
+    },
+
+    /// A hooks file is not valid
+    #[error("{message}")]
+    HookConfig {
+        message: String,
+        #[source]
+        source: Box<toml::de::Error>,
+    },
+
+    /// An external hook command exited with a failure status
+    #[error("{0}")]
+    Hook(String),
+
+    /// A database operation failed
+    #[cfg(feature = "sqlite")]
+    #[error("{message}")]
+    Database {
+        message: String,
+        #[source]
+        source: rusqlite::Error,
+    },
+
+    /// An argument or stored value the operation cannot work with
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl Error {
+    /// Error for an argument or stored value that cannot be used
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::Invalid(message.into())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl From<rusqlite::Error> for Error {
+    fn from(source: rusqlite::Error) -> Self {
+        Self::Database {
+            message: "Database operation failed".to_string(),
+            source,
+        }
+    }
+}
+
+/// Adds a message saying what failed to I/O errors, like `anyhow`'s
+/// `with_context`
+pub(crate) trait IoContext<T> {
+    fn io_context(self, message: impl FnOnce() -> String) -> Result<T>;
+}
+
+impl<T> IoContext<T> for std::io::Result<T> {
+    fn io_context(self, message: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|source| Error::Io {
+            message: message(),
+            source,
+        })
+    }
+}
+
+/// Adds a message saying what was being parsed to JSON errors
+pub(crate) trait ParseContext<T> {
+    fn parse_context(self, message: impl FnOnce() -> String) -> Result<T>;
+}
+
+impl<T> ParseContext<T> for serde_json::Result<T> {
+    fn parse_context(self, message: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|source| Error::DataParse {
+            message: message(),
+            source,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn test_io_context_keeps_source() {
+        let result: std::io::Result<()> = Err(std::io::ErrorKind::NotFound.into());
+        let err = result
+            .io_context(|| "Failed to read manifest: x/manifest.json".to_string())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Failed to read manifest: x/manifest.json");
+        assert!(matches!(err, Error::Io { .. }));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn test_converts_to_anyhow() {
+        let err: anyhow::Error = Error::ScanSetNotFound(PathBuf::from("scans")).into();
+        assert_eq!(err.to_string(), "Scan set directory does not exist: scans");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::ScanSetNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,230 @@
+//! ALTO v4 XML for one page
+//!
+//! Built from the OCR word boxes ([`OcrWord`]): words are grouped into
+//! text blocks and lines as Tesseract found them, with pixel coordinates.
+//! Pages whose word boxes are not available are written from their text
+//! alone, one `TextLine` per line without coordinates.
+
+use crate::ocr::{OcrOutput, OcrWord};
+use crate::types::{PageArtifact, PageId};
+use crate::validate::report::html_escape;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+/// ALTO v4 namespace
+pub const ALTO_NAMESPACE: &str = "http://www.loc.gov/standards/alto/ns-v4#";
+
+/// Source of a page's ALTO content
+#[derive(Debug, Clone, Copy)]
+pub enum AltoContent<'a> {
+    /// Word boxes from OCR
+    Words(&'a [OcrWord]),
+    /// Plain text, without coordinates
+    Text(&'a str),
+}
+
+/// Content of an artifact's page: its word boxes if `ocr` has them,
+/// otherwise its text
+pub(crate) fn page_content<'a>(
+    artifact: &'a PageArtifact,
+    ocr: &'a HashMap<PageId, OcrOutput>,
+) -> AltoContent<'a> {
+    match (ocr.get(&artifact.id), &artifact.content_text) {
+        (Some(output), _) if !output.words.is_empty() => AltoContent::Words(&output.words),
+        (_, Some(text)) => AltoContent::Text(text),
+        (_, None) => AltoContent::Text(""),
+    }
+}
+
+/// Render the ALTO document of one page
+///
+/// `number` is the page's position in the scan set (1-based), used in
+/// element ids; `image_file` names the page image; `size` is the image
+/// size in pixels, if known.
+pub fn alto_page(
+    number: usize,
+    image_file: &str,
+    size: Option<(u32, u32)>,
+    content: AltoContent,
+) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(xml, "<alto xmlns=\"{}\">", ALTO_NAMESPACE);
+    xml.push_str("  <Description>\n    <MeasurementUnit>pixel</MeasurementUnit>\n");
+    let _ = writeln!(
+        xml,
+        "    <sourceImageInformation><fileName>{}</fileName></sourceImageInformation>",
+        html_escape(image_file)
+    );
+    xml.push_str(
+        "    <OCRProcessing ID=\"OCR_0\"><ocrProcessingStep><processingSoftware>\
+         <softwareName>Tesseract</softwareName></processingSoftware>\
+         </ocrProcessingStep></OCRProcessing>\n  </Description>\n",
+    );
+
+    let page_size = size
+        .map(|(w, h)| format!(" WIDTH=\"{}\" HEIGHT=\"{}\"", w, h))
+        .unwrap_or_default();
+    let _ = writeln!(
+        xml,
+        "  <Layout>\n    <Page ID=\"P{0}\" PHYSICAL_IMG_NR=\"{0}\"{1}>\n      <PrintSpace>",
+        number, page_size
+    );
+    match content {
+        AltoContent::Words(words) => write_word_blocks(&mut xml, number, words),
+        AltoContent::Text(text) => write_text_block(&mut xml, number, text),
+    }
+    xml.push_str("      </PrintSpace>\n    </Page>\n  </Layout>\n</alto>\n");
+    xml
+}
+
+/// Blocks and lines with coordinates, from word boxes
+fn write_word_blocks(xml: &mut String, page: usize, words: &[OcrWord]) {
+    let mut block_no = 0;
+    let mut line_no = 0;
+    let mut start = 0;
+    while start < words.len() {
+        let first = &words[start];
+        if start == 0 || first.block != words[start - 1].block {
+            if start > 0 {
+                xml.push_str("        </TextBlock>\n");
+            }
+            block_no += 1;
+            let block = words[start..].iter().take_while(|w| w.block == first.block);
+            let _ = writeln!(
+                xml,
+                "        <TextBlock ID=\"P{}_B{}\"{}>",
+                page,
+                block_no,
+                position(block)
+            );
+        }
+        let len = words[start..]
+            .iter()
+            .take_while(|w| {
+                (w.block, w.paragraph, w.line) == (first.block, first.paragraph, first.line)
+            })
+            .count();
+        let line = &words[start..start + len];
+        line_no += 1;
+        let _ = writeln!(
+            xml,
+            "          <TextLine ID=\"P{}_L{}\"{}>",
+            page,
+            line_no,
+            position(line.iter())
+        );
+        for (idx, word) in line.iter().enumerate() {
+            if idx > 0 {
+                xml.push_str("            <SP/>\n");
+            }
+            let _ = writeln!(
+                xml,
+                "            <String CONTENT=\"{}\" WC=\"{:.2}\"{}/>",
+                html_escape(&word.text),
+                (word.confidence / 100.0).clamp(0.0, 1.0),
+                position(std::iter::once(word))
+            );
+        }
+        xml.push_str("          </TextLine>\n");
+        start += len;
+    }
+    if !words.is_empty() {
+        xml.push_str("        </TextBlock>\n");
+    }
+}
+
+/// A single block of lines without coordinates, from plain text
+fn write_text_block(xml: &mut String, page: usize, text: &str) {
+    let _ = writeln!(xml, "        <TextBlock ID=\"P{}_B1\">", page);
+    for (idx, line) in text.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+        let _ = writeln!(xml, "          <TextLine ID=\"P{}_L{}\">", page, idx + 1);
+        for (n, token) in line.split_whitespace().enumerate() {
+            if n > 0 {
+                xml.push_str("            <SP/>\n");
+            }
+            let _ = writeln!(
+                xml,
+                "            <String CONTENT=\"{}\"/>",
+                html_escape(token)
+            );
+        }
+        xml.push_str("          </TextLine>\n");
+    }
+    xml.push_str("        </TextBlock>\n");
+}
+
+/// HPOS/VPOS/WIDTH/HEIGHT attributes of the box enclosing some words
+fn position<'a>(words: impl Iterator<Item = &'a OcrWord>) -> String {
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for w in words {
+        let (right, bottom) = (w.left + w.width, w.top + w.height);
+        bounds = Some(match bounds {
+            None => (w.left, w.top, right, bottom),
+            Some((l, t, r, b)) => (l.min(w.left), t.min(w.top), r.max(right), b.max(bottom)),
+        });
+    }
+    match bounds {
+        Some((l, t, r, b)) => format!(
+            " HPOS=\"{}\" VPOS=\"{}\" WIDTH=\"{}\" HEIGHT=\"{}\"",
+            l,
+            t,
+            r - l,
+            b - t
+        ),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, block: u32, line: u32, left: u32, top: u32) -> OcrWord {
+        OcrWord {
+            text: text.to_string(),
+            block,
+            paragraph: 1,
+            line,
+            left,
+            top,
+            width: 20,
+            height: 10,
+            confidence: 90.0,
+        }
+    }
+
+    #[test]
+    fn test_alto_from_words() {
+        let words = vec![
+            word("X", 1, 1, 10, 10),
+            word("=", 1, 1, 40, 12),
+            word("1", 1, 2, 10, 30),
+            word("END", 2, 1, 10, 80),
+        ];
+        let xml = alto_page(
+            3,
+            "page-003.jpg",
+            Some((800, 600)),
+            AltoContent::Words(&words),
+        );
+
+        assert!(xml.contains("<Page ID=\"P3\" PHYSICAL_IMG_NR=\"3\" WIDTH=\"800\" HEIGHT=\"600\">"));
+        assert_eq!(xml.matches("<TextBlock").count(), 2);
+        assert_eq!(xml.matches("</TextBlock>").count(), 2);
+        assert_eq!(xml.matches("<TextLine").count(), 3);
+        assert!(xml.contains(
+            "<TextLine ID=\"P3_L1\" HPOS=\"10\" VPOS=\"10\" WIDTH=\"50\" HEIGHT=\"12\">"
+        ));
+        assert!(xml.contains(
+            "<String CONTENT=\"X\" WC=\"0.90\" HPOS=\"10\" VPOS=\"10\" WIDTH=\"20\" HEIGHT=\"10\"/>"
+        ));
+    }
+
+    #[test]
+    fn test_alto_from_text_escapes() {
+        let xml = alto_page(1, "a.jpg", None, AltoContent::Text("IF (A<B) GO TO 10\n\n"));
+        assert!(xml.contains("<Page ID=\"P1\" PHYSICAL_IMG_NR=\"1\">"));
+        assert!(xml.contains("<String CONTENT=\"(A&lt;B)\"/>"));
+        assert_eq!(xml.matches("<TextLine").count(), 1);
+    }
+}
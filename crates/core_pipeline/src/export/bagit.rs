@@ -0,0 +1,289 @@
+//! BagIt preservation bundle
+//!
+//! Packages a scan set for deposit into archival storage as a BagIt 1.0 bag
+//! (RFC 8493) with SHA-256 checksums:
+//!
+//! ```text
+//! bag/
+//! |-- bagit.txt
+//! |-- bag-info.txt
+//! |-- manifest-sha256.txt      # Checksum of every payload file
+//! |-- tagmanifest-sha256.txt   # Checksums of the tag files above
+//! `-- data/
+//!     |-- scan_set/            # Originals, derived images, artifacts
+//!     `-- exports/<name>/      # Exports included in the bag
+//! ```
+//!
+//! The stage cache is left out, since it only holds results that can be
+//! recomputed.
+
+use crate::error::{Error, IoContext, Result};
+use crate::preprocess::compute_file_hash;
+use crate::scan_set;
+use crate::stage_cache::CACHE_DIR;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// BagIt version written to `bagit.txt`
+pub const BAGIT_VERSION: &str = "1.0";
+
+/// Payload of a written bag
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BagSummary {
+    /// Number of payload files
+    pub files: usize,
+    /// Total size of the payload in bytes
+    pub bytes: u64,
+}
+
+/// Write a scan set and optional exports (files or directories) as a bag
+///
+/// `bag_dir` must not exist or be empty. `bagging_date` is written to
+/// `bag-info.txt` as given (`YYYY-MM-DD`).
+pub fn write_bag(
+    scan_set_dir: &Path,
+    exports: &[PathBuf],
+    bag_dir: &Path,
+    bagging_date: &str,
+) -> Result<BagSummary> {
+    if bag_dir.exists()
+        && fs::read_dir(bag_dir)
+            .io_context(|| format!("Failed to read {}", bag_dir.display()))?
+            .next()
+            .is_some()
+    {
+        return Err(Error::invalid(format!(
+            "Bag directory is not empty: {}",
+            bag_dir.display()
+        )));
+    }
+    let manifest = scan_set::load_manifest(scan_set_dir)?;
+
+    // Plan the payload before writing, so a bag inside the scan set does
+    // not end up in its own payload
+    let mut payload: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for file in list_files(scan_set_dir)? {
+        if !file.starts_with(CACHE_DIR) {
+            payload.push((scan_set_dir.join(&file), Path::new("scan_set").join(file)));
+        }
+    }
+    for export in exports {
+        let name = export.file_name().ok_or_else(|| {
+            Error::invalid(format!("Export has no file name: {}", export.display()))
+        })?;
+        let target = Path::new("exports").join(name);
+        if export.is_dir() {
+            for file in list_files(export)? {
+                payload.push((export.join(&file), target.join(file)));
+            }
+        } else {
+            payload.push((export.clone(), target));
+        }
+    }
+
+    let data_dir = bag_dir.join("data");
+    let mut manifest_text = String::new();
+    let mut summary = BagSummary { files: 0, bytes: 0 };
+    for (source, target) in &payload {
+        let path = data_dir.join(target);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .io_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        summary.bytes += fs::copy(source, &path)
+            .io_context(|| format!("Failed to copy {} to {}", source.display(), path.display()))?;
+        summary.files += 1;
+        let _ = writeln!(
+            manifest_text,
+            "{}  {}",
+            compute_file_hash(&path)?,
+            bag_path(&Path::new("data").join(target))
+        );
+    }
+
+    let bag_info = format!(
+        "Bag-Software-Agent: scan3data {}\n\
+         Bagging-Date: {}\n\
+         External-Identifier: {}\n\
+         External-Description: Scan set \"{}\" ({} images)\n\
+         Payload-Oxum: {}.{}\n",
+        env!("CARGO_PKG_VERSION"),
+        bagging_date,
+        manifest.scan_set_id.0,
+        manifest.name.replace(['\r', '\n'], " "),
+        manifest.image_count,
+        summary.bytes,
+        summary.files
+    );
+    let tag_files = [
+        (
+            "bagit.txt",
+            format!(
+                "BagIt-Version: {}\nTag-File-Character-Encoding: UTF-8\n",
+                BAGIT_VERSION
+            ),
+        ),
+        ("bag-info.txt", bag_info),
+        ("manifest-sha256.txt", manifest_text),
+    ];
+    let mut tag_manifest = String::new();
+    for (name, text) in &tag_files {
+        let path = bag_dir.join(name);
+        fs::write(&path, text).io_context(|| format!("Failed to write {}", path.display()))?;
+        let _ = writeln!(tag_manifest, "{}  {}", compute_file_hash(&path)?, name);
+    }
+    let path = bag_dir.join("tagmanifest-sha256.txt");
+    fs::write(&path, tag_manifest).io_context(|| format!("Failed to write {}", path.display()))?;
+
+    Ok(summary)
+}
+
+/// Check a bag's payload against its manifest
+///
+/// Returns one message per missing, changed or unlisted payload file; an
+/// empty list means the bag is complete and valid.
+pub fn verify_bag(bag_dir: &Path) -> Result<Vec<String>> {
+    let manifest_path = bag_dir.join("manifest-sha256.txt");
+    let manifest = fs::read_to_string(&manifest_path)
+        .io_context(|| format!("Failed to read {}", manifest_path.display()))?;
+
+    let mut problems = Vec::new();
+    let mut listed = HashSet::new();
+    for line in manifest.lines().filter(|l| !l.trim().is_empty()) {
+        let Some((hash, path)) = line.split_once(char::is_whitespace) else {
+            problems.push(format!("Malformed manifest line: {}", line));
+            continue;
+        };
+        let path = path
+            .trim_start()
+            .replace("%0A", "\n")
+            .replace("%0D", "\r")
+            .replace("%25", "%");
+        let file = bag_dir.join(&path);
+        if !file.is_file() {
+            problems.push(format!("Missing: {}", path));
+        } else if compute_file_hash(&file)? != hash {
+            problems.push(format!("Checksum mismatch: {}", path));
+        }
+        listed.insert(path);
+    }
+    for file in list_files(&bag_dir.join("data"))? {
+        let path = bag_path(&Path::new("data").join(file));
+        if !listed.contains(&path) {
+            problems.push(format!("Not in manifest: {}", path));
+        }
+    }
+    Ok(problems)
+}
+
+/// Files below a directory, relative to it, in sorted order
+fn list_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative) = pending.pop() {
+        let entries = fs::read_dir(dir.join(&relative))
+            .io_context(|| format!("Failed to read {}", dir.join(&relative).display()))?;
+        for entry in entries {
+            let entry =
+                entry.io_context(|| format!("Failed to read {}", dir.join(&relative).display()))?;
+            let path = relative.join(entry.file_name());
+            if entry
+                .file_type()
+                .io_context(|| format!("Failed to read {}", dir.join(&path).display()))?
+                .is_dir()
+            {
+                pending.push(path);
+            } else {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Manifest form of a path: `/` separators, line breaks and `%` encoded
+fn bag_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+        .replace('%', "%25")
+        .replace('\n', "%0A")
+        .replace('\r', "%0D")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ScanSetId, ScanSetManifest};
+    use tempfile::TempDir;
+
+    fn scan_set_dir() -> TempDir {
+        let dir = TempDir::new().unwrap();
+        let manifest = ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "Box 3".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            image_count: 1,
+            original_file_count: 1,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        };
+        scan_set::save_manifest(dir.path(), &manifest).unwrap();
+        fs::create_dir_all(dir.path().join("images")).unwrap();
+        fs::write(dir.path().join("images/ab.jpg"), b"jpeg").unwrap();
+        fs::create_dir_all(dir.path().join(CACHE_DIR).join("ocr")).unwrap();
+        fs::write(dir.path().join(CACHE_DIR).join("ocr/x.json"), b"{}").unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_write_and_verify_bag() {
+        let scan_set = scan_set_dir();
+        let export = TempDir::new().unwrap();
+        let deck = export.path().join("deck.json");
+        fs::write(&deck, b"[]").unwrap();
+        let out = TempDir::new().unwrap();
+        let bag = out.path().join("bag");
+
+        let summary = write_bag(scan_set.path(), &[deck], &bag, "2025-06-01").unwrap();
+        assert_eq!(summary.files, 3);
+
+        let manifest = fs::read_to_string(bag.join("manifest-sha256.txt")).unwrap();
+        assert!(manifest.contains("  data/scan_set/images/ab.jpg\n"));
+        assert!(manifest.contains("  data/exports/deck.json\n"));
+        assert!(!manifest.contains(CACHE_DIR));
+        let info = fs::read_to_string(bag.join("bag-info.txt")).unwrap();
+        assert!(info.contains(&format!("Payload-Oxum: {}.3\n", summary.bytes)));
+        assert!(verify_bag(&bag).unwrap().is_empty());
+
+        fs::write(bag.join("data/scan_set/images/ab.jpg"), b"changed").unwrap();
+        fs::write(bag.join("data/extra.txt"), b"x").unwrap();
+        assert_eq!(
+            verify_bag(&bag).unwrap(),
+            vec![
+                "Checksum mismatch: data/scan_set/images/ab.jpg",
+                "Not in manifest: data/extra.txt"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_refuses_non_empty_bag_dir() {
+        let scan_set = scan_set_dir();
+        let out = TempDir::new().unwrap();
+        fs::write(out.path().join("old.txt"), b"x").unwrap();
+        assert!(write_bag(scan_set.path(), &[], out.path(), "2025-06-01").is_err());
+    }
+
+    #[test]
+    fn test_bag_path_encoding() {
+        assert_eq!(bag_path(Path::new("data/a%b\nc")), "data/a%25b%0Ac");
+    }
+}
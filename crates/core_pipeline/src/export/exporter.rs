@@ -0,0 +1,429 @@
+//! Pluggable emulator output formats
+//!
+//! Each format is an [`Exporter`] turning reconstructed documents into the
+//! bytes of one output file. The built-in formats are listed in
+//! [`BUILTIN_EXPORTERS`]; an [`ExporterRegistry`] starts from that table
+//! and accepts further formats, so a downstream crate can add an emulator
+//! format by registering it instead of editing the `export` command:
+//!
+//! ```
+//! use core_pipeline::export::exporter::{Exporter, ExporterRegistry};
+//! use core_pipeline::types::HighLevelArtifact;
+//! use core_pipeline::Result;
+//!
+//! struct PlainText;
+//!
+//! impl Exporter for PlainText {
+//!     fn name(&self) -> &str {
+//!         "text"
+//!     }
+//!     fn extension(&self) -> &str {
+//!         "txt"
+//!     }
+//!     fn export(&self, documents: &[HighLevelArtifact]) -> Result<Vec<u8>> {
+//!         let mut text = String::new();
+//!         for document in documents {
+//!             if let HighLevelArtifact::SourceListing(listing) = document {
+//!                 for line in &listing.lines {
+//!                     text.push_str(&line.text);
+//!                     text.push('\n');
+//!                 }
+//!             }
+//!         }
+//!         Ok(text.into_bytes())
+//!     }
+//! }
+//!
+//! let mut registry = ExporterRegistry::default();
+//! registry.register(Box::new(PlainText));
+//! assert!(registry.get("text").is_some());
+//! ```
+
+use super::simh::binary_deck;
+use super::{listing_to_card_deck, listing_to_emulator, listing_to_text, CARD_COLUMNS};
+use crate::error::{Error, Result};
+use crate::keypunch::{KeypunchModel, KeypunchSettings};
+use crate::types::{EmulatorOutput, HighLevelArtifact, SourceListing};
+use std::collections::BTreeMap;
+
+/// An output format for reconstructed documents
+pub trait Exporter: Send + Sync {
+    /// Format name, as given to `export --format`
+    fn name(&self) -> &str;
+
+    /// File extension of the output, without the dot
+    fn extension(&self) -> &str;
+
+    /// Whether the format can export a document
+    ///
+    /// Source listings by default.
+    fn accepts(&self, document: &HighLevelArtifact) -> bool {
+        matches!(document, HighLevelArtifact::SourceListing(_))
+    }
+
+    /// Contents of the output file for the given documents
+    fn export(&self, documents: &[HighLevelArtifact]) -> Result<Vec<u8>>;
+}
+
+/// Card deck JSON (`card_deck`): one 80-column card per line
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CardDeckExporter;
+
+impl Exporter for CardDeckExporter {
+    fn name(&self) -> &str {
+        "card_deck"
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn export(&self, documents: &[HighLevelArtifact]) -> Result<Vec<u8>> {
+        emulator_json(documents, listing_to_card_deck)
+    }
+}
+
+/// Numbered listing JSON (`listing`)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListingExporter;
+
+impl Exporter for ListingExporter {
+    fn name(&self) -> &str {
+        "listing"
+    }
+
+    fn extension(&self) -> &str {
+        "json"
+    }
+
+    fn export(&self, documents: &[HighLevelArtifact]) -> Result<Vec<u8>> {
+        emulator_json(documents, listing_to_emulator)
+    }
+}
+
+/// Plain text listing (`lst`): numbered lines, listings separated by form
+/// feeds
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ListingTextExporter;
+
+impl Exporter for ListingTextExporter {
+    fn name(&self) -> &str {
+        "lst"
+    }
+
+    fn extension(&self) -> &str {
+        "lst"
+    }
+
+    fn export(&self, documents: &[HighLevelArtifact]) -> Result<Vec<u8>> {
+        let listings: Vec<String> = documents
+            .iter()
+            .filter_map(|document| match document {
+                HighLevelArtifact::SourceListing(listing) => Some(listing_to_text(listing)),
+                _ => None,
+            })
+            .collect();
+        if listings.is_empty() {
+            return Err(Error::invalid("No source listings to export"));
+        }
+        Ok(listings.join("\x0c").into_bytes())
+    }
+}
+
+/// Plain text deck (`text`): one 80-character line per card
+///
+/// Lines are padded or truncated to 80 columns. The sequence columns 73-80
+/// are kept unless the exporter strips them, leaving them blank.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextDeckExporter {
+    strip_sequence: bool,
+}
+
+/// First sequence column (1-based) of a card
+const SEQUENCE_COLUMN: usize = 73;
+
+impl TextDeckExporter {
+    /// Exporter keeping (`false`) or blanking (`true`) columns 73-80
+    pub const fn new(strip_sequence: bool) -> Self {
+        Self { strip_sequence }
+    }
+}
+
+impl Exporter for TextDeckExporter {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn extension(&self) -> &str {
+        "txt"
+    }
+
+    fn export(&self, documents: &[HighLevelArtifact]) -> Result<Vec<u8>> {
+        let width = if self.strip_sequence {
+            SEQUENCE_COLUMN - 1
+        } else {
+            CARD_COLUMNS
+        };
+        let mut deck = String::new();
+        let mut listings = 0;
+        for document in documents {
+            if let HighLevelArtifact::SourceListing(listing) = document {
+                listings += 1;
+                for line in &listing.lines {
+                    let card: String = line.text.chars().take(width).collect();
+                    deck.push_str(&format!("{:<width$}\n", card, width = CARD_COLUMNS));
+                }
+            }
+        }
+        if listings == 0 {
+            return Err(Error::invalid("No source listings to export"));
+        }
+        Ok(deck.into_bytes())
+    }
+}
+
+/// simh ibm1130 binary card deck (`simh`), for `attach -b cr`
+///
+/// Each source listing is punched on its document's keypunch, so the deck
+/// carries the hole patterns the original cards had.
+#[derive(Debug, Clone, Default)]
+pub struct SimhDeckExporter {
+    keypunch: KeypunchSettings,
+}
+
+impl SimhDeckExporter {
+    /// Exporter punching documents on the given keypunch models
+    pub const fn new(keypunch: KeypunchSettings) -> Self {
+        Self { keypunch }
+    }
+}
+
+impl Exporter for SimhDeckExporter {
+    fn name(&self) -> &str {
+        "simh"
+    }
+
+    fn extension(&self) -> &str {
+        "dck"
+    }
+
+    fn export(&self, documents: &[HighLevelArtifact]) -> Result<Vec<u8>> {
+        let mut deck = Vec::new();
+        for document in documents {
+            if let HighLevelArtifact::SourceListing(listing) = document {
+                let model = self.keypunch.for_document(listing.name.as_deref());
+                deck.extend(binary_deck(
+                    listing.lines.iter().map(|line| line.text.as_str()),
+                    model,
+                ));
+            }
+        }
+        if deck.is_empty() {
+            return Err(Error::invalid("No source listings to export"));
+        }
+        Ok(deck)
+    }
+}
+
+/// Built-in formats, in the order they are listed
+///
+/// The `simh` deck punches on an 029; register a [`SimhDeckExporter`] with
+/// the scan set's keypunch settings to replace it.
+pub static BUILTIN_EXPORTERS: [&dyn Exporter; 5] = [
+    &CardDeckExporter,
+    &ListingExporter,
+    &ListingTextExporter,
+    &TextDeckExporter::new(false),
+    &SimhDeckExporter::new(KeypunchSettings {
+        model: KeypunchModel::Ibm029,
+        documents: BTreeMap::new(),
+    }),
+];
+
+/// Formats available to the `export` command, looked up by name
+pub struct ExporterRegistry {
+    builtins: Vec<&'static dyn Exporter>,
+    registered: Vec<Box<dyn Exporter>>,
+}
+
+impl ExporterRegistry {
+    /// Registry without any formats
+    pub fn empty() -> Self {
+        Self {
+            builtins: Vec::new(),
+            registered: Vec::new(),
+        }
+    }
+
+    /// Add a format, replacing any format of the same name
+    pub fn register(&mut self, exporter: Box<dyn Exporter>) {
+        let name = exporter.name().to_string();
+        self.builtins.retain(|e| e.name() != name);
+        self.registered.retain(|e| e.name() != name);
+        self.registered.push(exporter);
+    }
+
+    /// Format with the given name
+    pub fn get(&self, name: &str) -> Option<&dyn Exporter> {
+        self.iter().find(|e| e.name() == name)
+    }
+
+    /// Names of all formats, built-ins first
+    pub fn names(&self) -> Vec<&str> {
+        self.iter().map(|e| e.name()).collect()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = &dyn Exporter> {
+        self.builtins
+            .iter()
+            .copied()
+            .chain(self.registered.iter().map(|e| e.as_ref()))
+    }
+}
+
+impl Default for ExporterRegistry {
+    /// Registry with the built-in formats
+    fn default() -> Self {
+        Self {
+            builtins: BUILTIN_EXPORTERS.to_vec(),
+            registered: Vec::new(),
+        }
+    }
+}
+
+/// Pretty JSON of the converted source listings: the output itself for
+/// one listing, an array for several
+fn emulator_json(
+    documents: &[HighLevelArtifact],
+    convert: fn(&SourceListing) -> EmulatorOutput,
+) -> Result<Vec<u8>> {
+    let outputs: Vec<EmulatorOutput> = documents
+        .iter()
+        .filter_map(|document| match document {
+            HighLevelArtifact::SourceListing(listing) => Some(convert(listing)),
+            _ => None,
+        })
+        .collect();
+    let json = match outputs.as_slice() {
+        [] => return Err(Error::invalid("No source listings to export")),
+        [output] => serde_json::to_string_pretty(output)?,
+        outputs => serde_json::to_string_pretty(outputs)?,
+    };
+    Ok(json.into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{MixedArtifact, SourceLine};
+
+    fn listing() -> HighLevelArtifact {
+        HighLevelArtifact::SourceListing(SourceListing {
+            name: None,
+            language: "fortran".to_string(),
+            pages: Vec::new(),
+            lines: vec![SourceLine {
+                line_no: None,
+                text: "      END".to_string(),
+                inferred: false,
+            }],
+        })
+    }
+
+    struct TextListing;
+
+    impl Exporter for TextListing {
+        fn name(&self) -> &str {
+            "listing"
+        }
+        fn extension(&self) -> &str {
+            "txt"
+        }
+        fn export(&self, _documents: &[HighLevelArtifact]) -> Result<Vec<u8>> {
+            Ok(b"END".to_vec())
+        }
+    }
+
+    #[test]
+    fn test_builtin_formats() {
+        let registry = ExporterRegistry::default();
+        assert_eq!(
+            registry.names(),
+            vec!["card_deck", "listing", "lst", "text", "simh"]
+        );
+        assert!(registry.get("iiif").is_none());
+
+        let bytes = registry
+            .get("card_deck")
+            .unwrap()
+            .export(&[listing()])
+            .unwrap();
+        let json = String::from_utf8(bytes).unwrap();
+        assert!(json.contains("\"type\": \"card_deck\""));
+        assert!(json.contains(&format!("{:<80}", "      END")));
+
+        let lst = registry.get("lst").unwrap().export(&[listing(), listing()]);
+        assert_eq!(
+            String::from_utf8(lst.unwrap()).unwrap(),
+            "    1        END\n\x0c    1        END\n"
+        );
+    }
+
+    #[test]
+    fn test_no_listings() {
+        let mixed = HighLevelArtifact::Mixed(MixedArtifact {
+            pages: Vec::new(),
+            cards: Vec::new(),
+            description: String::new(),
+        });
+        assert!(!ListingExporter.accepts(&mixed));
+        assert!(ListingExporter.export(&[mixed]).is_err());
+    }
+
+    #[test]
+    fn test_text_deck_sequence_columns() {
+        let HighLevelArtifact::SourceListing(mut listing) = listing() else {
+            unreachable!()
+        };
+        listing.lines[0].text = format!("{:<72}PROG0010EXTRA", "      END");
+        let documents = [HighLevelArtifact::SourceListing(listing)];
+
+        let kept = TextDeckExporter::new(false).export(&documents).unwrap();
+        let kept = String::from_utf8(kept).unwrap();
+        assert_eq!(kept, format!("{:<72}PROG0010\n", "      END"));
+
+        let stripped = TextDeckExporter::new(true).export(&documents).unwrap();
+        let stripped = String::from_utf8(stripped).unwrap();
+        assert_eq!(stripped, format!("{:<80}\n", "      END"));
+    }
+
+    #[test]
+    fn test_simh_deck_uses_document_keypunch() {
+        let mut keypunch = KeypunchSettings::default();
+        let HighLevelArtifact::SourceListing(mut listing) = listing() else {
+            unreachable!()
+        };
+        listing.name = Some("PAYROLL".to_string());
+        listing.lines[0].text = "+".to_string();
+        keypunch
+            .documents
+            .insert("PAYROLL".to_string(), KeypunchModel::Ibm026Fortran);
+
+        let deck = SimhDeckExporter::new(keypunch)
+            .export(&[HighLevelArtifact::SourceListing(listing)])
+            .unwrap();
+        assert_eq!(deck.len(), 160);
+        assert_eq!(&deck[..2], &[0x00, 0x80]);
+    }
+
+    #[test]
+    fn test_register_replaces_same_name() {
+        let mut registry = ExporterRegistry::default();
+        registry.register(Box::new(TextListing));
+        assert_eq!(
+            registry.names(),
+            vec!["card_deck", "lst", "text", "simh", "listing"]
+        );
+        assert_eq!(registry.get("listing").unwrap().extension(), "txt");
+    }
+}
@@ -0,0 +1,203 @@
+//! hOCR for one page
+//!
+//! hOCR is XHTML whose elements carry OCR layout in `title` properties:
+//! `ocr_page`, `ocr_carea` (block), `ocr_par`, `ocr_line` and `ocrx_word`,
+//! each with a `bbox` in pixels and words with their `x_wconf`. It is read
+//! by hocr-tools, OCRmyPDF, Internet Archive and most digital library
+//! pipelines. Like ALTO ([`super::alto`]), pages without word boxes are
+//! written from their text, one line per text line without coordinates.
+
+use super::alto::AltoContent;
+use crate::ocr::OcrWord;
+use crate::validate::report::html_escape;
+use std::fmt::Write as _;
+
+/// Render the hOCR document of one page
+///
+/// Arguments are as for [`super::alto::alto_page`].
+pub fn hocr_page(
+    number: usize,
+    image_file: &str,
+    size: Option<(u32, u32)>,
+    content: AltoContent,
+) -> String {
+    let mut html = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <!DOCTYPE html PUBLIC \"-//W3C//DTD XHTML 1.0 Transitional//EN\" \
+         \"http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd\">\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xml:lang=\"en\" lang=\"en\">\n <head>\n",
+    );
+    let _ = writeln!(html, "  <title>{}</title>", html_escape(image_file));
+    let _ = writeln!(
+        html,
+        "  <meta http-equiv=\"Content-Type\" content=\"text/html;charset=utf-8\"/>\n  \
+         <meta name=\"ocr-system\" content=\"scan3data {}\"/>\n  \
+         <meta name=\"ocr-capabilities\" content=\"ocr_page ocr_carea ocr_par ocr_line ocrx_word\"/>\n \
+         </head>\n <body>",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    let page_box = size
+        .map(|(w, h)| format!("; bbox 0 0 {} {}", w, h))
+        .unwrap_or_default();
+    let _ = writeln!(
+        html,
+        "  <div class=\"ocr_page\" id=\"page_{}\" title=\"image {}{}; ppageno {}\">",
+        number,
+        html_escape(&format!("\"{}\"", image_file)),
+        page_box,
+        number - 1
+    );
+    match content {
+        AltoContent::Words(words) => write_word_areas(&mut html, number, words),
+        AltoContent::Text(text) => write_text_area(&mut html, number, text),
+    }
+    html.push_str("  </div>\n </body>\n</html>\n");
+    html
+}
+
+/// Areas, paragraphs, lines and words with boxes, from word boxes
+fn write_word_areas(html: &mut String, page: usize, words: &[OcrWord]) {
+    let (mut par_no, mut line_no, mut word_no) = (0, 0, 0);
+    for (block_no, block) in words.chunk_by(|a, b| a.block == b.block).enumerate() {
+        let _ = writeln!(
+            html,
+            "   <div class=\"ocr_carea\" id=\"block_{}_{}\" title=\"{}\">",
+            page,
+            block_no + 1,
+            bbox(block)
+        );
+        for paragraph in block.chunk_by(|a, b| a.paragraph == b.paragraph) {
+            par_no += 1;
+            let _ = writeln!(
+                html,
+                "    <p class=\"ocr_par\" id=\"par_{}_{}\" title=\"{}\">",
+                page,
+                par_no,
+                bbox(paragraph)
+            );
+            for line in paragraph.chunk_by(|a, b| a.line == b.line) {
+                line_no += 1;
+                let _ = writeln!(
+                    html,
+                    "     <span class=\"ocr_line\" id=\"line_{}_{}\" title=\"{}\">",
+                    page,
+                    line_no,
+                    bbox(line)
+                );
+                for word in line {
+                    word_no += 1;
+                    let _ = writeln!(
+                        html,
+                        "      <span class=\"ocrx_word\" id=\"word_{}_{}\" title=\"{}; x_wconf {}\">{}</span>",
+                        page,
+                        word_no,
+                        bbox(std::slice::from_ref(word)),
+                        word.confidence.clamp(0.0, 100.0).round(),
+                        html_escape(&word.text)
+                    );
+                }
+                html.push_str("     </span>\n");
+            }
+            html.push_str("    </p>\n");
+        }
+        html.push_str("   </div>\n");
+    }
+}
+
+/// A single area of lines without boxes, from plain text
+fn write_text_area(html: &mut String, page: usize, text: &str) {
+    let _ = writeln!(
+        html,
+        "   <div class=\"ocr_carea\" id=\"block_{0}_1\">\n    <p class=\"ocr_par\" id=\"par_{0}_1\">",
+        page
+    );
+    let mut word_no = 0;
+    for (idx, line) in text.lines().filter(|l| !l.trim().is_empty()).enumerate() {
+        let _ = writeln!(
+            html,
+            "     <span class=\"ocr_line\" id=\"line_{}_{}\">",
+            page,
+            idx + 1
+        );
+        for token in line.split_whitespace() {
+            word_no += 1;
+            let _ = writeln!(
+                html,
+                "      <span class=\"ocrx_word\" id=\"word_{}_{}\">{}</span>",
+                page,
+                word_no,
+                html_escape(token)
+            );
+        }
+        html.push_str("     </span>\n");
+    }
+    html.push_str("    </p>\n   </div>\n");
+}
+
+/// `bbox left top right bottom` of the box enclosing some words
+fn bbox(words: &[OcrWord]) -> String {
+    let left = words.iter().map(|w| w.left).min().unwrap_or(0);
+    let top = words.iter().map(|w| w.top).min().unwrap_or(0);
+    let right = words.iter().map(|w| w.left + w.width).max().unwrap_or(0);
+    let bottom = words.iter().map(|w| w.top + w.height).max().unwrap_or(0);
+    format!("bbox {} {} {} {}", left, top, right, bottom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, block: u32, line: u32, left: u32, top: u32) -> OcrWord {
+        OcrWord {
+            text: text.to_string(),
+            block,
+            paragraph: 1,
+            line,
+            left,
+            top,
+            width: 20,
+            height: 10,
+            confidence: 87.6,
+        }
+    }
+
+    #[test]
+    fn test_hocr_from_words() {
+        let words = vec![
+            word("X", 1, 1, 10, 10),
+            word("<", 1, 1, 40, 12),
+            word("1", 1, 2, 10, 30),
+            word("END", 2, 1, 10, 80),
+        ];
+        let html = hocr_page(
+            3,
+            "page-003.jpg",
+            Some((800, 600)),
+            AltoContent::Words(&words),
+        );
+
+        assert!(html.contains(
+            "<div class=\"ocr_page\" id=\"page_3\" \
+             title=\"image &quot;page-003.jpg&quot;; bbox 0 0 800 600; ppageno 2\">"
+        ));
+        assert_eq!(html.matches("class=\"ocr_carea\"").count(), 2);
+        assert_eq!(html.matches("class=\"ocr_par\"").count(), 2);
+        assert_eq!(html.matches("class=\"ocr_line\"").count(), 3);
+        assert!(html.contains("id=\"block_3_1\" title=\"bbox 10 10 60 40\""));
+        assert!(html.contains("id=\"line_3_1\" title=\"bbox 10 10 60 22\""));
+        assert!(html.contains(
+            "<span class=\"ocrx_word\" id=\"word_3_2\" title=\"bbox 40 12 60 22; x_wconf 88\">&lt;</span>"
+        ));
+        assert!(html.ends_with("  </div>\n </body>\n</html>\n"));
+    }
+
+    #[test]
+    fn test_hocr_from_text() {
+        let html = hocr_page(1, "p.png", None, AltoContent::Text("A = B\n\n  END\n"));
+        assert!(html.contains("title=\"image &quot;p.png&quot;; ppageno 0\""));
+        assert_eq!(html.matches("class=\"ocr_line\"").count(), 2);
+        assert!(html.contains("<span class=\"ocrx_word\" id=\"word_1_4\">END</span>"));
+        assert!(!html.contains("bbox"));
+    }
+}
@@ -0,0 +1,221 @@
+//! IIIF Presentation 3.0 manifest export
+//!
+//! Describes a scan set as a IIIF manifest so the scans can be shown in
+//! IIIF viewers (Mirador, Universal Viewer, ...). Each page becomes a canvas
+//! painted with its image and supplemented by its OCR text as a textual
+//! annotation. Images are referenced either as files published next to the
+//! manifest or through a IIIF Image API server.
+
+use crate::types::{PageArtifact, PageId, ScanSetManifest};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Where the manifest and images are published
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IiifOptions {
+    /// Base URL the manifest is served from; page images are expected at
+    /// the same relative paths as in the scan set (`images/...`)
+    pub base_url: String,
+    /// Base URL of a IIIF Image API 3 server; images are then identified by
+    /// their file name without extension
+    pub image_service: Option<String>,
+}
+
+/// Build the manifest
+///
+/// `dimensions` gives each page's image size in pixels; pages without one
+/// are skipped, since a canvas needs a size.
+pub fn iiif_manifest(
+    manifest: &ScanSetManifest,
+    artifacts: &[PageArtifact],
+    dimensions: &HashMap<PageId, (u32, u32)>,
+    options: &IiifOptions,
+) -> Value {
+    let base = options.base_url.trim_end_matches('/');
+    let canvases: Vec<Value> = artifacts
+        .iter()
+        .filter_map(|artifact| Some((artifact, *dimensions.get(&artifact.id)?)))
+        .enumerate()
+        .map(|(idx, (artifact, size))| canvas(base, idx + 1, artifact, size, options))
+        .collect();
+
+    json!({
+        "@context": "http://iiif.io/api/presentation/3/context.json",
+        "id": format!("{}/manifest.json", base),
+        "type": "Manifest",
+        "label": { "none": [manifest.name] },
+        "metadata": [
+            metadata_entry("Scan set", &manifest.scan_set_id.0.to_string()),
+            metadata_entry("Scanned", &manifest.created_at),
+            metadata_entry("Pages", &manifest.image_count.to_string()),
+        ],
+        "items": canvases,
+    })
+}
+
+/// One canvas with its painting and OCR annotation pages
+fn canvas(
+    base: &str,
+    number: usize,
+    artifact: &PageArtifact,
+    (width, height): (u32, u32),
+    options: &IiifOptions,
+) -> Value {
+    let id = format!("{}/canvas/{}", base, number);
+    let image_path = &artifact.raw_image_path;
+
+    let mut body = match &options.image_service {
+        Some(service) => {
+            let service = format!(
+                "{}/{}",
+                service.trim_end_matches('/'),
+                image_path.file_stem().unwrap_or_default().to_string_lossy()
+            );
+            json!({
+                "id": format!("{}/full/max/0/default.jpg", service),
+                "type": "Image",
+                "format": "image/jpeg",
+                "service": [{ "id": service, "type": "ImageService3", "profile": "level1" }],
+            })
+        }
+        None => json!({
+            "id": format!("{}/{}", base, image_path.to_string_lossy().replace('\\', "/")),
+            "type": "Image",
+            "format": image_format(image_path),
+        }),
+    };
+    body["width"] = json!(width);
+    body["height"] = json!(height);
+
+    let mut canvas = json!({
+        "id": id,
+        "type": "Canvas",
+        "label": { "none": [format!("Page {}", number)] },
+        "width": width,
+        "height": height,
+        "items": [{
+            "id": format!("{}/page", id),
+            "type": "AnnotationPage",
+            "items": [{
+                "id": format!("{}/page/image", id),
+                "type": "Annotation",
+                "motivation": "painting",
+                "body": body,
+                "target": id,
+            }],
+        }],
+    });
+    if let Some(text) = &artifact.content_text {
+        canvas["annotations"] = json!([{
+            "id": format!("{}/ocr", id),
+            "type": "AnnotationPage",
+            "items": [{
+                "id": format!("{}/ocr/text", id),
+                "type": "Annotation",
+                "motivation": "supplementing",
+                "body": { "type": "TextualBody", "value": text, "format": "text/plain" },
+                "target": id,
+            }],
+        }]);
+    }
+    canvas
+}
+
+fn metadata_entry(label: &str, value: &str) -> Value {
+    json!({ "label": { "en": [label] }, "value": { "none": [value] } })
+}
+
+/// MIME type of an image file, from its extension
+fn image_format(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "png" => "image/png",
+        "tif" | "tiff" => "image/tiff",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        _ => "image/jpeg",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageMetadata, ScanSetId};
+    use std::path::PathBuf;
+
+    fn manifest() -> ScanSetManifest {
+        ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "Box 3".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            image_count: 2,
+            original_file_count: 2,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        }
+    }
+
+    fn page(text: Option<&str>) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/0123456789abcdef.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: text.map(str::to_string),
+            metadata: PageMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_manifest_with_published_images() {
+        let pages = vec![page(Some("      X = 1")), page(None), page(None)];
+        let dimensions = HashMap::from([(pages[0].id, (2550, 3300)), (pages[1].id, (2550, 3300))]);
+        let options = IiifOptions {
+            base_url: "https://example.org/box3/".to_string(),
+            image_service: None,
+        };
+        let value = iiif_manifest(&manifest(), &pages, &dimensions, &options);
+
+        assert_eq!(value["id"], "https://example.org/box3/manifest.json");
+        let items = value["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        let body = &items[0]["items"][0]["items"][0]["body"];
+        assert_eq!(
+            body["id"],
+            "https://example.org/box3/images/0123456789abcdef.jpg"
+        );
+        assert_eq!(body["width"], 2550);
+        let ocr = &items[0]["annotations"][0]["items"][0];
+        assert_eq!(ocr["motivation"], "supplementing");
+        assert_eq!(ocr["body"]["value"], "      X = 1");
+        assert!(items[1].get("annotations").is_none());
+    }
+
+    #[test]
+    fn test_manifest_with_image_service() {
+        let pages = vec![page(None)];
+        let dimensions = HashMap::from([(pages[0].id, (100, 200))]);
+        let options = IiifOptions {
+            base_url: "https://example.org/box3".to_string(),
+            image_service: Some("https://images.example.org/iiif/3/".to_string()),
+        };
+        let value = iiif_manifest(&manifest(), &pages, &dimensions, &options);
+        let body = &value["items"][0]["items"][0]["items"][0]["body"];
+        assert_eq!(
+            body["service"][0]["id"],
+            "https://images.example.org/iiif/3/0123456789abcdef"
+        );
+        assert_eq!(
+            body["id"],
+            "https://images.example.org/iiif/3/0123456789abcdef/full/max/0/default.jpg"
+        );
+    }
+}
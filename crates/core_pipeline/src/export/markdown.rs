@@ -0,0 +1,273 @@
+//! Markdown transcript export
+//!
+//! Renders a scan set as a Markdown transcript for publishing recovered
+//! listings: one section per page with a thumbnail, a metadata table and
+//! the corrected text in a fenced code block. Either a single
+//! `transcript.md` or an mdBook with one chapter per page:
+//!
+//! ```text
+//! transcript.md          book.toml
+//! images/page-001.png    src/SUMMARY.md
+//!                        src/page-001.md
+//!                        src/images/page-001.png
+//! ```
+//!
+//! Files are planned as [`RepoFile`]s and written with
+//! [`super::repository::write_repository`].
+
+use super::repository::{RepoContent, RepoFile};
+use crate::types::{PageArtifact, PageId, ScanSetManifest};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Longest side of page thumbnails, in pixels
+pub const THUMBNAIL_SIZE: u32 = 480;
+
+/// Shape of the transcript
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TranscriptLayout {
+    /// A single `transcript.md`
+    #[default]
+    SingleFile,
+    /// An mdBook source tree with one chapter per page
+    MdBook,
+}
+
+/// Plan the files of a Markdown transcript
+///
+/// `thumbnails` maps pages to thumbnail images (paths relative to the scan
+/// set); pages without one are rendered without an image.
+pub fn plan_transcript(
+    manifest: &ScanSetManifest,
+    artifacts: &[PageArtifact],
+    thumbnails: &HashMap<PageId, PathBuf>,
+    layout: TranscriptLayout,
+) -> Vec<RepoFile> {
+    let root = match layout {
+        TranscriptLayout::SingleFile => PathBuf::new(),
+        TranscriptLayout::MdBook => PathBuf::from("src"),
+    };
+    let mut files = Vec::new();
+    let mut sections = Vec::new();
+
+    for (idx, artifact) in artifacts.iter().enumerate() {
+        let number = idx + 1;
+        let image = thumbnails.get(&artifact.id).map(|thumbnail| {
+            let ext = thumbnail
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("png");
+            let path = PathBuf::from("images").join(format!("page-{:03}.{}", number, ext));
+            files.push(RepoFile {
+                path: root.join(&path),
+                content: RepoContent::CopyFrom(thumbnail.clone()),
+            });
+            path
+        });
+        sections.push(page_section(artifact, number, image.as_deref()));
+    }
+
+    match layout {
+        TranscriptLayout::SingleFile => {
+            files.insert(
+                0,
+                RepoFile::text("transcript.md", single_file(manifest, &sections)),
+            );
+        }
+        TranscriptLayout::MdBook => {
+            let mut summary = format!("# Summary\n\n[{}](introduction.md)\n\n", manifest.name);
+            for (idx, section) in sections.into_iter().enumerate() {
+                let chapter = format!("page-{:03}.md", idx + 1);
+                let _ = writeln!(summary, "- [Page {}]({})", idx + 1, chapter);
+                files.push(RepoFile::text(root.join(chapter), section));
+            }
+            let introduction = format!("# {}\n\n{}", manifest.name, provenance(manifest));
+            files.insert(
+                0,
+                RepoFile::text(root.join("introduction.md"), introduction),
+            );
+            files.insert(0, RepoFile::text(root.join("SUMMARY.md"), summary));
+            let book = format!(
+                "[book]\ntitle = \"{}\"\nsrc = \"src\"\n",
+                manifest.name.replace('\\', "\\\\").replace('"', "\\\"")
+            );
+            files.insert(0, RepoFile::text("book.toml", book));
+        }
+    }
+    files
+}
+
+/// Single-file transcript without page images
+pub fn transcript_text(manifest: &ScanSetManifest, artifacts: &[PageArtifact]) -> String {
+    let sections: Vec<String> = artifacts
+        .iter()
+        .enumerate()
+        .map(|(idx, artifact)| page_section(artifact, idx + 1, None))
+        .collect();
+    single_file(manifest, &sections)
+}
+
+/// Title, provenance and the page sections in one document
+fn single_file(manifest: &ScanSetManifest, sections: &[String]) -> String {
+    let mut text = format!("# {}\n\n", manifest.name);
+    text.push_str(&provenance(manifest));
+    for section in sections {
+        text.push('\n');
+        text.push_str(section);
+    }
+    text
+}
+
+/// Scan set details shown at the top of the transcript
+fn provenance(manifest: &ScanSetManifest) -> String {
+    let mut text = String::new();
+    let _ = writeln!(text, "| | |\n|---|---|");
+    let _ = writeln!(text, "| Scan set | `{}` |", manifest.scan_set_id.0);
+    let _ = writeln!(text, "| Scanned | {} |", manifest.created_at);
+    let _ = writeln!(text, "| Pages | {} |", manifest.image_count);
+    text
+}
+
+/// Markdown section for one page
+fn page_section(artifact: &PageArtifact, number: usize, image: Option<&Path>) -> String {
+    let metadata = &artifact.metadata;
+    let mut text = match &metadata.header {
+        Some(header) => format!("## Page {}: {}\n\n", number, escape(header.trim())),
+        None => format!("## Page {}\n\n", number),
+    };
+    if let Some(image) = image {
+        let _ = writeln!(text, "![Page {}]({})\n", number, image.display());
+    }
+
+    text.push_str("| Field | Value |\n|---|---|\n");
+    let _ = writeln!(
+        text,
+        "| Original files | {} |",
+        escape(&metadata.original_filenames.join(", "))
+    );
+    let _ = writeln!(text, "| Classification | {:?} |", artifact.layout_label);
+    if let Some(page_number) = metadata.page_number {
+        let _ = writeln!(text, "| Printed page | {} |", page_number);
+    }
+    let _ = writeln!(text, "| Confidence | {:.2} |", metadata.confidence);
+    let _ = writeln!(text, "| Corrections | {} |", metadata.revisions.len());
+    let _ = writeln!(
+        text,
+        "| Validation issues | {} |",
+        metadata.validation_issues.len()
+    );
+    text.push('\n');
+
+    match &artifact.content_text {
+        Some(content) => text.push_str(&fenced(content)),
+        None => text.push_str("*No text recognized.*\n"),
+    }
+    text
+}
+
+/// Text in a fenced code block, with a fence longer than any backtick run
+/// in the text
+fn fenced(content: &str) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}text\n{}\n{}\n", fence, content.trim_end(), fence)
+}
+
+/// Escape characters that would break a table cell or heading
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageMetadata, ScanSetId};
+
+    fn manifest() -> ScanSetManifest {
+        ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "Box 3".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            image_count: 2,
+            original_file_count: 2,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        }
+    }
+
+    fn page(text: Option<&str>) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/a.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: text.map(str::to_string),
+            metadata: PageMetadata {
+                original_filenames: vec!["scan|1.jpg".to_string()],
+                header: Some("PAYROLL".to_string()),
+                ..PageMetadata::default()
+            },
+        }
+    }
+
+    fn text_of(file: &RepoFile) -> &str {
+        match &file.content {
+            RepoContent::Text(text) => text,
+            RepoContent::CopyFrom(_) => panic!("expected text"),
+        }
+    }
+
+    #[test]
+    fn test_single_file_transcript() {
+        let pages = vec![page(Some("      X = 1\n")), page(None)];
+        let thumbnails = HashMap::from([(pages[0].id, PathBuf::from("derived/ab/t.png"))]);
+        let files = plan_transcript(
+            &manifest(),
+            &pages,
+            &thumbnails,
+            TranscriptLayout::SingleFile,
+        );
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].path, PathBuf::from("images/page-001.png"));
+        let text = text_of(&files[0]);
+        assert!(text.starts_with("# Box 3\n"));
+        assert!(text.contains("## Page 1: PAYROLL\n\n![Page 1](images/page-001.png)"));
+        assert!(text.contains("| Original files | scan\\|1.jpg |"));
+        assert!(text.contains("```text\n      X = 1\n```\n"));
+        assert!(text.contains("*No text recognized.*"));
+    }
+
+    #[test]
+    fn test_mdbook_transcript() {
+        let pages = vec![page(Some("A"))];
+        let files = plan_transcript(
+            &manifest(),
+            &pages,
+            &HashMap::new(),
+            TranscriptLayout::MdBook,
+        );
+        let paths: Vec<String> = files.iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "book.toml",
+                "src/SUMMARY.md",
+                "src/introduction.md",
+                "src/page-001.md"
+            ]
+        );
+        assert!(text_of(&files[1]).contains("- [Page 1](page-001.md)"));
+    }
+
+    #[test]
+    fn test_fence_longer_than_backticks_in_text() {
+        assert!(fenced("a ``` b").starts_with("````text\n"));
+        assert!(fenced("plain").starts_with("```text\n"));
+    }
+}
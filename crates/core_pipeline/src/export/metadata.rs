@@ -0,0 +1,189 @@
+//! Artifact metadata export as CSV or TSV
+//!
+//! One row per page artifact, for tracking a digitization project's
+//! progress in a spreadsheet.
+
+use crate::types::PageArtifact;
+use crate::validate::Severity;
+
+/// Column headers, in output order
+pub const COLUMNS: [&str; 13] = [
+    "id",
+    "content_hash",
+    "original_filenames",
+    "kind",
+    "confidence",
+    "status",
+    "errors",
+    "warnings",
+    "revisions",
+    "cost_usd",
+    "page_number",
+    "text_len",
+    "notes",
+];
+
+/// Field separator
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delimiter {
+    /// Comma-separated, fields quoted when needed
+    Comma,
+    /// Tab-separated, tabs and line breaks in fields replaced by spaces
+    Tab,
+}
+
+/// Progress of an artifact through the pipeline
+pub fn artifact_status(artifact: &PageArtifact) -> &'static str {
+    let metadata = &artifact.metadata;
+    if artifact.content_text.is_some() {
+        if metadata
+            .validation_issues
+            .iter()
+            .any(|i| i.severity == Severity::Error)
+        {
+            "needs-review"
+        } else {
+            "analyzed"
+        }
+    } else if metadata.notes.iter().any(|n| n.starts_with("OCR failed")) {
+        "ocr-failed"
+    } else {
+        "pending"
+    }
+}
+
+/// Metadata table with a header row
+///
+/// Multiple original filenames are joined with `;`, notes with `; `. The
+/// page number is empty unless one was detected; the text length counts
+/// characters (0 before analysis).
+pub fn metadata_table(artifacts: &[PageArtifact], delimiter: Delimiter) -> String {
+    let mut table = row(COLUMNS.iter().map(|c| c.to_string()), delimiter);
+    for artifact in artifacts {
+        let metadata = &artifact.metadata;
+        let count = |severity| {
+            metadata
+                .validation_issues
+                .iter()
+                .filter(|i| i.severity == severity)
+                .count()
+        };
+        let fields = [
+            artifact.id.0.to_string(),
+            metadata.content_hash.clone(),
+            metadata.original_filenames.join(";"),
+            format!("{:?}", artifact.layout_label),
+            format!("{:.3}", metadata.confidence),
+            artifact_status(artifact).to_string(),
+            count(Severity::Error).to_string(),
+            count(Severity::Warning).to_string(),
+            metadata.revisions.len().to_string(),
+            format!("{:.3}", metadata.cost_usd),
+            metadata
+                .page_number
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+            artifact
+                .content_text
+                .as_deref()
+                .map_or(0, |t| t.chars().count())
+                .to_string(),
+            metadata.notes.join("; "),
+        ];
+        table.push_str(&row(fields.into_iter(), delimiter));
+    }
+    table
+}
+
+/// One line of the table
+fn row(fields: impl Iterator<Item = String>, delimiter: Delimiter) -> String {
+    let fields: Vec<String> = fields
+        .map(|field| match delimiter {
+            Delimiter::Comma if field.contains([',', '"', '\n', '\r']) => {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            }
+            Delimiter::Comma => field,
+            Delimiter::Tab => field.replace(['\t', '\n', '\r'], " "),
+        })
+        .collect();
+    let separator = match delimiter {
+        Delimiter::Comma => ",",
+        Delimiter::Tab => "\t",
+    };
+    fields.join(separator) + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageId, PageMetadata, ScanSetId};
+    use crate::validate::ValidationIssue;
+    use std::path::PathBuf;
+
+    fn artifact(text: Option<&str>, filenames: &[&str]) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/a.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: text.map(str::to_string),
+            metadata: PageMetadata {
+                content_hash: "abc".to_string(),
+                original_filenames: filenames.iter().map(|f| f.to_string()).collect(),
+                confidence: 0.5,
+                ..PageMetadata::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_status() {
+        let mut page = artifact(None, &[]);
+        assert_eq!(artifact_status(&page), "pending");
+        page.metadata.notes.push("OCR failed: timeout".to_string());
+        assert_eq!(artifact_status(&page), "ocr-failed");
+        page.content_text = Some("X".to_string());
+        assert_eq!(artifact_status(&page), "analyzed");
+        page.metadata.validation_issues.push(ValidationIssue {
+            rule: "fortran.keyword".to_string(),
+            line_number: 1,
+            column: None,
+            severity: Severity::Error,
+            description: String::new(),
+            excerpt: String::new(),
+            suggestion: None,
+        });
+        assert_eq!(artifact_status(&page), "needs-review");
+    }
+
+    #[test]
+    fn test_csv_quotes_fields() {
+        let mut page = artifact(Some("X"), &["box 1, page \"2\".jpg", "copy.jpg"]);
+        page.metadata.page_number = Some(12);
+        page.metadata.notes = vec![
+            "Heuristic language: fortran".to_string(),
+            "damaged".to_string(),
+        ];
+        let csv = metadata_table(std::slice::from_ref(&page), Delimiter::Comma);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], COLUMNS.join(","));
+        assert_eq!(
+            lines[1],
+            format!(
+                "{},abc,\"box 1, page \"\"2\"\".jpg;copy.jpg\",ListingSource,0.500,analyzed,0,0,0,0.000,\
+                 12,1,Heuristic language: fortran; damaged",
+                page.id.0
+            )
+        );
+    }
+
+    #[test]
+    fn test_tsv_replaces_tabs() {
+        let page = artifact(None, &["a\tb.jpg"]);
+        let tsv = metadata_table(&[page], Delimiter::Tab);
+        let row: Vec<&str> = tsv.lines().nth(1).unwrap().split('\t').collect();
+        assert_eq!(row.len(), COLUMNS.len());
+        assert_eq!(row[2], "a b.jpg");
+    }
+}
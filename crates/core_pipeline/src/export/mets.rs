@@ -0,0 +1,216 @@
+//! METS/ALTO archival package
+//!
+//! The usual deliverable of library digitization projects: a METS document
+//! describing the page sequence, one ALTO file of OCR per page and the page
+//! images:
+//!
+//! ```text
+//! package/
+//! |-- mets.xml
+//! |-- images/page-001.jpg
+//! `-- alto/page-001.xml
+//! ```
+//!
+//! ALTO files use the OCR word boxes where the analysis stored them, and
+//! fall back to the page text without coordinates otherwise.
+
+use super::alto::{alto_page, page_content};
+use super::repository::{RepoContent, RepoFile};
+use crate::ocr::OcrOutput;
+use crate::types::{PageArtifact, PageId, ScanSetManifest};
+use crate::validate::report::html_escape;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::PathBuf;
+
+/// Plan the files of a METS/ALTO package
+///
+/// `ocr` holds the word boxes of pages that have them and `sizes` the
+/// image size of each page in pixels.
+pub fn plan_mets_package(
+    manifest: &ScanSetManifest,
+    artifacts: &[PageArtifact],
+    ocr: &HashMap<PageId, OcrOutput>,
+    sizes: &HashMap<PageId, (u32, u32)>,
+) -> Vec<RepoFile> {
+    let mut files = Vec::new();
+    let mut pages = Vec::new();
+
+    for (idx, artifact) in artifacts.iter().enumerate() {
+        let number = idx + 1;
+        let ext = artifact
+            .raw_image_path
+            .extension()
+            .map(|e| e.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "jpg".to_string());
+        let image = format!("images/page-{:03}.{}", number, ext);
+        let alto = format!("alto/page-{:03}.xml", number);
+
+        let content = page_content(artifact, ocr);
+        let xml = alto_page(number, &image, sizes.get(&artifact.id).copied(), content);
+
+        files.push(RepoFile {
+            path: PathBuf::from(&image),
+            content: RepoContent::CopyFrom(artifact.raw_image_path.clone()),
+        });
+        files.push(RepoFile::text(&alto, xml));
+        pages.push((image, alto, image_mime(&ext)));
+    }
+
+    files.insert(
+        0,
+        RepoFile::text("mets.xml", mets_document(manifest, &pages)),
+    );
+    files
+}
+
+/// The METS document: descriptive title, file groups and physical page order
+fn mets_document(manifest: &ScanSetManifest, pages: &[(String, String, &str)]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<mets:mets xmlns:mets=\"http://www.loc.gov/METS/\" \
+         xmlns:mods=\"http://www.loc.gov/mods/v3\" \
+         xmlns:xlink=\"http://www.w3.org/1999/xlink\" OBJID=\"{}\">",
+        manifest.scan_set_id.0
+    );
+    let _ = writeln!(
+        xml,
+        "  <mets:metsHdr CREATEDATE=\"{}\">\n    <mets:agent ROLE=\"CREATOR\" TYPE=\"OTHER\" \
+         OTHERTYPE=\"SOFTWARE\"><mets:name>scan3data {}</mets:name></mets:agent>\n  </mets:metsHdr>",
+        html_escape(&manifest.created_at),
+        env!("CARGO_PKG_VERSION")
+    );
+    let _ = writeln!(
+        xml,
+        "  <mets:dmdSec ID=\"DMD1\">\n    <mets:mdWrap MDTYPE=\"MODS\"><mets:xmlData>\n      \
+         <mods:mods><mods:titleInfo><mods:title>{}</mods:title></mods:titleInfo></mods:mods>\n    \
+         </mets:xmlData></mets:mdWrap>\n  </mets:dmdSec>",
+        html_escape(&manifest.name)
+    );
+
+    xml.push_str("  <mets:fileSec>\n    <mets:fileGrp USE=\"IMAGE\">\n");
+    for (idx, (image, _, mime)) in pages.iter().enumerate() {
+        let _ = writeln!(
+            xml,
+            "      <mets:file ID=\"IMG_{0:03}\" MIMETYPE=\"{1}\"><mets:FLocat LOCTYPE=\"URL\" \
+             xlink:href=\"{2}\"/></mets:file>",
+            idx + 1,
+            mime,
+            image
+        );
+    }
+    xml.push_str("    </mets:fileGrp>\n    <mets:fileGrp USE=\"FULLTEXT\">\n");
+    for (idx, (_, alto, _)) in pages.iter().enumerate() {
+        let _ = writeln!(
+            xml,
+            "      <mets:file ID=\"ALTO_{0:03}\" MIMETYPE=\"text/xml\"><mets:FLocat LOCTYPE=\"URL\" \
+             xlink:href=\"{1}\"/></mets:file>",
+            idx + 1,
+            alto
+        );
+    }
+    xml.push_str("    </mets:fileGrp>\n  </mets:fileSec>\n");
+
+    xml.push_str("  <mets:structMap TYPE=\"PHYSICAL\">\n    <mets:div TYPE=\"physSequence\" DMDID=\"DMD1\">\n");
+    for idx in 0..pages.len() {
+        let _ = writeln!(
+            xml,
+            "      <mets:div TYPE=\"page\" ORDER=\"{0}\">\n        <mets:fptr FILEID=\"IMG_{0:03}\"/>\n        \
+             <mets:fptr FILEID=\"ALTO_{0:03}\"/>\n      </mets:div>",
+            idx + 1
+        );
+    }
+    xml.push_str("    </mets:div>\n  </mets:structMap>\n</mets:mets>\n");
+    xml
+}
+
+/// MIME type for an image extension
+fn image_mime(ext: &str) -> &'static str {
+    match ext.to_ascii_lowercase().as_str() {
+        "png" => "image/png",
+        "tif" | "tiff" => "image/tiff",
+        _ => "image/jpeg",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ocr::OcrWord;
+    use crate::types::{ArtifactKind, PageMetadata, ScanSetId};
+
+    fn page(text: &str) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/0123456789abcdef.png"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some(text.to_string()),
+            metadata: PageMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_mets_package() {
+        let manifest = ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "Box <3>".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            image_count: 2,
+            original_file_count: 2,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        };
+        let pages = vec![page("X = 1"), page("END")];
+        let ocr = HashMap::from([(
+            pages[0].id,
+            OcrOutput {
+                text: "X = 1".to_string(),
+                words: vec![OcrWord {
+                    text: "X".to_string(),
+                    block: 1,
+                    paragraph: 1,
+                    line: 1,
+                    left: 5,
+                    top: 5,
+                    width: 10,
+                    height: 10,
+                    confidence: 80.0,
+                }],
+            },
+        )]);
+        let files = plan_mets_package(&manifest, &pages, &ocr, &HashMap::new());
+
+        let paths: Vec<String> = files.iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "mets.xml",
+                "images/page-001.png",
+                "alto/page-001.xml",
+                "images/page-002.png",
+                "alto/page-002.xml"
+            ]
+        );
+        let RepoContent::Text(mets) = &files[0].content else {
+            panic!("expected text");
+        };
+        assert!(mets.contains("<mods:title>Box &lt;3&gt;</mods:title>"));
+        assert!(mets.contains("MIMETYPE=\"image/png\""));
+        assert!(mets.contains("<mets:fptr FILEID=\"ALTO_002\"/>"));
+
+        let RepoContent::Text(first) = &files[2].content else {
+            panic!("expected text");
+        };
+        assert!(first.contains("HPOS=\"5\""));
+        let RepoContent::Text(second) = &files[4].content else {
+            panic!("expected text");
+        };
+        assert!(second.contains("<String CONTENT=\"END\"/>"));
+    }
+}
@@ -0,0 +1,143 @@
+//! Conversion of reconstructed artifacts to emulator formats
+//!
+//! Emulator formats are [`exporter::Exporter`]s looked up by name in an
+//! [`exporter::ExporterRegistry`]. Card decks can be accompanied by a simh
+//! run script ([`simh`]).
+//!
+//! Whole scan sets can also be exported as a preservation repository
+//! layout ([`repository`]), a Markdown transcript ([`markdown`]), a
+//! CSV/TSV table of artifact metadata ([`metadata`]), a IIIF manifest
+//! ([`iiif`]), a METS/ALTO archival package ([`mets`], [`alto`]), ALTO or
+//! hOCR files per page ([`page_ocr`], [`hocr`]), a searchable PDF
+//! ([`pdf`]) or a BagIt preservation bundle ([`bagit`]). Recovered
+//! programs can be written as a source tree ([`sources`]).
+//! Each scan can be described by a sidecar file next to it ([`sidecar`]).
+
+pub mod alto;
+pub mod bagit;
+pub mod exporter;
+pub mod hocr;
+pub mod iiif;
+pub mod markdown;
+pub mod metadata;
+pub mod mets;
+pub mod page_ocr;
+pub mod pdf;
+pub mod repository;
+pub mod sidecar;
+pub mod simh;
+pub mod sources;
+
+use crate::types::{EmulatorCard, EmulatorLine, EmulatorOutput, SourceListing};
+
+/// Card width in columns
+pub const CARD_COLUMNS: usize = 80;
+
+/// Convert a source listing to the emulator listing format
+///
+/// Lines without a sequence number are numbered by position.
+pub fn listing_to_emulator(listing: &SourceListing) -> EmulatorOutput {
+    EmulatorOutput::Listing {
+        language: listing.language.clone(),
+        lines: listing
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| EmulatorLine {
+                line_no: line.line_no.unwrap_or(idx as u32 + 1),
+                text: line.text.clone(),
+            })
+            .collect(),
+    }
+}
+
+/// Render a source listing as plain `.lst` text
+///
+/// Each line is its number, right-aligned in five columns, two spaces and
+/// the text, numbered like [`listing_to_emulator`].
+pub fn listing_to_text(listing: &SourceListing) -> String {
+    listing
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(idx, line)| {
+            let line_no = line.line_no.unwrap_or(idx as u32 + 1);
+            format!("{:>5}  {}\n", line_no, line.text.trim_end())
+        })
+        .collect()
+}
+
+/// Convert a source listing to a card deck, one card per line
+///
+/// Each card is padded or truncated to exactly 80 columns.
+pub fn listing_to_card_deck(listing: &SourceListing) -> EmulatorOutput {
+    EmulatorOutput::CardDeck {
+        machine: "IBM1130".to_string(),
+        cards: listing
+            .lines
+            .iter()
+            .enumerate()
+            .map(|(idx, line)| EmulatorCard {
+                seq: line.line_no.unwrap_or(idx as u32 + 1),
+                text: format!("{:<width$.width$}", line.text, width = CARD_COLUMNS),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SourceLine;
+
+    fn listing() -> SourceListing {
+        SourceListing {
+            name: None,
+            language: "fortran".to_string(),
+            pages: Vec::new(),
+            lines: vec![
+                SourceLine {
+                    line_no: None,
+                    text: "      X = 1".to_string(),
+                    inferred: false,
+                },
+                SourceLine {
+                    line_no: Some(20),
+                    text: format!("{:<72}PROG0020EXTRA", "      END"),
+                    inferred: false,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_listing_to_emulator() {
+        let EmulatorOutput::Listing { language, lines } = listing_to_emulator(&listing()) else {
+            panic!("expected listing output");
+        };
+        assert_eq!(language, "fortran");
+        assert_eq!(lines[0].line_no, 1);
+        assert_eq!(lines[1].line_no, 20);
+    }
+
+    #[test]
+    fn test_listing_to_text() {
+        let text = listing_to_text(&listing());
+        assert_eq!(
+            text,
+            format!(
+                "    1        X = 1\n   20  {:<72}PROG0020EXTRA\n",
+                "      END"
+            )
+        );
+    }
+
+    #[test]
+    fn test_listing_to_card_deck_pads_to_80_columns() {
+        let EmulatorOutput::CardDeck { cards, .. } = listing_to_card_deck(&listing()) else {
+            panic!("expected card deck output");
+        };
+        assert!(cards.iter().all(|c| c.text.chars().count() == CARD_COLUMNS));
+        assert!(cards[1].text.ends_with("PROG0020"));
+    }
+}
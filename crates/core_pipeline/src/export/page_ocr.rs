@@ -0,0 +1,113 @@
+//! Positional OCR of every page, as ALTO or hOCR files
+//!
+//! Digital library pipelines that do not take a METS package ingest a
+//! directory of page images, each with an OCR file of the same name:
+//!
+//! ```text
+//! alto/
+//! |-- 246d5d942fde3ba9.jpg
+//! `-- 246d5d942fde3ba9.xml    (246d5d942fde3ba9.hocr for hOCR)
+//! ```
+//!
+//! Files use the OCR word boxes where the analysis stored them, and fall
+//! back to the page text without coordinates otherwise, as in
+//! [`super::mets`].
+
+use super::alto::{alto_page, page_content};
+use super::hocr::hocr_page;
+use super::repository::{RepoContent, RepoFile};
+use crate::ocr::OcrOutput;
+use crate::types::{PageArtifact, PageId};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Positional OCR format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageOcrFormat {
+    /// ALTO v4 XML
+    Alto,
+    /// hOCR (XHTML)
+    Hocr,
+}
+
+impl PageOcrFormat {
+    /// Extension of the OCR files
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Alto => "xml",
+            Self::Hocr => "hocr",
+        }
+    }
+}
+
+/// Plan a page image and an OCR file for every artifact
+///
+/// `ocr` holds the word boxes of pages that have them and `sizes` the
+/// image size of each page in pixels.
+pub fn plan_page_ocr(
+    artifacts: &[PageArtifact],
+    ocr: &HashMap<PageId, OcrOutput>,
+    sizes: &HashMap<PageId, (u32, u32)>,
+    format: PageOcrFormat,
+) -> Vec<RepoFile> {
+    let mut files = Vec::new();
+    for (idx, artifact) in artifacts.iter().enumerate() {
+        let number = idx + 1;
+        let image = artifact
+            .raw_image_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| format!("page-{:03}.jpg", number));
+        let content = page_content(artifact, ocr);
+        let size = sizes.get(&artifact.id).copied();
+        let document = match format {
+            PageOcrFormat::Alto => alto_page(number, &image, size, content),
+            PageOcrFormat::Hocr => hocr_page(number, &image, size, content),
+        };
+
+        let ocr_file = PathBuf::from(&image).with_extension(format.extension());
+        files.push(RepoFile {
+            path: PathBuf::from(&image),
+            content: RepoContent::CopyFrom(artifact.raw_image_path.clone()),
+        });
+        files.push(RepoFile::text(ocr_file, document));
+    }
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageMetadata, ScanSetId};
+
+    #[test]
+    fn test_plan_page_ocr() {
+        let page = PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/0123456789abcdef.png"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some("END".to_string()),
+            metadata: PageMetadata::default(),
+        };
+        let sizes = HashMap::from([(page.id, (640, 480))]);
+        let pages = [page];
+
+        let files = plan_page_ocr(&pages, &HashMap::new(), &sizes, PageOcrFormat::Hocr);
+        let paths: Vec<String> = files.iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(paths, ["0123456789abcdef.png", "0123456789abcdef.hocr"]);
+        let RepoContent::Text(hocr) = &files[1].content else {
+            panic!("expected text");
+        };
+        assert!(hocr.contains("bbox 0 0 640 480"));
+        assert!(hocr.contains(">END</span>"));
+
+        let files = plan_page_ocr(&pages, &HashMap::new(), &sizes, PageOcrFormat::Alto);
+        assert_eq!(files[1].path, PathBuf::from("0123456789abcdef.xml"));
+        let RepoContent::Text(alto) = &files[1].content else {
+            panic!("expected text");
+        };
+        assert!(alto.contains("<fileName>0123456789abcdef.png</fileName>"));
+    }
+}
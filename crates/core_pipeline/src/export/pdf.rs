@@ -0,0 +1,474 @@
+//! Searchable PDF of a scan set
+//!
+//! Each scan becomes a page showing the image, with the corrected text
+//! laid over it in invisible type (text render mode 3), one line at a
+//! time. Viewers find, select and copy the text while showing the scan,
+//! as for a PDF from an OCR-capable scanner.
+//!
+//! Lines are placed on the OCR line boxes ([`OcrWord`]) when the corrected
+//! text has as many lines as OCR found; otherwise they are spread evenly
+//! over the area the words cover, or over the whole page without word
+//! boxes. The type is Courier, stretched horizontally to the width of each
+//! line, so a selection covers about the characters under it.
+//!
+//! JPEG scans (every raw image written by ingest) are embedded as they
+//! are; other images are encoded as JPEG first.
+
+use crate::error::{Error, IoContext, Result};
+use crate::ocr::OcrWord;
+use image::codecs::jpeg::{JpegDecoder, JpegEncoder};
+use image::{ColorType, ImageDecoder, ImageFormat};
+use std::fmt::Write as _;
+use std::io::{Cursor, Write};
+use std::path::Path;
+
+/// Resolution scans are assumed to have, in pixels per inch; gives the
+/// page size
+pub const PDF_DPI: f32 = 300.0;
+
+/// JPEG quality of images that are not JPEG already
+const JPEG_QUALITY: u8 = 90;
+
+/// Width of a Courier character, in text space units per point of size
+const COURIER_ADVANCE: f32 = 0.6;
+
+/// Height of the baseline above the bottom of a line box, as a fraction
+/// of the box height, leaving room for descenders
+const BASELINE: f32 = 0.2;
+
+/// Catalog, page tree, font and document information objects
+const CATALOG: usize = 1;
+const PAGES: usize = 2;
+const FONT: usize = 3;
+const INFO: usize = 4;
+
+/// A page image, JPEG-encoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct PdfImage {
+    /// JPEG data
+    pub jpeg: Vec<u8>,
+    /// Width in pixels
+    pub width: u32,
+    /// Height in pixels
+    pub height: u32,
+    /// Grayscale rather than RGB
+    pub gray: bool,
+}
+
+impl PdfImage {
+    /// Read an image file, keeping JPEG data as it is
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let bytes =
+            std::fs::read(path).io_context(|| format!("Failed to read {}", path.display()))?;
+        let load_error = |source| Error::ImageLoad {
+            path: path.to_path_buf(),
+            source,
+        };
+        if image::guess_format(&bytes).ok() == Some(ImageFormat::Jpeg) {
+            let decoder = JpegDecoder::new(Cursor::new(&bytes)).map_err(load_error)?;
+            let (width, height) = decoder.dimensions();
+            // CMYK and other JPEGs are re-encoded below
+            if let ColorType::L8 | ColorType::Rgb8 = decoder.color_type() {
+                return Ok(Self {
+                    gray: decoder.color_type() == ColorType::L8,
+                    jpeg: bytes,
+                    width,
+                    height,
+                });
+            }
+        }
+
+        let image = crate::image_loader::load_image(path, &Default::default())?.to_rgb8();
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, JPEG_QUALITY)
+            .encode_image(&image)
+            .map_err(|source| Error::ImageEncode {
+                message: format!("Failed to encode JPEG of {}", path.display()),
+                source,
+            })?;
+        Ok(Self {
+            jpeg,
+            width: image.width(),
+            height: image.height(),
+            gray: false,
+        })
+    }
+}
+
+/// One line of text and the box it covers, in image pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLine {
+    /// Text of the line
+    pub text: String,
+    /// Left edge
+    pub left: f32,
+    /// Top edge
+    pub top: f32,
+    /// Width
+    pub width: f32,
+    /// Height
+    pub height: f32,
+}
+
+/// Place the lines of a page's text on an image of `size` pixels
+///
+/// `words` are the OCR word boxes of the page, if any (see the module
+/// documentation).
+pub fn page_lines(text: &str, words: &[OcrWord], size: (u32, u32)) -> Vec<TextLine> {
+    let ocr_lines = ocr_line_boxes(words);
+    let text_lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    if !ocr_lines.is_empty() && ocr_lines.len() == text_lines.len() {
+        return text_lines
+            .into_iter()
+            .zip(ocr_lines)
+            .map(|(text, (left, top, width, height))| TextLine {
+                text: text.to_string(),
+                left,
+                top,
+                width,
+                height,
+            })
+            .collect();
+    }
+
+    // Evenly spaced over the text area, keeping indentation and blank
+    // lines
+    let (left, top, width, height) = ocr_lines
+        .into_iter()
+        .reduce(|a, b| {
+            let (left, top) = (a.0.min(b.0), a.1.min(b.1));
+            let right = (a.0 + a.2).max(b.0 + b.2);
+            let bottom = (a.1 + a.3).max(b.1 + b.3);
+            (left, top, right - left, bottom - top)
+        })
+        .unwrap_or((0.0, 0.0, size.0 as f32, size.1 as f32));
+    let lines: Vec<&str> = text.trim_end().lines().map(str::trim_end).collect();
+    let columns = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    if columns == 0 {
+        return Vec::new();
+    }
+    let pitch = height / lines.len() as f32;
+    let char_width = width / columns as f32;
+    lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(idx, line)| TextLine {
+            text: line.to_string(),
+            left,
+            top: top + idx as f32 * pitch,
+            width: line.chars().count() as f32 * char_width,
+            height: pitch,
+        })
+        .collect()
+}
+
+/// Boxes (left, top, width, height) of the lines of OCR words, in order
+fn ocr_line_boxes(words: &[OcrWord]) -> Vec<(f32, f32, f32, f32)> {
+    let mut boxes: Vec<((u32, u32, u32), [u32; 4])> = Vec::new();
+    for word in words {
+        let line = (word.block, word.paragraph, word.line);
+        let (right, bottom) = (word.left + word.width, word.top + word.height);
+        match boxes.last_mut() {
+            Some((last, edges)) if *last == line => {
+                edges[0] = edges[0].min(word.left);
+                edges[1] = edges[1].min(word.top);
+                edges[2] = edges[2].max(right);
+                edges[3] = edges[3].max(bottom);
+            }
+            _ => boxes.push((line, [word.left, word.top, right, bottom])),
+        }
+    }
+    boxes
+        .into_iter()
+        .map(|(_, [left, top, right, bottom])| {
+            (
+                left as f32,
+                top as f32,
+                (right - left) as f32,
+                (bottom - top) as f32,
+            )
+        })
+        .collect()
+}
+
+/// Writes a searchable PDF page by page, so only one image is held at a
+/// time
+pub struct SearchablePdf<W: Write> {
+    out: W,
+    written: usize,
+    /// Byte offset of each object, by object number - 1
+    offsets: Vec<Option<usize>>,
+    /// Object numbers of the pages
+    pages: Vec<usize>,
+}
+
+impl<W: Write> SearchablePdf<W> {
+    /// Start a PDF document
+    pub fn new(out: W) -> Result<Self> {
+        let mut pdf = Self {
+            out,
+            written: 0,
+            offsets: vec![None; INFO],
+            pages: Vec::new(),
+        };
+        // The binary comment marks the file as binary for transfer tools
+        pdf.write(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n")?;
+        pdf.object(
+            FONT,
+            b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier \
+              /Encoding /WinAnsiEncoding >>",
+        )?;
+        Ok(pdf)
+    }
+
+    /// Add a page showing an image, with lines of invisible text placed in
+    /// image pixels
+    pub fn add_page(&mut self, image: &PdfImage, lines: &[TextLine]) -> Result<()> {
+        let page = self.reserve();
+        let contents = self.reserve();
+        let xobject = self.reserve();
+        let scale = 72.0 / PDF_DPI;
+        let (width, height) = (image.width as f32 * scale, image.height as f32 * scale);
+
+        let mut dict = format!(
+            "<< /Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /{} \
+             /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>",
+            image.width,
+            image.height,
+            if image.gray {
+                "DeviceGray"
+            } else {
+                "DeviceRGB"
+            },
+            image.jpeg.len()
+        );
+        self.stream(xobject, &dict, &image.jpeg)?;
+
+        let mut content = format!("q {:.2} 0 0 {:.2} 0 0 cm /Im0 Do Q\n", width, height);
+        if !lines.is_empty() {
+            content.push_str("BT\n3 Tr\n");
+            for line in lines {
+                let chars = line.text.chars().count();
+                if chars == 0 || line.height <= 0.0 {
+                    continue;
+                }
+                let size = line.height * scale;
+                let stretch = 100.0 * line.width * scale / (chars as f32 * COURIER_ADVANCE * size);
+                let baseline = height - (line.top + line.height) * scale + BASELINE * size;
+                let _ = writeln!(
+                    content,
+                    "/F1 {:.2} Tf {:.2} Tz 1 0 0 1 {:.2} {:.2} Tm <{}> Tj",
+                    size,
+                    stretch,
+                    line.left * scale,
+                    baseline,
+                    win_ansi_hex(&line.text)
+                );
+            }
+            content.push_str("ET\n");
+        }
+        dict = format!("<< /Length {} >>", content.len());
+        self.stream(contents, &dict, content.as_bytes())?;
+
+        let page_dict = format!(
+            "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {:.2} {:.2}] \
+             /Resources << /Font << /F1 {} 0 R >> /XObject << /Im0 {} 0 R >> >> \
+             /Contents {} 0 R >>",
+            PAGES, width, height, FONT, xobject, contents
+        );
+        self.object(page, page_dict.as_bytes())?;
+        self.pages.push(page);
+        Ok(())
+    }
+
+    /// Write the page tree, the document information (`title`) and the
+    /// cross-reference table, and return the output
+    pub fn finish(mut self, title: &str) -> Result<W> {
+        let kids: Vec<String> = self.pages.iter().map(|p| format!("{} 0 R", p)).collect();
+        let pages = format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            self.pages.len()
+        );
+        self.object(PAGES, pages.as_bytes())?;
+        let catalog = format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES);
+        self.object(CATALOG, catalog.as_bytes())?;
+        let info = format!("<< /Title <{}> /Producer (scan3data) >>", utf16_hex(title));
+        self.object(INFO, info.as_bytes())?;
+
+        let xref = self.written;
+        let mut table = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len() + 1);
+        for offset in &self.offsets {
+            let _ = writeln!(table, "{:010} 00000 n ", offset.unwrap_or(0));
+        }
+        let _ = write!(
+            table,
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len() + 1,
+            CATALOG,
+            INFO,
+            xref
+        );
+        self.write(table.as_bytes())?;
+        self.out
+            .flush()
+            .io_context(|| "Failed to write PDF".to_string())?;
+        Ok(self.out)
+    }
+
+    /// Number a new object
+    fn reserve(&mut self) -> usize {
+        self.offsets.push(None);
+        self.offsets.len()
+    }
+
+    fn object(&mut self, number: usize, body: &[u8]) -> Result<()> {
+        self.offsets[number - 1] = Some(self.written);
+        self.write(format!("{} 0 obj\n", number).as_bytes())?;
+        self.write(body)?;
+        self.write(b"\nendobj\n")
+    }
+
+    fn stream(&mut self, number: usize, dict: &str, data: &[u8]) -> Result<()> {
+        let mut body = Vec::with_capacity(dict.len() + data.len() + 20);
+        body.extend_from_slice(dict.as_bytes());
+        body.extend_from_slice(b"\nstream\n");
+        body.extend_from_slice(data);
+        body.extend_from_slice(b"\nendstream");
+        self.object(number, &body)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.out
+            .write_all(bytes)
+            .io_context(|| "Failed to write PDF".to_string())?;
+        self.written += bytes.len();
+        Ok(())
+    }
+}
+
+/// Hex string of text in WinAnsiEncoding, the encoding of the standard
+/// fonts; characters it lacks become `?`
+fn win_ansi_hex(text: &str) -> String {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x20..=0x7e | 0xa0..=0xff => c as u32 as u8,
+            0x09 => b' ',
+            _ => b'?',
+        })
+        .map(|b| format!("{:02X}", b))
+        .collect()
+}
+
+/// Hex string of text in UTF-16BE with a byte order mark, for document
+/// information
+fn utf16_hex(text: &str) -> String {
+    std::iter::once(0xfeff)
+        .chain(text.encode_utf16())
+        .map(|unit| format!("{:04X}", unit))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, line: u32, left: u32, top: u32, width: u32) -> OcrWord {
+        OcrWord {
+            text: text.to_string(),
+            block: 1,
+            paragraph: 1,
+            line,
+            left,
+            top,
+            width,
+            height: 30,
+            confidence: 90.0,
+        }
+    }
+
+    #[test]
+    fn test_page_lines() {
+        let words = [
+            word("D0", 1, 100, 200, 40),
+            word("10", 1, 160, 202, 40),
+            word("END", 2, 100, 250, 60),
+        ];
+        // As many lines as OCR found: each goes on its box
+        let lines = page_lines("      DO 10\n\n      END\n", &words, (1000, 800));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text, "DO 10");
+        assert_eq!(
+            (lines[0].left, lines[0].top, lines[0].width, lines[0].height),
+            (100.0, 200.0, 100.0, 32.0)
+        );
+        assert_eq!(lines[1].text, "END");
+
+        // Otherwise spread over the area of the words
+        let lines = page_lines("A\nB = 1\nEND", &words, (1000, 800));
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            (lines[0].top, lines[2].top),
+            (200.0, 200.0 + 2.0 * (80.0 / 3.0))
+        );
+        assert_eq!(lines[1].width, 100.0);
+
+        // Or over the page, without word boxes
+        let lines = page_lines("  X\n\nY\n", &[], (300, 300));
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].text.as_str(), lines[0].width), ("  X", 300.0));
+        assert_eq!((lines[1].top, lines[1].height), (200.0, 100.0));
+        assert!(page_lines("", &[], (300, 300)).is_empty());
+    }
+
+    #[test]
+    fn test_searchable_pdf() {
+        let image = PdfImage {
+            jpeg: vec![0xff, 0xd8, 0xff, 0xd9],
+            width: 600,
+            height: 300,
+            gray: true,
+        };
+        let lines = [TextLine {
+            text: "CALL EXIT ¤".to_string(),
+            left: 0.0,
+            top: 0.0,
+            width: 330.0,
+            height: 50.0,
+        }];
+        let mut pdf = SearchablePdf::new(Vec::new()).unwrap();
+        pdf.add_page(&image, &lines).unwrap();
+        pdf.add_page(&image, &[]).unwrap();
+        let bytes = pdf.finish("Box 3").unwrap();
+        let contains = |needle: &str| bytes.windows(needle.len()).any(|w| w == needle.as_bytes());
+
+        assert!(bytes.starts_with(b"%PDF-1.4\n"));
+        assert!(contains("/MediaBox [0 0 144.00 72.00]"));
+        assert!(contains("/Count 2"));
+        assert!(contains("/ColorSpace /DeviceGray"));
+        assert!(contains(
+            "3 Tr\n/F1 12.00 Tf 100.00 Tz 1 0 0 1 0.00 62.40 Tm <43414C4C204558495420A4> Tj"
+        ));
+        assert!(bytes.ends_with(b"%%EOF\n"));
+
+        // Every cross-reference entry points at its object
+        let tail = String::from_utf8_lossy(&bytes[bytes.len() - 40..]).into_owned();
+        let startxref: usize = tail.lines().rev().nth(1).unwrap().parse().unwrap();
+        let xref = String::from_utf8(bytes[startxref..].to_vec()).unwrap();
+        assert!(xref.starts_with("xref\n0 11\n"));
+        for (number, entry) in xref.lines().skip(3).take(10).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            let object = format!("{} 0 obj\n", number + 1);
+            assert!(
+                bytes[offset..].starts_with(object.as_bytes()),
+                "object {}",
+                number + 1
+            );
+        }
+    }
+}
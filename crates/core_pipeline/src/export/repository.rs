@@ -0,0 +1,430 @@
+//! Repository export layout
+//!
+//! Writes a scan set's reconstructed documents as a directory tree ready to
+//! commit to a preservation repository:
+//!
+//! ```text
+//! repo/
+//! |-- README.md            # Index of documents with scan set provenance
+//! `-- <document>/
+//!     |-- README.md        # Pages, original files, notes and issues
+//!     |-- <document>.for   # Source text (.for, .asm, .fth or .txt)
+//!     |-- listing.txt      # Page text as scanned, pages separated by form feeds
+//!     |-- deck.json        # 80-column card deck for the emulator
+//!     `-- pages/           # Page images (unless referenced in place)
+//! ```
+//!
+//! Object decks get a `deck.json` of their parsed cards and run listings an
+//! `output.txt`. The export is planned as a list of files first, so the
+//! layout can be checked without touching the disk.
+
+use super::listing_to_card_deck;
+use crate::error::{IoContext, Result};
+use crate::types::{HighLevelArtifact, PageArtifact, PageId, ScanSetManifest, SourceListing};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How page images are included
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ImageMode {
+    /// Copy each page image into the document's `pages/` directory
+    #[default]
+    Copy,
+    /// List each image's path in the scan set and its hash, without copying
+    Reference,
+}
+
+/// Contents of a file in the exported repository
+#[derive(Debug, Clone, PartialEq)]
+pub enum RepoContent {
+    /// Generated text
+    Text(String),
+    /// Copy of a file in the scan set (path relative to the scan set)
+    CopyFrom(PathBuf),
+}
+
+/// A file in the exported repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoFile {
+    /// Path relative to the repository root
+    pub path: PathBuf,
+    /// What to write there
+    pub content: RepoContent,
+}
+
+impl RepoFile {
+    /// A generated text file
+    pub fn text(path: impl Into<PathBuf>, text: String) -> Self {
+        Self {
+            path: path.into(),
+            content: RepoContent::Text(text),
+        }
+    }
+}
+
+/// Plan the files of a repository export
+pub fn plan_repository(
+    manifest: &ScanSetManifest,
+    artifacts: &[PageArtifact],
+    documents: &[HighLevelArtifact],
+    images: ImageMode,
+) -> Result<Vec<RepoFile>> {
+    let pages: HashMap<PageId, &PageArtifact> = artifacts.iter().map(|a| (a.id, a)).collect();
+    let mut used = HashSet::new();
+    let mut files = Vec::new();
+    let mut index = Vec::new();
+
+    for (idx, document) in documents.iter().enumerate() {
+        let (kind, name, page_ids) = match document {
+            HighLevelArtifact::SourceListing(l) => {
+                let name = l
+                    .name
+                    .clone()
+                    .unwrap_or(format!("{} {}", l.language, idx + 1));
+                (l.language.clone(), name, l.pages.clone())
+            }
+            HighLevelArtifact::ObjectDeck(d) => ("object deck".to_string(), d.name.clone(), vec![]),
+            HighLevelArtifact::RunListing(r) => (
+                "run output".to_string(),
+                format!("run {}", idx + 1),
+                r.pages.clone(),
+            ),
+            // Unresolved mixtures have no file form yet
+            HighLevelArtifact::Mixed(_) => continue,
+        };
+        let dir = PathBuf::from(unique_slug(&name, &mut used));
+        let doc_pages: Vec<&PageArtifact> = page_ids
+            .iter()
+            .filter_map(|id| pages.get(id).copied())
+            .collect();
+
+        match document {
+            HighLevelArtifact::SourceListing(listing) => {
+                let file = format!("{}.{}", dir.display(), source_extension(&listing.language));
+                files.push(RepoFile::text(dir.join(file), source_text(listing)));
+                files.push(RepoFile::text(
+                    dir.join("listing.txt"),
+                    listing_text(&doc_pages),
+                ));
+                let deck = serde_json::to_string_pretty(&listing_to_card_deck(listing))?;
+                files.push(RepoFile::text(dir.join("deck.json"), deck));
+            }
+            HighLevelArtifact::ObjectDeck(deck) => {
+                let json = serde_json::to_string_pretty(&deck.object_cards)?;
+                files.push(RepoFile::text(dir.join("deck.json"), json));
+            }
+            HighLevelArtifact::RunListing(run) => {
+                let mut text = run.lines.join("\n");
+                text.push('\n');
+                files.push(RepoFile::text(dir.join("output.txt"), text));
+            }
+            HighLevelArtifact::Mixed(_) => unreachable!("skipped above"),
+        }
+
+        let mut image_paths = Vec::new();
+        if images == ImageMode::Copy {
+            for (n, page) in doc_pages.iter().enumerate() {
+                let ext = page
+                    .raw_image_path
+                    .extension()
+                    .map(|e| e.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "img".to_string());
+                let path = PathBuf::from("pages").join(format!("page-{:03}.{}", n + 1, ext));
+                files.push(RepoFile {
+                    path: dir.join(&path),
+                    content: RepoContent::CopyFrom(page.raw_image_path.clone()),
+                });
+                image_paths.push(path);
+            }
+        }
+        files.push(RepoFile::text(
+            dir.join("README.md"),
+            document_readme(manifest, &name, &kind, &doc_pages, &image_paths),
+        ));
+        index.push((dir, name, kind, doc_pages.len()));
+    }
+
+    let mut readme = format!("# {}\n\n", manifest.name);
+    readme.push_str("| Document | Type | Pages |\n|---|---|---|\n");
+    for (dir, name, kind, page_count) in &index {
+        let _ = writeln!(
+            readme,
+            "| [{}]({}/README.md) | {} | {} |",
+            name,
+            dir.display(),
+            kind,
+            page_count
+        );
+    }
+    readme.push_str("\n## Provenance\n\n");
+    let _ = writeln!(
+        readme,
+        "- Scan set: {} (`{}`)",
+        manifest.name, manifest.scan_set_id.0
+    );
+    let _ = writeln!(readme, "- Scanned: {}", manifest.created_at);
+    let _ = writeln!(
+        readme,
+        "- Images: {} unique of {} files ingested",
+        manifest.image_count, manifest.original_file_count
+    );
+    let _ = writeln!(
+        readme,
+        "- Generated by scan3data {}",
+        env!("CARGO_PKG_VERSION")
+    );
+    files.insert(0, RepoFile::text("README.md", readme));
+    Ok(files)
+}
+
+/// Write planned files under `output_dir`, copying images from the scan set
+pub fn write_repository(scan_set_dir: &Path, output_dir: &Path, files: &[RepoFile]) -> Result<()> {
+    for file in files {
+        let path = output_dir.join(&file.path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .io_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        match &file.content {
+            RepoContent::Text(text) => fs::write(&path, text),
+            RepoContent::CopyFrom(source) => fs::copy(scan_set_dir.join(source), &path).map(|_| ()),
+        }
+        .io_context(|| format!("Failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// File extension for a source language
+pub(crate) fn source_extension(language: &str) -> &'static str {
+    match language {
+        "fortran" => "for",
+        "assembler" => "asm",
+        "forth" => "fth",
+        _ => "txt",
+    }
+}
+
+/// Directory name for a document: lowercase letters, digits and dashes,
+/// numbered if another document already uses it
+pub(crate) fn unique_slug(name: &str, used: &mut HashSet<String>) -> String {
+    let mut slug = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let base = match slug.trim_end_matches('-') {
+        "" => "document".to_string(),
+        trimmed => trimmed.to_string(),
+    };
+    let mut candidate = base.clone();
+    let mut n = 2;
+    while !used.insert(candidate.clone()) {
+        candidate = format!("{}-{}", base, n);
+        n += 1;
+    }
+    candidate
+}
+
+/// Reconstructed source, one line per card, trailing blanks removed
+pub(crate) fn source_text(listing: &SourceListing) -> String {
+    listing
+        .lines
+        .iter()
+        .map(|line| format!("{}\n", line.text.trim_end()))
+        .collect()
+}
+
+/// Page text as scanned, pages separated by form feeds
+fn listing_text(pages: &[&PageArtifact]) -> String {
+    pages
+        .iter()
+        .map(|page| page.content_text.as_deref().unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n\u{c}\n")
+        + "\n"
+}
+
+/// Per-document README with page provenance
+fn document_readme(
+    manifest: &ScanSetManifest,
+    name: &str,
+    kind: &str,
+    pages: &[&PageArtifact],
+    image_paths: &[PathBuf],
+) -> String {
+    let mut readme = format!("# {}\n\n", name);
+    let _ = writeln!(readme, "- Type: {}", kind);
+    let _ = writeln!(
+        readme,
+        "- Scan set: {} (`{}`)",
+        manifest.name, manifest.scan_set_id.0
+    );
+    if pages.is_empty() {
+        return readme;
+    }
+
+    readme.push_str("\n## Pages\n\n| # | Image | Original files | Confidence | Fixes | Issues |\n");
+    readme.push_str("|---|---|---|---|---|---|\n");
+    for (n, page) in pages.iter().enumerate() {
+        let image = match image_paths.get(n) {
+            Some(path) => format!("[{}]({})", path.display(), path.display()),
+            None => format!(
+                "`{}` (sha256 `{}`)",
+                page.raw_image_path.display(),
+                page.metadata.content_hash
+            ),
+        };
+        let _ = writeln!(
+            readme,
+            "| {} | {} | {} | {:.2} | {} | {} |",
+            n + 1,
+            image,
+            page.metadata.original_filenames.join(", "),
+            page.metadata.confidence,
+            page.metadata.revisions.len(),
+            page.metadata.validation_issues.len()
+        );
+    }
+
+    let notes: Vec<(usize, &String)> = pages
+        .iter()
+        .enumerate()
+        .flat_map(|(n, page)| page.metadata.notes.iter().map(move |note| (n + 1, note)))
+        .collect();
+    if !notes.is_empty() {
+        readme.push_str("\n## Notes\n\n");
+        for (n, note) in notes {
+            let _ = writeln!(readme, "- Page {}: {}", n, note);
+        }
+    }
+    readme
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageMetadata, ScanSetId, SourceLine};
+
+    fn manifest() -> ScanSetManifest {
+        ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "Box 3".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            image_count: 1,
+            original_file_count: 1,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        }
+    }
+
+    fn page() -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/0123456789abcdef.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some("      X = 1   \n".to_string()),
+            metadata: PageMetadata {
+                original_filenames: vec!["scan001.jpg".to_string()],
+                notes: vec!["Vision-corrected OCR".to_string()],
+                ..PageMetadata::default()
+            },
+        }
+    }
+
+    fn listing(page: &PageArtifact, name: &str) -> HighLevelArtifact {
+        HighLevelArtifact::SourceListing(SourceListing {
+            name: Some(name.to_string()),
+            language: "fortran".to_string(),
+            pages: vec![page.id],
+            lines: vec![SourceLine {
+                line_no: None,
+                text: "      X = 1   ".to_string(),
+                inferred: false,
+            }],
+        })
+    }
+
+    fn paths(files: &[RepoFile]) -> Vec<String> {
+        files.iter().map(|f| f.path.display().to_string()).collect()
+    }
+
+    #[test]
+    fn test_plan_repository_layout() {
+        let page = page();
+        let documents = vec![
+            listing(&page, "PAYROLL / MAIN"),
+            listing(&page, "Payroll main"),
+        ];
+        let files = plan_repository(&manifest(), &[page], &documents, ImageMode::Copy).unwrap();
+
+        assert_eq!(
+            paths(&files),
+            vec![
+                "README.md",
+                "payroll-main/payroll-main.for",
+                "payroll-main/listing.txt",
+                "payroll-main/deck.json",
+                "payroll-main/pages/page-001.jpg",
+                "payroll-main/README.md",
+                "payroll-main-2/payroll-main-2.for",
+                "payroll-main-2/listing.txt",
+                "payroll-main-2/deck.json",
+                "payroll-main-2/pages/page-001.jpg",
+                "payroll-main-2/README.md",
+            ]
+        );
+        assert_eq!(
+            files[1].content,
+            RepoContent::Text("      X = 1\n".to_string())
+        );
+        let RepoContent::Text(index) = &files[0].content else {
+            panic!("expected text");
+        };
+        assert!(index.contains("[PAYROLL / MAIN](payroll-main/README.md) | fortran | 1"));
+    }
+
+    #[test]
+    fn test_reference_mode_lists_image_paths() {
+        let page = page();
+        let documents = vec![listing(&page, "MAIN")];
+        let files =
+            plan_repository(&manifest(), &[page], &documents, ImageMode::Reference).unwrap();
+        assert!(!paths(&files).iter().any(|p| p.contains("pages/")));
+        let RepoContent::Text(readme) = &files.last().unwrap().content else {
+            panic!("expected text");
+        };
+        assert!(readme.contains("`images/0123456789abcdef.jpg`"));
+        assert!(readme.contains("scan001.jpg"));
+        assert!(readme.contains("- Page 1: Vision-corrected OCR"));
+    }
+
+    #[test]
+    fn test_write_repository() {
+        let scan_set = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        fs::create_dir_all(scan_set.path().join("images")).unwrap();
+        fs::write(scan_set.path().join("images/a.jpg"), b"JPEG").unwrap();
+        let files = vec![
+            RepoFile::text("doc/README.md", "# doc\n".to_string()),
+            RepoFile {
+                path: PathBuf::from("doc/pages/page-001.jpg"),
+                content: RepoContent::CopyFrom(PathBuf::from("images/a.jpg")),
+            },
+        ];
+        write_repository(scan_set.path(), output.path(), &files).unwrap();
+        assert_eq!(
+            fs::read(output.path().join("doc/pages/page-001.jpg")).unwrap(),
+            b"JPEG"
+        );
+    }
+}
@@ -0,0 +1,190 @@
+//! Sidecar files describing a scan, for photo and asset management tools
+//!
+//! A sidecar sits next to an image with the same name and another
+//! extension, and holds what the scan contains: the corrected text, the
+//! classification and its confidence. XMP sidecars (`page.xmp`) are read
+//! by Lightroom, darktable, digiKam, Bridge and most DAM systems, which
+//! show the text as the image's description and the classification as a
+//! keyword; JSON sidecars (`page.json`) are for scripts.
+
+use crate::error::Result;
+use crate::types::{ArtifactKind, PageArtifact};
+use crate::validate::report::html_escape;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+/// Namespace of the scan3data XMP properties
+pub const XMP_NAMESPACE: &str = "https://github.com/softwarewrighter/scan3data/ns/xmp/1.0/";
+
+/// Sidecar file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidecarFormat {
+    /// XMP packet
+    Xmp,
+    /// JSON object ([`Sidecar`])
+    Json,
+}
+
+impl SidecarFormat {
+    /// Parse a format name ("xmp" or "json")
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "xmp" => Some(Self::Xmp),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// Extension of sidecar files
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Xmp => "xmp",
+            Self::Json => "json",
+        }
+    }
+
+    /// Path of the sidecar of an image: the image path with the sidecar
+    /// extension
+    pub fn sidecar_path(&self, image: &Path) -> PathBuf {
+        image.with_extension(self.extension())
+    }
+}
+
+/// What a scan contains
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Sidecar {
+    /// Artifact identifier
+    pub artifact_id: String,
+    /// Files the scan was ingested from
+    pub original_filenames: Vec<String>,
+    /// Classification of the page
+    pub classification: ArtifactKind,
+    /// Confidence of the classification (0.0-1.0)
+    pub confidence: f32,
+    /// Corrected text
+    pub text: Option<String>,
+    /// Text was typed in by a person rather than recognized
+    pub human_transcribed: bool,
+}
+
+impl Sidecar {
+    /// Sidecar contents of an artifact
+    pub fn of_artifact(artifact: &PageArtifact) -> Self {
+        Self {
+            artifact_id: artifact.id.0.to_string(),
+            original_filenames: artifact.metadata.original_filenames.clone(),
+            classification: artifact.layout_label,
+            confidence: artifact.metadata.confidence,
+            text: artifact.content_text.clone(),
+            human_transcribed: artifact.metadata.human_transcribed,
+        }
+    }
+
+    /// Contents of the sidecar file in a format
+    pub fn contents(&self, format: SidecarFormat) -> Result<String> {
+        Ok(match format {
+            SidecarFormat::Xmp => self.to_xmp(),
+            SidecarFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+        })
+    }
+
+    /// XMP packet: the text as `dc:description`, the classification as a
+    /// `dc:subject` keyword, and every field as a scan3data property
+    pub fn to_xmp(&self) -> String {
+        let classification = format!("{:?}", self.classification);
+        let mut xmp = String::from(
+            "<?xpacket begin=\"\u{feff}\" id=\"W5M0MpCehiHzreSzNTczkc9d\"?>\n\
+             <x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n \
+             <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\">\n",
+        );
+        let _ = writeln!(
+            xmp,
+            "  <rdf:Description rdf:about=\"\"\n    \
+             xmlns:dc=\"http://purl.org/dc/elements/1.1/\"\n    \
+             xmlns:scan3data=\"{}\"\n    \
+             scan3data:ArtifactId=\"{}\"\n    \
+             scan3data:Classification=\"{}\"\n    \
+             scan3data:Confidence=\"{:.3}\"\n    \
+             scan3data:HumanTranscribed=\"{}\">",
+            XMP_NAMESPACE,
+            html_escape(&self.artifact_id),
+            classification,
+            self.confidence,
+            if self.human_transcribed {
+                "True"
+            } else {
+                "False"
+            }
+        );
+        if let Some(text) = &self.text {
+            let _ = writeln!(
+                xmp,
+                "   <dc:description><rdf:Alt><rdf:li xml:lang=\"x-default\">{}</rdf:li>\
+                 </rdf:Alt></dc:description>",
+                html_escape(text)
+            );
+        }
+        let _ = writeln!(
+            xmp,
+            "   <dc:subject><rdf:Bag><rdf:li>{}</rdf:li></rdf:Bag></dc:subject>",
+            classification
+        );
+        if !self.original_filenames.is_empty() {
+            xmp.push_str("   <scan3data:OriginalFilenames><rdf:Bag>\n");
+            for name in &self.original_filenames {
+                let _ = writeln!(xmp, "    <rdf:li>{}</rdf:li>", html_escape(name));
+            }
+            xmp.push_str("   </rdf:Bag></scan3data:OriginalFilenames>\n");
+        }
+        xmp.push_str("  </rdf:Description>\n </rdf:RDF>\n</x:xmpmeta>\n<?xpacket end=\"w\"?>\n");
+        xmp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PageId, PageMetadata, ScanSetId};
+
+    fn artifact() -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/ab12.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some("      IF (A .LT. B) GO TO 10\n      END".to_string()),
+            metadata: PageMetadata {
+                original_filenames: vec!["box3 & 4/p1.tif".to_string()],
+                confidence: 0.875,
+                ..PageMetadata::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_xmp() {
+        let xmp = Sidecar::of_artifact(&artifact()).to_xmp();
+        assert!(xmp.starts_with("<?xpacket begin="));
+        assert!(xmp.contains("scan3data:Classification=\"ListingSource\""));
+        assert!(xmp.contains("scan3data:Confidence=\"0.875\""));
+        assert!(xmp.contains(
+            "<rdf:li xml:lang=\"x-default\">      IF (A .LT. B) GO TO 10\n      END</rdf:li>"
+        ));
+        assert!(xmp.contains("<rdf:Bag><rdf:li>ListingSource</rdf:li></rdf:Bag>"));
+        assert!(xmp.contains("<rdf:li>box3 &amp; 4/p1.tif</rdf:li>"));
+        assert!(xmp.ends_with("<?xpacket end=\"w\"?>\n"));
+    }
+
+    #[test]
+    fn test_sidecar_path() {
+        let image = Path::new("images/ab12.jpg");
+        assert_eq!(
+            SidecarFormat::Xmp.sidecar_path(image),
+            Path::new("images/ab12.xmp")
+        );
+        assert_eq!(SidecarFormat::parse("JSON"), Some(SidecarFormat::Json));
+        assert_eq!(SidecarFormat::parse("txt"), None);
+    }
+}
@@ -0,0 +1,220 @@
+//! simh run scripts for exported decks
+//!
+//! Writes a card deck as a DMS job stream in the plain text form the simh
+//! `ibm1130` card reader reads (one card per line), together with an ini
+//! script that attaches the deck and a DMS disk and cold-starts the
+//! monitor:
+//!
+//! ```text
+//! ibm1130 deck.ini
+//! ```
+//!
+//! Decks that do not start with a `// JOB` record are wrapped in one, with
+//! a `// FOR` or `// ASM` record for the listing's language and a `// XEQ`
+//! record to run the result.
+//!
+//! Decks can also be written in the card reader's binary deck format
+//! ([`binary_deck`]), which holds the hole patterns themselves and so keeps
+//! the keypunch's character codes:
+//!
+//! ```text
+//! attach -b cr deck.dck
+//! ```
+
+use super::CARD_COLUMNS;
+use crate::error::{Error, Result};
+use crate::keypunch::{card_holes, ColumnHoles, KeypunchModel, ROWS};
+use crate::reconstruct::is_monitor_record;
+use crate::types::SourceListing;
+
+/// DMS disk image attached by default
+pub const DEFAULT_DMS_DISK: &str = "dms.dsk";
+
+/// Cards of the job stream for a listing
+///
+/// Listings that already hold monitor records are left as they are; cards
+/// are truncated to 80 columns.
+pub fn job_deck(listing: &SourceListing) -> Vec<String> {
+    let mut cards: Vec<String> = listing
+        .lines
+        .iter()
+        .map(|line| {
+            line.text
+                .chars()
+                .take(CARD_COLUMNS)
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .collect();
+
+    let has_job = cards
+        .iter()
+        .find(|card| !card.trim().is_empty())
+        .is_some_and(|card| card.trim_start().starts_with("// JOB"));
+    if has_job {
+        return cards;
+    }
+
+    let compile = match listing.language.as_str() {
+        "fortran" => Some("// FOR"),
+        "assembler" => Some("// ASM"),
+        _ => None,
+    };
+    let mut job = vec!["// JOB".to_string()];
+    if let Some(compile) = compile {
+        if !cards.iter().any(|card| is_monitor_record(card)) {
+            job.push(compile.to_string());
+            cards.push("// XEQ".to_string());
+        }
+    }
+    job.extend(cards);
+    job
+}
+
+/// Job stream file contents, one card per line
+pub fn deck_text(cards: &[String]) -> String {
+    cards.iter().map(|card| format!("{}\n", card)).collect()
+}
+
+/// Binary deck file contents for cards punched on a keypunch model
+///
+/// Each card is 80 little-endian 16-bit words, one per column, with rows
+/// 12, 11, 0-9 in bits 15 down to 4. Characters the model cannot punch
+/// leave their column blank.
+pub fn binary_deck<'a>(cards: impl IntoIterator<Item = &'a str>, model: KeypunchModel) -> Vec<u8> {
+    cards
+        .into_iter()
+        .flat_map(|card| card_holes(card, model))
+        .flat_map(|holes| column_word(holes).to_le_bytes())
+        .collect()
+}
+
+/// Card reader word of a column: row 12 (bit 0 of the holes) in bit 15
+fn column_word(holes: ColumnHoles) -> u16 {
+    (0..ROWS.len())
+        .filter(|row| holes & 1 << row != 0)
+        .fold(0, |word, row| word | 0x8000 >> row)
+}
+
+/// Hole patterns of the cards of a binary deck file
+pub fn read_binary_deck(bytes: &[u8]) -> Result<Vec<[ColumnHoles; CARD_COLUMNS]>> {
+    const CARD_BYTES: usize = CARD_COLUMNS * 2;
+    if !bytes.len().is_multiple_of(CARD_BYTES) {
+        return Err(Error::invalid(format!(
+            "Binary deck of {} bytes is not a whole number of {}-byte cards",
+            bytes.len(),
+            CARD_BYTES
+        )));
+    }
+    Ok(bytes
+        .chunks(CARD_BYTES)
+        .map(|card| {
+            let mut holes = [0; CARD_COLUMNS];
+            for (column, word) in holes.iter_mut().zip(card.chunks(2)) {
+                *column = column_holes(u16::from_le_bytes([word[0], word[1]]));
+            }
+            holes
+        })
+        .collect())
+}
+
+/// Holes of a card reader word, the reverse of [`column_word`]
+fn column_holes(word: u16) -> ColumnHoles {
+    (0..ROWS.len())
+        .filter(|row| word & 0x8000 >> row != 0)
+        .fold(0, |holes, row| holes | 1 << row)
+}
+
+/// simh ini script running a job deck under DMS
+///
+/// File names are written as given, so they are resolved relative to the
+/// directory simh is started in.
+pub fn simh_script(deck_file: &str, print_file: &str, dms_disk: &str) -> String {
+    format!(
+        "; Run {deck} under DMS (generated by scan3data)\n\
+         ; Usage: ibm1130 <this file>\n\
+         set cpu 32K\n\
+         attach dsk0 {disk}\n\
+         attach cr {deck}\n\
+         attach prt {print}\n\
+         ; Cold start DMS; the monitor reads the job from the card reader\n\
+         boot dsk\n\
+         detach prt\n\
+         quit\n",
+        deck = deck_file,
+        disk = dms_disk,
+        print = print_file
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::SourceLine;
+
+    fn listing(language: &str, lines: &[&str]) -> SourceListing {
+        SourceListing {
+            name: None,
+            language: language.to_string(),
+            pages: Vec::new(),
+            lines: lines
+                .iter()
+                .map(|text| SourceLine {
+                    line_no: None,
+                    text: text.to_string(),
+                    inferred: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_fortran_deck_wrapped_in_job() {
+        let deck = job_deck(&listing("fortran", &["      X = 1   ", "      END"]));
+        assert_eq!(
+            deck,
+            vec!["// JOB", "// FOR", "      X = 1", "      END", "// XEQ"]
+        );
+    }
+
+    #[test]
+    fn test_job_stream_left_unchanged() {
+        let lines = ["// JOB", "// ASM", "      END", "// XEQ"];
+        assert_eq!(job_deck(&listing("assembler", &lines)), lines);
+    }
+
+    #[test]
+    fn test_binary_deck() {
+        let deck = binary_deck(["A1", "&"], KeypunchModel::Ibm029);
+        assert_eq!(deck.len(), 2 * CARD_COLUMNS * 2);
+        // A is 12-1, 1 is row 1, & is row 12 on the 029
+        assert_eq!(&deck[..4], &[0x00, 0x90, 0x00, 0x10]);
+        assert_eq!(&deck[160..162], &[0x00, 0x80]);
+        assert!(deck[4..160].iter().all(|&b| b == 0));
+
+        // The 026 punches + as 12
+        let deck = binary_deck(["+"], KeypunchModel::Ibm026Fortran);
+        assert_eq!(&deck[..2], &[0x00, 0x80]);
+    }
+
+    #[test]
+    fn test_read_binary_deck() {
+        let cards = ["      CALL EXIT", "// XEQ"];
+        let deck = binary_deck(cards, KeypunchModel::Ibm029);
+        let holes = read_binary_deck(&deck).unwrap();
+        assert_eq!(
+            holes,
+            cards.map(|card| card_holes(card, KeypunchModel::Ibm029))
+        );
+        assert!(read_binary_deck(&deck[1..]).is_err());
+    }
+
+    #[test]
+    fn test_script_attaches_deck() {
+        let script = simh_script("deck.txt", "deck.lst", DEFAULT_DMS_DISK);
+        assert!(script.contains("attach dsk0 dms.dsk\n"));
+        assert!(script.contains("attach cr deck.txt\n"));
+        assert!(script.contains("boot dsk\n"));
+    }
+}
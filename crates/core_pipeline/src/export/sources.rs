@@ -0,0 +1,288 @@
+//! Source tree export
+//!
+//! Writes each recovered program as one source file, with a manifest of
+//! where it came from, ready to go straight into a git repository:
+//!
+//! ```text
+//! sources/
+//! |-- manifest.json        # Programs with their language, pages and cards
+//! |-- payroll.for          # Source text (.for, .asm, .fth or .txt)
+//! `-- sort.asm
+//! ```
+//!
+//! Programs are the reconstructed source listings, grouped by the name in
+//! their running header, and the text card decks, grouped by deck name and
+//! put in sequence-number order. Listings without a name each make their
+//! own program.
+
+use super::repository::{source_extension, source_text, unique_slug, RepoFile};
+use crate::classify::detect_language;
+use crate::error::Result;
+use crate::reconstruct::objects::sequence_key;
+use crate::types::{ArtifactKind, CardArtifact, HighLevelArtifact, ScanSetManifest};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Name of the manifest file of a source tree
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Manifest of an exported source tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceTreeManifest {
+    /// Scan set identifier
+    pub scan_set_id: String,
+    /// Scan set name
+    pub scan_set: String,
+    /// Tool and version that wrote the tree
+    pub generated_by: String,
+    /// Programs, in the order of the scan set
+    pub programs: Vec<ProgramEntry>,
+}
+
+/// A program in a source tree
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProgramEntry {
+    /// Program name, from the listing header or the deck name
+    pub name: String,
+    /// Source language (assembler, fortran, forth or unknown)
+    pub language: String,
+    /// Source file, relative to the tree
+    pub file: String,
+    /// Number of source lines
+    pub lines: usize,
+    /// Lines changed by a correction step or inferred for missing pages
+    pub inferred_lines: usize,
+    /// Page artifacts the program was read from
+    pub pages: Vec<String>,
+    /// Card artifacts the program was read from
+    pub cards: Vec<String>,
+}
+
+/// A program before it gets a file name
+struct Program {
+    name: String,
+    language: String,
+    text: String,
+    lines: usize,
+    inferred_lines: usize,
+    pages: Vec<String>,
+    cards: Vec<String>,
+}
+
+/// Plan the files of a source tree: one source file per program, then
+/// the manifest
+pub fn plan_source_tree(
+    manifest: &ScanSetManifest,
+    documents: &[HighLevelArtifact],
+    cards: &[CardArtifact],
+) -> Result<Vec<RepoFile>> {
+    let mut programs = listing_programs(documents);
+    programs.extend(deck_programs(cards));
+
+    let mut used = HashSet::new();
+    let mut files = Vec::new();
+    let mut entries = Vec::new();
+    for program in programs {
+        let file = format!(
+            "{}.{}",
+            unique_slug(&program.name, &mut used),
+            source_extension(&program.language)
+        );
+        files.push(RepoFile::text(&file, program.text));
+        entries.push(ProgramEntry {
+            name: program.name,
+            language: program.language,
+            file,
+            lines: program.lines,
+            inferred_lines: program.inferred_lines,
+            pages: program.pages,
+            cards: program.cards,
+        });
+    }
+
+    let tree = SourceTreeManifest {
+        scan_set_id: manifest.scan_set_id.0.to_string(),
+        scan_set: manifest.name.clone(),
+        generated_by: format!("scan3data {}", env!("CARGO_PKG_VERSION")),
+        programs: entries,
+    };
+    let mut json = serde_json::to_string_pretty(&tree)?;
+    json.push('\n');
+    files.push(RepoFile::text(MANIFEST_FILE, json));
+    Ok(files)
+}
+
+/// Programs of the source listings, listings of the same name joined in
+/// order
+fn listing_programs(documents: &[HighLevelArtifact]) -> Vec<Program> {
+    let mut programs: Vec<Program> = Vec::new();
+    for (idx, document) in documents.iter().enumerate() {
+        let HighLevelArtifact::SourceListing(listing) = document else {
+            continue;
+        };
+        let pages = listing.pages.iter().map(|id| id.0.to_string());
+        let inferred_lines = listing.lines.iter().filter(|l| l.inferred).count();
+        let existing = listing
+            .name
+            .as_ref()
+            .and_then(|name| programs.iter_mut().find(|p| &p.name == name));
+        if let Some(program) = existing {
+            program.text.push_str(&source_text(listing));
+            program.lines += listing.lines.len();
+            program.inferred_lines += inferred_lines;
+            program.pages.extend(pages);
+            continue;
+        }
+        programs.push(Program {
+            name: listing
+                .name
+                .clone()
+                .unwrap_or(format!("{} {}", listing.language, idx + 1)),
+            language: listing.language.clone(),
+            text: source_text(listing),
+            lines: listing.lines.len(),
+            inferred_lines,
+            pages: pages.collect(),
+            cards: Vec::new(),
+        });
+    }
+    programs
+}
+
+/// Cards of a text deck with their text, by deck name
+type DeckGroup<'a> = (Option<&'a str>, Vec<(&'a CardArtifact, &'a str)>);
+
+/// Programs of the text card decks: cards grouped by deck name, in order
+/// of first appearance, and sorted by sequence number
+fn deck_programs(cards: &[CardArtifact]) -> Vec<Program> {
+    let mut groups: Vec<DeckGroup> = Vec::new();
+    for card in cards {
+        let Some(text) = card.text_80col.as_deref() else {
+            continue;
+        };
+        if card.layout_label != ArtifactKind::CardText {
+            continue;
+        }
+        let name = card.metadata.deck_name.as_deref();
+        match groups.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, group)) => group.push((card, text)),
+            None => groups.push((name, vec![(card, text)])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .enumerate()
+        .map(|(idx, (name, mut group))| {
+            group.sort_by_key(|(card, _)| sequence_key(card.metadata.sequence_number.as_deref()));
+            let text: String = group
+                .iter()
+                .map(|(_, text)| format!("{}\n", text.trim_end()))
+                .collect();
+            Program {
+                name: name.map_or_else(|| format!("DECK{}", idx + 1), str::to_string),
+                language: detect_language(&text).0.as_str().to_string(),
+                text,
+                lines: group.len(),
+                inferred_lines: 0,
+                pages: Vec::new(),
+                cards: group
+                    .iter()
+                    .map(|(card, _)| card.id.0.to_string())
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::repository::RepoContent;
+    use crate::types::{CardId, CardMetadata, PageId, ScanSetId, SourceLine, SourceListing};
+    use std::path::PathBuf;
+
+    fn manifest() -> ScanSetManifest {
+        ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "Box 3".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            image_count: 1,
+            original_file_count: 1,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        }
+    }
+
+    fn listing(name: Option<&str>, text: &str) -> HighLevelArtifact {
+        HighLevelArtifact::SourceListing(SourceListing {
+            name: name.map(str::to_string),
+            language: "fortran".to_string(),
+            pages: vec![PageId::new()],
+            lines: vec![SourceLine {
+                line_no: None,
+                text: text.to_string(),
+                inferred: true,
+            }],
+        })
+    }
+
+    fn card(deck: &str, sequence: &str, text: &str) -> CardArtifact {
+        CardArtifact {
+            id: CardId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::new(),
+            processed_image_path: None,
+            layout_label: ArtifactKind::CardText,
+            text_80col: Some(text.to_string()),
+            binary_80col: None,
+            metadata: CardMetadata {
+                deck_name: Some(deck.to_string()),
+                sequence_number: Some(sequence.to_string()),
+                ..CardMetadata::default()
+            },
+        }
+    }
+
+    fn text(file: &RepoFile) -> &str {
+        let RepoContent::Text(text) = &file.content else {
+            panic!("expected text");
+        };
+        text
+    }
+
+    #[test]
+    fn test_plan_source_tree() {
+        let documents = vec![
+            listing(Some("PAYROLL"), "      X = 1   "),
+            listing(None, "      Y = 2"),
+            listing(Some("PAYROLL"), "      END"),
+        ];
+        let cards = vec![
+            card("SORT", "0002", "      END"),
+            card("SORT", "0001", "      I = 0"),
+        ];
+        let files = plan_source_tree(&manifest(), &documents, &cards).unwrap();
+
+        let paths: Vec<String> = files.iter().map(|f| f.path.display().to_string()).collect();
+        assert_eq!(
+            paths,
+            ["payroll.for", "fortran-2.for", "sort.for", "manifest.json"]
+        );
+        assert_eq!(text(&files[0]), "      X = 1\n      END\n");
+        assert_eq!(text(&files[2]), "      I = 0\n      END\n");
+
+        let tree: SourceTreeManifest = serde_json::from_str(text(&files[3])).unwrap();
+        assert_eq!(tree.scan_set, "Box 3");
+        assert_eq!(tree.programs.len(), 3);
+        assert_eq!(tree.programs[0].name, "PAYROLL");
+        assert_eq!(tree.programs[0].lines, 2);
+        assert_eq!(tree.programs[0].inferred_lines, 2);
+        assert_eq!(tree.programs[0].pages.len(), 2);
+        assert_eq!(tree.programs[2].language, "fortran");
+        assert_eq!(tree.programs[2].cards[0], cards[1].id.0.to_string());
+    }
+}
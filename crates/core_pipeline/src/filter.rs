@@ -0,0 +1,238 @@
+//! Artifact filters for bulk operations
+//!
+//! A filter is a list of conditions an artifact must all meet, each
+//! written as a field, an operator and a value:
+//!
+//! ```text
+//! text-len<90       fewer than 90 characters of text
+//! lines>=40         at least 40 non-blank lines
+//! confidence<0.5    classified with less than 50% confidence
+//! issues>0          with validation issues
+//! text~// JOB       text contains "// JOB"
+//! text!~CALL        text does not contain "CALL"
+//! ```
+//!
+//! Numeric fields take `<`, `<=`, `>`, `>=`, `=` and `!=`; `text` takes
+//! `~` (contains) and `!~` (does not contain). Artifacts without text have
+//! a text length and line count of 0 and no text to contain anything.
+
+use crate::error::{Error, Result};
+use crate::types::PageArtifact;
+
+/// Operators, longest first so `<=` is not read as `<`
+const OPERATORS: [(&str, Operator); 8] = [
+    ("<=", Operator::Le),
+    (">=", Operator::Ge),
+    ("!=", Operator::Ne),
+    ("!~", Operator::NotContains),
+    ("<", Operator::Lt),
+    (">", Operator::Gt),
+    ("=", Operator::Eq),
+    ("~", Operator::Contains),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    TextLen,
+    Lines,
+    Confidence,
+    Issues,
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+    Contains,
+    NotContains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Condition {
+    field: Field,
+    operator: Operator,
+    value: Value,
+}
+
+/// Conditions an artifact must all meet; an empty filter matches every
+/// artifact
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ArtifactFilter {
+    conditions: Vec<Condition>,
+}
+
+impl ArtifactFilter {
+    /// Filter of the given conditions (`text-len<90`, ...)
+    pub fn parse<S: AsRef<str>>(conditions: &[S]) -> Result<Self> {
+        let conditions = conditions
+            .iter()
+            .map(|condition| parse_condition(condition.as_ref()))
+            .collect::<Result<_>>()?;
+        Ok(Self { conditions })
+    }
+
+    /// Whether an artifact meets every condition
+    pub fn matches(&self, artifact: &PageArtifact) -> bool {
+        self.conditions.iter().all(|c| c.matches(artifact))
+    }
+}
+
+fn parse_condition(condition: &str) -> Result<Condition> {
+    let Some((at, symbol, operator)) = OPERATORS
+        .iter()
+        .filter_map(|&(symbol, operator)| Some((condition.find(symbol)?, symbol, operator)))
+        .min_by_key(|&(at, symbol, _)| (at, std::cmp::Reverse(symbol.len())))
+    else {
+        return Err(Error::invalid(format!(
+            "Filter has no operator (<, <=, >, >=, =, !=, ~, !~): {}",
+            condition
+        )));
+    };
+    let name = condition[..at].trim();
+    let value = condition[at + symbol.len()..].trim();
+
+    let field = match name.to_ascii_lowercase().as_str() {
+        "text-len" => Field::TextLen,
+        "lines" => Field::Lines,
+        "confidence" => Field::Confidence,
+        "issues" => Field::Issues,
+        "text" => Field::Text,
+        _ => {
+            return Err(Error::invalid(format!(
+                "Unknown filter field: {} (use text-len, lines, confidence, issues or text)",
+                name
+            )))
+        }
+    };
+    let value = match (field, operator) {
+        (Field::Text, Operator::Contains | Operator::NotContains) => Value::Text(value.to_string()),
+        (Field::Text, _) => {
+            return Err(Error::invalid(format!(
+                "The text filter takes ~ or !~: {}",
+                condition
+            )))
+        }
+        (_, Operator::Contains | Operator::NotContains) => {
+            return Err(Error::invalid(format!(
+                "~ and !~ only apply to text: {}",
+                condition
+            )))
+        }
+        _ => {
+            Value::Number(value.parse().map_err(|_| {
+                Error::invalid(format!("Filter value is not a number: {}", condition))
+            })?)
+        }
+    };
+    Ok(Condition {
+        field,
+        operator,
+        value,
+    })
+}
+
+impl Condition {
+    fn matches(&self, artifact: &PageArtifact) -> bool {
+        let text = artifact.content_text.as_deref();
+        let number = match self.field {
+            Field::TextLen => text.map_or(0, |t| t.chars().count()) as f64,
+            Field::Lines => {
+                text.map_or(0, |t| t.lines().filter(|l| !l.trim().is_empty()).count()) as f64
+            }
+            Field::Confidence => artifact.metadata.confidence as f64,
+            Field::Issues => artifact.metadata.validation_issues.len() as f64,
+            Field::Text => {
+                let Value::Text(needle) = &self.value else {
+                    return false;
+                };
+                let contains = text.is_some_and(|t| t.contains(needle.as_str()));
+                return match self.operator {
+                    Operator::NotContains => !contains,
+                    _ => contains,
+                };
+            }
+        };
+        let Value::Number(mut value) = self.value else {
+            return false;
+        };
+        if self.field == Field::Confidence {
+            // Confidence is stored as f32: 0.3 must equal 0.3
+            value = value as f32 as f64;
+        }
+        match self.operator {
+            Operator::Lt => number < value,
+            Operator::Le => number <= value,
+            Operator::Gt => number > value,
+            Operator::Ge => number >= value,
+            Operator::Eq => number == value,
+            Operator::Ne => number != value,
+            Operator::Contains | Operator::NotContains => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageId, PageMetadata, ScanSetId};
+    use std::path::PathBuf;
+
+    fn artifact(text: Option<&str>, confidence: f32) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/a.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::Unknown,
+            content_text: text.map(str::to_string),
+            metadata: PageMetadata {
+                confidence,
+                ..PageMetadata::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_conditions() {
+        let card = artifact(Some("// JOB\n\n      CALL EXIT"), 0.3);
+        let matches = |condition: &str| ArtifactFilter::parse(&[condition]).unwrap().matches(&card);
+        assert!(matches("text-len<90"));
+        assert!(!matches("text-len >= 90"));
+        assert!(matches("lines=2"));
+        assert!(matches("confidence<=0.3"));
+        assert!(matches("issues=0"));
+        assert!(matches("text~// JOB"));
+        assert!(matches("text!~PAUSE"));
+        assert!(!matches("text~PAUSE"));
+
+        // Every condition must hold; no text has length 0
+        let filter = ArtifactFilter::parse(&["text-len<90", "confidence>0.5"]).unwrap();
+        assert!(!filter.matches(&card));
+        assert!(ArtifactFilter::parse(&["text-len<90"])
+            .unwrap()
+            .matches(&artifact(None, 0.0)));
+        assert!(ArtifactFilter::default().matches(&card));
+    }
+
+    #[test]
+    fn test_invalid_conditions() {
+        for condition in ["text-len", "size<3", "text-len<many", "text=A", "lines~3"] {
+            assert!(
+                ArtifactFilter::parse(&[condition]).is_err(),
+                "{}",
+                condition
+            );
+        }
+    }
+}
@@ -0,0 +1,223 @@
+//! Column grid of printed text
+//!
+//! Column-based parsing (FORTRAN fields, card columns) assumes the text of
+//! a page sits on a fixed character pitch. The pitch is detected from the
+//! periodicity of the ink across the page, and the grid is placed so its
+//! lines run through the gaps between characters. Drawing the grid over
+//! the image shows whether the assumption holds.
+
+use image::{GrayImage, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+/// Pixels darker than this count as ink
+const INK_THRESHOLD: u8 = 128;
+
+/// Smallest character pitch looked for, in pixels
+const MIN_PITCH: usize = 4;
+
+/// Pixel columns with less ink than this fraction of the inkiest column
+/// are taken as blank when looking for the left margin
+const MARGIN_INK: f64 = 0.01;
+
+/// Grid line colors: every column, every tenth column
+const COLUMN_LINE: Rgb<u8> = Rgb([0, 120, 255]);
+const TENTH_LINE: Rgb<u8> = Rgb([220, 0, 0]);
+
+/// Fixed-pitch column grid of a page, in pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ColumnGrid {
+    /// Left edge of column 1
+    pub origin: f32,
+    /// Width of a column
+    pub pitch: f32,
+}
+
+impl ColumnGrid {
+    /// Detect the character pitch and first column of a page's text
+    ///
+    /// Returns `None` for a blank page or one without periodic text, and
+    /// for pages too narrow to hold `columns` columns of [`MIN_PITCH`].
+    pub fn detect(image: &GrayImage, columns: usize) -> Option<Self> {
+        let profile = ink_profile(image);
+        let max_ink = profile.iter().copied().fold(0.0, f64::max);
+        if max_ink == 0.0 {
+            return None;
+        }
+        let pitch = detect_pitch(&profile, profile.len() / columns.max(1))?;
+
+        // Grid lines run through the gaps between characters
+        let steps = pitch.ceil() as usize;
+        let phase = (0..steps).map(|offset| offset as f32).min_by(|&a, &b| {
+            ink_on_lines(&profile, a, pitch).total_cmp(&ink_on_lines(&profile, b, pitch))
+        })?;
+
+        // Column 1 starts at the last grid line before the left margin
+        let margin = profile.iter().position(|&ink| ink > max_ink * MARGIN_INK)? as f32;
+        let origin = phase + ((margin - phase) / pitch).floor() * pitch;
+        Some(Self { origin, pitch })
+    }
+
+    /// Left edge of a column (0-based)
+    pub fn boundary(&self, column: usize) -> f32 {
+        self.origin + column as f32 * self.pitch
+    }
+}
+
+/// Draw a grid of `columns` columns over a page
+///
+/// Column lines are blue, every tenth red.
+pub fn draw_grid(image: &GrayImage, grid: &ColumnGrid, columns: usize) -> RgbImage {
+    let mut output = RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let luma = image.get_pixel(x, y).0[0];
+        Rgb([luma, luma, luma])
+    });
+    for column in 0..=columns {
+        let x = grid.boundary(column).round();
+        if x < 0.0 || x >= image.width() as f32 {
+            continue;
+        }
+        let color = if column % 10 == 0 {
+            TENTH_LINE
+        } else {
+            COLUMN_LINE
+        };
+        for y in 0..image.height() {
+            output.put_pixel(x as u32, y, color);
+        }
+    }
+    output
+}
+
+/// Ink pixels in each pixel column
+fn ink_profile(image: &GrayImage) -> Vec<f64> {
+    let mut profile = vec![0.0; image.width() as usize];
+    for (x, _, pixel) in image.enumerate_pixels() {
+        if pixel.0[0] < INK_THRESHOLD {
+            profile[x as usize] += 1.0;
+        }
+    }
+    profile
+}
+
+/// Period of an ink profile of at most `max_pitch` pixels
+///
+/// The shortest lag with an autocorrelation close to the strongest one is
+/// taken, since multiples of the pitch correlate as well. It is refined to
+/// a fraction of a pixel over a multiple of itself, as an error of half a
+/// pixel would add up to a column over 80 columns.
+fn detect_pitch(profile: &[f64], max_pitch: usize) -> Option<f32> {
+    if max_pitch < MIN_PITCH + 2 {
+        return None;
+    }
+    let mean = profile.iter().sum::<f64>() / profile.len() as f64;
+    let centered: Vec<f64> = profile.iter().map(|ink| ink - mean).collect();
+    let correlation = |lag: usize| -> f64 {
+        let pairs = centered.len().saturating_sub(lag);
+        if pairs == 0 {
+            return 0.0;
+        }
+        centered
+            .iter()
+            .zip(&centered[lag..])
+            .map(|(a, b)| a * b)
+            .sum::<f64>()
+            / pairs as f64
+    };
+
+    let correlations: Vec<f64> = (0..=max_pitch + 1).map(correlation).collect();
+    let peaks: Vec<usize> = (MIN_PITCH..=max_pitch)
+        .filter(|&lag| {
+            correlations[lag] > 0.0
+                && correlations[lag] >= correlations[lag - 1]
+                && correlations[lag] >= correlations[lag + 1]
+        })
+        .collect();
+    let strongest = peaks
+        .iter()
+        .map(|&lag| correlations[lag])
+        .fold(0.0, f64::max);
+    let lag = *peaks
+        .iter()
+        .find(|&&lag| correlations[lag] >= strongest * 0.8)?;
+
+    // Refine over the largest multiple spanning at most half the page; an
+    // error of under half a pixel per period stays under half a period
+    let multiple = (profile.len() / 2 / lag).clamp(1, 10);
+    let slack = multiple / 2;
+    let refined = (lag * multiple - slack..=lag * multiple + slack)
+        .max_by(|&a, &b| correlation(a).total_cmp(&correlation(b)))?;
+    Some(refined as f32 / multiple as f32)
+}
+
+/// Ink under the grid lines of a phase and pitch
+fn ink_on_lines(profile: &[f64], phase: f32, pitch: f32) -> f64 {
+    let mut ink = 0.0;
+    let mut x = phase;
+    while (x.round() as usize) < profile.len() {
+        ink += profile[x.round() as usize];
+        x += pitch;
+    }
+    ink
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    /// Page of `columns` characters 8 pixels wide on a pitch, starting at
+    /// x = 30, with varying glyph shapes
+    fn page(columns: usize, pitch: f32) -> GrayImage {
+        let mut image = GrayImage::from_pixel(1200, 40, Luma([255]));
+        for column in 0..columns {
+            let left = 30 + (column as f32 * pitch) as u32;
+            let rows = 10 + (column as u32 * 7) % 20;
+            for x in left..left + 8 {
+                for y in 5..5 + rows {
+                    if !(x + y + column as u32).is_multiple_of(3) {
+                        image.put_pixel(x, y, Luma([0]));
+                    }
+                }
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn test_detect() {
+        let grid = ColumnGrid::detect(&page(80, 12.0), 80).unwrap();
+        assert!((grid.pitch - 12.0).abs() < 0.1, "pitch {}", grid.pitch);
+        // Column 1 starts in the gap before the first character
+        assert!(
+            (26.0..=30.0).contains(&grid.origin),
+            "origin {}",
+            grid.origin
+        );
+        // and the last column in the gap before the 80th
+        assert!((966.0..=978.0).contains(&grid.boundary(79)));
+
+        // Pitch between whole pixels
+        let grid = ColumnGrid::detect(&page(80, 12.6), 80).unwrap();
+        assert!((grid.pitch - 12.6).abs() < 0.1, "pitch {}", grid.pitch);
+
+        assert_eq!(
+            ColumnGrid::detect(&GrayImage::from_pixel(1200, 40, Luma([255])), 80),
+            None
+        );
+        assert_eq!(ColumnGrid::detect(&page(80, 12.0), 1000), None);
+    }
+
+    #[test]
+    fn test_draw_grid() {
+        let image = GrayImage::from_pixel(100, 10, Luma([200]));
+        let grid = ColumnGrid {
+            origin: 10.0,
+            pitch: 8.0,
+        };
+        let drawn = draw_grid(&image, &grid, 80);
+        assert_eq!(*drawn.get_pixel(10, 5), TENTH_LINE);
+        assert_eq!(*drawn.get_pixel(18, 5), COLUMN_LINE);
+        assert_eq!(*drawn.get_pixel(19, 5), Rgb([200, 200, 200]));
+        assert_eq!(*drawn.get_pixel(90, 0), TENTH_LINE);
+    }
+}
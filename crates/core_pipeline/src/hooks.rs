@@ -0,0 +1,338 @@
+//! External command hooks run at pipeline stages
+//!
+//! A hook splices a user's own script into analysis without forking the
+//! crate: an alternative corrector replacing the OCR text, or a custom
+//! validator adding notes. Hooks are configured in a TOML file:
+//!
+//! ```toml
+//! [[hook]]
+//! stage = "post-ocr"
+//! command = ["python3", "fix_labels.py"]
+//!
+//! [[hook]]
+//! stage = "post-correct"
+//! command = ["./check_deck.sh", "--strict"]
+//! # Send the raw image instead of the text (default "text")
+//! input = "image"
+//! # Add each output line as a note instead of replacing the text
+//! output = "notes"
+//! ```
+//!
+//! The artifact's text or raw image bytes go to the command's stdin. The
+//! command also gets `SCAN3DATA_STAGE`, `SCAN3DATA_ARTIFACT_ID` and
+//! `SCAN3DATA_IMAGE` (path of the raw image) in its environment. Hooks of
+//! the same stage run in file order, each seeing the previous one's text.
+//! A hook exiting with a non-zero status fails with its stderr. Hook
+//! results are not cached. The notes hooks add start with
+//! [`NOTE_PREFIX`] and the stage, so a new run can replace them.
+
+use crate::error::{Error, IoContext, Result};
+use crate::types::PageArtifact;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Start of every note a hook adds to an artifact
+pub const NOTE_PREFIX: &str = "Hook ";
+
+/// Pipeline stages hooks can run at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookStage {
+    /// After OCR, before vision correction
+    PostOcr,
+    /// After vision correction and auto-fix, before classification
+    PostCorrect,
+}
+
+impl HookStage {
+    /// Stage name as written in hook files
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PostOcr => "post-ocr",
+            Self::PostCorrect => "post-correct",
+        }
+    }
+}
+
+/// What a hook gets on stdin
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookInput {
+    /// The artifact's current text
+    #[default]
+    Text,
+    /// The bytes of the artifact's raw image file
+    Image,
+}
+
+/// How a hook's stdout is used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HookOutput {
+    /// Replaces the artifact's text
+    #[default]
+    Text,
+    /// Each non-empty line is added to the artifact's notes
+    Notes,
+}
+
+/// An external command run at a pipeline stage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hook {
+    /// Stage the hook runs at
+    pub stage: HookStage,
+    /// Program and arguments
+    pub command: Vec<String>,
+    /// What the command gets on stdin
+    #[serde(default)]
+    pub input: HookInput,
+    /// How the command's stdout is used
+    #[serde(default)]
+    pub output: HookOutput,
+}
+
+impl Hook {
+    /// Run the command with `stdin` as input, returning its stdout
+    pub fn run(&self, stdin: &[u8], env: &[(&str, &str)]) -> Result<String> {
+        let Some((program, args)) = self.command.split_first() else {
+            return Err(Error::invalid(format!(
+                "{} hook has an empty command",
+                self.stage.as_str()
+            )));
+        };
+        let mut child = Command::new(program)
+            .args(args)
+            .envs(env.iter().copied())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .io_context(|| format!("Failed to run hook: {}", program))?;
+
+        // Feed stdin from a separate thread so a command writing a lot of
+        // output before reading all its input cannot deadlock
+        let mut child_stdin = child.stdin.take().expect("stdin is piped");
+        let output = std::thread::scope(|scope| {
+            scope.spawn(move || {
+                // A command that does not read its input closes the pipe
+                child_stdin.write_all(stdin).ok();
+            });
+            child.wait_with_output()
+        })
+        .io_context(|| format!("Failed to run hook: {}", program))?;
+
+        if !output.status.success() {
+            return Err(Error::Hook(format!(
+                "{} hook `{}` failed ({}): {}",
+                self.stage.as_str(),
+                self.command.join(" "),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+}
+
+/// Hooks configured for a run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookSet {
+    /// Hooks in the order they run
+    #[serde(rename = "hook")]
+    pub hooks: Vec<Hook>,
+}
+
+impl HookSet {
+    /// Parse hooks from TOML
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        toml::from_str(toml).map_err(|source| Error::HookConfig {
+            message: "Failed to parse hooks".to_string(),
+            source: Box::new(source),
+        })
+    }
+
+    /// Load hooks from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let toml = fs::read_to_string(path)
+            .io_context(|| format!("Failed to read hooks: {}", path.display()))?;
+        toml::from_str(&toml).map_err(|source| Error::HookConfig {
+            message: format!("Invalid hooks file: {}", path.display()),
+            source: Box::new(source),
+        })
+    }
+
+    /// Whether no hooks are configured
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Run the hooks of a stage on an artifact of the scan set
+    ///
+    /// Text hooks are skipped for artifacts without text. Returns the
+    /// number of hooks run; stops at the first failing hook.
+    pub fn apply(
+        &self,
+        stage: HookStage,
+        scan_set_dir: &Path,
+        artifact: &mut PageArtifact,
+    ) -> Result<usize> {
+        let image_path = scan_set_dir.join(&artifact.raw_image_path);
+        let image_env = image_path.display().to_string();
+        let artifact_id = artifact.id.0.to_string();
+        let env = [
+            ("SCAN3DATA_STAGE", stage.as_str()),
+            ("SCAN3DATA_ARTIFACT_ID", artifact_id.as_str()),
+            ("SCAN3DATA_IMAGE", image_env.as_str()),
+        ];
+
+        let mut run = 0;
+        for hook in self.hooks.iter().filter(|hook| hook.stage == stage) {
+            let stdout = match (hook.input, &artifact.content_text) {
+                (HookInput::Text, Some(text)) => hook.run(text.as_bytes(), &env)?,
+                (HookInput::Text, None) => continue,
+                (HookInput::Image, _) => {
+                    let image = fs::read(&image_path)
+                        .io_context(|| format!("Failed to read image: {}", image_path.display()))?;
+                    hook.run(&image, &env)?
+                }
+            };
+            run += 1;
+
+            match hook.output {
+                HookOutput::Text => {
+                    if artifact.content_text.as_deref() != Some(stdout.as_str()) {
+                        artifact.metadata.notes.push(format!(
+                            "{}{} replaced the text: {}",
+                            NOTE_PREFIX,
+                            stage.as_str(),
+                            hook.command.join(" ")
+                        ));
+                        artifact.content_text = Some(stdout);
+                    }
+                }
+                HookOutput::Notes => artifact.metadata.notes.extend(
+                    stdout
+                        .lines()
+                        .map(str::trim)
+                        .filter(|line| !line.is_empty())
+                        .map(|line| format!("{}{}: {}", NOTE_PREFIX, stage.as_str(), line)),
+                ),
+            }
+        }
+        Ok(run)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageId, PageMetadata, ScanSetId};
+    use std::path::PathBuf;
+
+    fn artifact(text: Option<&str>) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/ab.png"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::Unknown,
+            content_text: text.map(str::to_string),
+            metadata: PageMetadata {
+                content_hash: String::new(),
+                file_hash: None,
+                original_filenames: Vec::new(),
+                page_number: None,
+                header: None,
+                footer: None,
+                notes: Vec::new(),
+                confidence: 0.0,
+                revisions: Vec::new(),
+                validation_issues: Vec::new(),
+                derived_images: Vec::new(),
+                cost_usd: 0.0,
+                source_pages: Vec::new(),
+                perceptual_hash: None,
+                ingest_index: 0,
+                originals: Vec::new(),
+                reading_order: None,
+                human_transcribed: false,
+                cleaned_image_path: None,
+                thumbnail_path: None,
+                manual_classification: false,
+                base_confidence: None,
+            },
+        }
+    }
+
+    fn hooks(toml: &str) -> HookSet {
+        HookSet::from_toml(toml).unwrap()
+    }
+
+    #[test]
+    fn test_text_and_notes_hooks() {
+        let hooks = hooks(
+            r#"
+            [[hook]]
+            stage = "post-ocr"
+            command = ["tr", "O", "0"]
+
+            [[hook]]
+            stage = "post-ocr"
+            command = ["sh", "-c", "grep -c 0; echo \"stage $SCAN3DATA_STAGE\""]
+            output = "notes"
+
+            [[hook]]
+            stage = "post-correct"
+            command = ["false"]
+            "#,
+        );
+        let mut artifact = artifact(Some("      GO TO 10\n"));
+        let run = hooks
+            .apply(HookStage::PostOcr, Path::new("."), &mut artifact)
+            .unwrap();
+
+        assert_eq!(run, 2);
+        assert_eq!(artifact.content_text.as_deref(), Some("      G0 T0 10\n"));
+        assert_eq!(
+            artifact.metadata.notes,
+            vec![
+                "Hook post-ocr replaced the text: tr O 0",
+                "Hook post-ocr: 1",
+                "Hook post-ocr: stage post-ocr"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_skips_text_hooks_without_text() {
+        let hooks = hooks("[[hook]]\nstage = \"post-ocr\"\ncommand = [\"cat\"]\n");
+        let mut artifact = artifact(None);
+        assert_eq!(
+            hooks
+                .apply(HookStage::PostOcr, Path::new("."), &mut artifact)
+                .unwrap(),
+            0
+        );
+        assert!(artifact.content_text.is_none());
+    }
+
+    #[test]
+    fn test_failing_hook() {
+        let hooks = hooks(
+            "[[hook]]\nstage = \"post-correct\"\ncommand = [\"sh\", \"-c\", \"echo bad deck >&2; exit 3\"]\n",
+        );
+        let mut artifact = artifact(Some("X"));
+        let err = hooks
+            .apply(HookStage::PostCorrect, Path::new("."), &mut artifact)
+            .unwrap_err();
+        assert!(matches!(err, Error::Hook(_)));
+        assert!(err.to_string().ends_with("bad deck"));
+        assert_eq!(artifact.content_text.as_deref(), Some("X"));
+
+        assert!(HookSet::from_toml("[[hook]]\nstage = \"pre-ocr\"\ncommand = []\n").is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! Bounded-memory image loading
+//!
+//! A 600-DPI TIFF page decodes to 100+ MB, so images are only decoded when
+//! a stage needs their pixels, and stages that work at lower resolution
+//! (vision models, thumbnails) downscale right after decoding so only the
+//! smaller copy is kept.
+//!
+//! JPEG, PNG, TIFF, BMP and WebP decode out of the box; HEIC/HEIF (phone
+//! photos) needs the `heic` feature and libheif.
+
+use crate::error::{Error, IoContext, Result};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+/// How to load an image
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LoadOptions {
+    /// Downscale so neither side exceeds this many pixels (full size if unset)
+    pub max_dimension: Option<u32>,
+    /// Refuse to decode images needing more memory than this (bytes)
+    pub max_alloc: Option<u64>,
+}
+
+impl LoadOptions {
+    /// Full resolution (e.g. for OCR and pixel hashing)
+    pub fn full() -> Self {
+        Self::default()
+    }
+
+    /// Downscaled to fit within `max_dimension` pixels
+    pub fn downscaled(max_dimension: u32) -> Self {
+        Self {
+            max_dimension: Some(max_dimension),
+            ..Self::default()
+        }
+    }
+}
+
+/// Check if a file extension is an image format that can be decoded
+pub fn is_supported_extension(ext: &str) -> bool {
+    let ext = ext.to_ascii_lowercase();
+    matches!(
+        ext.as_str(),
+        "jpg" | "jpeg" | "png" | "tif" | "tiff" | "bmp" | "webp"
+    ) || (cfg!(feature = "heic") && matches!(ext.as_str(), "heic" | "heif"))
+}
+
+/// Decode an image file, downscaling it if requested
+pub fn load_image(path: &Path, options: &LoadOptions) -> Result<DynamicImage> {
+    #[cfg(feature = "heic")]
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("heic") || ext.eq_ignore_ascii_case("heif"))
+    {
+        return Ok(fit(heic::decode(path)?, options));
+    }
+
+    let mut reader = ImageReader::open(path)
+        .and_then(|r| r.with_guessed_format())
+        .io_context(|| format!("Failed to open image: {}", path.display()))?;
+    if let Some(max_alloc) = options.max_alloc {
+        let mut limits = Limits::default();
+        limits.max_alloc = Some(max_alloc);
+        reader.limits(limits);
+    }
+    let image = reader.decode().map_err(|source| Error::ImageLoad {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    Ok(fit(image, options))
+}
+
+/// Downscale a decoded image to the requested size
+fn fit(image: DynamicImage, options: &LoadOptions) -> DynamicImage {
+    match options.max_dimension {
+        Some(max) if image.width() > max || image.height() > max => {
+            image.resize(max, max, FilterType::Triangle)
+        }
+        _ => image,
+    }
+}
+
+/// HEIC/HEIF decoding through libheif
+#[cfg(feature = "heic")]
+mod heic {
+    use crate::error::{Error, Result};
+    use image::error::{DecodingError, ImageFormatHint};
+    use image::{DynamicImage, ImageError, RgbImage};
+    use libheif_rs::{ColorSpace, HeifContext, HeifError, LibHeif, RgbChroma};
+    use std::path::Path;
+
+    /// Decode the primary image of a HEIC/HEIF file to RGB
+    pub fn decode(path: &Path) -> Result<DynamicImage> {
+        let load_error = |err: HeifError| Error::ImageLoad {
+            path: path.to_path_buf(),
+            source: ImageError::Decoding(DecodingError::new(
+                ImageFormatHint::Name("HEIC".to_string()),
+                err,
+            )),
+        };
+        let context = HeifContext::read_from_file(&path.to_string_lossy()).map_err(load_error)?;
+        let handle = context.primary_image_handle().map_err(load_error)?;
+        let decoded = LibHeif::new()
+            .decode(&handle, ColorSpace::Rgb(RgbChroma::Rgb), None)
+            .map_err(load_error)?;
+
+        let planes = decoded.planes();
+        let plane = planes
+            .interleaved
+            .expect("interleaved RGB decodes to an interleaved plane");
+        let row_len = plane.width as usize * 3;
+        let mut pixels = Vec::with_capacity(row_len * plane.height as usize);
+        for row in plane.data.chunks(plane.stride).take(plane.height as usize) {
+            pixels.extend_from_slice(&row[..row_len]);
+        }
+        let image = RgbImage::from_raw(plane.width, plane.height, pixels)
+            .expect("pixel buffer matches the plane size");
+        Ok(DynamicImage::ImageRgb8(image))
+    }
+}
+
+/// Encode an image as PNG bytes (e.g. to send a downscaled copy to a model)
+pub fn encode_png(image: &DynamicImage) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .map_err(|source| Error::ImageEncode {
+            message: "Failed to encode PNG".to_string(),
+            source,
+        })?;
+    Ok(bytes)
+}
+
+/// An image that is decoded on first use and can be released afterwards
+#[derive(Debug)]
+pub struct LazyImage {
+    path: PathBuf,
+    options: LoadOptions,
+    image: Option<DynamicImage>,
+}
+
+impl LazyImage {
+    /// Refer to an image file without decoding it
+    pub fn new(path: impl Into<PathBuf>, options: LoadOptions) -> Self {
+        Self {
+            path: path.into(),
+            options,
+            image: None,
+        }
+    }
+
+    /// Path of the image file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Dimensions of the stored image, read from the file header only
+    pub fn dimensions(&self) -> Result<(u32, u32)> {
+        image::image_dimensions(&self.path).map_err(|source| Error::ImageLoad {
+            path: self.path.clone(),
+            source,
+        })
+    }
+
+    /// Decode the image if not already loaded
+    pub fn get(&mut self) -> Result<&DynamicImage> {
+        if self.image.is_none() {
+            self.image = Some(load_image(&self.path, &self.options)?);
+        }
+        Ok(self.image.as_ref().expect("image loaded above"))
+    }
+
+    /// Whether the pixels are currently in memory
+    pub fn is_loaded(&self) -> bool {
+        self.image.is_some()
+    }
+
+    /// Drop the decoded pixels; the next `get` decodes again
+    pub fn release(&mut self) {
+        self.image = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Rgb};
+
+    fn save_test_image(dir: &Path, width: u32, height: u32) -> PathBuf {
+        let path = dir.join("page.png");
+        ImageBuffer::from_pixel(width, height, Rgb([200u8, 200u8, 200u8]))
+            .save(&path)
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_downscaled_keeps_aspect_ratio() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = save_test_image(dir.path(), 400, 200);
+
+        let full = load_image(&path, &LoadOptions::full()).unwrap();
+        assert_eq!((full.width(), full.height()), (400, 200));
+        let small = load_image(&path, &LoadOptions::downscaled(100)).unwrap();
+        assert_eq!((small.width(), small.height()), (100, 50));
+        // Never upscaled
+        let same = load_image(&path, &LoadOptions::downscaled(1000)).unwrap();
+        assert_eq!(same.width(), 400);
+    }
+
+    #[test]
+    fn test_max_alloc_rejects_large_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = save_test_image(dir.path(), 400, 200);
+        let options = LoadOptions {
+            max_alloc: Some(1024),
+            ..LoadOptions::default()
+        };
+        assert!(load_image(&path, &options).is_err());
+    }
+
+    #[test]
+    fn test_lazy_image() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = save_test_image(dir.path(), 40, 20);
+
+        let mut lazy = LazyImage::new(&path, LoadOptions::full());
+        assert_eq!(lazy.dimensions().unwrap(), (40, 20));
+        assert!(!lazy.is_loaded());
+        assert_eq!(lazy.get().unwrap().width(), 40);
+        assert!(lazy.is_loaded());
+        lazy.release();
+        assert!(!lazy.is_loaded());
+    }
+
+    #[test]
+    fn test_supported_extensions() {
+        assert!(is_supported_extension("JPG"));
+        assert!(is_supported_extension("webp"));
+        assert_eq!(is_supported_extension("heic"), cfg!(feature = "heic"));
+        assert!(!is_supported_extension("pdf"));
+    }
+
+    #[test]
+    fn test_load_webp() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("card.webp");
+        ImageBuffer::from_pixel(30, 20, Rgb([10u8, 20, 30]))
+            .save(&path)
+            .unwrap();
+        let image = load_image(&path, &LoadOptions::full()).unwrap();
+        assert_eq!((image.width(), image.height()), (30, 20));
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let image = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(4, 4, Rgb([0u8, 0, 0])));
+        let bytes = encode_png(&image).unwrap();
+        assert_eq!(&bytes[1..4], b"PNG");
+    }
+}
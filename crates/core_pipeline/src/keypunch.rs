@@ -0,0 +1,426 @@
+//! Virtual keypunch: Hollerith hole patterns for transcribed cards
+//!
+//! Renders the holes a keypunch would have made for a card's transcribed
+//! text and compares them with the holes detected in the scanned card. The
+//! holes are an encoding independent of the printed line at the top of the
+//! card, so columns where they disagree with the transcription point to OCR
+//! errors (or to a print line that does not match the punches).
+//!
+//! Hole positions follow the standard card: 7.375" x 3.25", column 1
+//! centered 0.251" from the left edge with 0.087" between columns, row 12
+//! centered 0.25" from the top with 0.25" between rows. Scans are expected
+//! to be cropped to the card edges and deskewed, with holes showing darker
+//! than the card stock (dark scanner backing).
+//!
+//! Hole patterns depend on the [`KeypunchModel`] the deck was punched on,
+//! set per scan set with per-document overrides ([`KeypunchSettings`]).
+
+use crate::export::CARD_COLUMNS;
+use crate::ocr::IBM1130_CHARSET;
+use image::{GrayImage, Luma};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Punch rows, top to bottom
+pub const ROWS: [u8; 12] = [12, 11, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+
+/// Holes of one column, one bit per entry of [`ROWS`] (bit 0 is row 12)
+pub type ColumnHoles = u16;
+
+/// Card geometry in inches
+pub(crate) const CARD_WIDTH: f32 = 7.375;
+pub(crate) const CARD_HEIGHT: f32 = 3.25;
+pub(crate) const FIRST_COLUMN_X: f32 = 0.251;
+pub(crate) const COLUMN_PITCH: f32 = 0.087;
+pub(crate) const FIRST_ROW_Y: f32 = 0.25;
+pub(crate) const ROW_PITCH: f32 = 0.25;
+const HOLE_WIDTH: f32 = 0.055;
+const HOLE_HEIGHT: f32 = 0.125;
+
+/// IBM 029 card codes of the special characters the 1130 prints
+const CODES_029: &[(char, &[u8])] = &[
+    ('&', &[12]),
+    ('-', &[11]),
+    ('/', &[0, 1]),
+    ('.', &[12, 3, 8]),
+    ('<', &[12, 4, 8]),
+    ('(', &[12, 5, 8]),
+    ('+', &[12, 6, 8]),
+    ('|', &[12, 7, 8]),
+    ('!', &[11, 2, 8]),
+    ('$', &[11, 3, 8]),
+    ('*', &[11, 4, 8]),
+    (')', &[11, 5, 8]),
+    (';', &[11, 6, 8]),
+    (',', &[0, 3, 8]),
+    ('%', &[0, 4, 8]),
+    ('_', &[0, 5, 8]),
+    ('>', &[0, 6, 8]),
+    ('?', &[0, 7, 8]),
+    (':', &[2, 8]),
+    ('#', &[3, 8]),
+    ('@', &[4, 8]),
+    ('\'', &[5, 8]),
+    ('=', &[6, 8]),
+    ('"', &[7, 8]),
+];
+
+/// IBM 026 FORTRAN ("H") card codes: same holes as the commercial set,
+/// printed as FORTRAN operators
+const CODES_026_FORTRAN: &[(char, &[u8])] = &[
+    ('+', &[12]),
+    ('-', &[11]),
+    ('/', &[0, 1]),
+    ('.', &[12, 3, 8]),
+    (')', &[12, 4, 8]),
+    ('$', &[11, 3, 8]),
+    ('*', &[11, 4, 8]),
+    (',', &[0, 3, 8]),
+    ('(', &[0, 4, 8]),
+    ('=', &[3, 8]),
+    ('\'', &[4, 8]),
+];
+
+/// IBM 026 commercial ("A") card codes
+const CODES_026_COMMERCIAL: &[(char, &[u8])] = &[
+    ('&', &[12]),
+    ('-', &[11]),
+    ('/', &[0, 1]),
+    ('.', &[12, 3, 8]),
+    ('¤', &[12, 4, 8]),
+    ('$', &[11, 3, 8]),
+    ('*', &[11, 4, 8]),
+    (',', &[0, 3, 8]),
+    ('%', &[0, 4, 8]),
+    ('#', &[3, 8]),
+    ('@', &[4, 8]),
+];
+
+/// Keypunch a deck was punched on
+///
+/// The models punch letters and digits alike but print different graphics
+/// for some hole patterns: 12 is `&` on an 029 and `+` on an 026 with the
+/// FORTRAN character set, 12-4-8 is `<` on an 029 and `)` on the 026.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeypunchModel {
+    /// IBM 029 (EBCDIC card code, the 1130's own character set)
+    #[default]
+    #[serde(rename = "029")]
+    Ibm029,
+    /// IBM 026 with the FORTRAN character set
+    #[serde(rename = "026-fortran")]
+    Ibm026Fortran,
+    /// IBM 026 with the commercial character set
+    #[serde(rename = "026-commercial")]
+    Ibm026Commercial,
+}
+
+impl KeypunchModel {
+    /// Every model
+    pub const ALL: [Self; 3] = [Self::Ibm029, Self::Ibm026Fortran, Self::Ibm026Commercial];
+
+    /// Parse a model name ("029", "026-fortran" or "026-commercial")
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|model| model.as_str().eq_ignore_ascii_case(name))
+    }
+
+    /// Name of the model
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ibm029 => "029",
+            Self::Ibm026Fortran => "026-fortran",
+            Self::Ibm026Commercial => "026-commercial",
+        }
+    }
+
+    /// Characters the model prints, for the OCR whitelist
+    pub fn charset(&self) -> &'static str {
+        match self {
+            Self::Ibm029 => IBM1130_CHARSET,
+            Self::Ibm026Fortran => "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 +-/.)$*,(='",
+            Self::Ibm026Commercial => "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 &-/.¤$*,%#@",
+        }
+    }
+
+    fn special_codes(&self) -> &'static [(char, &'static [u8])] {
+        match self {
+            Self::Ibm029 => CODES_029,
+            Self::Ibm026Fortran => CODES_026_FORTRAN,
+            Self::Ibm026Commercial => CODES_026_COMMERCIAL,
+        }
+    }
+
+    /// Hole pattern of a character, `None` if the model cannot punch it
+    ///
+    /// Lowercase letters are punched as uppercase, like a keypunch does.
+    pub fn hollerith(&self, c: char) -> Option<ColumnHoles> {
+        let c = c.to_ascii_uppercase();
+        match c {
+            ' ' => Some(0),
+            '0'..='9' => Some(punches(&[c as u8 - b'0'])),
+            'A'..='I' => Some(punches(&[12, c as u8 - b'A' + 1])),
+            'J'..='R' => Some(punches(&[11, c as u8 - b'J' + 1])),
+            'S'..='Z' => Some(punches(&[0, c as u8 - b'S' + 2])),
+            _ => self
+                .special_codes()
+                .iter()
+                .find(|(code, _)| *code == c)
+                .map(|(_, rows)| punches(rows)),
+        }
+    }
+
+    /// Character the model prints for the given holes
+    pub fn decode(&self, holes: ColumnHoles) -> Option<char> {
+        self.charset()
+            .chars()
+            .find(|&c| self.hollerith(c) == Some(holes))
+    }
+}
+
+/// Keypunch models of a scan set
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeypunchSettings {
+    /// Model most of the scan set was punched on
+    pub model: KeypunchModel,
+    /// Documents punched on another model, by document name
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub documents: BTreeMap<String, KeypunchModel>,
+}
+
+impl KeypunchSettings {
+    /// Model of a document (the scan set's model for unnamed documents and
+    /// documents without an override)
+    pub fn for_document(&self, name: Option<&str>) -> KeypunchModel {
+        name.and_then(|name| self.documents.get(name))
+            .copied()
+            .unwrap_or(self.model)
+    }
+}
+
+/// Holes for a set of punch rows (12, 11, 0-9)
+fn punches(rows: &[u8]) -> ColumnHoles {
+    rows.iter()
+        .filter_map(|row| ROWS.iter().position(|r| r == row))
+        .fold(0, |holes, idx| holes | 1 << idx)
+}
+
+/// Hole patterns of a card's text, padded with blank columns
+///
+/// Characters the model cannot punch leave their column blank.
+pub fn card_holes(text: &str, model: KeypunchModel) -> [ColumnHoles; CARD_COLUMNS] {
+    let mut holes = [0; CARD_COLUMNS];
+    for (column, c) in text.chars().take(CARD_COLUMNS).enumerate() {
+        holes[column] = model.hollerith(c).unwrap_or(0);
+    }
+    holes
+}
+
+/// Pixel rectangle `(x, y, width, height)` of a hole in a card image
+pub(crate) fn hole_rect(
+    width: u32,
+    height: u32,
+    column: usize,
+    row: usize,
+) -> (u32, u32, u32, u32) {
+    let sx = width as f32 / CARD_WIDTH;
+    let sy = height as f32 / CARD_HEIGHT;
+    let cx = (FIRST_COLUMN_X + column as f32 * COLUMN_PITCH) * sx;
+    let cy = (FIRST_ROW_Y + row as f32 * ROW_PITCH) * sy;
+    let w = (HOLE_WIDTH * sx).max(1.0);
+    let h = (HOLE_HEIGHT * sy).max(1.0);
+    let x = (cx - w / 2.0).max(0.0) as u32;
+    let y = (cy - h / 2.0).max(0.0) as u32;
+    (
+        x,
+        y,
+        (w as u32).max(1).min(width - x),
+        (h as u32).max(1).min(height - y),
+    )
+}
+
+/// Render a card with the given holes: white stock, black holes
+pub fn render_card(holes: &[ColumnHoles; CARD_COLUMNS], width: u32) -> GrayImage {
+    let height = (width as f32 * CARD_HEIGHT / CARD_WIDTH).round() as u32;
+    let mut image = GrayImage::from_pixel(width, height, Luma([255]));
+    for (column, &pattern) in holes.iter().enumerate() {
+        for row in (0..ROWS.len()).filter(|row| pattern & 1 << row != 0) {
+            let (x, y, w, h) = hole_rect(width, height, column, row);
+            for py in y..y + h {
+                for px in x..x + w {
+                    image.put_pixel(px, py, Luma([0]));
+                }
+            }
+        }
+    }
+    image
+}
+
+/// How dark each hole position of a scanned card is, from 0.0 (card stock)
+/// to 1.0 (black), by column and row
+///
+/// Darkness is measured against the card's overall brightness, over the
+/// central half of each hole position to tolerate slight misregistration.
+pub fn hole_darkness(image: &GrayImage) -> Vec<[f32; 12]> {
+    let (width, height) = image.dimensions();
+    let pixels = image.as_raw();
+    let background = pixels.iter().map(|&p| p as f64).sum::<f64>() / pixels.len().max(1) as f64;
+
+    (0..CARD_COLUMNS)
+        .map(|column| {
+            let mut rows = [0.0; 12];
+            for (row, darkness) in rows.iter_mut().enumerate() {
+                let (x, y, w, h) = hole_rect(width, height, column, row);
+                let (x, y) = (x + w / 4, y + h / 4);
+                let (w, h) = ((w / 2).max(1), (h / 2).max(1));
+                let (mut sum, mut count) = (0u64, 0u64);
+                for py in y..(y + h).min(height) {
+                    for px in x..(x + w).min(width) {
+                        sum += image.get_pixel(px, py)[0] as u64;
+                        count += 1;
+                    }
+                }
+                let mean = sum as f64 / count.max(1) as f64;
+                *darkness = if background > 0.0 {
+                    (1.0 - mean / background).clamp(0.0, 1.0) as f32
+                } else {
+                    0.0
+                };
+            }
+            rows
+        })
+        .collect()
+}
+
+/// Holes detected in a scanned card (hole positions at least half dark)
+pub fn detect_holes(image: &GrayImage) -> [ColumnHoles; CARD_COLUMNS] {
+    let mut holes = [0; CARD_COLUMNS];
+    for (column, rows) in hole_darkness(image).iter().enumerate() {
+        for (row, &darkness) in rows.iter().enumerate() {
+            if darkness >= 0.5 {
+                holes[column] |= 1 << row;
+            }
+        }
+    }
+    holes
+}
+
+/// Agreement between transcription and punches in one column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnComparison {
+    /// Column number (1-80)
+    pub column: usize,
+    /// Holes the transcribed character needs
+    pub expected: ColumnHoles,
+    /// Holes found in the scan
+    pub detected: ColumnHoles,
+    /// Mean difference between expected and measured darkness over the
+    /// column's rows, from 0.0 (agree) to 1.0 (every row wrong)
+    pub disagreement: f32,
+}
+
+/// Compare a card's transcribed text with the holes in its scan, column by
+/// column
+pub fn compare_card(text: &str, image: &GrayImage, model: KeypunchModel) -> Vec<ColumnComparison> {
+    let expected = card_holes(text, model);
+    let darkness = hole_darkness(image);
+    let detected = detect_holes(image);
+    (0..CARD_COLUMNS)
+        .map(|column| {
+            let error: f32 = darkness[column]
+                .iter()
+                .enumerate()
+                .map(|(row, &dark)| {
+                    let punched = if expected[column] & 1 << row != 0 {
+                        1.0
+                    } else {
+                        0.0
+                    };
+                    (punched - dark).abs()
+                })
+                .sum();
+            ColumnComparison {
+                column: column + 1,
+                expected: expected[column],
+                detected: detected[column],
+                disagreement: error / ROWS.len() as f32,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IBM029: KeypunchModel = KeypunchModel::Ibm029;
+
+    #[test]
+    fn test_hollerith_codes() {
+        assert_eq!(IBM029.hollerith(' '), Some(0));
+        assert_eq!(IBM029.hollerith('A'), Some(punches(&[12, 1])));
+        assert_eq!(IBM029.hollerith('s'), Some(punches(&[0, 2])));
+        assert_eq!(IBM029.hollerith('/'), Some(punches(&[0, 1])));
+        assert_eq!(IBM029.hollerith('='), Some(punches(&[6, 8])));
+        assert_eq!(IBM029.hollerith('~'), None);
+        assert_eq!(IBM029.decode(punches(&[11, 9])), Some('R'));
+    }
+
+    #[test]
+    fn test_026_graphics() {
+        let fortran = KeypunchModel::Ibm026Fortran;
+        assert_eq!(fortran.hollerith('+'), IBM029.hollerith('&'));
+        assert_eq!(fortran.hollerith(')'), IBM029.hollerith('<'));
+        assert_eq!(fortran.hollerith('='), IBM029.hollerith('#'));
+        assert_eq!(fortran.hollerith('<'), None);
+        assert_eq!(fortran.decode(punches(&[0, 4, 8])), Some('('));
+        assert_eq!(
+            KeypunchModel::Ibm026Commercial.decode(punches(&[12])),
+            Some('&')
+        );
+    }
+
+    #[test]
+    fn test_charsets_match_codes() {
+        for model in KeypunchModel::ALL {
+            for c in model.charset().chars() {
+                let holes = model.hollerith(c).unwrap();
+                assert_eq!(model.decode(holes), Some(c), "{} {:?}", model.as_str(), c);
+            }
+            assert_eq!(KeypunchModel::parse(model.as_str()), Some(model));
+        }
+    }
+
+    #[test]
+    fn test_document_override() {
+        let settings = KeypunchSettings {
+            model: KeypunchModel::Ibm026Fortran,
+            documents: BTreeMap::from([("PAYROLL".to_string(), IBM029)]),
+        };
+        assert_eq!(settings.for_document(Some("PAYROLL")), IBM029);
+        assert_eq!(settings.for_document(None), KeypunchModel::Ibm026Fortran);
+    }
+
+    #[test]
+    fn test_render_and_detect_round_trip() {
+        let text = "      CALL EXIT(1) = 'A/B', X.LT.Y $* #@";
+        let holes = card_holes(text, IBM029);
+        assert_eq!(detect_holes(&render_card(&holes, 1475)), holes);
+    }
+
+    #[test]
+    fn test_compare_flags_wrong_column() {
+        let image = render_card(&card_holes("      X = 1", IBM029), 1475);
+        let columns = compare_card("      X = 7", &image, IBM029);
+        assert_eq!(columns.len(), CARD_COLUMNS);
+        let flagged: Vec<usize> = columns
+            .iter()
+            .filter(|c| c.disagreement > 0.1)
+            .map(|c| c.column)
+            .collect();
+        assert_eq!(flagged, vec![11]);
+        assert_eq!(IBM029.decode(columns[10].detected), Some('1'));
+    }
+}
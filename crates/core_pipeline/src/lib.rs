@@ -6,9 +6,31 @@
 //!
 //! Copyright (c) 2025 Michael A Wright
 
+pub mod autofix;
+pub mod card_render;
+pub mod classify;
 pub mod decoder;
+pub mod derived;
+pub mod diff;
+pub mod error;
+pub mod export;
+pub mod filter;
+pub mod grid;
+pub mod hooks;
+pub mod image_loader;
+pub mod keypunch;
 pub mod ocr;
+pub mod ocr_diff;
 pub mod preprocess;
+pub mod profile;
+pub mod reconstruct;
+pub mod scan_set;
+pub mod score;
+pub mod stage_cache;
+pub mod stats;
+pub mod storage;
 pub mod types;
+pub mod validate;
 
+pub use error::{Error, Result};
 pub use types::*;
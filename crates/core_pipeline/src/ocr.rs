@@ -3,61 +3,168 @@
 //! Provides baseline OCR capabilities using Tesseract (via leptess).
 //! This is the non-LLM approach for text extraction.
 
-use anyhow::{Context, Result};
+use crate::derived::DerivedKey;
+use crate::error::{Error, Result};
+use crate::keypunch::KeypunchModel;
+use crate::stage_cache::CacheKey;
 use image::GrayImage;
-use leptess::{LepTess, Variable};
+use leptess::tesseract::TessApi;
+use leptess::Variable;
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+
+/// IBM 1130 character whitelist
+///
+/// Uppercase A-Z, digits 0-9, and punch card special characters.
+/// No lowercase - punch cards don't have lowercase. This is the IBM 029
+/// set; decks from other keypunches use [`KeypunchModel::charset`].
+pub const IBM1130_CHARSET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 +-*/=().,;:$#@'&|_<>?!\"";
+
+/// Tesseract settings that affect OCR output (cache key parameters):
+/// language, character whitelist and resolution
+pub fn ocr_params(keypunch: KeypunchModel) -> (&'static str, &'static str, i32) {
+    ("eng", keypunch.charset(), 300)
+}
+
+/// Stage cache key of the [`OcrOutput`] for a preprocessed image
+pub fn ocr_cache_key(preprocessed: &DerivedKey, keypunch: KeypunchModel) -> Result<CacheKey> {
+    CacheKey::new("ocr", &preprocessed.id(), &ocr_params(keypunch))
+}
+
+/// A word recognized by OCR, with its position in the image
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OcrWord {
+    /// Recognized text
+    pub text: String,
+    /// Text block number (1-based, from Tesseract)
+    pub block: u32,
+    /// Paragraph number within the block
+    pub paragraph: u32,
+    /// Line number within the paragraph
+    pub line: u32,
+    /// Left edge of the bounding box, in image pixels
+    pub left: u32,
+    /// Top edge of the bounding box
+    pub top: u32,
+    /// Width of the bounding box
+    pub width: u32,
+    /// Height of the bounding box
+    pub height: u32,
+    /// Recognition confidence (0-100)
+    pub confidence: f32,
+}
+
+/// OCR text together with word coordinates
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OcrOutput {
+    /// Page text, preserving layout
+    pub text: String,
+    /// Recognized words in reading order
+    pub words: Vec<OcrWord>,
+}
 
 /// Extract text from an image using Tesseract OCR with layout preservation
 ///
 /// Configures Tesseract to preserve whitespace and column alignment for punch cards.
 /// Uses PSM (Page Segmentation Mode) 6 for uniform block of text.
-/// Restricts to IBM 1130 character set for better accuracy.
+/// Restricts to the characters of the deck's keypunch for better accuracy.
 ///
 /// # Arguments
 /// * `input` - Grayscale image to extract text from
+/// * `keypunch` - Keypunch model whose character set is whitelisted
 ///
 /// # Returns
 /// * Extracted text as a string, preserving layout and whitespace
 ///
 /// # Errors
 /// * Returns error if Tesseract is not installed or OCR fails
-pub fn extract_text_tesseract(input: &GrayImage) -> Result<String> {
+pub fn extract_text_tesseract(input: &GrayImage, keypunch: KeypunchModel) -> Result<String> {
+    Ok(extract_ocr_tesseract(input, keypunch)?.text)
+}
+
+/// Check that Tesseract can be initialized with the language data used
+/// for a keypunch
+pub fn check_tesseract(keypunch: KeypunchModel) -> Result<()> {
+    let (language, _, _) = ocr_params(keypunch);
+    TessApi::new(None, language).map_err(|source| Error::OcrEngineMissing { source })?;
+    Ok(())
+}
+
+/// Extract text and word coordinates from an image using Tesseract OCR
+///
+/// Same settings as [`extract_text_tesseract`]; the words come from the
+/// same recognition pass.
+pub fn extract_ocr_tesseract(input: &GrayImage, keypunch: KeypunchModel) -> Result<OcrOutput> {
+    let (language, charset, dpi) = ocr_params(keypunch);
+
     // Initialize Tesseract
-    let mut tesseract = LepTess::new(None, "eng")
-        .context("Failed to initialize Tesseract. Is Tesseract installed?")?;
+    let mut tesseract =
+        TessApi::new(None, language).map_err(|source| Error::OcrEngineMissing { source })?;
 
-    // IBM 1130 character whitelist
-    // Uppercase A-Z, digits 0-9, and punch card special characters
-    // No lowercase - punch cards don't have lowercase
-    let ibm1130_chars = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 +-*/=().,;:$#@'&|_<>?!\"";
+    let whitelist = CString::new(charset)
+        .map_err(|_| Error::Ocr("Character whitelist contains a NUL byte".to_string()))?;
     tesseract
-        .set_variable(Variable::TesseditCharWhitelist, ibm1130_chars)
-        .context("Failed to set character whitelist")?;
-
-    // Convert GrayImage to PNG bytes for leptess
-    // leptess requires image data in a standard format (PNG, JPEG, etc.)
-    let mut png_bytes = Vec::new();
-    let mut cursor = std::io::Cursor::new(&mut png_bytes);
-    input
-        .write_to(&mut cursor, image::ImageFormat::Png)
-        .context("Failed to encode image as PNG")?;
-
-    // Set image in Tesseract
+        .raw
+        .set_variable(Variable::TesseditCharWhitelist.as_cstr(), &whitelist)
+        .map_err(|_| Error::Ocr("Failed to set character whitelist".to_string()))?;
+
+    // Hand the 8-bit pixels to Tesseract directly (one byte per pixel,
+    // rows packed without padding) instead of encoding an image file
+    let (width, height) = input.dimensions();
+    let width =
+        i32::try_from(width).map_err(|_| Error::Ocr("Image too wide for Tesseract".to_string()))?;
+    let height = i32::try_from(height)
+        .map_err(|_| Error::Ocr("Image too tall for Tesseract".to_string()))?;
     tesseract
-        .set_image_from_mem(&png_bytes)
-        .context("Failed to load image into Tesseract")?;
+        .raw
+        .set_image(input.as_raw(), width, height, 1, width)
+        .map_err(|e| Error::Ocr(format!("Failed to load image into Tesseract: {}", e)))?;
 
     // Set higher DPI for better recognition
     // Tesseract works best at 300 DPI
     // Must be called AFTER set_image
-    tesseract.set_source_resolution(300);
+    tesseract.set_source_resolution(dpi);
 
-    // Extract text
+    // Extract text, then the word boxes of the same recognition
     let text = tesseract
         .get_utf8_text()
-        .context("Failed to extract text from image")?;
+        .map_err(|e| Error::Ocr(format!("Failed to extract text from image: {}", e)))?;
+    let tsv = tesseract
+        .get_tsv_text(0)
+        .map_err(|e| Error::Ocr(format!("Failed to extract word boxes from image: {}", e)))?;
+
+    Ok(OcrOutput {
+        text,
+        words: parse_tesseract_tsv(&tsv),
+    })
+}
 
-    Ok(text)
+/// Parse the words out of Tesseract's TSV output
+///
+/// Columns: level, page, block, paragraph, line, word, left, top, width,
+/// height, confidence, text. Only word rows (level 5) with text are kept;
+/// the header and malformed rows are skipped.
+pub fn parse_tesseract_tsv(tsv: &str) -> Vec<OcrWord> {
+    tsv.lines()
+        .filter_map(|row| {
+            let fields: Vec<&str> = row.splitn(12, '\t').collect();
+            if fields.len() < 12 || fields[0] != "5" || fields[11].trim().is_empty() {
+                return None;
+            }
+            let num = |i: usize| fields[i].parse::<u32>().ok();
+            Some(OcrWord {
+                text: fields[11].trim().to_string(),
+                block: num(2)?,
+                paragraph: num(3)?,
+                line: num(4)?,
+                left: num(6)?,
+                top: num(7)?,
+                width: num(8)?,
+                height: num(9)?,
+                confidence: fields[10].parse().ok()?,
+            })
+        })
+        .collect()
 }
 
 /// Extract 80-column card text from a card image
@@ -79,7 +186,7 @@ mod tests {
     fn test_extract_text_returns_string() {
         // Simple test: black image should return empty or whitespace
         let img = ImageBuffer::from_pixel(100, 100, Luma([0u8]));
-        let result = extract_text_tesseract(&img);
+        let result = extract_text_tesseract(&img, KeypunchModel::default());
         assert!(result.is_ok());
         // Result should be a string (even if empty)
         let text = result.unwrap();
@@ -90,7 +197,7 @@ mod tests {
     fn test_extract_text_white_image() {
         // White image (no text) should return empty string
         let img = ImageBuffer::from_pixel(100, 100, Luma([255u8]));
-        let result = extract_text_tesseract(&img);
+        let result = extract_text_tesseract(&img, KeypunchModel::default());
         assert!(result.is_ok());
         let text = result.unwrap();
         assert!(text.is_empty() || text.trim().is_empty());
@@ -101,7 +208,7 @@ mod tests {
         // If Tesseract is not installed, should return meaningful error
         // This test documents expected behavior, implementation will determine actual behavior
         let img = ImageBuffer::from_pixel(100, 100, Luma([0u8]));
-        let result = extract_text_tesseract(&img);
+        let result = extract_text_tesseract(&img, KeypunchModel::default());
         // For now, we expect it to work if Tesseract is installed
         // or fail gracefully if not
         match result {
@@ -114,6 +221,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_tesseract_tsv() {
+        let tsv = "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext\n\
+                   4\t1\t1\t1\t1\t0\t10\t20\t300\t30\t-1\t\n\
+                   5\t1\t1\t1\t1\t1\t10\t20\t40\t30\t91.5\tX\n\
+                   5\t1\t1\t1\t1\t2\t60\t20\t20\t30\t95\t=\n\
+                   5\t1\t1\t1\t2\t1\t10\t60\t40\t30\t-1\t \n";
+        let words = parse_tesseract_tsv(tsv);
+        assert_eq!(words.len(), 2);
+        assert_eq!(words[0].text, "X");
+        assert_eq!((words[0].left, words[0].top), (10, 20));
+        assert_eq!(words[0].confidence, 91.5);
+        assert_eq!(words[1].line, 1);
+    }
+
     #[test]
     fn test_extract_card_text_length() {
         let img = ImageBuffer::from_pixel(100, 100, Luma([0u8]));
@@ -0,0 +1,389 @@
+//! Character-level changes made to raw OCR text
+//!
+//! Compares the raw Tesseract text of each artifact (from the stage cache)
+//! with its final text, after vision correction, auto-fix and hooks, and
+//! lists every character that was substituted, deleted or inserted. The
+//! changes are aggregated into a confusion table (`O` -> `0`: 37 times),
+//! showing which kinds of correction are made often and deserve trust or
+//! an audit.
+//!
+//! Lines are matched first and characters aligned within changed lines,
+//! so a line added or dropped by the model counts its characters as
+//! insertions or deletions rather than shifting every later line.
+
+use crate::diff::{line_diff, DiffLine};
+use crate::error::Result;
+use crate::scan_set;
+use crate::score::{cached_ocr_text, normalized_lines};
+use crate::stage_cache::StageCache;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// One character changed between the raw OCR text and the final text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CharChange {
+    /// Line of the final text (1-based)
+    pub line: usize,
+    /// Column of the final text (1-based); for a deletion, where the
+    /// character was
+    pub column: usize,
+    /// Character in the OCR text (none for an insertion)
+    pub from: Option<char>,
+    /// Character in the final text (none for a deletion)
+    pub to: Option<char>,
+}
+
+/// Number of times one character was changed into another
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Confusion {
+    /// Character in the OCR text (none for insertions)
+    pub from: Option<char>,
+    /// Character in the final text (none for deletions)
+    pub to: Option<char>,
+    /// Times the change was made
+    pub count: usize,
+}
+
+/// Changes to the OCR text of one artifact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactChanges {
+    /// Artifact identifier
+    pub artifact_id: String,
+    /// Raw image path (relative to the scan set)
+    pub image: PathBuf,
+    /// Changed characters, in text order
+    pub changes: Vec<CharChange>,
+}
+
+/// Character changes to the OCR text of a scan set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OcrDiffReport {
+    /// Human-readable scan set name
+    pub name: String,
+    /// Artifacts whose text differs from the OCR text
+    pub artifacts: Vec<ArtifactChanges>,
+    /// Artifacts compared (with both OCR and final text)
+    pub compared: usize,
+    /// Artifacts without cached OCR text or without final text (not
+    /// compared); human transcriptions are not compared either
+    pub skipped: usize,
+    /// Changes by kind, most frequent first
+    pub confusions: Vec<Confusion>,
+}
+
+impl OcrDiffReport {
+    /// Compare the OCR text of every artifact of a scan set with its final
+    /// text
+    pub fn of_scan_set(scan_set_dir: &Path) -> Result<Self> {
+        let (manifest, artifacts) = scan_set::load(scan_set_dir)?;
+        let stage_cache = StageCache::new(scan_set_dir);
+        let mut report = Self {
+            name: manifest.name.clone(),
+            artifacts: Vec::new(),
+            compared: 0,
+            skipped: 0,
+            confusions: Vec::new(),
+        };
+
+        let mut counts: BTreeMap<(Option<char>, Option<char>), usize> = BTreeMap::new();
+        for artifact in &artifacts {
+            let ocr_text = if artifact.metadata.human_transcribed {
+                None
+            } else {
+                cached_ocr_text(scan_set_dir, &stage_cache, artifact, &manifest)?
+            };
+            let (Some(ocr_text), Some(text)) = (ocr_text, artifact.content_text.as_deref()) else {
+                report.skipped += 1;
+                continue;
+            };
+            report.compared += 1;
+
+            let changes = char_changes(&ocr_text, text);
+            for change in &changes {
+                *counts.entry((change.from, change.to)).or_default() += 1;
+            }
+            if !changes.is_empty() {
+                report.artifacts.push(ArtifactChanges {
+                    artifact_id: artifact.id.0.to_string(),
+                    image: artifact.raw_image_path.clone(),
+                    changes,
+                });
+            }
+        }
+
+        report.confusions = counts
+            .into_iter()
+            .map(|((from, to), count)| Confusion { from, to, count })
+            .collect();
+        report.confusions.sort_by(|a, b| {
+            b.count
+                .cmp(&a.count)
+                .then((a.from, a.to).cmp(&(b.from, b.to)))
+        });
+        Ok(report)
+    }
+}
+
+/// Characters changed from one text to another
+///
+/// Trailing whitespace and trailing blank lines are ignored, as in
+/// [`crate::score::Accuracy::measure`].
+pub fn char_changes(from: &str, to: &str) -> Vec<CharChange> {
+    let from = normalized_lines(from);
+    let to = normalized_lines(to);
+    let mut changes = Vec::new();
+
+    // Pair the removed and added lines of each run of changed lines;
+    // `line` counts the lines of the final text passed so far
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let mut line = 0;
+    let end = DiffLine::Same(String::new());
+    for diff_line in line_diff(&from, &to).into_iter().chain([end]) {
+        match diff_line {
+            DiffLine::Removed(text) => removed.push(text),
+            DiffLine::Added(text) => {
+                added.push(text);
+                line += 1;
+            }
+            DiffLine::Same(_) => {
+                let first = line - added.len();
+                for idx in 0..removed.len().max(added.len()) {
+                    let old = removed.get(idx).map_or("", String::as_str);
+                    let new = added.get(idx).map_or("", String::as_str);
+                    // Lines dropped beyond the added ones are reported at
+                    // the line following the run
+                    let at = if idx < added.len() { first + idx } else { line };
+                    changes.extend(line_changes(old, new, at + 1));
+                }
+                removed.clear();
+                added.clear();
+                line += 1;
+            }
+        }
+    }
+    changes
+}
+
+/// Lines of `text`, each character with whether it differs from `other`
+///
+/// A character is marked if `other` has another character in its place or
+/// none; marking both texts against each other highlights a change on
+/// both sides. Lines are those of [`char_changes`].
+pub fn changed_chars(text: &str, other: &str) -> Vec<Vec<(char, bool)>> {
+    let mut lines: Vec<Vec<(char, bool)>> = normalized_lines(text)
+        .into_iter()
+        .map(|line| line.chars().map(|c| (c, false)).collect())
+        .collect();
+    for change in char_changes(other, text) {
+        if change.to.is_none() {
+            continue;
+        }
+        if let Some(c) = lines
+            .get_mut(change.line - 1)
+            .and_then(|line| line.get_mut(change.column - 1))
+        {
+            c.1 = true;
+        }
+    }
+    lines
+}
+
+/// Lines of `text`, each character with whether `other` has another
+/// character, or none, at the same column
+///
+/// Unlike [`changed_chars`], characters are not aligned: an inserted or
+/// dropped character marks the rest of its line, showing where columns
+/// drift. Lines are paired as in [`char_changes`]; lines `other` lacks are
+/// marked whole.
+pub fn column_changed_chars(text: &str, other: &str) -> Vec<Vec<(char, bool)>> {
+    let lines = normalized_lines(text);
+    let other_lines = normalized_lines(other);
+    let mut marked: Vec<Vec<(char, bool)>> = Vec::new();
+
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let end = DiffLine::Same(String::new());
+    for diff_line in line_diff(&other_lines, &lines).into_iter().chain([end]) {
+        match diff_line {
+            DiffLine::Removed(line) => removed.push(line),
+            DiffLine::Added(line) => added.push(line),
+            DiffLine::Same(line) => {
+                for (idx, new) in added.iter().enumerate() {
+                    let old: Vec<char> =
+                        removed.get(idx).map_or(Vec::new(), |l| l.chars().collect());
+                    marked.push(
+                        new.chars()
+                            .enumerate()
+                            .map(|(column, c)| (c, old.get(column) != Some(&c)))
+                            .collect(),
+                    );
+                }
+                removed.clear();
+                added.clear();
+                marked.push(line.chars().map(|c| (c, false)).collect());
+            }
+        }
+    }
+    // The end marker
+    marked.pop();
+    marked
+}
+
+/// Characters changed within one line, aligned by edit distance
+fn line_changes(from: &str, to: &str, line: usize) -> Vec<CharChange> {
+    let a: Vec<char> = from.chars().collect();
+    let b: Vec<char> = to.chars().collect();
+
+    // distance[i][j]: edit distance of a[i..] and b[j..]
+    let mut distance = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..=a.len()).rev() {
+        for j in (0..=b.len()).rev() {
+            distance[i][j] = if i == a.len() {
+                b.len() - j
+            } else if j == b.len() {
+                a.len() - i
+            } else {
+                let substitute = distance[i + 1][j + 1] + usize::from(a[i] != b[j]);
+                substitute
+                    .min(distance[i + 1][j] + 1)
+                    .min(distance[i][j + 1] + 1)
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        let column = j + 1;
+        if i < a.len()
+            && j < b.len()
+            && distance[i][j] == distance[i + 1][j + 1] + usize::from(a[i] != b[j])
+        {
+            if a[i] != b[j] {
+                changes.push(CharChange {
+                    line,
+                    column,
+                    from: Some(a[i]),
+                    to: Some(b[j]),
+                });
+            }
+            i += 1;
+            j += 1;
+        } else if i < a.len() && distance[i][j] == distance[i + 1][j] + 1 {
+            changes.push(CharChange {
+                line,
+                column,
+                from: Some(a[i]),
+                to: None,
+            });
+            i += 1;
+        } else {
+            changes.push(CharChange {
+                line,
+                column,
+                from: None,
+                to: Some(b[j]),
+            });
+            j += 1;
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(line: usize, column: usize, from: Option<char>, to: Option<char>) -> CharChange {
+        CharChange {
+            line,
+            column,
+            from,
+            to,
+        }
+    }
+
+    #[test]
+    fn test_char_changes() {
+        let ocr = "      D0 10 I=1,5\n      X = Y\n      STOP\n      END  \n";
+        let fixed = "      DO 10 I=1,5\n      X = Y\n      CALL EXIT\n      STOP\n      END\n";
+        assert_eq!(
+            char_changes(ocr, fixed),
+            vec![
+                change(1, 8, Some('0'), Some('O')),
+                // The inserted line counts its characters as insertions
+                change(3, 1, None, Some(' ')),
+                change(3, 2, None, Some(' ')),
+                change(3, 3, None, Some(' ')),
+                change(3, 4, None, Some(' ')),
+                change(3, 5, None, Some(' ')),
+                change(3, 6, None, Some(' ')),
+                change(3, 7, None, Some('C')),
+                change(3, 8, None, Some('A')),
+                change(3, 9, None, Some('L')),
+                change(3, 10, None, Some('L')),
+                change(3, 11, None, Some(' ')),
+                change(3, 12, None, Some('E')),
+                change(3, 13, None, Some('X')),
+                change(3, 14, None, Some('I')),
+                change(3, 15, None, Some('T')),
+            ]
+        );
+
+        // Deletions are placed where the character was
+        assert_eq!(
+            char_changes("A  = B8\nJUNK\n", "A = B\n"),
+            vec![
+                change(1, 3, Some(' '), None),
+                change(1, 6, Some('8'), None),
+                change(2, 1, Some('J'), None),
+                change(2, 1, Some('U'), None),
+                change(2, 1, Some('N'), None),
+                change(2, 1, Some('K'), None),
+            ]
+        );
+        assert!(char_changes("SAME  \n\n", "SAME").is_empty());
+    }
+
+    #[test]
+    fn test_changed_chars() {
+        let marked = |text: &str, other: &str| -> Vec<String> {
+            changed_chars(text, other)
+                .into_iter()
+                .map(|line| {
+                    line.into_iter()
+                        .map(|(c, changed)| if changed { '^' } else { c })
+                        .collect()
+                })
+                .collect()
+        };
+        let ocr = "D0 10\nX = Y\n";
+        let fixed = "DO 10\nCALL\nX = Y";
+        assert_eq!(marked(fixed, ocr), ["D^ 10", "^^^^", "X = Y"]);
+        assert_eq!(marked(ocr, fixed), ["D^ 10", "X = Y"]);
+    }
+
+    #[test]
+    fn test_column_changed_chars() {
+        let marked = |text: &str, other: &str| -> Vec<String> {
+            column_changed_chars(text, other)
+                .into_iter()
+                .map(|line| {
+                    line.into_iter()
+                        .map(|(c, changed)| if changed { '^' } else { c })
+                        .collect()
+                })
+                .collect()
+        };
+        // A dropped space shifts the rest of the line
+        let ocr = "      X = Y\n      DO 10\n      END\n";
+        let fixed = "     X = Y\n      D0 10\n      CALL EXIT\n      END";
+        assert_eq!(
+            marked(fixed, ocr),
+            ["     ^^^^^", "      D^ 10", "^^^^^^^^^^^^^^^", "      END"]
+        );
+        assert_eq!(marked("SAME", "SAME  \n\n"), ["SAME"]);
+    }
+}
@@ -9,22 +9,83 @@
 //! - Cropping
 //! - Duplicate detection via SHA-256 hashing
 
-use anyhow::Result;
-use image::{DynamicImage, GrayImage, ImageBuffer, Rgb};
+use crate::derived::DerivedKey;
+use crate::error::{Error, IoContext, Result};
+use crate::image_loader::{load_image, LoadOptions};
+use crate::profile::StageTimings;
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, ImageBuffer, ImageFormat, ImageReader, Rgb};
+use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+/// Version of the preprocessing steps, part of the key of stored
+/// preprocessed images; bump it when `preprocess_image` changes output
+pub const PREPROCESS_VERSION: u32 = 1;
+
+/// Optional steps of `preprocess_image`, all enabled by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreprocessOptions {
+    /// Even out the alternating light and dark bands of greenbar paper
+    pub greenbar: bool,
+    /// Erase long horizontal lines (band boundaries, printer artifacts)
+    pub horizontal_lines: bool,
+}
+
+impl Default for PreprocessOptions {
+    fn default() -> Self {
+        Self {
+            greenbar: true,
+            horizontal_lines: true,
+        }
+    }
+}
+
+/// Derived image key of the preprocessed version of a source image
+///
+/// The default steps keep the key they had before the steps could be
+/// chosen, so images stored back then are still found.
+pub fn preprocess_key(source_hash: &str, options: &PreprocessOptions) -> Result<DerivedKey> {
+    if *options == PreprocessOptions::default() {
+        DerivedKey::new(source_hash, "preprocess", &PREPROCESS_VERSION)
+    } else {
+        DerivedKey::new(source_hash, "preprocess", &(PREPROCESS_VERSION, options))
+    }
+}
 
 /// Preprocess a scanned image for OCR/analysis
-pub fn preprocess_image(input: &DynamicImage) -> Result<GrayImage> {
+pub fn preprocess_image(input: &DynamicImage, options: &PreprocessOptions) -> Result<GrayImage> {
+    preprocess_image_timed(input, options, &mut StageTimings::default())
+}
+
+/// Preprocess a scanned image, recording the time of each step
+///
+/// Steps are recorded as `preprocess.grayscale`, `preprocess.greenbar`
+/// and `preprocess.hlines`; disabled steps are skipped.
+pub fn preprocess_image_timed(
+    input: &DynamicImage,
+    options: &PreprocessOptions,
+    timings: &mut StageTimings,
+) -> Result<GrayImage> {
+    if input.width() == 0 || input.height() == 0 {
+        return Err(Error::Preprocess("Image has no pixels".to_string()));
+    }
+
     // Convert to grayscale
-    let gray = input.to_luma8();
+    let mut cleaned = timings.time("preprocess.grayscale", || input.to_luma8());
 
     // Remove greenbar artifacts (alternating light/dark horizontal bands)
-    let degreenbarred = remove_greenbar_bands(&gray);
+    if options.greenbar {
+        cleaned = timings.time("preprocess.greenbar", || remove_greenbar_bands(&cleaned));
+    }
 
     // Remove horizontal lines (printed on band boundaries)
-    let cleaned = remove_horizontal_lines(&degreenbarred);
+    if options.horizontal_lines {
+        cleaned = timings.time("preprocess.hlines", || remove_horizontal_lines(&cleaned));
+    }
 
     // TODO: Add contrast stretching
     // TODO: Add adaptive thresholding
@@ -42,87 +103,76 @@ pub fn preprocess_image(input: &DynamicImage) -> Result<GrayImage> {
 fn remove_greenbar_bands(input: &GrayImage) -> GrayImage {
     let (width, height) = input.dimensions();
     let mut output = GrayImage::new(width, height);
+    if width == 0 {
+        return output;
+    }
 
-    // Process each row independently
-    for y in 0..height {
-        // Calculate mean and std dev for this row
-        let mut sum = 0u32;
-        let mut count = 0u32;
-
-        for x in 0..width {
-            let pixel = input.get_pixel(x, y)[0];
-            sum += pixel as u32;
-            count += 1;
-        }
-
-        let mean = if count > 0 { sum / count } else { 128 };
-
-        // Normalize each pixel in this row
-        for x in 0..width {
-            let pixel = input.get_pixel(x, y)[0];
-            let normalized = if pixel > mean as u8 {
-                // Lighter than mean - boost to white
-                let diff = pixel - mean as u8;
-                255u8.saturating_sub(diff.saturating_mul(2))
-            } else {
-                // Darker than mean - boost to black
-                let diff = mean as u8 - pixel;
-                diff.saturating_mul(3)
-            };
-
-            output.put_pixel(x, y, image::Luma([normalized]));
+    // Process each row independently, over the raw row slices
+    let rows_in = input.as_raw().chunks_exact(width as usize);
+    let rows_out = output.chunks_exact_mut(width as usize);
+    for (row_in, row_out) in rows_in.zip(rows_out) {
+        let sum: u32 = row_in.iter().map(|&p| p as u32).sum();
+        let mean = (sum / width) as u8;
+
+        // The mapping only depends on the pixel value, so build it once per row
+        let table = normalization_table(mean);
+        for (out, &pixel) in row_out.iter_mut().zip(row_in) {
+            *out = table[pixel as usize];
         }
     }
 
     output
 }
 
+/// Pixel mapping for a row with the given mean intensity
+fn normalization_table(mean: u8) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for (pixel, entry) in (0..=255u8).zip(table.iter_mut()) {
+        *entry = if pixel > mean {
+            // Lighter than mean - boost to white
+            255u8.saturating_sub((pixel - mean).saturating_mul(2))
+        } else {
+            // Darker than mean - boost to black
+            (mean - pixel).saturating_mul(3)
+        };
+    }
+    table
+}
+
 /// Remove horizontal lines (from greenbar boundaries or printer artifacts)
 ///
 /// Detects nearly-horizontal runs of dark pixels and removes them.
 /// This helps eliminate lines that OCR interprets as dashes/hyphens.
 fn remove_horizontal_lines(input: &GrayImage) -> GrayImage {
-    let (width, height) = input.dimensions();
+    let width = input.width() as usize;
     let mut output = input.clone();
+    if width == 0 {
+        return output;
+    }
 
-    // Scan each row for long horizontal dark runs
-    for y in 0..height {
-        let mut run_start: Option<u32> = None;
-        let threshold = 128u8; // Pixels darker than this are considered "dark"
-
-        for x in 0..width {
-            let pixel = input.get_pixel(x, y)[0];
-
-            if pixel < threshold {
-                // Dark pixel - extend or start run
-                if run_start.is_none() {
-                    run_start = Some(x);
-                }
-            } else {
-                // Light pixel - check if we just ended a long run
-                if let Some(start_x) = run_start {
-                    let run_length = x - start_x;
-
-                    // If run is longer than 30% of image width, it's likely a line
-                    if run_length > width / 3 {
-                        // Erase this horizontal line
-                        for erase_x in start_x..x {
-                            output.put_pixel(erase_x, y, image::Luma([255u8]));
-                        }
-                    }
-                }
-                run_start = None;
-            }
-        }
+    let threshold = 128u8; // Pixels darker than this are considered "dark"
+                           // If a run is longer than 30% of image width, it's likely a line
+    let min_run = width / 3;
 
-        // Handle run that extends to edge of image
-        if let Some(start_x) = run_start {
-            let run_length = width - start_x;
-            if run_length > width / 3 {
-                for erase_x in start_x..width {
-                    output.put_pixel(erase_x, y, image::Luma([255u8]));
-                }
+    // Scan each row for long horizontal dark runs
+    for row in output.chunks_exact_mut(width) {
+        let mut x = 0;
+        while x < width {
+            // Skip to the next dark pixel, then to the end of its run
+            let Some(offset) = row[x..].iter().position(|&p| p < threshold) else {
+                break;
+            };
+            let start = x + offset;
+            let end = row[start..]
+                .iter()
+                .position(|&p| p >= threshold)
+                .map_or(width, |len| start + len);
+
+            if end - start > min_run {
+                // Erase this horizontal line
+                row[start..end].fill(255);
             }
+            x = end;
         }
     }
 
@@ -162,8 +212,11 @@ pub fn compute_image_hash(image: &RgbImage) -> String {
 /// Group representing images with identical content
 #[derive(Debug, Clone)]
 pub struct DuplicateGroup {
-    /// SHA-256 hash of the image content
-    pub hash: String,
+    /// SHA-256 hash of the encoded bytes of the first file, if the files
+    /// were hashed
+    pub file_hash: Option<String>,
+    /// SHA-256 hash of the decoded pixels, if the image was decoded
+    pub pixel_hash: Option<String>,
     /// All filenames that map to this image
     pub filenames: Vec<PathBuf>,
 }
@@ -175,7 +228,8 @@ pub type RgbImage = ImageBuffer<Rgb<u8>, Vec<u8>>;
 ///
 /// Takes a list of (filename, image) tuples and returns groups of images
 /// with identical content. Each group contains the hash and all filenames
-/// that map to that content.
+/// that map to that content, sorted; groups are sorted by their first
+/// filename.
 pub fn detect_duplicates(images: &[(PathBuf, RgbImage)]) -> Vec<DuplicateGroup> {
     let mut hash_map: HashMap<String, Vec<PathBuf>> = HashMap::new();
 
@@ -186,10 +240,176 @@ pub fn detect_duplicates(images: &[(PathBuf, RgbImage)]) -> Vec<DuplicateGroup>
     }
 
     // Convert to DuplicateGroup vec
-    hash_map
+    let mut groups: Vec<DuplicateGroup> = hash_map
         .into_iter()
-        .map(|(hash, filenames)| DuplicateGroup { hash, filenames })
-        .collect()
+        .map(|(hash, filenames)| DuplicateGroup {
+            file_hash: None,
+            pixel_hash: Some(hash),
+            filenames,
+        })
+        .collect();
+    sort_groups(&mut groups);
+    groups
+}
+
+/// Sort the filenames of each group, and the groups by their first
+/// filename, so the same files always come out in the same order
+fn sort_groups(groups: &mut [DuplicateGroup]) {
+    for group in groups.iter_mut() {
+        group.filenames.sort();
+    }
+    groups.sort_by(|a, b| a.filenames[0].cmp(&b.filenames[0]));
+}
+
+/// Side of the thumbnail a perceptual hash is computed from
+const PERCEPTUAL_HASH_SIZE: u32 = 16;
+
+/// Perceptual (difference) hash of an image
+///
+/// The image is shrunk to a 17x16 grayscale thumbnail and each of the 256
+/// bits records whether a pixel is brighter than its right neighbour.
+/// Rescans of the same page with a slightly different crop or exposure
+/// differ in a few bits, unlike their SHA-256 hashes. Returned as 64
+/// hexadecimal digits.
+pub fn perceptual_hash(image: &DynamicImage) -> String {
+    let thumbnail = image
+        .resize_exact(
+            PERCEPTUAL_HASH_SIZE + 1,
+            PERCEPTUAL_HASH_SIZE,
+            FilterType::Triangle,
+        )
+        .to_luma8();
+    let mut hash =
+        String::with_capacity((PERCEPTUAL_HASH_SIZE * PERCEPTUAL_HASH_SIZE / 4) as usize);
+    let mut nibble = 0;
+    for y in 0..PERCEPTUAL_HASH_SIZE {
+        for x in 0..PERCEPTUAL_HASH_SIZE {
+            let brighter = thumbnail.get_pixel(x, y)[0] > thumbnail.get_pixel(x + 1, y)[0];
+            nibble = (nibble << 1) | u32::from(brighter);
+            if x % 4 == 3 {
+                hash.push(char::from_digit(nibble, 16).expect("nibble is below 16"));
+                nibble = 0;
+            }
+        }
+    }
+    hash
+}
+
+/// Number of bits two perceptual hashes differ in (`None` if they are not
+/// hashes of the same size)
+pub fn perceptual_distance(a: &str, b: &str) -> Option<u32> {
+    if a.len() != b.len() {
+        return None;
+    }
+    a.chars()
+        .zip(b.chars())
+        .map(|(a, b)| Some((a.to_digit(16)? ^ b.to_digit(16)?).count_ones()))
+        .sum()
+}
+
+/// Find pairs of images that look alike
+///
+/// Returns `(earlier, later, distance)` index pairs of `hashes` at most
+/// `max_distance` bits apart. Only pairs with `later >= from` are checked,
+/// so hashes appended to an already checked list are compared against all
+/// others without repeating old pairs. Images without a hash are skipped.
+pub fn find_near_duplicates(
+    hashes: &[Option<&str>],
+    from: usize,
+    max_distance: u32,
+) -> Vec<(usize, usize, u32)> {
+    let mut pairs = Vec::new();
+    for later in from..hashes.len() {
+        let Some(later_hash) = hashes[later] else {
+            continue;
+        };
+        for (earlier, hash) in hashes[..later].iter().enumerate() {
+            let distance = hash.and_then(|hash| perceptual_distance(hash, later_hash));
+            if let Some(distance) = distance.filter(|&d| d <= max_distance) {
+                pairs.push((earlier, later, distance));
+            }
+        }
+    }
+    pairs
+}
+
+/// Compute SHA-256 hash of a file's encoded bytes, streamed from disk
+pub fn compute_file_hash(path: &Path) -> Result<String> {
+    let mut file = File::open(path).io_context(|| format!("Failed to open: {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)
+        .io_context(|| format!("Failed to read: {}", path.display()))?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Detect duplicate image files without decoding every image
+///
+/// Files are first grouped by a hash of their encoded bytes, which catches
+/// exact copies. Files with different bytes can still hold the same pixels
+/// (re-saved with different metadata or compression), so only files whose
+/// format and dimensions match another file's are decoded and regrouped by
+/// pixel hash. Every group has a file hash, that of its first file; only
+/// decoded groups have a pixel hash. Filenames and groups are sorted as by
+/// [`detect_duplicates`], whatever the order of `paths`.
+pub fn detect_duplicate_files(paths: &[PathBuf]) -> Result<Vec<DuplicateGroup>> {
+    let mut groups: Vec<DuplicateGroup> = Vec::new();
+    let mut file_hashes: HashMap<&Path, String> = HashMap::new();
+    let mut by_file_hash: HashMap<String, usize> = HashMap::new();
+    for path in paths {
+        let hash = compute_file_hash(path)?;
+        file_hashes.insert(path, hash.clone());
+        match by_file_hash.get(&hash) {
+            Some(&idx) => groups[idx].filenames.push(path.clone()),
+            None => {
+                by_file_hash.insert(hash.clone(), groups.len());
+                groups.push(DuplicateGroup {
+                    file_hash: Some(hash),
+                    pixel_hash: None,
+                    filenames: vec![path.clone()],
+                });
+            }
+        }
+    }
+
+    // Only images of the same format and size can be re-saved copies
+    let layouts: Vec<Option<(ImageFormat, (u32, u32))>> = groups
+        .iter()
+        .map(|g| image_layout(&g.filenames[0]))
+        .collect();
+    let mut layout_counts: HashMap<(ImageFormat, (u32, u32)), usize> = HashMap::new();
+    for layout in layouts.iter().flatten() {
+        *layout_counts.entry(*layout).or_default() += 1;
+    }
+
+    let mut merged: Vec<DuplicateGroup> = Vec::with_capacity(groups.len());
+    let mut by_pixel_hash: HashMap<String, usize> = HashMap::new();
+    for (mut group, layout) in groups.into_iter().zip(layouts) {
+        if layout.is_some_and(|layout| layout_counts[&layout] > 1) {
+            let image = load_image(&group.filenames[0], &LoadOptions::full())?;
+            let hash = compute_image_hash(&image.to_rgb8());
+            if let Some(&idx) = by_pixel_hash.get(&hash) {
+                merged[idx].filenames.extend(group.filenames);
+                continue;
+            }
+            by_pixel_hash.insert(hash.clone(), merged.len());
+            group.pixel_hash = Some(hash);
+        }
+        merged.push(group);
+    }
+
+    sort_groups(&mut merged);
+    for group in &mut merged {
+        group.file_hash = file_hashes.get(group.filenames[0].as_path()).cloned();
+    }
+    Ok(merged)
+}
+
+/// Encoded format and dimensions of an image file, read without decoding
+/// the pixels
+fn image_layout(path: &Path) -> Option<(ImageFormat, (u32, u32))> {
+    let reader = ImageReader::open(path).ok()?.with_guessed_format().ok()?;
+    let format = reader.format()?;
+    Some((format, reader.into_dimensions().ok()?))
 }
 
 #[cfg(test)]
@@ -202,10 +422,157 @@ mod tests {
         let img = ImageBuffer::from_pixel(100, 100, Rgb([255u8, 255u8, 255u8]));
         let dynamic = DynamicImage::ImageRgb8(img);
 
-        let result = preprocess_image(&dynamic);
+        let result = preprocess_image(&dynamic, &PreprocessOptions::default());
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_preprocess_empty_image() {
+        let dynamic = DynamicImage::ImageRgb8(ImageBuffer::new(0, 0));
+        assert!(matches!(
+            preprocess_image(&dynamic, &PreprocessOptions::default()),
+            Err(Error::Preprocess(_))
+        ));
+    }
+
+    #[test]
+    fn test_disabled_steps_are_skipped() {
+        // A dark line across the image would be erased
+        let mut img = GrayImage::from_pixel(10, 3, image::Luma([255]));
+        for x in 0..10 {
+            img.put_pixel(x, 1, image::Luma([0]));
+        }
+        let dynamic = DynamicImage::ImageLuma8(img.clone());
+        let none = PreprocessOptions {
+            greenbar: false,
+            horizontal_lines: false,
+        };
+        assert_eq!(preprocess_image(&dynamic, &none).unwrap(), img);
+        assert_ne!(
+            preprocess_image(&dynamic, &PreprocessOptions::default()).unwrap(),
+            img
+        );
+    }
+
+    #[test]
+    fn test_preprocess_key_of_options() {
+        let hash = "aa11aa11aa11aa11";
+        let default = preprocess_key(hash, &PreprocessOptions::default()).unwrap();
+        assert_eq!(
+            default,
+            DerivedKey::new(hash, "preprocess", &PREPROCESS_VERSION).unwrap()
+        );
+        let no_lines = PreprocessOptions {
+            horizontal_lines: false,
+            ..PreprocessOptions::default()
+        };
+        assert_ne!(preprocess_key(hash, &no_lines).unwrap(), default);
+    }
+
+    #[test]
+    fn test_remove_greenbar_bands_normalizes_rows() {
+        // Row mean 100: lighter pixels fade to white, darker ones to black
+        let input = GrayImage::from_raw(4, 1, vec![90, 100, 110, 100]).unwrap();
+        let output = remove_greenbar_bands(&input);
+        assert_eq!(output.as_raw(), &vec![30, 0, 235, 0]);
+    }
+
+    #[test]
+    fn test_remove_horizontal_lines_erases_long_runs() {
+        let mut row = vec![255u8; 12];
+        row[1..7].fill(0); // 6 dark pixels > 12 / 3: a line
+        row[9] = 0; // short run: text
+        let mut pixels = row.clone();
+        pixels.extend(vec![0u8; 12]); // line reaching the edge
+        let output = remove_horizontal_lines(&GrayImage::from_raw(12, 2, pixels).unwrap());
+
+        let mut expected = vec![255u8; 24];
+        expected[9] = 0;
+        assert_eq!(output.as_raw(), &expected);
+    }
+
+    #[test]
+    fn test_detect_duplicate_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let gray = ImageBuffer::from_pixel(5, 5, Rgb([100u8, 100u8, 100u8]));
+        let other = ImageBuffer::from_pixel(5, 5, Rgb([200u8, 200u8, 200u8]));
+        let wide = ImageBuffer::from_pixel(8, 5, Rgb([100u8, 100u8, 100u8]));
+
+        let path = |name: &str| dir.path().join(name);
+        gray.save(path("a.png")).unwrap();
+        std::fs::copy(path("a.png"), path("a_copy.png")).unwrap();
+        // Same pixels, different bytes: with an alpha channel, or as BMP
+        DynamicImage::ImageRgb8(gray.clone())
+            .to_rgba8()
+            .save(path("a_alpha.png"))
+            .unwrap();
+        gray.save(path("a.bmp")).unwrap();
+        other.save(path("b.png")).unwrap();
+        wide.save(path("c.png")).unwrap();
+
+        let names = [
+            "a.png",
+            "a_copy.png",
+            "a_alpha.png",
+            "a.bmp",
+            "b.png",
+            "c.png",
+        ];
+        let paths: Vec<PathBuf> = names.iter().map(|name| path(name)).collect();
+        let groups = detect_duplicate_files(&paths).unwrap();
+        let filenames: Vec<&[PathBuf]> = groups.iter().map(|g| &g.filenames[..]).collect();
+        // Exact and re-encoded copies of the same format are merged; the
+        // BMP is another format, so its pixels are not compared
+        assert_eq!(
+            filenames,
+            [
+                &[path("a.bmp")][..],
+                &[path("a.png"), path("a_alpha.png"), path("a_copy.png")],
+                &[path("b.png")],
+                &[path("c.png")],
+            ]
+        );
+        assert_eq!(groups[1].pixel_hash, Some(compute_image_hash(&gray)));
+        assert_eq!(
+            groups[1].file_hash,
+            Some(compute_file_hash(&path("a.png")).unwrap())
+        );
+        // Unique format and dimensions keep only the file hash
+        assert_eq!(groups[0].pixel_hash, None);
+        assert_eq!(groups[3].pixel_hash, None);
+        assert_eq!(
+            groups[3].file_hash,
+            Some(compute_file_hash(&path("c.png")).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_near_duplicates() {
+        let page = DynamicImage::ImageRgb8(ImageBuffer::from_fn(340, 320, |x, y| {
+            let shade = ((x * 7 + y * 3) % 251) as u8 / 2
+                + if (x / 40 + y / 25) % 2 == 0 { 0 } else { 120 };
+            Rgb([shade, shade, shade])
+        }));
+        let rescan = page.crop_imm(3, 2, 334, 316).brighten(12);
+        let other = page.fliph();
+
+        let hashes: Vec<String> = [&page, &rescan, &other]
+            .iter()
+            .map(|image| perceptual_hash(image))
+            .collect();
+        assert_eq!(hashes[0].len(), 64);
+        let close = perceptual_distance(&hashes[0], &hashes[1]).unwrap();
+        let far = perceptual_distance(&hashes[0], &hashes[2]).unwrap();
+        assert!(close < 20 && far > 60, "close {} far {}", close, far);
+
+        let refs: Vec<Option<&str>> =
+            vec![Some(&hashes[0]), None, Some(&hashes[2]), Some(&hashes[1])];
+        assert_eq!(find_near_duplicates(&refs, 0, 20), vec![(0, 3, close)]);
+        assert!(find_near_duplicates(&refs, 0, close - 1).is_empty());
+        assert!(find_near_duplicates(&refs[..3], 2, 20).is_empty());
+        assert_eq!(perceptual_distance("ab", "abc"), None);
+    }
+
     #[test]
     fn test_compute_image_hash_deterministic() {
         // Same image should produce same hash
@@ -249,6 +616,7 @@ mod tests {
 
         // Should have 2 groups: one with img1+img2, one with img3
         assert_eq!(groups.len(), 2);
+        assert_eq!(groups[1].filenames, vec![PathBuf::from("image3.jpg")]);
 
         // Find the duplicate group
         let duplicate_group = groups
@@ -0,0 +1,113 @@
+//! Stage timing collection
+//!
+//! Records how long each pipeline stage takes per artifact and summarizes
+//! the samples with percentiles, to show where analysis time goes.
+
+use std::time::{Duration, Instant};
+
+/// Durations recorded per stage, in first-recorded order
+#[derive(Debug, Clone, Default)]
+pub struct StageTimings {
+    stages: Vec<(String, Vec<Duration>)>,
+}
+
+/// Summary of one stage's timings
+#[derive(Debug, Clone, PartialEq)]
+pub struct StageSummary {
+    /// Stage name (sub-stages use dotted names, e.g. "preprocess.greenbar")
+    pub stage: String,
+    /// Number of samples
+    pub count: usize,
+    /// Sum of all samples
+    pub total: Duration,
+    /// Mean sample
+    pub mean: Duration,
+    /// Median
+    pub p50: Duration,
+    /// 90th percentile
+    pub p90: Duration,
+    /// 99th percentile
+    pub p99: Duration,
+    /// Slowest sample
+    pub max: Duration,
+}
+
+impl StageTimings {
+    /// Record one sample for a stage
+    pub fn record(&mut self, stage: &str, duration: Duration) {
+        match self.stages.iter_mut().find(|(name, _)| name == stage) {
+            Some((_, samples)) => samples.push(duration),
+            None => self.stages.push((stage.to_string(), vec![duration])),
+        }
+    }
+
+    /// Run `f`, recording its duration under `stage`
+    pub fn time<T>(&mut self, stage: &str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let result = f();
+        self.record(stage, start.elapsed());
+        result
+    }
+
+    /// Summaries for all stages, in first-recorded order
+    pub fn summary(&self) -> Vec<StageSummary> {
+        self.stages
+            .iter()
+            .map(|(stage, samples)| {
+                let mut sorted = samples.clone();
+                sorted.sort();
+                let total: Duration = sorted.iter().sum();
+                StageSummary {
+                    stage: stage.clone(),
+                    count: sorted.len(),
+                    total,
+                    mean: total / sorted.len() as u32,
+                    p50: percentile(&sorted, 50.0),
+                    p90: percentile(&sorted, 90.0),
+                    p99: percentile(&sorted, 99.0),
+                    max: *sorted.last().unwrap_or(&Duration::ZERO),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary_percentiles() {
+        let mut timings = StageTimings::default();
+        for ms in (1..=100).rev() {
+            timings.record("ocr", Duration::from_millis(ms));
+        }
+        timings.record("io", Duration::from_millis(5));
+
+        let summary = timings.summary();
+        assert_eq!(summary[0].stage, "ocr");
+        assert_eq!(summary[0].count, 100);
+        assert_eq!(summary[0].p50, Duration::from_millis(50));
+        assert_eq!(summary[0].p90, Duration::from_millis(90));
+        assert_eq!(summary[0].p99, Duration::from_millis(99));
+        assert_eq!(summary[0].max, Duration::from_millis(100));
+        assert_eq!(summary[0].mean, Duration::from_micros(50_500));
+        assert_eq!(summary[1].p99, Duration::from_millis(5));
+    }
+
+    #[test]
+    fn test_time_records_and_returns() {
+        let mut timings = StageTimings::default();
+        assert_eq!(timings.time("stage", || 42), 42);
+        assert_eq!(timings.summary()[0].count, 1);
+    }
+}
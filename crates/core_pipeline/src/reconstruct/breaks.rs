@@ -0,0 +1,183 @@
+//! Logical page break detection
+//!
+//! A long continuous scan (e.g. a fan-fold printout photographed in one
+//! shot) can hold several printed pages. Page boundaries inside a scan are
+//! found from:
+//! - form feed characters left by carriage control
+//! - bands of blank lines between pages
+//! - page-numbered header lines (a break before each) or footer lines (a
+//!   break after each)
+//!
+//! Each logical page becomes its own artifact so header extraction and page
+//! ordering work on printed pages rather than physical scans.
+
+use super::headers::{labeled_page_number, HEADER_LINES};
+use crate::types::{PageArtifact, PageId};
+
+/// Form feed (carriage control skip to channel 1)
+const FORM_FEED: char = '\x0c';
+
+/// Minimum run of blank lines treated as a page boundary
+pub const BLANK_BAND_LINES: usize = 4;
+
+/// Line indices (0-based) where logical pages start; always includes 0
+pub fn page_breaks(text: &str) -> Vec<usize> {
+    let lines: Vec<&str> = text.lines().collect();
+    let is_blank = |line: &str| line.trim_matches(|c: char| c.is_whitespace()).is_empty();
+
+    // Page numbers at the top of the first page mean numbered lines are headers
+    let headers_on_top = lines
+        .iter()
+        .filter(|l| !is_blank(l))
+        .position(|l| labeled_page_number(l).is_some())
+        .is_some_and(|pos| pos < HEADER_LINES);
+
+    let mut breaks = vec![0];
+    let mut content = 0;
+    let mut blanks = 0;
+    let mut after_footer = false;
+    for (idx, line) in lines.iter().enumerate() {
+        if is_blank(line) && !line.contains(FORM_FEED) {
+            blanks += 1;
+            continue;
+        }
+        let numbered = labeled_page_number(line).is_some();
+        let starts_page = line.contains(FORM_FEED)
+            || blanks >= BLANK_BAND_LINES
+            || after_footer
+            || (numbered && headers_on_top && content >= HEADER_LINES);
+        if starts_page && content > 0 {
+            breaks.push(idx);
+            content = 0;
+        }
+        after_footer = numbered && !headers_on_top;
+        blanks = 0;
+        if !is_blank(line) {
+            content += 1;
+        }
+    }
+    breaks
+}
+
+/// Split scans holding several printed pages into one artifact per page
+///
+/// Split artifacts share the original image, get new ids and a note, and
+/// keep the revisions and validation issues that fall on their lines
+/// (renumbered). Page number, header and footer are cleared for
+/// re-extraction. Artifacts with a single page are returned unchanged.
+pub fn split_logical_pages(artifacts: Vec<PageArtifact>) -> Vec<PageArtifact> {
+    let mut pages = Vec::with_capacity(artifacts.len());
+    for artifact in artifacts {
+        let breaks = artifact
+            .content_text
+            .as_deref()
+            .map_or_else(|| vec![0], page_breaks);
+        if breaks.len() <= 1 {
+            pages.push(artifact);
+            continue;
+        }
+
+        let text = artifact.content_text.clone().unwrap_or_default();
+        let lines: Vec<&str> = text.lines().collect();
+        let ends = breaks.iter().skip(1).copied().chain([lines.len()]);
+        for (part, (start, end)) in breaks.iter().copied().zip(ends).enumerate() {
+            let in_page = |line_number: usize| (start + 1..=end).contains(&line_number);
+            let mut page = artifact.clone();
+            page.id = PageId::new();
+            page.content_text = Some(
+                lines[start..end]
+                    .iter()
+                    .map(|l| l.replace(FORM_FEED, ""))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+            );
+
+            let metadata = &mut page.metadata;
+            metadata.page_number = None;
+            metadata.header = None;
+            metadata.footer = None;
+            metadata.revisions.retain(|r| in_page(r.line_number));
+            metadata
+                .revisions
+                .iter_mut()
+                .for_each(|r| r.line_number -= start);
+            metadata
+                .validation_issues
+                .retain(|i| in_page(i.line_number));
+            metadata
+                .validation_issues
+                .iter_mut()
+                .for_each(|i| i.line_number -= start);
+            metadata.notes.push(format!(
+                "Logical page {} of {} in scan",
+                part + 1,
+                breaks.len()
+            ));
+            pages.push(page);
+        }
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageMetadata, ScanSetId, TextRevision};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_form_feed_breaks() {
+        assert_eq!(page_breaks("      X = 1\n\x0c      Y = 2"), [0, 1]);
+        // A leading form feed does not create an empty page
+        assert_eq!(page_breaks("\x0c      X = 1"), [0]);
+    }
+
+    #[test]
+    fn test_blank_band_breaks() {
+        assert_eq!(page_breaks("A\n\n\n\n\nB"), [0, 5]);
+        assert_eq!(page_breaks("A\n\n\nB"), [0]);
+    }
+
+    #[test]
+    fn test_header_breaks() {
+        let text = "PROG  PAGE 1\nA\nB\nC\nPROG  PAGE 2\nD";
+        assert_eq!(page_breaks(text), [0, 4]);
+    }
+
+    #[test]
+    fn test_footer_breaks() {
+        let text = "A\nB\nC\nD\nPAGE 1\nE\nF\nPAGE 2";
+        assert_eq!(page_breaks(text), [0, 5]);
+    }
+
+    #[test]
+    fn test_split_logical_pages() {
+        let mut metadata = PageMetadata::default();
+        metadata.revisions.push(TextRevision {
+            source: "autofix".to_string(),
+            line_number: 2,
+            column: 7,
+            before: "V".to_string(),
+            after: "Y".to_string(),
+            reason: String::new(),
+            confidence: 0.95,
+        });
+        let artifact = PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("scan.png"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some("      X = 1\n\x0c      Y = 2".to_string()),
+            metadata,
+        };
+
+        let pages = split_logical_pages(vec![artifact]);
+        assert_eq!(pages.len(), 2);
+        assert_ne!(pages[0].id, pages[1].id);
+        assert_eq!(pages[1].content_text.as_deref(), Some("      Y = 2"));
+        assert!(pages[0].metadata.revisions.is_empty());
+        assert_eq!(pages[1].metadata.revisions[0].line_number, 1);
+        assert_eq!(pages[1].metadata.notes, ["Logical page 2 of 2 in scan"]);
+    }
+}
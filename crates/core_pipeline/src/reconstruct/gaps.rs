@@ -0,0 +1,126 @@
+//! Missing page detection
+//!
+//! Lost pages show up as jumps in page numbering, or (in listings with
+//! object code) as a jump in the location counter between the last line of
+//! one page and the first line of the next. Each gap becomes a placeholder
+//! line marked as inferred, written as a comment in the listing's language
+//! so the exported listing still assembles/compiles.
+
+use crate::classify::Language;
+use crate::types::SourceLine;
+use crate::validate::object::listing_object_words;
+
+/// Largest location counter step between pages not treated as a gap
+pub const MAX_ADDRESS_STEP: u16 = 0x20;
+
+/// Pseudo-ops that legitimately move the location counter
+const LOCATION_OPS: &[&str] = &["BSS", "BES", "ORG"];
+
+/// Lines at the end of a page searched for location-moving pseudo-ops
+const BOUNDARY_LINES: usize = 3;
+
+/// Describe pages missing between two consecutive page numbers
+///
+/// `previous` is `None` for the first page of a document, in which case
+/// pages before it are reported missing.
+pub fn page_gap(previous: Option<u32>, next: u32) -> Option<String> {
+    let first_missing = previous.map_or(1, |p| p + 1);
+    match next.checked_sub(1) {
+        Some(last_missing) if last_missing > first_missing => {
+            Some(format!("GAP: PAGES {first_missing}-{last_missing} MISSING"))
+        }
+        Some(last_missing) if last_missing == first_missing => {
+            Some(format!("GAP: PAGE {first_missing} MISSING"))
+        }
+        _ => None,
+    }
+}
+
+/// Describe a location counter jump between the end of one page and the
+/// start of the next
+///
+/// Jumps explained by a `BSS`, `BES` or `ORG` at the end of the previous
+/// page are not gaps.
+pub fn address_gap(previous_page: &str, next_page: &str) -> Option<String> {
+    let last = *listing_object_words(previous_page).keys().next_back()?;
+    let first = *listing_object_words(next_page).keys().next()?;
+
+    let moves_location = previous_page
+        .lines()
+        .rev()
+        .filter(|l| !l.trim().is_empty())
+        .take(BOUNDARY_LINES)
+        .any(|line| {
+            line.split_whitespace()
+                .any(|token| LOCATION_OPS.contains(&token))
+        });
+    if moves_location || first.wrapping_sub(last) <= MAX_ADDRESS_STEP {
+        return None;
+    }
+    Some(format!("GAP: ADDRESS JUMP {last:04X} TO {first:04X}"))
+}
+
+/// Build an inferred placeholder line, commented out for the language
+pub fn gap_marker(language: Language, description: &str) -> SourceLine {
+    let text = match language {
+        Language::Fortran => format!("C     *** {description} ***"),
+        // Assembler comments start with an asterisk in column 21
+        Language::Assembler => format!("{:20}* *** {description} ***", ""),
+        Language::Forth => format!("\\ *** {description} ***"),
+        _ => format!("*** {description} ***"),
+    };
+    SourceLine {
+        line_no: None,
+        text,
+        inferred: true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_gap() {
+        assert_eq!(page_gap(Some(2), 3), None);
+        assert_eq!(page_gap(None, 1), None);
+        assert_eq!(page_gap(Some(2), 4).as_deref(), Some("GAP: PAGE 3 MISSING"));
+        assert_eq!(
+            page_gap(Some(2), 6).as_deref(),
+            Some("GAP: PAGES 3-5 MISSING")
+        );
+        assert_eq!(page_gap(None, 3).as_deref(), Some("GAP: PAGES 1-2 MISSING"));
+        // Out-of-order numbers are duplicates, not gaps
+        assert_eq!(page_gap(Some(4), 4), None);
+    }
+
+    #[test]
+    fn test_address_gap() {
+        let first = "0100 0 C400      START LD   L X\n0102 0 D401            STO  L Y";
+        let near = "0104 0 C402            LD   L Z";
+        let far = "0180 0 C402            LD   L Z";
+        assert_eq!(address_gap(first, near), None);
+        assert_eq!(
+            address_gap(first, far).as_deref(),
+            Some("GAP: ADDRESS JUMP 0102 TO 0180")
+        );
+    }
+
+    #[test]
+    fn test_address_gap_explained_by_bss() {
+        let first = "0100 0 C400      START LD   L X\n0102              BUF   BSS  128";
+        let next = "0182 0 C402            LD   L Z";
+        assert_eq!(address_gap(first, next), None);
+    }
+
+    #[test]
+    fn test_gap_marker_is_a_comment() {
+        let marker = gap_marker(Language::Fortran, "GAP: PAGE 3 MISSING");
+        assert!(marker.inferred);
+        assert!(marker.text.starts_with('C'));
+        assert_eq!(
+            gap_marker(Language::Assembler, "X").text.find('*'),
+            Some(20)
+        );
+    }
+}
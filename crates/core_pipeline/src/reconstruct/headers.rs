@@ -0,0 +1,303 @@
+//! Running header and footer extraction
+//!
+//! 1130 listings print a running header on every page (program name, date
+//! and `PAGE   3`), and some documents put the page number in a footer
+//! instead (`- 3 -`). A first or last line counts as a header or footer
+//! when it carries a page number or a date, or when it repeats (apart from
+//! its digits) on other pages of the scan set.
+
+use crate::types::PageArtifact;
+use std::collections::{HashMap, HashSet};
+
+/// Number of non-blank lines at the top/bottom of a page searched
+pub(crate) const HEADER_LINES: usize = 3;
+
+/// Month abbreviations recognized in dates
+const MONTHS: &[&str] = &[
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+/// Monitor control words that are not part of a program name
+const CONTROL_WORDS: &[&str] = &["//", "JOB", "FOR", "ASM", "DUP", "XEQ", "PAGE", "PG"];
+
+/// Extract headers, footers and page numbers into page metadata
+///
+/// Populates `header`, `footer` and `page_number`. Pages without text, or
+/// without recognizable header/footer lines, are left unchanged.
+pub fn extract_headers(artifacts: &mut [PageArtifact]) {
+    let running_headers = running_lines(artifacts, false);
+    let running_footers = running_lines(artifacts, true);
+
+    for artifact in artifacts {
+        let Some(text) = &artifact.content_text else {
+            continue;
+        };
+        let lines = content_lines(text);
+        if lines.is_empty() {
+            continue;
+        }
+
+        // Header: a page-numbered line near the top, else a running/dated first line
+        let numbered_header = lines
+            .iter()
+            .take(HEADER_LINES)
+            .find_map(|line| labeled_page_number(line).map(|n| (Some(n), *line)));
+        let header = numbered_header.or_else(|| {
+            let first = lines[0];
+            (running_headers.contains(&normalize(first)) || contains_date(first))
+                .then_some((None, first))
+        });
+
+        // Footer: a page-numbered line near the bottom, else a running/dated last line
+        let body = &lines[usize::from(header.is_some())..];
+        let last = body.len().saturating_sub(HEADER_LINES);
+        let numbered_footer = body[last..]
+            .iter()
+            .rev()
+            .enumerate()
+            .find_map(|(idx, line)| {
+                labeled_page_number(line)
+                    .or_else(|| (idx == 0).then(|| bare_page_number(line)).flatten())
+                    .map(|n| (Some(n), *line))
+            });
+        let footer = numbered_footer.or_else(|| {
+            let last = *body.last()?;
+            (running_footers.contains(&normalize(last)) || contains_date(last))
+                .then_some((None, last))
+        });
+
+        let metadata = &mut artifact.metadata;
+        if let Some((number, line)) = header {
+            metadata.header = Some(line.trim().to_string());
+            metadata.page_number = metadata.page_number.or(number);
+        }
+        if let Some((number, line)) = footer {
+            metadata.footer = Some(line.trim().to_string());
+            metadata.page_number = metadata.page_number.or(number);
+        }
+    }
+}
+
+/// Derive a document name from a header line
+///
+/// Drops page labels and numbers, dates and monitor control words, keeping
+/// the remaining words (usually the program name).
+pub fn document_name(header: &str) -> Option<String> {
+    let words: Vec<&str> = header
+        .split_whitespace()
+        .filter(|word| {
+            let upper = word.trim_end_matches(['.', ':', ',']).to_ascii_uppercase();
+            !CONTROL_WORDS.contains(&upper.as_str())
+                && !is_date(word)
+                && !MONTHS.contains(&upper.as_str())
+                && parse_number(&upper).is_none()
+                && !word
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == ',' || c == '-')
+        })
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Non-blank lines of a page
+fn content_lines(text: &str) -> Vec<&str> {
+    text.lines().filter(|l| !l.trim().is_empty()).collect()
+}
+
+/// Normalized first (or last) lines that repeat on several different pages
+///
+/// A line only counts as running if the pages it appears on differ in
+/// their neighbouring line, so rescans of the same page do not qualify.
+fn running_lines(artifacts: &[PageArtifact], from_end: bool) -> HashSet<String> {
+    let mut neighbours: HashMap<String, HashSet<String>> = HashMap::new();
+    for text in artifacts.iter().filter_map(|a| a.content_text.as_deref()) {
+        let mut lines = content_lines(text);
+        if from_end {
+            lines.reverse();
+        }
+        let Some(line) = lines.first() else {
+            continue;
+        };
+        let neighbour = lines.get(1).map_or(String::new(), |l| normalize(l));
+        neighbours
+            .entry(normalize(line))
+            .or_default()
+            .insert(neighbour);
+    }
+    neighbours
+        .into_iter()
+        .filter(|(_, next)| next.len() > 1)
+        .map(|(line, _)| line)
+        .collect()
+}
+
+/// Normalize a line for comparison: digits masked, whitespace collapsed
+fn normalize(line: &str) -> String {
+    line.split_whitespace()
+        .map(|word| {
+            word.chars()
+                .map(|c| if c.is_ascii_digit() { '#' } else { c })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Check whether a line contains a date
+fn contains_date(line: &str) -> bool {
+    line.split_whitespace().any(is_date)
+}
+
+/// Recognize `12/31/72`, `31-12-1972` and `31-DEC-72` style dates
+fn is_date(word: &str) -> bool {
+    let parts: Vec<&str> = word.split(['/', '-']).collect();
+    if parts.len() != 3 {
+        return false;
+    }
+    let is_digits = |p: &str, max: usize| {
+        !p.is_empty() && p.len() <= max && p.chars().all(|c| c.is_ascii_digit())
+    };
+    let month_ok =
+        is_digits(parts[1], 2) || MONTHS.contains(&parts[1].to_ascii_uppercase().as_str());
+    is_digits(parts[0], 2)
+        && month_ok
+        && (parts[2].len() == 2 || parts[2].len() == 4)
+        && is_digits(parts[2], 4)
+}
+
+/// Find a page number introduced by `PAGE` or `PG` (e.g. `PAGE 12`, `PAGE12`)
+pub(crate) fn labeled_page_number(line: &str) -> Option<u32> {
+    let tokens: Vec<String> = line
+        .split_whitespace()
+        .map(|t| t.trim_end_matches(['.', ':']).to_ascii_uppercase())
+        .collect();
+
+    tokens.iter().enumerate().find_map(|(idx, token)| {
+        if token == "PAGE" || token == "PG" {
+            tokens.get(idx + 1).and_then(|next| parse_number(next))
+        } else {
+            token.strip_prefix("PAGE").and_then(parse_number)
+        }
+    })
+}
+
+/// Read a footer consisting of just a number, optionally dashed (`- 12 -`)
+fn bare_page_number(line: &str) -> Option<u32> {
+    parse_number(line.trim().trim_matches(['-', ' ']))
+}
+
+/// Parse a short page number, tolerating O/I/l read for 0/1
+fn parse_number(token: &str) -> Option<u32> {
+    if token.is_empty() || token.len() > 4 || !token.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    token
+        .chars()
+        .map(|c| match c {
+            'O' | 'o' => Some('0'),
+            'I' | 'l' => Some('1'),
+            c if c.is_ascii_digit() => Some(c),
+            _ => None,
+        })
+        .collect::<Option<String>>()?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageId, PageMetadata, ScanSetId};
+    use std::path::PathBuf;
+
+    fn page(text: &str) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::new(),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some(text.to_string()),
+            metadata: PageMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_header_page_number() {
+        let mut pages = vec![
+            page("// FOR                                      PAGE   3\n      X = 1"),
+            page("SUBROUTINE LISTING   PAGE 1O\n      X = 1"),
+            page("PG. 7\n      X = 1"),
+        ];
+        extract_headers(&mut pages);
+        assert_eq!(pages[0].metadata.page_number, Some(3));
+        assert!(pages[0]
+            .metadata
+            .header
+            .as_deref()
+            .unwrap()
+            .ends_with("PAGE   3"));
+        assert_eq!(pages[1].metadata.page_number, Some(10));
+        assert_eq!(pages[2].metadata.page_number, Some(7));
+    }
+
+    #[test]
+    fn test_footer_page_number() {
+        let mut pages = vec![
+            page("      X = 1\n      Y = 2\n\n   - 12 -\n"),
+            page("      X = 1\n   20 CONTINUE"),
+        ];
+        extract_headers(&mut pages);
+        assert_eq!(pages[0].metadata.page_number, Some(12));
+        assert_eq!(pages[0].metadata.footer.as_deref(), Some("- 12 -"));
+        // A statement number in the last line is not a page number
+        assert_eq!(pages[1].metadata.page_number, None);
+        assert_eq!(pages[1].metadata.footer, None);
+    }
+
+    #[test]
+    fn test_dated_header_and_running_footer() {
+        let mut pages = vec![
+            page("PAYROLL  03/15/72\n      X = 1\nCOMPANY CONFIDENTIAL"),
+            page("      Y = 2\n      Z = 3\nCOMPANY CONFIDENTIAL"),
+        ];
+        extract_headers(&mut pages);
+        assert_eq!(
+            pages[0].metadata.header.as_deref(),
+            Some("PAYROLL  03/15/72")
+        );
+        assert_eq!(pages[0].metadata.page_number, None);
+        assert_eq!(pages[1].metadata.header, None);
+        assert_eq!(
+            pages[1].metadata.footer.as_deref(),
+            Some("COMPANY CONFIDENTIAL")
+        );
+    }
+
+    #[test]
+    fn test_rescanned_page_is_not_a_running_header() {
+        let mut pages = vec![
+            page("      X = 1\n      Y = 2"),
+            page("      X = 1\n      Y = 2"),
+        ];
+        extract_headers(&mut pages);
+        assert_eq!(pages[0].metadata.header, None);
+    }
+
+    #[test]
+    fn test_document_name() {
+        assert_eq!(
+            document_name("PAYROLL UPDATE  15-MAR-72   PAGE  3").as_deref(),
+            Some("PAYROLL UPDATE")
+        );
+        assert_eq!(document_name("// FOR  PAGE 1"), None);
+    }
+
+    #[test]
+    fn test_is_date() {
+        assert!(is_date("12/31/72"));
+        assert!(is_date("31-DEC-1972"));
+        assert!(!is_date("0100"));
+        assert!(!is_date("A-B-C"));
+    }
+}
@@ -0,0 +1,184 @@
+//! Job splitting by monitor control records
+//!
+//! A single printout often holds several DMS jobs back to back. Each job
+//! starts with a `// JOB` record; within a job, every `// FOR` or `// ASM`
+//! record after the first starts another compilation. Splitting at these
+//! records turns one long listing into units that can be exported on their
+//! own.
+
+use crate::classify::detect_language;
+use crate::types::{RunListing, SourceLine, SourceListing};
+
+/// Record starting a new job
+const JOB_RECORD: &str = "// JOB";
+
+/// Records starting a compilation or assembly within a job
+const COMPILE_RECORDS: &[&str] = &["// FOR", "// ASM"];
+
+/// Check whether a line is a DMS monitor control record (`// XXX`)
+pub fn is_monitor_record(line: &str) -> bool {
+    line.trim_start()
+        .strip_prefix("// ")
+        .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '*'))
+}
+
+/// Split a source listing into one listing per job or compilation
+///
+/// Listings without a second job or compilation are returned unchanged.
+/// Split listings keep the original page list, detect their own language
+/// and are named after the original with a job number.
+pub fn split_jobs(listing: SourceListing) -> Vec<SourceListing> {
+    let texts: Vec<&str> = listing.lines.iter().map(|l| l.text.as_str()).collect();
+    let lengths = unit_lengths(&job_starts(&texts), texts.len());
+    if lengths.len() <= 1 {
+        return vec![listing];
+    }
+
+    let SourceListing {
+        name, pages, lines, ..
+    } = listing;
+    let mut lines = lines.into_iter();
+    lengths
+        .into_iter()
+        .enumerate()
+        .map(|(idx, len)| {
+            let lines: Vec<SourceLine> = lines.by_ref().take(len).collect();
+            let text: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+            SourceListing {
+                name: Some(match &name {
+                    Some(name) => format!("{name} JOB {}", idx + 1),
+                    None => format!("JOB {}", idx + 1),
+                }),
+                language: detect_language(&text.join("\n")).0.as_str().to_string(),
+                pages: pages.clone(),
+                lines,
+            }
+        })
+        .collect()
+}
+
+/// Split a run listing into one run listing per job
+pub fn split_run_jobs(run: RunListing) -> Vec<RunListing> {
+    let texts: Vec<&str> = run.lines.iter().map(String::as_str).collect();
+    let starts: Vec<usize> = job_starts(&texts)
+        .into_iter()
+        .filter(|&idx| idx == 0 || texts[idx].trim_start().starts_with(JOB_RECORD))
+        .collect();
+    let lengths = unit_lengths(&starts, texts.len());
+    if lengths.len() <= 1 {
+        return vec![run];
+    }
+
+    let mut lines = run.lines.into_iter();
+    lengths
+        .into_iter()
+        .map(|len| RunListing {
+            pages: run.pages.clone(),
+            lines: lines.by_ref().take(len).collect(),
+        })
+        .collect()
+}
+
+/// Line indices where a new unit starts (always including 0)
+fn job_starts(lines: &[&str]) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut compiled = false;
+    for (idx, line) in lines.iter().enumerate() {
+        let line = line.trim_start();
+        if line.starts_with(JOB_RECORD) {
+            // Monitor records before the first // JOB belong to it
+            let unit_has_content = lines[*starts.last().unwrap_or(&0)..idx]
+                .iter()
+                .any(|l| !is_monitor_record(l));
+            if idx > 0 && unit_has_content {
+                starts.push(idx);
+            }
+            compiled = false;
+        } else if COMPILE_RECORDS.iter().any(|r| line.starts_with(r)) {
+            if compiled {
+                starts.push(idx);
+            }
+            compiled = true;
+        }
+    }
+    starts.dedup();
+    starts
+}
+
+/// Lengths of the units starting at `starts`, out of `total` lines
+fn unit_lengths(starts: &[usize], total: usize) -> Vec<usize> {
+    starts
+        .iter()
+        .zip(starts.iter().skip(1).chain(std::iter::once(&total)))
+        .map(|(start, end)| end - start)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn listing(text: &str) -> SourceListing {
+        SourceListing {
+            name: Some("PRINTOUT".to_string()),
+            language: "unknown".to_string(),
+            pages: Vec::new(),
+            lines: text
+                .lines()
+                .map(|line| SourceLine {
+                    line_no: None,
+                    text: line.to_string(),
+                    inferred: false,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_split_jobs_at_job_records() {
+        let text = "// JOB\n// FOR\n      X = 1\n// JOB\n// ASM\n      LD   L X";
+        let jobs = split_jobs(listing(text));
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[0].name.as_deref(), Some("PRINTOUT JOB 1"));
+        assert_eq!(jobs[0].language, "fortran");
+        assert_eq!(jobs[0].lines.len(), 3);
+        assert_eq!(jobs[1].language, "assembler");
+        assert_eq!(jobs[1].lines[0].text, "// JOB");
+    }
+
+    #[test]
+    fn test_split_jobs_at_second_compilation() {
+        let text = "// JOB\n// FOR\n      X = 1\n// FOR\n      Y = 2";
+        let jobs = split_jobs(listing(text));
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[1].lines[0].text, "// FOR");
+    }
+
+    #[test]
+    fn test_single_job_unchanged() {
+        let jobs = split_jobs(listing("// JOB\n// JOB\n// FOR\n      X = 1"));
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].name.as_deref(), Some("PRINTOUT"));
+    }
+
+    #[test]
+    fn test_split_run_jobs() {
+        let run = RunListing {
+            pages: Vec::new(),
+            lines: ["// JOB", "// XEQ MAIN", "RESULT 1", "// JOB", "RESULT 2"]
+                .map(str::to_string)
+                .to_vec(),
+        };
+        let jobs = split_run_jobs(run);
+        assert_eq!(jobs.len(), 2);
+        assert_eq!(jobs[1].lines, ["// JOB", "RESULT 2"]);
+    }
+
+    #[test]
+    fn test_is_monitor_record() {
+        assert!(is_monitor_record("// JOB"));
+        assert!(is_monitor_record("// * COMMENT"));
+        assert!(!is_monitor_record("      X = 1"));
+        assert!(!is_monitor_record("// "));
+    }
+}
@@ -0,0 +1,253 @@
+//! Source listing reconstruction
+//!
+//! Assembles the ordered, corrected pages of a document into a single
+//! [`SourceListing`]: header and footer lines are dropped, the language is
+//! detected from the content, lines changed by a correction step are
+//! flagged as inferred, lines repeated by overlapping shots are dropped,
+//! and missing pages are marked with inferred placeholder lines.
+
+use super::gaps::{address_gap, gap_marker, page_gap};
+use super::headers::document_name;
+use super::jobs::{split_jobs, split_run_jobs};
+use super::pages::order_pages;
+use super::stitch::find_overlap;
+use crate::classify::detect_language;
+use crate::types::{
+    ArtifactKind, HighLevelArtifact, PageArtifact, RunListing, SourceLine, SourceListing,
+};
+use crate::validate::columns;
+use crate::validate::fortran::sequence_number;
+use std::collections::HashSet;
+
+/// Assemble ordered pages into a source listing
+///
+/// `pages` must already be in reading order.
+pub fn build_source_listing(pages: &[&PageArtifact]) -> SourceListing {
+    let texts: Vec<&str> = pages
+        .iter()
+        .filter_map(|p| p.content_text.as_deref())
+        .collect();
+    let (language, _) = detect_language(&texts.join("\n"));
+    let mut lines = Vec::new();
+    let mut previous_number = None;
+    let mut previous_text: Option<&str> = None;
+
+    for page in pages {
+        let Some(text) = &page.content_text else {
+            continue;
+        };
+
+        // Mark missing pages before this one
+        let gap = match page.metadata.page_number {
+            Some(number) => {
+                let gap = page_gap(previous_number, number);
+                previous_number = Some(number);
+                gap
+            }
+            None => None,
+        };
+        if let Some(gap) = gap.or_else(|| previous_text.and_then(|prev| address_gap(prev, text))) {
+            lines.push(gap_marker(language, &gap));
+        }
+        previous_text = Some(text);
+
+        let revised: HashSet<usize> = page
+            .metadata
+            .revisions
+            .iter()
+            .map(|r| r.line_number)
+            .collect();
+        let is_page_marker = |line: &str| {
+            let line = line.trim();
+            page.metadata.header.as_deref() == Some(line)
+                || page.metadata.footer.as_deref() == Some(line)
+        };
+
+        let page_lines: Vec<SourceLine> = text
+            .lines()
+            .enumerate()
+            .map(|(idx, line)| (idx, line.trim_end()))
+            .filter(|(_, line)| !line.is_empty() && !is_page_marker(line))
+            .map(|(idx, line)| {
+                let chars: Vec<char> = line.chars().collect();
+                SourceLine {
+                    line_no: sequence_number(&columns(&chars, 73, 80))
+                        .and_then(|(_, number)| u32::try_from(number).ok()),
+                    text: line.to_string(),
+                    inferred: revised.contains(&(idx + 1)),
+                }
+            })
+            .collect();
+
+        // Drop lines this page repeats from the end of the previous page
+        let previous: Vec<&str> = lines
+            .iter()
+            .rev()
+            .take(page_lines.len())
+            .rev()
+            .map(|l: &SourceLine| l.text.as_str())
+            .collect();
+        let next: Vec<&str> = page_lines.iter().map(|l| l.text.as_str()).collect();
+        let overlap = find_overlap(&previous, &next);
+        lines.extend(page_lines.into_iter().skip(overlap));
+    }
+
+    SourceListing {
+        name: pages
+            .iter()
+            .filter_map(|p| p.metadata.header.as_deref())
+            .find_map(document_name),
+        language: language.as_str().to_string(),
+        pages: pages.iter().map(|p| p.id).collect(),
+        lines,
+    }
+}
+
+/// Build high-level artifacts for each document in a scan set
+///
+/// Documents made only of runtime output become [`RunListing`]s; all
+/// others become [`SourceListing`]s. Documents holding several jobs are
+/// split into one artifact per job. Pages without text are skipped.
+pub fn build_documents(
+    artifacts: &[PageArtifact],
+    documents: &[Vec<usize>],
+) -> Vec<HighLevelArtifact> {
+    documents
+        .iter()
+        .filter_map(|document| {
+            let pages: Vec<&PageArtifact> = order_pages(artifacts, document)
+                .order
+                .into_iter()
+                .map(|idx| &artifacts[idx])
+                .filter(|page| page.content_text.is_some())
+                .collect();
+            if pages.is_empty() {
+                return None;
+            }
+
+            let is_run_output = pages
+                .iter()
+                .all(|page| page.layout_label == ArtifactKind::RuntimeOutput);
+            let listing = build_source_listing(&pages);
+            if is_run_output {
+                let run = RunListing {
+                    pages: listing.pages,
+                    lines: listing.lines.into_iter().map(|l| l.text).collect(),
+                };
+                return Some(
+                    split_run_jobs(run)
+                        .into_iter()
+                        .map(HighLevelArtifact::RunListing)
+                        .collect::<Vec<_>>(),
+                );
+            }
+            Some(
+                split_jobs(listing)
+                    .into_iter()
+                    .map(HighLevelArtifact::SourceListing)
+                    .collect(),
+            )
+        })
+        .flatten()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::Language;
+    use crate::types::{PageId, PageMetadata, ScanSetId, TextRevision};
+    use std::path::PathBuf;
+
+    fn page(text: &str, page_number: Option<u32>) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::new(),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some(text.to_string()),
+            metadata: PageMetadata {
+                page_number,
+                ..PageMetadata::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_build_source_listing_drops_headers_and_flags_revisions() {
+        let mut first = page("PAGE 1\n      X = 1\n\n      Y = 2", Some(1));
+        first.metadata.header = Some("PAGE 1".to_string());
+        first.metadata.revisions.push(TextRevision {
+            source: "autofix".to_string(),
+            line_number: 4,
+            column: 7,
+            before: "V".to_string(),
+            after: "Y".to_string(),
+            reason: String::new(),
+            confidence: 0.95,
+        });
+        let second = page(&format!("{:<72}PROG0030", "      END"), Some(2));
+
+        let listing = build_source_listing(&[&first, &second]);
+        assert_eq!(listing.name, None);
+        assert_eq!(listing.language, Language::Fortran.as_str());
+        assert_eq!(listing.pages, vec![first.id, second.id]);
+        let texts: Vec<&str> = listing.lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(texts[..2], ["      X = 1", "      Y = 2"]);
+        assert!(!listing.lines[0].inferred);
+        assert!(listing.lines[1].inferred);
+        assert_eq!(listing.lines[2].line_no, Some(30));
+    }
+
+    #[test]
+    fn test_build_source_listing_stitches_overlap() {
+        let first = page("      A = 1\n      B = 2\n      C = 3", Some(1));
+        let second = page("      B = 2\n      C = 3\n      D = 4", Some(2));
+
+        let listing = build_source_listing(&[&first, &second]);
+        let texts: Vec<&str> = listing.lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            ["      A = 1", "      B = 2", "      C = 3", "      D = 4"]
+        );
+    }
+
+    #[test]
+    fn test_build_source_listing_marks_missing_pages() {
+        let first = page("      X = 1", Some(2));
+        let second = page("      Y = 2", Some(4));
+
+        let listing = build_source_listing(&[&first, &second]);
+        let texts: Vec<&str> = listing.lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            [
+                "C     *** GAP: PAGE 1 MISSING ***",
+                "      X = 1",
+                "C     *** GAP: PAGE 3 MISSING ***",
+                "      Y = 2"
+            ]
+        );
+        assert!(listing.lines[0].inferred);
+        assert!(listing.lines[2].inferred);
+    }
+
+    #[test]
+    fn test_build_documents_orders_pages() {
+        let artifacts = vec![page("      Y = 2", Some(2)), page("      X = 1", Some(1))];
+        let built = build_documents(&artifacts, &[vec![0, 1]]);
+        let HighLevelArtifact::SourceListing(listing) = &built[0] else {
+            panic!("expected a source listing");
+        };
+        assert_eq!(listing.lines[0].text, "      X = 1");
+    }
+
+    #[test]
+    fn test_build_documents_run_listing() {
+        let mut output = page("RESULT = 42", None);
+        output.layout_label = ArtifactKind::RuntimeOutput;
+        let built = build_documents(&[output], &[vec![0]]);
+        assert!(matches!(&built[0], HighLevelArtifact::RunListing(run) if run.lines.len() == 1));
+    }
+}
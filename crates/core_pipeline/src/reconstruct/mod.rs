@@ -0,0 +1,30 @@
+//! Reconstruction module
+//!
+//! Turns individually scanned and corrected pages back into documents:
+//! - `breaks` - Logical page breaks inside continuous scans
+//! - `headers` - Running headers/footers and page numbers
+//! - `pages` - Document grouping and page ordering
+//! - `gaps` - Missing page detection and placeholder lines
+//! - `jobs` - Listings split into jobs at monitor control records
+//! - `listing` - Ordered pages assembled into source listings
+//! - `stitch` - Overlap detection between consecutive shots
+//! - `objects` - Object cards grouped and decoded into object decks
+
+pub mod breaks;
+pub mod gaps;
+pub mod headers;
+pub mod jobs;
+pub mod listing;
+pub mod objects;
+pub mod pages;
+pub mod stitch;
+
+pub use breaks::{page_breaks, split_logical_pages};
+pub use headers::{document_name, extract_headers};
+pub use jobs::{is_monitor_record, split_jobs, split_run_jobs};
+pub use listing::{build_documents, build_source_listing};
+pub use objects::build_object_decks;
+pub use pages::{
+    group_documents, number_ranges, order_pages, page_numbering, DocumentNumbering, PageOrder,
+};
+pub use stitch::{find_overlap, line_similarity};
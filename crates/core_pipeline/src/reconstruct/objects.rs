@@ -0,0 +1,174 @@
+//! Object deck reconstruction
+//!
+//! Groups scanned object cards into decks. Cards are grouped by the deck
+//! name in their identification field, put in sequence-number order, and
+//! split into separate decks at header and end cards.
+
+use crate::decoder::{card_identification, decode_object_card};
+use crate::types::{ArtifactKind, CardArtifact, ObjectCardType, ObjectDeck};
+
+/// An object card with its identification resolved
+struct IdentifiedCard<'a> {
+    card: &'a CardArtifact,
+    binary: &'a [u8],
+    sequence: Option<String>,
+}
+
+/// Build object decks from card artifacts
+///
+/// Only `CardObject` artifacts with binary data take part. The deck name
+/// and sequence number come from card metadata, falling back to the
+/// card's identification field. Cards without a sequence number keep their
+/// scan order; cards that fail to decode are skipped.
+pub fn build_object_decks(cards: &[CardArtifact]) -> Vec<ObjectDeck> {
+    // Group by deck name, in order of first appearance
+    let mut groups: Vec<(Option<String>, Vec<IdentifiedCard>)> = Vec::new();
+    for card in cards {
+        let Some(binary) = card.binary_80col.as_deref() else {
+            continue;
+        };
+        if card.layout_label != ArtifactKind::CardObject {
+            continue;
+        }
+        let (id_name, id_sequence) = card_identification(binary);
+        let name = card.metadata.deck_name.clone().or(id_name);
+        let identified = IdentifiedCard {
+            card,
+            binary,
+            sequence: card.metadata.sequence_number.clone().or(id_sequence),
+        };
+        match groups.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, group)) => group.push(identified),
+            None => groups.push((name, vec![identified])),
+        }
+    }
+
+    let mut decks = Vec::new();
+    for (name, mut group) in groups {
+        group.sort_by_key(|c| sequence_key(c.sequence.as_deref()));
+
+        let mut current: Option<ObjectDeck> = None;
+        for IdentifiedCard { card, binary, .. } in group {
+            let Ok(object_card) = decode_object_card(binary) else {
+                continue;
+            };
+
+            let card_type = object_card.card_type;
+            if card_type == ObjectCardType::Header {
+                decks.extend(current.take());
+            }
+            let deck = current.get_or_insert_with(|| ObjectDeck {
+                name: deck_name(name.as_deref(), decks.len()),
+                cards: Vec::new(),
+                object_cards: Vec::new(),
+            });
+            deck.cards.push(card.id);
+            deck.object_cards.push(object_card);
+            if card_type == ObjectCardType::End {
+                decks.extend(current.take());
+            }
+        }
+        decks.extend(current);
+    }
+
+    decks
+}
+
+/// Sort key for a sequence number: numeric where possible, unnumbered last
+pub(crate) fn sequence_key(sequence: Option<&str>) -> (bool, u64) {
+    match sequence.and_then(|s| s.trim().parse().ok()) {
+        Some(number) => (false, number),
+        None => (true, 0),
+    }
+}
+
+/// Name a deck after its identification field, or by position
+fn deck_name(name: Option<&str>, index: usize) -> String {
+    name.map_or_else(|| format!("DECK{}", index + 1), str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{CardId, CardMetadata, ScanSetId};
+    use std::path::PathBuf;
+
+    fn card(type_code: u8, address: u16, deck: Option<&str>, seq: Option<&str>) -> CardArtifact {
+        let mut data = vec![0u8; 80];
+        data[0..2].copy_from_slice(&address.to_be_bytes());
+        data[4] = type_code;
+        data[5] = 1;
+        data[6..8].copy_from_slice(&address.to_be_bytes());
+        CardArtifact {
+            id: CardId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::new(),
+            processed_image_path: None,
+            layout_label: ArtifactKind::CardObject,
+            text_80col: None,
+            binary_80col: Some(data),
+            metadata: CardMetadata {
+                deck_name: deck.map(str::to_string),
+                sequence_number: seq.map(str::to_string),
+                ..CardMetadata::default()
+            },
+        }
+    }
+
+    #[test]
+    fn test_cards_sorted_by_sequence_within_deck() {
+        let cards = vec![
+            card(0x0A, 0x0102, Some("PROG"), Some("0003")),
+            card(0x01, 0x0100, Some("PROG"), Some("0001")),
+            card(0x0F, 0x0000, Some("PROG"), Some("0004")),
+            card(0x0A, 0x0100, Some("PROG"), Some("0002")),
+        ];
+        let decks = build_object_decks(&cards);
+        assert_eq!(decks.len(), 1);
+        assert_eq!(decks[0].name, "PROG");
+        let types: Vec<_> = decks[0].object_cards.iter().map(|c| c.card_type).collect();
+        assert_eq!(
+            types,
+            [
+                ObjectCardType::Header,
+                ObjectCardType::Text,
+                ObjectCardType::Text,
+                ObjectCardType::End
+            ]
+        );
+        assert_eq!(decks[0].object_cards[1].address, Some(0x0100));
+    }
+
+    #[test]
+    fn test_decks_split_by_name_and_header_cards() {
+        let cards = vec![
+            card(0x01, 0x0100, None, None),
+            card(0x0A, 0x0100, None, None),
+            card(0x01, 0x0200, None, None),
+            card(0x0A, 0x0200, Some("SUBR"), Some("0001")),
+        ];
+        let decks = build_object_decks(&cards);
+        let names: Vec<&str> = decks.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["DECK1", "DECK2", "SUBR"]);
+        assert_eq!(decks[0].cards.len(), 2);
+    }
+
+    #[test]
+    fn test_identification_field_used_without_metadata() {
+        let mut first = card(0x0A, 0x0102, None, None);
+        let mut second = card(0x0A, 0x0100, None, None);
+        first.binary_80col.as_mut().unwrap()[72..80].copy_from_slice(b"ABCD0002");
+        second.binary_80col.as_mut().unwrap()[72..80].copy_from_slice(b"ABCD0001");
+
+        let decks = build_object_decks(&[first, second]);
+        assert_eq!(decks[0].name, "ABCD");
+        assert_eq!(decks[0].object_cards[0].address, Some(0x0100));
+    }
+
+    #[test]
+    fn test_non_object_cards_ignored() {
+        let mut text_card = card(0x0A, 0x0100, None, None);
+        text_card.layout_label = ArtifactKind::CardText;
+        assert!(build_object_decks(&[text_card]).is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+//! Page ordering
+//!
+//! Scans are rarely taken in order, so pages are grouped into documents and
+//! sorted by the page numbers found in their headers/footers, flagging
+//! missing and repeated pages. [`page_numbering`] reports those problems,
+//! and pages scanned out of order, for every document, to know what to
+//! rescan.
+
+use super::headers::{document_name, HEADER_LINES};
+use crate::types::PageArtifact;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Split artifacts (in scan order) into documents
+///
+/// A new document starts at a page numbered 1, or at a page whose first
+/// lines contain a `// JOB` monitor control record. Returns artifact
+/// indices for each document.
+pub fn group_documents(artifacts: &[PageArtifact]) -> Vec<Vec<usize>> {
+    let mut documents: Vec<Vec<usize>> = Vec::new();
+
+    for (idx, artifact) in artifacts.iter().enumerate() {
+        let starts_job = artifact.content_text.as_deref().is_some_and(|text| {
+            text.lines()
+                .take(HEADER_LINES)
+                .any(|l| l.trim_start().starts_with("// JOB"))
+        });
+        let starts_document = starts_job || artifact.metadata.page_number == Some(1);
+
+        match documents.last_mut() {
+            Some(document) if !starts_document => document.push(idx),
+            _ => documents.push(vec![idx]),
+        }
+    }
+
+    documents
+}
+
+/// Pages of a document in reading order, with numbering problems
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PageOrder {
+    /// Artifact indices in page order
+    pub order: Vec<usize>,
+    /// Page numbers missing between the first and last page
+    pub gaps: Vec<u32>,
+    /// Page numbers that appear on more than one page
+    pub duplicates: Vec<u32>,
+}
+
+/// Order the pages of one document by detected page number
+///
+/// Unnumbered pages stay directly after the numbered page they were
+/// scanned after (or first, if scanned before any numbered page).
+pub fn order_pages(artifacts: &[PageArtifact], document: &[usize]) -> PageOrder {
+    // Group each numbered page with the unnumbered pages scanned after it
+    let mut groups: Vec<(Option<u32>, Vec<usize>)> = Vec::new();
+    for &idx in document {
+        match (artifacts[idx].metadata.page_number, groups.last_mut()) {
+            (None, Some(group)) => group.1.push(idx),
+            (number, _) => groups.push((number, vec![idx])),
+        }
+    }
+    groups.sort_by_key(|(number, _)| *number);
+
+    let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+    for (number, _) in &groups {
+        if let Some(number) = number {
+            *counts.entry(*number).or_insert(0) += 1;
+        }
+    }
+
+    let gaps = match (counts.keys().next(), counts.keys().next_back()) {
+        (Some(&first), Some(&last)) => (first..=last).filter(|n| !counts.contains_key(n)).collect(),
+        _ => Vec::new(),
+    };
+    let duplicates = counts
+        .iter()
+        .filter(|(_, count)| **count > 1)
+        .map(|(number, _)| *number)
+        .collect();
+
+    PageOrder {
+        order: groups.into_iter().flat_map(|(_, pages)| pages).collect(),
+        gaps,
+        duplicates,
+    }
+}
+
+/// Page numbering of one document, in scan order
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentNumbering {
+    /// Document name from the running header, if detected
+    pub name: Option<String>,
+    /// Number of pages
+    pub pages: usize,
+    /// Page numbers missing between the first and last page
+    pub missing: Vec<u32>,
+    /// Page numbers that appear on more than one page
+    pub duplicates: Vec<u32>,
+    /// Page numbers scanned after a higher-numbered page
+    pub out_of_order: Vec<u32>,
+}
+
+impl DocumentNumbering {
+    /// Whether the numbering has no gaps, repeats or misordered pages
+    pub fn is_complete(&self) -> bool {
+        self.missing.is_empty() && self.duplicates.is_empty() && self.out_of_order.is_empty()
+    }
+}
+
+/// Numbering of every document of a scan set that has detected page
+/// numbers
+///
+/// Artifacts must be in scan order: once `reconstruct` has saved them in
+/// reading order, no page is out of order any more.
+pub fn page_numbering(artifacts: &[PageArtifact]) -> Vec<DocumentNumbering> {
+    group_documents(artifacts)
+        .iter()
+        .filter_map(|document| {
+            let numbers: Vec<u32> = document
+                .iter()
+                .filter_map(|&idx| artifacts[idx].metadata.page_number)
+                .collect();
+            if numbers.is_empty() {
+                return None;
+            }
+            let order = order_pages(artifacts, document);
+            let mut highest = 0;
+            let mut out_of_order = Vec::new();
+            for number in numbers {
+                if number < highest {
+                    out_of_order.push(number);
+                }
+                highest = highest.max(number);
+            }
+            Some(DocumentNumbering {
+                name: document
+                    .iter()
+                    .filter_map(|&idx| artifacts[idx].metadata.header.as_deref())
+                    .find_map(document_name),
+                pages: document.len(),
+                missing: order.gaps,
+                duplicates: order.duplicates,
+                out_of_order,
+            })
+        })
+        .collect()
+}
+
+/// Runs of consecutive page numbers, as first and last number
+pub fn number_ranges(numbers: &[u32]) -> Vec<(u32, u32)> {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &number in numbers {
+        match ranges.last_mut() {
+            Some((_, last)) if *last + 1 == number => *last = number,
+            _ => ranges.push((number, number)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageId, PageMetadata, ScanSetId};
+    use std::path::PathBuf;
+
+    fn page(text: &str) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::new(),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some(text.to_string()),
+            metadata: PageMetadata::default(),
+        }
+    }
+
+    fn numbered(numbers: &[Option<u32>]) -> Vec<PageArtifact> {
+        numbers
+            .iter()
+            .map(|n| {
+                let mut p = page("");
+                p.metadata.page_number = *n;
+                p
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_order_with_gaps_and_duplicates() {
+        let pages = numbered(&[Some(3), None, Some(1), Some(5), Some(3)]);
+        let order = order_pages(&pages, &[0, 1, 2, 3, 4]);
+        assert_eq!(order.order, vec![2, 0, 1, 4, 3]);
+        assert_eq!(order.gaps, vec![2, 4]);
+        assert_eq!(order.duplicates, vec![3]);
+    }
+
+    #[test]
+    fn test_leading_unnumbered_pages_stay_first() {
+        let pages = numbered(&[None, Some(2), Some(1)]);
+        let order = order_pages(&pages, &[0, 1, 2]);
+        assert_eq!(order.order, vec![0, 2, 1]);
+        assert!(order.gaps.is_empty());
+    }
+
+    #[test]
+    fn test_page_numbering() {
+        let mut pages = numbered(&[Some(1), Some(2), Some(6), Some(5), Some(5), None]);
+        pages[0].metadata.header = Some("PAYROLL PAGE 1".to_string());
+        pages.extend(numbered(&[None]));
+        let numbering = page_numbering(&pages);
+        assert_eq!(
+            numbering,
+            vec![DocumentNumbering {
+                name: Some("PAYROLL".to_string()),
+                pages: 7,
+                missing: vec![3, 4],
+                duplicates: vec![5],
+                out_of_order: vec![5, 5],
+            }]
+        );
+        assert!(!numbering[0].is_complete());
+        assert!(page_numbering(&numbered(&[None, None])).is_empty());
+    }
+
+    #[test]
+    fn test_number_ranges() {
+        assert_eq!(
+            number_ranges(&[3, 14, 15, 16, 20]),
+            vec![(3, 3), (14, 16), (20, 20)]
+        );
+        assert!(number_ranges(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_group_documents() {
+        let mut pages = numbered(&[Some(1), Some(2), Some(1), None]);
+        pages[3].content_text = Some("// JOB\n// FOR".to_string());
+        assert_eq!(group_documents(&pages), vec![vec![0, 1], vec![2], vec![3]]);
+    }
+}
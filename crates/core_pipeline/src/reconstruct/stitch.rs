@@ -0,0 +1,112 @@
+//! Overlapping scan stitching
+//!
+//! Long listings are often photographed in overlapping shots, so the last
+//! lines of one page re-appear at the top of the next. The overlap is found
+//! by comparing lines for similarity (OCR rarely reads the same line the
+//! same way twice) and dropped from the later page.
+
+/// Minimum number of lines that must match to count as an overlap
+pub const MIN_OVERLAP_LINES: usize = 2;
+
+/// Minimum similarity for two lines to be considered the same (0.0-1.0)
+pub const LINE_SIMILARITY: f32 = 0.85;
+
+/// Similarity of two lines, ignoring differences in spacing
+///
+/// Returns 1.0 for identical lines and 0.0 for completely different ones,
+/// based on the edit distance between the whitespace-normalized lines.
+pub fn line_similarity(a: &str, b: &str) -> f32 {
+    let a: Vec<char> = normalize(a).chars().collect();
+    let b: Vec<char> = normalize(b).chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(&a, &b) as f32 / longest as f32
+}
+
+/// Count the leading lines of `next` that repeat the trailing lines of
+/// `previous`
+///
+/// The longest overlap of at least [`MIN_OVERLAP_LINES`] lines wins;
+/// returns 0 when the pages do not overlap.
+pub fn find_overlap(previous: &[&str], next: &[&str]) -> usize {
+    let longest = previous.len().min(next.len());
+    (MIN_OVERLAP_LINES..=longest)
+        .rev()
+        .find(|&len| {
+            previous[previous.len() - len..]
+                .iter()
+                .zip(&next[..len])
+                .all(|(a, b)| line_similarity(a, b) >= LINE_SIMILARITY)
+        })
+        .unwrap_or(0)
+}
+
+/// Collapse runs of whitespace and trim the ends
+fn normalize(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Levenshtein distance between two sequences (of characters or lines)
+pub(crate) fn edit_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_similarity() {
+        assert_eq!(line_similarity("      X = 1", "  X   =  1"), 1.0);
+        assert!(line_similarity("      SUM = SUM + I", "      SUM = SUM + 1") > 0.9);
+        assert!(line_similarity("      X = 1", "   10 CONTINUE") < 0.5);
+        assert_eq!(line_similarity("", "   "), 1.0);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        let chars = |s: &str| s.chars().collect::<Vec<_>>();
+        assert_eq!(edit_distance(&chars("kitten"), &chars("sitting")), 3);
+        assert_eq!(edit_distance(&chars(""), &chars("abc")), 3);
+    }
+
+    #[test]
+    fn test_find_overlap_tolerates_ocr_differences() {
+        let previous = [
+            "      A = 1",
+            "      B = 2",
+            "      C = 3",
+            "      DELTA = 4",
+        ];
+        let next = ["      C = 3", "      0ELTA = 4", "      E = 5"];
+        assert_eq!(find_overlap(&previous, &next), 2);
+    }
+
+    #[test]
+    fn test_single_matching_line_is_not_an_overlap() {
+        let previous = ["      A = 1", "      CONTINUE"];
+        let next = ["      CONTINUE", "      B = 2"];
+        assert_eq!(find_overlap(&previous, &next), 0);
+    }
+
+    #[test]
+    fn test_no_overlap() {
+        assert_eq!(find_overlap(&["      A = 1"], &[]), 0);
+        assert_eq!(
+            find_overlap(&["      A = 1", "      B = 2"], &["      C = 3"]),
+            0
+        );
+    }
+}
@@ -0,0 +1,445 @@
+//! Scan set persistence
+//!
+//! A scan set is a directory with the following layout:
+//!
+//! ```text
+//! scan_set/
+//! |-- manifest.json    # ScanSetManifest
+//! |-- artifacts.json   # Vec<PageArtifact>
+//! |-- artifacts.journal.jsonl  # PageArtifact per line (while analyzing)
+//! |-- cards.json       # Vec<CardArtifact> (if any cards were scanned)
+//! |-- high_level.json  # Vec<HighLevelArtifact> (after reconstruction)
+//! |-- scan_set.sqlite  # Manifest and artifacts (SQLite storage, see crate::storage)
+//! |-- images/          # Unique raw images (named by hash prefix)
+//! |-- originals/       # Untouched source files (ingest --copy-originals)
+//! |-- reference/       # Known-good transcripts for scoring (see crate::score)
+//! `-- derived/         # Derived images by source hash, stage and params
+//! ```
+//!
+//! The manifest and artifacts live either in the JSON files or in
+//! `scan_set.sqlite`; the functions below use whichever backend the
+//! directory was created with (see [`crate::storage`]).
+//!
+//! JSON files are written atomically (temporary file, then rename). Long
+//! runs append each finished artifact to the journal, so a crash loses at
+//! most the artifact in progress; the journal is replayed on load and
+//! compacted into `artifacts.json` at the end of the run. A run that
+//! records its settings starts the journal with them as a header line,
+//! `{"run": ...}`, so resuming can check that they still apply.
+
+use crate::error::{Error, IoContext, ParseContext, Result};
+use crate::storage;
+use crate::types::{CardArtifact, HighLevelArtifact, PageArtifact, PageId, ScanSetManifest};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Manifest filename within a scan set directory
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Artifacts filename within a scan set directory
+pub const ARTIFACTS_FILE: &str = "artifacts.json";
+
+/// Card artifacts filename within a scan set directory
+pub const CARDS_FILE: &str = "cards.json";
+
+/// Reconstructed high-level artifacts filename within a scan set directory
+pub const HIGH_LEVEL_FILE: &str = "high_level.json";
+
+/// Directory of untouched source files within a scan set directory
+pub const ORIGINALS_DIR: &str = "originals";
+
+/// Artifact journal filename within a scan set directory
+pub const JOURNAL_FILE: &str = "artifacts.journal.jsonl";
+
+/// Whether a directory holds a scan set, with either storage backend
+pub fn exists(scan_set_dir: &Path) -> bool {
+    scan_set_dir.join(MANIFEST_FILE).is_file() || scan_set_dir.join(storage::SQLITE_FILE).is_file()
+}
+
+/// Load the manifest of a scan set
+pub fn load_manifest(scan_set_dir: &Path) -> Result<ScanSetManifest> {
+    storage::open(scan_set_dir)?.load_manifest()
+}
+
+/// Load the artifacts of a scan set, including those saved to the journal
+/// by an interrupted run
+pub fn load_artifacts(scan_set_dir: &Path) -> Result<Vec<PageArtifact>> {
+    storage::open(scan_set_dir)?.load_artifacts()
+}
+
+/// Load the artifacts of a scan set that a filter includes; the SQLite
+/// backend reads only those
+pub fn find_artifacts(
+    scan_set_dir: &Path,
+    filter: &storage::ArtifactFilter,
+) -> Result<Vec<PageArtifact>> {
+    storage::open(scan_set_dir)?.find_artifacts(filter)
+}
+
+/// Load both the manifest and the artifacts of a scan set
+///
+/// Artifacts saved to the journal by an interrupted run are included.
+/// Fails if the directory does not exist.
+pub fn load(scan_set_dir: &Path) -> Result<(ScanSetManifest, Vec<PageArtifact>)> {
+    if !scan_set_dir.exists() {
+        return Err(Error::ScanSetNotFound(scan_set_dir.to_path_buf()));
+    }
+    let store = storage::open(scan_set_dir)?;
+    Ok((store.load_manifest()?, store.load_artifacts()?))
+}
+
+/// Write the manifest of a scan set
+pub fn save_manifest(scan_set_dir: &Path, manifest: &ScanSetManifest) -> Result<()> {
+    storage::open(scan_set_dir)?.save_manifest(manifest)
+}
+
+/// Write the artifacts of a scan set, superseding an interrupted run's
+/// journal
+pub fn save_artifacts(scan_set_dir: &Path, artifacts: &[PageArtifact]) -> Result<()> {
+    storage::open(scan_set_dir)?.save_artifacts(artifacts)
+}
+
+/// Read `manifest.json`
+pub(crate) fn read_manifest(scan_set_dir: &Path) -> Result<ScanSetManifest> {
+    let manifest_path = scan_set_dir.join(MANIFEST_FILE);
+    let manifest_json = fs::read_to_string(&manifest_path)
+        .io_context(|| format!("Failed to read manifest: {}", manifest_path.display()))?;
+    serde_json::from_str(&manifest_json).map_err(|source| Error::ManifestParse {
+        path: manifest_path,
+        source,
+    })
+}
+
+/// Read `artifacts.json`
+pub(crate) fn read_artifacts(scan_set_dir: &Path) -> Result<Vec<PageArtifact>> {
+    let artifacts_path = scan_set_dir.join(ARTIFACTS_FILE);
+    let artifacts_json = fs::read_to_string(&artifacts_path)
+        .io_context(|| format!("Failed to read artifacts: {}", artifacts_path.display()))?;
+    serde_json::from_str(&artifacts_json)
+        .parse_context(|| format!("Failed to parse {}", ARTIFACTS_FILE))
+}
+
+/// Write `manifest.json`
+pub(crate) fn write_manifest(scan_set_dir: &Path, manifest: &ScanSetManifest) -> Result<()> {
+    let manifest_path = scan_set_dir.join(MANIFEST_FILE);
+    let manifest_json = serde_json::to_string_pretty(manifest)?;
+    write_atomic(&manifest_path, &manifest_json)
+        .io_context(|| format!("Failed to write manifest: {}", manifest_path.display()))
+}
+
+/// Write `artifacts.json`
+pub(crate) fn write_artifacts(scan_set_dir: &Path, artifacts: &[PageArtifact]) -> Result<()> {
+    let artifacts_path = scan_set_dir.join(ARTIFACTS_FILE);
+    let artifacts_json = serde_json::to_string_pretty(artifacts)?;
+    write_atomic(&artifacts_path, &artifacts_json)
+        .io_context(|| format!("Failed to write artifacts: {}", artifacts_path.display()))
+}
+
+/// Load the card artifacts of a scan set
+///
+/// Returns an empty list if the scan set has no cards file.
+pub fn load_cards(scan_set_dir: &Path) -> Result<Vec<CardArtifact>> {
+    let cards_path = scan_set_dir.join(CARDS_FILE);
+    if !cards_path.exists() {
+        return Ok(Vec::new());
+    }
+    let cards_json = fs::read_to_string(&cards_path)
+        .io_context(|| format!("Failed to read cards: {}", cards_path.display()))?;
+    serde_json::from_str(&cards_json).parse_context(|| format!("Failed to parse {}", CARDS_FILE))
+}
+
+/// Write the card artifacts of a scan set
+pub fn save_cards(scan_set_dir: &Path, cards: &[CardArtifact]) -> Result<()> {
+    let cards_path = scan_set_dir.join(CARDS_FILE);
+    let cards_json = serde_json::to_string_pretty(cards)?;
+    write_atomic(&cards_path, &cards_json)
+        .io_context(|| format!("Failed to write cards: {}", cards_path.display()))
+}
+
+/// Load the reconstructed high-level artifacts of a scan set
+pub fn load_high_level(scan_set_dir: &Path) -> Result<Vec<HighLevelArtifact>> {
+    let path = scan_set_dir.join(HIGH_LEVEL_FILE);
+    let json = fs::read_to_string(&path).io_context(|| {
+        format!(
+            "Failed to read {} (run `scan3data reconstruct` first)",
+            path.display()
+        )
+    })?;
+    serde_json::from_str(&json).parse_context(|| format!("Failed to parse {}", HIGH_LEVEL_FILE))
+}
+
+/// Write the reconstructed high-level artifacts of a scan set
+pub fn save_high_level(scan_set_dir: &Path, artifacts: &[HighLevelArtifact]) -> Result<()> {
+    let path = scan_set_dir.join(HIGH_LEVEL_FILE);
+    let json = serde_json::to_string_pretty(artifacts)?;
+    write_atomic(&path, &json).io_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Append-only log of artifacts finished during a long run
+pub struct ArtifactJournal {
+    file: fs::File,
+}
+
+impl ArtifactJournal {
+    /// Open (or create) the journal of a scan set for appending
+    pub fn open(scan_set_dir: &Path) -> Result<Self> {
+        let path = scan_set_dir.join(JOURNAL_FILE);
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .io_context(|| format!("Failed to open journal: {}", path.display()))?;
+        Ok(Self { file })
+    }
+
+    /// Start a new journal for a run, replacing any earlier one, with the
+    /// run's settings as its header
+    pub fn start(scan_set_dir: &Path, settings: &serde_json::Value) -> Result<Self> {
+        let path = scan_set_dir.join(JOURNAL_FILE);
+        let mut line = serde_json::to_string(&JournalHeader {
+            run: settings.clone(),
+        })?;
+        line.push('\n');
+        fs::write(&path, line)
+            .io_context(|| format!("Failed to start journal: {}", path.display()))?;
+        Self::open(scan_set_dir)
+    }
+
+    /// Append an artifact and flush it to disk
+    pub fn append(&mut self, artifact: &PageArtifact) -> Result<()> {
+        let mut line = serde_json::to_string(artifact)?;
+        line.push('\n');
+        self.file
+            .write_all(line.as_bytes())
+            .and_then(|_| self.file.sync_data())
+            .io_context(|| "Failed to append to artifact journal".to_string())
+    }
+}
+
+/// Header line of a journal
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JournalHeader {
+    /// Settings of the run the journal checkpoints
+    run: serde_json::Value,
+}
+
+/// Settings recorded in the journal's header, if it has one
+pub fn journal_settings(scan_set_dir: &Path) -> Result<Option<serde_json::Value>> {
+    let path = scan_set_dir.join(JOURNAL_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let journal = fs::read_to_string(&path)
+        .io_context(|| format!("Failed to read journal: {}", path.display()))?;
+    Ok(journal
+        .lines()
+        .next()
+        .and_then(|line| serde_json::from_str::<JournalHeader>(line).ok())
+        .map(|header| header.run))
+}
+
+/// Apply journaled artifacts on top of loaded ones
+///
+/// Later entries win. A torn final line (from a crash mid-write) is
+/// ignored. Returns the ids of the artifacts found in the journal.
+pub fn replay_journal(
+    scan_set_dir: &Path,
+    artifacts: &mut [PageArtifact],
+) -> Result<HashSet<PageId>> {
+    let path = scan_set_dir.join(JOURNAL_FILE);
+    if !path.exists() {
+        return Ok(HashSet::new());
+    }
+    let journal = fs::read_to_string(&path)
+        .io_context(|| format!("Failed to read journal: {}", path.display()))?;
+
+    let lines: Vec<&str> = journal.lines().filter(|l| !l.trim().is_empty()).collect();
+    let mut replayed = HashSet::new();
+    for (idx, line) in lines.iter().enumerate() {
+        if idx == 0 && serde_json::from_str::<JournalHeader>(line).is_ok() {
+            continue;
+        }
+        let entry: PageArtifact = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            Err(_) if idx + 1 == lines.len() => break,
+            Err(e) => {
+                return Err(e)
+                    .parse_context(|| format!("Failed to parse {} line {}", JOURNAL_FILE, idx + 1))
+            }
+        };
+        if let Some(artifact) = artifacts.iter_mut().find(|a| a.id == entry.id) {
+            replayed.insert(entry.id);
+            *artifact = entry;
+        }
+    }
+    Ok(replayed)
+}
+
+/// Save artifacts and remove the journal they supersede
+pub fn compact_journal(scan_set_dir: &Path, artifacts: &[PageArtifact]) -> Result<()> {
+    write_artifacts(scan_set_dir, artifacts)?;
+    let path = scan_set_dir.join(JOURNAL_FILE);
+    if path.exists() {
+        fs::remove_file(&path)
+            .io_context(|| format!("Failed to remove journal: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Write a file via a temporary file and rename, so readers never see a
+/// partially written file
+fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut tmp = PathBuf::from(path);
+    tmp.as_mut_os_string().push(".tmp");
+    let mut file = fs::File::create(&tmp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ScanSetId;
+
+    fn manifest() -> ScanSetManifest {
+        ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "test".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            image_count: 0,
+            original_file_count: 0,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = manifest();
+        save_manifest(dir.path(), &manifest).unwrap();
+        save_artifacts(dir.path(), &[]).unwrap();
+
+        let (loaded, artifacts) = load(dir.path()).unwrap();
+        assert_eq!(loaded.scan_set_id, manifest.scan_set_id);
+        assert!(artifacts.is_empty());
+    }
+
+    #[test]
+    fn test_load_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(matches!(
+            load(&dir.path().join("missing")),
+            Err(Error::ScanSetNotFound(_))
+        ));
+
+        fs::write(dir.path().join(MANIFEST_FILE), "{").unwrap();
+        save_artifacts(dir.path(), &[]).unwrap();
+        assert!(matches!(load(dir.path()), Err(Error::ManifestParse { .. })));
+    }
+
+    #[test]
+    fn test_cards_optional() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_cards(dir.path()).unwrap().is_empty());
+
+        save_cards(dir.path(), &[]).unwrap();
+        assert!(dir.path().join(CARDS_FILE).exists());
+    }
+
+    #[test]
+    fn test_high_level_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_high_level(dir.path()).is_err());
+
+        save_high_level(dir.path(), &[]).unwrap();
+        assert!(load_high_level(dir.path()).unwrap().is_empty());
+    }
+
+    fn artifact() -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/a.jpg"),
+            processed_image_path: None,
+            layout_label: crate::types::ArtifactKind::Unknown,
+            content_text: None,
+            metadata: crate::types::PageMetadata::default(),
+        }
+    }
+
+    #[test]
+    fn test_journal_replay_and_compact() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts = vec![artifact(), artifact()];
+        save_manifest(dir.path(), &manifest()).unwrap();
+        save_artifacts(dir.path(), &artifacts).unwrap();
+
+        let mut done = artifacts[1].clone();
+        done.content_text = Some("      X = 1".to_string());
+        let mut journal = ArtifactJournal::open(dir.path()).unwrap();
+        journal.append(&done).unwrap();
+        drop(journal);
+        // Simulate a crash in the middle of the next append
+        let journal_path = dir.path().join(JOURNAL_FILE);
+        let mut torn = fs::read_to_string(&journal_path).unwrap();
+        torn.push_str("{\"id\":");
+        fs::write(&journal_path, torn).unwrap();
+
+        let (_, loaded) = load(dir.path()).unwrap();
+        assert_eq!(loaded[1].content_text, done.content_text);
+        assert_eq!(loaded[0].content_text, None);
+
+        let mut reloaded = load_artifacts(dir.path()).unwrap();
+        let replayed = replay_journal(dir.path(), &mut reloaded).unwrap();
+        assert_eq!(replayed, HashSet::from([done.id]));
+
+        assert_eq!(journal_settings(dir.path()).unwrap(), None);
+
+        compact_journal(dir.path(), &reloaded).unwrap();
+        assert!(!journal_path.exists());
+        assert_eq!(
+            load_artifacts(dir.path()).unwrap()[1].content_text,
+            done.content_text
+        );
+    }
+
+    #[test]
+    fn test_journal_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let artifacts = vec![artifact()];
+        save_artifacts(dir.path(), &artifacts).unwrap();
+        let settings = serde_json::json!({"autofix_threshold": 0.9});
+
+        let mut journal = ArtifactJournal::start(dir.path(), &settings).unwrap();
+        assert_eq!(journal_settings(dir.path()).unwrap(), Some(settings));
+        let mut done = artifacts[0].clone();
+        done.content_text = Some("DONE".to_string());
+        journal.append(&done).unwrap();
+
+        let mut reloaded = artifacts.clone();
+        let replayed = replay_journal(dir.path(), &mut reloaded).unwrap();
+        assert_eq!(replayed, HashSet::from([done.id]));
+        assert_eq!(reloaded[0].content_text.as_deref(), Some("DONE"));
+
+        // Starting again drops the earlier run's checkpoints
+        ArtifactJournal::start(dir.path(), &serde_json::Value::Null).unwrap();
+        assert!(replay_journal(dir.path(), &mut reloaded)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_load_missing_directory() {
+        let result = load(Path::new("/nonexistent/scan_set"));
+        assert!(result.unwrap_err().to_string().contains("does not exist"));
+    }
+}
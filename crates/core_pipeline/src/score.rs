@@ -0,0 +1,404 @@
+//! Accuracy against reference transcripts (golden corpus)
+//!
+//! Known-good transcripts are stored in the scan set as fixtures:
+//!
+//! ```text
+//! scan_set/
+//! `-- reference/
+//!     |-- 3fa2c1d04b5e6f70.txt   # named after the raw image ...
+//!     `-- PAGE_017.txt           # ... or an original filename
+//! ```
+//!
+//! Each artifact with a reference is scored at every stage whose output is
+//! still available: the raw OCR text from the stage cache and the final
+//! text after correction, auto-fix and hooks. Comparing the totals of two
+//! reports shows whether a new model, prompt or preprocessing change
+//! actually improved the results.
+
+use crate::error::{IoContext, ParseContext, Result};
+use crate::ocr::{ocr_cache_key, OcrOutput};
+use crate::preprocess::{compute_file_hash, preprocess_key};
+use crate::reconstruct::stitch::edit_distance;
+use crate::scan_set;
+use crate::stage_cache::StageCache;
+use crate::types::{PageArtifact, ScanSetId, ScanSetManifest};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory of reference transcripts within a scan set
+pub const REFERENCE_DIR: &str = "reference";
+
+/// Pipeline stages whose text is scored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScoreStage {
+    /// Raw Tesseract output
+    Ocr,
+    /// Artifact text after correction, auto-fix and hooks
+    Final,
+}
+
+impl ScoreStage {
+    /// All stages, in pipeline order
+    pub const ALL: [ScoreStage; 2] = [ScoreStage::Ocr, ScoreStage::Final];
+
+    /// Stage name for reports
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ocr => "ocr",
+            Self::Final => "final",
+        }
+    }
+}
+
+/// Edit-distance errors of a text against its reference
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Accuracy {
+    /// Characters in the reference
+    pub chars: usize,
+    /// Character insertions, deletions and substitutions
+    pub char_errors: usize,
+    /// Lines in the reference
+    pub lines: usize,
+    /// Inserted, deleted and changed lines
+    pub line_errors: usize,
+    /// Words in the reference
+    #[serde(default)]
+    pub words: usize,
+    /// Word insertions, deletions and substitutions
+    #[serde(default)]
+    pub word_errors: usize,
+}
+
+impl Accuracy {
+    /// Compare a text with its reference
+    ///
+    /// Trailing whitespace and trailing blank lines are ignored, since
+    /// card images pad lines to 80 columns.
+    pub fn measure(reference: &str, text: &str) -> Self {
+        let reference = normalized_lines(reference);
+        let text = normalized_lines(text);
+        let reference_chars: Vec<char> = reference.join("\n").chars().collect();
+        let text_chars: Vec<char> = text.join("\n").chars().collect();
+        let reference_words: Vec<&str> = reference
+            .iter()
+            .flat_map(|l| l.split_whitespace())
+            .collect();
+        let text_words: Vec<&str> = text.iter().flat_map(|l| l.split_whitespace()).collect();
+        Self {
+            chars: reference_chars.len(),
+            char_errors: edit_distance(&reference_chars, &text_chars),
+            lines: reference.len(),
+            line_errors: edit_distance(&reference, &text),
+            words: reference_words.len(),
+            word_errors: edit_distance(&reference_words, &text_words),
+        }
+    }
+
+    /// Fraction of reference characters read correctly (0.0-1.0)
+    pub fn char_accuracy(&self) -> f64 {
+        fraction_correct(self.char_errors, self.chars)
+    }
+
+    /// Fraction of reference lines read exactly (0.0-1.0)
+    pub fn line_accuracy(&self) -> f64 {
+        fraction_correct(self.line_errors, self.lines)
+    }
+
+    /// Character errors per reference character (CER)
+    pub fn char_error_rate(&self) -> f64 {
+        error_rate(self.char_errors, self.chars)
+    }
+
+    /// Word errors per reference word (WER)
+    pub fn word_error_rate(&self) -> f64 {
+        error_rate(self.word_errors, self.words)
+    }
+
+    /// Add another text's counts to these
+    pub fn add(&mut self, other: &Accuracy) {
+        self.chars += other.chars;
+        self.char_errors += other.char_errors;
+        self.lines += other.lines;
+        self.line_errors += other.line_errors;
+        self.words += other.words;
+        self.word_errors += other.word_errors;
+    }
+}
+
+/// Scores of one artifact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactScore {
+    /// Artifact identifier
+    pub artifact_id: String,
+    /// Raw image path (relative to the scan set)
+    pub image: PathBuf,
+    /// Reference transcript (relative to the scan set)
+    pub reference: PathBuf,
+    /// Accuracy at each stage with available text
+    pub stages: BTreeMap<ScoreStage, Accuracy>,
+}
+
+/// Accuracy of a scan set against its reference transcripts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreReport {
+    /// Scan set the report covers
+    pub scan_set_id: ScanSetId,
+    /// Human-readable scan set name
+    pub name: String,
+    /// Artifacts with a reference transcript
+    pub artifacts: Vec<ArtifactScore>,
+    /// Artifacts without a reference transcript (not scored)
+    pub without_reference: usize,
+    /// Counts over all scored artifacts, per stage
+    pub totals: BTreeMap<ScoreStage, Accuracy>,
+}
+
+impl ScoreReport {
+    /// Score every artifact of a scan set that has a reference transcript
+    pub fn score(scan_set_dir: &Path) -> Result<Self> {
+        let (manifest, artifacts) = scan_set::load(scan_set_dir)?;
+        let stage_cache = StageCache::new(scan_set_dir);
+        let mut report = Self {
+            scan_set_id: manifest.scan_set_id,
+            name: manifest.name.clone(),
+            artifacts: Vec::new(),
+            without_reference: 0,
+            totals: BTreeMap::new(),
+        };
+
+        for artifact in &artifacts {
+            let Some(reference_path) = reference_path(scan_set_dir, artifact) else {
+                report.without_reference += 1;
+                continue;
+            };
+            let reference = fs::read_to_string(scan_set_dir.join(&reference_path))
+                .io_context(|| format!("Failed to read reference: {}", reference_path.display()))?;
+
+            let ocr_text = cached_ocr_text(scan_set_dir, &stage_cache, artifact, &manifest)?;
+
+            let mut stages = BTreeMap::new();
+            for (stage, text) in [
+                (ScoreStage::Ocr, ocr_text.as_deref()),
+                (ScoreStage::Final, artifact.content_text.as_deref()),
+            ] {
+                if let Some(text) = text {
+                    let accuracy = Accuracy::measure(&reference, text);
+                    report.totals.entry(stage).or_default().add(&accuracy);
+                    stages.insert(stage, accuracy);
+                }
+            }
+            report.artifacts.push(ArtifactScore {
+                artifact_id: artifact.id.0.to_string(),
+                image: artifact.raw_image_path.clone(),
+                reference: reference_path,
+                stages,
+            });
+        }
+        Ok(report)
+    }
+
+    /// Load a report written by [`ScoreReport::write`]
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .io_context(|| format!("Failed to read score report: {}", path.display()))?;
+        serde_json::from_str(&json)
+            .parse_context(|| format!("Failed to parse score report: {}", path.display()))
+    }
+
+    /// Write the report as JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(path, json)
+            .io_context(|| format!("Failed to write score report: {}", path.display()))
+    }
+
+    /// Change in character and line accuracy of a stage since a baseline
+    /// report, if both scored the stage
+    pub fn delta(&self, baseline: &ScoreReport, stage: ScoreStage) -> Option<(f64, f64)> {
+        let current = self.totals.get(&stage)?;
+        let previous = baseline.totals.get(&stage)?;
+        Some((
+            current.char_accuracy() - previous.char_accuracy(),
+            current.line_accuracy() - previous.line_accuracy(),
+        ))
+    }
+}
+
+/// Reference transcript of an artifact, relative to the scan set
+///
+/// Looks for `reference/<name>.txt` named after the raw image, then after
+/// each original filename.
+pub fn reference_path(scan_set_dir: &Path, artifact: &PageArtifact) -> Option<PathBuf> {
+    transcript_path(&scan_set_dir.join(REFERENCE_DIR), artifact).and_then(|path| {
+        path.file_name()
+            .map(|name| Path::new(REFERENCE_DIR).join(name))
+    })
+}
+
+/// Transcript of an artifact in a directory of `<name>.txt` files, named
+/// after the raw image or one of the original filenames
+pub fn transcript_path(dir: &Path, artifact: &PageArtifact) -> Option<PathBuf> {
+    std::iter::once(artifact.raw_image_path.as_path())
+        .chain(artifact.metadata.original_filenames.iter().map(Path::new))
+        .filter_map(Path::file_stem)
+        .map(|stem| dir.join(stem).with_extension("txt"))
+        .find(|path| path.is_file())
+}
+
+/// Raw OCR text of an artifact, if the OCR stage result of the scan set's
+/// keypunch and preprocessing steps is still cached
+pub fn cached_ocr_text(
+    scan_set_dir: &Path,
+    stage_cache: &StageCache,
+    artifact: &PageArtifact,
+    manifest: &ScanSetManifest,
+) -> Result<Option<String>> {
+    let source_hash = if artifact.metadata.content_hash.is_empty() {
+        compute_file_hash(&scan_set_dir.join(&artifact.raw_image_path))?
+    } else {
+        artifact.metadata.content_hash.clone()
+    };
+    let ocr_key = ocr_cache_key(
+        &preprocess_key(&source_hash, &manifest.preprocess)?,
+        manifest.keypunch.model,
+    )?;
+    Ok(stage_cache
+        .get::<OcrOutput>(&ocr_key)?
+        .map(|output| output.text))
+}
+
+/// Lines with trailing whitespace and trailing blank lines removed
+pub(crate) fn normalized_lines(text: &str) -> Vec<&str> {
+    let mut lines: Vec<&str> = text.lines().map(str::trim_end).collect();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+fn error_rate(errors: usize, total: usize) -> f64 {
+    if total == 0 {
+        return if errors == 0 { 0.0 } else { 1.0 };
+    }
+    errors as f64 / total as f64
+}
+
+fn fraction_correct(errors: usize, total: usize) -> f64 {
+    if total == 0 {
+        return if errors == 0 { 1.0 } else { 0.0 };
+    }
+    (1.0 - errors as f64 / total as f64).max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageId, PageMetadata, ScanSetManifest};
+
+    #[test]
+    fn test_measure() {
+        let reference = "      X = 1\n      END\n";
+        let perfect = Accuracy::measure(reference, "      X = 1   \n      END\n\n");
+        assert_eq!(perfect.char_errors, 0);
+        assert_eq!(perfect.line_accuracy(), 1.0);
+
+        let misread = Accuracy::measure(reference, "      X = I\n      END");
+        assert_eq!((misread.chars, misread.char_errors), (21, 1));
+        assert_eq!((misread.lines, misread.line_errors), (2, 1));
+        assert_eq!(misread.line_accuracy(), 0.5);
+        assert_eq!((misread.words, misread.word_errors), (4, 1));
+        assert_eq!(misread.word_error_rate(), 0.25);
+        assert_eq!(misread.char_error_rate(), 1.0 / 21.0);
+
+        assert_eq!(Accuracy::measure("AB", "").char_accuracy(), 0.0);
+        assert_eq!(Accuracy::measure("", "").char_accuracy(), 1.0);
+        assert_eq!(Accuracy::measure("", "").word_error_rate(), 0.0);
+        assert_eq!(Accuracy::measure("A", "B C D").word_error_rate(), 3.0);
+    }
+
+    #[test]
+    fn test_score_scan_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "Box 3".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            image_count: 2,
+            original_file_count: 2,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        };
+        let artifact = |hash: &str, original: &str, text: &str| PageArtifact {
+            id: PageId::new(),
+            scan_set: manifest.scan_set_id,
+            raw_image_path: PathBuf::from(format!("images/{}.png", hash)),
+            processed_image_path: None,
+            layout_label: ArtifactKind::Unknown,
+            content_text: Some(text.to_string()),
+            metadata: PageMetadata {
+                content_hash: hash.to_string(),
+                file_hash: None,
+                original_filenames: vec![original.to_string()],
+                page_number: None,
+                header: None,
+                footer: None,
+                notes: Vec::new(),
+                confidence: 0.0,
+                revisions: Vec::new(),
+                validation_issues: Vec::new(),
+                derived_images: Vec::new(),
+                cost_usd: 0.0,
+                source_pages: Vec::new(),
+                perceptual_hash: None,
+                ingest_index: 0,
+                originals: Vec::new(),
+                reading_order: None,
+                human_transcribed: false,
+                cleaned_image_path: None,
+                thumbnail_path: None,
+                manual_classification: false,
+                base_confidence: None,
+            },
+        };
+        let artifacts = vec![
+            artifact("aa11aa11aa11aa11", "PAGE_001.jpg", "      X = 1\n"),
+            artifact("bb22bb22bb22bb22", "PAGE_002.jpg", "      END\n"),
+        ];
+        scan_set::save_manifest(dir.path(), &manifest).unwrap();
+        scan_set::save_artifacts(dir.path(), &artifacts).unwrap();
+
+        fs::create_dir(dir.path().join(REFERENCE_DIR)).unwrap();
+        fs::write(dir.path().join("reference/PAGE_001.txt"), "      X = 1\n").unwrap();
+        let ocr_key = ocr_cache_key(
+            &preprocess_key("aa11aa11aa11aa11", &manifest.preprocess).unwrap(),
+            Default::default(),
+        )
+        .unwrap();
+        let ocr = OcrOutput {
+            text: "      X = I\n".to_string(),
+            ..Default::default()
+        };
+        StageCache::new(dir.path()).put(&ocr_key, &ocr).unwrap();
+
+        let report = ScoreReport::score(dir.path()).unwrap();
+        assert_eq!(report.without_reference, 1);
+        assert_eq!(report.artifacts.len(), 1);
+        assert_eq!(
+            report.artifacts[0].reference,
+            PathBuf::from("reference/PAGE_001.txt")
+        );
+        assert_eq!(report.totals[&ScoreStage::Ocr].char_errors, 1);
+        assert_eq!(report.totals[&ScoreStage::Final].char_errors, 0);
+
+        let path = dir.path().join("score.json");
+        report.write(&path).unwrap();
+        let baseline = ScoreReport::load(&path).unwrap();
+        assert_eq!(report.delta(&baseline, ScoreStage::Final), Some((0.0, 0.0)));
+    }
+}
@@ -0,0 +1,219 @@
+//! Pipeline stage cache
+//!
+//! Stores the result of a pipeline stage (OCR text, vision correction, ...)
+//! under a key made of the stage name, a hash identifying the stage input
+//! and a hash of the stage parameters:
+//!
+//! ```text
+//! cache/<stage>/<input hash>-<params hash>.json
+//! ```
+//!
+//! Re-running with unchanged inputs and parameters reuses the stored
+//! result, so changing one setting only re-runs the stages it affects.
+//! Derived images are cached the same way by [`crate::derived`].
+
+use crate::error::{Error, IoContext, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Stage cache directory within a scan set directory
+pub const CACHE_DIR: &str = "cache";
+
+/// Hex digits of each hash used in cache paths
+pub(crate) const HASH_PREFIX_LEN: usize = 16;
+
+/// SHA-256 of the JSON form of stage parameters (or any serializable input)
+pub fn hash_json(value: &impl Serialize) -> Result<String> {
+    let json = serde_json::to_string(value)?;
+    Ok(format!("{:x}", Sha256::digest(json.as_bytes())))
+}
+
+/// Identity of a cached stage result
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    /// Stage name (e.g. "ocr")
+    pub stage: String,
+    /// Hash identifying the stage input
+    pub input_hash: String,
+    /// Hash of the stage parameters
+    pub params_hash: String,
+}
+
+impl CacheKey {
+    /// Build a key for a stage run on `input` with `params`
+    ///
+    /// `input` is anything identifying the input content, such as a content
+    /// hash or the id of an upstream cache entry.
+    pub fn new(stage: &str, input: &impl Serialize, params: &impl Serialize) -> Result<Self> {
+        if stage.is_empty() || !stage.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(Error::invalid(format!(
+                "Invalid stage name for cache: '{}'",
+                stage
+            )));
+        }
+        Ok(Self {
+            stage: stage.to_string(),
+            input_hash: hash_json(input)?[..HASH_PREFIX_LEN].to_string(),
+            params_hash: hash_json(params)?[..HASH_PREFIX_LEN].to_string(),
+        })
+    }
+
+    /// Unique identifier, usable as the input of a downstream stage
+    pub fn id(&self) -> String {
+        format!("{}-{}-{}", self.stage, self.input_hash, self.params_hash)
+    }
+
+    /// Path relative to the scan set directory
+    pub fn relative_path(&self) -> PathBuf {
+        PathBuf::from(CACHE_DIR)
+            .join(&self.stage)
+            .join(format!("{}-{}.json", self.input_hash, self.params_hash))
+    }
+}
+
+/// Stage result cache of a scan set
+#[derive(Debug, Clone)]
+pub struct StageCache {
+    scan_set_dir: PathBuf,
+}
+
+impl StageCache {
+    /// Open the cache of a scan set directory
+    pub fn new(scan_set_dir: &Path) -> Self {
+        Self {
+            scan_set_dir: scan_set_dir.to_path_buf(),
+        }
+    }
+
+    /// Load a cached result
+    ///
+    /// Entries that no longer parse (e.g. written by an older version) are
+    /// treated as missing.
+    pub fn get<T: DeserializeOwned>(&self, key: &CacheKey) -> Result<Option<T>> {
+        let path = self.scan_set_dir.join(key.relative_path());
+        if !path.exists() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(&path)
+            .io_context(|| format!("Failed to read cache entry: {}", path.display()))?;
+        Ok(serde_json::from_str(&json).ok())
+    }
+
+    /// Store a result (atomically, via a temporary file)
+    pub fn put<T: Serialize>(&self, key: &CacheKey, value: &T) -> Result<()> {
+        let path = self.scan_set_dir.join(key.relative_path());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .io_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(value)?)
+            .and_then(|_| fs::rename(&tmp, &path))
+            .io_context(|| format!("Failed to write cache entry: {}", path.display()))
+    }
+
+    /// Return the cached result, or compute and store it
+    ///
+    /// Errors from `compute` are returned and not cached; `compute` may
+    /// use its own error type (e.g. `anyhow::Error`) as long as cache
+    /// errors convert into it. The flag is true when the result came from
+    /// the cache.
+    pub fn get_or_compute<T, E>(
+        &self,
+        key: &CacheKey,
+        compute: impl FnOnce() -> Result<T, E>,
+    ) -> Result<(T, bool), E>
+    where
+        T: Serialize + DeserializeOwned,
+        E: From<Error>,
+    {
+        if let Some(value) = self.get(key)? {
+            return Ok((value, true));
+        }
+        let value = compute()?;
+        self.put(key, &value)?;
+        Ok((value, false))
+    }
+
+    /// Delete cached results of one stage, or of all stages
+    ///
+    /// Returns the number of entries deleted.
+    pub fn clear(&self, stage: Option<&str>) -> Result<usize> {
+        let root = self.scan_set_dir.join(CACHE_DIR);
+        let dirs: Vec<PathBuf> = match stage {
+            Some(stage) => vec![root.join(stage)],
+            None if root.exists() => fs::read_dir(&root)
+                .and_then(|entries| entries.map(|entry| entry.map(|e| e.path())).collect())
+                .io_context(|| format!("Failed to read {}", root.display()))?,
+            None => Vec::new(),
+        };
+
+        let mut removed = 0;
+        for dir in dirs.into_iter().filter(|d| d.is_dir()) {
+            removed += fs::read_dir(&dir)
+                .io_context(|| format!("Failed to read {}", dir.display()))?
+                .count();
+            fs::remove_dir_all(&dir)
+                .io_context(|| format!("Failed to remove {}", dir.display()))?;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_depends_on_input_and_params() {
+        let key = CacheKey::new("ocr", &"abc", &1).unwrap();
+        assert_eq!(key, CacheKey::new("ocr", &"abc", &1).unwrap());
+        assert_ne!(key, CacheKey::new("ocr", &"abd", &1).unwrap());
+        assert_ne!(key, CacheKey::new("ocr", &"abc", &2).unwrap());
+        assert!(key.relative_path().starts_with("cache/ocr"));
+        assert!(CacheKey::new("../ocr", &"abc", &1).is_err());
+    }
+
+    #[test]
+    fn test_get_or_compute() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = StageCache::new(dir.path());
+        let key = CacheKey::new("ocr", &"abc", &1).unwrap();
+
+        let (text, cached) = cache
+            .get_or_compute(&key, || Ok::<_, Error>("HELLO".to_string()))
+            .unwrap();
+        assert_eq!((text.as_str(), cached), ("HELLO", false));
+
+        let (text, cached) = cache
+            .get_or_compute::<String, Error>(&key, || panic!("should be cached"))
+            .unwrap();
+        assert_eq!((text.as_str(), cached), ("HELLO", true));
+
+        // Failures are not cached
+        let other = CacheKey::new("ocr", &"def", &1).unwrap();
+        assert!(cache
+            .get_or_compute::<String, _>(&other, || Err(Error::Ocr("no text".to_string())))
+            .is_err());
+        assert!(cache.get::<String>(&other).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = StageCache::new(dir.path());
+        cache
+            .put(&CacheKey::new("ocr", &"a", &1).unwrap(), &"A")
+            .unwrap();
+        cache
+            .put(&CacheKey::new("vision", &"a", &1).unwrap(), &"B")
+            .unwrap();
+
+        assert_eq!(cache.clear(Some("ocr")).unwrap(), 1);
+        assert_eq!(cache.clear(Some("ocr")).unwrap(), 0);
+        assert_eq!(cache.clear(None).unwrap(), 1);
+    }
+}
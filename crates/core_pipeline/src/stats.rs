@@ -0,0 +1,219 @@
+//! Scan set analytics
+//!
+//! Summarizes what analysis made of a scan set: how artifacts were
+//! classified and how confidently, how much text OCR found, which
+//! processing notes come up most, how many scans were duplicates, and
+//! which pages are missing, repeated or out of order.
+
+use crate::error::Result;
+use crate::reconstruct::{page_numbering, DocumentNumbering};
+use crate::scan_set;
+use crate::types::{PageArtifact, ScanSetManifest};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Number of buckets of the confidence histogram (0.0-0.1, ..., 0.9-1.0)
+pub const CONFIDENCE_BUCKETS: usize = 10;
+
+/// Statistics of a scan set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScanSetStats {
+    /// Number of artifacts
+    pub artifacts: usize,
+    /// Artifacts per classification (`CardText`, `ListingSource`, ...)
+    pub kinds: BTreeMap<String, usize>,
+    /// Artifacts per tenth of classification confidence
+    pub confidence: [usize; CONFIDENCE_BUCKETS],
+    /// Artifacts with non-blank OCR text
+    pub with_text: usize,
+    /// Average text length of the artifacts with text (characters)
+    pub average_text_len: f64,
+    /// Artifacts per kind of processing note
+    ///
+    /// Notes are grouped by their text up to the first `:`, so "Hook
+    /// post-ocr replaced the text: ..." notes count together whatever the
+    /// hook.
+    pub notes: BTreeMap<String, usize>,
+    /// Duplicate detection at ingest
+    pub duplicates: DuplicateStats,
+    /// Page numbering of the documents with detected page numbers
+    #[serde(default)]
+    pub numbering: Vec<DocumentNumbering>,
+}
+
+/// Duplicate statistics of a scan set
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DuplicateStats {
+    /// Files (and PDF pages) ingested
+    pub files: usize,
+    /// Files that duplicated another one
+    pub duplicate_files: usize,
+    /// Artifacts ingested from more than one file
+    pub merged_artifacts: usize,
+    /// Suspected near-duplicate pairs awaiting review
+    pub near_duplicates: usize,
+}
+
+impl ScanSetStats {
+    /// Compute the statistics of a scan set directory
+    pub fn of_scan_set(scan_set_dir: &Path) -> Result<Self> {
+        let (manifest, artifacts) = scan_set::load(scan_set_dir)?;
+        Ok(Self::compute(&manifest, &artifacts))
+    }
+
+    /// Compute the statistics of a loaded scan set
+    pub fn compute(manifest: &ScanSetManifest, artifacts: &[PageArtifact]) -> Self {
+        let mut stats = Self {
+            artifacts: artifacts.len(),
+            duplicates: DuplicateStats {
+                files: manifest.original_file_count,
+                duplicate_files: manifest.duplicate_count,
+                merged_artifacts: artifacts
+                    .iter()
+                    .filter(|a| a.metadata.original_filenames.len() > 1)
+                    .count(),
+                near_duplicates: manifest.near_duplicates.len(),
+            },
+            numbering: page_numbering(artifacts),
+            ..Self::default()
+        };
+
+        let mut text_len = 0;
+        for artifact in artifacts {
+            *stats
+                .kinds
+                .entry(format!("{:?}", artifact.layout_label))
+                .or_default() += 1;
+
+            let confidence = artifact.metadata.confidence.clamp(0.0, 1.0);
+            let bucket =
+                ((confidence * CONFIDENCE_BUCKETS as f32) as usize).min(CONFIDENCE_BUCKETS - 1);
+            stats.confidence[bucket] += 1;
+
+            if let Some(text) = artifact
+                .content_text
+                .as_deref()
+                .filter(|text| !text.trim().is_empty())
+            {
+                stats.with_text += 1;
+                text_len += text.chars().count();
+            }
+
+            for note in &artifact.metadata.notes {
+                let kind = note.split(':').next().unwrap_or(note).trim();
+                *stats.notes.entry(kind.to_string()).or_default() += 1;
+            }
+        }
+        if stats.with_text > 0 {
+            stats.average_text_len = text_len as f64 / stats.with_text as f64;
+        }
+        stats
+    }
+
+    /// Share of artifacts with OCR text (0.0-1.0)
+    pub fn ocr_coverage(&self) -> f64 {
+        if self.artifacts == 0 {
+            return 0.0;
+        }
+        self.with_text as f64 / self.artifacts as f64
+    }
+
+    /// Note kinds, most frequent first
+    pub fn top_notes(&self) -> Vec<(&str, usize)> {
+        let mut notes: Vec<(&str, usize)> = self
+            .notes
+            .iter()
+            .map(|(note, &count)| (note.as_str(), count))
+            .collect();
+        notes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        notes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, NearDuplicate, PageId, ScanSetId};
+    use std::path::PathBuf;
+
+    fn artifact(
+        kind: ArtifactKind,
+        confidence: f32,
+        text: Option<&str>,
+        notes: &[&str],
+    ) -> PageArtifact {
+        let mut artifact = PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/a.jpg"),
+            processed_image_path: None,
+            layout_label: kind,
+            content_text: text.map(str::to_string),
+            metadata: Default::default(),
+        };
+        artifact.metadata.confidence = confidence;
+        artifact.metadata.notes = notes.iter().map(|note| note.to_string()).collect();
+        artifact
+    }
+
+    #[test]
+    fn test_compute_stats() {
+        let manifest = ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "box3".to_string(),
+            created_at: String::new(),
+            image_count: 3,
+            original_file_count: 5,
+            duplicate_count: 2,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: vec![NearDuplicate {
+                first: PageId::new(),
+                second: PageId::new(),
+                distance: 3,
+            }],
+        };
+        let mut merged = artifact(ArtifactKind::CardText, 1.0, Some("  ABC\n"), &[]);
+        merged.metadata.original_filenames = vec!["a.png".to_string(), "a2.png".to_string()];
+        let artifacts = vec![
+            merged,
+            artifact(
+                ArtifactKind::CardText,
+                0.55,
+                Some("ABCDEFGHI"),
+                &["Hook post-ocr replaced the text: tr O 0", "damaged"],
+            ),
+            artifact(
+                ArtifactKind::Unknown,
+                0.0,
+                Some(" \n"),
+                &["Hook post-ocr replaced the text: fix.py"],
+            ),
+        ];
+
+        let stats = ScanSetStats::compute(&manifest, &artifacts);
+        assert_eq!(stats.artifacts, 3);
+        assert_eq!(stats.kinds["CardText"], 2);
+        assert_eq!(stats.kinds["Unknown"], 1);
+        assert_eq!(stats.confidence, [1, 0, 0, 0, 0, 1, 0, 0, 0, 1]);
+        assert_eq!(stats.with_text, 2);
+        assert!((stats.ocr_coverage() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(stats.average_text_len, 7.5);
+        assert_eq!(
+            stats.top_notes(),
+            vec![("Hook post-ocr replaced the text", 2), ("damaged", 1)]
+        );
+        assert_eq!(
+            stats.duplicates,
+            DuplicateStats {
+                files: 5,
+                duplicate_files: 2,
+                merged_artifacts: 1,
+                near_duplicates: 1,
+            }
+        );
+        assert!(stats.numbering.is_empty());
+    }
+}
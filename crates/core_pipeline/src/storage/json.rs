@@ -0,0 +1,121 @@
+//! JSON-files storage backend
+
+use super::{ArtifactFilter, ScanSetStore, StorageBackend};
+use crate::error::Result;
+use crate::scan_set;
+use crate::types::{PageArtifact, PageId, ScanSetManifest};
+use crate::validate::ValidationIssue;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Scan set stored as `manifest.json` and `artifacts.json`
+///
+/// Single-artifact updates go to the artifact journal, so they do not
+/// rewrite `artifacts.json`; a full save compacts the journal. A run's
+/// settings are the journal's header.
+#[derive(Debug, Clone)]
+pub struct JsonStore {
+    scan_set_dir: PathBuf,
+}
+
+impl JsonStore {
+    /// Use the JSON files of a scan set directory
+    pub fn new(scan_set_dir: &Path) -> Self {
+        Self {
+            scan_set_dir: scan_set_dir.to_path_buf(),
+        }
+    }
+
+    /// Artifacts of `artifacts.json`, without the journal
+    fn read_artifacts(&self) -> Result<Vec<PageArtifact>> {
+        if self.scan_set_dir.join(scan_set::ARTIFACTS_FILE).exists() {
+            scan_set::read_artifacts(&self.scan_set_dir)
+        } else {
+            Ok(Vec::new())
+        }
+    }
+}
+
+impl ScanSetStore for JsonStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Json
+    }
+
+    fn load_manifest(&self) -> Result<ScanSetManifest> {
+        scan_set::read_manifest(&self.scan_set_dir)
+    }
+
+    fn save_manifest(&mut self, manifest: &ScanSetManifest) -> Result<()> {
+        scan_set::write_manifest(&self.scan_set_dir, manifest)
+    }
+
+    fn load_artifacts(&self) -> Result<Vec<PageArtifact>> {
+        let mut artifacts = self.read_artifacts()?;
+        scan_set::replay_journal(&self.scan_set_dir, &mut artifacts)?;
+        Ok(artifacts)
+    }
+
+    fn find_artifacts(&self, filter: &ArtifactFilter) -> Result<Vec<PageArtifact>> {
+        let mut artifacts = self.load_artifacts()?;
+        artifacts.retain(|artifact| filter.includes(artifact));
+        Ok(artifacts)
+    }
+
+    fn save_artifacts(&mut self, artifacts: &[PageArtifact]) -> Result<()> {
+        scan_set::compact_journal(&self.scan_set_dir, artifacts)
+    }
+
+    fn save_artifact(&mut self, artifact: &PageArtifact) -> Result<()> {
+        let mut artifacts = self.read_artifacts()?;
+        if artifacts.iter().any(|a| a.id == artifact.id) {
+            scan_set::ArtifactJournal::open(&self.scan_set_dir)?.append(artifact)
+        } else {
+            // The journal only updates existing artifacts; keep it, since
+            // it still holds the checkpoints
+            artifacts.push(artifact.clone());
+            scan_set::write_artifacts(&self.scan_set_dir, &artifacts)
+        }
+    }
+
+    fn checkpointed(&self) -> Result<HashSet<PageId>> {
+        let mut artifacts = self.read_artifacts()?;
+        scan_set::replay_journal(&self.scan_set_dir, &mut artifacts)
+    }
+
+    fn begin_run(&mut self, settings: &serde_json::Value) -> Result<()> {
+        // Fold an earlier run's updates in before replacing its journal
+        let artifacts = self.load_artifacts()?;
+        scan_set::compact_journal(&self.scan_set_dir, &artifacts)?;
+        scan_set::ArtifactJournal::start(&self.scan_set_dir, settings).map(drop)
+    }
+
+    fn run_settings(&self) -> Result<Option<serde_json::Value>> {
+        scan_set::journal_settings(&self.scan_set_dir)
+    }
+
+    fn issues_by_rule(&self, rule: &str) -> Result<Vec<(PageId, ValidationIssue)>> {
+        Ok(self
+            .load_artifacts()?
+            .into_iter()
+            .flat_map(|a| {
+                let id = a.id;
+                a.metadata
+                    .validation_issues
+                    .into_iter()
+                    .map(move |issue| (id, issue))
+            })
+            .filter(|(_, issue)| issue.rule == rule)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_store() {
+        let dir = tempfile::tempdir().unwrap();
+        super::super::tests::exercise_store(&mut JsonStore::new(dir.path()));
+    }
+}
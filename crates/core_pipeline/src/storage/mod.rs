@@ -0,0 +1,356 @@
+//! Scan set storage backends
+//!
+//! A scan set's manifest and page artifacts can be stored either as the
+//! JSON files described in [`crate::scan_set`] or, with the `sqlite`
+//! feature, in a single SQLite database inside the scan set directory.
+//! The SQLite backend updates one artifact at a time instead of rewriting
+//! the whole artifact list, and keeps revisions and validation issues in
+//! indexed tables so they can be queried without loading every page.
+//!
+//! The backend is chosen per scan set when it is ingested: a directory
+//! containing [`SQLITE_FILE`] uses SQLite, anything else uses JSON. The
+//! functions of [`crate::scan_set`] go through the scan set's store, so
+//! commands work the same with either. Images, derived images and cached
+//! stage results stay on disk with either backend.
+
+mod json;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use json::JsonStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteStore;
+
+use crate::error::{Error, Result};
+use crate::types::{ArtifactKind, PageArtifact, PageId, ScanSetManifest};
+use crate::validate::ValidationIssue;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// SQLite database filename within a scan set directory
+pub const SQLITE_FILE: &str = "scan_set.sqlite";
+
+/// How a scan set's manifest and artifacts are stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// `manifest.json` and `artifacts.json` (plus the artifact journal)
+    #[default]
+    Json,
+    /// A single SQLite database
+    Sqlite,
+}
+
+impl StorageBackend {
+    /// Backend used by an existing scan set directory
+    pub fn detect(scan_set_dir: &Path) -> Self {
+        if scan_set_dir.join(SQLITE_FILE).exists() {
+            Self::Sqlite
+        } else {
+            Self::Json
+        }
+    }
+
+    /// Parse a backend name ("json" or "sqlite")
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "sqlite" => Some(Self::Sqlite),
+            _ => None,
+        }
+    }
+
+    /// Lowercase name of the backend
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Sqlite => "sqlite",
+        }
+    }
+}
+
+/// Which artifacts to load; every filter is optional
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ArtifactFilter {
+    /// Artifact kind
+    pub kind: Option<ArtifactKind>,
+    /// Lowest classification confidence (0.0-1.0)
+    pub min_confidence: Option<f32>,
+    /// Highest classification confidence (0.0-1.0)
+    pub max_confidence: Option<f32>,
+}
+
+impl ArtifactFilter {
+    /// Whether the filter includes an artifact
+    pub fn includes(&self, artifact: &PageArtifact) -> bool {
+        let confidence = artifact.metadata.confidence;
+        self.kind.is_none_or(|kind| kind == artifact.layout_label)
+            && self.min_confidence.is_none_or(|min| confidence >= min)
+            && self.max_confidence.is_none_or(|max| confidence <= max)
+    }
+}
+
+/// Storage for a scan set's manifest and page artifacts
+pub trait ScanSetStore: Send {
+    /// Which backend this is
+    fn backend(&self) -> StorageBackend;
+
+    /// Load the manifest
+    fn load_manifest(&self) -> Result<ScanSetManifest>;
+
+    /// Replace the manifest
+    fn save_manifest(&mut self, manifest: &ScanSetManifest) -> Result<()>;
+
+    /// Load all artifacts, in scan set order
+    fn load_artifacts(&self) -> Result<Vec<PageArtifact>>;
+
+    /// Load the artifacts a filter includes, in scan set order
+    fn find_artifacts(&self, filter: &ArtifactFilter) -> Result<Vec<PageArtifact>>;
+
+    /// Replace all artifacts, ending the checkpoints of a run
+    fn save_artifacts(&mut self, artifacts: &[PageArtifact]) -> Result<()>;
+
+    /// Insert or update a single artifact
+    ///
+    /// New artifacts are added at the end of the scan set. An update is a
+    /// checkpoint of a long run: it is listed by [`Self::checkpointed`]
+    /// until the next [`Self::save_artifacts`].
+    fn save_artifact(&mut self, artifact: &PageArtifact) -> Result<()>;
+
+    /// Artifacts updated one at a time since the last full save, i.e.
+    /// those an interrupted run finished
+    fn checkpointed(&self) -> Result<HashSet<PageId>>;
+
+    /// Start a run that checkpoints: record its settings and end the
+    /// checkpoints of an earlier run, keeping the artifacts it saved
+    fn begin_run(&mut self, settings: &serde_json::Value) -> Result<()>;
+
+    /// Settings of the run the checkpoints belong to, if one was begun
+    /// since the last full save
+    fn run_settings(&self) -> Result<Option<serde_json::Value>>;
+
+    /// Validation issues reported by a rule, with the artifact they belong to
+    fn issues_by_rule(&self, rule: &str) -> Result<Vec<(PageId, ValidationIssue)>>;
+}
+
+/// Open the store of an existing scan set, using the backend it was
+/// created with
+pub fn open(scan_set_dir: &Path) -> Result<Box<dyn ScanSetStore>> {
+    open_with(scan_set_dir, StorageBackend::detect(scan_set_dir))
+}
+
+/// Open (or create) the store of a scan set with a specific backend
+pub fn open_with(scan_set_dir: &Path, backend: StorageBackend) -> Result<Box<dyn ScanSetStore>> {
+    match backend {
+        StorageBackend::Json => Ok(Box::new(JsonStore::new(scan_set_dir))),
+        #[cfg(feature = "sqlite")]
+        StorageBackend::Sqlite => Ok(Box::new(SqliteStore::open(scan_set_dir)?)),
+        #[cfg(not(feature = "sqlite"))]
+        StorageBackend::Sqlite => Err(Error::invalid(format!(
+            "Scan set {} uses SQLite storage, but this build lacks the `sqlite` feature",
+            scan_set_dir.display()
+        ))),
+    }
+}
+
+/// Copy a scan set's manifest and artifacts into another backend
+///
+/// The source files are left in place; a directory holding both is read
+/// as SQLite, so delete the database to switch back to JSON.
+pub fn convert(scan_set_dir: &Path, to: StorageBackend) -> Result<()> {
+    let from = StorageBackend::detect(scan_set_dir);
+    if from == to {
+        return Err(Error::invalid(format!(
+            "Scan set already uses {} storage",
+            to.as_str()
+        )));
+    }
+    let source = open_with(scan_set_dir, from)?;
+    let manifest = source.load_manifest()?;
+    let artifacts = source.load_artifacts()?;
+    drop(source);
+
+    let mut target = open_with(scan_set_dir, to)?;
+    target.save_manifest(&manifest)?;
+    target.save_artifacts(&artifacts)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageMetadata, ScanSetId, TextRevision};
+    use crate::validate::Severity;
+    use std::path::PathBuf;
+
+    pub(crate) fn manifest() -> ScanSetManifest {
+        ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "test".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            image_count: 2,
+            original_file_count: 2,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        }
+    }
+
+    pub(crate) fn artifact(rule: &str) -> PageArtifact {
+        let mut metadata = PageMetadata::default();
+        metadata.revisions.push(TextRevision {
+            source: "autofix".to_string(),
+            line_number: 1,
+            column: 7,
+            before: "O".to_string(),
+            after: "0".to_string(),
+            reason: "digit context".to_string(),
+            confidence: 0.9,
+        });
+        metadata.validation_issues.push(ValidationIssue {
+            rule: rule.to_string(),
+            line_number: 2,
+            column: Some(7),
+            severity: Severity::Error,
+            description: "bad".to_string(),
+            excerpt: "      X".to_string(),
+            suggestion: None,
+        });
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/a.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some("      X = 1".to_string()),
+            metadata,
+        }
+    }
+
+    /// Behaviour every backend must share
+    pub(crate) fn exercise_store(store: &mut dyn ScanSetStore) {
+        let manifest = manifest();
+        store.save_manifest(&manifest).unwrap();
+        assert_eq!(
+            store.load_manifest().unwrap().scan_set_id,
+            manifest.scan_set_id
+        );
+
+        let mut artifacts = vec![artifact("fortran.keyword"), artifact("fortran.label")];
+        store.save_artifacts(&artifacts).unwrap();
+        let loaded = store.load_artifacts().unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1].id, artifacts[1].id);
+        assert_eq!(
+            loaded[0].metadata.revisions,
+            artifacts[0].metadata.revisions
+        );
+
+        // Update one artifact in place and add another at the end
+        artifacts[0].content_text = Some("UPDATED".to_string());
+        store.save_artifact(&artifacts[0]).unwrap();
+        let added = artifact("fortran.label");
+        store.save_artifact(&added).unwrap();
+        let loaded = store.load_artifacts().unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[0].content_text.as_deref(), Some("UPDATED"));
+        assert_eq!(loaded[2].id, added.id);
+
+        // Kind and confidence filters
+        let mut low = artifact("fortran.keyword");
+        low.layout_label = ArtifactKind::CardText;
+        low.metadata.confidence = 0.1;
+        store.save_artifact(&low).unwrap();
+        let found = |filter: ArtifactFilter| -> Vec<PageId> {
+            let found = store.find_artifacts(&filter).unwrap();
+            found.iter().map(|a| a.id).collect()
+        };
+        let filter = ArtifactFilter {
+            kind: Some(ArtifactKind::CardText),
+            ..ArtifactFilter::default()
+        };
+        assert_eq!(found(filter), vec![low.id]);
+        let filter = ArtifactFilter {
+            min_confidence: Some(0.1),
+            ..ArtifactFilter::default()
+        };
+        assert_eq!(found(filter), vec![low.id]);
+        let filter = ArtifactFilter {
+            kind: Some(ArtifactKind::ListingSource),
+            max_confidence: Some(0.05),
+            ..ArtifactFilter::default()
+        };
+        assert_eq!(
+            found(filter),
+            vec![artifacts[0].id, artifacts[1].id, added.id]
+        );
+        assert_eq!(
+            store
+                .find_artifacts(&ArtifactFilter::default())
+                .unwrap()
+                .len(),
+            4
+        );
+        let loaded: Vec<PageArtifact> = store
+            .load_artifacts()
+            .unwrap()
+            .into_iter()
+            .filter(|a| a.id != low.id)
+            .collect();
+
+        let issues = store.issues_by_rule("fortran.label").unwrap();
+        let ids: Vec<PageId> = issues.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, vec![artifacts[1].id, added.id]);
+        assert_eq!(issues[0].1.column, Some(7));
+
+        // Only updates are checkpoints, and a full save ends them
+        assert_eq!(
+            store.checkpointed().unwrap(),
+            HashSet::from([artifacts[0].id])
+        );
+        store.save_artifacts(&loaded).unwrap();
+        assert!(store.checkpointed().unwrap().is_empty());
+        assert_eq!(
+            store.load_artifacts().unwrap()[0].content_text.as_deref(),
+            Some("UPDATED")
+        );
+
+        // A run records its settings; beginning another keeps the
+        // artifacts the first one saved but not its checkpoints
+        let settings = serde_json::json!({"autofix_threshold": 0.9});
+        assert_eq!(store.run_settings().unwrap(), None);
+        store.begin_run(&settings).unwrap();
+        artifacts[1].content_text = Some("RUN".to_string());
+        store.save_artifact(&artifacts[1]).unwrap();
+        assert_eq!(store.run_settings().unwrap(), Some(settings));
+        assert_eq!(
+            store.checkpointed().unwrap(),
+            HashSet::from([artifacts[1].id])
+        );
+        store.begin_run(&serde_json::Value::Null).unwrap();
+        assert!(store.checkpointed().unwrap().is_empty());
+        assert_eq!(
+            store.load_artifacts().unwrap()[1].content_text.as_deref(),
+            Some("RUN")
+        );
+        store
+            .save_artifacts(&store.load_artifacts().unwrap())
+            .unwrap();
+        assert_eq!(store.run_settings().unwrap(), None);
+    }
+
+    #[test]
+    fn test_detect_and_parse_backend() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(StorageBackend::detect(dir.path()), StorageBackend::Json);
+        std::fs::write(dir.path().join(SQLITE_FILE), b"").unwrap();
+        assert_eq!(StorageBackend::detect(dir.path()), StorageBackend::Sqlite);
+        assert_eq!(
+            StorageBackend::parse("SQLite"),
+            Some(StorageBackend::Sqlite)
+        );
+        assert_eq!(StorageBackend::parse("xml"), None);
+    }
+}
@@ -0,0 +1,536 @@
+//! SQLite storage backend
+//!
+//! Artifacts are stored as JSON rows, without their revisions and
+//! validation issues, which get tables of their own:
+//!
+//! ```text
+//! manifest          (id = 1, json)
+//! artifacts         (id, position, layout_label, content_hash, confidence, json)
+//! revisions         (artifact_id, seq, source, line_number, ...)
+//! validation_issues (artifact_id, seq, rule, severity, line_number, ...)
+//! checkpoints       (artifact_id)  -- updated since the last full save
+//! run               (id = 1, settings)  -- the run those updates belong to
+//! ```
+//!
+//! Kind and confidence have indexed columns, so filtered loads
+//! ([`ScanSetStore::find_artifacts`]) only read the matching rows.
+//!
+//! The schema version is kept in SQLite's `user_version`; databases from a
+//! newer version are refused rather than misread, older ones are migrated.
+
+use super::{ArtifactFilter, ScanSetStore, StorageBackend, SQLITE_FILE};
+use crate::error::{Error, ParseContext, Result};
+use crate::types::{PageArtifact, PageId, ScanSetManifest, TextRevision};
+use crate::validate::{Severity, ValidationIssue};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, ToSql, Transaction};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Version of [`SCHEMA`], stored as the database's `user_version`
+const SCHEMA_VERSION: u32 = 4;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS manifest (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    json TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS artifacts (
+    id TEXT PRIMARY KEY,
+    position INTEGER NOT NULL,
+    layout_label TEXT NOT NULL,
+    content_hash TEXT NOT NULL,
+    confidence REAL NOT NULL DEFAULT 0,
+    json TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS artifacts_position ON artifacts (position);
+CREATE INDEX IF NOT EXISTS artifacts_content_hash ON artifacts (content_hash);
+CREATE INDEX IF NOT EXISTS artifacts_kind ON artifacts (layout_label, confidence);
+CREATE INDEX IF NOT EXISTS artifacts_confidence ON artifacts (confidence);
+CREATE TABLE IF NOT EXISTS revisions (
+    artifact_id TEXT NOT NULL REFERENCES artifacts (id) ON DELETE CASCADE,
+    seq INTEGER NOT NULL,
+    source TEXT NOT NULL,
+    line_number INTEGER NOT NULL,
+    col INTEGER NOT NULL,
+    before TEXT NOT NULL,
+    after TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    PRIMARY KEY (artifact_id, seq)
+);
+CREATE INDEX IF NOT EXISTS revisions_source ON revisions (source);
+CREATE TABLE IF NOT EXISTS validation_issues (
+    artifact_id TEXT NOT NULL REFERENCES artifacts (id) ON DELETE CASCADE,
+    seq INTEGER NOT NULL,
+    rule TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    line_number INTEGER NOT NULL,
+    col INTEGER,
+    description TEXT NOT NULL,
+    excerpt TEXT NOT NULL,
+    suggestion TEXT,
+    PRIMARY KEY (artifact_id, seq)
+);
+CREATE INDEX IF NOT EXISTS validation_issues_rule ON validation_issues (rule);
+CREATE TABLE IF NOT EXISTS checkpoints (
+    artifact_id TEXT PRIMARY KEY REFERENCES artifacts (id) ON DELETE CASCADE
+);
+CREATE TABLE IF NOT EXISTS run (
+    id INTEGER PRIMARY KEY CHECK (id = 1),
+    settings TEXT NOT NULL
+);
+";
+
+/// Scan set stored in a single SQLite database
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Connection,
+}
+
+impl SqliteStore {
+    /// Open the database of a scan set directory, creating it if needed
+    pub fn open(scan_set_dir: &Path) -> Result<Self> {
+        let path = scan_set_dir.join(SQLITE_FILE);
+        let conn = Connection::open(&path)
+            .db_context(|| format!("Failed to open database: {}", path.display()))?;
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+
+        let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            return Err(Error::SchemaVersion {
+                path,
+                found: version,
+                supported: SCHEMA_VERSION,
+            });
+        }
+        if (1..4).contains(&version) {
+            add_confidence_column(&conn)?;
+        }
+        conn.execute_batch(SCHEMA)
+            .db_context(|| "Failed to create scan set schema".to_string())?;
+        conn.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))?;
+        Ok(Self { conn })
+    }
+}
+
+impl ScanSetStore for SqliteStore {
+    fn backend(&self) -> StorageBackend {
+        StorageBackend::Sqlite
+    }
+
+    fn load_manifest(&self) -> Result<ScanSetManifest> {
+        let json: String = self
+            .conn
+            .query_row("SELECT json FROM manifest WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()?
+            .ok_or_else(|| Error::invalid("Scan set database has no manifest"))?;
+        serde_json::from_str(&json).parse_context(|| "Failed to parse manifest".to_string())
+    }
+
+    fn save_manifest(&mut self, manifest: &ScanSetManifest) -> Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO manifest (id, json) VALUES (1, ?1)",
+            [serde_json::to_string(manifest)?],
+        )?;
+        Ok(())
+    }
+
+    fn load_artifacts(&self) -> Result<Vec<PageArtifact>> {
+        self.find_artifacts(&ArtifactFilter::default())
+    }
+
+    fn find_artifacts(&self, filter: &ArtifactFilter) -> Result<Vec<PageArtifact>> {
+        let mut conditions = vec!["1"];
+        let mut values: Vec<Box<dyn ToSql>> = Vec::new();
+        if let Some(kind) = filter.kind {
+            conditions.push("layout_label = ?");
+            values.push(Box::new(serde_json::to_string(&kind)?));
+        }
+        if let Some(min) = filter.min_confidence {
+            conditions.push("confidence >= ?");
+            values.push(Box::new(f64::from(min)));
+        }
+        if let Some(max) = filter.max_confidence {
+            conditions.push("confidence <= ?");
+            values.push(Box::new(f64::from(max)));
+        }
+
+        let mut revisions = load_revisions(&self.conn)?;
+        let mut issues = load_issues(&self.conn, None)?.into_iter().fold(
+            HashMap::<String, Vec<_>>::new(),
+            |mut map, (id, issue)| {
+                map.entry(id).or_default().push(issue);
+                map
+            },
+        );
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, json FROM artifacts WHERE {} ORDER BY position",
+            conditions.join(" AND ")
+        ))?;
+        let rows = stmt.query_map(params_from_iter(values), |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut artifacts = Vec::new();
+        for row in rows {
+            let (id, json) = row?;
+            let mut artifact: PageArtifact = serde_json::from_str(&json)
+                .parse_context(|| format!("Failed to parse artifact {}", id))?;
+            artifact.metadata.revisions = revisions.remove(&id).unwrap_or_default();
+            artifact.metadata.validation_issues = issues.remove(&id).unwrap_or_default();
+            artifacts.push(artifact);
+        }
+        Ok(artifacts)
+    }
+
+    fn save_artifacts(&mut self, artifacts: &[PageArtifact]) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM artifacts", [])?;
+        tx.execute("DELETE FROM checkpoints", [])?;
+        tx.execute("DELETE FROM run", [])?;
+        for (position, artifact) in artifacts.iter().enumerate() {
+            write_artifact(&tx, artifact, position as i64)?;
+        }
+        tx.commit()
+            .db_context(|| "Failed to save artifacts".to_string())
+    }
+
+    fn save_artifact(&mut self, artifact: &PageArtifact) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        let id = artifact.id.0.to_string();
+        let existing: Option<i64> = tx
+            .query_row(
+                "SELECT position FROM artifacts WHERE id = ?1",
+                [&id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let position = match existing {
+            Some(position) => position,
+            None => tx.query_row(
+                "SELECT COALESCE(MAX(position) + 1, 0) FROM artifacts",
+                [],
+                |row| row.get(0),
+            )?,
+        };
+        tx.execute("DELETE FROM artifacts WHERE id = ?1", [&id])?;
+        write_artifact(&tx, artifact, position)?;
+        if existing.is_some() {
+            tx.execute("INSERT INTO checkpoints (artifact_id) VALUES (?1)", [&id])?;
+        }
+        tx.commit()
+            .db_context(|| "Failed to save artifact".to_string())
+    }
+
+    fn checkpointed(&self) -> Result<HashSet<PageId>> {
+        let mut stmt = self.conn.prepare("SELECT artifact_id FROM checkpoints")?;
+        let ids = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        let mut checkpointed = HashSet::new();
+        for id in ids {
+            checkpointed.insert(parse_id(&id?)?);
+        }
+        Ok(checkpointed)
+    }
+
+    fn begin_run(&mut self, settings: &serde_json::Value) -> Result<()> {
+        let tx = self.conn.transaction()?;
+        tx.execute("DELETE FROM checkpoints", [])?;
+        tx.execute(
+            "INSERT OR REPLACE INTO run (id, settings) VALUES (1, ?1)",
+            [serde_json::to_string(settings)?],
+        )?;
+        tx.commit().db_context(|| "Failed to start run".to_string())
+    }
+
+    fn run_settings(&self) -> Result<Option<serde_json::Value>> {
+        let settings: Option<String> = self
+            .conn
+            .query_row("SELECT settings FROM run WHERE id = 1", [], |row| {
+                row.get(0)
+            })
+            .optional()?;
+        settings
+            .map(|json| {
+                serde_json::from_str(&json)
+                    .parse_context(|| "Failed to parse run settings".to_string())
+            })
+            .transpose()
+    }
+
+    fn issues_by_rule(&self, rule: &str) -> Result<Vec<(PageId, ValidationIssue)>> {
+        load_issues(&self.conn, Some(rule))?
+            .into_iter()
+            .map(|(id, issue)| Ok((parse_id(&id)?, issue)))
+            .collect()
+    }
+}
+
+fn parse_id(id: &str) -> Result<PageId> {
+    let uuid = id
+        .parse()
+        .map_err(|_| Error::invalid(format!("Invalid artifact id in database: {}", id)))?;
+    Ok(PageId(uuid))
+}
+
+/// Add the `confidence` column of schema version 4 to an older database,
+/// filled in from the artifacts' JSON
+fn add_confidence_column(conn: &Connection) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    tx.execute(
+        "ALTER TABLE artifacts ADD COLUMN confidence REAL NOT NULL DEFAULT 0",
+        [],
+    )?;
+    let rows: Vec<(String, String)> = tx
+        .prepare("SELECT id, json FROM artifacts")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+    for (id, json) in rows {
+        let artifact: PageArtifact = serde_json::from_str(&json)
+            .parse_context(|| format!("Failed to parse artifact {}", id))?;
+        tx.execute(
+            "UPDATE artifacts SET confidence = ?1 WHERE id = ?2",
+            params![f64::from(artifact.metadata.confidence), id],
+        )?;
+    }
+    tx.commit()
+        .db_context(|| "Failed to add the confidence column".to_string())
+}
+
+/// Insert one artifact row and its revision and issue rows
+fn write_artifact(tx: &Transaction, artifact: &PageArtifact, position: i64) -> Result<()> {
+    let id = artifact.id.0.to_string();
+    let mut stored = artifact.clone();
+    let revisions = std::mem::take(&mut stored.metadata.revisions);
+    let issues = std::mem::take(&mut stored.metadata.validation_issues);
+
+    tx.execute(
+        "INSERT INTO artifacts (id, position, layout_label, content_hash, confidence, json)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            id,
+            position,
+            serde_json::to_string(&stored.layout_label)?,
+            stored.metadata.content_hash,
+            f64::from(stored.metadata.confidence),
+            serde_json::to_string(&stored)?,
+        ],
+    )?;
+    for (seq, r) in revisions.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO revisions
+             (artifact_id, seq, source, line_number, col, before, after, reason, confidence)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                id,
+                seq as i64,
+                r.source,
+                r.line_number as i64,
+                r.column as i64,
+                r.before,
+                r.after,
+                r.reason,
+                r.confidence,
+            ],
+        )?;
+    }
+    for (seq, issue) in issues.iter().enumerate() {
+        tx.execute(
+            "INSERT INTO validation_issues
+             (artifact_id, seq, rule, severity, line_number, col, description, excerpt, suggestion)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                id,
+                seq as i64,
+                issue.rule,
+                severity_name(issue.severity),
+                issue.line_number as i64,
+                issue.column.map(|c| c as i64),
+                issue.description,
+                issue.excerpt,
+                issue.suggestion,
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Revisions of every artifact, keyed by artifact id
+fn load_revisions(conn: &Connection) -> Result<HashMap<String, Vec<TextRevision>>> {
+    let mut stmt = conn.prepare(
+        "SELECT artifact_id, source, line_number, col, before, after, reason, confidence
+         FROM revisions ORDER BY artifact_id, seq",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            TextRevision {
+                source: row.get(1)?,
+                line_number: row.get::<_, i64>(2)? as usize,
+                column: row.get::<_, i64>(3)? as usize,
+                before: row.get(4)?,
+                after: row.get(5)?,
+                reason: row.get(6)?,
+                confidence: row.get(7)?,
+            },
+        ))
+    })?;
+    let mut revisions: HashMap<String, Vec<TextRevision>> = HashMap::new();
+    for row in rows {
+        let (id, revision) = row?;
+        revisions.entry(id).or_default().push(revision);
+    }
+    Ok(revisions)
+}
+
+/// Validation issues (of one rule, or all), in scan set order
+fn load_issues(conn: &Connection, rule: Option<&str>) -> Result<Vec<(String, ValidationIssue)>> {
+    let mut stmt = conn.prepare(
+        "SELECT v.artifact_id, v.rule, v.severity, v.line_number, v.col,
+                v.description, v.excerpt, v.suggestion
+         FROM validation_issues v JOIN artifacts a ON a.id = v.artifact_id
+         WHERE ?1 IS NULL OR v.rule = ?1
+         ORDER BY a.position, v.seq",
+    )?;
+    let rows = stmt.query_map([rule], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, Option<i64>>(4)?,
+            row.get::<_, String>(5)?,
+            row.get::<_, String>(6)?,
+            row.get::<_, Option<String>>(7)?,
+        ))
+    })?;
+    let mut issues = Vec::new();
+    for row in rows {
+        let (id, rule, severity, line_number, column, description, excerpt, suggestion) = row?;
+        issues.push((
+            id,
+            ValidationIssue {
+                rule,
+                line_number: line_number as usize,
+                column: column.map(|c| c as usize),
+                severity: parse_severity(&severity)?,
+                description,
+                excerpt,
+                suggestion,
+            },
+        ));
+    }
+    Ok(issues)
+}
+
+fn severity_name(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Warning => "warning",
+        Severity::Error => "error",
+    }
+}
+
+fn parse_severity(name: &str) -> Result<Severity> {
+    match name {
+        "warning" => Ok(Severity::Warning),
+        "error" => Ok(Severity::Error),
+        other => Err(Error::invalid(format!(
+            "Unknown severity in database: {}",
+            other
+        ))),
+    }
+}
+
+/// Adds a message saying what failed to database errors
+trait DbContext<T> {
+    fn db_context(self, message: impl FnOnce() -> String) -> Result<T>;
+}
+
+impl<T> DbContext<T> for rusqlite::Result<T> {
+    fn db_context(self, message: impl FnOnce() -> String) -> Result<T> {
+        self.map_err(|source| Error::Database {
+            message: message(),
+            source,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{convert, open, JsonStore};
+
+    #[test]
+    fn test_sqlite_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = SqliteStore::open(dir.path()).unwrap();
+        super::super::tests::exercise_store(&mut store);
+    }
+
+    #[test]
+    fn test_refuses_newer_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        drop(SqliteStore::open(dir.path()).unwrap());
+        let conn = Connection::open(dir.path().join(SQLITE_FILE)).unwrap();
+        conn.execute_batch("PRAGMA user_version = 99").unwrap();
+        drop(conn);
+
+        assert!(matches!(
+            SqliteStore::open(dir.path()),
+            Err(Error::SchemaVersion {
+                found: 99,
+                supported: SCHEMA_VERSION,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_migrates_confidence_column() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut artifact = super::super::tests::artifact("fortran.keyword");
+        artifact.metadata.confidence = 0.75;
+        let mut store = SqliteStore::open(dir.path()).unwrap();
+        store
+            .save_artifacts(std::slice::from_ref(&artifact))
+            .unwrap();
+        // Back to version 3, which had no confidence column
+        store
+            .conn
+            .execute_batch(
+                "DROP INDEX artifacts_kind; DROP INDEX artifacts_confidence;
+                 ALTER TABLE artifacts DROP COLUMN confidence; PRAGMA user_version = 3",
+            )
+            .unwrap();
+        drop(store);
+
+        let store = SqliteStore::open(dir.path()).unwrap();
+        let filter = ArtifactFilter {
+            min_confidence: Some(0.75),
+            ..ArtifactFilter::default()
+        };
+        assert_eq!(store.find_artifacts(&filter).unwrap()[0].id, artifact.id);
+    }
+
+    #[test]
+    fn test_convert_from_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut json = JsonStore::new(dir.path());
+        json.save_manifest(&super::super::tests::manifest())
+            .unwrap();
+        let artifact = super::super::tests::artifact("fortran.keyword");
+        json.save_artifacts(std::slice::from_ref(&artifact))
+            .unwrap();
+
+        convert(dir.path(), StorageBackend::Sqlite).unwrap();
+        let store = open(dir.path()).unwrap();
+        assert_eq!(store.backend(), StorageBackend::Sqlite);
+        let loaded = store.load_artifacts().unwrap();
+        assert_eq!(loaded[0].id, artifact.id);
+        assert_eq!(
+            loaded[0].metadata.validation_issues,
+            artifact.metadata.validation_issues
+        );
+    }
+}
@@ -3,6 +3,10 @@
 //! This module defines the Canonical Intermediate Representation (CIR)
 //! used throughout the processing pipeline.
 
+use crate::derived::DerivedImageRef;
+use crate::keypunch::KeypunchSettings;
+use crate::preprocess::PreprocessOptions;
+use crate::validate::ValidationIssue;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use uuid::Uuid;
@@ -38,6 +42,53 @@ pub struct ScanSetManifest {
     pub original_file_count: usize,
     /// Number of duplicate images detected
     pub duplicate_count: usize,
+    /// Keypunch models the cards were punched on
+    #[serde(default)]
+    pub keypunch: KeypunchSettings,
+    /// Pairs of artifacts that look alike but are not identical (e.g. the
+    /// same page rescanned with a different crop), kept for review
+    #[serde(default)]
+    pub near_duplicates: Vec<NearDuplicate>,
+    /// Paid service calls made for the artifacts (e.g. Gemini cleaning)
+    #[serde(default)]
+    pub spending: Vec<Spend>,
+    /// Preprocessing steps of the last analysis, under which its images
+    /// and OCR results are stored
+    #[serde(default)]
+    pub preprocess: PreprocessOptions,
+}
+
+impl ScanSetManifest {
+    /// Money spent on paid services for the scan set (USD)
+    pub fn total_cost_usd(&self) -> f64 {
+        self.spending
+            .iter()
+            .fold(0.0, |total, spend| total + spend.cost_usd)
+    }
+}
+
+/// Money spent on a paid service for one artifact
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Spend {
+    /// Artifact the service was used for
+    pub artifact: PageId,
+    /// Service and model (e.g. "gemini:gemini-2.5-flash-image")
+    pub service: String,
+    /// Cost of the call (USD)
+    pub cost_usd: f64,
+    /// When the call was made (ISO 8601)
+    pub at: String,
+}
+
+/// Two artifacts whose images look alike
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NearDuplicate {
+    /// Artifact ingested first
+    pub first: PageId,
+    /// Artifact ingested later
+    pub second: PageId,
+    /// Bits their perceptual hashes differ in
+    pub distance: u32,
 }
 
 /// Unique identifier for a page artifact
@@ -91,11 +142,35 @@ pub enum ArtifactKind {
     Unknown,
 }
 
+impl ArtifactKind {
+    /// Every kind
+    pub const ALL: [Self; 7] = [
+        Self::CardText,
+        Self::CardObject,
+        Self::CardData,
+        Self::ListingSource,
+        Self::ListingObject,
+        Self::RuntimeOutput,
+        Self::Unknown,
+    ];
+
+    /// Parse a kind name as it is serialized (`CardText`), ignoring case
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| format!("{:?}", kind).eq_ignore_ascii_case(name))
+    }
+}
+
 /// Metadata for a page artifact
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PageMetadata {
-    /// SHA-256 hash of the image content (for duplicate detection)
+    /// SHA-256 hash of the decoded pixels (for duplicate detection)
     pub content_hash: String,
+    /// SHA-256 hash of the encoded bytes of the file the image was read
+    /// from (for duplicate detection without decoding)
+    #[serde(default)]
+    pub file_hash: Option<String>,
     /// All original filenames that map to this image (duplicate detection)
     pub original_filenames: Vec<String>,
     /// Detected page number (if present in header/footer)
@@ -108,22 +183,115 @@ pub struct PageMetadata {
     pub notes: Vec<String>,
     /// Confidence score for classification (0.0-1.0)
     pub confidence: f32,
+    /// Automatic or manual changes applied to the content text
+    #[serde(default)]
+    pub revisions: Vec<TextRevision>,
+    /// Issues found by validating the content text
+    #[serde(default)]
+    pub validation_issues: Vec<ValidationIssue>,
+    /// Images derived from the scan (preprocessed, thumbnails, ...)
+    #[serde(default)]
+    pub derived_images: Vec<DerivedImageRef>,
+    /// Money spent on paid services for this page (USD)
+    #[serde(default)]
+    pub cost_usd: f64,
+    /// PDF pages the image was rasterized from (empty for image files)
+    #[serde(default)]
+    pub source_pages: Vec<SourcePage>,
+    /// Perceptual hash of the image, for near-duplicate detection
+    #[serde(default)]
+    pub perceptual_hash: Option<String>,
+    /// Position of the artifact in ingest order (sorted by file name),
+    /// the same on every ingest of the same files
+    #[serde(default)]
+    pub ingest_index: usize,
+    /// Untouched source files kept in the scan set, relative to it (empty
+    /// unless ingested with `--copy-originals`)
+    #[serde(default)]
+    pub originals: Vec<PathBuf>,
+    /// Position of the page in reading order (set by `reorder`)
+    #[serde(default)]
+    pub reading_order: Option<usize>,
+    /// Content text was typed in by a person (set by `import-text`);
+    /// analysis keeps it instead of running OCR
+    #[serde(default)]
+    pub human_transcribed: bool,
+    /// Image cleaned of greenbar and background by Gemini (set by
+    /// `clean`), relative to the scan set
+    #[serde(default)]
+    pub cleaned_image_path: Option<PathBuf>,
+    /// Small preview of the raw image (set by `thumbnails`), relative to
+    /// the scan set
+    #[serde(default)]
+    pub thumbnail_path: Option<PathBuf>,
+    /// Classification was set by a person (set by `relabel`); analysis
+    /// keeps it instead of the heuristic classification
+    #[serde(default)]
+    pub manual_classification: bool,
+    /// Confidence before validation issues lowered it (set by `analyze`,
+    /// which lowers this rather than `confidence`, so re-runs do not
+    /// compound); `None` when `confidence` was not lowered
+    #[serde(default)]
+    pub base_confidence: Option<f32>,
 }
 
 impl Default for PageMetadata {
     fn default() -> Self {
         Self {
             content_hash: String::new(),
+            file_hash: None,
             original_filenames: Vec::new(),
             page_number: None,
             header: None,
             footer: None,
             notes: Vec::new(),
             confidence: 0.0,
+            revisions: Vec::new(),
+            validation_issues: Vec::new(),
+            derived_images: Vec::new(),
+            cost_usd: 0.0,
+            source_pages: Vec::new(),
+            perceptual_hash: None,
+            ingest_index: 0,
+            originals: Vec::new(),
+            reading_order: None,
+            human_transcribed: false,
+            cleaned_image_path: None,
+            thumbnail_path: None,
+            manual_classification: false,
+            base_confidence: None,
         }
     }
 }
 
+/// A page of an ingested PDF
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourcePage {
+    /// Path of the PDF as given to ingest
+    pub pdf: String,
+    /// Page index, starting at 1
+    pub page: u32,
+}
+
+/// A single recorded change to an artifact's content text
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextRevision {
+    /// What made the change (e.g., "autofix", "manual")
+    pub source: String,
+    /// Line number of the change (1-based)
+    pub line_number: usize,
+    /// Column where the replaced text starts (1-based)
+    pub column: usize,
+    /// Text before the change
+    pub before: String,
+    /// Text after the change
+    pub after: String,
+    /// Why the change was made
+    pub reason: String,
+    /// Confidence in the change (0.0-1.0)
+    pub confidence: f32,
+}
+
 /// Metadata for a card artifact
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CardMetadata {
@@ -213,6 +381,9 @@ pub enum HighLevelArtifact {
 /// A reconstructed source listing
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SourceListing {
+    /// Document name (from the running header, if detected)
+    #[serde(default)]
+    pub name: Option<String>,
     /// Type of source (assembler, FORTRAN, Forth, etc.)
     pub language: String,
     /// Original page artifacts
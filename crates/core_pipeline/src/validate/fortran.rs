@@ -0,0 +1,637 @@
+//! IBM 1130 FORTRAN fixed-format validation
+//!
+//! Card layout enforced by these rules:
+//! - Column 1: `C` marks a comment line, `*` a compiler control record
+//! - Columns 1-5: Statement number (digits only)
+//! - Column 6: Continuation mark (any character other than blank or zero)
+//! - Columns 7-72: Statement text
+//! - Columns 73-80: Identification/sequence field (ignored by the compiler)
+
+use super::{columns, Severity, ValidationIssue};
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+
+/// Statement keywords accepted by 1130 FORTRAN (blanks removed)
+pub(crate) const KEYWORDS: &[&str] = &[
+    "CALL",
+    "COMMON",
+    "CONTINUE",
+    "DATA",
+    "DEFINEFILE",
+    "DIMENSION",
+    "DO",
+    "END",
+    "EQUIVALENCE",
+    "EXTERNAL",
+    "FIND",
+    "FORMAT",
+    "FUNCTION",
+    "GOTO",
+    "IF",
+    "INTEGER",
+    "PAUSE",
+    "READ",
+    "REAL",
+    "RETURN",
+    "STOP",
+    "SUBROUTINE",
+    "WRITE",
+];
+
+/// Tunable parameters for the FORTRAN card layout rules
+///
+/// Column ranges are 1-based and inclusive. The defaults describe a
+/// standard 1130 FORTRAN source card.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FortranRules {
+    /// Statement number field
+    pub label_columns: (usize, usize),
+    /// Continuation mark column
+    pub continuation_column: usize,
+    /// Statement text field
+    pub statement_columns: (usize, usize),
+    /// Identification/sequence field
+    pub ident_columns: (usize, usize),
+    /// Maximum line length
+    pub max_line_length: usize,
+    /// Characters allowed in the statement field (no check if unset)
+    pub allowed_charset: Option<String>,
+    /// Expected increment between sequence numbers (no check if unset)
+    pub sequence_increment: Option<u64>,
+}
+
+impl Default for FortranRules {
+    fn default() -> Self {
+        Self {
+            label_columns: (1, 5),
+            continuation_column: 6,
+            statement_columns: (7, 72),
+            ident_columns: (73, 80),
+            max_line_length: 80,
+            allowed_charset: None,
+            sequence_increment: None,
+        }
+    }
+}
+
+impl FortranRules {
+    /// Check that every column lies on an 80-column card and every range
+    /// runs forward
+    pub fn check(&self) -> Result<()> {
+        let ranges = [
+            ("label_columns", self.label_columns),
+            (
+                "continuation_column",
+                (self.continuation_column, self.continuation_column),
+            ),
+            ("statement_columns", self.statement_columns),
+            ("ident_columns", self.ident_columns),
+        ];
+        for (name, (first, last)) in ranges {
+            if !(1 <= first && first <= last && last <= 80) {
+                return Err(Error::invalid(format!(
+                    "Invalid {name}: columns must satisfy 1 <= first <= last <= 80, got {first}-{last}"
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validate FORTRAN source text against the standard card format
+///
+/// Comment lines, compiler control records (`*LIST ALL`) and monitor
+/// control records (`// FOR`) are skipped. Blank lines are ignored.
+pub fn validate_fortran(text: &str) -> Vec<ValidationIssue> {
+    validate_fortran_with(text, &FortranRules::default())
+}
+
+/// Validate FORTRAN source text using custom rule parameters
+pub fn validate_fortran_with(text: &str, rules: &FortranRules) -> Vec<ValidationIssue> {
+    validate_lines(text, rules, true)
+}
+
+/// Check only the layout every card deck shares, whatever its language:
+/// line length and, if an increment is set, the sequence numbers of the
+/// identification field
+pub fn check_card_layout(text: &str, rules: &FortranRules) -> Vec<ValidationIssue> {
+    validate_lines(text, rules, false)
+}
+
+/// Check each line, with the FORTRAN statement rules if `fortran` is set
+fn validate_lines(text: &str, rules: &FortranRules, fortran: bool) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    let mut seen_statement = false;
+    let mut last_sequence: Option<(String, u64)> = None;
+    let (label_first, label_last) = rules.label_columns;
+    let (statement_first, statement_last) = rules.statement_columns;
+    let (ident_first, ident_last) = rules.ident_columns;
+
+    for (idx, raw_line) in text.lines().enumerate() {
+        let line = raw_line.trim_end_matches('\r');
+        let line_number = idx + 1;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let chars: Vec<char> = line.chars().collect();
+        let issue =
+            |rule: &str, column, severity, description: String, suggestion| ValidationIssue {
+                rule: rule.to_string(),
+                line_number,
+                column,
+                severity,
+                description,
+                excerpt: line.to_string(),
+                suggestion,
+            };
+
+        // Sequence numbers run through comment and control cards too
+        let ident = columns(&chars, ident_first, ident_last);
+        let sequence = rules
+            .sequence_increment
+            .and_then(|increment| Some((increment, sequence_number(&ident)?)));
+        if let Some((increment, (prefix, number))) = sequence {
+            if let Some((last_prefix, last_number)) = &last_sequence {
+                let expected = last_number + increment;
+                if *last_prefix == prefix && number != expected {
+                    issues.push(issue(
+                        "fortran.sequence",
+                        Some(ident_first),
+                        Severity::Warning,
+                        format!(
+                            "Sequence number {number} does not follow {last_number} \
+                             (expected {expected})"
+                        ),
+                        Some(
+                            "Check for a missing, duplicated or out-of-order card, or an \
+                             OCR misread in the sequence field"
+                                .to_string(),
+                        ),
+                    ));
+                }
+            }
+            last_sequence = Some((prefix, number));
+        }
+
+        let too_long = chars.len() > rules.max_line_length;
+        let line_length = || {
+            issue(
+                "fortran.line-length",
+                Some(rules.max_line_length + 1),
+                Severity::Error,
+                format!(
+                    "Line is {} columns long (maximum {})",
+                    chars.len(),
+                    rules.max_line_length
+                ),
+                Some(format!(
+                    "Remove characters after column {}",
+                    rules.max_line_length
+                )),
+            )
+        };
+        if !fortran {
+            if too_long {
+                issues.push(line_length());
+            }
+            continue;
+        }
+
+        if is_non_statement(line) {
+            continue;
+        }
+
+        let label = columns(&chars, label_first, label_last);
+        let continuation = rules
+            .continuation_column
+            .checked_sub(1)
+            .and_then(|idx| chars.get(idx))
+            .copied()
+            .unwrap_or(' ');
+        let is_continuation = continuation != ' ' && continuation != '0';
+
+        // Statement number field: digits or blanks only
+        if let Some(pos) = label.chars().position(|c| c != ' ' && !c.is_ascii_digit()) {
+            let suggestion = match digit_correction(label.trim()) {
+                Some(fixed) => {
+                    format!("Statement number may be {fixed} (OCR letter/digit confusion)")
+                }
+                None => format!("Statement text must start in column {statement_first}"),
+            };
+            issues.push(issue(
+                "fortran.statement-number",
+                Some(label_first + pos),
+                Severity::Error,
+                format!(
+                    "Non-numeric statement number '{}' in columns {}-{}",
+                    label.trim(),
+                    label_first,
+                    label_last
+                ),
+                Some(suggestion),
+            ));
+        }
+
+        // Continuation mark: only valid on a line that continues a statement
+        if is_continuation {
+            if !label.trim().is_empty() {
+                issues.push(issue(
+                    "fortran.continuation",
+                    Some(rules.continuation_column),
+                    Severity::Error,
+                    "Continuation line carries a statement number".to_string(),
+                    Some(format!(
+                        "Remove the statement number or blank column {}",
+                        rules.continuation_column
+                    )),
+                ));
+            }
+            if !seen_statement {
+                issues.push(issue(
+                    "fortran.continuation",
+                    Some(rules.continuation_column),
+                    Severity::Warning,
+                    "Continuation mark on the first statement".to_string(),
+                    Some(format!(
+                        "Column {} should be blank on an initial line",
+                        rules.continuation_column
+                    )),
+                ));
+            }
+        }
+
+        // Statement field: must begin with a keyword or be an assignment
+        let statement = columns(&chars, statement_first, statement_last);
+        if !is_continuation && !statement.trim().is_empty() {
+            check_statement(&statement, |description, suggestion| {
+                issues.push(issue(
+                    "fortran.keyword",
+                    Some(statement_first + statement.len() - statement.trim_start().len()),
+                    Severity::Error,
+                    description,
+                    suggestion,
+                ))
+            });
+        }
+        seen_statement = true;
+
+        // Statement field: restricted character set, if configured
+        if let Some(charset) = &rules.allowed_charset {
+            if let Some((pos, c)) = statement
+                .chars()
+                .enumerate()
+                .find(|(_, c)| !charset.contains(*c))
+            {
+                issues.push(issue(
+                    "fortran.charset",
+                    Some(statement_first + pos),
+                    Severity::Warning,
+                    format!("Character '{c}' is not in the allowed character set"),
+                    Some("Check for an OCR misread or stray mark".to_string()),
+                ));
+            }
+        }
+
+        // Identification field: alphanumeric sequence data only
+        if let Some(pos) = ident
+            .chars()
+            .position(|c| c != ' ' && !c.is_ascii_alphanumeric())
+        {
+            issues.push(issue(
+                "fortran.identification-field",
+                Some(ident_first + pos),
+                Severity::Warning,
+                format!(
+                    "Unexpected characters '{}' in columns {}-{}",
+                    ident.trim(),
+                    ident_first,
+                    ident_last
+                ),
+                Some(format!(
+                    "Columns {ident_first}-{ident_last} are ignored by the compiler; remove \
+                     stray marks or check for statement text running past column \
+                     {statement_last}"
+                )),
+            ));
+        }
+
+        if too_long {
+            issues.push(line_length());
+        }
+    }
+
+    issues
+}
+
+/// Split an identification field into its prefix and trailing sequence number
+pub(crate) fn sequence_number(ident: &str) -> Option<(String, u64)> {
+    let ident = ident.trim();
+    // Byte offset after the last non-digit, which may be multibyte (`¢`)
+    let digits_start = ident
+        .char_indices()
+        .rfind(|(_, c)| !c.is_ascii_digit())
+        .map_or(0, |(pos, c)| pos + c.len_utf8());
+    let number = ident[digits_start..].parse().ok()?;
+    Some((ident[..digits_start].to_string(), number))
+}
+
+/// Check whether a line is a comment or control record rather than a statement
+pub(crate) fn is_non_statement(line: &str) -> bool {
+    line.starts_with('C') || line.starts_with('*') || line.starts_with("//")
+}
+
+/// Check whether a line looks like a standard-layout FORTRAN statement
+pub(crate) fn is_statement(line: &str) -> bool {
+    if is_non_statement(line) {
+        return false;
+    }
+    let chars: Vec<char> = line.chars().collect();
+    let compact: String = columns(&chars, 7, 72)
+        .chars()
+        .filter(|c| *c != ' ')
+        .collect();
+    !compact.is_empty() && (starts_with_keyword(&compact) || is_assignment(&compact))
+}
+
+/// Check the statement field, reporting unrecognized statements
+fn check_statement(statement: &str, mut report: impl FnMut(String, Option<String>)) {
+    let compact: String = statement.chars().filter(|c| *c != ' ').collect();
+
+    if starts_with_keyword(&compact) || is_assignment(&compact) {
+        return;
+    }
+
+    let leading_word: String = compact
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect();
+    let letters: String = leading_word.chars().map(digit_to_letter).collect();
+    let suggestion = KEYWORDS
+        .iter()
+        .find(|kw| letters.starts_with(**kw))
+        .map(|kw| format!("Did you mean {kw}? (OCR letter/digit confusion)"));
+
+    report(
+        format!(
+            "Unrecognized statement '{}'",
+            statement.trim().chars().take(20).collect::<String>()
+        ),
+        suggestion,
+    );
+}
+
+/// Check whether a blank-free statement begins with a known keyword
+fn starts_with_keyword(compact: &str) -> bool {
+    KEYWORDS.iter().any(|kw| compact.starts_with(kw))
+}
+
+/// Check whether a blank-free statement is an assignment (`NAME = expr`)
+///
+/// The left-hand side must be a variable name, optionally subscripted.
+fn is_assignment(compact: &str) -> bool {
+    let mut depth = 0i32;
+    for (idx, c) in compact.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '=' if depth == 0 => {
+                let lhs = &compact[..idx];
+                let name_end = lhs.find('(').unwrap_or(lhs.len());
+                let name = &lhs[..name_end];
+                return name.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && name.chars().all(|c| c.is_ascii_alphanumeric());
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Map letters commonly misread for digits back to the digit
+fn letter_to_digit(c: char) -> Option<char> {
+    match c {
+        '0'..='9' => Some(c),
+        'O' | 'Q' => Some('0'),
+        'I' | 'L' | 'l' | '|' => Some('1'),
+        'Z' => Some('2'),
+        'S' => Some('5'),
+        'G' => Some('6'),
+        'B' => Some('8'),
+        _ => None,
+    }
+}
+
+/// Map digits commonly misread for letters back to the letter
+fn digit_to_letter(c: char) -> char {
+    match c {
+        '0' => 'O',
+        '1' => 'I',
+        '2' => 'Z',
+        '5' => 'S',
+        '6' => 'G',
+        '8' => 'B',
+        _ => c,
+    }
+}
+
+/// Try to read a statement number whose digits were OCR'd as letters
+fn digit_correction(label: &str) -> Option<String> {
+    if label.is_empty() {
+        return None;
+    }
+    label.chars().map(letter_to_digit).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules_of(issues: &[ValidationIssue]) -> Vec<&str> {
+        issues.iter().map(|i| i.rule.as_str()).collect()
+    }
+
+    #[test]
+    fn test_valid_program_has_no_issues() {
+        let source = format!(
+            "\
+C     COMPUTE A SUM
+      INTEGER SUM
+      SUM = 0
+      DO 10 I = 1, 5
+   10 SUM = SUM + I
+      WRITE (1, 20) SUM
+   20 FORMAT (I5,
+     1  5X)
+      CALL EXIT
+{:<72}SUM00010",
+            "      END"
+        );
+        assert!(validate_fortran(&source).is_empty());
+    }
+
+    #[test]
+    fn test_non_numeric_statement_number_suggests_digits() {
+        let issues = validate_fortran("   1O CONTINUE");
+        assert_eq!(rules_of(&issues), vec!["fortran.statement-number"]);
+        assert_eq!(issues[0].column, Some(5));
+        assert!(issues[0].suggestion.as_deref().unwrap().contains("10"));
+    }
+
+    #[test]
+    fn test_statement_in_label_field() {
+        let issues = validate_fortran("  X = 1");
+        assert_eq!(rules_of(&issues)[0], "fortran.statement-number");
+        assert!(issues[0]
+            .suggestion
+            .as_deref()
+            .unwrap()
+            .contains("column 7"));
+    }
+
+    #[test]
+    fn test_continuation_with_statement_number() {
+        let issues = validate_fortran("      X = 1\n   101  + 2");
+        assert_eq!(rules_of(&issues), vec!["fortran.continuation"]);
+        assert_eq!(issues[0].line_number, 2);
+        assert_eq!(issues[0].severity, Severity::Error);
+    }
+
+    #[test]
+    fn test_continuation_on_first_line() {
+        let issues = validate_fortran("     1X = 1");
+        assert_eq!(rules_of(&issues), vec!["fortran.continuation"]);
+        assert_eq!(issues[0].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_unrecognized_keyword_with_ocr_confusion() {
+        let issues = validate_fortran("      C0NTINUE");
+        assert_eq!(rules_of(&issues), vec!["fortran.keyword"]);
+        assert_eq!(issues[0].column, Some(7));
+        assert!(issues[0]
+            .suggestion
+            .as_deref()
+            .unwrap()
+            .contains("CONTINUE"));
+    }
+
+    #[test]
+    fn test_assignment_detection() {
+        assert!(is_assignment("X=1"));
+        assert!(is_assignment("A(I,J)=B(I)+1"));
+        assert!(!is_assignment("1X=2"));
+        assert!(!is_assignment("WRITE(1,2)"));
+    }
+
+    #[test]
+    fn test_identification_field_junk() {
+        let line = format!("{:<72}{}", "      X = 1", "--==-.-");
+        let issues = validate_fortran(&line);
+        assert_eq!(rules_of(&issues), vec!["fortran.identification-field"]);
+        assert_eq!(issues[0].column, Some(73));
+    }
+
+    #[test]
+    fn test_line_longer_than_80_columns() {
+        let line = format!("{:<80}XYZ", "      X = 1");
+        let issues = validate_fortran(&line);
+        assert_eq!(rules_of(&issues), vec!["fortran.line-length"]);
+    }
+
+    #[test]
+    fn test_sequence_increment() {
+        let rules = FortranRules {
+            sequence_increment: Some(10),
+            ..FortranRules::default()
+        };
+        let source = [
+            format!("{:<72}SUM00010", "C     COMMENT"),
+            format!("{:<72}SUM00020", "      X = 1"),
+            format!("{:<72}SUM00040", "      Y = 2"),
+        ]
+        .join("\n");
+
+        let issues = validate_fortran_with(&source, &rules);
+        assert_eq!(rules_of(&issues), vec!["fortran.sequence"]);
+        assert_eq!(issues[0].line_number, 3);
+        assert!(issues[0].description.contains("expected 30"));
+
+        // Sequence numbers are not checked by default
+        assert!(validate_fortran(&source).is_empty());
+    }
+
+    #[test]
+    fn test_sequence_number_multibyte_ident() {
+        // `¢` is an IBM 1130 character; it must not split a slice
+        assert_eq!(sequence_number("AB\u{a2}"), None);
+        assert_eq!(
+            sequence_number("\u{a2}X0010"),
+            Some(("\u{a2}X".to_string(), 10))
+        );
+        let line = format!("{:<72}AB\u{a2}", "      X = 1");
+        let rules = FortranRules {
+            sequence_increment: Some(10),
+            ..FortranRules::default()
+        };
+        for issues in [
+            validate_fortran(&line),
+            validate_fortran_with(&line, &rules),
+        ] {
+            assert!(!rules_of(&issues).contains(&"fortran.sequence"));
+        }
+    }
+
+    #[test]
+    fn test_allowed_charset() {
+        let rules = FortranRules {
+            allowed_charset: Some("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 =+-*/(),.".to_string()),
+            ..FortranRules::default()
+        };
+        let issues = validate_fortran_with("      X = Y % 2", &rules);
+        assert_eq!(rules_of(&issues), vec!["fortran.charset"]);
+        assert_eq!(issues[0].column, Some(13));
+    }
+
+    #[test]
+    fn test_custom_columns() {
+        let rules = FortranRules {
+            max_line_length: 72,
+            ..FortranRules::default()
+        };
+        let line = format!("{:<72}SUM00010", "      X = 1");
+        assert_eq!(
+            rules_of(&validate_fortran_with(&line, &rules)),
+            vec!["fortran.line-length"]
+        );
+    }
+
+    #[test]
+    fn test_check_columns() {
+        assert!(FortranRules::default().check().is_ok());
+        for rules in [
+            FortranRules {
+                continuation_column: 0,
+                ..FortranRules::default()
+            },
+            FortranRules {
+                statement_columns: (72, 7),
+                ..FortranRules::default()
+            },
+            FortranRules {
+                ident_columns: (73, 81),
+                ..FortranRules::default()
+            },
+        ] {
+            assert!(rules.check().is_err());
+        }
+    }
+
+    #[test]
+    fn test_comments_and_control_records_skipped() {
+        let source = "// JOB\n// FOR\n*LIST ALL\nC ANYTHING %%% GOES HERE";
+        assert!(validate_fortran(source).is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+//! Keypunch character set check
+//!
+//! Flags characters the deck's keypunch cannot have punched. Where another
+//! keypunch prints the character for a hole pattern this one prints
+//! differently (an 029 `&` is an 026 FORTRAN `+`), the character this
+//! keypunch prints is suggested: such misreads come from OCR or correction
+//! models that assume the wrong character set.
+
+use super::{Severity, ValidationIssue};
+use crate::keypunch::KeypunchModel;
+
+/// Check every character of the text against the keypunch's character set
+pub fn validate_keypunch(text: &str, model: KeypunchModel) -> Vec<ValidationIssue> {
+    let mut issues = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        for (column, c) in line.chars().enumerate() {
+            if model.hollerith(c).is_some() && !c.is_ascii_lowercase() {
+                continue;
+            }
+            let suggestion = if c.is_ascii_lowercase() {
+                Some(c.to_ascii_uppercase())
+            } else {
+                KeypunchModel::ALL
+                    .iter()
+                    .filter_map(|other| other.hollerith(c))
+                    .find_map(|holes| model.decode(holes))
+            };
+            issues.push(ValidationIssue {
+                rule: "keypunch.charset".to_string(),
+                line_number: idx + 1,
+                column: Some(column + 1),
+                severity: Severity::Warning,
+                description: format!(
+                    "Character '{}' cannot be punched on an {} keypunch",
+                    c,
+                    model.as_str()
+                ),
+                excerpt: line.to_string(),
+                suggestion: suggestion.map(|s| format!("Replace '{}' with '{}'", c, s)),
+            });
+        }
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_029_graphics_on_026_fortran_deck() {
+        let issues =
+            validate_keypunch("      X = A & B\n      Y = 1", KeypunchModel::Ibm026Fortran);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].line_number, 1);
+        assert_eq!(issues[0].column, Some(13));
+        assert_eq!(
+            issues[0].suggestion.as_deref(),
+            Some("Replace '&' with '+'")
+        );
+    }
+
+    #[test]
+    fn test_029_deck() {
+        assert!(validate_keypunch("      X = A + B", KeypunchModel::Ibm029).is_empty());
+        let issues = validate_keypunch("x~", KeypunchModel::Ibm029);
+        assert_eq!(
+            issues[0].suggestion.as_deref(),
+            Some("Replace 'x' with 'X'")
+        );
+        assert_eq!(issues[1].suggestion, None);
+    }
+}
@@ -0,0 +1,164 @@
+//! Validation module
+//!
+//! Checks OCR'd text against IBM 1130 source formats and reports issues
+//! with the offending line and, where possible, a suggested fix.
+//!
+//! Rules are grouped by format:
+//! - `fortran` - 1130 FORTRAN fixed-format card layout
+//! - `keypunch` - Characters the deck's keypunch cannot punch
+//! - `object` - Listing location sequence, and object code cross-checked
+//!   against object decks
+//! - `xref` - FORTRAN statement number cross-reference over whole listings
+//!
+//! Which rules run, and their parameters, are controlled by a [`RuleSet`].
+//! Findings can be recorded on the artifacts as processing notes with
+//! [`record_notes`].
+
+pub mod fortran;
+pub mod keypunch;
+pub mod object;
+pub mod report;
+pub mod rules;
+pub mod xref;
+
+pub use fortran::{check_card_layout, validate_fortran, validate_fortran_with, FortranRules};
+pub use keypunch::validate_keypunch;
+pub use object::{
+    check_address_sequence, cross_validate_decks, cross_validate_listing, listing_matches_deck,
+};
+pub use report::ValidationReport;
+pub use rules::RuleSet;
+pub use xref::{check_statement_labels, cross_reference, LabelXref};
+
+use crate::types::PageMetadata;
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the processing notes written by [`record_notes`]
+pub const NOTE_PREFIX: &str = "Validation ";
+
+/// How serious a validation issue is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Severity {
+    /// Suspicious, but may be legitimate
+    Warning,
+    /// Violates the format and will not assemble/compile as-is
+    Error,
+}
+
+/// A single issue reported by a validation rule
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ValidationIssue {
+    /// Identifier of the rule that produced this issue (e.g. "fortran.keyword")
+    pub rule: String,
+    /// Line number within the artifact text (1-based)
+    pub line_number: usize,
+    /// Column where the problem starts (1-based, if known)
+    pub column: Option<usize>,
+    /// Severity of the issue
+    pub severity: Severity,
+    /// Human-readable description
+    pub description: String,
+    /// The offending line as it appears in the text
+    pub excerpt: String,
+    /// Suggested fix (if one can be inferred)
+    pub suggestion: Option<String>,
+}
+
+/// Factor to scale an artifact's confidence by, given its validation issues
+///
+/// Errors count fully and warnings half, relative to the number of lines,
+/// so a page where every line has an error drops to zero confidence.
+pub fn confidence_factor(issues: &[ValidationIssue], line_count: usize) -> f32 {
+    let weight: f32 = issues
+        .iter()
+        .map(|issue| match issue.severity {
+            Severity::Error => 1.0,
+            Severity::Warning => 0.5,
+        })
+        .sum();
+    (1.0 - weight / line_count.max(1) as f32).clamp(0.0, 1.0)
+}
+
+/// Replace an artifact's validation notes with the given issues
+///
+/// Notes from an earlier validation run are dropped first, so validating
+/// again (e.g. with another rule set) does not pile up stale findings.
+pub fn record_notes(metadata: &mut PageMetadata, issues: &[ValidationIssue]) {
+    metadata.notes.retain(|note| !note.starts_with(NOTE_PREFIX));
+    metadata.notes.extend(issues.iter().map(|issue| {
+        format!(
+            "{}{}: line {}: {}",
+            NOTE_PREFIX, issue.rule, issue.line_number, issue.description
+        )
+    }));
+}
+
+/// Extract a 1-based, inclusive column range from a line
+///
+/// Columns past the end of the line are treated as blank, so the result
+/// is never longer than the requested range.
+pub(crate) fn columns(chars: &[char], first: usize, last: usize) -> String {
+    chars
+        .iter()
+        .skip(first.saturating_sub(1))
+        .take((last + 1).saturating_sub(first))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns_extracts_inclusive_range() {
+        let chars: Vec<char> = "ABCDEFGH".chars().collect();
+        assert_eq!(columns(&chars, 1, 3), "ABC");
+        assert_eq!(columns(&chars, 6, 10), "FGH");
+        assert_eq!(columns(&chars, 9, 12), "");
+    }
+
+    #[test]
+    fn test_confidence_factor() {
+        let issue = |severity| ValidationIssue {
+            rule: "test".to_string(),
+            line_number: 1,
+            column: None,
+            severity,
+            description: String::new(),
+            excerpt: String::new(),
+            suggestion: None,
+        };
+        assert_eq!(confidence_factor(&[], 10), 1.0);
+        assert_eq!(confidence_factor(&[issue(Severity::Error)], 10), 0.9);
+        assert_eq!(confidence_factor(&[issue(Severity::Warning)], 10), 0.95);
+        assert_eq!(confidence_factor(&[issue(Severity::Error)], 0), 0.0);
+    }
+
+    #[test]
+    fn test_record_notes_replaces_earlier_run() {
+        let issue = |rule: &str| ValidationIssue {
+            rule: rule.to_string(),
+            line_number: 3,
+            column: None,
+            severity: Severity::Warning,
+            description: "Bad".to_string(),
+            excerpt: String::new(),
+            suggestion: None,
+        };
+        let mut metadata = PageMetadata {
+            notes: vec!["damaged".to_string()],
+            ..Default::default()
+        };
+        record_notes(&mut metadata, &[issue("fortran.keyword")]);
+        record_notes(&mut metadata, &[issue("fortran.sequence")]);
+        assert_eq!(
+            metadata.notes,
+            vec!["damaged", "Validation fortran.sequence: line 3: Bad"]
+        );
+    }
+
+    #[test]
+    fn test_severity_ordering() {
+        assert!(Severity::Error > Severity::Warning);
+    }
+}
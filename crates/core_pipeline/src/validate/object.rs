@@ -0,0 +1,389 @@
+//! Cross-validation of assembler listings against object decks
+//!
+//! An assembler listing with object columns prints the location and the
+//! generated object words for every statement:
+//!
+//! ```text
+//! 0100 0 C400 0010      LD   L X
+//! |    | |    +- word 2 (optional)
+//! |    | +- word 1
+//! |    +- relocation flag
+//! +- location (hex)
+//! ```
+//!
+//! When the matching object deck was also scanned, every word can be
+//! checked address-by-address. A discrepancy means one of the two
+//! transcriptions is wrong.
+//!
+//! Without a deck, the locations alone still have to increase: each line
+//! starts at or after the end of the words of the line before it.
+
+use super::{Severity, ValidationIssue};
+use crate::autofix::MNEMONICS;
+use crate::types::{ObjectCardType, ObjectDeck};
+use std::collections::BTreeMap;
+
+/// Column (0-based) of the location field of a listing line
+const LOCATION_COLUMN: usize = 0;
+
+/// Columns (0-based) of the object words of a listing line; the source
+/// statement starts in column 21, after them
+const WORD_COLUMNS: [usize; 2] = [7, 12];
+
+/// An object word printed on a listing line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListingWord {
+    /// Object word value
+    pub value: u16,
+    /// Line of the listing it was read from (1-based)
+    pub line_number: usize,
+}
+
+/// Extract object words from an assembler listing, keyed by address
+///
+/// Lines that do not start with a 4-digit hex location are ignored.
+/// Common OCR confusions in hex fields (O for 0, I/L for 1) are tolerated.
+pub fn listing_object_words(text: &str) -> BTreeMap<u16, ListingWord> {
+    let mut words = BTreeMap::new();
+
+    for (line_number, address, values) in listing_lines(text) {
+        for (offset, value) in values.into_iter().enumerate() {
+            words.insert(
+                address.wrapping_add(offset as u16),
+                ListingWord { value, line_number },
+            );
+        }
+    }
+
+    words
+}
+
+/// Check that listing locations run in ascending order
+///
+/// A line whose location falls before the end of the previous line's
+/// object words is reported; gaps are fine (`BSS`, `ORG`). Such lines are
+/// usually an OCR misread of a hex digit in the location field.
+pub fn check_address_sequence(listing_text: &str) -> Vec<ValidationIssue> {
+    let lines: Vec<&str> = listing_text.lines().collect();
+    let mut issues = Vec::new();
+    let mut next: Option<(u16, u16)> = None;
+
+    for (line_number, address, values) in listing_lines(listing_text) {
+        if let Some((previous, expected)) = next {
+            if address < expected {
+                issues.push(ValidationIssue {
+                    rule: "object.address-sequence".to_string(),
+                    line_number,
+                    column: Some(1),
+                    severity: Severity::Warning,
+                    description: format!(
+                        "Address {address:04X} does not follow {previous:04X} \
+                         (expected {expected:04X} or later)"
+                    ),
+                    excerpt: lines[line_number - 1].to_string(),
+                    suggestion: Some(
+                        "Check the location field for a misread hex digit, or a page \
+                         out of order"
+                            .to_string(),
+                    ),
+                });
+            }
+        }
+        next = Some((address, address.saturating_add(values.len() as u16)));
+    }
+
+    issues
+}
+
+/// Listing lines with a location: line number (1-based), location and
+/// object words, read from their columns
+fn listing_lines(text: &str) -> impl Iterator<Item = (usize, u16, Vec<u16>)> + '_ {
+    text.lines().enumerate().filter_map(|(idx, line)| {
+        let fields = listing_fields(line)?;
+        let mut values = fields.iter().map_while(|(_, field)| parse_hex_word(field));
+        let address = values.next()?;
+        Some((idx + 1, address, values.collect()))
+    })
+}
+
+/// Hex fields of a listing line, read by column: the location, then up to
+/// two object words, each with its column (0-based)
+///
+/// A field is the four characters at its column, set off by blanks, and
+/// is not a mnemonic: in a line without object words, the source
+/// statement can reach the word columns. A word is only read when the one
+/// before it was. Returns `None` without a location field. The characters
+/// are not checked to be hex digits, so OCR confusions can be handled by
+/// the caller.
+pub(crate) fn listing_fields(line: &str) -> Option<Vec<(usize, String)>> {
+    let chars: Vec<char> = line.chars().collect();
+    let field = |column: usize| {
+        let text: String = chars.get(column..column + 4)?.iter().collect();
+        let set_off = column
+            .checked_sub(1)
+            .is_none_or(|before| chars[before].is_whitespace())
+            && chars.get(column + 4).is_none_or(|c| c.is_whitespace());
+        (set_off && !text.contains(char::is_whitespace) && !MNEMONICS.contains(&text.as_str()))
+            .then_some((column, text))
+    };
+    let mut fields = vec![field(LOCATION_COLUMN)?];
+    fields.extend(WORD_COLUMNS.into_iter().map_while(field));
+    Some(fields)
+}
+
+/// Collect the words loaded by an object deck, keyed by address
+///
+/// Only text cards with a load address contribute; their data is read as
+/// consecutive big-endian 16-bit words starting at that address.
+pub fn deck_object_words(deck: &ObjectDeck) -> BTreeMap<u16, u16> {
+    let mut words = BTreeMap::new();
+
+    for card in &deck.object_cards {
+        let (ObjectCardType::Text, Some(address)) = (card.card_type, card.address) else {
+            continue;
+        };
+        for (offset, pair) in card.data.chunks_exact(2).enumerate() {
+            let value = u16::from_be_bytes([pair[0], pair[1]]);
+            words.insert(address.wrapping_add(offset as u16), value);
+        }
+    }
+
+    words
+}
+
+/// Check whether a listing and an object deck describe the same program
+///
+/// Returns true when at least half of the listing's addresses are also
+/// loaded by the deck.
+pub fn listing_matches_deck(listing_text: &str, deck: &ObjectDeck) -> bool {
+    let listing = listing_object_words(listing_text);
+    if listing.is_empty() {
+        return false;
+    }
+    let deck_words = deck_object_words(deck);
+    let shared = listing
+        .keys()
+        .filter(|a| deck_words.contains_key(a))
+        .count();
+    shared * 2 >= listing.len()
+}
+
+/// Compare listing object words against a decoded object deck
+///
+/// Reports words that differ, words missing from the deck, and deck words
+/// falling inside the listing's address range that the listing lacks.
+pub fn cross_validate_listing(listing_text: &str, deck: &ObjectDeck) -> Vec<ValidationIssue> {
+    let lines: Vec<&str> = listing_text.lines().collect();
+    let listing = listing_object_words(listing_text);
+    let deck_words = deck_object_words(deck);
+    let mut issues = Vec::new();
+
+    let issue =
+        |rule: &str, line_number: usize, severity, description, suggestion| ValidationIssue {
+            rule: rule.to_string(),
+            line_number,
+            column: None,
+            severity,
+            description,
+            excerpt: lines
+                .get(line_number.wrapping_sub(1))
+                .unwrap_or(&"")
+                .to_string(),
+            suggestion: Some(suggestion),
+        };
+
+    for (&address, word) in &listing {
+        match deck_words.get(&address) {
+            Some(&value) if value != word.value => issues.push(issue(
+                "object.mismatch",
+                word.line_number,
+                Severity::Error,
+                format!(
+                    "Address {address:04X}: listing has {:04X}, deck '{}' has {value:04X}",
+                    word.value, deck.name
+                ),
+                format!("Check both transcriptions at address {address:04X}"),
+            )),
+            Some(_) => {}
+            None => issues.push(issue(
+                "object.missing-in-deck",
+                word.line_number,
+                Severity::Warning,
+                format!(
+                    "Address {address:04X} is on the listing but not loaded by deck '{}'",
+                    deck.name
+                ),
+                "Check for a missing or misread object card".to_string(),
+            )),
+        }
+    }
+
+    if let (Some(&first), Some(&last)) = (listing.keys().next(), listing.keys().next_back()) {
+        for (&address, &value) in deck_words.range(first..=last) {
+            if listing.contains_key(&address) {
+                continue;
+            }
+            let line_number = listing
+                .range(..address)
+                .next_back()
+                .map_or(0, |(_, w)| w.line_number);
+            issues.push(issue(
+                "object.missing-in-listing",
+                line_number,
+                Severity::Warning,
+                format!(
+                    "Deck loads {value:04X} at {address:04X} but the listing has no word there"
+                ),
+                "Check for a dropped listing line near this address".to_string(),
+            ));
+        }
+    }
+
+    issues
+}
+
+/// Compare a listing against every scanned deck it matches
+///
+/// Decks that do not share most of the listing's addresses (see
+/// [`listing_matches_deck`]) are skipped, so a listing can be checked
+/// against all of a scan set's decks.
+pub fn cross_validate_decks(listing_text: &str, decks: &[ObjectDeck]) -> Vec<ValidationIssue> {
+    let mut issues: Vec<ValidationIssue> = decks
+        .iter()
+        .filter(|deck| listing_matches_deck(listing_text, deck))
+        .flat_map(|deck| cross_validate_listing(listing_text, deck))
+        .collect();
+    issues.sort_by_key(|issue| issue.line_number);
+    issues
+}
+
+/// Parse a 4-character hex word, tolerating common OCR confusions
+fn parse_hex_word(token: &str) -> Option<u16> {
+    if token.chars().count() != 4 {
+        return None;
+    }
+    let normalized: String = token
+        .chars()
+        .map(|c| match c {
+            'O' | 'o' | 'Q' => '0',
+            'I' | 'L' | 'l' | '|' => '1',
+            _ => c,
+        })
+        .collect();
+    u16::from_str_radix(&normalized, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::ObjectCard;
+
+    fn deck(address: u16, words: &[u16]) -> ObjectDeck {
+        ObjectDeck {
+            name: "TEST".to_string(),
+            cards: Vec::new(),
+            object_cards: vec![ObjectCard {
+                card_type: ObjectCardType::Text,
+                address: Some(address),
+                data: words.iter().flat_map(|w| w.to_be_bytes()).collect(),
+                symbols: Vec::new(),
+            }],
+        }
+    }
+
+    const LISTING: &str = "\
+0100 0 C400 0010      LD   L X
+0102 0 D010           STO    Y
+                      END";
+
+    #[test]
+    fn test_listing_object_words() {
+        let words = listing_object_words(LISTING);
+        assert_eq!(words.len(), 3);
+        assert_eq!(words[&0x0100].value, 0xC400);
+        assert_eq!(words[&0x0101].value, 0x0010);
+        assert_eq!(words[&0x0102].line_number, 2);
+    }
+
+    #[test]
+    fn test_listing_tolerates_ocr_confusions() {
+        let words = listing_object_words("OBFO 0 OO78      DC   123");
+        assert_eq!(words[&0x0BF0].value, 0x0078);
+    }
+
+    #[test]
+    fn test_matching_listing_and_deck_have_no_issues() {
+        let deck = deck(0x0100, &[0xC400, 0x0010, 0xD010]);
+        assert!(listing_matches_deck(LISTING, &deck));
+        assert!(cross_validate_listing(LISTING, &deck).is_empty());
+    }
+
+    #[test]
+    fn test_mismatched_word_reported() {
+        let deck = deck(0x0100, &[0xC400, 0x0018, 0xD010]);
+        let issues = cross_validate_listing(LISTING, &deck);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, "object.mismatch");
+        assert_eq!(issues[0].line_number, 1);
+        assert!(issues[0].description.contains("0101"));
+    }
+
+    #[test]
+    fn test_missing_words_reported_both_ways() {
+        let listing = "0100 0 C400\n0103 0 D010";
+        let deck = deck(0x0100, &[0xC400, 0x0001, 0x0002]);
+        let issues = cross_validate_listing(listing, &deck);
+        let rules: Vec<&str> = issues.iter().map(|i| i.rule.as_str()).collect();
+        assert_eq!(
+            rules,
+            vec![
+                "object.missing-in-deck",
+                "object.missing-in-listing",
+                "object.missing-in-listing"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_address_sequence() {
+        assert!(check_address_sequence(LISTING).is_empty());
+        // Gaps are fine, going back is not
+        let listing = "0100 0 C400 0010\n0180 0 D010\n0118 0 C400\n0119 0 D010";
+        let issues = check_address_sequence(listing);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, "object.address-sequence");
+        assert_eq!(issues[0].line_number, 3);
+        assert!(issues[0].description.contains("0181"));
+    }
+
+    #[test]
+    fn test_mnemonic_after_one_word_is_not_a_word() {
+        let listing = "\
+0102 0 4000      LIBF FLOAT
+0103 0 4000      CALL FILE
+0104 0 C400 0010      LD   L X";
+        let words = listing_object_words(listing);
+        let addresses: Vec<u16> = words.keys().copied().collect();
+        assert_eq!(addresses, [0x0102, 0x0103, 0x0104, 0x0105]);
+        assert_eq!(words[&0x0103].value, 0x4000);
+        assert!(check_address_sequence(listing).is_empty());
+    }
+
+    #[test]
+    fn test_unrelated_deck_does_not_match() {
+        let deck = deck(0x4000, &[0x1234]);
+        assert!(!listing_matches_deck(LISTING, &deck));
+    }
+
+    #[test]
+    fn test_cross_validate_decks_skips_unrelated() {
+        let decks = [
+            deck(0x4000, &[0x1234]),
+            deck(0x0100, &[0xC400, 0x0018, 0xD010]),
+        ];
+        let issues = cross_validate_decks(LISTING, &decks);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, "object.mismatch");
+        assert!(cross_validate_decks(LISTING, &decks[..1]).is_empty());
+    }
+}
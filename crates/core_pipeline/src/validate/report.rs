@@ -0,0 +1,331 @@
+//! Validation report export
+//!
+//! Collects validation issues for every artifact in a scan set and renders
+//! them as machine-readable JSON or a browsable HTML summary.
+
+use super::{Severity, ValidationIssue};
+use crate::error::{IoContext, Result};
+use crate::types::{PageArtifact, ScanSetId, ScanSetManifest};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Validation results for a whole scan set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationReport {
+    /// Scan set the report covers
+    pub scan_set_id: ScanSetId,
+    /// Human-readable scan set name
+    pub name: String,
+    /// Per-artifact results (including artifacts without issues)
+    pub artifacts: Vec<ArtifactReport>,
+}
+
+/// Validation results for a single artifact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArtifactReport {
+    /// Artifact identifier
+    pub artifact_id: String,
+    /// Raw image path (relative to the scan set)
+    pub image: PathBuf,
+    /// Original filenames, for locating the physical page
+    pub original_filenames: Vec<String>,
+    /// Issues found in this artifact's text
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    /// Create an empty report for a scan set
+    pub fn new(manifest: &ScanSetManifest) -> Self {
+        Self {
+            scan_set_id: manifest.scan_set_id,
+            name: manifest.name.clone(),
+            artifacts: Vec::new(),
+        }
+    }
+
+    /// Record the issues found for an artifact
+    pub fn add_artifact(&mut self, artifact: &PageArtifact, issues: Vec<ValidationIssue>) {
+        self.artifacts.push(ArtifactReport {
+            artifact_id: artifact.id.0.to_string(),
+            image: artifact.raw_image_path.clone(),
+            original_filenames: artifact.metadata.original_filenames.clone(),
+            issues,
+        });
+    }
+
+    /// Total number of issues across all artifacts
+    pub fn issue_count(&self) -> usize {
+        self.artifacts.iter().map(|a| a.issues.len()).sum()
+    }
+
+    /// Number of issues with the given severity
+    pub fn count_severity(&self, severity: Severity) -> usize {
+        self.issues().filter(|i| i.severity == severity).count()
+    }
+
+    /// Number of issues reported by each rule
+    pub fn rule_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for issue in self.issues() {
+            *counts.entry(issue.rule.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    fn issues(&self) -> impl Iterator<Item = &ValidationIssue> {
+        self.artifacts.iter().flat_map(|a| a.issues.iter())
+    }
+
+    /// Render the report as pretty-printed JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Render the report as a standalone HTML page
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str(&html_header(&self.name));
+
+        // Summary
+        html.push_str(&format!(
+            r#"<div class="summary">
+    <div><strong>Scan Set ID:</strong> {}</div>
+    <div><strong>Artifacts:</strong> {} ({} with issues)</div>
+    <div><strong>Issues:</strong> {} ({} errors, {} warnings)</div>
+    <table>
+        <tr><th>Rule</th><th>Count</th></tr>
+"#,
+            self.scan_set_id.0,
+            self.artifacts.len(),
+            self.artifacts
+                .iter()
+                .filter(|a| !a.issues.is_empty())
+                .count(),
+            self.issue_count(),
+            self.count_severity(Severity::Error),
+            self.count_severity(Severity::Warning),
+        ));
+        for (rule, count) in self.rule_counts() {
+            html.push_str(&format!(
+                "        <tr><td>{}</td><td>{}</td></tr>\n",
+                html_escape(&rule),
+                count
+            ));
+        }
+        html.push_str("    </table>\n</div>\n");
+
+        // Per-artifact details
+        for (idx, artifact) in self.artifacts.iter().enumerate() {
+            html.push_str(&artifact_html(idx, self.artifacts.len(), artifact));
+        }
+
+        html.push_str("</body></html>\n");
+        html
+    }
+
+    /// Write the report, choosing JSON or HTML from the file extension
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let contents = if is_json {
+            self.to_json()?
+        } else {
+            self.to_html()
+        };
+        fs::write(path, contents)
+            .io_context(|| format!("Failed to write validation report: {}", path.display()))
+    }
+}
+
+/// Render one artifact's section of the HTML report
+fn artifact_html(idx: usize, total: usize, artifact: &ArtifactReport) -> String {
+    let mut html = format!(
+        r#"<div class="artifact">
+    <h2>Artifact {}/{} <span class="count">{} issue(s)</span></h2>
+    <div class="metadata">
+        <div><strong>ID:</strong> {}</div>
+        <div><strong>Image:</strong> {}</div>
+        <div><strong>Original files:</strong> {}</div>
+    </div>
+"#,
+        idx + 1,
+        total,
+        artifact.issues.len(),
+        artifact.artifact_id,
+        html_escape(&artifact.image.display().to_string()),
+        html_escape(&artifact.original_filenames.join(", ")),
+    );
+
+    for issue in &artifact.issues {
+        let (class, label) = match issue.severity {
+            Severity::Error => ("error", "ERROR"),
+            Severity::Warning => ("warning", "WARNING"),
+        };
+        let column = issue
+            .column
+            .map(|c| format!(", column {c}"))
+            .unwrap_or_default();
+        html.push_str(&format!(
+            r#"    <div class="issue {}">
+        <div><strong>{}</strong> [{}] Line {}{}: {}</div>
+        <pre class="excerpt">{}</pre>
+"#,
+            class,
+            label,
+            html_escape(&issue.rule),
+            issue.line_number,
+            column,
+            html_escape(&issue.description),
+            html_escape(&issue.excerpt),
+        ));
+        if let Some(suggestion) = &issue.suggestion {
+            html.push_str(&format!(
+                "        <div class=\"suggestion\">Suggestion: {}</div>\n",
+                html_escape(suggestion)
+            ));
+        }
+        html.push_str("    </div>\n");
+    }
+
+    html.push_str("</div>\n");
+    html
+}
+
+/// HTML page header with CSS styling
+fn html_header(name: &str) -> String {
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>Validation Report: {0}</title>
+    <style>
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
+            background: #f5f5f5;
+            padding: 20px;
+            color: #333;
+        }}
+        .summary, .artifact {{
+            background: white;
+            border-radius: 8px;
+            padding: 20px;
+            margin-bottom: 20px;
+            box-shadow: 0 2px 4px rgba(0,0,0,0.1);
+        }}
+        .summary table {{ border-collapse: collapse; margin-top: 10px; }}
+        .summary td, .summary th {{ border: 1px solid #ddd; padding: 4px 10px; text-align: left; }}
+        .metadata {{ font-size: 14px; color: #666; margin-bottom: 10px; }}
+        .count {{ font-size: 14px; color: #888; }}
+        .issue {{ border-left: 4px solid #ccc; padding: 8px 12px; margin: 8px 0; background: #fafafa; }}
+        .issue.error {{ border-color: #d33; }}
+        .issue.warning {{ border-color: #e90; }}
+        .excerpt {{
+            font-family: "Courier New", Courier, monospace;
+            font-size: 12px;
+            white-space: pre;
+            overflow-x: auto;
+            background: white;
+            border: 1px solid #ddd;
+            padding: 6px;
+            margin: 6px 0;
+        }}
+        .suggestion {{ color: #060; font-size: 14px; }}
+    </style>
+</head>
+<body>
+    <h1>IBM 1130 Validation Report: {0}</h1>
+"#,
+        html_escape(name)
+    )
+}
+
+/// Escape HTML special characters
+pub(crate) fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ArtifactKind, PageId, PageMetadata};
+
+    fn report() -> ValidationReport {
+        let manifest = ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "deck <1>".to_string(),
+            created_at: String::new(),
+            image_count: 2,
+            original_file_count: 2,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        };
+        let artifact = PageArtifact {
+            id: PageId::new(),
+            scan_set: manifest.scan_set_id,
+            raw_image_path: PathBuf::from("images/abc.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some("   1O CONTINUE".to_string()),
+            metadata: PageMetadata::default(),
+        };
+
+        let mut report = ValidationReport::new(&manifest);
+        report.add_artifact(&artifact, super::super::validate_fortran("   1O CONTINUE"));
+        report.add_artifact(&artifact, Vec::new());
+        report
+    }
+
+    #[test]
+    fn test_report_counts() {
+        let report = report();
+        assert_eq!(report.issue_count(), 1);
+        assert_eq!(report.count_severity(Severity::Error), 1);
+        assert_eq!(report.rule_counts()["fortran.statement-number"], 1);
+    }
+
+    #[test]
+    fn test_report_json_roundtrip() {
+        let report = report();
+        let json = report.to_json().unwrap();
+        let parsed: ValidationReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.artifacts.len(), 2);
+        assert_eq!(parsed.artifacts[0].issues[0].line_number, 1);
+    }
+
+    #[test]
+    fn test_report_html_escapes_and_includes_suggestions() {
+        let html = report().to_html();
+        assert!(html.contains("deck &lt;1&gt;"));
+        assert!(html.contains("fortran.statement-number"));
+        assert!(html.contains("Suggestion:"));
+        assert!(html.contains("Artifact 2/2"));
+    }
+
+    #[test]
+    fn test_write_chooses_format_by_extension() {
+        let dir = tempfile::tempdir().unwrap();
+        let report = report();
+
+        let json_path = dir.path().join("report.json");
+        report.write(&json_path).unwrap();
+        assert!(fs::read_to_string(&json_path).unwrap().starts_with('{'));
+
+        let html_path = dir.path().join("report.html");
+        report.write(&html_path).unwrap();
+        assert!(fs::read_to_string(&html_path)
+            .unwrap()
+            .starts_with("<!DOCTYPE html>"));
+    }
+}
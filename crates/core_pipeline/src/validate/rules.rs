@@ -0,0 +1,199 @@
+//! Configurable validation rule sets
+//!
+//! Data decks and source decks need different strictness, so rules can be
+//! disabled and tuned from a TOML rule-set file:
+//!
+//! ```toml
+//! name = "data-deck"
+//! # Rule identifiers, or whole groups such as "fortran"
+//! disabled = ["fortran.keyword", "fortran.continuation"]
+//! # Keypunch character set (defaults to the scan set's keypunch)
+//! keypunch = "026-fortran"
+//!
+//! [fortran]
+//! statement_columns = [1, 72]
+//! sequence_increment = 10
+//! allowed_charset = "0123456789 +-.,"
+//! ```
+//!
+//! Any omitted setting keeps its default.
+
+use super::fortran::{check_card_layout, validate_fortran_with, FortranRules};
+use super::keypunch::validate_keypunch;
+use super::object::{check_address_sequence, cross_validate_decks};
+use super::xref::check_statement_labels;
+use super::ValidationIssue;
+use crate::error::{Error, IoContext, Result};
+use crate::keypunch::KeypunchModel;
+use crate::types::ObjectDeck;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A named set of enabled rules and their parameters
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RuleSet {
+    /// Rule set name (for reports)
+    pub name: String,
+    /// Disabled rule identifiers or rule group prefixes
+    pub disabled: Vec<String>,
+    /// FORTRAN card layout parameters
+    pub fortran: FortranRules,
+    /// Keypunch whose character set the text must fit (029 if unset)
+    pub keypunch: Option<KeypunchModel>,
+}
+
+impl RuleSet {
+    /// Parse a rule set from TOML
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        let rules: Self = toml::from_str(toml).map_err(|source| Error::RuleSet {
+            message: "Failed to parse rule set".to_string(),
+            source: Box::new(source),
+        })?;
+        rules.fortran.check()?;
+        Ok(rules)
+    }
+
+    /// Load a rule set from a TOML file
+    pub fn load(path: &Path) -> Result<Self> {
+        let toml = fs::read_to_string(path)
+            .io_context(|| format!("Failed to read rule set: {}", path.display()))?;
+        let rules: Self = toml::from_str(&toml).map_err(|source| Error::RuleSet {
+            message: format!("Invalid rule set: {}", path.display()),
+            source: Box::new(source),
+        })?;
+        rules.fortran.check().map_err(|err| {
+            Error::invalid(format!("Invalid rule set: {}: {}", path.display(), err))
+        })?;
+        Ok(rules)
+    }
+
+    /// Check whether a rule is enabled
+    ///
+    /// A disabled entry matches the rule itself or any rule in its group
+    /// (`fortran` disables `fortran.keyword`).
+    pub fn is_enabled(&self, rule: &str) -> bool {
+        !self.disabled.iter().any(|disabled| {
+            rule == disabled
+                || rule
+                    .strip_prefix(disabled.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    /// Drop issues reported by disabled rules
+    pub fn filter(&self, issues: Vec<ValidationIssue>) -> Vec<ValidationIssue> {
+        issues
+            .into_iter()
+            .filter(|issue| self.is_enabled(&issue.rule))
+            .collect()
+    }
+
+    /// Validate FORTRAN source text with this rule set, including the
+    /// keypunch character set check
+    pub fn validate_fortran(&self, text: &str) -> Vec<ValidationIssue> {
+        let mut issues = validate_fortran_with(text, &self.fortran);
+        issues.extend(validate_keypunch(text, self.keypunch.unwrap_or_default()));
+        issues.sort_by_key(|issue| issue.line_number);
+        self.filter(issues)
+    }
+
+    /// Check text of any language with this rule set: the card layout
+    /// (line length, sequence numbers) and the keypunch character set
+    pub fn check_card(&self, text: &str) -> Vec<ValidationIssue> {
+        let mut issues = check_card_layout(text, &self.fortran);
+        issues.extend(validate_keypunch(text, self.keypunch.unwrap_or_default()));
+        issues.sort_by_key(|issue| issue.line_number);
+        self.filter(issues)
+    }
+
+    /// Check text against the keypunch character set with this rule set
+    pub fn check_keypunch(&self, text: &str) -> Vec<ValidationIssue> {
+        self.filter(validate_keypunch(text, self.keypunch.unwrap_or_default()))
+    }
+
+    /// Check the location sequence of an assembler listing with this rule set
+    pub fn check_object_listing(&self, text: &str) -> Vec<ValidationIssue> {
+        self.filter(check_address_sequence(text))
+    }
+
+    /// Compare an assembler listing's object words against the scanned
+    /// object decks it matches, with this rule set
+    pub fn check_against_decks(&self, text: &str, decks: &[ObjectDeck]) -> Vec<ValidationIssue> {
+        self.filter(cross_validate_decks(text, decks))
+    }
+
+    /// Cross-check FORTRAN statement numbers of a whole listing with this rule set
+    pub fn check_statement_labels(&self, text: &str) -> Vec<ValidationIssue> {
+        self.filter(check_statement_labels(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_partial_rule_set() {
+        let rules = RuleSet::from_toml(
+            r#"
+name = "data-deck"
+disabled = ["fortran.keyword"]
+keypunch = "026-fortran"
+
+[fortran]
+sequence_increment = 10
+statement_columns = [1, 72]
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(rules.name, "data-deck");
+        assert_eq!(rules.keypunch, Some(KeypunchModel::Ibm026Fortran));
+        assert_eq!(rules.fortran.sequence_increment, Some(10));
+        assert_eq!(rules.fortran.statement_columns, (1, 72));
+        assert_eq!(rules.fortran.ident_columns, (73, 80));
+    }
+
+    #[test]
+    fn test_disabled_rules_and_groups() {
+        let rules = RuleSet {
+            disabled: vec!["fortran.keyword".to_string(), "object".to_string()],
+            ..RuleSet::default()
+        };
+        assert!(!rules.is_enabled("fortran.keyword"));
+        assert!(rules.is_enabled("fortran.keywords"));
+        assert!(rules.is_enabled("fortran.continuation"));
+        assert!(!rules.is_enabled("object.mismatch"));
+        assert!(rules.is_enabled("objective.rule"));
+    }
+
+    #[test]
+    fn test_validate_skips_disabled_rules() {
+        let rules = RuleSet {
+            disabled: vec!["fortran.keyword".to_string()],
+            ..RuleSet::default()
+        };
+        assert!(rules.validate_fortran("      1 2 3 4 5").is_empty());
+        assert_eq!(
+            RuleSet::default().validate_fortran("      1 2 3 4 5").len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_check_card_skips_statement_rules() {
+        let rules = RuleSet::default();
+        assert!(rules.check_card("      1 2 3 4 5").is_empty());
+        let long = "9".repeat(81);
+        assert_eq!(rules.check_card(&long)[0].rule, "fortran.line-length");
+    }
+
+    #[test]
+    fn test_invalid_rule_set() {
+        assert!(RuleSet::from_toml("disabled = 5").is_err());
+        assert!(RuleSet::from_toml("[fortran]\ncontinuation_column = 0").is_err());
+        assert!(RuleSet::from_toml("[fortran]\nident_columns = [73, 90]").is_err());
+    }
+}
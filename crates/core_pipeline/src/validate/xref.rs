@@ -0,0 +1,287 @@
+//! FORTRAN statement number cross-reference
+//!
+//! Collects the statement numbers defined in columns 1-5 and the numbers
+//! referenced by `GO TO`, arithmetic `IF`, `DO` and `READ`/`WRITE` format
+//! references. A reference to a number that is never defined almost always
+//! means a digit was misread, so this runs on whole reconstructed listings
+//! (a single page would miss labels defined on other pages).
+
+use super::fortran::is_non_statement;
+use super::{columns, Severity, ValidationIssue};
+use std::collections::btree_map::Entry;
+use std::collections::BTreeMap;
+
+/// Statement numbers defined and referenced in a FORTRAN listing
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LabelXref {
+    /// Statement number to the line defining it (1-based)
+    pub defined: BTreeMap<u32, usize>,
+    /// Statement number to the lines referencing it (1-based)
+    pub references: BTreeMap<u32, Vec<usize>>,
+    /// Statement numbers defined again, with the line of the repeat
+    pub duplicates: Vec<(u32, usize)>,
+}
+
+/// A statement with its continuation lines joined
+struct Statement {
+    line_number: usize,
+    label: Option<u32>,
+    text: String,
+}
+
+/// Build the statement number cross-reference of a FORTRAN listing
+pub fn cross_reference(text: &str) -> LabelXref {
+    let mut xref = LabelXref::default();
+    for statement in statements(text) {
+        if let Some(label) = statement.label {
+            match xref.defined.entry(label) {
+                Entry::Occupied(_) => xref.duplicates.push((label, statement.line_number)),
+                Entry::Vacant(entry) => {
+                    entry.insert(statement.line_number);
+                }
+            }
+        }
+        for label in referenced_labels(&statement.text) {
+            let lines = xref.references.entry(label).or_default();
+            if lines.last() != Some(&statement.line_number) {
+                lines.push(statement.line_number);
+            }
+        }
+    }
+    xref
+}
+
+/// Report references to undefined statement numbers and duplicate labels
+///
+/// For an undefined reference, a defined number differing in a single
+/// digit is suggested as the likely OCR reading.
+pub fn check_statement_labels(text: &str) -> Vec<ValidationIssue> {
+    let xref = cross_reference(text);
+    let lines: Vec<&str> = text.lines().collect();
+    let issue =
+        |rule: &str, line_number: usize, severity, description, suggestion| ValidationIssue {
+            rule: rule.to_string(),
+            line_number,
+            column: None,
+            severity,
+            description,
+            excerpt: lines.get(line_number - 1).unwrap_or(&"").to_string(),
+            suggestion,
+        };
+
+    let mut issues = Vec::new();
+    for (&label, references) in &xref.references {
+        if xref.defined.contains_key(&label) {
+            continue;
+        }
+        let candidates: Vec<u32> = xref
+            .defined
+            .keys()
+            .copied()
+            .filter(|&defined| differs_by_one_digit(label, defined))
+            .collect();
+        let suggestion = match candidates[..] {
+            [only] => Some(format!("Did you mean {only}? (OCR digit error)")),
+            _ => None,
+        };
+        for &line_number in references {
+            issues.push(issue(
+                "fortran.label-undefined",
+                line_number,
+                Severity::Error,
+                format!("Reference to undefined statement number {label}"),
+                suggestion.clone(),
+            ));
+        }
+    }
+    for &(label, line_number) in &xref.duplicates {
+        issues.push(issue(
+            "fortran.label-duplicate",
+            line_number,
+            Severity::Error,
+            format!(
+                "Statement number {label} already defined on line {}",
+                xref.defined[&label]
+            ),
+            None,
+        ));
+    }
+    issues.sort_by_key(|issue| issue.line_number);
+    issues
+}
+
+/// Split a listing into statements, joining continuation lines
+fn statements(text: &str) -> Vec<Statement> {
+    let mut statements: Vec<Statement> = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        if line.trim().is_empty() || is_non_statement(line) {
+            continue;
+        }
+        let chars: Vec<char> = line.chars().collect();
+        let body: String = columns(&chars, 7, 72)
+            .chars()
+            .filter(|c| *c != ' ')
+            .collect();
+        let continuation = !matches!(chars.get(5), None | Some(' ') | Some('0'));
+
+        match statements.last_mut() {
+            Some(statement) if continuation => statement.text.push_str(&body),
+            _ => {
+                let label = columns(&chars, 1, 5);
+                let label = label.trim();
+                statements.push(Statement {
+                    line_number: idx + 1,
+                    label: label
+                        .chars()
+                        .all(|c| c.is_ascii_digit())
+                        .then(|| label.parse().ok())
+                        .flatten(),
+                    text: body,
+                });
+            }
+        }
+    }
+    statements
+}
+
+/// Statement numbers referenced by a blank-free statement
+fn referenced_labels(statement: &str) -> Vec<u32> {
+    if let Some(rest) = statement.strip_prefix("GOTO") {
+        // Computed GO TO (n1, n2, ...), I or plain GO TO n
+        return match rest.strip_prefix('(') {
+            Some(list) => number_list(list.split(')').next().unwrap_or("")),
+            None => leading_number(rest).into_iter().collect(),
+        };
+    }
+    if let Some(rest) = statement.strip_prefix("IF(") {
+        let Some(close) = closing_paren(rest) else {
+            return Vec::new();
+        };
+        let branch = &rest[close + 1..];
+        return if branch.starts_with(|c: char| c.is_ascii_digit()) {
+            number_list(branch)
+        } else {
+            referenced_labels(branch)
+        };
+    }
+    if let Some(rest) = statement.strip_prefix("DO") {
+        // DO 10 I = 1, N (as opposed to an assignment to DO10I)
+        let is_loop = rest
+            .split_once('=')
+            .is_some_and(|(_, range)| has_top_level_comma(range));
+        if is_loop {
+            return leading_number(rest).into_iter().collect();
+        }
+    }
+    for io in ["READ(", "WRITE("] {
+        if let Some(rest) = statement.strip_prefix(io) {
+            let control = rest.split(')').next().unwrap_or("");
+            return control
+                .split(',')
+                .nth(1)
+                .and_then(leading_number)
+                .into_iter()
+                .collect();
+        }
+    }
+    Vec::new()
+}
+
+/// Index of the parenthesis closing an already-opened one
+fn closing_paren(text: &str) -> Option<usize> {
+    let mut depth = 1;
+    for (idx, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(idx);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Check whether a comma appears outside parentheses
+fn has_top_level_comma(text: &str) -> bool {
+    let mut depth = 0i32;
+    text.chars().any(|c| {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {}
+        }
+        c == ',' && depth == 0
+    })
+}
+
+/// Parse the statement number at the start of a string
+fn leading_number(text: &str) -> Option<u32> {
+    let digits: String = text.chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Parse a comma-separated list of statement numbers
+fn number_list(text: &str) -> Vec<u32> {
+    text.split(',').filter_map(leading_number).collect()
+}
+
+/// Check whether two statement numbers differ in exactly one digit
+fn differs_by_one_digit(a: u32, b: u32) -> bool {
+    let (a, b) = (a.to_string(), b.to_string());
+    a.len() == b.len() && a.chars().zip(b.chars()).filter(|(x, y)| x != y).count() == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cross_reference() {
+        let text = "      DO 10 I = 1, N\n      IF (X) 20, 30, 20\n   \
+                    10 CONTINUE\n   20 GO TO (10, 30), K\n   30 WRITE (3, 100) X\n  \
+                    100 FORMAT (F10.2)";
+        let xref = cross_reference(text);
+        assert_eq!(
+            xref.defined.keys().copied().collect::<Vec<_>>(),
+            [10, 20, 30, 100]
+        );
+        assert_eq!(xref.references[&10], [1, 4]);
+        assert_eq!(xref.references[&20], [2]);
+        assert_eq!(xref.references[&100], [5]);
+        assert!(xref.duplicates.is_empty());
+    }
+
+    #[test]
+    fn test_undefined_label_suggests_ocr_fix() {
+        let text = "      GO TO 18\n   10 CONTINUE";
+        let issues = check_statement_labels(text);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, "fortran.label-undefined");
+        assert_eq!(issues[0].line_number, 1);
+        assert_eq!(
+            issues[0].suggestion.as_deref(),
+            Some("Did you mean 10? (OCR digit error)")
+        );
+    }
+
+    #[test]
+    fn test_duplicate_label() {
+        let issues = check_statement_labels("   10 CONTINUE\n   10 CONTINUE");
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].rule, "fortran.label-duplicate");
+        assert_eq!(issues[0].line_number, 2);
+    }
+
+    #[test]
+    fn test_continuation_and_assignment() {
+        let text = "      GO TO\n     1 10\n      DO10I = 1.5\n   10 CONTINUE\nC     GO TO 99";
+        let xref = cross_reference(text);
+        assert_eq!(xref.references.keys().copied().collect::<Vec<_>>(), [10]);
+        assert_eq!(xref.references[&10], [1]);
+        assert!(check_statement_labels(text).is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+//! Assembler listings checked against object decks read back from a scan set
+
+use core_pipeline::reconstruct::build_object_decks;
+use core_pipeline::scan_set;
+use core_pipeline::types::{ArtifactKind, CardArtifact, CardId, CardMetadata, ScanSetId};
+use core_pipeline::validate::RuleSet;
+use std::path::PathBuf;
+
+const LISTING: &str = "\
+0100 0 C400 0010      LD   L X
+0102 0 D010           STO    Y
+0103 0 4C00 0100      BSC  L START
+                      END";
+
+/// A text card loading `words` at `address`, followed by an end card
+fn deck(name: &str, address: u16, words: &[u16]) -> Vec<CardArtifact> {
+    let card = |type_code: u8, data: &[u16], sequence: &str| {
+        let mut binary = vec![0u8; 80];
+        binary[0..2].copy_from_slice(&address.to_be_bytes());
+        binary[4] = type_code;
+        binary[5] = data.len() as u8;
+        for (i, word) in data.iter().enumerate() {
+            binary[6 + i * 2..8 + i * 2].copy_from_slice(&word.to_be_bytes());
+        }
+        binary[72..80].copy_from_slice(format!("{name:<4}{sequence}").as_bytes());
+        CardArtifact {
+            id: CardId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from(format!("images/{name}{sequence}.png")),
+            processed_image_path: None,
+            layout_label: ArtifactKind::CardObject,
+            text_80col: None,
+            binary_80col: Some(binary),
+            metadata: CardMetadata::default(),
+        }
+    };
+    vec![card(0x0A, words, "0001"), card(0x0F, &[], "0002")]
+}
+
+#[test]
+fn test_listing_checked_against_scanned_decks() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut cards = deck("PROG", 0x0100, &[0xC400, 0x0010, 0xD010, 0x4C00, 0x0108]);
+    cards.extend(deck("OTHR", 0x4000, &[0x1234, 0x5678]));
+    scan_set::save_cards(dir.path(), &cards).unwrap();
+
+    let decks = build_object_decks(&scan_set::load_cards(dir.path()).unwrap());
+    assert_eq!(decks.len(), 2);
+
+    // Only PROG matches the listing; its last word disagrees
+    let rules = RuleSet::default();
+    let issues = rules.check_against_decks(LISTING, &decks);
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0].rule, "object.mismatch");
+    assert_eq!(issues[0].line_number, 3);
+
+    // A rule set can turn the comparison off
+    let rules = RuleSet::from_toml("disabled = [\"object.mismatch\"]").unwrap();
+    assert!(rules.check_against_decks(LISTING, &decks).is_empty());
+}
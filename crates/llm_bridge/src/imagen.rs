@@ -7,6 +7,9 @@ use anyhow::{Context, Result};
 use base64::{engine::general_purpose, Engine as _};
 use serde::{Deserialize, Serialize};
 
+/// Price of cleaning one image with Gemini 2.5 Flash Image (USD)
+pub const COST_PER_IMAGE_USD: f64 = 0.039;
+
 /// Configuration for Gemini API client
 #[derive(Debug, Clone)]
 pub struct GeminiConfig {
@@ -55,6 +58,11 @@ impl GeminiClient {
         Self::new(config)
     }
 
+    /// Gemini model the client uses
+    pub fn model(&self) -> &str {
+        &self.config.model
+    }
+
     /// Clean an image by removing greenbar lines and background artifacts
     ///
     /// # Arguments
@@ -125,6 +133,32 @@ impl GeminiClient {
 
         anyhow::bail!("No image in Gemini response")
     }
+
+    /// Check that the API key is accepted and the model is available
+    ///
+    /// Looks the model up instead of generating anything, so it costs
+    /// nothing.
+    pub async fn check_api_key(&self) -> Result<()> {
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}",
+            self.config.model
+        );
+
+        let response = self
+            .client
+            .get(&url)
+            .header("x-goog-api-key", &self.config.api_key)
+            .send()
+            .await
+            .context("Failed to send request to Gemini API")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            anyhow::bail!("Gemini API error ({}): {}", status, error_text);
+        }
+        Ok(())
+    }
 }
 
 /// Gemini API request structure
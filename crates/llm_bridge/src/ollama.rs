@@ -55,6 +55,35 @@ impl OllamaClient {
         let chat_response: ChatResponse = response.json().await?;
         Ok(chat_response)
     }
+
+    /// List the names of the models pulled into Ollama
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let url = format!("{}/api/tags", self.config.base_url);
+
+        let response = self.client.get(&url).send().await?;
+
+        if !response.status().is_success() {
+            anyhow::bail!("Ollama API error: {}", response.status());
+        }
+
+        let tags: TagsResponse = response.json().await?;
+        Ok(tags.models.into_iter().map(|model| model.name).collect())
+    }
+}
+
+/// Check whether a model is among the pulled models
+///
+/// A name without a tag refers to the `latest` tag, as in `ollama run`.
+pub fn has_model(models: &[String], name: &str) -> bool {
+    let with_tag = |name: &str| {
+        if name.contains(':') {
+            name.to_string()
+        } else {
+            format!("{}:latest", name)
+        }
+    };
+    let name = with_tag(name);
+    models.iter().any(|model| with_tag(model) == name)
 }
 
 /// Chat request to Ollama
@@ -83,6 +112,17 @@ pub struct ChatResponse {
     pub done: bool,
 }
 
+/// Model list response from Ollama
+#[derive(Debug, Clone, Deserialize)]
+struct TagsResponse {
+    models: Vec<ModelTag>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ModelTag {
+    name: String,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -94,6 +134,16 @@ mod tests {
         assert_eq!(config.timeout_secs, 120);
     }
 
+    #[test]
+    fn test_has_model() {
+        let models = vec!["llava:latest".to_string(), "qwen2.5vl:7b".to_string()];
+        assert!(has_model(&models, "llava"));
+        assert!(has_model(&models, "llava:latest"));
+        assert!(has_model(&models, "qwen2.5vl:7b"));
+        assert!(!has_model(&models, "qwen2.5vl"));
+        assert!(!has_model(&models, "llama3.2"));
+    }
+
     #[test]
     fn test_chat_request_serialization() {
         let request = ChatRequest {
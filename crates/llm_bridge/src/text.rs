@@ -3,6 +3,9 @@
 use crate::ollama::{ChatMessage, ChatRequest, OllamaClient};
 use anyhow::Result;
 
+/// Text model used when none is configured
+pub const DEFAULT_TEXT_MODEL: &str = "qwen2.5:3b";
+
 /// Text model for refining and analyzing extracted text
 pub struct TextModel {
     client: OllamaClient,
@@ -19,7 +22,7 @@ impl TextModel {
     pub fn default_model() -> Result<Self> {
         Ok(Self::new(
             OllamaClient::default_client()?,
-            "qwen2.5:3b".to_string(),
+            DEFAULT_TEXT_MODEL.to_string(),
         ))
     }
 
@@ -62,16 +65,69 @@ Return JSON only: {{"language": "...", "purpose": "...", "confidence": 0.0}}"#,
     }
 
     /// Suggest ordering for a collection of pages/cards
+    ///
+    /// Returns indices into `items` in suggested reading order. Every index
+    /// appears exactly once, whatever the model answered.
     pub async fn suggest_ordering(&self, items: &[OrderingItem]) -> Result<Vec<usize>> {
-        // TODO: Implement ordering suggestion
-        // Create prompt with first/last lines of each item
-        // Ask LLM to propose ordering
-        // Parse response
+        if items.len() < 2 {
+            return Ok((0..items.len()).collect());
+        }
+
+        let mut pages = String::new();
+        for (idx, item) in items.iter().enumerate() {
+            pages.push_str(&format!(
+                "Page {} ({}):\nFirst lines:\n{}\nLast lines:\n{}\n\n",
+                idx, item.id, item.first_lines, item.last_lines
+            ));
+        }
+        let prompt = format!(
+            r#"These pages of an IBM 1130 computer listing or card deck were scanned out of order.
+
+{}Put the pages in reading order. Use statements continuing across pages, sequence numbers in columns 73-80, ascending addresses and headers/footers.
+
+Return JSON only: the page numbers in reading order, e.g. [2, 0, 1]"#,
+            pages
+        );
+
+        let request = ChatRequest {
+            model: self.model_name.clone(),
+            messages: vec![ChatMessage {
+                role: "user".to_string(),
+                content: prompt,
+                images: None,
+            }],
+            stream: Some(false),
+        };
 
-        Ok((0..items.len()).collect())
+        let response = self.client.chat(request).await?;
+        parse_ordering(&response.message.content, items.len())
     }
 }
 
+/// Parse an ordering answer (a JSON list of indices, possibly wrapped in
+/// prose or a code fence) into a permutation of `0..len`
+///
+/// Out-of-range and repeated indices are dropped; indices the model left
+/// out are appended in their original order.
+fn parse_ordering(response: &str, len: usize) -> Result<Vec<usize>> {
+    let (Some(start), Some(end)) = (response.find('['), response.rfind(']')) else {
+        anyhow::bail!("No ordering in text model response: {}", response.trim());
+    };
+    let suggested: Vec<usize> = serde_json::from_str(&response[start..=end])
+        .map_err(|e| anyhow::anyhow!("Invalid ordering in text model response: {}", e))?;
+
+    let mut seen = vec![false; len];
+    let mut order = Vec::with_capacity(len);
+    for idx in suggested {
+        if idx < len && !seen[idx] {
+            seen[idx] = true;
+            order.push(idx);
+        }
+    }
+    order.extend((0..len).filter(|&idx| !seen[idx]));
+    Ok(order)
+}
+
 /// Result of text refinement
 pub struct RefinementResult {
     pub language: String,
@@ -91,6 +147,17 @@ pub struct OrderingItem {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_ordering() {
+        assert_eq!(parse_ordering("[2, 0, 1]", 3).unwrap(), vec![2, 0, 1]);
+        assert_eq!(
+            parse_ordering("```json\n[1, 1, 7, 0]\n```", 3).unwrap(),
+            vec![1, 0, 2]
+        );
+        assert!(parse_ordering("Page 2 comes first", 3).is_err());
+        assert!(parse_ordering("[\"a\"]", 3).is_err());
+    }
+
     #[test]
     fn test_text_model_creation() {
         let result = TextModel::default_model();
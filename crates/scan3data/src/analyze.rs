@@ -0,0 +1,1052 @@
+//! Phase 2: OCR, correction, classification and validation of a scan set
+//!
+//! Each artifact is preprocessed, OCRed, optionally corrected by a vision
+//! model and auto-fixed, classified, and validated. Configured external
+//! hooks run after OCR and after correction. Finished artifacts are
+//! checkpointed as they complete, along with the run's settings, so an
+//! interrupted run can be resumed where it stopped with the same settings;
+//! stage results are cached, so rerunning with the same settings reuses
+//! them. Artifacts with an imported human transcription are left
+//! as they are.
+//!
+//! A run has two phases: preprocessing and OCR of every artifact on a
+//! number of worker threads, then correction, classification and
+//! validation with a separate limit on model calls in flight, since a
+//! local vision model is usually the scarcer resource. A single artifact
+//! can be analyzed again with [`reprocess_artifact`].
+
+use crate::artifact::find_artifact;
+use anyhow::{bail, Result};
+use core_pipeline::classify::{classify_text, cross_check, Language};
+use core_pipeline::derived::{record_derived, DerivedStore};
+use core_pipeline::hooks::{self, HookSet, HookStage};
+use core_pipeline::image_loader::{encode_png, load_image, LoadOptions};
+use core_pipeline::keypunch::KeypunchModel;
+use core_pipeline::ocr::{extract_ocr_tesseract, ocr_cache_key, OcrOutput};
+use core_pipeline::preprocess::{
+    compute_file_hash, preprocess_image, preprocess_key, PreprocessOptions,
+};
+use core_pipeline::scan_set;
+use core_pipeline::stage_cache::{CacheKey, StageCache};
+use core_pipeline::storage;
+use core_pipeline::types::{ArtifactKind, PageArtifact, PageId};
+use core_pipeline::validate::{confidence_factor, RuleSet};
+use futures::stream::{self, StreamExt};
+use llm_bridge::text::DEFAULT_TEXT_MODEL;
+use llm_bridge::{OllamaClient, OllamaConfig, TextModel, VisionModel};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// Optional stages of an analysis run
+#[derive(Debug, Clone, Default)]
+pub struct AnalyzeOptions {
+    /// Ollama server the models run on
+    pub ollama: OllamaConfig,
+    /// Cross-check the heuristic classification with the text model
+    pub use_llm: bool,
+    /// Text model for the cross-check (qwen2.5:3b if unset)
+    pub text_model: Option<String>,
+    /// Correct OCR text with this Ollama vision model
+    pub vision_model: Option<String>,
+    /// Downscale images sent to the vision model to this many pixels on
+    /// the longest side
+    pub vision_max_dimension: Option<u32>,
+    /// Auto-fix OCR confusions with at least this confidence
+    pub autofix_threshold: Option<f32>,
+    /// External commands run after OCR and after correction
+    pub hooks: HookSet,
+    /// Artifacts preprocessed and OCRed at the same time (1 if 0)
+    pub jobs: usize,
+    /// Vision and text model calls in flight at the same time (1 if 0)
+    pub model_jobs: usize,
+    /// Start from the Gemini-cleaned image of artifacts that have one
+    pub prefer_cleaned: bool,
+    /// Preprocessing steps before OCR
+    pub preprocess: PreprocessOptions,
+    /// Validation rules (with the scan set's keypunch if they name none)
+    pub rules: RuleSet,
+    /// Continue an interrupted run instead of starting over; refused if
+    /// it used different settings
+    pub resume: bool,
+}
+
+/// Options that change what a run produces, recorded with its
+/// checkpoints so that a resumed run finishes with the same ones
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct RunSettings {
+    use_llm: bool,
+    text_model: Option<String>,
+    vision_model: Option<String>,
+    vision_max_dimension: Option<u32>,
+    autofix_threshold: Option<f32>,
+    hooks: HookSet,
+    prefer_cleaned: bool,
+    preprocess: PreprocessOptions,
+    rules: RuleSet,
+}
+
+impl RunSettings {
+    fn of(options: &AnalyzeOptions) -> Self {
+        Self {
+            use_llm: options.use_llm,
+            text_model: options.text_model.clone(),
+            vision_model: options.vision_model.clone(),
+            vision_max_dimension: options.vision_max_dimension,
+            autofix_threshold: options.autofix_threshold,
+            hooks: options.hooks.clone(),
+            prefer_cleaned: options.prefer_cleaned,
+            preprocess: options.preprocess,
+            rules: options.rules.clone(),
+        }
+    }
+}
+
+/// Phases of a run, as reported to the progress callback
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnalyzePhase {
+    /// Preprocessing and OCR
+    Ocr,
+    /// Correction, classification and validation
+    Correct,
+}
+
+impl AnalyzePhase {
+    /// Phase name for progress output
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ocr => "OCR",
+            Self::Correct => "Correct",
+        }
+    }
+}
+
+/// Progress of a run, as reported to the progress callback after each
+/// artifact finishes a phase
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AnalyzeProgress {
+    /// Phase the artifact finished
+    pub phase: AnalyzePhase,
+    /// Artifacts finished in the phase so far
+    pub done: usize,
+    /// Artifacts to analyze
+    pub total: usize,
+    /// The artifact that finished
+    pub artifact: PageId,
+    /// Its position in ingest order
+    pub index: usize,
+    /// Stages that failed for the artifact, as recorded in its notes
+    /// (known once it is corrected)
+    pub errors: Vec<String>,
+    /// What analysis made of the artifact, once it is corrected
+    pub result: Option<ArtifactResult>,
+}
+
+/// What analysis made of one artifact
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArtifactResult {
+    /// Classification
+    pub kind: ArtifactKind,
+    /// Confidence of the classification (0.0-1.0)
+    pub confidence: f32,
+    /// Characters of text found
+    pub text_len: usize,
+    /// Validation issues found
+    pub validation_issues: usize,
+}
+
+impl ArtifactResult {
+    /// Result of an analyzed artifact
+    pub fn of_artifact(artifact: &PageArtifact) -> Self {
+        Self {
+            kind: artifact.layout_label,
+            confidence: artifact.metadata.confidence,
+            text_len: artifact.content_text.as_ref().map_or(0, |t| t.len()),
+            validation_issues: artifact.metadata.validation_issues.len(),
+        }
+    }
+}
+
+/// Outcome of an analysis run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnalyzeSummary {
+    /// Number of artifacts in the scan set
+    pub artifacts: usize,
+    /// Artifacts analyzed by an earlier, interrupted run and skipped
+    /// (with `resume`)
+    pub resumed: usize,
+    /// Artifacts with a human transcription, skipped
+    pub transcribed: usize,
+    /// Stage results reused from the stage cache
+    pub cached_stages: usize,
+    /// Artifacts with OCR text
+    pub with_text: usize,
+    /// Average length of the OCR text of artifacts that have text
+    pub average_text_len: f64,
+    /// Validation issues over all artifacts
+    pub validation_issues: usize,
+}
+
+/// Analyze a scan set using OCR and optional LLM classification
+///
+/// `progress` is called as each artifact finishes a phase, with the number
+/// finished in it so far and the number to analyze. Without `resume`
+/// every artifact is analyzed again; with it, those finished by an
+/// earlier, interrupted run are skipped and not counted, provided that run
+/// had the same settings. Failures of a single
+/// artifact's OCR, vision correction, hooks or LLM classification are
+/// logged and recorded in the artifact's notes instead of failing the run.
+pub async fn analyze_scan_set(
+    scan_set_path: &Path,
+    options: &AnalyzeOptions,
+    progress: &mut (dyn FnMut(&AnalyzeProgress) + Send),
+) -> Result<AnalyzeSummary> {
+    if !scan_set_path.exists() {
+        bail!(
+            "Scan set directory does not exist: {}",
+            scan_set_path.display()
+        );
+    }
+
+    let mut store = storage::open(scan_set_path)?;
+    let mut manifest = store.load_manifest()?;
+    let mut artifacts = store.load_artifacts()?;
+
+    // Resume after an interrupted run: skip artifacts it already saved,
+    // but only if it would have produced the same results
+    let settings = RunSettings::of(options);
+    let already_analyzed = if options.resume {
+        store.checkpointed()?
+    } else {
+        HashSet::new()
+    };
+    if already_analyzed.is_empty() {
+        store.begin_run(&serde_json::to_value(&settings)?)?;
+    } else {
+        let recorded = store
+            .run_settings()?
+            .and_then(|value| serde_json::from_value::<RunSettings>(value).ok());
+        if recorded.as_ref() != Some(&settings) {
+            bail!(
+                "The interrupted analysis of {} used different settings; \
+                 analyze without --resume to start over",
+                scan_set_path.display()
+            );
+        }
+    }
+
+    // Later stages look up the stored images and OCR results by the steps
+    if manifest.preprocess != options.preprocess {
+        manifest.preprocess = options.preprocess;
+        store.save_manifest(&manifest)?;
+    }
+
+    let run = Run::new(scan_set_path, options, manifest.keypunch.model)?;
+
+    let mut pending: Vec<&mut PageArtifact> = artifacts
+        .iter_mut()
+        .filter(|artifact| {
+            !already_analyzed.contains(&artifact.id) && !artifact.metadata.human_transcribed
+        })
+        .collect();
+    let total = pending.len();
+    let recognized = run.recognize_all(&mut pending, options.jobs.max(1), progress)?;
+
+    // Correct and classify, persisting each finished artifact so a crash
+    // loses no progress
+    let mut cached_stages = 0;
+    let run = &run;
+    let finishing: Vec<_> = pending
+        .into_iter()
+        .zip(recognized)
+        .map(|(artifact, recognized)| {
+            let notes_before = artifact.metadata.notes.len();
+            async move {
+                let (artifact, cached) = run.finish(artifact, recognized).await?;
+                Ok::<_, anyhow::Error>((artifact, cached, notes_before))
+            }
+        })
+        .collect();
+    let mut finished = stream::iter(finishing).buffer_unordered(options.model_jobs.max(1));
+    let mut done = 0;
+    while let Some(result) = finished.next().await {
+        let (artifact, cached, notes_before) = result?;
+        cached_stages += cached;
+        store.save_artifact(artifact)?;
+        done += 1;
+        progress(&AnalyzeProgress {
+            phase: AnalyzePhase::Correct,
+            done,
+            total,
+            artifact: artifact.id,
+            index: artifact.metadata.ingest_index,
+            errors: stage_failures(&artifact.metadata.notes[notes_before..]),
+            result: Some(ArtifactResult::of_artifact(artifact)),
+        });
+    }
+    drop(finished);
+
+    // Save updated artifacts, ending the run's checkpoints
+    store.save_artifacts(&artifacts)?;
+
+    let with_text = artifacts
+        .iter()
+        .filter(|a| a.content_text.is_some())
+        .count();
+    let total_text_len: usize = artifacts
+        .iter()
+        .filter_map(|a| a.content_text.as_ref())
+        .map(|t| t.len())
+        .sum();
+
+    Ok(AnalyzeSummary {
+        artifacts: artifacts.len(),
+        resumed: already_analyzed.len(),
+        transcribed: artifacts
+            .iter()
+            .filter(|a| a.metadata.human_transcribed)
+            .count(),
+        cached_stages,
+        with_text,
+        average_text_len: total_text_len as f64 / with_text.max(1) as f64,
+        validation_issues: artifacts
+            .iter()
+            .map(|a| a.metadata.validation_issues.len())
+            .sum(),
+    })
+}
+
+/// Analyze one artifact (by ID or unique ID prefix) again
+///
+/// Runs the same stages as [`analyze_scan_set`] on just that artifact,
+/// asking the vision model again rather than reusing a cached correction.
+/// Preprocessing and OCR are deterministic and still come from the cache.
+/// If an interrupted analysis left checkpoints, the result is saved as one
+/// so resuming it keeps the new result. Returns the updated artifact.
+pub async fn reprocess_artifact(
+    scan_set_path: &Path,
+    id: &str,
+    options: &AnalyzeOptions,
+) -> Result<PageArtifact> {
+    let (mut manifest, mut artifacts) = scan_set::load(scan_set_path)?;
+    let mut store = storage::open(scan_set_path)?;
+    let interrupted = !store.checkpointed()?.is_empty();
+    if manifest.preprocess != options.preprocess {
+        manifest.preprocess = options.preprocess;
+        store.save_manifest(&manifest)?;
+    }
+    let idx = find_artifact(&artifacts, id)?;
+    if artifacts[idx].metadata.human_transcribed {
+        bail!("Artifact {} has a human transcription", artifacts[idx].id.0);
+    }
+
+    let mut run = Run::new(scan_set_path, options, manifest.keypunch.model)?;
+    run.fresh_corrections = true;
+    let artifact = &mut artifacts[idx];
+    let recognized = run.recognize(artifact)?;
+    run.finish(artifact, recognized).await?;
+
+    if interrupted {
+        store.save_artifact(&artifacts[idx])?;
+    } else {
+        store.save_artifacts(&artifacts)?;
+    }
+    Ok(artifacts.swap_remove(idx))
+}
+
+/// Settings, stores and models shared by the artifacts of a run
+pub(crate) struct Run<'a> {
+    scan_set_path: &'a Path,
+    options: &'a AnalyzeOptions,
+    keypunch: KeypunchModel,
+    validation_rules: RuleSet,
+    derived_store: DerivedStore,
+    stage_cache: StageCache,
+    text_model: Option<TextModel>,
+    vision: Option<VisionModel>,
+    /// Ask the vision model again instead of reusing a cached correction
+    fresh_corrections: bool,
+}
+
+impl<'a> Run<'a> {
+    /// Set up the stores and models of a run
+    pub(crate) fn new(
+        scan_set_path: &'a Path,
+        options: &'a AnalyzeOptions,
+        keypunch: KeypunchModel,
+    ) -> Result<Self> {
+        // Text model cross-checks the heuristic classification
+        let text_model = if options.use_llm {
+            let model = options.text_model.as_deref().unwrap_or(DEFAULT_TEXT_MODEL);
+            Some(TextModel::new(
+                OllamaClient::new(options.ollama.clone())?,
+                model.to_string(),
+            ))
+        } else {
+            None
+        };
+
+        let vision = match options.vision_model {
+            Some(ref model) => {
+                let client = OllamaClient::new(options.ollama.clone())?;
+                Some(VisionModel::new(client, model.clone()))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            scan_set_path,
+            options,
+            keypunch,
+            validation_rules: RuleSet {
+                keypunch: Some(options.rules.keypunch.unwrap_or(keypunch)),
+                ..options.rules.clone()
+            },
+            derived_store: DerivedStore::new(scan_set_path),
+            stage_cache: StageCache::new(scan_set_path),
+            text_model,
+            vision,
+            fresh_corrections: false,
+        })
+    }
+}
+
+/// Outcome of preprocessing and OCR of an artifact
+pub(crate) struct Recognized {
+    /// Image the artifact was analyzed from (raw or cleaned)
+    pub(crate) image_path: PathBuf,
+    /// Hash of that image
+    pub(crate) source_hash: String,
+    /// OCR text and word boxes, and whether they came from the cache
+    pub(crate) ocr: core_pipeline::Result<(OcrOutput, bool)>,
+}
+
+impl Run<'_> {
+    /// Preprocess and OCR artifacts on `jobs` worker threads
+    ///
+    /// Returns the outcomes in the order of `artifacts`. A failure other
+    /// than of OCR itself stops the workers and fails the run.
+    pub(crate) fn recognize_all(
+        &self,
+        artifacts: &mut [&mut PageArtifact],
+        jobs: usize,
+        progress: &mut (dyn FnMut(&AnalyzeProgress) + Send),
+    ) -> Result<Vec<Recognized>> {
+        let total = artifacts.len();
+        let queue = Mutex::new(artifacts.iter_mut().enumerate());
+        let progress = Mutex::new((0, progress));
+        let failed = AtomicBool::new(false);
+
+        let worker = || -> Result<Vec<(usize, Recognized)>> {
+            let mut recognized = Vec::new();
+            while !failed.load(Ordering::Relaxed) {
+                let Some((idx, artifact)) = queue.lock().expect("queue lock").next() else {
+                    break;
+                };
+                match self.recognize(artifact) {
+                    Ok(result) => recognized.push((idx, result)),
+                    Err(e) => {
+                        failed.store(true, Ordering::Relaxed);
+                        return Err(e);
+                    }
+                }
+                let mut progress = progress.lock().expect("progress lock");
+                progress.0 += 1;
+                let done = progress.0;
+                (progress.1)(&AnalyzeProgress {
+                    phase: AnalyzePhase::Ocr,
+                    done,
+                    total,
+                    artifact: artifact.id,
+                    index: artifact.metadata.ingest_index,
+                    errors: Vec::new(),
+                    result: None,
+                });
+            }
+            Ok(recognized)
+        };
+        let results: Vec<_> = thread::scope(|scope| {
+            let workers: Vec<_> = (0..jobs.min(total)).map(|_| scope.spawn(worker)).collect();
+            workers
+                .into_iter()
+                .map(|w| w.join().expect("OCR worker panicked"))
+                .collect()
+        });
+
+        let mut ordered: Vec<Option<Recognized>> = (0..total).map(|_| None).collect();
+        for result in results {
+            for (idx, recognized) in result? {
+                ordered[idx] = Some(recognized);
+            }
+        }
+        Ok(ordered
+            .into_iter()
+            .map(|r| r.expect("every artifact recognized"))
+            .collect())
+    }
+
+    /// Preprocess and OCR one artifact
+    fn recognize(&self, artifact: &mut PageArtifact) -> Result<Recognized> {
+        // This run's notes and issues replace those of the run before
+        artifact.metadata.notes.retain(|note| !is_run_note(note));
+        artifact.metadata.validation_issues.clear();
+
+        // Start from the cleaned image if preferred; it is a different
+        // image, so its results are cached under its own hash
+        let cleaned = artifact
+            .metadata
+            .cleaned_image_path
+            .as_ref()
+            .filter(|_| self.options.prefer_cleaned);
+        let (image_path, source_hash) = match cleaned {
+            Some(cleaned) => {
+                let path = self.scan_set_path.join(cleaned);
+                let hash = compute_file_hash(&path)?;
+                (path, hash)
+            }
+            None => {
+                let path = self.scan_set_path.join(&artifact.raw_image_path);
+                let hash = if artifact.metadata.content_hash.is_empty() {
+                    compute_file_hash(&path)?
+                } else {
+                    artifact.metadata.content_hash.clone()
+                };
+                (path, hash)
+            }
+        };
+
+        // Reuse the preprocessed image from an earlier run if present
+        let key = preprocess_key(&source_hash, &self.options.preprocess)?;
+        let preprocess = || -> core_pipeline::Result<image::GrayImage> {
+            // Load the raw image, keeping only the preprocessed copy in memory
+            let img = load_image(&image_path, &LoadOptions::full())?;
+            let preprocessed = preprocess_image(&img, &self.options.preprocess)?;
+            drop(img);
+            self.derived_store
+                .store(&key, &image::DynamicImage::ImageLuma8(preprocessed.clone()))?;
+            Ok(preprocessed)
+        };
+        let mut preprocessed = None;
+        if !self.derived_store.contains(&key) {
+            preprocessed = Some(preprocess()?);
+        }
+
+        // Update artifact with processed image path
+        let reference = DerivedStore::reference(&key);
+        artifact.processed_image_path = Some(reference.path.clone());
+        record_derived(artifact, reference);
+
+        // Run OCR, reusing the text and word boxes from an earlier run with
+        // the same image and settings
+        let ocr_key = ocr_cache_key(&key, self.keypunch)?;
+        let ocr = self.stage_cache.get_or_compute(&ocr_key, || {
+            let image = match preprocessed.take() {
+                Some(image) => image,
+                None => match self.derived_store.load(&key)? {
+                    Some(cached) => cached.to_luma8(),
+                    None => preprocess()?,
+                },
+            };
+            extract_ocr_tesseract(&image, self.keypunch)
+        });
+        Ok(Recognized {
+            image_path,
+            source_hash,
+            ocr,
+        })
+    }
+
+    /// Correct OCR text with a vision model, reusing the correction of an
+    /// earlier run with the same image, text and settings
+    ///
+    /// Returns the corrected text and whether it came from the cache.
+    pub(crate) async fn correct(
+        &self,
+        vision: &VisionModel,
+        vision_model: &str,
+        image_path: &Path,
+        source_hash: &str,
+        text: &str,
+    ) -> Result<(String, bool)> {
+        let vision_max_dimension = self.options.vision_max_dimension;
+        let vision_key = CacheKey::new(
+            "vision",
+            &(source_hash, text),
+            &(vision_model, vision_max_dimension),
+        )?;
+        if !self.fresh_corrections {
+            if let Some(corrected) = self.stage_cache.get::<String>(&vision_key)? {
+                return Ok((corrected, true));
+            }
+        }
+
+        // Load original image bytes for vision model, downscaled if requested
+        let image_bytes = match vision_max_dimension {
+            Some(max) => encode_png(&load_image(image_path, &LoadOptions::downscaled(max))?)?,
+            None => fs::read(image_path)?,
+        };
+        let corrected = vision.correct_ocr_with_layout(&image_bytes, text).await?;
+        self.stage_cache.put(&vision_key, &corrected)?;
+        Ok((corrected, false))
+    }
+
+    /// Correct, classify and validate a recognized artifact
+    ///
+    /// Returns the artifact with the number of stage results reused from
+    /// the cache.
+    async fn finish<'b>(
+        &self,
+        artifact: &'b mut PageArtifact,
+        recognized: Recognized,
+    ) -> Result<(&'b mut PageArtifact, usize)> {
+        let options = self.options;
+        let image_path = recognized.image_path;
+        let mut cached_stages = 0;
+        match recognized.ocr {
+            Ok((OcrOutput { text, .. }, cached)) => {
+                cached_stages += usize::from(cached);
+
+                // Let post-OCR hooks rework the raw text before correction
+                artifact.content_text = Some(text);
+                run_hooks(
+                    &options.hooks,
+                    HookStage::PostOcr,
+                    self.scan_set_path,
+                    artifact,
+                );
+                let text = artifact.content_text.clone().unwrap_or_default();
+
+                // If vision correction is enabled, correct the OCR text
+                if let (Some(vision), Some(vision_model)) = (&self.vision, &options.vision_model) {
+                    let corrected = self
+                        .correct(
+                            vision,
+                            vision_model,
+                            &image_path,
+                            &recognized.source_hash,
+                            &text,
+                        )
+                        .await
+                        .map(|(corrected, cached)| {
+                            cached_stages += usize::from(cached);
+                            corrected
+                        });
+
+                    match corrected {
+                        Ok(corrected_text) => {
+                            artifact.content_text = Some(corrected_text);
+                            artifact
+                                .metadata
+                                .notes
+                                .push("Vision-corrected OCR".to_string());
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                "Vision correction failed for {}: {}",
+                                artifact.raw_image_path.display(),
+                                e
+                            );
+                            // Fall back to raw OCR text
+                            artifact.content_text = Some(text);
+                            artifact
+                                .metadata
+                                .notes
+                                .push(format!("Vision correction failed: {}", e));
+                        }
+                    }
+                } else {
+                    artifact.content_text = Some(text);
+                }
+            }
+            Err(e) => {
+                // Log OCR error but continue processing
+                tracing::warn!(
+                    "OCR failed for {}: {}",
+                    artifact.raw_image_path.display(),
+                    e
+                );
+                artifact.metadata.notes.push(format!("OCR failed: {}", e));
+            }
+        }
+
+        // Auto-fix common OCR confusions, recording each change
+        if let (Some(threshold), Some(text)) = (options.autofix_threshold, &artifact.content_text) {
+            let mut classification = classify_text(text);
+            if artifact.metadata.manual_classification {
+                classification.kind = artifact.layout_label;
+            }
+            let result = core_pipeline::autofix::autofix(text, &classification, threshold);
+            if !result.revisions.is_empty() {
+                artifact.metadata.notes.push(format!(
+                    "Auto-fixed {} OCR confusion(s)",
+                    result.revisions.len()
+                ));
+                artifact.content_text = Some(result.text);
+                artifact.metadata.revisions.extend(result.revisions);
+            }
+        }
+
+        run_hooks(
+            &options.hooks,
+            HookStage::PostCorrect,
+            self.scan_set_path,
+            artifact,
+        );
+
+        // Heuristic classification (non-LLM baseline)
+        let Some(ref text) = artifact.content_text else {
+            return Ok((artifact, cached_stages));
+        };
+        let classification = classify_text(text);
+        // A classification set by hand (`relabel`) is kept, with the
+        // confidence it had before an earlier run's validation lowered it
+        let base_confidence = if artifact.metadata.manual_classification {
+            let metadata = &artifact.metadata;
+            metadata.base_confidence.unwrap_or(metadata.confidence)
+        } else {
+            artifact.layout_label = classification.kind;
+            classification.confidence
+        };
+        artifact.metadata.notes.push(format!(
+            "Heuristic language: {}",
+            classification.language.as_str()
+        ));
+
+        // Cross-check against the LLM classification
+        if let Some(ref model) = self.text_model {
+            match model.refine_and_classify(text).await {
+                Ok(result) => {
+                    if cross_check(classification.language, &result.language) == Some(false) {
+                        artifact.metadata.notes.push(format!(
+                            "LLM disagrees: heuristic {}, LLM {}",
+                            classification.language.as_str(),
+                            result.language
+                        ));
+                    }
+                }
+                Err(e) => {
+                    artifact
+                        .metadata
+                        .notes
+                        .push(format!("LLM classification failed: {}", e));
+                }
+            }
+        }
+
+        // Validate the text, statement rules only for FORTRAN as in
+        // `validate`, and lower confidence in proportion to the issues
+        let rules = &self.validation_rules;
+        let mut issues = if classification.language == Language::Fortran {
+            rules.validate_fortran(text)
+        } else {
+            rules.check_card(text)
+        };
+        if artifact.layout_label == ArtifactKind::ListingObject {
+            issues.extend(rules.check_object_listing(text));
+            issues.sort_by_key(|issue| issue.line_number);
+        }
+        let metadata = &mut artifact.metadata;
+        metadata.confidence = base_confidence * confidence_factor(&issues, text.lines().count());
+        metadata.base_confidence = Some(base_confidence);
+        metadata.validation_issues = issues;
+
+        Ok((artifact, cached_stages))
+    }
+}
+
+/// Starts of the notes an analysis run adds to an artifact
+const RUN_NOTE_PREFIXES: &[&str] = &[
+    "OCR failed: ",
+    "Vision-corrected OCR",
+    "Vision correction failed: ",
+    "Auto-fixed ",
+    "Heuristic language: ",
+    "LLM disagrees: ",
+    "LLM classification failed: ",
+    "post-ocr hook failed: ",
+    "post-correct hook failed: ",
+    hooks::NOTE_PREFIX,
+];
+
+/// Whether an analysis run added the note
+fn is_run_note(note: &str) -> bool {
+    RUN_NOTE_PREFIXES
+        .iter()
+        .any(|prefix| note.starts_with(prefix))
+}
+
+/// Notes recording a failed stage (`OCR failed: ...`, `post-ocr hook
+/// failed: ...`)
+fn stage_failures(notes: &[String]) -> Vec<String> {
+    notes
+        .iter()
+        .filter(|note| note.contains(" failed: "))
+        .cloned()
+        .collect()
+}
+
+/// Run the hooks of a stage, recording a failure in the artifact's notes
+fn run_hooks(hooks: &HookSet, stage: HookStage, scan_set_path: &Path, artifact: &mut PageArtifact) {
+    if let Err(e) = hooks.apply(stage, scan_set_path, artifact) {
+        tracing::warn!(
+            "{} hook failed for {}: {}",
+            stage.as_str(),
+            artifact.raw_image_path.display(),
+            e
+        );
+        artifact
+            .metadata
+            .notes
+            .push(format!("{} hook failed: {}", stage.as_str(), e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_pipeline::storage::StorageBackend;
+    use core_pipeline::types::PageMetadata;
+    use core_pipeline::validate::{Severity, ValidationIssue};
+    use futures::executor::block_on;
+
+    /// Scan set of `pages` distinct blank pages
+    fn ingested(dir: &Path, pages: u8) -> PathBuf {
+        let scans = dir.join("scans");
+        fs::create_dir_all(&scans).unwrap();
+        for page in 0..pages {
+            image::GrayImage::from_pixel(40, 30, image::Luma([page * 20]))
+                .save(scans.join(format!("p{page}.png")))
+                .unwrap();
+        }
+        let output = dir.join("set");
+        crate::ingest_scan_set(
+            &scans,
+            &output,
+            KeypunchModel::default(),
+            &crate::IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+        output
+    }
+
+    #[test]
+    fn test_parallel_phases_report_every_artifact_once() {
+        let data = tempfile::tempdir().unwrap();
+        let dir = ingested(data.path(), 5);
+        let options = AnalyzeOptions {
+            jobs: 3,
+            model_jobs: 2,
+            ..AnalyzeOptions::default()
+        };
+        let events = Mutex::new(Vec::new());
+        let summary = block_on(analyze_scan_set(&dir, &options, &mut |p| {
+            events
+                .lock()
+                .unwrap()
+                .push((p.phase, p.done, p.total, p.index));
+        }))
+        .unwrap();
+        assert_eq!(summary.artifacts, 5);
+
+        let events = events.into_inner().unwrap();
+        for phase in [AnalyzePhase::Ocr, AnalyzePhase::Correct] {
+            let reported: Vec<_> = events.iter().filter(|e| e.0 == phase).collect();
+            // Counts go up by one, whichever artifact finishes first
+            let done: Vec<usize> = reported.iter().map(|e| e.1).collect();
+            assert_eq!(done, [1, 2, 3, 4, 5]);
+            assert!(reported.iter().all(|e| e.2 == 5));
+            let mut indexes: Vec<usize> = reported.iter().map(|e| e.3).collect();
+            indexes.sort();
+            assert_eq!(indexes, [0, 1, 2, 3, 4]);
+        }
+        // Every OCR result reached its own artifact
+        let artifacts = scan_set::load_artifacts(&dir).unwrap();
+        for artifact in &artifacts {
+            let processed = artifact.processed_image_path.as_ref().unwrap();
+            let stem = artifact
+                .raw_image_path
+                .file_stem()
+                .unwrap()
+                .to_str()
+                .unwrap();
+            assert!(processed.starts_with(Path::new("derived").join(stem)));
+        }
+    }
+
+    #[test]
+    fn test_reprocess_one_artifact() {
+        let data = tempfile::tempdir().unwrap();
+        let dir = ingested(data.path(), 2);
+        let artifacts = scan_set::load_artifacts(&dir).unwrap();
+        let prefix = &artifacts[1].id.0.to_string()[..8];
+        let options = AnalyzeOptions::default();
+
+        let reprocessed = block_on(reprocess_artifact(&dir, prefix, &options)).unwrap();
+        assert_eq!(reprocessed.id, artifacts[1].id);
+        // Only that artifact was analyzed and saved
+        let saved = scan_set::load_artifacts(&dir).unwrap();
+        assert!(saved[0].processed_image_path.is_none());
+        assert_eq!(
+            saved[1].processed_image_path,
+            reprocessed.processed_image_path
+        );
+        assert!(saved[1].processed_image_path.is_some());
+
+        assert!(block_on(reprocess_artifact(&dir, "no-such-id", &options)).is_err());
+        let mut transcribed = saved;
+        transcribed[0].metadata.human_transcribed = true;
+        scan_set::save_artifacts(&dir, &transcribed).unwrap();
+        let id = transcribed[0].id.0.to_string();
+        let err = block_on(reprocess_artifact(&dir, &id, &options)).unwrap_err();
+        assert!(err.to_string().contains("human transcription"));
+    }
+
+    #[test]
+    fn test_rerun_replaces_run_notes() {
+        let data = tempfile::tempdir().unwrap();
+        let dir = ingested(data.path(), 1);
+        let mut artifacts = scan_set::load_artifacts(&dir).unwrap();
+        artifacts[0].metadata.notes = vec![
+            "Checked against the printout".to_string(),
+            "Vision-corrected OCR".to_string(),
+            "Hook post-ocr: label 10 unused".to_string(),
+        ];
+        scan_set::save_artifacts(&dir, &artifacts).unwrap();
+
+        let options = AnalyzeOptions::default();
+        block_on(analyze_scan_set(&dir, &options, &mut |_| {})).unwrap();
+        let first = scan_set::load_artifacts(&dir).unwrap()[0]
+            .metadata
+            .notes
+            .clone();
+        assert_eq!(first[0], "Checked against the printout");
+        assert!(!first
+            .iter()
+            .any(|note| note == "Vision-corrected OCR" || note.starts_with("Hook ")));
+        // OCR's outcome, whether or not Tesseract is installed
+        assert!(first.len() > 1 && first[1..].iter().all(|note| is_run_note(note)));
+
+        block_on(analyze_scan_set(&dir, &options, &mut |_| {})).unwrap();
+        let second = scan_set::load_artifacts(&dir).unwrap()[0]
+            .metadata
+            .notes
+            .clone();
+        assert_eq!(second, first);
+        block_on(reprocess_artifact(
+            &dir,
+            &artifacts[0].id.0.to_string(),
+            &options,
+        ))
+        .unwrap();
+        let third = scan_set::load_artifacts(&dir).unwrap()[0]
+            .metadata
+            .notes
+            .clone();
+        assert_eq!(third, first);
+    }
+
+    #[test]
+    fn test_rerun_does_not_compound_confidence() {
+        let data = tempfile::tempdir().unwrap();
+        let dir = ingested(data.path(), 1);
+        let mut artifacts = scan_set::load_artifacts(&dir).unwrap();
+        // Relabeled, with text kept if OCR is unavailable; the line is too
+        // long for a card
+        let metadata = &mut artifacts[0].metadata;
+        metadata.manual_classification = true;
+        metadata.confidence = 1.0;
+        metadata.validation_issues = vec![ValidationIssue {
+            rule: "stale.rule".to_string(),
+            line_number: 1,
+            column: None,
+            severity: Severity::Warning,
+            description: "From an earlier run".to_string(),
+            excerpt: String::new(),
+            suggestion: None,
+        }];
+        artifacts[0].content_text = Some(format!("      DATA {}", "A".repeat(80)));
+        scan_set::save_artifacts(&dir, &artifacts).unwrap();
+
+        let options = AnalyzeOptions::default();
+        let analyze = || {
+            block_on(analyze_scan_set(&dir, &options, &mut |_| {})).unwrap();
+            scan_set::load_artifacts(&dir).unwrap().remove(0).metadata
+        };
+        let first = analyze();
+        assert_eq!(first.base_confidence, Some(1.0));
+        assert!(!first
+            .validation_issues
+            .iter()
+            .any(|issue| issue.rule == "stale.rule"));
+        let second = analyze();
+        assert_eq!(second.confidence, first.confidence);
+        assert_eq!(second.validation_issues, first.validation_issues);
+    }
+
+    #[test]
+    fn test_resume_needs_same_settings() {
+        let data = tempfile::tempdir().unwrap();
+        let (dir, manifest) = crate::create_scan_set(
+            data.path(),
+            "Box 1",
+            KeypunchModel::default(),
+            StorageBackend::Json,
+        )
+        .unwrap();
+        // Transcribed, so analysis needs no OCR
+        let artifact = PageArtifact {
+            id: PageId::new(),
+            scan_set: manifest.scan_set_id,
+            raw_image_path: PathBuf::from("images/a.png"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::Unknown,
+            content_text: Some("      X = 1".to_string()),
+            metadata: PageMetadata {
+                human_transcribed: true,
+                ..PageMetadata::default()
+            },
+        };
+        scan_set::save_artifacts(&dir, std::slice::from_ref(&artifact)).unwrap();
+
+        let interrupted = AnalyzeOptions {
+            autofix_threshold: Some(0.9),
+            ..AnalyzeOptions::default()
+        };
+        let interrupt = || {
+            let mut store = storage::open(&dir).unwrap();
+            let settings = serde_json::to_value(RunSettings::of(&interrupted)).unwrap();
+            store.begin_run(&settings).unwrap();
+            store.save_artifact(&artifact).unwrap();
+        };
+        let analyze =
+            |options: &AnalyzeOptions| block_on(analyze_scan_set(&dir, options, &mut |_| {}));
+
+        // Starting over is the default
+        interrupt();
+        assert_eq!(analyze(&AnalyzeOptions::default()).unwrap().resumed, 0);
+
+        interrupt();
+        let resume = AnalyzeOptions {
+            resume: true,
+            ..AnalyzeOptions::default()
+        };
+        let err = analyze(&resume).unwrap_err();
+        assert!(err.to_string().contains("without --resume"));
+        let resume = AnalyzeOptions {
+            resume: true,
+            ..interrupted.clone()
+        };
+        assert_eq!(analyze(&resume).unwrap().resumed, 1);
+        assert!(storage::open(&dir)
+            .unwrap()
+            .checkpointed()
+            .unwrap()
+            .is_empty());
+    }
+}
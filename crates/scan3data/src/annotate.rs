@@ -0,0 +1,100 @@
+//! Sidecar files next to the scans of a scan set
+//!
+//! Writes a sidecar (XMP or JSON, see [`core_pipeline::export::sidecar`])
+//! next to the raw image (`images/`) of every analyzed artifact, so photo
+//! and asset management tools pointed at the scan set show what each scan
+//! contains. Kept originals get none, since a PDF original holds several
+//! pages. Sidecars are rewritten from the artifacts on every run;
+//! artifacts without text yet get none.
+
+use anyhow::{Context, Result};
+use core_pipeline::export::sidecar::{Sidecar, SidecarFormat};
+use core_pipeline::scan_set;
+use std::fs;
+use std::path::Path;
+
+/// Result of writing sidecars
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AnnotateSummary {
+    /// Artifacts annotated
+    pub annotated: usize,
+    /// Artifacts without text (not analyzed yet)
+    pub unanalyzed: usize,
+}
+
+/// Write a sidecar next to the raw image of every analyzed artifact of a
+/// scan set
+///
+/// `progress` is called with the number of artifacts done and the total.
+pub fn annotate_scan_set(
+    scan_set_dir: &Path,
+    format: SidecarFormat,
+    progress: &mut (dyn FnMut(usize, usize) + Send),
+) -> Result<AnnotateSummary> {
+    let (_, artifacts) = scan_set::load(scan_set_dir)?;
+    let mut summary = AnnotateSummary::default();
+
+    let total = artifacts.len();
+    for (done, artifact) in artifacts.iter().enumerate() {
+        if artifact.content_text.is_none() {
+            summary.unanalyzed += 1;
+        } else {
+            let contents = Sidecar::of_artifact(artifact).contents(format)?;
+            let path = scan_set_dir.join(format.sidecar_path(&artifact.raw_image_path));
+            fs::write(&path, contents)
+                .with_context(|| format!("Failed to write sidecar: {}", path.display()))?;
+            summary.annotated += 1;
+        }
+        progress(done + 1, total);
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{ingest_scan_set, IngestOptions};
+    use core_pipeline::keypunch::KeypunchModel;
+    use core_pipeline::types::ArtifactKind;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_annotate_scan_set() {
+        let input = TempDir::new().unwrap();
+        for (name, shade) in [("p1.png", 0), ("p2.png", 255)] {
+            image::GrayImage::from_pixel(8, 8, image::Luma([shade]))
+                .save(input.path().join(name))
+                .unwrap();
+        }
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("box3");
+        ingest_scan_set(
+            input.path(),
+            &source,
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+        let (_, mut artifacts) = scan_set::load(&source).unwrap();
+        artifacts[0].content_text = Some("      END".to_string());
+        artifacts[0].layout_label = ArtifactKind::CardText;
+        scan_set::save_artifacts(&source, &artifacts).unwrap();
+
+        let summary = annotate_scan_set(&source, SidecarFormat::Json, &mut |_, _| {}).unwrap();
+        assert_eq!(
+            summary,
+            AnnotateSummary {
+                annotated: 1,
+                unanalyzed: 1
+            }
+        );
+        let path = source.join(SidecarFormat::Json.sidecar_path(&artifacts[0].raw_image_path));
+        let sidecar = fs::read_to_string(path).unwrap();
+        assert!(sidecar.contains("\"text\": \"      END\""), "{}", sidecar);
+        assert!(sidecar.contains("\"classification\": \"CardText\""));
+        assert!(!source
+            .join(SidecarFormat::Json.sidecar_path(&artifacts[1].raw_image_path))
+            .exists());
+    }
+}
@@ -0,0 +1,126 @@
+//! Single-artifact management
+//!
+//! Artifacts are addressed by ID or by a unique prefix of it, as listed by
+//! `scan3data artifact list`.
+
+use anyhow::{bail, Context, Result};
+use core_pipeline::scan_set;
+use core_pipeline::types::PageArtifact;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Index of the artifact with the given ID or unique ID prefix
+pub fn find_artifact(artifacts: &[PageArtifact], id: &str) -> Result<usize> {
+    let id = id.to_ascii_lowercase();
+    let mut matches = artifacts
+        .iter()
+        .enumerate()
+        .filter(|(_, artifact)| artifact.id.0.to_string().starts_with(&id));
+    match (matches.next(), matches.next()) {
+        (Some((idx, _)), None) if !id.is_empty() => Ok(idx),
+        (Some(_), _) => bail!("Artifact ID prefix is ambiguous: {}", id),
+        (None, _) => bail!("No artifact with ID: {}", id),
+    }
+}
+
+/// Result of removing an artifact
+#[derive(Debug, Clone)]
+pub struct RemovedArtifact {
+    /// The removed artifact
+    pub artifact: PageArtifact,
+    /// Image and original files deleted with it
+    pub files: Vec<PathBuf>,
+}
+
+/// Remove an artifact from a scan set
+///
+/// Deletes its raw, derived, original and cleaned files (unless another
+/// artifact refers to them) and updates the manifest counts and
+/// near-duplicates.
+pub fn remove_artifact(scan_set_dir: &Path, id: &str) -> Result<RemovedArtifact> {
+    let (mut manifest, mut artifacts) = scan_set::load(scan_set_dir)?;
+    let artifact = artifacts.remove(find_artifact(&artifacts, id)?);
+
+    let in_use: HashSet<&PathBuf> = artifacts.iter().flat_map(artifact_files).collect();
+    let mut files = Vec::new();
+    for path in artifact_files(&artifact) {
+        let full_path = scan_set_dir.join(path);
+        if in_use.contains(path) || !full_path.is_file() {
+            continue;
+        }
+        fs::remove_file(&full_path)
+            .with_context(|| format!("Failed to delete: {}", full_path.display()))?;
+        files.push(path.clone());
+    }
+
+    let file_count = artifact.metadata.original_filenames.len().max(1);
+    manifest.image_count = manifest.image_count.saturating_sub(1);
+    manifest.original_file_count = manifest.original_file_count.saturating_sub(file_count);
+    manifest.duplicate_count = manifest.duplicate_count.saturating_sub(file_count - 1);
+    manifest
+        .near_duplicates
+        .retain(|pair| pair.first != artifact.id && pair.second != artifact.id);
+
+    scan_set::save_artifacts(scan_set_dir, &artifacts)?;
+    scan_set::save_manifest(scan_set_dir, &manifest)?;
+
+    Ok(RemovedArtifact { artifact, files })
+}
+
+/// Files of an artifact within the scan set
+fn artifact_files(artifact: &PageArtifact) -> impl Iterator<Item = &PathBuf> {
+    std::iter::once(&artifact.raw_image_path)
+        .chain(&artifact.processed_image_path)
+        .chain(artifact.metadata.derived_images.iter().map(|d| &d.path))
+        .chain(&artifact.metadata.originals)
+        .chain(&artifact.metadata.cleaned_image_path)
+        .chain(&artifact.metadata.thumbnail_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{ingest_scan_set, IngestOptions};
+    use core_pipeline::keypunch::KeypunchModel;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_remove_artifact() {
+        let input = TempDir::new().unwrap();
+        for (name, shade) in [("p1.png", 0), ("p1-copy.png", 0), ("p2.png", 255)] {
+            image::GrayImage::from_pixel(8, 8, image::Luma([shade]))
+                .save(input.path().join(name))
+                .unwrap();
+        }
+        let dir = TempDir::new().unwrap();
+        ingest_scan_set(
+            input.path(),
+            dir.path(),
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+        let (_, artifacts) = scan_set::load(dir.path()).unwrap();
+        let id = artifacts[0].id.0.to_string();
+        assert_eq!(artifacts[0].metadata.original_filenames.len(), 2);
+        assert_eq!(find_artifact(&artifacts, &id.to_uppercase()).unwrap(), 0);
+        assert!(find_artifact(&artifacts, "").is_err());
+        assert!(find_artifact(&artifacts, "not-an-id").is_err());
+
+        let removed = remove_artifact(dir.path(), &id[..8]).unwrap();
+        assert_eq!(removed.artifact.id, artifacts[0].id);
+        assert_eq!(removed.files, vec![artifacts[0].raw_image_path.clone()]);
+        assert!(!dir.path().join(&artifacts[0].raw_image_path).exists());
+
+        let (manifest, remaining) = scan_set::load(dir.path()).unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(manifest.image_count, 1);
+        assert_eq!(manifest.original_file_count, 1);
+        assert_eq!(manifest.duplicate_count, 0);
+        assert!(manifest.near_duplicates.is_empty());
+
+        assert!(remove_artifact(dir.path(), &id).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! Benchmark OCR configurations against ground truth transcriptions
+//!
+//! To choose a vision model, the artifacts with a known-good transcription
+//! in a truth directory (`<name>.txt`, named after the raw image or an
+//! original file, like reference transcripts) are read with Tesseract
+//! alone and with Tesseract corrected by each vision model. Each result is
+//! scored by character error rate (CER) and word error rate (WER).
+//!
+//! The artifacts of the scan set are not changed. Preprocessing, OCR and
+//! vision corrections go through the stage cache like analysis does, so a
+//! benchmark after an analysis with the same settings reuses its results.
+
+use crate::analyze::{AnalyzeOptions, AnalyzePhase, AnalyzeProgress, Run};
+use anyhow::{bail, Context, Result};
+use core_pipeline::ocr::OcrOutput;
+use core_pipeline::scan_set;
+use core_pipeline::score::{transcript_path, Accuracy};
+use llm_bridge::{OllamaClient, VisionModel};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the Tesseract-only configuration
+pub const TESSERACT: &str = "tesseract";
+
+/// Scores of one artifact
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchArtifact {
+    /// Artifact identifier
+    pub artifact_id: String,
+    /// Raw image path (relative to the scan set)
+    pub image: PathBuf,
+    /// Ground truth transcription it was scored against
+    pub truth: PathBuf,
+    /// Errors of each configuration that produced text
+    pub results: BTreeMap<String, Accuracy>,
+    /// Why each other configuration produced no text
+    pub failures: BTreeMap<String, String>,
+}
+
+/// Errors of OCR configurations against ground truth
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    /// Configurations compared: [`TESSERACT`], then `tesseract+<model>`
+    /// for each vision model
+    pub configurations: Vec<String>,
+    /// Artifacts with a ground truth transcription
+    pub artifacts: Vec<BenchArtifact>,
+    /// Artifacts without a ground truth transcription (not scored)
+    pub without_truth: usize,
+    /// Artifacts every configuration produced text for
+    pub compared: usize,
+    /// Counts per configuration over the compared artifacts, so every
+    /// configuration is measured on the same pages
+    pub totals: BTreeMap<String, Accuracy>,
+}
+
+/// Name of the configuration correcting Tesseract with a vision model
+pub fn vision_configuration(model: &str) -> String {
+    format!("{}+{}", TESSERACT, model)
+}
+
+/// Benchmark Tesseract alone and corrected by each of `vision_models`
+/// against the transcriptions in `truth_dir`
+///
+/// Ollama, preprocessing and OCR settings are taken from `options`; its
+/// own vision model, hooks, auto-fix and LLM classification are not used.
+/// `progress` is called like for [`crate::analyze_scan_set`]. A failure of
+/// OCR or of a vision model on one artifact is recorded in the report.
+pub async fn bench_scan_set(
+    scan_set_path: &Path,
+    truth_dir: &Path,
+    options: &AnalyzeOptions,
+    vision_models: &[String],
+    progress: &mut (dyn FnMut(&AnalyzeProgress) + Send),
+) -> Result<BenchReport> {
+    if !truth_dir.is_dir() {
+        bail!("Truth directory does not exist: {}", truth_dir.display());
+    }
+    let (manifest, mut artifacts) = scan_set::load(scan_set_path)?;
+    let options = AnalyzeOptions {
+        vision_model: None,
+        use_llm: false,
+        autofix_threshold: None,
+        hooks: Default::default(),
+        ..options.clone()
+    };
+    let run = Run::new(scan_set_path, &options, manifest.keypunch.model)?;
+    let visions = vision_models
+        .iter()
+        .map(|model| {
+            let client = OllamaClient::new(options.ollama.clone())?;
+            Ok((model, VisionModel::new(client, model.clone())))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut configurations = vec![TESSERACT.to_string()];
+    configurations.extend(vision_models.iter().map(|m| vision_configuration(m)));
+    let mut report = BenchReport {
+        configurations,
+        artifacts: Vec::new(),
+        without_truth: 0,
+        compared: 0,
+        totals: BTreeMap::new(),
+    };
+
+    // The loaded artifacts are never saved, so the scan set is unchanged
+    let mut truths = Vec::new();
+    let mut pending = Vec::new();
+    for artifact in artifacts.iter_mut() {
+        match transcript_path(truth_dir, artifact) {
+            Some(path) => {
+                let truth = fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read truth: {}", path.display()))?;
+                truths.push((path, truth));
+                pending.push(artifact);
+            }
+            None => report.without_truth += 1,
+        }
+    }
+    let total = pending.len();
+    let recognized = run.recognize_all(&mut pending, options.jobs.max(1), progress)?;
+
+    for (done, ((artifact, recognized), (truth_path, truth))) in
+        pending.into_iter().zip(recognized).zip(truths).enumerate()
+    {
+        let mut scored = BenchArtifact {
+            artifact_id: artifact.id.0.to_string(),
+            image: artifact.raw_image_path.clone(),
+            truth: truth_path,
+            results: BTreeMap::new(),
+            failures: BTreeMap::new(),
+        };
+        match &recognized.ocr {
+            Ok((OcrOutput { text, .. }, _)) => {
+                scored
+                    .results
+                    .insert(TESSERACT.to_string(), Accuracy::measure(&truth, text));
+                for (model, vision) in &visions {
+                    let configuration = vision_configuration(model);
+                    match run
+                        .correct(
+                            vision,
+                            model,
+                            &recognized.image_path,
+                            &recognized.source_hash,
+                            text,
+                        )
+                        .await
+                    {
+                        Ok((corrected, _)) => {
+                            scored
+                                .results
+                                .insert(configuration, Accuracy::measure(&truth, &corrected));
+                        }
+                        Err(e) => {
+                            tracing::warn!(
+                                "Vision correction with {} failed for {}: {}",
+                                model,
+                                artifact.raw_image_path.display(),
+                                e
+                            );
+                            scored.failures.insert(configuration, e.to_string());
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                // Without OCR text there is nothing to correct either
+                for configuration in &report.configurations {
+                    scored
+                        .failures
+                        .insert(configuration.clone(), format!("OCR failed: {}", e));
+                }
+            }
+        }
+
+        if scored.failures.is_empty() {
+            report.compared += 1;
+            for (configuration, accuracy) in &scored.results {
+                report
+                    .totals
+                    .entry(configuration.clone())
+                    .or_default()
+                    .add(accuracy);
+            }
+        }
+        let errors = scored.failures.values().cloned().collect();
+        report.artifacts.push(scored);
+        progress(&AnalyzeProgress {
+            phase: AnalyzePhase::Correct,
+            done: done + 1,
+            total,
+            artifact: artifact.id,
+            index: artifact.metadata.ingest_index,
+            errors,
+            result: None,
+        });
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{ingest_scan_set, IngestOptions};
+    use core_pipeline::keypunch::KeypunchModel;
+    use futures::executor::block_on;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bench_without_truth() {
+        let input = TempDir::new().unwrap();
+        image::GrayImage::from_pixel(8, 8, image::Luma([0]))
+            .save(input.path().join("p1.png"))
+            .unwrap();
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("box3");
+        ingest_scan_set(
+            input.path(),
+            &source,
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+        let truth = dir.path().join("truth");
+        fs::create_dir_all(&truth).unwrap();
+        fs::write(truth.join("other.txt"), "      END\n").unwrap();
+
+        let report = block_on(bench_scan_set(
+            &source,
+            &truth,
+            &AnalyzeOptions::default(),
+            &["llava:latest".to_string()],
+            &mut |_| {},
+        ))
+        .unwrap();
+        assert_eq!(
+            report.configurations,
+            vec![
+                "tesseract".to_string(),
+                "tesseract+llava:latest".to_string()
+            ]
+        );
+        assert!(report.artifacts.is_empty());
+        assert_eq!(report.without_truth, 1);
+        assert!(report.totals.is_empty());
+
+        let missing = block_on(bench_scan_set(
+            &source,
+            &dir.path().join("nope"),
+            &AnalyzeOptions::default(),
+            &[],
+            &mut |_| {},
+        ));
+        assert!(missing.is_err());
+    }
+}
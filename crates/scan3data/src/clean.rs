@@ -0,0 +1,235 @@
+//! Clean scans of greenbar printouts with Gemini
+//!
+//! Gemini 2.5 Flash Image removes the greenbar bands and background noise
+//! from a scan while keeping the printed text. Cleaned images are written
+//! to [`CLEANED_DIR`] as PNG, named like the raw image, and recorded on the
+//! artifact with their cost. Unlike derived images they are not a cache:
+//! each one is paid for, so artifacts already cleaned are skipped unless
+//! forced, and the scan set is saved after every image.
+//!
+//! Every call is recorded in the manifest's spending. [`estimate_clean`]
+//! projects the cost of a run without calling Gemini, and a run stops
+//! before going over its budget.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use core_pipeline::scan_set;
+use core_pipeline::types::{PageArtifact, Spend};
+use llm_bridge::imagen::COST_PER_IMAGE_USD;
+use llm_bridge::GeminiClient;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory of cleaned images in a scan set
+pub const CLEANED_DIR: &str = "cleaned";
+
+/// Options of the clean step
+#[derive(Debug, Clone, Default)]
+pub struct CleanOptions {
+    /// Clean artifacts that already have a cleaned image again
+    pub force: bool,
+    /// Stop before this run spends more than this (USD)
+    pub max_cost_usd: Option<f64>,
+}
+
+/// Projected cost of a clean run
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CleanEstimate {
+    /// Images that would be cleaned
+    pub images: usize,
+    /// Artifacts that would be skipped because they were already cleaned
+    pub skipped: usize,
+    /// Cost of cleaning all of them (USD)
+    pub cost_usd: f64,
+    /// Images the budget allows, if there is one
+    pub within_budget: Option<usize>,
+    /// Money already spent on the scan set (USD)
+    pub spent_usd: f64,
+}
+
+/// Result of cleaning a scan set
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CleanSummary {
+    /// Images cleaned
+    pub cleaned: usize,
+    /// Artifacts skipped because they were already cleaned
+    pub skipped: usize,
+    /// Images Gemini failed to clean (recorded in the artifacts' notes)
+    pub failed: usize,
+    /// Money spent on this run (USD)
+    pub cost_usd: f64,
+    /// The run stopped at its budget with images left to clean
+    pub budget_reached: bool,
+}
+
+/// Project the cost of cleaning a scan set, without calling Gemini
+pub fn estimate_clean(scan_set_dir: &Path, options: &CleanOptions) -> Result<CleanEstimate> {
+    let (manifest, artifacts) = scan_set::load(scan_set_dir)?;
+    let images = pending(&artifacts, options).len();
+    Ok(CleanEstimate {
+        images,
+        skipped: artifacts.len() - images,
+        cost_usd: images as f64 * COST_PER_IMAGE_USD,
+        within_budget: options.max_cost_usd.map(|max| images.min(affordable(max))),
+        spent_usd: manifest.total_cost_usd(),
+    })
+}
+
+/// Clean the images of a scan set with Gemini
+///
+/// `progress` is called with the number of artifacts done and the number
+/// to clean. A failure to clean one image is recorded in the artifact's
+/// notes instead of failing the run.
+pub async fn clean_scan_set(
+    scan_set_dir: &Path,
+    client: &GeminiClient,
+    options: &CleanOptions,
+    progress: &mut (dyn FnMut(usize, usize) + Send),
+) -> Result<CleanSummary> {
+    let (mut manifest, mut artifacts) = scan_set::load(scan_set_dir)?;
+    let pending = pending(&artifacts, options);
+    let mut summary = CleanSummary {
+        skipped: artifacts.len() - pending.len(),
+        ..CleanSummary::default()
+    };
+    let service = format!("gemini:{}", client.model());
+
+    let total = pending.len();
+    for (done, idx) in pending.into_iter().enumerate() {
+        // Only images Gemini returned are paid for, so count what was spent
+        let cleaned_so_far = (summary.cost_usd / COST_PER_IMAGE_USD).round() as usize;
+        if options
+            .max_cost_usd
+            .is_some_and(|max| cleaned_so_far >= affordable(max))
+        {
+            summary.budget_reached = true;
+            break;
+        }
+        let artifact = &mut artifacts[idx];
+        let raw_image_path = scan_set_dir.join(&artifact.raw_image_path);
+        let image_bytes = fs::read(&raw_image_path)
+            .with_context(|| format!("Failed to read image: {}", raw_image_path.display()))?;
+
+        match client.clean_image(&image_bytes).await {
+            Ok(cleaned) => {
+                // Gemini was paid whether or not its image is usable
+                artifact.metadata.cost_usd += COST_PER_IMAGE_USD;
+                summary.cost_usd += COST_PER_IMAGE_USD;
+                manifest.spending.push(Spend {
+                    artifact: artifact.id,
+                    service: service.clone(),
+                    cost_usd: COST_PER_IMAGE_USD,
+                    at: Utc::now().to_rfc3339(),
+                });
+                let path = cleaned_path(&artifact.raw_image_path)?;
+                match save_cleaned(&cleaned, &scan_set_dir.join(&path)) {
+                    Ok(()) => {
+                        artifact.metadata.cleaned_image_path = Some(path);
+                        summary.cleaned += 1;
+                    }
+                    Err(e) => {
+                        artifact
+                            .metadata
+                            .notes
+                            .push(format!("Image cleaning failed: {:#}", e));
+                        summary.failed += 1;
+                    }
+                }
+            }
+            Err(e) => {
+                tracing::warn!(
+                    "Image cleaning failed for {}: {}",
+                    artifact.raw_image_path.display(),
+                    e
+                );
+                artifact
+                    .metadata
+                    .notes
+                    .push(format!("Image cleaning failed: {}", e));
+                summary.failed += 1;
+            }
+        }
+        scan_set::save_artifacts(scan_set_dir, &artifacts)?;
+        scan_set::save_manifest(scan_set_dir, &manifest)?;
+        progress(done + 1, total);
+    }
+
+    Ok(summary)
+}
+
+/// Indices of the artifacts a run cleans
+fn pending(artifacts: &[PageArtifact], options: &CleanOptions) -> Vec<usize> {
+    (0..artifacts.len())
+        .filter(|&idx| options.force || artifacts[idx].metadata.cleaned_image_path.is_none())
+        .collect()
+}
+
+/// Number of images a budget pays for
+fn affordable(max_cost_usd: f64) -> usize {
+    // Tolerate rounding, so a budget of exactly n images allows n
+    ((max_cost_usd + 1e-9) / COST_PER_IMAGE_USD)
+        .floor()
+        .max(0.0) as usize
+}
+
+/// Path of the cleaned image of a raw image, relative to the scan set
+fn cleaned_path(raw_image_path: &Path) -> Result<PathBuf> {
+    let Some(stem) = raw_image_path.file_stem() else {
+        bail!("Image has no file name: {}", raw_image_path.display());
+    };
+    let mut name = stem.to_os_string();
+    name.push(".png");
+    Ok(Path::new(CLEANED_DIR).join(name))
+}
+
+/// Decode an image returned by Gemini and save it as PNG
+fn save_cleaned(image_bytes: &[u8], path: &Path) -> Result<()> {
+    let image =
+        image::load_from_memory(image_bytes).context("Gemini returned an unreadable image")?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    image
+        .save_with_format(path, image::ImageFormat::Png)
+        .with_context(|| format!("Failed to write cleaned image: {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_affordable() {
+        assert_eq!(affordable(0.0), 0);
+        assert_eq!(affordable(0.038), 0);
+        assert_eq!(affordable(COST_PER_IMAGE_USD * 3.0), 3);
+        assert_eq!(affordable(1.0), 25);
+        assert_eq!(affordable(-1.0), 0);
+    }
+
+    #[test]
+    fn test_cleaned_path() {
+        assert_eq!(
+            cleaned_path(Path::new("images/246d5d942fde3ba9.jpg")).unwrap(),
+            PathBuf::from("cleaned/246d5d942fde3ba9.png")
+        );
+        assert!(cleaned_path(Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_save_cleaned() {
+        let dir = TempDir::new().unwrap();
+        let mut png = Vec::new();
+        image::DynamicImage::ImageLuma8(image::GrayImage::new(4, 2))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let path = dir.path().join(CLEANED_DIR).join("a.png");
+        save_cleaned(&png, &path).unwrap();
+        assert_eq!(image::open(&path).unwrap().width(), 4);
+        assert!(save_cleaned(b"not an image", &path).is_err());
+    }
+}
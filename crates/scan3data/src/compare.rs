@@ -0,0 +1,829 @@
+//! HTML comparison view of original scans next to their OCR text
+//!
+//! With raw OCR, each artifact gets a third panel: the Tesseract text
+//! before correction, with the characters the correction changed marked
+//! on both sides, to audit what the vision model and the fixes did. Column
+//! marking compares the texts column by column instead, so a character
+//! inserted or dropped marks the rest of the line and column drift stands
+//! out. Characters past column 80 are marked in every text panel.
+//!
+//! Scans are embedded in the page by default, which makes a self-contained
+//! file but one of several hundred megabytes for a large scan set; linked
+//! images keep the page small, and thumbnails keep it quick to load.
+//! Images load lazily, as they scroll into view, and a long view can be
+//! split into numbered pages with links between them. A review can be
+//! limited to one classification and a confidence range, such as the
+//! low-confidence object listings.
+
+use crate::thumbnails::generate_thumbnails;
+use anyhow::{Context, Result};
+use core_pipeline::export::CARD_COLUMNS;
+use core_pipeline::ocr_diff::{changed_chars, column_changed_chars};
+use core_pipeline::scan_set;
+use core_pipeline::score::cached_ocr_text;
+use core_pipeline::stage_cache::StageCache;
+use core_pipeline::types::{ArtifactKind, PageArtifact, PageId};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// Options of the comparison view
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompareOptions {
+    /// Overlay faint column guides on the text
+    pub show_grid: bool,
+    /// Add a panel of raw OCR text, from the stage cache
+    pub raw_ocr: bool,
+    /// Mark characters of the raw and corrected text that differ at the
+    /// same column, rather than the aligned changes
+    pub column_diff: bool,
+    /// Link the images by relative path instead of embedding them
+    pub link_images: bool,
+    /// Make thumbnails of at most this many pixels first, and show them
+    pub thumbnail_size: Option<u32>,
+    /// Split the view into pages of this many artifacts
+    pub per_page: Option<usize>,
+    /// Only artifacts of this classification
+    pub only_kind: Option<ArtifactKind>,
+    /// Only artifacts classified with at least this confidence
+    pub min_confidence: Option<f32>,
+    /// Only artifacts classified with at most this confidence
+    pub max_confidence: Option<f32>,
+}
+
+impl CompareOptions {
+    /// Whether an artifact passes the classification and confidence
+    /// filters
+    pub fn includes(&self, artifact: &PageArtifact) -> bool {
+        let confidence = artifact.metadata.confidence;
+        self.only_kind
+            .is_none_or(|kind| artifact.layout_label == kind)
+            && self.min_confidence.is_none_or(|min| confidence >= min)
+            && self.max_confidence.is_none_or(|max| confidence <= max)
+    }
+}
+
+/// How the comparison page shows scans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageSource {
+    /// Embedded as data URLs, so the page can be opened or passed on
+    /// without the scan set
+    Embed,
+    /// Linked, by the path of the scan set directory relative to the page
+    Link(PathBuf),
+}
+
+/// HTML page with each artifact's original scan next to its (corrected)
+/// OCR text
+///
+/// Scans are read from the scan set directory and embedded or linked as
+/// `images` says. Artifacts with a thumbnail (see
+/// [`crate::generate_thumbnails`]) show it instead of the full scan,
+/// keeping the page small. `raw_ocr` adds a panel of raw OCR text, by
+/// artifact, between the scan and the corrected text. Of `options`, only
+/// `show_grid` and `column_diff` apply.
+pub fn comparison_html(
+    scan_set_dir: &Path,
+    artifacts: &[PageArtifact],
+    options: &CompareOptions,
+    raw_ocr: Option<&HashMap<PageId, String>>,
+    images: &ImageSource,
+) -> Result<String> {
+    let view = View {
+        scan_set_dir,
+        raw_ocr,
+        images,
+        column_diff: options.column_diff,
+        total: artifacts.len(),
+    };
+    let mut html = generate_html_header(options.show_grid);
+    for (idx, artifact) in artifacts.iter().enumerate() {
+        html.push_str(&view.artifact_html(idx + 1, artifact)?);
+    }
+    html.push_str("</body></html>");
+    Ok(html)
+}
+
+/// What every artifact of a comparison view is shown with
+struct View<'a> {
+    scan_set_dir: &'a Path,
+    raw_ocr: Option<&'a HashMap<PageId, String>>,
+    images: &'a ImageSource,
+    column_diff: bool,
+    /// Artifacts in the whole view
+    total: usize,
+}
+
+impl View<'_> {
+    /// Panels of one artifact, numbered from 1 in the whole view
+    fn artifact_html(&self, number: usize, artifact: &PageArtifact) -> Result<String> {
+        // The thumbnail if there is one
+        let image = artifact
+            .metadata
+            .thumbnail_path
+            .as_ref()
+            .filter(|path| self.scan_set_dir.join(path).is_file())
+            .unwrap_or(&artifact.raw_image_path);
+        let image_src = match self.images {
+            ImageSource::Embed => {
+                let image_path = self.scan_set_dir.join(image);
+                let image_bytes = fs::read(&image_path)
+                    .with_context(|| format!("Failed to read image: {}", image_path.display()))?;
+                let image_b64 = base64::Engine::encode(
+                    &base64::engine::general_purpose::STANDARD,
+                    &image_bytes,
+                );
+                let image_ext = image_path
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .unwrap_or("jpg");
+                format!("data:image/{};base64,{}", image_ext, image_b64)
+            }
+            ImageSource::Link(base) => html_escape(&url_path(&base.join(image))),
+        };
+
+        let corrected_text = artifact
+            .content_text
+            .as_deref()
+            .unwrap_or("[No text extracted]");
+
+        let filenames = artifact.metadata.original_filenames.join(", ");
+        let notes = if artifact.metadata.notes.is_empty() {
+            "None".to_string()
+        } else {
+            artifact.metadata.notes.join("; ")
+        };
+        let overlong = corrected_text
+            .lines()
+            .filter(|line| line.trim_end().chars().count() > CARD_COLUMNS)
+            .count();
+        let overlong = if overlong > 0 {
+            format!(
+                "\n            <div class=\"warning\"><strong>Lines over {} columns:</strong> {}</div>",
+                CARD_COLUMNS, overlong
+            )
+        } else {
+            String::new()
+        };
+
+        // Raw OCR panel, with changes marked in both texts
+        let (layout, raw_panel, corrected_html) = match self.raw_ocr {
+            None => (
+                "side-by-side",
+                String::new(),
+                text_html(unmarked(corrected_text), "added"),
+            ),
+            Some(raw_ocr) => {
+                let raw = raw_ocr.get(&artifact.id);
+                let raw_html = match (raw, &artifact.content_text) {
+                    (Some(raw), Some(text)) => text_html(self.changes(raw, text), "removed"),
+                    (Some(raw), None) => text_html(unmarked(raw), "removed"),
+                    (None, _) => "[No cached OCR text]".to_string(),
+                };
+                let corrected_html = match (raw, &artifact.content_text) {
+                    (Some(raw), Some(text)) => text_html(self.changes(text, raw), "added"),
+                    _ => text_html(unmarked(corrected_text), "added"),
+                };
+                let panel = format!(
+                    r#"
+        <div class="panel">
+            <h3>Raw OCR Text</h3>
+            <div class="text-container">
+                <pre class="ocr-text">{}</pre>
+            </div>
+        </div>"#,
+                    raw_html
+                );
+                ("three-panels", panel, corrected_html)
+            }
+        };
+
+        Ok(format!(
+            r#"
+<div class="comparison">
+    <div class="header">
+        <h2>Artifact {}/{}</h2>
+        <div class="metadata">
+            <div><strong>Original files:</strong> {}</div>
+            <div><strong>Classification:</strong> {:?} ({:.0}% confidence)</div>
+            <div><strong>Processing notes:</strong> {}</div>{}
+        </div>
+    </div>
+    <div class="{}">
+        <div class="panel">
+            <h3>Original Scan</h3>
+            <div class="image-container">
+                <img src="{}" alt="Original scan" loading="lazy" />
+            </div>
+        </div>{}
+        <div class="panel">
+            <h3>Corrected OCR Text</h3>
+            <div class="text-container">
+                <pre class="ocr-text">{}</pre>
+            </div>
+        </div>
+    </div>
+</div>
+"#,
+            number,
+            self.total,
+            html_escape(&filenames),
+            artifact.layout_label,
+            artifact.metadata.confidence * 100.0,
+            html_escape(&notes),
+            overlong,
+            layout,
+            image_src,
+            raw_panel,
+            corrected_html
+        ))
+    }
+
+    /// Lines of `text` with the characters marked that differ from `other`
+    fn changes(&self, text: &str, other: &str) -> Vec<Vec<(char, bool)>> {
+        if self.column_diff {
+            column_changed_chars(text, other)
+        } else {
+            changed_chars(text, other)
+        }
+    }
+}
+
+/// Write the comparison view of a scan set to a file
+///
+/// With `raw_ocr`, the raw OCR text is read from the stage cache; artifacts
+/// whose OCR result is no longer cached show no raw text. Linked images
+/// are addressed relative to the output file, which must stay where it is
+/// relative to the scan set. Returns the number of artifacts in the view,
+/// those that pass the filters of `options`.
+pub fn generate_comparison_html(
+    scan_set_dir: &Path,
+    output_file: &Path,
+    options: &CompareOptions,
+) -> Result<usize> {
+    if let Some(size) = options.thumbnail_size {
+        generate_thumbnails(scan_set_dir, size, &mut |_, _| {})?;
+    }
+    let (manifest, mut artifacts) = scan_set::load(scan_set_dir)?;
+    artifacts.retain(|artifact| options.includes(artifact));
+    let images = if options.link_images {
+        let page_dir = match output_file.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        ImageSource::Link(relative_path(scan_set_dir, page_dir)?)
+    } else {
+        ImageSource::Embed
+    };
+    let raw_texts = if options.raw_ocr {
+        let stage_cache = StageCache::new(scan_set_dir);
+        let mut texts = HashMap::new();
+        for artifact in &artifacts {
+            let text = cached_ocr_text(scan_set_dir, &stage_cache, artifact, &manifest)?;
+            if let Some(text) = text {
+                texts.insert(artifact.id, text);
+            }
+        }
+        Some(texts)
+    } else {
+        None
+    };
+    let view = View {
+        scan_set_dir,
+        raw_ocr: raw_texts.as_ref(),
+        images: &images,
+        column_diff: options.column_diff,
+        total: artifacts.len(),
+    };
+
+    let per_page = options.per_page.unwrap_or(artifacts.len()).max(1);
+    let pages: Vec<&[PageArtifact]> = if artifacts.is_empty() {
+        vec![&[]]
+    } else {
+        artifacts.chunks(per_page).collect()
+    };
+    for (idx, page) in pages.iter().enumerate() {
+        let mut html = generate_html_header(options.show_grid);
+        let navigation = navigation_html(output_file, idx + 1, pages.len());
+        html.push_str(&navigation);
+        for (n, artifact) in page.iter().enumerate() {
+            html.push_str(&view.artifact_html(idx * per_page + n + 1, artifact)?);
+        }
+        html.push_str(&navigation);
+        html.push_str("</body></html>");
+
+        let path = page_path(output_file, idx + 1);
+        fs::write(&path, html)
+            .with_context(|| format!("Failed to write HTML file: {}", path.display()))?;
+    }
+    Ok(artifacts.len())
+}
+
+/// File of a page of the view: the output file for the first page, then
+/// `comparison_2.html` and so on
+pub fn page_path(output_file: &Path, page: usize) -> PathBuf {
+    if page == 1 {
+        return output_file.to_path_buf();
+    }
+    let stem = output_file
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = match output_file.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, page, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, page),
+    };
+    output_file.with_file_name(name)
+}
+
+/// Links to the previous and next pages; nothing for a single page
+fn navigation_html(output_file: &Path, page: usize, pages: usize) -> String {
+    if pages <= 1 {
+        return String::new();
+    }
+    let link = |page: usize, label: &str| {
+        let file = page_path(output_file, page);
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        format!(
+            "<a href=\"{}\">{}</a>",
+            html_escape(&url_path(Path::new(name.as_ref()))),
+            label
+        )
+    };
+    let previous = if page > 1 {
+        link(page - 1, "&larr; Previous")
+    } else {
+        "<span>&larr; Previous</span>".to_string()
+    };
+    let next = if page < pages {
+        link(page + 1, "Next &rarr;")
+    } else {
+        "<span>Next &rarr;</span>".to_string()
+    };
+    format!(
+        "\n<nav class=\"pages\">{} <span>Page {} of {}</span> {}</nav>\n",
+        previous, page, pages, next
+    )
+}
+
+/// Path of `target` relative to the directory `base`
+fn relative_path(target: &Path, base: &Path) -> Result<PathBuf> {
+    let canonical = |path: &Path| {
+        path.canonicalize()
+            .with_context(|| format!("Failed to resolve {}", path.display()))
+    };
+    let (target, base) = (canonical(target)?, canonical(base)?);
+    let target: Vec<Component> = target.components().collect();
+    let base: Vec<Component> = base.components().collect();
+    let common = target.iter().zip(&base).take_while(|(a, b)| a == b).count();
+    let mut relative: PathBuf = base[common..].iter().map(|_| "..").collect();
+    relative.extend(&target[common..]);
+    Ok(relative)
+}
+
+/// Relative URL of a path: `/` separators, with the characters that end
+/// or escape a path percent-encoded
+pub fn url_path(path: &Path) -> String {
+    let mut url = String::new();
+    for (idx, component) in path.components().enumerate() {
+        if idx > 0 {
+            url.push('/');
+        }
+        for c in component.as_os_str().to_string_lossy().chars() {
+            match c {
+                '%' => url.push_str("%25"),
+                ' ' => url.push_str("%20"),
+                '#' => url.push_str("%23"),
+                '?' => url.push_str("%3F"),
+                _ => url.push(c),
+            }
+        }
+    }
+    url
+}
+
+/// Lines of a text with no characters marked
+fn unmarked(text: &str) -> Vec<Vec<(char, bool)>> {
+    text.lines()
+        .map(|line| line.chars().map(|c| (c, false)).collect())
+        .collect()
+}
+
+/// Escaped lines with characters past the last card column wrapped in
+/// `<mark class="overflow">`, and the other marked characters in
+/// `<mark class="{class}">`
+fn text_html(lines: Vec<Vec<(char, bool)>>, class: &str) -> String {
+    let mut html = String::new();
+    for (idx, line) in lines.into_iter().enumerate() {
+        if idx > 0 {
+            html.push('\n');
+        }
+        let mut open = None;
+        for (column, (c, changed)) in line.into_iter().enumerate() {
+            let mark = if column >= CARD_COLUMNS {
+                Some("overflow")
+            } else if changed {
+                Some(class)
+            } else {
+                None
+            };
+            if mark != open {
+                if open.is_some() {
+                    html.push_str("</mark>");
+                }
+                if let Some(mark) = mark {
+                    html.push_str(&format!("<mark class=\"{}\">", mark));
+                }
+                open = mark;
+            }
+            html.push_str(&html_escape(c.encode_utf8(&mut [0; 4])));
+        }
+        if open.is_some() {
+            html.push_str("</mark>");
+        }
+    }
+    html
+}
+
+/// Generate HTML header with CSS styling
+fn generate_html_header(show_grid: bool) -> String {
+    let grid_css = if show_grid {
+        r#"
+        .ocr-text {
+            background-image: repeating-linear-gradient(
+                to right,
+                transparent,
+                transparent 0.6ch,
+                rgba(0, 150, 255, 0.1) 0.6ch,
+                rgba(0, 150, 255, 0.1) 0.61ch
+            );
+        }
+        "#
+    } else {
+        ""
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>OCR Comparison View</title>
+    <style>
+        * {{
+            margin: 0;
+            padding: 0;
+            box-sizing: border-box;
+        }}
+        body {{
+            font-family: -apple-system, BlinkMacSystemFont, "Segoe UI", Roboto, sans-serif;
+            background: #f5f5f5;
+            padding: 20px;
+        }}
+        .comparison {{
+            background: white;
+            border-radius: 8px;
+            padding: 20px;
+            margin-bottom: 30px;
+            box-shadow: 0 2px 4px rgba(0,0,0,0.1);
+        }}
+        .header {{
+            margin-bottom: 20px;
+            border-bottom: 2px solid #e0e0e0;
+            padding-bottom: 15px;
+        }}
+        .header h2 {{
+            color: #333;
+            margin-bottom: 10px;
+        }}
+        .metadata {{
+            font-size: 14px;
+            color: #666;
+        }}
+        .metadata div {{
+            margin: 5px 0;
+        }}
+        .side-by-side {{
+            display: grid;
+            grid-template-columns: 1fr 1fr;
+            gap: 20px;
+        }}
+        .pages {{
+            display: flex;
+            justify-content: center;
+            gap: 20px;
+            margin: 0 0 30px;
+            color: #666;
+        }}
+        .pages a {{
+            color: #0366d6;
+            text-decoration: none;
+        }}
+        .three-panels {{
+            display: grid;
+            grid-template-columns: 1fr 1fr 1fr;
+            gap: 20px;
+        }}
+        mark.removed {{
+            background: #ffd7d5;
+            color: #82071e;
+        }}
+        mark.overflow {{
+            background: #fff3bf;
+            color: #7a4d00;
+        }}
+        .warning {{
+            color: #9a6700;
+        }}
+        mark.added {{
+            background: #ccffd8;
+            color: #055d20;
+        }}
+        .panel {{
+            border: 1px solid #ddd;
+            border-radius: 4px;
+            overflow: hidden;
+        }}
+        .panel h3 {{
+            background: #f8f8f8;
+            padding: 10px 15px;
+            margin: 0;
+            font-size: 16px;
+            color: #555;
+            border-bottom: 1px solid #ddd;
+        }}
+        .image-container {{
+            padding: 15px;
+            background: #fafafa;
+            display: flex;
+            justify-content: center;
+            align-items: flex-start;
+            overflow: auto;
+            max-height: 800px;
+        }}
+        .image-container img {{
+            max-width: 100%;
+            height: auto;
+            border: 1px solid #ddd;
+            background: white;
+        }}
+        .text-container {{
+            padding: 15px;
+            background: #fafafa;
+            overflow: auto;
+            max-height: 800px;
+        }}
+        .ocr-text {{
+            font-family: "Courier New", Courier, monospace;
+            font-size: 12px;
+            line-height: 1.4;
+            white-space: pre;
+            background: white;
+            padding: 15px;
+            border: 1px solid #ddd;
+            border-radius: 2px;
+            color: #222;
+        }}
+        {}
+        @media (max-width: 1200px) {{
+            .side-by-side, .three-panels {{
+                grid-template-columns: 1fr;
+            }}
+        }}
+    </style>
+</head>
+<body>
+    <h1 style="margin-bottom: 20px; color: #333;">IBM 1130 OCR Comparison View</h1>
+"#,
+        grid_css
+    )
+}
+
+/// Escape HTML special characters
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{ingest_scan_set, IngestOptions};
+    use core_pipeline::keypunch::KeypunchModel;
+    use core_pipeline::types::{ArtifactKind, PageMetadata, ScanSetId};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_comparison_html() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("images")).unwrap();
+        fs::write(dir.path().join("images/ab.png"), b"png").unwrap();
+        let mut artifact = PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/ab.png"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::Unknown,
+            content_text: Some("      IF (I .LT. 0) GO TO 10 <".to_string()),
+            metadata: PageMetadata {
+                content_hash: String::new(),
+                file_hash: None,
+                original_filenames: vec!["p&1.png".to_string()],
+                page_number: None,
+                header: None,
+                footer: None,
+                notes: Vec::new(),
+                confidence: 0.0,
+                revisions: Vec::new(),
+                validation_issues: Vec::new(),
+                derived_images: Vec::new(),
+                cost_usd: 0.0,
+                source_pages: Vec::new(),
+                perceptual_hash: None,
+                ingest_index: 0,
+                originals: Vec::new(),
+                reading_order: None,
+                human_transcribed: false,
+                cleaned_image_path: None,
+                thumbnail_path: None,
+                manual_classification: false,
+                base_confidence: None,
+            },
+        };
+
+        let html = comparison_html(
+            dir.path(),
+            std::slice::from_ref(&artifact),
+            &CompareOptions {
+                show_grid: true,
+                ..CompareOptions::default()
+            },
+            None,
+            &ImageSource::Embed,
+        )
+        .unwrap();
+        assert!(html.contains("<h2>Artifact 1/1</h2>"));
+        assert!(html.contains("<strong>Classification:</strong> Unknown (0% confidence)"));
+        assert!(html.contains("src=\"data:image/png;base64,cG5n\""));
+        assert!(html.contains("GO TO 10 &lt;</pre>"));
+        assert!(html.contains("p&amp;1.png"));
+        assert!(html.contains("repeating-linear-gradient"));
+        assert!(html.ends_with("</body></html>"));
+
+        // A thumbnail is shown instead of the scan
+        fs::create_dir_all(dir.path().join("thumbnails")).unwrap();
+        fs::write(dir.path().join("thumbnails/ab-256.jpg"), b"jpg").unwrap();
+        artifact.metadata.thumbnail_path = Some(PathBuf::from("thumbnails/ab-256.jpg"));
+        let html = comparison_html(
+            dir.path(),
+            std::slice::from_ref(&artifact),
+            &CompareOptions::default(),
+            None,
+            &ImageSource::Embed,
+        )
+        .unwrap();
+        assert!(html.contains("src=\"data:image/jpg;base64,anBn\""));
+        assert!(!html.contains("Raw OCR Text"));
+
+        // Or linked relative to the page
+        let html = comparison_html(
+            dir.path(),
+            std::slice::from_ref(&artifact),
+            &CompareOptions::default(),
+            None,
+            &ImageSource::Link(PathBuf::from("../box #3")),
+        )
+        .unwrap();
+        assert!(html.contains("src=\"../box%20%233/thumbnails/ab-256.jpg\""));
+
+        // Raw OCR in a third panel, changes marked on both sides
+        let raw = HashMap::from([(artifact.id, "      IF (I .LT. O) GO T0 10 <".to_string())]);
+        let html = comparison_html(
+            dir.path(),
+            std::slice::from_ref(&artifact),
+            &CompareOptions::default(),
+            Some(&raw),
+            &ImageSource::Embed,
+        )
+        .unwrap();
+        assert!(html.contains("<div class=\"three-panels\">"));
+        assert!(html.contains(
+            "IF (I .LT. <mark class=\"removed\">O</mark>) GO T<mark class=\"removed\">0</mark> 10 &lt;"
+        ));
+        assert!(html.contains(
+            "IF (I .LT. <mark class=\"added\">0</mark>) GO T<mark class=\"added\">O</mark> 10 &lt;"
+        ));
+        assert!(!html.contains("Lines over 80 columns"));
+
+        // Column marking shows the drift after a dropped space; the part
+        // past column 80 is marked as overflow
+        let long = format!("     IF (I .LT. 0) GO TO 10{}X", " ".repeat(53));
+        artifact.content_text = Some(long);
+        let html = comparison_html(
+            dir.path(),
+            std::slice::from_ref(&artifact),
+            &CompareOptions {
+                column_diff: true,
+                ..CompareOptions::default()
+            },
+            Some(&raw),
+            &ImageSource::Embed,
+        )
+        .unwrap();
+        assert!(html.contains("     <mark class=\"added\">IF (I .LT. 0) GO TO 10 </mark>"));
+        assert!(html.contains("<mark class=\"overflow\">X</mark>"));
+        assert!(html.contains("<strong>Lines over 80 columns:</strong> 1"));
+    }
+
+    #[test]
+    fn test_paginated_view() {
+        let input = TempDir::new().unwrap();
+        for (idx, shade) in [40u8, 120, 200].into_iter().enumerate() {
+            image::GrayImage::from_pixel(40, 20, image::Luma([shade]))
+                .save(input.path().join(format!("p{}.png", idx + 1)))
+                .unwrap();
+        }
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("box3");
+        ingest_scan_set(
+            input.path(),
+            &source,
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+
+        let output = dir.path().join("view.html");
+        let options = CompareOptions {
+            link_images: true,
+            per_page: Some(2),
+            ..CompareOptions::default()
+        };
+        assert_eq!(
+            generate_comparison_html(&source, &output, &options).unwrap(),
+            3
+        );
+
+        let first = fs::read_to_string(&output).unwrap();
+        assert_eq!(first.matches("<div class=\"comparison\">").count(), 2);
+        assert!(first.contains("<span>Page 1 of 2</span> <a href=\"view_2.html\">Next &rarr;</a>"));
+        assert!(first.contains("src=\"box3/images/"));
+        assert!(first.contains("loading=\"lazy\""));
+        let second = fs::read_to_string(dir.path().join("view_2.html")).unwrap();
+        assert!(second.contains("<h2>Artifact 3/3</h2>"));
+        assert!(second.contains("<a href=\"view.html\">&larr; Previous</a>"));
+    }
+
+    #[test]
+    fn test_includes() {
+        let mut artifact = PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/ab.png"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingObject,
+            content_text: None,
+            metadata: PageMetadata {
+                confidence: 0.4,
+                ..PageMetadata::default()
+            },
+        };
+        let review = CompareOptions {
+            only_kind: Some(ArtifactKind::ListingObject),
+            max_confidence: Some(0.6),
+            ..CompareOptions::default()
+        };
+        assert!(review.includes(&artifact));
+        assert!(CompareOptions::default().includes(&artifact));
+        artifact.metadata.confidence = 0.9;
+        assert!(!review.includes(&artifact));
+        artifact.metadata.confidence = 0.4;
+        artifact.layout_label = ArtifactKind::ListingSource;
+        assert!(!review.includes(&artifact));
+        assert!(!CompareOptions {
+            min_confidence: Some(0.5),
+            ..CompareOptions::default()
+        }
+        .includes(&artifact));
+    }
+
+    #[test]
+    fn test_relative_path() {
+        let dir = TempDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("sets/box3")).unwrap();
+        fs::create_dir_all(dir.path().join("reports")).unwrap();
+        assert_eq!(
+            relative_path(&dir.path().join("sets/box3"), &dir.path().join("reports")).unwrap(),
+            Path::new("../sets/box3")
+        );
+        assert_eq!(
+            relative_path(&dir.path().join("sets/box3"), &dir.path().join("sets/box3")).unwrap(),
+            Path::new("")
+        );
+    }
+}
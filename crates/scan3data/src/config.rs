@@ -0,0 +1,349 @@
+//! Project configuration (`scan3data.toml`)
+//!
+//! Defaults for the CLI and the server, read from `scan3data.toml` in the
+//! working directory or from a file given with `--config`:
+//!
+//! ```toml
+//! [ingest]
+//! pdf_dpi = 400
+//! near_duplicate_distance = 8
+//! storage = "sqlite"
+//!
+//! [ollama]
+//! base_url = "http://gpu-box:11434"
+//! timeout_secs = 300
+//!
+//! [models]
+//! vision = "llama3.2-vision:11b"
+//! text = "qwen2.5:7b"
+//! gemini = "gemini-2.5-flash-image"
+//!
+//! [preprocess]
+//! greenbar = false
+//! horizontal_lines = true
+//!
+//! [analyze]
+//! vision_max_dimension = 1600
+//! autofix_threshold = 0.85
+//! jobs = 8
+//! model_jobs = 2
+//! rules = "data-deck.toml"
+//!
+//! [clean]
+//! max_cost_usd = 10.0
+//!
+//! [export]
+//! format = "listing"
+//!
+//! [server]
+//! host = "0.0.0.0"
+//! port = 7214
+//! dist = "crates/yew_frontend/dist"
+//! data_dir = "/srv/scan3data"
+//! database = "/srv/scan3data/server.sqlite"
+//! api_keys = ["change-me"]
+//! ```
+//!
+//! Every setting is optional; command-line flags override the file, and
+//! the file overrides the built-in defaults. Relative paths in the file are
+//! relative to the directory it is in.
+
+use anyhow::{Context, Result};
+use core_pipeline::preprocess::PreprocessOptions;
+use core_pipeline::storage::StorageBackend;
+use core_pipeline::validate::RuleSet;
+use llm_bridge::{GeminiConfig, OllamaConfig};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Configuration file looked for in the working directory
+pub const CONFIG_FILE: &str = "scan3data.toml";
+
+/// Environment variable with more API keys of the server, comma-separated
+pub const API_KEYS_ENV: &str = "SCAN3DATA_API_KEYS";
+
+/// Contents of a configuration file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// `ingest` defaults
+    pub ingest: IngestSettings,
+    /// Ollama server
+    pub ollama: OllamaSettings,
+    /// Model names
+    pub models: ModelSettings,
+    /// Image preprocessing before OCR
+    pub preprocess: PreprocessSettings,
+    /// `analyze` defaults
+    pub analyze: AnalyzeSettings,
+    /// `clean` defaults
+    pub clean: CleanSettings,
+    /// `export` defaults
+    pub export: ExportSettings,
+    /// Server defaults
+    pub server: ServerSettings,
+}
+
+/// `ingest` defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IngestSettings {
+    /// Resolution PDF pages are rasterized at
+    pub pdf_dpi: Option<u32>,
+    /// Perceptual hash distance of suspected near-duplicates
+    pub near_duplicate_distance: Option<u32>,
+    /// Keep the untouched source files under `originals/`
+    pub copy_originals: Option<bool>,
+    /// How new scan sets store their manifest and artifacts (`sqlite`
+    /// needs the `sqlite` feature)
+    pub storage: Option<StorageBackend>,
+}
+
+/// Ollama server settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OllamaSettings {
+    /// Base URL of the Ollama API
+    pub base_url: Option<String>,
+    /// Request timeout in seconds
+    pub timeout_secs: Option<u64>,
+}
+
+/// Model names
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelSettings {
+    /// Ollama vision model for OCR correction
+    pub vision: Option<String>,
+    /// Ollama text model for classification
+    pub text: Option<String>,
+    /// Gemini model for image cleaning
+    pub gemini: Option<String>,
+}
+
+/// Image preprocessing steps; every step runs unless turned off
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PreprocessSettings {
+    /// Even out the bands of greenbar paper
+    pub greenbar: Option<bool>,
+    /// Erase long horizontal lines
+    pub horizontal_lines: Option<bool>,
+}
+
+/// `analyze` defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AnalyzeSettings {
+    /// Longest side of images sent to the vision model
+    pub vision_max_dimension: Option<u32>,
+    /// Minimum confidence of auto-fixes
+    pub autofix_threshold: Option<f32>,
+    /// Artifacts preprocessed and OCRed in parallel
+    pub jobs: Option<usize>,
+    /// Model calls in flight at the same time
+    pub model_jobs: Option<usize>,
+    /// Validation rule-set file, as for `validate --rules`
+    pub rules: Option<PathBuf>,
+}
+
+/// `clean` defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CleanSettings {
+    /// Budget of a run in USD
+    pub max_cost_usd: Option<f64>,
+}
+
+/// `export` defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExportSettings {
+    /// Output format
+    pub format: Option<String>,
+}
+
+/// Server defaults
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerSettings {
+    /// Address to listen on (`0.0.0.0` for every interface; default
+    /// `127.0.0.1`, this machine only)
+    pub host: Option<String>,
+    /// Port to listen on
+    pub port: Option<u16>,
+    /// Directory of the built frontend served by `serve --mode spa`
+    pub dist: Option<PathBuf>,
+    /// Directory the API server creates new scan sets in
+    pub data_dir: Option<PathBuf>,
+    /// SQLite database the API server keeps its state in across restarts
+    /// (needs the server's `sqlite` feature)
+    pub database: Option<PathBuf>,
+    /// Keys API clients must present; more come from the
+    /// `SCAN3DATA_API_KEYS` environment variable (comma-separated)
+    pub api_keys: Option<Vec<String>>,
+}
+
+impl Config {
+    /// Parse a configuration from TOML
+    pub fn from_toml(toml: &str) -> Result<Self> {
+        toml::from_str(toml).context("Failed to parse configuration")
+    }
+
+    /// Load a configuration file, with its relative paths made relative to
+    /// the directory it is in
+    pub fn load(path: &Path) -> Result<Self> {
+        let toml = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read configuration: {}", path.display()))?;
+        let mut config: Self = toml::from_str(&toml)
+            .with_context(|| format!("Invalid configuration: {}", path.display()))?;
+        if let Some(dir) = path.parent() {
+            config.resolve_paths(dir);
+        }
+        Ok(config)
+    }
+
+    /// Join relative paths to `dir`
+    fn resolve_paths(&mut self, dir: &Path) {
+        let paths = [
+            &mut self.analyze.rules,
+            &mut self.server.dist,
+            &mut self.server.data_dir,
+            &mut self.server.database,
+        ];
+        for path in paths.into_iter().flatten() {
+            if path.is_relative() {
+                *path = dir.join(&*path);
+            }
+        }
+    }
+
+    /// Load the given file, else `scan3data.toml` in the working directory
+    /// if there is one, else the defaults
+    pub fn discover(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::load(path),
+            None if Path::new(CONFIG_FILE).is_file() => Self::load(Path::new(CONFIG_FILE)),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Ollama client configuration, with defaults for unset settings
+    pub fn ollama_config(&self) -> OllamaConfig {
+        let defaults = OllamaConfig::default();
+        OllamaConfig {
+            base_url: self.ollama.base_url.clone().unwrap_or(defaults.base_url),
+            timeout_secs: self.ollama.timeout_secs.unwrap_or(defaults.timeout_secs),
+        }
+    }
+
+    /// Preprocessing steps, with the unset ones enabled
+    pub fn preprocess_options(&self) -> PreprocessOptions {
+        let defaults = PreprocessOptions::default();
+        PreprocessOptions {
+            greenbar: self.preprocess.greenbar.unwrap_or(defaults.greenbar),
+            horizontal_lines: self
+                .preprocess
+                .horizontal_lines
+                .unwrap_or(defaults.horizontal_lines),
+        }
+    }
+
+    /// Validation rules of `analyze` and `validate`: the given file, else
+    /// the configured one, else the defaults
+    pub fn rule_set(&self, path: Option<&Path>) -> Result<RuleSet> {
+        match path.or(self.analyze.rules.as_deref()) {
+            Some(path) => Ok(RuleSet::load(path)?),
+            None => Ok(RuleSet::default()),
+        }
+    }
+
+    /// API keys of the server, from the configuration and the environment
+    pub fn api_keys(&self) -> Vec<String> {
+        let from_env = std::env::var(API_KEYS_ENV).unwrap_or_default();
+        self.server
+            .api_keys
+            .iter()
+            .flatten()
+            .map(String::as_str)
+            .chain(from_env.split(','))
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// Gemini client configuration from the environment, with the
+    /// configured model
+    pub fn gemini_config(&self) -> Result<GeminiConfig> {
+        let mut config = GeminiConfig::from_env()?;
+        if let Some(ref model) = self.models.gemini {
+            config.model = model.clone();
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config = Config::from_toml(
+            "[ollama]\nbase_url = \"http://gpu-box:11434\"\n\n[analyze]\njobs = 8\n",
+        )
+        .unwrap();
+        assert_eq!(config.ollama_config().base_url, "http://gpu-box:11434");
+        assert_eq!(config.ollama_config().timeout_secs, 120);
+        assert_eq!(config.analyze.jobs, Some(8));
+        assert_eq!(config.models, ModelSettings::default());
+        assert_eq!(config.preprocess_options(), PreprocessOptions::default());
+
+        assert_eq!(Config::from_toml("").unwrap(), Config::default());
+        assert!(Config::from_toml("[analyze]\njbos = 8\n").is_err());
+    }
+
+    #[test]
+    fn test_rule_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data-deck.toml");
+        fs::write(&path, "name = \"data-deck\"\n").unwrap();
+        let mut config = Config::default();
+        assert_eq!(config.rule_set(None).unwrap(), RuleSet::default());
+        config.analyze.rules = Some(path.clone());
+        assert_eq!(config.rule_set(None).unwrap().name, "data-deck");
+
+        fs::write(&path, "[fortran]\ncontinuation_column = 0\n").unwrap();
+        assert!(config.rule_set(None).is_err());
+    }
+
+    #[test]
+    fn test_preprocess_options() {
+        let config = Config::from_toml("[preprocess]\ngreenbar = false\n").unwrap();
+        let options = config.preprocess_options();
+        assert!(!options.greenbar);
+        assert!(options.horizontal_lines);
+    }
+
+    #[test]
+    fn test_load_resolves_relative_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE);
+        fs::write(
+            &path,
+            "[analyze]\nrules = \"rules/data-deck.toml\"\n\n\
+             [server]\ndist = \"/srv/dist\"\ndata_dir = \"sets\"\n",
+        )
+        .unwrap();
+        let config = Config::load(&path).unwrap();
+        assert_eq!(
+            config.analyze.rules,
+            Some(dir.path().join("rules/data-deck.toml"))
+        );
+        assert_eq!(config.server.dist, Some(PathBuf::from("/srv/dist")));
+        assert_eq!(config.server.data_dir, Some(dir.path().join("sets")));
+        assert_eq!(config.server.database, None);
+    }
+}
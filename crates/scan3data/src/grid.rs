@@ -0,0 +1,133 @@
+//! Column grid overlays of processed images
+//!
+//! Draws an 80-column grid, at the character pitch detected on each page,
+//! over the processed (preprocessed) image of every artifact, to check by
+//! eye that the text lines up with the columns before trusting
+//! column-based parsing. Overlays are written as PNG named like the raw
+//! image; the scan set is not changed.
+
+use anyhow::{Context, Result};
+use core_pipeline::export::CARD_COLUMNS;
+use core_pipeline::grid::{draw_grid, ColumnGrid};
+use core_pipeline::scan_set;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Overlay of one artifact
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridPage {
+    /// Raw image path (relative to the scan set)
+    pub image: PathBuf,
+    /// Detected grid; no overlay is written without one
+    pub grid: Option<ColumnGrid>,
+    /// Overlay written
+    pub output: Option<PathBuf>,
+}
+
+/// Result of rendering grid overlays
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GridSummary {
+    /// Artifacts with a processed image
+    pub pages: Vec<GridPage>,
+    /// Artifacts without a processed image (not analyzed yet)
+    pub unprocessed: usize,
+}
+
+/// Render grid overlays of the processed images of a scan set into
+/// `output_dir`
+///
+/// `progress` is called with the number of images done and the total.
+pub fn render_grids(
+    scan_set_dir: &Path,
+    output_dir: &Path,
+    progress: &mut (dyn FnMut(usize, usize) + Send),
+) -> Result<GridSummary> {
+    let (_, artifacts) = scan_set::load(scan_set_dir)?;
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create directory: {}", output_dir.display()))?;
+
+    let processed: Vec<_> = artifacts
+        .iter()
+        .filter_map(|a| a.processed_image_path.as_ref().map(|path| (a, path)))
+        .collect();
+    let mut summary = GridSummary {
+        unprocessed: artifacts.len() - processed.len(),
+        ..GridSummary::default()
+    };
+    let total = processed.len();
+    for (done, (artifact, processed_path)) in processed.into_iter().enumerate() {
+        let path = scan_set_dir.join(processed_path);
+        let image = image::open(&path)
+            .with_context(|| format!("Failed to read processed image: {}", path.display()))?
+            .to_luma8();
+
+        let grid = ColumnGrid::detect(&image, CARD_COLUMNS);
+        let output = match (grid, artifact.raw_image_path.file_stem()) {
+            (Some(grid), Some(stem)) => {
+                let output = output_dir.join(stem).with_extension("png");
+                draw_grid(&image, &grid, CARD_COLUMNS)
+                    .save(&output)
+                    .with_context(|| format!("Failed to write: {}", output.display()))?;
+                Some(output)
+            }
+            _ => None,
+        };
+        summary.pages.push(GridPage {
+            image: artifact.raw_image_path.clone(),
+            grid,
+            output,
+        });
+        progress(done + 1, total);
+    }
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{ingest_scan_set, IngestOptions};
+    use core_pipeline::keypunch::KeypunchModel;
+    use image::{GrayImage, Luma};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_render_grids() {
+        let input = TempDir::new().unwrap();
+        for (name, shade) in [("p1.png", 0), ("p2.png", 255)] {
+            GrayImage::from_pixel(8, 8, Luma([shade]))
+                .save(input.path().join(name))
+                .unwrap();
+        }
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("box3");
+        ingest_scan_set(
+            input.path(),
+            &source,
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+
+        // A line of text on a 10 pixel pitch as the first processed image
+        let text = GrayImage::from_fn(1000, 20, |x, y| {
+            Luma([if x % 10 < 6 && (x + y) % 4 != 0 {
+                0
+            } else {
+                255
+            }])
+        });
+        text.save(source.join("text.png")).unwrap();
+        let (_, mut artifacts) = scan_set::load(&source).unwrap();
+        artifacts[0].processed_image_path = Some(PathBuf::from("text.png"));
+        scan_set::save_artifacts(&source, &artifacts).unwrap();
+
+        let output = dir.path().join("grids");
+        let summary = render_grids(&source, &output, &mut |_, _| {}).unwrap();
+        assert_eq!(summary.unprocessed, 1);
+        assert_eq!(summary.pages.len(), 1);
+        let grid = summary.pages[0].grid.unwrap();
+        assert!((grid.pitch - 10.0).abs() < 0.1, "{:?}", grid);
+        assert!(summary.pages[0].output.as_ref().unwrap().is_file());
+    }
+}
@@ -0,0 +1,215 @@
+//! Import existing transcriptions into a scan set
+//!
+//! Some pages were typed in by volunteers before scanning started. A CSV
+//! mapping file pairs each with its scan, one row per page:
+//!
+//! ```text
+//! image,transcription
+//! box3/page-001.jpg,typed/payroll-1.txt
+//! 4f1c2e...,typed/payroll-2.txt
+//! ```
+//!
+//! The image is named by its original file name (the full path given at
+//! ingest, or just the file name if that is unique) or by its content
+//! hash; transcription paths are relative to the mapping file. Imported
+//! text replaces the artifact's content text and marks it as human
+//! transcribed, so analysis keeps it and skips OCR.
+
+use anyhow::{bail, Context, Result};
+use core_pipeline::scan_set;
+use core_pipeline::types::PageArtifact;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Header row of a mapping file (optional)
+const HEADER: [&str; 2] = ["image", "transcription"];
+
+/// Result of importing transcriptions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Artifacts that received a transcription
+    pub imported: usize,
+    /// Images of the mapping that matched no artifact
+    pub unmatched: Vec<String>,
+}
+
+/// Attach the transcriptions listed in a CSV mapping file to the artifacts
+/// of a scan set
+///
+/// Fails, leaving the scan set unchanged, if the mapping cannot be parsed,
+/// an image matches more than one artifact or a transcription cannot be
+/// read. Images matching no artifact are reported in the summary.
+pub fn import_transcriptions(scan_set_dir: &Path, mapping_file: &Path) -> Result<ImportSummary> {
+    let (_, mut artifacts) = scan_set::load(scan_set_dir)?;
+    let mapping = fs::read_to_string(mapping_file)
+        .with_context(|| format!("Failed to read mapping: {}", mapping_file.display()))?;
+    let base_dir = mapping_file.parent().unwrap_or(Path::new(""));
+
+    let mut summary = ImportSummary::default();
+    for (line, image, transcription) in parse_mapping(&mapping)? {
+        let Some(idx) = match_image(&artifacts, &image)
+            .with_context(|| format!("{} line {}", mapping_file.display(), line))?
+        else {
+            summary.unmatched.push(image);
+            continue;
+        };
+        let path = base_dir.join(&transcription);
+        let text = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read transcription: {}", path.display()))?;
+
+        let artifact = &mut artifacts[idx];
+        artifact.content_text = Some(text);
+        artifact.metadata.human_transcribed = true;
+        artifact.metadata.notes.push(format!(
+            "Transcription imported from {}",
+            transcription.display()
+        ));
+        summary.imported += 1;
+    }
+
+    scan_set::save_artifacts(scan_set_dir, &artifacts)?;
+    Ok(summary)
+}
+
+/// Rows of a mapping file as (line number, image, transcription)
+///
+/// Fields may be quoted (with `""` for a quote); blank lines, lines
+/// starting with `#` and the header row are skipped.
+fn parse_mapping(mapping: &str) -> Result<Vec<(usize, String, PathBuf)>> {
+    let mut rows = Vec::new();
+    for (idx, line) in mapping.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_csv_line(line).with_context(|| format!("Mapping line {}", idx + 1))?;
+        match fields.as_slice() {
+            [image, transcription]
+                if rows.is_empty() && [image.as_str(), transcription.as_str()] == HEADER => {}
+            [image, transcription] if !image.is_empty() && !transcription.is_empty() => {
+                rows.push((idx + 1, image.clone(), PathBuf::from(transcription)))
+            }
+            _ => bail!(
+                "Mapping line {}: expected two fields, image and transcription",
+                idx + 1
+            ),
+        }
+    }
+    Ok(rows)
+}
+
+/// Fields of a CSV line, unquoted and trimmed
+fn split_csv_line(line: &str) -> Result<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut chars = line.chars().peekable();
+    let mut quoted = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => fields.push(std::mem::take(&mut field).trim().to_string()),
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        bail!("Unterminated quoted field");
+    }
+    fields.push(field.trim().to_string());
+    Ok(fields)
+}
+
+/// Index of the artifact an image name of the mapping refers to
+fn match_image(artifacts: &[PageArtifact], image: &str) -> Result<Option<usize>> {
+    let by_path = |artifact: &PageArtifact| {
+        artifact.metadata.content_hash.eq_ignore_ascii_case(image)
+            || artifact
+                .metadata
+                .original_filenames
+                .iter()
+                .any(|n| n == image)
+    };
+    if let Some(idx) = artifacts.iter().position(by_path) {
+        return Ok(Some(idx));
+    }
+
+    let by_name: Vec<usize> = artifacts
+        .iter()
+        .enumerate()
+        .filter(|(_, artifact)| {
+            artifact
+                .metadata
+                .original_filenames
+                .iter()
+                .any(|n| Path::new(n).file_name() == Some(image.as_ref()))
+        })
+        .map(|(idx, _)| idx)
+        .collect();
+    match by_name.as_slice() {
+        [] => Ok(None),
+        [idx] => Ok(Some(*idx)),
+        _ => bail!("File name {} matches {} artifacts", image, by_name.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_pipeline::types::{ArtifactKind, PageId, ScanSetId};
+
+    fn page(hash: &str, filename: &str) -> PageArtifact {
+        let mut artifact = PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::new(),
+            processed_image_path: None,
+            layout_label: ArtifactKind::Unknown,
+            content_text: None,
+            metadata: Default::default(),
+        };
+        artifact.metadata.content_hash = hash.to_string();
+        artifact.metadata.original_filenames = vec![filename.to_string()];
+        artifact
+    }
+
+    #[test]
+    fn test_parse_mapping() {
+        let rows = parse_mapping(
+            "image,transcription\n\n# box 3\np1.jpg, typed/p1.txt\n\"a, b.jpg\",\"say \"\"hi\"\".txt\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (4, "p1.jpg".to_string(), PathBuf::from("typed/p1.txt")),
+                (5, "a, b.jpg".to_string(), PathBuf::from("say \"hi\".txt")),
+            ]
+        );
+        assert!(parse_mapping("p1.jpg\n").is_err());
+        assert!(parse_mapping("\"p1.jpg,p1.txt\n").is_err());
+    }
+
+    #[test]
+    fn test_match_image() {
+        let artifacts = vec![
+            page("aa11", "/scans/box3/p1.jpg"),
+            page("bb22", "/scans/box3/p2.jpg"),
+            page("cc33", "/scans/box4/p2.jpg"),
+        ];
+        assert_eq!(match_image(&artifacts, "BB22").unwrap(), Some(1));
+        assert_eq!(
+            match_image(&artifacts, "/scans/box4/p2.jpg").unwrap(),
+            Some(2)
+        );
+        assert_eq!(match_image(&artifacts, "p1.jpg").unwrap(), Some(0));
+        assert!(match_image(&artifacts, "p2.jpg").is_err());
+        assert_eq!(match_image(&artifacts, "p9.jpg").unwrap(), None);
+    }
+}
@@ -0,0 +1,791 @@
+//! Phase 1: ingest scans into a new scan set
+//!
+//! Inputs are image files and PDFs; each PDF page is rasterized and
+//! ingested like a scanned image. A scan set can also be created empty,
+//! for images to be appended later (as the server does).
+
+use crate::pdf::{is_pdf, rasterize_pdf, DEFAULT_PDF_DPI};
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use core_pipeline::image_loader::{is_supported_extension, load_image, LoadOptions};
+use core_pipeline::keypunch::{KeypunchModel, KeypunchSettings};
+use core_pipeline::preprocess::{
+    compute_file_hash, compute_image_hash, detect_duplicate_files, find_near_duplicates,
+    perceptual_hash, DuplicateGroup,
+};
+use core_pipeline::scan_set::{self, ORIGINALS_DIR};
+use core_pipeline::storage::{self, StorageBackend};
+use core_pipeline::types::{
+    ArtifactKind, NearDuplicate, PageArtifact, PageId, PageMetadata, ScanSetId, ScanSetManifest,
+    SourcePage,
+};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Check if a file is a supported image format
+pub fn is_supported_image(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| is_supported_extension(&ext.to_string_lossy()))
+}
+
+/// Near-duplicate distance used when none is configured (bits of 256)
+pub const DEFAULT_NEAR_DUPLICATE_DISTANCE: u32 = 12;
+
+/// Options of the ingest phase
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IngestOptions {
+    /// Resolution PDF pages are rasterized at
+    pub pdf_dpi: u32,
+    /// Images whose perceptual hashes differ in at most this many bits are
+    /// listed in the manifest as suspected near-duplicates
+    pub near_duplicate_distance: u32,
+    /// Also keep the untouched source files (and PDFs) under `originals/`
+    pub copy_originals: bool,
+    /// How a new scan set stores its manifest and artifacts
+    pub storage: StorageBackend,
+}
+
+impl Default for IngestOptions {
+    fn default() -> Self {
+        Self {
+            pdf_dpi: DEFAULT_PDF_DPI,
+            near_duplicate_distance: DEFAULT_NEAR_DUPLICATE_DISTANCE,
+            copy_originals: false,
+            storage: StorageBackend::Json,
+        }
+    }
+}
+
+/// Scratch directory of the scan set PDF pages are rasterized into
+const PDF_PAGES_DIR: &str = ".pdf_pages";
+
+/// Check if a file can be ingested: a supported image or a PDF
+pub(crate) fn is_ingestible(path: &Path) -> bool {
+    is_supported_image(path) || is_pdf(path)
+}
+
+/// Collect all image and PDF files from input path (file or directory),
+/// sorted by path
+pub fn collect_image_files(input_path: &Path) -> Result<Vec<PathBuf>> {
+    if !input_path.exists() {
+        bail!("Input path does not exist: {}", input_path.display());
+    }
+
+    let mut image_files = Vec::new();
+
+    if input_path.is_file() {
+        if is_ingestible(input_path) {
+            image_files.push(input_path.to_path_buf());
+        } else {
+            bail!(
+                "File is not a supported image format or PDF: {}",
+                input_path.display()
+            );
+        }
+    } else if input_path.is_dir() {
+        for entry in WalkDir::new(input_path)
+            .follow_links(true)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let entry_path = entry.path();
+            if entry_path.is_file() && is_ingestible(entry_path) {
+                image_files.push(entry_path.to_path_buf());
+            }
+        }
+    } else {
+        bail!(
+            "Input path is neither a file nor directory: {}",
+            input_path.display()
+        );
+    }
+
+    if image_files.is_empty() {
+        bail!(
+            "No supported image files found in: {}",
+            input_path.display()
+        );
+    }
+
+    image_files.sort();
+    Ok(image_files)
+}
+
+/// Ingest images into a new scan set
+///
+/// Duplicate scans (same content under different names) are stored once,
+/// with all their file names kept in the artifact metadata. Images that
+/// only look alike (e.g. rescans with a different crop) are kept apart
+/// and listed in the manifest for review. PDF pages are rasterized and
+/// recorded as the artifact's source pages. `progress` is called with the
+/// number of images saved so far and the total. Returns the manifest
+/// written to the scan set.
+pub fn ingest_scan_set(
+    input_path: &Path,
+    output_dir: &Path,
+    keypunch: KeypunchModel,
+    options: &IngestOptions,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<ScanSetManifest> {
+    let mut manifest = new_manifest(input_path, keypunch);
+    let mut artifacts = Vec::new();
+    add_images(
+        input_path,
+        output_dir,
+        options,
+        &mut manifest,
+        &mut artifacts,
+        progress,
+    )?;
+
+    let mut store = storage::open_with(output_dir, options.storage)?;
+    store.save_manifest(&manifest)?;
+    store.save_artifacts(&artifacts)?;
+
+    Ok(manifest)
+}
+
+/// Create an empty scan set in a new directory under `data_dir`, named by
+/// its scan set ID
+///
+/// The directory gets the manifest, an empty artifact list (in `storage`)
+/// and the `images/` directory, so [`append_to_scan_set`] and the CLI
+/// commands work on it. Returns the directory and the manifest.
+pub fn create_scan_set(
+    data_dir: &Path,
+    name: &str,
+    keypunch: KeypunchModel,
+    storage: StorageBackend,
+) -> Result<(PathBuf, ScanSetManifest)> {
+    let mut manifest = new_manifest(Path::new(name), keypunch);
+    manifest.name = name.to_string();
+    let scan_set_dir = data_dir.join(manifest.scan_set_id.0.to_string());
+    let images_dir = scan_set_dir.join("images");
+    fs::create_dir_all(&images_dir).with_context(|| {
+        format!(
+            "Failed to create scan set directory: {}",
+            scan_set_dir.display()
+        )
+    })?;
+
+    let mut store = storage::open_with(&scan_set_dir, storage)?;
+    store.save_manifest(&manifest)?;
+    store.save_artifacts(&[])?;
+
+    Ok((scan_set_dir, manifest))
+}
+
+/// Manifest of an empty scan set named after its input directory or file
+pub(crate) fn new_manifest(input_path: &Path, keypunch: KeypunchModel) -> ScanSetManifest {
+    ScanSetManifest {
+        scan_set_id: ScanSetId::new(),
+        name: input_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("scan_set")
+            .to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        image_count: 0,
+        original_file_count: 0,
+        duplicate_count: 0,
+        keypunch: KeypunchSettings {
+            model: keypunch,
+            ..KeypunchSettings::default()
+        },
+        near_duplicates: Vec::new(),
+        spending: Vec::new(),
+        preprocess: Default::default(),
+    }
+}
+
+/// Result of appending images to a scan set
+#[derive(Debug, Clone, Serialize)]
+pub struct AppendSummary {
+    /// The updated manifest
+    pub manifest: ScanSetManifest,
+    /// Image files (and PDF pages) found in the input
+    pub files: usize,
+    /// Artifacts added; the other files duplicate new or existing images
+    pub added: usize,
+    /// Suspected near-duplicates involving the added artifacts
+    pub near_duplicates: usize,
+}
+
+/// Ingest images into an existing scan set
+///
+/// Like [`ingest_scan_set`], but images already in the scan set (same
+/// file or pixels) only add their file names to the existing artifact. The
+/// manifest counts and artifact list are extended in place.
+pub fn append_to_scan_set(
+    input_path: &Path,
+    scan_set_dir: &Path,
+    options: &IngestOptions,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<AppendSummary> {
+    let (mut manifest, mut artifacts) = scan_set::load(scan_set_dir)?;
+    let files_before = manifest.original_file_count;
+    let images_before = manifest.image_count;
+    let near_duplicates_before = manifest.near_duplicates.len();
+    add_images(
+        input_path,
+        scan_set_dir,
+        options,
+        &mut manifest,
+        &mut artifacts,
+        progress,
+    )?;
+
+    scan_set::save_manifest(scan_set_dir, &manifest)?;
+    scan_set::save_artifacts(scan_set_dir, &artifacts)?;
+
+    Ok(AppendSummary {
+        files: manifest.original_file_count - files_before,
+        added: manifest.image_count - images_before,
+        near_duplicates: manifest.near_duplicates.len() - near_duplicates_before,
+        manifest,
+    })
+}
+
+/// Save the images of `input_path` into the scan set, adding an artifact
+/// for each image not yet in `artifacts` and updating the manifest counts
+/// and near-duplicates
+fn add_images(
+    input_path: &Path,
+    output_dir: &Path,
+    options: &IngestOptions,
+    manifest: &mut ScanSetManifest,
+    artifacts: &mut Vec<PageArtifact>,
+    progress: &mut dyn FnMut(usize, usize),
+) -> Result<()> {
+    let input_files = collect_image_files(input_path)?;
+
+    // Rasterize PDFs, remembering which page each rendered image is
+    let pages_dir = output_dir.join(PDF_PAGES_DIR);
+    let mut image_files = Vec::new();
+    let mut source_pages: HashMap<PathBuf, SourcePage> = HashMap::new();
+    for (idx, file) in input_files.iter().enumerate() {
+        if !is_pdf(file) {
+            image_files.push(file.clone());
+            continue;
+        }
+        for (page, image) in rasterize_pdf(file, options.pdf_dpi, &pages_dir.join(idx.to_string()))?
+        {
+            let pdf = file.to_string_lossy().to_string();
+            source_pages.insert(image.clone(), SourcePage { pdf, page });
+            image_files.push(image);
+        }
+    }
+
+    // Hash file bytes, decoding only images that may be re-encoded copies
+    let duplicate_groups = detect_duplicate_files(&image_files)?;
+
+    // Create scan set directory structure
+    let images_dir = output_dir.join("images");
+    fs::create_dir_all(&images_dir).with_context(|| {
+        format!(
+            "Failed to create output directory: {}",
+            output_dir.display()
+        )
+    })?;
+
+    // Source files kept verbatim, including those of earlier ingests
+    let mut copied = CopiedOriginals::default();
+    for stored in artifacts.iter().flat_map(|a| &a.metadata.originals) {
+        if let Some(prefix) = stored
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.get(..16))
+        {
+            copied.by_hash.insert(prefix.to_string(), stored.clone());
+        }
+    }
+    let mut originals_of = |group: &DuplicateGroup| -> Result<Vec<PathBuf>> {
+        let mut originals = Vec::new();
+        if options.copy_originals {
+            for path in &group.filenames {
+                let stored = copy_original(path, output_dir, &source_pages, &mut copied)?;
+                if !originals.contains(&stored) {
+                    originals.push(stored);
+                }
+            }
+        }
+        Ok(originals)
+    };
+
+    // Images already in the scan set only gain file names
+    let existing = ExistingImages {
+        by_file_hash: artifacts
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, a)| Some((a.metadata.file_hash.clone()?, idx)))
+            .collect(),
+        by_pixel_hash: artifacts
+            .iter()
+            .enumerate()
+            .map(|(idx, a)| (a.metadata.content_hash.clone(), idx))
+            .collect(),
+        sizes: artifacts
+            .iter()
+            .filter_map(|a| image::image_dimensions(output_dir.join(&a.raw_image_path)).ok())
+            .collect(),
+    };
+    let mut new_groups = Vec::new();
+    for group in &duplicate_groups {
+        let Some(idx) = existing.find(group)? else {
+            new_groups.push(group);
+            continue;
+        };
+        let metadata = &mut artifacts[idx].metadata;
+        for name in original_filenames(&group.filenames, &source_pages) {
+            if !metadata.original_filenames.contains(&name) {
+                metadata.original_filenames.push(name);
+            }
+        }
+        metadata.source_pages.extend(
+            group
+                .filenames
+                .iter()
+                .filter_map(|p| source_pages.get(p).cloned()),
+        );
+        for stored in originals_of(group)? {
+            if !metadata.originals.contains(&stored) {
+                metadata.originals.push(stored);
+            }
+        }
+    }
+
+    // Number new images by file name, PDF pages in page order
+    new_groups.sort_by_cached_key(|group| {
+        group
+            .filenames
+            .iter()
+            .map(|path| match source_pages.get(path) {
+                Some(source) => (source.pdf.clone(), source.page),
+                None => (path.to_string_lossy().to_string(), 0),
+            })
+            .min()
+    });
+
+    // Save images and create artifacts
+    let first_new = artifacts.len();
+    let unique_count = new_groups.len();
+    for (idx, group) in new_groups.into_iter().enumerate() {
+        progress(idx + 1, unique_count);
+
+        // Decode the first file of the group
+        let source_path = &group.filenames[0];
+        let source_image = load_image(source_path, &LoadOptions::full())?;
+        let perceptual_hash = perceptual_hash(&source_image);
+        let source_image = source_image.to_rgb8();
+        let content_hash = group
+            .pixel_hash
+            .clone()
+            .unwrap_or_else(|| compute_image_hash(&source_image));
+
+        // Save image with hash as filename
+        let image_filename = format!("{}.jpg", &content_hash[..16]); // Use first 16 chars
+        let image_dest = images_dir.join(&image_filename);
+        image::save_buffer(
+            &image_dest,
+            source_image.as_raw(),
+            source_image.width(),
+            source_image.height(),
+            image::ColorType::Rgb8,
+        )?;
+
+        artifacts.push(PageArtifact {
+            id: PageId::new(),
+            scan_set: manifest.scan_set_id,
+            raw_image_path: PathBuf::from("images").join(&image_filename),
+            processed_image_path: None,
+            layout_label: ArtifactKind::Unknown,
+            content_text: None,
+            metadata: PageMetadata {
+                content_hash,
+                file_hash: group.file_hash.clone(),
+                original_filenames: original_filenames(&group.filenames, &source_pages),
+                page_number: None,
+                header: None,
+                footer: None,
+                notes: Vec::new(),
+                confidence: 0.0,
+                revisions: Vec::new(),
+                validation_issues: Vec::new(),
+                derived_images: Vec::new(),
+                cost_usd: 0.0,
+                source_pages: group
+                    .filenames
+                    .iter()
+                    .filter_map(|p| source_pages.get(p).cloned())
+                    .collect(),
+                perceptual_hash: Some(perceptual_hash),
+                ingest_index: first_new + idx,
+                originals: originals_of(group)?,
+                reading_order: None,
+                human_transcribed: false,
+                cleaned_image_path: None,
+                thumbnail_path: None,
+                manual_classification: false,
+                base_confidence: None,
+            },
+        });
+    }
+
+    // Flag new images that look like another image without being identical
+    let hashes: Vec<Option<&str>> = artifacts
+        .iter()
+        .map(|a| a.metadata.perceptual_hash.as_deref())
+        .collect();
+    for (first, second, distance) in
+        find_near_duplicates(&hashes, first_new, options.near_duplicate_distance)
+    {
+        manifest.near_duplicates.push(NearDuplicate {
+            first: artifacts[first].id,
+            second: artifacts[second].id,
+            distance,
+        });
+    }
+
+    if pages_dir.exists() {
+        fs::remove_dir_all(&pages_dir)
+            .with_context(|| format!("Failed to remove PDF pages: {}", pages_dir.display()))?;
+    }
+
+    manifest.image_count += unique_count;
+    manifest.original_file_count += image_files.len();
+    manifest.duplicate_count += image_files.len() - unique_count;
+    Ok(())
+}
+
+/// Source files copied into `originals/`, as paths in the scan set
+#[derive(Default)]
+struct CopiedOriginals {
+    /// By source path, so PDFs are hashed once for all their pages
+    by_source: HashMap<PathBuf, PathBuf>,
+    /// By file hash prefix, so identical files are stored once
+    by_hash: HashMap<String, PathBuf>,
+}
+
+/// Copy the source file of `path` (the PDF of a rasterized page) into the
+/// scan set's `originals/` unless already copied, returning its path in
+/// the scan set
+///
+/// Copies are named by file hash prefix and the name of the first file
+/// with that content, so names never collide.
+fn copy_original(
+    path: &Path,
+    output_dir: &Path,
+    source_pages: &HashMap<PathBuf, SourcePage>,
+    copied: &mut CopiedOriginals,
+) -> Result<PathBuf> {
+    let source = match source_pages.get(path) {
+        Some(page) => PathBuf::from(&page.pdf),
+        None => path.to_path_buf(),
+    };
+    if let Some(stored) = copied.by_source.get(&source) {
+        return Ok(stored.clone());
+    }
+
+    let prefix = compute_file_hash(&source)?[..16].to_string();
+    let stored = match copied.by_hash.get(&prefix) {
+        Some(stored) => stored.clone(),
+        None => {
+            let name = source
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let stored = PathBuf::from(ORIGINALS_DIR).join(format!("{}-{}", prefix, name));
+            fs::create_dir_all(output_dir.join(ORIGINALS_DIR))
+                .with_context(|| format!("Failed to create directory: {}", output_dir.display()))?;
+            fs::copy(&source, output_dir.join(&stored))
+                .with_context(|| format!("Failed to copy original: {}", source.display()))?;
+            copied.by_hash.insert(prefix, stored.clone());
+            stored
+        }
+    };
+    copied.by_source.insert(source, stored.clone());
+    Ok(stored)
+}
+
+/// Images already in a scan set, for matching new ones against
+struct ExistingImages {
+    /// Artifact index by the hash of the file the image was read from
+    by_file_hash: HashMap<String, usize>,
+    /// Artifact index by the hash of the image's pixels
+    by_pixel_hash: HashMap<String, usize>,
+    /// Dimensions of the images
+    sizes: HashSet<(u32, u32)>,
+}
+
+impl ExistingImages {
+    /// Artifact holding the same image as `group`
+    ///
+    /// Files are matched by their bytes first. Only if none matches, and
+    /// the image's size matches an existing one, are its pixels compared.
+    fn find(&self, group: &DuplicateGroup) -> Result<Option<usize>> {
+        for (i, path) in group.filenames.iter().enumerate() {
+            let hash = match &group.file_hash {
+                Some(hash) if i == 0 => hash.clone(),
+                _ => compute_file_hash(path)?,
+            };
+            if let Some(&idx) = self.by_file_hash.get(&hash) {
+                return Ok(Some(idx));
+            }
+        }
+        let pixel_hash = match &group.pixel_hash {
+            Some(hash) => hash.clone(),
+            None => {
+                let path = &group.filenames[0];
+                if !image::image_dimensions(path).is_ok_and(|size| self.sizes.contains(&size)) {
+                    return Ok(None);
+                }
+                let image = load_image(path, &LoadOptions::full())?;
+                compute_image_hash(&image.to_rgb8())
+            }
+        };
+        Ok(self.by_pixel_hash.get(&pixel_hash).copied())
+    }
+}
+
+/// Names of the files a duplicate group came from, with rasterized pages
+/// named by their PDF
+fn original_filenames(
+    filenames: &[PathBuf],
+    source_pages: &HashMap<PathBuf, SourcePage>,
+) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for path in filenames {
+        let name = match source_pages.get(path) {
+            Some(source) => source.pdf.clone(),
+            None => path.to_string_lossy().to_string(),
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_png(path: &Path, shade: u8) {
+        image::GrayImage::from_pixel(8, 8, image::Luma([shade]))
+            .save(path)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_collect_image_files() {
+        let dir = TempDir::new().unwrap();
+        write_png(&dir.path().join("a.png"), 0);
+        fs::create_dir_all(dir.path().join("box")).unwrap();
+        write_png(&dir.path().join("box/b.PNG"), 0);
+        fs::write(dir.path().join("notes.txt"), "x").unwrap();
+        fs::write(dir.path().join("box/listing.pdf"), "%PDF-1.4").unwrap();
+
+        let mut files = collect_image_files(dir.path()).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                dir.path().join("a.png"),
+                dir.path().join("box/b.PNG"),
+                dir.path().join("box/listing.pdf")
+            ]
+        );
+        assert!(collect_image_files(&dir.path().join("notes.txt")).is_err());
+    }
+
+    #[test]
+    fn test_ingest_merges_duplicates() {
+        let input = TempDir::new().unwrap();
+        write_png(&input.path().join("p1.png"), 0);
+        write_png(&input.path().join("copy-of-p1.png"), 0);
+        write_png(&input.path().join("p2.png"), 255);
+        let output = TempDir::new().unwrap();
+
+        let mut calls = Vec::new();
+        let manifest = ingest_scan_set(
+            input.path(),
+            output.path(),
+            KeypunchModel::Ibm026Fortran,
+            &IngestOptions::default(),
+            &mut |done, total| calls.push((done, total)),
+        )
+        .unwrap();
+
+        assert_eq!(manifest.image_count, 2);
+        assert_eq!(manifest.original_file_count, 3);
+        assert_eq!(manifest.duplicate_count, 1);
+        assert_eq!(calls, vec![(1, 2), (2, 2)]);
+        // Blank pages look alike
+        assert_eq!(manifest.near_duplicates.len(), 1);
+        assert_eq!(manifest.near_duplicates[0].distance, 0);
+
+        let (loaded, artifacts) = scan_set::load(output.path()).unwrap();
+        assert_eq!(loaded.keypunch.model, KeypunchModel::Ibm026Fortran);
+        assert_eq!(artifacts.len(), 2);
+        // Numbered by file name: copy-of-p1.png sorts first
+        assert_eq!(artifacts[0].metadata.ingest_index, 0);
+        assert_eq!(
+            artifacts[1].metadata.original_filenames,
+            vec![input.path().join("p2.png").to_string_lossy().to_string()]
+        );
+        assert_eq!(artifacts[1].metadata.ingest_index, 1);
+        for artifact in &artifacts {
+            assert!(output.path().join(&artifact.raw_image_path).is_file());
+        }
+        assert!(artifacts
+            .iter()
+            .any(|a| a.metadata.original_filenames.len() == 2));
+        // Both hashes are kept, each in its own field
+        let p2 = input.path().join("p2.png");
+        assert_eq!(
+            artifacts[1].metadata.file_hash,
+            Some(compute_file_hash(&p2).unwrap())
+        );
+        let pixels = load_image(&p2, &LoadOptions::full()).unwrap().to_rgb8();
+        assert_eq!(
+            artifacts[1].metadata.content_hash,
+            compute_image_hash(&pixels)
+        );
+    }
+
+    #[test]
+    fn test_copy_originals() {
+        let input = TempDir::new().unwrap();
+        write_png(&input.path().join("p1.png"), 0);
+        fs::copy(
+            input.path().join("p1.png"),
+            input.path().join("p1-again.png"),
+        )
+        .unwrap();
+        let output = TempDir::new().unwrap();
+        let options = IngestOptions {
+            copy_originals: true,
+            ..IngestOptions::default()
+        };
+        ingest_scan_set(
+            input.path(),
+            output.path(),
+            KeypunchModel::Ibm029,
+            &options,
+            &mut |_, _| {},
+        )
+        .unwrap();
+
+        let artifacts = scan_set::load_artifacts(output.path()).unwrap();
+        // Byte-identical files are stored once
+        let originals = &artifacts[0].metadata.originals;
+        assert_eq!(originals.len(), 1);
+        assert!(originals[0].starts_with(ORIGINALS_DIR));
+        assert!(originals[0].to_string_lossy().ends_with("-p1-again.png"));
+        assert_eq!(
+            fs::read(output.path().join(&originals[0])).unwrap(),
+            fs::read(input.path().join("p1.png")).unwrap()
+        );
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn test_ingest_into_sqlite_storage() {
+        let input = TempDir::new().unwrap();
+        write_png(&input.path().join("p1.png"), 0);
+        let output = TempDir::new().unwrap();
+        let options = IngestOptions {
+            storage: StorageBackend::Sqlite,
+            ..IngestOptions::default()
+        };
+        ingest_scan_set(
+            input.path(),
+            output.path(),
+            KeypunchModel::Ibm029,
+            &options,
+            &mut |_, _| {},
+        )
+        .unwrap();
+
+        assert_eq!(
+            StorageBackend::detect(output.path()),
+            StorageBackend::Sqlite
+        );
+        assert!(!output.path().join(scan_set::MANIFEST_FILE).exists());
+        assert!(scan_set::exists(output.path()));
+        let (_, artifacts) = scan_set::load(output.path()).unwrap();
+        assert_eq!(artifacts.len(), 1);
+    }
+
+    #[test]
+    fn test_append_dedupes_against_scan_set() {
+        let first = TempDir::new().unwrap();
+        write_png(&first.path().join("p1.png"), 0);
+        let output = TempDir::new().unwrap();
+        ingest_scan_set(
+            first.path(),
+            output.path(),
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+
+        let second = TempDir::new().unwrap();
+        write_png(&second.path().join("rescan-p1.png"), 0);
+        write_png(&second.path().join("p2.png"), 255);
+        let summary = append_to_scan_set(
+            second.path(),
+            output.path(),
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+
+        assert_eq!((summary.files, summary.added), (2, 1));
+        assert_eq!(summary.manifest.image_count, 2);
+        assert_eq!(summary.manifest.original_file_count, 3);
+        assert_eq!(summary.manifest.duplicate_count, 1);
+
+        let (loaded, artifacts) = scan_set::load(output.path()).unwrap();
+        assert_eq!(loaded.scan_set_id, summary.manifest.scan_set_id);
+        assert_eq!(artifacts.len(), 2);
+        assert_eq!(artifacts[0].metadata.original_filenames.len(), 2);
+        assert_eq!(artifacts[1].metadata.ingest_index, 1);
+        assert!(artifacts.iter().all(|a| a.scan_set == loaded.scan_set_id));
+    }
+
+    #[test]
+    fn test_create_scan_set() {
+        let data = TempDir::new().unwrap();
+        let (dir, manifest) = create_scan_set(
+            data.path(),
+            "Box 3",
+            KeypunchModel::Ibm029,
+            StorageBackend::Json,
+        )
+        .unwrap();
+        assert_eq!(dir, data.path().join(manifest.scan_set_id.0.to_string()));
+        assert!(dir.join("images").is_dir());
+
+        let (loaded, artifacts) = scan_set::load(&dir).unwrap();
+        assert_eq!(loaded.scan_set_id, manifest.scan_set_id);
+        assert_eq!(loaded.name, "Box 3");
+        assert!(artifacts.is_empty());
+
+        let input = TempDir::new().unwrap();
+        write_png(&input.path().join("p1.png"), 0);
+        let summary = append_to_scan_set(
+            input.path(),
+            &dir,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+        assert_eq!(summary.added, 1);
+    }
+}
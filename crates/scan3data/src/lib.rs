@@ -0,0 +1,76 @@
+//! scan3data pipeline library
+//!
+//! The steps behind the `scan3data` commands, callable from the server,
+//! tests and other tools without going through the command line:
+//! - [`ingest`] - Phase 1: turn a directory of scans into a scan set
+//! - [`pdf`] - Rasterize PDF pages for ingest
+//! - [`watch`] - Ingest scans as they are dropped into a directory
+//! - [`merge`] - Combine two scan sets into one
+//! - [`split`] - Split a scan set by classification or selection
+//! - [`pack`] - Share a scan set as a single zip file
+//! - [`artifact`] - Look up and remove single artifacts
+//! - [`import_text`] - Attach existing transcriptions to artifacts
+//! - [`thumbnails`] - Small previews of the scans
+//! - [`clean`] - Remove greenbar backgrounds with Gemini
+//! - [`analyze`] - Phase 2: OCR, correction, classification and validation
+//! - [`grid`] - Column grid overlays to check column alignment
+//! - [`bench`] - Compare OCR configurations against ground truth
+//! - [`relabel`] - Fix the classification of many artifacts at once
+//! - [`reorder`] - Put shuffled pages in reading order
+//! - [`text_dump`] - OCR text of a scan set as one plain text file
+//! - [`compare`] - HTML view of each scan next to its text
+//! - [`annotate`] - Sidecar files describing each scan, for photo tools
+//! - [`config`] - Defaults from `scan3data.toml`, shared with the server
+//!
+//! Functions take paths and option structs and return summaries; they
+//! print nothing, leaving output to the caller. Long-running steps report
+//! progress through a callback.
+//!
+//! Copyright (c) 2025 Michael A Wright
+
+pub mod analyze;
+pub mod annotate;
+pub mod artifact;
+pub mod bench;
+pub mod clean;
+pub mod compare;
+pub mod config;
+pub mod grid;
+pub mod import_text;
+pub mod ingest;
+pub mod merge;
+pub mod pack;
+pub mod pdf;
+pub mod relabel;
+pub mod reorder;
+pub mod split;
+pub mod text_dump;
+pub mod thumbnails;
+pub mod watch;
+
+pub use analyze::{
+    analyze_scan_set, reprocess_artifact, AnalyzeOptions, AnalyzePhase, AnalyzeProgress,
+    AnalyzeSummary, ArtifactResult,
+};
+pub use annotate::{annotate_scan_set, AnnotateSummary};
+pub use artifact::{find_artifact, remove_artifact, RemovedArtifact};
+pub use bench::{bench_scan_set, BenchArtifact, BenchReport};
+pub use clean::{clean_scan_set, estimate_clean, CleanEstimate, CleanOptions, CleanSummary};
+pub use compare::{comparison_html, generate_comparison_html, CompareOptions, ImageSource};
+pub use config::Config;
+pub use grid::{render_grids, GridPage, GridSummary};
+pub use import_text::{import_transcriptions, ImportSummary};
+pub use ingest::{
+    append_to_scan_set, collect_image_files, create_scan_set, ingest_scan_set, AppendSummary,
+    IngestOptions,
+};
+pub use merge::{merge_scan_sets, MergeSummary};
+pub use pack::{pack_scan_set, unpack_scan_set, PackSummary};
+pub use relabel::{relabel_scan_set, Relabel};
+pub use reorder::{reorder_scan_set, ReorderOptions, ReorderSummary};
+pub use split::{split_by_kind, split_selection, SplitPart};
+pub use text_dump::{
+    text_dump, text_dump_scan_set, text_dump_split, TextDumpFormat, TextDumpSummary,
+};
+pub use thumbnails::{generate_thumbnails, ThumbnailSummary};
+pub use watch::{watch_folder, WatchEvent};
@@ -0,0 +1,302 @@
+//! Merge two scan sets into a new one
+//!
+//! Boxes of cards arrive in several shipments, each ingested (and maybe
+//! analyzed) as its own scan set. Merging keeps every artifact with its
+//! analysis results: artifacts of the second set holding an image already
+//! in the first (same content hash) only add their file names to it.
+//!
+//! Cards and reconstructed listings are not merged; run `reconstruct` on
+//! the merged scan set instead.
+
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use core_pipeline::derived::DERIVED_DIR;
+use core_pipeline::keypunch::KeypunchSettings;
+use core_pipeline::preprocess::find_near_duplicates;
+use core_pipeline::scan_set;
+use core_pipeline::score::REFERENCE_DIR;
+use core_pipeline::stage_cache::CACHE_DIR;
+use core_pipeline::types::{NearDuplicate, PageArtifact, ScanSetId, ScanSetManifest};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+/// Result of merging two scan sets
+#[derive(Debug, Clone)]
+pub struct MergeSummary {
+    /// Manifest of the merged scan set
+    pub manifest: ScanSetManifest,
+    /// Artifacts of the second scan set whose image was already in the first
+    pub duplicates: usize,
+}
+
+/// Merge scan sets `a` and `b` into a new scan set at `output_dir`
+///
+/// Artifacts of `a` come first, in their order, then the artifacts of `b`
+/// not already in `a`. Images, originals, derived images, cached stage
+/// results and reference transcripts are copied; near-duplicates are recomputed over the merged
+/// artifacts. Both scan sets must use the same keypunch settings.
+pub fn merge_scan_sets(
+    a: &Path,
+    b: &Path,
+    output_dir: &Path,
+    near_duplicate_distance: u32,
+) -> Result<MergeSummary> {
+    let (manifest_a, artifacts_a) = scan_set::load(a)?;
+    let (manifest_b, artifacts_b) = scan_set::load(b)?;
+    if scan_set::exists(output_dir) {
+        bail!("Scan set already exists: {}", output_dir.display());
+    }
+    let keypunch = merge_keypunch(&manifest_a.keypunch, &manifest_b.keypunch)?;
+
+    let mut manifest = ScanSetManifest {
+        scan_set_id: ScanSetId::new(),
+        name: output_dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("scan_set")
+            .to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        image_count: 0,
+        original_file_count: manifest_a.original_file_count + manifest_b.original_file_count,
+        duplicate_count: 0,
+        keypunch,
+        near_duplicates: Vec::new(),
+        // Money spent on either set stays spent, duplicates or not
+        spending: [&manifest_a.spending, &manifest_b.spending]
+            .into_iter()
+            .flatten()
+            .cloned()
+            .collect(),
+        // Stored results of the second set under other steps are redone
+        // by the next analysis
+        preprocess: manifest_a.preprocess,
+    };
+
+    let mut artifacts: Vec<PageArtifact> = Vec::new();
+    let mut by_hash: HashMap<String, usize> = HashMap::new();
+    let mut duplicates = 0;
+    for (source_dir, source_artifacts) in [(a, artifacts_a), (b, artifacts_b)] {
+        for mut artifact in source_artifacts {
+            match by_hash.get(&artifact.metadata.content_hash) {
+                Some(&idx) => {
+                    duplicates += 1;
+                    let kept = &mut artifacts[idx].metadata;
+                    let merged = artifact.metadata;
+                    for name in merged.original_filenames {
+                        if !kept.original_filenames.contains(&name) {
+                            kept.original_filenames.push(name);
+                        }
+                    }
+                    for original in merged.originals {
+                        if !kept.originals.contains(&original) {
+                            copy_missing(&source_dir.join(&original), &output_dir.join(&original))?;
+                            kept.originals.push(original);
+                        }
+                    }
+                    for source in merged.source_pages {
+                        if !kept.source_pages.contains(&source) {
+                            kept.source_pages.push(source);
+                        }
+                    }
+                }
+                None => {
+                    copy_files(source_dir, output_dir, &artifact)?;
+                    artifact.scan_set = manifest.scan_set_id;
+                    artifact.metadata.ingest_index = artifacts.len();
+                    if !artifact.metadata.content_hash.is_empty() {
+                        by_hash.insert(artifact.metadata.content_hash.clone(), artifacts.len());
+                    }
+                    artifacts.push(artifact);
+                }
+            }
+        }
+        // Cached results are keyed by content and reference transcripts by
+        // image name, so both sets' files can live side by side
+        for dir in [DERIVED_DIR, CACHE_DIR, REFERENCE_DIR] {
+            copy_tree(&source_dir.join(dir), &output_dir.join(dir))?;
+        }
+    }
+    manifest.image_count = artifacts.len();
+    manifest.duplicate_count = manifest.original_file_count.saturating_sub(artifacts.len());
+
+    let hashes: Vec<Option<&str>> = artifacts
+        .iter()
+        .map(|a| a.metadata.perceptual_hash.as_deref())
+        .collect();
+    for (first, second, distance) in find_near_duplicates(&hashes, 0, near_duplicate_distance) {
+        manifest.near_duplicates.push(NearDuplicate {
+            first: artifacts[first].id,
+            second: artifacts[second].id,
+            distance,
+        });
+    }
+
+    scan_set::save_manifest(output_dir, &manifest)?;
+    scan_set::save_artifacts(output_dir, &artifacts)?;
+
+    Ok(MergeSummary {
+        manifest,
+        duplicates,
+    })
+}
+
+/// Keypunch settings of the merged scan set; per-document models are
+/// combined as long as they agree
+fn merge_keypunch(a: &KeypunchSettings, b: &KeypunchSettings) -> Result<KeypunchSettings> {
+    if a.model != b.model {
+        bail!(
+            "Scan sets were punched on different keypunches ({} and {})",
+            a.model.as_str(),
+            b.model.as_str()
+        );
+    }
+    let mut merged = a.clone();
+    for (document, &model) in &b.documents {
+        match merged.documents.insert(document.clone(), model) {
+            Some(previous) if previous != model => bail!(
+                "Document {} has different keypunches ({} and {})",
+                document,
+                previous.as_str(),
+                model.as_str()
+            ),
+            _ => {}
+        }
+    }
+    Ok(merged)
+}
+
+/// Copy the images, kept originals and cleaned image of an artifact into
+/// another scan set
+///
+/// All of them are named by content, so files already there are the same
+/// and are left alone. Derived images and thumbnails are a cache and are
+/// skipped if gone.
+pub(crate) fn copy_files(
+    source_dir: &Path,
+    output_dir: &Path,
+    artifact: &PageArtifact,
+) -> Result<()> {
+    let kept = std::iter::once(&artifact.raw_image_path)
+        .chain(&artifact.metadata.originals)
+        .chain(&artifact.metadata.cleaned_image_path);
+    for path in kept {
+        copy_missing(&source_dir.join(path), &output_dir.join(path))?;
+    }
+    let derived = artifact
+        .processed_image_path
+        .iter()
+        .chain(artifact.metadata.derived_images.iter().map(|d| &d.path))
+        .chain(&artifact.metadata.thumbnail_path);
+    for path in derived {
+        if source_dir.join(path).is_file() {
+            copy_missing(&source_dir.join(path), &output_dir.join(path))?;
+        }
+    }
+    Ok(())
+}
+
+/// Copy the files of a directory tree that are not at the destination yet
+fn copy_tree(source: &Path, dest: &Path) -> Result<()> {
+    if !source.is_dir() {
+        return Ok(());
+    }
+    for entry in WalkDir::new(source) {
+        let entry = entry?;
+        if entry.file_type().is_file() {
+            let relative = entry.path().strip_prefix(source)?;
+            copy_missing(entry.path(), &dest.join(relative))?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_missing(source: &Path, dest: &Path) -> Result<()> {
+    if dest.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+    }
+    fs::copy(source, dest)
+        .with_context(|| format!("Failed to copy {} to {}", source.display(), dest.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{ingest_scan_set, IngestOptions, DEFAULT_NEAR_DUPLICATE_DISTANCE};
+    use core_pipeline::keypunch::KeypunchModel;
+    use tempfile::TempDir;
+
+    fn write_png(path: &Path, shade: u8) {
+        image::GrayImage::from_pixel(8, 8, image::Luma([shade]))
+            .save(path)
+            .unwrap();
+    }
+
+    fn scan_set(dir: &Path, files: &[(&str, u8)]) {
+        let input = TempDir::new().unwrap();
+        for (name, shade) in files {
+            write_png(&input.path().join(name), *shade);
+        }
+        ingest_scan_set(
+            input.path(),
+            dir,
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_merge_scan_sets() {
+        let dir = TempDir::new().unwrap();
+        let (a, b, output) = (
+            dir.path().join("a"),
+            dir.path().join("b"),
+            dir.path().join("combined"),
+        );
+        scan_set(&a, &[("p1.png", 0), ("p2.png", 255)]);
+        scan_set(&b, &[("p2-again.png", 255), ("p3.png", 128)]);
+
+        let summary = merge_scan_sets(&a, &b, &output, DEFAULT_NEAR_DUPLICATE_DISTANCE).unwrap();
+        assert_eq!(summary.duplicates, 1);
+        assert_eq!(summary.manifest.name, "combined");
+        assert_eq!(summary.manifest.image_count, 3);
+        assert_eq!(summary.manifest.original_file_count, 4);
+        assert_eq!(summary.manifest.duplicate_count, 1);
+
+        let (manifest, artifacts) = scan_set::load(&output).unwrap();
+        assert_eq!(manifest.scan_set_id, summary.manifest.scan_set_id);
+        assert_eq!(artifacts.len(), 3);
+        let names: Vec<usize> = artifacts
+            .iter()
+            .map(|a| a.metadata.original_filenames.len())
+            .collect();
+        assert_eq!(names, vec![1, 2, 1]);
+        for (idx, artifact) in artifacts.iter().enumerate() {
+            assert_eq!(artifact.scan_set, manifest.scan_set_id);
+            assert_eq!(artifact.metadata.ingest_index, idx);
+            assert!(output.join(&artifact.raw_image_path).is_file());
+        }
+
+        // Merging into an existing scan set is refused
+        assert!(merge_scan_sets(&a, &b, &output, DEFAULT_NEAR_DUPLICATE_DISTANCE).is_err());
+    }
+
+    #[test]
+    fn test_merge_requires_same_keypunch() {
+        let a = KeypunchSettings::default();
+        let b = KeypunchSettings {
+            model: KeypunchModel::Ibm026Fortran,
+            ..KeypunchSettings::default()
+        };
+        assert!(merge_keypunch(&a, &a).is_ok());
+        assert!(merge_keypunch(&a, &b).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+//! Pack a scan set into a single zip file, and unpack it again
+//!
+//! To share a complete scan set with another researcher, everything in it
+//! (manifest, artifacts, images, originals, derived images, exports) is
+//! packed into one zip archive. The stage cache is left out, since it only
+//! holds results that can be recomputed. The archive carries the SHA-256
+//! of every file in [`CHECKSUMS_FILE`], and unpacking verifies them.
+
+use anyhow::{bail, Context, Result};
+use core_pipeline::preprocess::compute_file_hash;
+use core_pipeline::scan_set;
+use core_pipeline::stage_cache::CACHE_DIR;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read};
+use std::path::Path;
+use walkdir::WalkDir;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+/// Checksum list at the root of a pack, as `<sha256>  <path>` lines
+pub const CHECKSUMS_FILE: &str = "pack-sha256.txt";
+
+/// What a pack holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackSummary {
+    /// Number of scan set files
+    pub files: usize,
+    /// Their total size in bytes (uncompressed)
+    pub bytes: u64,
+}
+
+/// Pack a scan set into a zip file
+pub fn pack_scan_set(scan_set_dir: &Path, pack_file: &Path) -> Result<PackSummary> {
+    scan_set::load_manifest(scan_set_dir)?;
+    // The pack may be written inside the scan set; leave it out
+    let pack_path = pack_file
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."))
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve: {}", pack_file.display()))?
+        .join(pack_file.file_name().unwrap_or_default());
+    let scan_set_path = scan_set_dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve: {}", scan_set_dir.display()))?;
+
+    let mut files = Vec::new();
+    for entry in WalkDir::new(&scan_set_path).sort_by_file_name() {
+        let entry = entry?;
+        let relative = entry.path().strip_prefix(&scan_set_path)?;
+        if entry.file_type().is_file()
+            && !relative.starts_with(CACHE_DIR)
+            && entry.path() != pack_path
+        {
+            files.push(relative.to_path_buf());
+        }
+    }
+
+    let file = File::create(pack_file)
+        .with_context(|| format!("Failed to create: {}", pack_file.display()))?;
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+    let mut checksums = String::new();
+    let mut summary = PackSummary { files: 0, bytes: 0 };
+    for relative in &files {
+        let path = scan_set_path.join(relative);
+        let name = zip_name(relative)?;
+        zip.start_file(name.as_str(), options)?;
+        let mut source =
+            File::open(&path).with_context(|| format!("Failed to open: {}", path.display()))?;
+        summary.bytes += io::copy(&mut source, &mut zip)
+            .with_context(|| format!("Failed to pack: {}", path.display()))?;
+        summary.files += 1;
+        let _ = writeln!(checksums, "{}  {}", compute_file_hash(&path)?, name);
+    }
+    zip.start_file(CHECKSUMS_FILE, options)?;
+    io::Write::write_all(&mut zip, checksums.as_bytes())?;
+    zip.finish()?;
+
+    Ok(summary)
+}
+
+/// Unpack a zip file written by [`pack_scan_set`] into `output_dir`
+///
+/// `output_dir` must not hold a scan set. Fails if a file is missing or
+/// its checksum does not match; the unpacked files are left in place for
+/// inspection.
+pub fn unpack_scan_set(pack_file: &Path, output_dir: &Path) -> Result<PackSummary> {
+    if scan_set::exists(output_dir) {
+        bail!("Scan set already exists: {}", output_dir.display());
+    }
+    let file = File::open(pack_file)
+        .with_context(|| format!("Failed to open: {}", pack_file.display()))?;
+    let mut zip = ZipArchive::new(BufReader::new(file))
+        .with_context(|| format!("Not a zip file: {}", pack_file.display()))?;
+
+    let checksums = {
+        let mut entry = zip
+            .by_name(CHECKSUMS_FILE)
+            .with_context(|| format!("Not a scan set pack (no {})", CHECKSUMS_FILE))?;
+        let mut text = String::new();
+        entry.read_to_string(&mut text)?;
+        parse_checksums(&text)?
+    };
+
+    let mut summary = PackSummary { files: 0, bytes: 0 };
+    for idx in 0..zip.len() {
+        let mut entry = zip.by_index(idx)?;
+        if entry.is_dir() || entry.name() == CHECKSUMS_FILE {
+            continue;
+        }
+        let Some(relative) = entry.enclosed_name() else {
+            bail!("Unsafe path in pack: {}", entry.name());
+        };
+        if !checksums.contains_key(entry.name()) {
+            bail!("Not in {}: {}", CHECKSUMS_FILE, entry.name());
+        }
+        let path = output_dir.join(relative);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+        let mut dest =
+            File::create(&path).with_context(|| format!("Failed to create: {}", path.display()))?;
+        summary.bytes += io::copy(&mut entry, &mut dest)
+            .with_context(|| format!("Failed to unpack: {}", path.display()))?;
+        summary.files += 1;
+    }
+
+    let mut problems = Vec::new();
+    for (name, hash) in &checksums {
+        let path = output_dir.join(name);
+        if !path.is_file() {
+            problems.push(format!("Missing: {}", name));
+        } else if compute_file_hash(&path)? != *hash {
+            problems.push(format!("Checksum mismatch: {}", name));
+        }
+    }
+    if !problems.is_empty() {
+        bail!("Pack is damaged:\n  {}", problems.join("\n  "));
+    }
+
+    Ok(summary)
+}
+
+/// Name of a file in the zip (`/`-separated)
+fn zip_name(relative: &Path) -> Result<String> {
+    let parts: Option<Vec<&str>> = relative.iter().map(|part| part.to_str()).collect();
+    match parts {
+        Some(parts) => Ok(parts.join("/")),
+        None => bail!("File name is not valid UTF-8: {}", relative.display()),
+    }
+}
+
+/// Checksums by file name
+fn parse_checksums(text: &str) -> Result<BTreeMap<String, String>> {
+    let mut checksums = BTreeMap::new();
+    for line in text.lines().filter(|l| !l.trim().is_empty()) {
+        let Some((hash, name)) = line.split_once("  ") else {
+            bail!("Malformed {} line: {}", CHECKSUMS_FILE, line);
+        };
+        checksums.insert(name.to_string(), hash.to_string());
+    }
+    Ok(checksums)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{ingest_scan_set, IngestOptions};
+    use core_pipeline::keypunch::KeypunchModel;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_pack_round_trip() {
+        let input = TempDir::new().unwrap();
+        image::GrayImage::from_pixel(8, 8, image::Luma([0]))
+            .save(input.path().join("p1.png"))
+            .unwrap();
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("box3");
+        ingest_scan_set(
+            input.path(),
+            &source,
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+        fs::create_dir_all(source.join(CACHE_DIR)).unwrap();
+        fs::write(source.join(CACHE_DIR).join("x.json"), "{}").unwrap();
+
+        // Packed into the scan set itself, which must not pack itself
+        let pack = source.join("box3.zip");
+        let packed = pack_scan_set(&source, &pack).unwrap();
+        assert_eq!(packed.files, 3);
+
+        let output = dir.path().join("copy");
+        let unpacked = unpack_scan_set(&pack, &output).unwrap();
+        assert_eq!(unpacked, packed);
+        let (_, artifacts) = scan_set::load(&output).unwrap();
+        assert!(output.join(&artifacts[0].raw_image_path).is_file());
+        assert!(!output.join(CACHE_DIR).exists());
+
+        // Unpacking over a scan set is refused
+        assert!(unpack_scan_set(&pack, &output).is_err());
+    }
+
+    #[test]
+    fn test_unpack_detects_damage() {
+        let dir = TempDir::new().unwrap();
+        let pack = dir.path().join("bad.zip");
+        let mut zip = ZipWriter::new(File::create(&pack).unwrap());
+        let options = SimpleFileOptions::default();
+        zip.start_file(scan_set::MANIFEST_FILE, options).unwrap();
+        zip.write_all(b"{}").unwrap();
+        zip.start_file(CHECKSUMS_FILE, options).unwrap();
+        zip.write_all(format!("{}  {}\n", "0".repeat(64), scan_set::MANIFEST_FILE).as_bytes())
+            .unwrap();
+        zip.finish().unwrap();
+
+        let error = unpack_scan_set(&pack, &dir.path().join("out")).unwrap_err();
+        assert!(error.to_string().contains("Checksum mismatch"));
+    }
+}
@@ -0,0 +1,85 @@
+//! PDF rasterization for ingest
+//!
+//! Pages are rendered with `pdftoppm` from poppler-utils (like Tesseract,
+//! an external program), one PNG per page.
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Resolution PDF pages are rasterized at by default
+pub const DEFAULT_PDF_DPI: u32 = 300;
+
+/// Check if a file is a PDF
+pub fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+/// Rasterize every page of a PDF into `out_dir` at `dpi`
+///
+/// Returns the page images with their page index (starting at 1), in page
+/// order.
+pub fn rasterize_pdf(pdf: &Path, dpi: u32, out_dir: &Path) -> Result<Vec<(u32, PathBuf)>> {
+    fs::create_dir_all(out_dir)
+        .with_context(|| format!("Failed to create directory: {}", out_dir.display()))?;
+
+    let output = Command::new("pdftoppm")
+        .arg("-r")
+        .arg(dpi.to_string())
+        .arg("-png")
+        .arg(pdf)
+        .arg(out_dir.join("page"))
+        .output()
+        .context("Failed to run pdftoppm (install poppler-utils to ingest PDFs)")?;
+    if !output.status.success() {
+        bail!(
+            "pdftoppm failed on {}: {}",
+            pdf.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    let mut pages = Vec::new();
+    for entry in fs::read_dir(out_dir)
+        .with_context(|| format!("Failed to read directory: {}", out_dir.display()))?
+    {
+        let path = entry?.path();
+        if let Some(page) = page_index(&path) {
+            pages.push((page, path));
+        }
+    }
+    if pages.is_empty() {
+        bail!("No pages rendered from PDF: {}", pdf.display());
+    }
+    pages.sort();
+    Ok(pages)
+}
+
+/// Page index of a `pdftoppm` output file (`page-7.png`, `page-007.png`)
+fn page_index(path: &Path) -> Option<u32> {
+    if path.extension()? != "png" {
+        return None;
+    }
+    path.file_stem()?
+        .to_str()?
+        .strip_prefix("page-")?
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_page_index() {
+        assert_eq!(page_index(Path::new("out/page-7.png")), Some(7));
+        assert_eq!(page_index(Path::new("out/page-012.png")), Some(12));
+        assert_eq!(page_index(Path::new("out/page-1.ppm")), None);
+        assert_eq!(page_index(Path::new("out/cover.png")), None);
+        assert!(is_pdf(Path::new("box3/LISTING.PDF")));
+        assert!(!is_pdf(Path::new("box3/listing.png")));
+    }
+}
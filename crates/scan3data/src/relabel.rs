@@ -0,0 +1,134 @@
+//! Bulk reclassification
+//!
+//! The heuristic classifier tends to get whole batches wrong the same way
+//! (short cards taken for `Unknown`, runtime output for a listing). A
+//! relabel sets the classification of every artifact that has a given
+//! kind and meets an [`ArtifactFilter`]. Relabeled artifacts are marked as
+//! classified by hand, with full confidence, so a later `analyze` keeps
+//! the new kind.
+
+use anyhow::Result;
+use core_pipeline::filter::ArtifactFilter;
+use core_pipeline::scan_set;
+use core_pipeline::types::ArtifactKind;
+use std::path::{Path, PathBuf};
+
+/// Classification change of one artifact
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relabel {
+    /// Artifact identifier
+    pub artifact_id: String,
+    /// Raw image path (relative to the scan set)
+    pub image: PathBuf,
+    /// Classification before
+    pub from: ArtifactKind,
+    /// Characters of text, to judge the selection by
+    pub text_len: usize,
+}
+
+/// Set the classification of the artifacts of kind `from` (any kind if
+/// `None`) that meet `filter` to `to`
+///
+/// Artifacts already of kind `to` are left alone. With `dry_run` nothing
+/// is saved; the changes that would be made are returned either way.
+pub fn relabel_scan_set(
+    scan_set_dir: &Path,
+    from: Option<ArtifactKind>,
+    to: ArtifactKind,
+    filter: &ArtifactFilter,
+    dry_run: bool,
+) -> Result<Vec<Relabel>> {
+    let (_, mut artifacts) = scan_set::load(scan_set_dir)?;
+    let mut changes = Vec::new();
+    for artifact in &mut artifacts {
+        if artifact.layout_label == to
+            || from.is_some_and(|from| artifact.layout_label != from)
+            || !filter.matches(artifact)
+        {
+            continue;
+        }
+        changes.push(Relabel {
+            artifact_id: artifact.id.0.to_string(),
+            image: artifact.raw_image_path.clone(),
+            from: artifact.layout_label,
+            text_len: artifact
+                .content_text
+                .as_deref()
+                .map_or(0, |t| t.chars().count()),
+        });
+        artifact.metadata.notes.push(format!(
+            "Relabeled from {:?} to {:?}",
+            artifact.layout_label, to
+        ));
+        artifact.layout_label = to;
+        artifact.metadata.confidence = 1.0;
+        artifact.metadata.base_confidence = None;
+        artifact.metadata.manual_classification = true;
+    }
+
+    if !dry_run && !changes.is_empty() {
+        scan_set::save_artifacts(scan_set_dir, &artifacts)?;
+    }
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{ingest_scan_set, IngestOptions};
+    use core_pipeline::keypunch::KeypunchModel;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_relabel_scan_set() {
+        let input = TempDir::new().unwrap();
+        for (name, shade) in [("p1.png", 0), ("p2.png", 128), ("p3.png", 255)] {
+            image::GrayImage::from_pixel(8, 8, image::Luma([shade]))
+                .save(input.path().join(name))
+                .unwrap();
+        }
+        let dir = TempDir::new().unwrap();
+        ingest_scan_set(
+            input.path(),
+            dir.path(),
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+        let (_, mut artifacts) = scan_set::load(dir.path()).unwrap();
+        artifacts[0].content_text = Some("      CALL EXIT".to_string());
+        artifacts[1].content_text = Some("X".repeat(200));
+        artifacts[2].content_text = Some("      END".to_string());
+        artifacts[2].layout_label = ArtifactKind::ListingSource;
+        scan_set::save_artifacts(dir.path(), &artifacts).unwrap();
+
+        let filter = ArtifactFilter::parse(&["text-len<90"]).unwrap();
+        let relabel = |dry_run| {
+            relabel_scan_set(
+                dir.path(),
+                Some(ArtifactKind::Unknown),
+                ArtifactKind::CardText,
+                &filter,
+                dry_run,
+            )
+            .unwrap()
+        };
+
+        // A preview changes nothing
+        let preview = relabel(true);
+        assert_eq!(preview.len(), 1);
+        assert_eq!(preview[0].image, artifacts[0].raw_image_path);
+        assert_eq!(preview[0].text_len, 15);
+        let (_, unchanged) = scan_set::load(dir.path()).unwrap();
+        assert_eq!(unchanged[0].layout_label, ArtifactKind::Unknown);
+
+        assert_eq!(relabel(false), preview);
+        let (_, relabeled) = scan_set::load(dir.path()).unwrap();
+        assert_eq!(relabeled[0].layout_label, ArtifactKind::CardText);
+        assert!(relabeled[0].metadata.manual_classification);
+        assert_eq!(relabeled[1].layout_label, ArtifactKind::Unknown);
+        assert_eq!(relabeled[2].layout_label, ArtifactKind::ListingSource);
+        assert!(relabel(false).is_empty());
+    }
+}
@@ -0,0 +1,191 @@
+//! Put the pages of a scan set in reading order
+//!
+//! Pages come back from the scanner shuffled, so pages are grouped into
+//! documents by the name in their running header rather than by scan
+//! order. Each document is ordered by the page numbers in its headers and
+//! footers; a document with unnumbered pages is ordered by the text model
+//! instead, when enabled, from the first and last lines of each page. The
+//! resulting position is stored in each artifact's `reading_order` and the
+//! artifacts are saved in that order, ready for `reconstruct`.
+
+use anyhow::Result;
+use core_pipeline::reconstruct::{document_name, extract_headers, order_pages};
+use core_pipeline::scan_set;
+use core_pipeline::types::PageArtifact;
+use llm_bridge::text::{OrderingItem, DEFAULT_TEXT_MODEL};
+use llm_bridge::{OllamaClient, OllamaConfig, TextModel};
+use std::path::Path;
+
+/// Lines from each end of a page shown to the text model
+const CONTEXT_LINES: usize = 3;
+
+/// Options of the reorder step
+#[derive(Debug, Clone, Default)]
+pub struct ReorderOptions {
+    /// Ollama server the text model runs on
+    pub ollama: OllamaConfig,
+    /// Order documents with unnumbered pages with the text model
+    pub use_llm: bool,
+    /// Text model (qwen2.5:3b if unset)
+    pub text_model: Option<String>,
+}
+
+/// Result of reordering a scan set
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReorderSummary {
+    /// Documents found
+    pub documents: usize,
+    /// Documents ordered by page number
+    pub by_page_number: usize,
+    /// Documents ordered by the text model
+    pub by_model: usize,
+    /// Documents the text model failed on (ordered by page number instead)
+    pub model_failures: usize,
+    /// Pages whose position changed
+    pub moved: usize,
+}
+
+/// Order the pages of a scan set and save them in reading order
+pub async fn reorder_scan_set(
+    scan_set_dir: &Path,
+    options: &ReorderOptions,
+) -> Result<ReorderSummary> {
+    let (_, mut artifacts) = scan_set::load(scan_set_dir)?;
+    let text_model = if options.use_llm {
+        let model = options.text_model.as_deref().unwrap_or(DEFAULT_TEXT_MODEL);
+        Some(TextModel::new(
+            OllamaClient::new(options.ollama.clone())?,
+            model.to_string(),
+        ))
+    } else {
+        None
+    };
+
+    extract_headers(&mut artifacts);
+    let documents = group_by_name(&artifacts);
+    let mut summary = ReorderSummary {
+        documents: documents.len(),
+        ..ReorderSummary::default()
+    };
+
+    let mut order = Vec::with_capacity(artifacts.len());
+    for document in &documents {
+        let numbered = document
+            .iter()
+            .all(|&idx| artifacts[idx].metadata.page_number.is_some());
+        if let (false, Some(model)) = (numbered, &text_model) {
+            let items: Vec<OrderingItem> = document
+                .iter()
+                .map(|&idx| ordering_item(&artifacts[idx]))
+                .collect();
+            match model.suggest_ordering(&items).await {
+                Ok(suggested) => {
+                    summary.by_model += 1;
+                    order.extend(suggested.into_iter().map(|i| document[i]));
+                    continue;
+                }
+                Err(e) => {
+                    tracing::warn!("Text model ordering failed: {}", e);
+                    summary.model_failures += 1;
+                }
+            }
+        }
+        summary.by_page_number += 1;
+        order.extend(order_pages(&artifacts, document).order);
+    }
+
+    summary.moved = order
+        .iter()
+        .enumerate()
+        .filter(|(position, &idx)| *position != idx)
+        .count();
+    for (position, &idx) in order.iter().enumerate() {
+        artifacts[idx].metadata.reading_order = Some(position);
+    }
+    artifacts.sort_by_key(|a| a.metadata.reading_order);
+    scan_set::save_artifacts(scan_set_dir, &artifacts)?;
+
+    Ok(summary)
+}
+
+/// Split artifacts into documents by the name in their header
+///
+/// Documents are listed in the order their first page was scanned; pages
+/// without a recognizable name form one document.
+fn group_by_name(artifacts: &[PageArtifact]) -> Vec<Vec<usize>> {
+    let mut names: Vec<Option<String>> = Vec::new();
+    let mut documents: Vec<Vec<usize>> = Vec::new();
+    for (idx, artifact) in artifacts.iter().enumerate() {
+        let name = artifact.metadata.header.as_deref().and_then(document_name);
+        match names.iter().position(|n| *n == name) {
+            Some(document) => documents[document].push(idx),
+            None => {
+                names.push(name);
+                documents.push(vec![idx]);
+            }
+        }
+    }
+    documents
+}
+
+/// First and last lines of a page, for the text model
+fn ordering_item(artifact: &PageArtifact) -> OrderingItem {
+    let lines: Vec<&str> = artifact
+        .content_text
+        .as_deref()
+        .unwrap_or("")
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let first = lines.len().min(CONTEXT_LINES);
+    let last = lines.len().saturating_sub(CONTEXT_LINES).max(first);
+    OrderingItem {
+        id: artifact.id.0.to_string(),
+        first_lines: lines[..first].join("\n"),
+        last_lines: lines[last..].join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_pipeline::types::{ArtifactKind, PageId, ScanSetId};
+    use std::path::PathBuf;
+
+    fn page(text: &str) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::new(),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some(text.to_string()),
+            metadata: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_group_by_name() {
+        let mut pages: Vec<PageArtifact> = (0..4).map(|_| page("")).collect();
+        for (page, header) in pages
+            .iter_mut()
+            .zip(["PAYROLL PAGE 2", "", "INVENT PAGE 1"])
+        {
+            page.metadata.header = (!header.is_empty()).then(|| header.to_string());
+        }
+        pages[3].metadata.header = Some("PAYROLL  PAGE 1".to_string());
+        assert_eq!(group_by_name(&pages), vec![vec![0, 3], vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn test_ordering_item() {
+        let item = ordering_item(&page("A\n\nB\nC\nD\nE\nF\nG\nH"));
+        assert_eq!(item.first_lines, "A\nB\nC");
+        assert_eq!(item.last_lines, "F\nG\nH");
+
+        // Short pages are not repeated in the last lines
+        let item = ordering_item(&page("A\nB"));
+        assert_eq!(item.first_lines, "A\nB");
+        assert_eq!(item.last_lines, "");
+    }
+}
@@ -0,0 +1,208 @@
+//! Split a scan set into smaller ones
+//!
+//! A box often mixes FORTRAN listings, object decks and data cards. Split
+//! by classification, each kind becomes its own scan set that can be
+//! exported on its own; a hand-picked selection of artifacts can be split
+//! off as well. Artifacts keep their analysis results, and the source
+//! scan set is left unchanged.
+
+use crate::artifact::find_artifact;
+use crate::merge::copy_files;
+use anyhow::{bail, Result};
+use chrono::Utc;
+use core_pipeline::scan_set;
+use core_pipeline::types::{PageArtifact, ScanSetId, ScanSetManifest};
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// A scan set written by a split
+#[derive(Debug, Clone)]
+pub struct SplitPart {
+    /// Scan set directory
+    pub dir: PathBuf,
+    /// Its manifest
+    pub manifest: ScanSetManifest,
+}
+
+/// Split a scan set into one scan set per classification
+///
+/// The scan sets are written to subdirectories of `output_dir` named
+/// after the kind (`ListingSource`, `CardObject`, ...).
+pub fn split_by_kind(scan_set_dir: &Path, output_dir: &Path) -> Result<Vec<SplitPart>> {
+    let (manifest, artifacts) = scan_set::load(scan_set_dir)?;
+    let mut by_kind: BTreeMap<String, Vec<PageArtifact>> = BTreeMap::new();
+    for artifact in artifacts {
+        by_kind
+            .entry(format!("{:?}", artifact.layout_label))
+            .or_default()
+            .push(artifact);
+    }
+
+    let mut parts = Vec::new();
+    for (kind, artifacts) in by_kind {
+        let dir = output_dir.join(kind);
+        let manifest = write_part(scan_set_dir, &manifest, artifacts, &dir)?;
+        parts.push(SplitPart { dir, manifest });
+    }
+    Ok(parts)
+}
+
+/// Split the given artifacts (by ID or unique ID prefix) off into a new
+/// scan set at `output_dir`
+pub fn split_selection(
+    scan_set_dir: &Path,
+    ids: &[String],
+    output_dir: &Path,
+) -> Result<SplitPart> {
+    let (manifest, artifacts) = scan_set::load(scan_set_dir)?;
+    let mut selected = HashSet::new();
+    for id in ids {
+        selected.insert(find_artifact(&artifacts, id)?);
+    }
+    let artifacts = artifacts
+        .into_iter()
+        .enumerate()
+        .filter(|(idx, _)| selected.contains(idx))
+        .map(|(_, artifact)| artifact)
+        .collect();
+    let manifest = write_part(scan_set_dir, &manifest, artifacts, output_dir)?;
+    Ok(SplitPart {
+        dir: output_dir.to_path_buf(),
+        manifest,
+    })
+}
+
+/// Write artifacts of a scan set, with their files, as a new scan set
+fn write_part(
+    source_dir: &Path,
+    source: &ScanSetManifest,
+    mut artifacts: Vec<PageArtifact>,
+    dir: &Path,
+) -> Result<ScanSetManifest> {
+    if scan_set::exists(dir) {
+        bail!("Scan set already exists: {}", dir.display());
+    }
+
+    let scan_set_id = ScanSetId::new();
+    for (idx, artifact) in artifacts.iter_mut().enumerate() {
+        copy_files(source_dir, dir, artifact)?;
+        artifact.scan_set = scan_set_id;
+        artifact.metadata.ingest_index = idx;
+    }
+
+    let ids: HashSet<_> = artifacts.iter().map(|a| a.id).collect();
+    let files: usize = artifacts
+        .iter()
+        .map(|a| a.metadata.original_filenames.len().max(1))
+        .sum();
+    let manifest = ScanSetManifest {
+        scan_set_id,
+        name: dir
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("scan_set")
+            .to_string(),
+        created_at: Utc::now().to_rfc3339(),
+        image_count: artifacts.len(),
+        original_file_count: files,
+        duplicate_count: files - artifacts.len(),
+        keypunch: source.keypunch.clone(),
+        spending: source
+            .spending
+            .iter()
+            .filter(|spend| ids.contains(&spend.artifact))
+            .cloned()
+            .collect(),
+        preprocess: source.preprocess,
+        near_duplicates: source
+            .near_duplicates
+            .iter()
+            .filter(|pair| ids.contains(&pair.first) && ids.contains(&pair.second))
+            .cloned()
+            .collect(),
+    };
+
+    scan_set::save_manifest(dir, &manifest)?;
+    scan_set::save_artifacts(dir, &artifacts)?;
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{ingest_scan_set, IngestOptions};
+    use core_pipeline::keypunch::KeypunchModel;
+    use core_pipeline::types::ArtifactKind;
+    use tempfile::TempDir;
+
+    /// Scan set of three pages: a listing and two object deck cards
+    fn scan_set(dir: &Path) -> Vec<PageArtifact> {
+        let input = TempDir::new().unwrap();
+        for (name, shade) in [("p1.png", 0), ("p2.png", 128), ("p3.png", 255)] {
+            image::GrayImage::from_pixel(8, 8, image::Luma([shade]))
+                .save(input.path().join(name))
+                .unwrap();
+        }
+        ingest_scan_set(
+            input.path(),
+            dir,
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+        let (_, mut artifacts) = scan_set::load(dir).unwrap();
+        for (artifact, kind) in artifacts.iter_mut().zip([
+            ArtifactKind::ListingSource,
+            ArtifactKind::CardObject,
+            ArtifactKind::CardObject,
+        ]) {
+            artifact.layout_label = kind;
+        }
+        scan_set::save_artifacts(dir, &artifacts).unwrap();
+        artifacts
+    }
+
+    #[test]
+    fn test_split_by_kind() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("box");
+        scan_set(&source);
+
+        let parts = split_by_kind(&source, &dir.path().join("split")).unwrap();
+        let names: Vec<(&str, usize)> = parts
+            .iter()
+            .map(|p| (p.manifest.name.as_str(), p.manifest.image_count))
+            .collect();
+        assert_eq!(names, vec![("CardObject", 2), ("ListingSource", 1)]);
+
+        let (manifest, artifacts) = scan_set::load(&parts[0].dir).unwrap();
+        assert_eq!(manifest.original_file_count, 2);
+        assert_eq!(manifest.duplicate_count, 0);
+        for artifact in &artifacts {
+            assert_eq!(artifact.scan_set, manifest.scan_set_id);
+            assert!(parts[0].dir.join(&artifact.raw_image_path).is_file());
+        }
+        // The near-duplicate pair within the object deck stays with it
+        assert_eq!(manifest.near_duplicates.len(), 1);
+        assert!(scan_set::load(&parts[1].dir)
+            .unwrap()
+            .0
+            .near_duplicates
+            .is_empty());
+    }
+
+    #[test]
+    fn test_split_selection() {
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("box");
+        let artifacts = scan_set(&source);
+        let id = artifacts[1].id.0.to_string();
+
+        let part =
+            split_selection(&source, &[id[..8].to_string()], &dir.path().join("pick")).unwrap();
+        let (_, picked) = scan_set::load(&part.dir).unwrap();
+        assert_eq!(picked.len(), 1);
+        assert_eq!(picked[0].id, artifacts[1].id);
+    }
+}
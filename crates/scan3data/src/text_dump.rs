@@ -0,0 +1,274 @@
+//! Plain text dump of a scan set's OCR text, for inspection
+//!
+//! The dump can also be written as Markdown, one section per artifact with
+//! a metadata table and the text in a fenced code block, which renders on
+//! GitHub (the transcript of [`core_pipeline::export::markdown`], without
+//! page images).
+//!
+//! A split dump writes the text of each artifact to its own file instead,
+//! named by ingest index and image hash (`0003-246d5d942fde3ba9.txt`), so
+//! single pages can be diffed and their corrections tracked in git.
+
+use anyhow::{Context, Result};
+use core_pipeline::export::markdown::transcript_text;
+use core_pipeline::scan_set;
+use core_pipeline::types::{PageArtifact, ScanSetManifest};
+use std::fs;
+use std::path::Path;
+
+const RULE: &str =
+    "================================================================================\n";
+const THIN_RULE: &str =
+    "--------------------------------------------------------------------------------\n";
+
+/// Format of a text dump
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TextDumpFormat {
+    /// Plain text with ruled sections
+    #[default]
+    Text,
+    /// Markdown with fenced code blocks
+    Markdown,
+}
+
+impl TextDumpFormat {
+    /// Parse a format name ("text" or "markdown")
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "markdown" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// Counts of a written text dump
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextDumpSummary {
+    /// Number of artifacts in the scan set
+    pub artifacts: usize,
+    /// Number of artifacts with OCR text
+    pub with_text: usize,
+    /// Total characters of OCR text
+    pub total_chars: usize,
+}
+
+/// Text dump of a scan set: a header, each artifact with its OCR text, and
+/// a summary footer
+pub fn text_dump(manifest: &ScanSetManifest, artifacts: &[PageArtifact]) -> String {
+    let mut output = String::new();
+
+    // Header
+    output.push_str(RULE);
+    output.push_str("SCAN SET OCR TEXT DUMP\n");
+    output.push_str(&format!("Scan Set ID: {}\n", manifest.scan_set_id.0));
+    output.push_str(&format!("Name: {}\n", manifest.name));
+    output.push_str(&format!("Created: {}\n", manifest.created_at));
+    output.push_str(&format!(
+        "Images: {} unique ({} total, {} duplicates)\n",
+        manifest.image_count, manifest.original_file_count, manifest.duplicate_count
+    ));
+    output.push_str(RULE);
+    output.push('\n');
+
+    let summary = summarize(artifacts);
+    for (idx, artifact) in artifacts.iter().enumerate() {
+        output.push_str(RULE);
+        output.push_str(&format!("ARTIFACT {}/{}\n", idx + 1, artifacts.len()));
+        output.push_str(RULE);
+        output.push_str(&format!("ID: {}\n", artifact.id.0));
+        output.push_str(&format!("Image: {}\n", artifact.raw_image_path.display()));
+
+        if let Some(ref processed) = artifact.processed_image_path {
+            output.push_str(&format!("Processed: {}\n", processed.display()));
+        }
+
+        output.push_str(&format!("Classification: {:?}\n", artifact.layout_label));
+        output.push_str(&format!("Confidence: {}\n", artifact.metadata.confidence));
+
+        // Show original filenames if available
+        if !artifact.metadata.original_filenames.is_empty() {
+            output.push_str("Original Files:\n");
+            for filename in &artifact.metadata.original_filenames {
+                output.push_str(&format!("  - {}\n", filename));
+            }
+        }
+
+        output.push_str(THIN_RULE);
+
+        if let Some(ref text) = artifact.content_text {
+            output.push_str("OCR TEXT:\n");
+            output.push_str(THIN_RULE);
+            output.push_str(text);
+            if !text.ends_with('\n') {
+                output.push('\n');
+            }
+        } else {
+            output.push_str("(No OCR text available)\n");
+        }
+
+        output.push_str(RULE);
+        output.push('\n');
+    }
+
+    // Summary footer
+    output.push_str(RULE);
+    output.push_str("SUMMARY\n");
+    output.push_str(RULE);
+    output.push_str(&format!("Total artifacts: {}\n", summary.artifacts));
+    output.push_str(&format!("Artifacts with text: {}\n", summary.with_text));
+    output.push_str(&format!("Total characters: {}\n", summary.total_chars));
+    if let Some(average) = summary.total_chars.checked_div(summary.with_text) {
+        output.push_str(&format!("Average characters per artifact: {}\n", average));
+    }
+    output.push_str(RULE);
+
+    output
+}
+
+/// Write the text dump of a scan set to a file
+pub fn text_dump_scan_set(
+    scan_set_dir: &Path,
+    output_file: &Path,
+    format: TextDumpFormat,
+) -> Result<TextDumpSummary> {
+    let (manifest, artifacts) = scan_set::load(scan_set_dir)?;
+    let dump = match format {
+        TextDumpFormat::Text => text_dump(&manifest, &artifacts),
+        TextDumpFormat::Markdown => transcript_text(&manifest, &artifacts),
+    };
+    fs::write(output_file, dump)
+        .with_context(|| format!("Failed to write output file: {}", output_file.display()))?;
+    Ok(summarize(&artifacts))
+}
+
+/// Write the OCR text of each artifact of a scan set to its own file in
+/// `output_dir`, which is created if needed
+///
+/// Artifacts without text get an empty file, so the files match the
+/// artifacts one to one.
+pub fn text_dump_split(scan_set_dir: &Path, output_dir: &Path) -> Result<TextDumpSummary> {
+    let (_, artifacts) = scan_set::load(scan_set_dir)?;
+    fs::create_dir_all(output_dir)
+        .with_context(|| format!("Failed to create directory: {}", output_dir.display()))?;
+    for artifact in &artifacts {
+        let path = output_dir.join(split_file_name(artifact));
+        let mut text = artifact.content_text.clone().unwrap_or_default();
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        fs::write(&path, text)
+            .with_context(|| format!("Failed to write output file: {}", path.display()))?;
+    }
+    Ok(summarize(&artifacts))
+}
+
+/// Name of an artifact's file in a split dump: ingest index and the image
+/// hash prefix
+pub fn split_file_name(artifact: &PageArtifact) -> String {
+    let hash = &artifact.metadata.content_hash;
+    let prefix = hash.get(..16).unwrap_or(hash);
+    format!("{:04}-{}.txt", artifact.metadata.ingest_index, prefix)
+}
+
+fn summarize(artifacts: &[PageArtifact]) -> TextDumpSummary {
+    let texts = artifacts.iter().filter_map(|a| a.content_text.as_ref());
+    TextDumpSummary {
+        artifacts: artifacts.len(),
+        with_text: texts.clone().count(),
+        total_chars: texts.map(|t| t.len()).sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_pipeline::types::{ArtifactKind, PageId, PageMetadata, ScanSetId};
+    use std::path::PathBuf;
+
+    fn artifact(scan_set: ScanSetId, text: Option<&str>) -> PageArtifact {
+        PageArtifact {
+            id: PageId::new(),
+            scan_set,
+            raw_image_path: PathBuf::from("images/ab.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::Unknown,
+            content_text: text.map(str::to_string),
+            metadata: PageMetadata {
+                content_hash: String::new(),
+                file_hash: None,
+                original_filenames: vec!["p1.jpg".to_string()],
+                page_number: None,
+                header: None,
+                footer: None,
+                notes: Vec::new(),
+                confidence: 0.0,
+                revisions: Vec::new(),
+                validation_issues: Vec::new(),
+                derived_images: Vec::new(),
+                cost_usd: 0.0,
+                source_pages: Vec::new(),
+                perceptual_hash: None,
+                ingest_index: 0,
+                originals: Vec::new(),
+                reading_order: None,
+                human_transcribed: false,
+                cleaned_image_path: None,
+                thumbnail_path: None,
+                manual_classification: false,
+                base_confidence: None,
+            },
+        }
+    }
+
+    #[test]
+    fn test_text_dump() {
+        let manifest = ScanSetManifest {
+            scan_set_id: ScanSetId::new(),
+            name: "Box 3".to_string(),
+            created_at: "2025-01-01T00:00:00Z".to_string(),
+            image_count: 2,
+            original_file_count: 2,
+            duplicate_count: 0,
+            keypunch: Default::default(),
+            spending: Vec::new(),
+            preprocess: Default::default(),
+            near_duplicates: Vec::new(),
+        };
+        let artifacts = vec![
+            artifact(manifest.scan_set_id, Some("      X = 1")),
+            artifact(manifest.scan_set_id, None),
+        ];
+
+        let dump = text_dump(&manifest, &artifacts);
+        assert!(dump.contains("Name: Box 3\n"));
+        assert!(dump.contains("ARTIFACT 2/2\n"));
+        assert!(dump.contains("  - p1.jpg\n"));
+        assert!(dump.contains("      X = 1\n"));
+        assert!(dump.contains("(No OCR text available)\n"));
+        assert!(dump.contains("Average characters per artifact: 11\n"));
+
+        let markdown = transcript_text(&manifest, &artifacts);
+        assert!(markdown.starts_with("# Box 3\n"));
+        assert!(markdown.contains("## Page 2\n"));
+        assert!(markdown.contains("```text\n      X = 1\n```\n"));
+        assert_eq!(
+            summarize(&artifacts),
+            TextDumpSummary {
+                artifacts: 2,
+                with_text: 1,
+                total_chars: 11
+            }
+        );
+    }
+
+    #[test]
+    fn test_split_file_name() {
+        let mut page = artifact(ScanSetId::new(), None);
+        page.metadata.ingest_index = 3;
+        page.metadata.content_hash = "246d5d942fde3ba9c0ffee".to_string();
+        assert_eq!(split_file_name(&page), "0003-246d5d942fde3ba9.txt");
+        page.metadata.content_hash = "abc".to_string();
+        assert_eq!(split_file_name(&page), "0003-abc.txt");
+    }
+}
@@ -0,0 +1,127 @@
+//! Small previews of the scans of a scan set
+//!
+//! Scans are often several megabytes; pages listing many of them (the
+//! comparison view, the web UI) show a downscaled JPEG instead. Previews
+//! are written to [`THUMBNAILS_DIR`] as `<raw image name>-<size>.jpg` and
+//! recorded on the artifact. They can be made again from the raw image at
+//! any time, so a merge or split skips them if they are gone.
+
+use anyhow::{bail, Context, Result};
+use core_pipeline::image_loader::{load_image, LoadOptions};
+use core_pipeline::scan_set;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory of thumbnails in a scan set
+pub const THUMBNAILS_DIR: &str = "thumbnails";
+
+/// Longest side of thumbnails, in pixels, unless another size is asked for
+pub const DEFAULT_THUMBNAIL_SIZE: u32 = 256;
+
+/// Result of making thumbnails
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ThumbnailSummary {
+    /// Thumbnails written
+    pub created: usize,
+    /// Artifacts that already had a thumbnail of the size
+    pub existing: usize,
+}
+
+/// Write a thumbnail of at most `size` pixels on the longest side for
+/// every artifact of a scan set
+///
+/// A thumbnail of another size made earlier is replaced. `progress` is
+/// called with the number of artifacts done and the total.
+pub fn generate_thumbnails(
+    scan_set_dir: &Path,
+    size: u32,
+    progress: &mut (dyn FnMut(usize, usize) + Send),
+) -> Result<ThumbnailSummary> {
+    let (_, mut artifacts) = scan_set::load(scan_set_dir)?;
+    let mut summary = ThumbnailSummary::default();
+
+    let total = artifacts.len();
+    for (done, artifact) in artifacts.iter_mut().enumerate() {
+        let path = thumbnail_path(&artifact.raw_image_path, size)?;
+        let output = scan_set_dir.join(&path);
+        if artifact.metadata.thumbnail_path.as_ref() == Some(&path) && output.is_file() {
+            summary.existing += 1;
+        } else {
+            let raw_image_path = scan_set_dir.join(&artifact.raw_image_path);
+            let thumbnail = load_image(&raw_image_path, &LoadOptions::downscaled(size))?;
+            if let Some(parent) = output.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+            }
+            thumbnail
+                .to_rgb8()
+                .save_with_format(&output, image::ImageFormat::Jpeg)
+                .with_context(|| format!("Failed to write thumbnail: {}", output.display()))?;
+
+            // Drop a thumbnail of another size
+            if let Some(previous) = artifact.metadata.thumbnail_path.replace(path) {
+                let previous = scan_set_dir.join(previous);
+                if previous != output && previous.is_file() {
+                    fs::remove_file(&previous)
+                        .with_context(|| format!("Failed to remove: {}", previous.display()))?;
+                }
+            }
+            summary.created += 1;
+        }
+        progress(done + 1, total);
+    }
+
+    scan_set::save_artifacts(scan_set_dir, &artifacts)?;
+    Ok(summary)
+}
+
+/// Path of the thumbnail of a raw image, relative to the scan set
+fn thumbnail_path(raw_image_path: &Path, size: u32) -> Result<PathBuf> {
+    let Some(stem) = raw_image_path.file_stem() else {
+        bail!("Image has no file name: {}", raw_image_path.display());
+    };
+    let mut name = stem.to_os_string();
+    name.push(format!("-{}.jpg", size));
+    Ok(Path::new(THUMBNAILS_DIR).join(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{ingest_scan_set, IngestOptions};
+    use core_pipeline::keypunch::KeypunchModel;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_generate_thumbnails() {
+        let input = TempDir::new().unwrap();
+        image::GrayImage::from_pixel(800, 400, image::Luma([90]))
+            .save(input.path().join("p1.png"))
+            .unwrap();
+        let dir = TempDir::new().unwrap();
+        let source = dir.path().join("box3");
+        ingest_scan_set(
+            input.path(),
+            &source,
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            &mut |_, _| {},
+        )
+        .unwrap();
+
+        let summary = generate_thumbnails(&source, 256, &mut |_, _| {}).unwrap();
+        assert_eq!(summary.created, 1);
+        let (_, artifacts) = scan_set::load(&source).unwrap();
+        let small = source.join(artifacts[0].metadata.thumbnail_path.as_ref().unwrap());
+        assert_eq!(image::image_dimensions(&small).unwrap(), (256, 128));
+
+        // Same size again is kept; another size replaces it
+        let again = generate_thumbnails(&source, 256, &mut |_, _| {}).unwrap();
+        assert_eq!((again.created, again.existing), (0, 1));
+        generate_thumbnails(&source, 128, &mut |_, _| {}).unwrap();
+        let (_, artifacts) = scan_set::load(&source).unwrap();
+        let smaller = source.join(artifacts[0].metadata.thumbnail_path.as_ref().unwrap());
+        assert_eq!(image::image_dimensions(&smaller).unwrap(), (128, 64));
+        assert!(!small.exists());
+    }
+}
@@ -0,0 +1,264 @@
+//! Watch-folder ingest
+//!
+//! Ingests scans as a document scanner drops them into a directory. The
+//! scan set is created on first use; files already recorded in it (by
+//! original filename) are skipped, so an interrupted watch picks up where
+//! it left off. Scanners write files progressively, so a file is only
+//! ingested once its size has stopped changing for [`SETTLE_TIME`].
+
+use crate::ingest::{
+    append_to_scan_set, collect_image_files, is_ingestible, new_manifest, AppendSummary,
+    IngestOptions,
+};
+use anyhow::{anyhow, bail, Context, Result};
+use core_pipeline::keypunch::KeypunchModel;
+use core_pipeline::scan_set;
+use core_pipeline::types::ScanSetManifest;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::time::{Duration, Instant};
+
+/// How long a file's size must stay the same before it is ingested
+pub const SETTLE_TIME: Duration = Duration::from_secs(2);
+
+/// Something that happened while watching
+#[derive(Debug)]
+pub enum WatchEvent<'a> {
+    /// Watching started on the (possibly new) scan set
+    Started {
+        /// The scan set's manifest
+        manifest: &'a ScanSetManifest,
+        /// Files waiting to be ingested from before the watch started
+        pending: usize,
+    },
+    /// A file was ingested
+    Ingested {
+        /// The file
+        path: &'a Path,
+        /// What appending it did
+        summary: &'a AppendSummary,
+    },
+    /// A file could not be ingested; watching goes on
+    Failed {
+        /// The file
+        path: &'a Path,
+        /// Why
+        error: &'a anyhow::Error,
+    },
+}
+
+/// Ingest scans dropped into `input_dir` into the scan set, until
+/// `on_event` breaks
+///
+/// The scan set is created (with the given keypunch) if it does not exist.
+pub fn watch_folder(
+    input_dir: &Path,
+    scan_set_dir: &Path,
+    keypunch: KeypunchModel,
+    options: &IngestOptions,
+    on_event: &mut dyn FnMut(WatchEvent) -> ControlFlow<()>,
+) -> Result<()> {
+    watch(
+        input_dir,
+        scan_set_dir,
+        keypunch,
+        options,
+        SETTLE_TIME,
+        on_event,
+    )
+}
+
+fn watch(
+    input_dir: &Path,
+    scan_set_dir: &Path,
+    keypunch: KeypunchModel,
+    options: &IngestOptions,
+    settle_time: Duration,
+    on_event: &mut dyn FnMut(WatchEvent) -> ControlFlow<()>,
+) -> Result<()> {
+    if !input_dir.is_dir() {
+        bail!("Watched path is not a directory: {}", input_dir.display());
+    }
+    // Events carry absolute paths, so ingested names are absolute too
+    let input_dir = input_dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve: {}", input_dir.display()))?;
+
+    if !scan_set::exists(scan_set_dir) {
+        fs::create_dir_all(scan_set_dir)
+            .with_context(|| format!("Failed to create scan set: {}", scan_set_dir.display()))?;
+        scan_set::save_manifest(scan_set_dir, &new_manifest(&input_dir, keypunch))?;
+        scan_set::save_artifacts(scan_set_dir, &[])?;
+    }
+    let (manifest, artifacts) = scan_set::load(scan_set_dir)?;
+    // The scan set may live inside the watched directory
+    let scan_set_dir = scan_set_dir
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve: {}", scan_set_dir.display()))?;
+
+    let (sender, events) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    watcher.watch(&input_dir, RecursiveMode::Recursive)?;
+
+    // Files from before the watch that are not in the scan set yet
+    let mut done: HashSet<PathBuf> = artifacts
+        .iter()
+        .flat_map(|a| &a.metadata.original_filenames)
+        .map(PathBuf::from)
+        .collect();
+    let mut pending = Pending::default();
+    // An empty directory is fine, scans are yet to come
+    for path in collect_image_files(&input_dir).unwrap_or_default() {
+        if !done.contains(&path) && !path.starts_with(&scan_set_dir) {
+            pending.touch(path);
+        }
+    }
+    if on_event(WatchEvent::Started {
+        manifest: &manifest,
+        pending: pending.files.len(),
+    })
+    .is_break()
+    {
+        return Ok(());
+    }
+
+    let tick = settle_time.min(Duration::from_millis(250));
+    loop {
+        match events.recv_timeout(tick) {
+            Ok(event) => {
+                let event = event?;
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        if is_ingestible(&path)
+                            && !done.contains(&path)
+                            && !path.starts_with(&scan_set_dir)
+                        {
+                            pending.touch(path);
+                        }
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(anyhow!("File watcher stopped"));
+            }
+        }
+
+        for path in pending.settled(settle_time) {
+            let result = append_to_scan_set(&path, &scan_set_dir, options, &mut |_, _| {});
+            let flow = match result {
+                Ok(summary) => on_event(WatchEvent::Ingested {
+                    path: &path,
+                    summary: &summary,
+                }),
+                Err(error) => on_event(WatchEvent::Failed {
+                    path: &path,
+                    error: &error,
+                }),
+            };
+            done.insert(path);
+            if flow.is_break() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Files seen but not yet ingested, with their last size and when it
+/// last changed
+#[derive(Default)]
+struct Pending {
+    files: HashMap<PathBuf, (Option<u64>, Instant)>,
+}
+
+impl Pending {
+    /// Note a file that was created or written to
+    fn touch(&mut self, path: PathBuf) {
+        let size = fs::metadata(&path).ok().map(|m| m.len());
+        self.files.insert(path, (size, Instant::now()));
+    }
+
+    /// Take the files whose size has not changed for `settle_time`, in
+    /// file name order
+    fn settled(&mut self, settle_time: Duration) -> Vec<PathBuf> {
+        let now = Instant::now();
+        let mut settled = Vec::new();
+        self.files.retain(|path, (size, since)| {
+            let current = fs::metadata(path).ok().map(|m| m.len());
+            if current.is_none() {
+                // Gone again (e.g. a scanner's temporary file)
+                return false;
+            }
+            if current != *size {
+                *size = current;
+                *since = now;
+                return true;
+            }
+            if now.duration_since(*since) >= settle_time {
+                settled.push(path.clone());
+                return false;
+            }
+            true
+        });
+        settled.sort();
+        settled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_png(path: &Path, shade: u8) {
+        image::GrayImage::from_pixel(8, 8, image::Luma([shade]))
+            .save(path)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_watch_ingests_new_scans() {
+        let input = TempDir::new().unwrap();
+        write_png(&input.path().join("p1.png"), 0);
+        let output = TempDir::new().unwrap();
+        let scan_set_dir = output.path().join("set");
+
+        let mut ingested = Vec::new();
+        watch(
+            input.path(),
+            &scan_set_dir,
+            KeypunchModel::Ibm029,
+            &IngestOptions::default(),
+            Duration::from_millis(100),
+            &mut |event| {
+                match event {
+                    WatchEvent::Started { pending, .. } => {
+                        assert_eq!(pending, 1);
+                        // Dropped in by the scanner after the watch started
+                        write_png(&input.path().join("p2.png"), 255);
+                    }
+                    WatchEvent::Ingested { path, summary } => {
+                        assert_eq!(summary.added, 1);
+                        ingested.push(path.file_name().unwrap().to_owned());
+                    }
+                    WatchEvent::Failed { error, .. } => panic!("{}", error),
+                }
+                if ingested.len() == 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            },
+        )
+        .unwrap();
+
+        assert_eq!(ingested, ["p1.png", "p2.png"]);
+        let (manifest, artifacts) = scan_set::load(&scan_set_dir).unwrap();
+        assert_eq!(manifest.image_count, 2);
+        assert_eq!(artifacts.len(), 2);
+    }
+}
@@ -0,0 +1,150 @@
+//! API key authentication
+//!
+//! With keys configured (`server.api_keys` or `SCAN3DATA_API_KEYS`), API
+//! requests must present one, so the server can listen beyond localhost
+//! without strangers spending the Gemini budget. A key goes in the
+//! `X-API-Key` header, as `Authorization: Bearer <key>`, or, for
+//! WebSockets and `EventSource`, which cannot set headers, in the
+//! `api_key` query parameter. Routes added after the layer, such as
+//! `/health`, and the frontend's files need no key; the frontend sends the
+//! key its page was opened with (`?api_key=`).
+
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::borrow::Cow;
+use std::sync::Arc;
+
+/// Header carrying an API key
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Query parameter carrying an API key
+pub const API_KEY_PARAM: &str = "api_key";
+
+/// Keys the server accepts
+#[derive(Debug, Clone, Default)]
+pub struct ApiKeys(Arc<Vec<String>>);
+
+impl ApiKeys {
+    /// Accept these keys
+    pub fn new(keys: Vec<String>) -> Self {
+        Self(Arc::new(keys))
+    }
+
+    /// Whether no keys are configured, leaving the API open
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Whether a request presents one of the keys
+    pub fn allows(&self, request: &Request) -> bool {
+        presented_key(request).is_some_and(|presented| {
+            self.0
+                .iter()
+                .any(|key| constant_time_eq(key.as_bytes(), presented.as_bytes()))
+        })
+    }
+}
+
+/// Middleware answering 401 to requests without a valid key
+pub async fn require_api_key(
+    State(keys): State<ApiKeys>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if keys.allows(&request) {
+        next.run(request).await
+    } else {
+        (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            "Missing or invalid API key",
+        )
+            .into_response()
+    }
+}
+
+/// Key a request presents, from the header, the bearer token or the
+/// (percent-decoded) query
+fn presented_key(request: &Request) -> Option<Cow<'_, str>> {
+    let headers = request.headers();
+    let header_value = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    if let Some(key) = header_value(API_KEY_HEADER) {
+        return Some(Cow::Borrowed(key.trim()));
+    }
+    if let Some(token) =
+        header_value(header::AUTHORIZATION.as_str()).and_then(|value| value.strip_prefix("Bearer "))
+    {
+        return Some(Cow::Borrowed(token.trim()));
+    }
+    form_urlencoded::parse(request.uri().query()?.as_bytes())
+        .find(|(name, _)| name == API_KEY_PARAM)
+        .map(|(_, key)| key)
+}
+
+/// Compare without returning early, so timing does not reveal how much of
+/// a key matched
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, middleware, routing::get, Router};
+    use tower::ServiceExt;
+
+    async fn status(app: &Router, uri: &str, headers: &[(&str, &str)]) -> StatusCode {
+        let mut request = Request::builder().uri(uri);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        let request = request.body(Body::empty()).unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_require_api_key() {
+        let keys = ApiKeys::new(vec!["secret".to_string()]);
+        let app = Router::new()
+            .route("/api/jobs", get(|| async { "jobs" }))
+            .route_layer(middleware::from_fn_with_state(keys, require_api_key))
+            .route("/health", get(|| async { "OK" }));
+
+        assert_eq!(status(&app, "/health", &[]).await, StatusCode::OK);
+        assert_eq!(
+            status(&app, "/api/jobs", &[]).await,
+            StatusCode::UNAUTHORIZED
+        );
+        let ok = StatusCode::OK;
+        assert_eq!(
+            status(&app, "/api/jobs", &[("X-API-Key", "secret")]).await,
+            ok
+        );
+        let bearer = [("Authorization", "Bearer secret")];
+        assert_eq!(status(&app, "/api/jobs", &bearer).await, ok);
+        assert_eq!(status(&app, "/api/jobs?x=1&api_key=secret", &[]).await, ok);
+        let wrong = [("X-API-Key", "secreT")];
+        assert_eq!(
+            status(&app, "/api/jobs", &wrong).await,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            status(&app, "/api/jobs?api_keys=secret", &[]).await,
+            StatusCode::UNAUTHORIZED
+        );
+
+        // Query keys are percent-decoded
+        let keys = ApiKeys::new(vec!["a+b/c=d&e".to_string()]);
+        let app = Router::new()
+            .route("/api/jobs", get(|| async { "jobs" }))
+            .route_layer(middleware::from_fn_with_state(keys, require_api_key));
+        assert_eq!(
+            status(&app, "/api/jobs?api_key=a%2Bb%2Fc%3Dd%26e", &[]).await,
+            ok
+        );
+    }
+}
@@ -0,0 +1,602 @@
+//! Background jobs
+//!
+//! Long-running work, analyzing or cleaning a scan set, is queued as a job
+//! and run in the background, one job at a time, since a run already uses
+//! the machine's OCR threads and model slots. Clients poll a job's status
+//! for its progress, or follow its [`JobEvent`]s as they happen (the server
+//! pushes them over a WebSocket).
+//!
+//! Jobs live in memory, up to [`FINISHED_JOBS_KEPT`] finished ones; a
+//! [`JobLog`] (the server's SQLite store) records them so they outlive a
+//! restart.
+
+use futures::stream::{self, Stream, StreamExt};
+use llm_bridge::GeminiClient;
+use scan3data::{
+    AnalyzeOptions, AnalyzePhase, AnalyzeProgress, AnalyzeSummary, CleanOptions, CleanSummary,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::runtime::Handle;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::Semaphore;
+
+/// Events kept for subscribers that fall behind; older progress is
+/// dropped, since every progress event carries the counts so far
+const EVENT_BUFFER: usize = 256;
+
+/// Finished jobs kept in memory; the oldest are dropped beyond this
+pub const FINISHED_JOBS_KEPT: usize = 100;
+
+/// Kind of work a job does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    /// OCR, correction, classification and validation of a scan set
+    Analyze,
+    /// Gemini cleaning of a scan set's images
+    Clean,
+}
+
+/// Where a job is in its life
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    /// Waiting for the job before it to finish
+    Queued,
+    /// Running
+    Running,
+    /// Finished successfully
+    Completed,
+    /// Stopped by an error
+    Failed,
+}
+
+/// A stage that failed for one artifact, without failing the job
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactError {
+    /// Artifact identifier
+    pub artifact: String,
+    /// What failed, as recorded in the artifact's notes
+    pub message: String,
+}
+
+/// Something that happened to a job, as pushed to subscribers
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JobEvent {
+    /// Status of the job when the client subscribed
+    Status(Job),
+    /// The job started running
+    Started,
+    /// An artifact finished a stage, with what analysis made of it once
+    /// corrected
+    Progress(AnalyzeProgress),
+    /// An image of a clean job was cleaned (or failed to be)
+    CleanProgress {
+        /// Images done
+        done: usize,
+        /// Images to clean
+        total: usize,
+    },
+    /// The job completed or failed, with its final status
+    Finished(Job),
+}
+
+/// Status and progress of a job
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    /// Job identifier
+    pub id: String,
+    /// Kind of work
+    pub kind: JobKind,
+    /// Scan set the job works on
+    pub scan_set_id: String,
+    /// Where the job is in its life
+    pub state: JobState,
+    /// Phase of an analysis
+    pub phase: Option<AnalyzePhase>,
+    /// Artifact that finished last
+    pub current_artifact: Option<String>,
+    /// Artifacts finished in the phase
+    pub completed: usize,
+    /// Artifacts to analyze or clean
+    pub total: usize,
+    /// Stages that failed for single artifacts
+    pub errors: Vec<ArtifactError>,
+    /// Error that stopped the job
+    pub error: Option<String>,
+    /// Outcome of an analysis, once completed
+    pub summary: Option<AnalyzeSummary>,
+    /// Outcome of a clean job, once completed
+    #[serde(default)]
+    pub clean_summary: Option<CleanSummary>,
+}
+
+impl Job {
+    /// A queued job
+    pub(crate) fn new(kind: JobKind, scan_set_id: &str) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            scan_set_id: scan_set_id.to_string(),
+            state: JobState::Queued,
+            phase: None,
+            current_artifact: None,
+            completed: 0,
+            total: 0,
+            errors: Vec::new(),
+            error: None,
+            summary: None,
+            clean_summary: None,
+        }
+    }
+
+    /// Whether the job completed or failed
+    pub fn is_finished(&self) -> bool {
+        matches!(self.state, JobState::Completed | JobState::Failed)
+    }
+
+    /// Record the progress reported by a run
+    fn record(&mut self, progress: &AnalyzeProgress) {
+        let artifact = progress.artifact.0.to_string();
+        self.phase = Some(progress.phase);
+        self.completed = progress.done;
+        self.total = progress.total;
+        self.errors
+            .extend(progress.errors.iter().map(|message| ArtifactError {
+                artifact: artifact.clone(),
+                message: message.clone(),
+            }));
+        self.current_artifact = Some(artifact);
+    }
+}
+
+/// Where jobs are recorded beyond the server's memory
+pub trait JobLog: Send + Sync {
+    /// Record a job as it is queued, starts and finishes
+    fn record(&self, job: &Job);
+}
+
+/// A job with the channel its events go out on
+struct Entry {
+    job: Job,
+    events: broadcast::Sender<JobEvent>,
+}
+
+/// Jobs in memory, with the finished ones in the order they finished
+#[derive(Default)]
+struct Jobs {
+    entries: HashMap<String, Entry>,
+    finished: VecDeque<String>,
+}
+
+impl Jobs {
+    /// Note that a job finished, dropping the oldest finished jobs beyond
+    /// [`FINISHED_JOBS_KEPT`]
+    fn finished(&mut self, id: &str) {
+        self.finished.push_back(id.to_string());
+        while self.finished.len() > FINISHED_JOBS_KEPT {
+            if let Some(oldest) = self.finished.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// What a run produced
+enum Outcome {
+    Analyze(AnalyzeSummary),
+    Clean(CleanSummary),
+}
+
+/// Jobs of the server, run one at a time in the order queued
+#[derive(Clone)]
+pub struct JobQueue {
+    jobs: Arc<Mutex<Jobs>>,
+    slot: Arc<Semaphore>,
+    log: Option<Arc<dyn JobLog>>,
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self {
+            jobs: Arc::default(),
+            slot: Arc::new(Semaphore::new(1)),
+            log: None,
+        }
+    }
+}
+
+impl JobQueue {
+    /// Queue recording its jobs to `log`, holding the jobs recorded
+    /// before; jobs a restart left unfinished have failed
+    pub fn with_log(log: Arc<dyn JobLog>, jobs: Vec<Job>) -> Self {
+        let queue = Self {
+            log: Some(log.clone()),
+            ..Self::default()
+        };
+        for mut job in jobs {
+            if !job.is_finished() {
+                job.state = JobState::Failed;
+                job.error = Some("Interrupted by a server restart".to_string());
+                log.record(&job);
+            }
+            queue.insert(job);
+        }
+        queue
+    }
+
+    /// Status of a job
+    pub fn get(&self, id: &str) -> Option<Job> {
+        let jobs = self.jobs.lock().expect("jobs lock");
+        jobs.entries.get(id).map(|entry| entry.job.clone())
+    }
+
+    /// Events of a job: its status now, then what happens to it, ending
+    /// once it finishes
+    pub fn events(&self, id: &str) -> Option<impl Stream<Item = JobEvent>> {
+        // Taken together, so no event falls between status and channel
+        let (job, receiver) = {
+            let jobs = self.jobs.lock().expect("jobs lock");
+            let entry = jobs.entries.get(id)?;
+            (entry.job.clone(), entry.events.subscribe())
+        };
+        let finished = job.is_finished();
+        let status = stream::once(async move { JobEvent::Status(job) });
+        let later = stream::unfold(
+            (receiver, finished),
+            |(mut receiver, finished)| async move {
+                if finished {
+                    return None;
+                }
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) => {
+                            let finished = matches!(event, JobEvent::Finished(_));
+                            return Some((event, (receiver, finished)));
+                        }
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            },
+        );
+        Some(status.chain(later))
+    }
+
+    /// Add a job
+    fn insert(&self, job: Job) {
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let mut jobs = self.jobs.lock().expect("jobs lock");
+        let id = job.id.clone();
+        let finished = job.is_finished();
+        jobs.entries.insert(id.clone(), Entry { job, events });
+        if finished {
+            jobs.finished(&id);
+        }
+    }
+
+    /// Queue an analysis of the scan set in `scan_set_dir`, returning the
+    /// queued job
+    ///
+    /// Must be called from within the Tokio runtime.
+    pub fn enqueue_analyze(
+        &self,
+        scan_set_id: &str,
+        scan_set_dir: PathBuf,
+        options: AnalyzeOptions,
+    ) -> Job {
+        let job = Job::new(JobKind::Analyze, scan_set_id);
+        self.start(job, move |queue, id| async move {
+            tracing::info!("Job {}: analyzing {}", id, scan_set_dir.display());
+            let summary = scan3data::analyze_scan_set(&scan_set_dir, &options, &mut |progress| {
+                queue.update(&id, |job| {
+                    job.record(progress);
+                    JobEvent::Progress(progress.clone())
+                })
+            })
+            .await?;
+            Ok(Outcome::Analyze(summary))
+        })
+    }
+
+    /// Queue a Gemini cleaning of the scan set in `scan_set_dir`, returning
+    /// the queued job
+    ///
+    /// Must be called from within the Tokio runtime.
+    pub fn enqueue_clean(
+        &self,
+        scan_set_id: &str,
+        scan_set_dir: PathBuf,
+        client: GeminiClient,
+        options: CleanOptions,
+    ) -> Job {
+        let job = Job::new(JobKind::Clean, scan_set_id);
+        self.start(job, move |queue, id| async move {
+            tracing::info!("Job {}: cleaning {}", id, scan_set_dir.display());
+            let summary =
+                scan3data::clean_scan_set(&scan_set_dir, &client, &options, &mut |done, total| {
+                    queue.update(&id, |job| {
+                        job.completed = done;
+                        job.total = total;
+                        JobEvent::CleanProgress { done, total }
+                    })
+                })
+                .await?;
+            Ok(Outcome::Clean(summary))
+        })
+    }
+
+    /// Queue a job, running `run` with the queue and the job's identifier
+    /// once the jobs before it finished
+    fn start<F, Fut>(&self, job: Job, run: F) -> Job
+    where
+        F: FnOnce(JobQueue, String) -> Fut + Send + 'static,
+        Fut: Future<Output = anyhow::Result<Outcome>>,
+    {
+        let id = job.id.clone();
+        self.insert(job.clone());
+        if let Some(log) = &self.log {
+            log.record(&job);
+        }
+
+        let queue = self.clone();
+        tokio::spawn(async move {
+            let _permit = queue.slot.clone().acquire_owned().await;
+            queue.update(&id, |job| {
+                job.state = JobState::Running;
+                JobEvent::Started
+            });
+
+            // The OCR workers block, so the run gets a thread of its own
+            let runner = queue.clone();
+            let job_id = id.clone();
+            let result = tokio::task::spawn_blocking(move || {
+                Handle::current().block_on(run(runner, job_id))
+            })
+            .await;
+
+            queue.update(&id, |job| {
+                match result {
+                    Ok(Ok(Outcome::Analyze(summary))) => {
+                        job.state = JobState::Completed;
+                        job.summary = Some(summary);
+                    }
+                    Ok(Ok(Outcome::Clean(summary))) => {
+                        job.state = JobState::Completed;
+                        job.clean_summary = Some(summary);
+                    }
+                    Ok(Err(e)) => {
+                        job.state = JobState::Failed;
+                        job.error = Some(format!("{:#}", e));
+                    }
+                    Err(e) => {
+                        job.state = JobState::Failed;
+                        job.error = Some(format!("Job panicked: {}", e));
+                    }
+                }
+                JobEvent::Finished(job.clone())
+            });
+            if let Some(job) = queue.get(&id) {
+                tracing::info!("Job {}: {:?}", id, job.state);
+            }
+        });
+        job
+    }
+
+    /// Change a job's status, sending the event `change` returns to its
+    /// subscribers
+    ///
+    /// Starts and finishes are recorded to the log, progress is not.
+    fn update(&self, id: &str, change: impl FnOnce(&mut Job) -> JobEvent) {
+        let recorded = {
+            let mut jobs = self.jobs.lock().expect("jobs lock");
+            let Some(entry) = jobs.entries.get_mut(id) else {
+                return;
+            };
+            let event = change(&mut entry.job);
+            let progress = matches!(
+                event,
+                JobEvent::Progress(_) | JobEvent::CleanProgress { .. }
+            );
+            let finished = matches!(event, JobEvent::Finished(_));
+            let recorded = (!progress).then(|| entry.job.clone());
+            // No subscribers is not an error
+            let _ = entry.events.send(event);
+            if finished {
+                jobs.finished(id);
+            }
+            recorded
+        };
+        if let (Some(log), Some(job)) = (&self.log, recorded) {
+            log.record(&job);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_pipeline::keypunch::KeypunchModel;
+    use core_pipeline::storage::StorageBackend;
+    use core_pipeline::types::PageId;
+    use std::time::Duration;
+
+    #[test]
+    fn test_record_progress() {
+        let mut job = Job::new(JobKind::Analyze, "set");
+        let artifact = PageId::new();
+        job.record(&AnalyzeProgress {
+            phase: AnalyzePhase::Correct,
+            done: 2,
+            total: 5,
+            artifact,
+            index: 1,
+            errors: vec!["OCR failed: no text".to_string()],
+            result: None,
+        });
+        assert_eq!(job.phase, Some(AnalyzePhase::Correct));
+        assert_eq!((job.completed, job.total), (2, 5));
+        assert_eq!(job.current_artifact, Some(artifact.0.to_string()));
+        assert_eq!(job.errors[0].message, "OCR failed: no text");
+
+        let json = serde_json::to_value(&job).unwrap();
+        assert_eq!(json["kind"], "analyze");
+        assert_eq!(json["state"], "queued");
+        assert_eq!(json["phase"], "correct");
+    }
+
+    #[tokio::test]
+    async fn test_events() {
+        let queue = JobQueue::default();
+        let job = Job::new(JobKind::Analyze, "set");
+        let id = job.id.clone();
+        queue.insert(job);
+
+        let events = queue.events(&id).unwrap();
+        queue.update(&id, |job| {
+            job.state = JobState::Running;
+            JobEvent::Started
+        });
+        queue.update(&id, |job| {
+            job.state = JobState::Completed;
+            JobEvent::Finished(job.clone())
+        });
+        let events: Vec<JobEvent> = events.collect().await;
+        assert_eq!(events.len(), 3);
+        assert!(matches!(&events[0], JobEvent::Status(job) if job.state == JobState::Queued));
+        assert_eq!(events[1], JobEvent::Started);
+        assert!(matches!(&events[2], JobEvent::Finished(job) if job.is_finished()));
+        assert_eq!(
+            serde_json::to_value(&events[1]).unwrap(),
+            serde_json::json!({ "event": "started" })
+        );
+
+        // A finished job has nothing more to tell
+        let events: Vec<JobEvent> = queue.events(&id).unwrap().collect().await;
+        assert_eq!(events.len(), 1);
+        assert!(queue.events("unknown").is_none());
+    }
+
+    #[derive(Default)]
+    struct MemoryLog(Mutex<Vec<Job>>);
+
+    impl JobLog for MemoryLog {
+        fn record(&self, job: &Job) {
+            self.0.lock().unwrap().push(job.clone());
+        }
+    }
+
+    #[test]
+    fn test_restore_jobs() {
+        let log = Arc::new(MemoryLog::default());
+        let mut finished = Job::new(JobKind::Analyze, "set");
+        finished.state = JobState::Completed;
+        let running = Job::new(JobKind::Analyze, "set");
+        let queue = JobQueue::with_log(log.clone(), vec![finished.clone(), running.clone()]);
+
+        assert_eq!(queue.get(&finished.id), Some(finished));
+        let interrupted = queue.get(&running.id).unwrap();
+        assert_eq!(interrupted.state, JobState::Failed);
+        assert_eq!(*log.0.lock().unwrap(), vec![interrupted]);
+
+        let json = serde_json::to_string(&queue.get(&running.id).unwrap()).unwrap();
+        let job: Job = serde_json::from_str(&json).unwrap();
+        assert_eq!(job.id, running.id);
+    }
+
+    #[test]
+    fn test_finished_jobs_are_dropped() {
+        let queue = JobQueue::default();
+        let queued = Job::new(JobKind::Analyze, "set");
+        queue.insert(queued.clone());
+        let ids: Vec<String> = (0..=FINISHED_JOBS_KEPT)
+            .map(|_| {
+                let job = Job::new(JobKind::Analyze, "set");
+                let id = job.id.clone();
+                queue.insert(job);
+                queue.update(&id, |job| {
+                    job.state = JobState::Completed;
+                    JobEvent::Finished(job.clone())
+                });
+                id
+            })
+            .collect();
+
+        assert!(queue.get(&ids[0]).is_none());
+        assert!(queue.get(&ids[1]).is_some());
+        assert!(queue.get(&ids[FINISHED_JOBS_KEPT]).is_some());
+        assert_eq!(queue.get(&queued.id), Some(queued));
+        assert_eq!(
+            queue.jobs.lock().unwrap().entries.len(),
+            FINISHED_JOBS_KEPT + 1
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_analyze_job_completes() {
+        let data = tempfile::tempdir().unwrap();
+        let (dir, manifest) = scan3data::create_scan_set(
+            data.path(),
+            "Box 3",
+            KeypunchModel::default(),
+            StorageBackend::Json,
+        )
+        .unwrap();
+        let queue = JobQueue::default();
+        let id = manifest.scan_set_id.0.to_string();
+        let job = queue.enqueue_analyze(&id, dir, AnalyzeOptions::default());
+        assert_eq!(job.state, JobState::Queued);
+
+        let mut state = job.state;
+        for _ in 0..100 {
+            state = queue.get(&job.id).unwrap().state;
+            if state == JobState::Completed || state == JobState::Failed {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let finished = queue.get(&job.id).unwrap();
+        assert_eq!(state, JobState::Completed, "{:?}", finished.error);
+        assert_eq!(finished.summary.unwrap().artifacts, 0);
+        assert!(queue.get("unknown").is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_clean_job_completes() {
+        let data = tempfile::tempdir().unwrap();
+        let (dir, manifest) = scan3data::create_scan_set(
+            data.path(),
+            "Box 3",
+            KeypunchModel::default(),
+            StorageBackend::Json,
+        )
+        .unwrap();
+        // Nothing to clean, so Gemini is never called
+        let client = GeminiClient::new(llm_bridge::GeminiConfig {
+            api_key: "unused".to_string(),
+            model: "gemini-2.5-flash-image".to_string(),
+            timeout_secs: 1,
+        })
+        .unwrap();
+        let queue = JobQueue::default();
+        let id = manifest.scan_set_id.0.to_string();
+        let job = queue.enqueue_clean(&id, dir, client, CleanOptions::default());
+        assert_eq!(job.kind, JobKind::Clean);
+
+        for _ in 0..100 {
+            if queue.get(&job.id).unwrap().is_finished() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        let finished = queue.get(&job.id).unwrap();
+        assert_eq!(finished.state, JobState::Completed, "{:?}", finished.error);
+        assert_eq!(finished.clean_summary.unwrap().cleaned, 0);
+        assert_eq!(serde_json::to_value(finished.kind).unwrap(), "clean");
+    }
+}
@@ -0,0 +1,1004 @@
+//! scan3data REST API server
+//!
+//! Three-phase processing pipeline: Scan -> Classify & Correct -> Convert
+//!
+//! The router is run by the `scan3data-server` binary and in-process by
+//! `scan3data serve --mode api`. It serves one scan set directory, the
+//! built frontend and the API:
+//!
+//! ```text
+//! GET  /health                        OK
+//! GET  /api/scan_sets                 the served scan set (id and name), or all
+//!                                     the store knows
+//! POST /api/scan_sets                 create a scan set under the data directory
+//! POST /api/scan_sets/:id/upload      upload an image
+//! GET  /api/scan_sets/:id/artifacts   artifacts of the served or a created scan set
+//!                                     (?kind=&min_confidence=&max_confidence=)
+//! GET  /api/scan_sets/:id/artifacts/:artifact/history
+//!                                     edits made to an artifact's text
+//! GET  /api/scan_sets/:id/files/*path a file of the scan set (images, thumbnails)
+//! POST /api/scan_sets/:id/analyze     queue an analysis job
+//! POST /api/scan_sets/:id/clean       queue a Gemini cleaning job
+//! GET  /api/jobs/:id                  status and progress of a job
+//! GET  /api/jobs/:id/ws               WebSocket pushing a job's events as JSON
+//! GET  /api/jobs/:id/events           the same events as Server-Sent Events
+//! POST /api/clean-image               clean an image with Gemini
+//! POST /api/ocr                       OCR an image (base64 JSON or multipart)
+//! ```
+//!
+//! Created scan sets are directories named by their ID under the data
+//! directory, in the CLI's format, so the CLI can process them. Analysis
+//! and cleaning run the CLI's `analyze` and `clean` pipelines as background
+//! jobs ([`jobs`]).
+//!
+//! With the `sqlite` feature and a database configured, the server keeps
+//! the scan sets it knows, its jobs and edit history in SQLite (`store`),
+//! so they survive a restart, and creates scan sets with SQLite storage;
+//! without one, it only knows the served scan set and those under the data
+//! directory, and keeps jobs in memory.
+//!
+//! With API keys configured, every API route but `/health` needs one
+//! ([`auth`]).
+//!
+//! Copyright (c) 2025 Michael A Wright
+
+pub mod auth;
+pub mod jobs;
+pub mod ocr;
+#[cfg(feature = "sqlite")]
+pub mod store;
+
+use anyhow::Result;
+use auth::{require_api_key, ApiKeys};
+use axum::{
+    body::Bytes,
+    extract::ws::{Message, WebSocket, WebSocketUpgrade},
+    extract::{DefaultBodyLimit, FromRequest, Multipart, Path as UrlPath, Query, Request, State},
+    http::{header, StatusCode},
+    middleware,
+    response::sse::{Event, KeepAlive, Sse},
+    response::{IntoResponse, Json, Response},
+    routing::{get, post},
+    Router,
+};
+use base64::{engine::general_purpose, Engine as _};
+use core_pipeline::keypunch::KeypunchModel;
+use core_pipeline::scan_set;
+use core_pipeline::storage::{ArtifactFilter, StorageBackend};
+use core_pipeline::types::{ArtifactKind, PageArtifact, TextRevision};
+use futures::{Stream, StreamExt};
+use jobs::{Job, JobEvent, JobQueue};
+use scan3data::compare::url_path;
+use scan3data::{AnalyzeOptions, CleanOptions, Config};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+#[cfg(feature = "sqlite")]
+use store::ServerStore;
+use tower_http::cors::CorsLayer;
+use tower_http::services::{ServeDir, ServeFile};
+use tower_http::trace::TraceLayer;
+
+/// Address the server listens on unless configured otherwise; only this
+/// machine can connect
+pub const DEFAULT_HOST: &str = "127.0.0.1";
+
+/// Port the server listens on unless configured otherwise
+pub const DEFAULT_PORT: u16 = 7214;
+
+/// Directory of the built frontend unless configured otherwise
+pub const DEFAULT_DIST_DIR: &str = "dist";
+
+/// Directory new scan sets are created in unless configured otherwise
+pub const DEFAULT_DATA_DIR: &str = "scan_sets";
+
+/// Largest request body `/api/ocr` accepts: a phone photo, base64-encoded
+const OCR_BODY_LIMIT: usize = 32 * 1024 * 1024;
+
+/// Name of a created scan set when the request names none
+const DEFAULT_SCAN_SET_NAME: &str = "scan_set";
+
+/// What the server serves
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// Settings from `scan3data.toml`, shared with the CLI
+    pub config: Config,
+    /// Scan set directory the API reads, if any
+    pub scan_set: Option<PathBuf>,
+    /// Directory of the built frontend
+    pub dist: PathBuf,
+    /// Directory new scan sets are created in
+    pub data_dir: PathBuf,
+    /// SQLite database the server keeps its state in, if any (needs the
+    /// `sqlite` feature)
+    pub database: Option<PathBuf>,
+}
+
+#[derive(Clone)]
+struct AppState {
+    /// Settings from `scan3data.toml`, shared with the CLI
+    config: Config,
+    /// Scan set directory the API reads
+    scan_set: Option<PathBuf>,
+    /// Directory new scan sets are created in
+    data_dir: PathBuf,
+    /// Background jobs
+    jobs: JobQueue,
+    /// Database of the server's state, if configured
+    #[cfg(feature = "sqlite")]
+    store: Option<Arc<ServerStore>>,
+}
+
+impl AppState {
+    /// Directory of the scan set with this ID: the served one, one the
+    /// store knows, or one created under the data directory
+    fn scan_set_dir(&self, id: &str) -> Option<PathBuf> {
+        #[cfg(feature = "sqlite")]
+        if let Some(store) = &self.store {
+            match store.scan_set_path(id) {
+                Ok(Some(dir)) => return Some(dir),
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to look up scan set {}: {:#}", id, e),
+            }
+        }
+        if let Some(dir) = &self.scan_set {
+            // A served set that fails to load still leaves the others
+            match scan_set::load_manifest(dir) {
+                Ok(manifest) if manifest.scan_set_id.0.to_string() == id => {
+                    return Some(dir.clone())
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Failed to load scan set {}: {}", dir.display(), e),
+            }
+        }
+        // Only IDs, never paths, become directory names
+        let id = uuid::Uuid::parse_str(id).ok()?;
+        let dir = self.data_dir.join(id.to_string());
+        scan_set::exists(&dir).then_some(dir)
+    }
+
+    /// Storage of the scan sets the server creates: SQLite along with the
+    /// server's own database
+    fn storage(&self) -> StorageBackend {
+        #[cfg(feature = "sqlite")]
+        if self.store.is_some() {
+            return StorageBackend::Sqlite;
+        }
+        StorageBackend::Json
+    }
+}
+
+/// Router of the API and the frontend: API routes take precedence, then
+/// static files
+///
+/// Fails if the configured database cannot be opened.
+pub fn router(options: ServerOptions) -> Result<Router> {
+    let state = Arc::new(app_state(&options)?);
+
+    let mut api_routes = Router::new()
+        .route("/api/scan_sets", get(list_scan_sets).post(create_scan_set))
+        .route("/api/scan_sets/:id/upload", post(upload_image))
+        .route("/api/scan_sets/:id/artifacts", get(get_artifacts))
+        .route(
+            "/api/scan_sets/:id/artifacts/:artifact/history",
+            get(get_artifact_history),
+        )
+        .route("/api/scan_sets/:id/files/*path", get(get_file))
+        .route("/api/scan_sets/:id/analyze", post(analyze_scan_set))
+        .route("/api/scan_sets/:id/clean", post(clean_scan_set))
+        .route("/api/jobs/:id", get(get_job))
+        .route("/api/jobs/:id/ws", get(job_events_ws))
+        .route("/api/jobs/:id/events", get(job_events_sse))
+        .route("/api/clean-image", post(clean_image))
+        .route(
+            "/api/ocr",
+            post(run_ocr).layer(DefaultBodyLimit::max(OCR_BODY_LIMIT)),
+        );
+    let keys = ApiKeys::new(options.config.api_keys());
+    if keys.is_empty() {
+        tracing::info!("No API keys configured; the API is open");
+    } else {
+        api_routes = api_routes.route_layer(middleware::from_fn_with_state(keys, require_api_key));
+    }
+    // Added after the key check, so it needs no key
+    let api_routes = api_routes
+        .route("/health", get(health_check))
+        .with_state(state);
+
+    Ok(Router::new()
+        .merge(api_routes)
+        .merge(frontend(&options.dist))
+        .layer(CorsLayer::permissive())
+        .layer(TraceLayer::new_for_http()))
+}
+
+/// State of a server without a database
+#[cfg(not(feature = "sqlite"))]
+fn app_state(options: &ServerOptions) -> Result<AppState> {
+    if let Some(database) = &options.database {
+        anyhow::bail!(
+            "Database {} is configured, but this build lacks the `sqlite` feature",
+            database.display()
+        );
+    }
+    Ok(AppState {
+        config: options.config.clone(),
+        scan_set: options.scan_set.clone(),
+        data_dir: options.data_dir.clone(),
+        jobs: JobQueue::default(),
+    })
+}
+
+/// State of the server, with its database if configured: the scan sets it
+/// knows (the served one and those under the data directory among them)
+/// are read again, and its jobs restored
+#[cfg(feature = "sqlite")]
+fn app_state(options: &ServerOptions) -> Result<AppState> {
+    let mut state = AppState {
+        config: options.config.clone(),
+        scan_set: options.scan_set.clone(),
+        data_dir: options.data_dir.clone(),
+        jobs: JobQueue::default(),
+        store: None,
+    };
+    let Some(database) = &options.database else {
+        return Ok(state);
+    };
+    let store = Arc::new(ServerStore::open(database)?);
+    let mut dirs: Vec<PathBuf> = store.scan_sets()?.into_iter().map(|s| s.path).collect();
+    dirs.extend(options.scan_set.clone());
+    if let Ok(entries) = std::fs::read_dir(&options.data_dir) {
+        dirs.extend(entries.flatten().map(|entry| entry.path()));
+    }
+    dirs.sort();
+    dirs.dedup();
+    for dir in dirs {
+        if scan_set::exists(&dir) {
+            store.add_scan_set(&dir)?;
+        } else {
+            tracing::warn!("Scan set {} is gone", dir.display());
+        }
+    }
+    state.jobs = JobQueue::with_log(store.clone(), store.jobs()?);
+    state.store = Some(store);
+    Ok(state)
+}
+
+/// Router serving the built frontend (WASM), with `index.html` for paths
+/// that are not files so client-side routes survive a reload
+pub fn frontend(dist: &Path) -> Router {
+    let index = ServeFile::new(dist.join("index.html"));
+    Router::new().fallback_service(ServeDir::new(dist).fallback(index))
+}
+
+async fn health_check() -> &'static str {
+    "OK"
+}
+
+async fn list_scan_sets(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ScanSetInfo>>, StatusCode> {
+    #[cfg(feature = "sqlite")]
+    if let Some(store) = &state.store {
+        let scan_sets = store.scan_sets().map_err(internal_error)?;
+        return Ok(Json(
+            scan_sets
+                .into_iter()
+                .map(|s| ScanSetInfo {
+                    id: s.id,
+                    name: s.name,
+                })
+                .collect(),
+        ));
+    }
+    let Some(dir) = &state.scan_set else {
+        return Ok(Json(Vec::new()));
+    };
+    let manifest = scan_set::load_manifest(dir).map_err(|e| {
+        tracing::error!("Failed to load scan set {}: {}", dir.display(), e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok(Json(vec![ScanSetInfo {
+        id: manifest.scan_set_id.0.to_string(),
+        name: manifest.name,
+    }]))
+}
+
+async fn create_scan_set(
+    State(state): State<Arc<AppState>>,
+    request: Option<Json<CreateScanSetRequest>>,
+) -> Result<Json<CreateScanSetResponse>, StatusCode> {
+    let name = request
+        .and_then(|Json(request)| request.name)
+        .filter(|name| !name.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_SCAN_SET_NAME.to_string());
+    let storage = state.storage();
+    let (dir, manifest) =
+        scan3data::create_scan_set(&state.data_dir, &name, KeypunchModel::default(), storage)
+            .map_err(|e| {
+                tracing::error!("Failed to create scan set: {:#}", e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+    tracing::info!("Created scan set {} in {}", name, dir.display());
+    #[cfg(feature = "sqlite")]
+    if let Some(store) = &state.store {
+        store.add_scan_set(&dir).map_err(internal_error)?;
+    }
+    Ok(Json(CreateScanSetResponse {
+        id: manifest.scan_set_id.0.to_string(),
+        name: manifest.name,
+        manifest: match storage {
+            StorageBackend::Json => dir.join(scan_set::MANIFEST_FILE),
+            StorageBackend::Sqlite => dir.join(core_pipeline::storage::SQLITE_FILE),
+        }
+        .display()
+        .to_string(),
+        images: dir.join("images").display().to_string(),
+        path: dir.display().to_string(),
+    }))
+}
+
+async fn upload_image(
+    State(_state): State<Arc<AppState>>,
+) -> Result<Json<UploadResponse>, StatusCode> {
+    // TODO: Handle image upload
+    Ok(Json(UploadResponse {
+        artifact_id: uuid::Uuid::new_v4().to_string(),
+        status: "uploaded".to_string(),
+    }))
+}
+
+async fn get_artifacts(
+    State(state): State<Arc<AppState>>,
+    UrlPath(id): UrlPath<String>,
+    Query(query): Query<ArtifactQuery>,
+) -> Result<Json<ArtifactsResponse>, StatusCode> {
+    let artifacts = load_artifacts(&state, &id, &query)?;
+    Ok(Json(ArtifactsResponse {
+        artifacts: artifacts
+            .iter()
+            .map(|artifact| ArtifactInfo::of_artifact(&id, artifact))
+            .collect(),
+    }))
+}
+
+async fn get_artifact_history(
+    State(state): State<Arc<AppState>>,
+    UrlPath((id, artifact_id)): UrlPath<(String, String)>,
+) -> Result<Json<Vec<TextRevision>>, StatusCode> {
+    let artifacts = load_artifacts(&state, &id, &ArtifactQuery::default())?;
+    let artifact = artifacts
+        .into_iter()
+        .find(|artifact| artifact.id.0.to_string() == artifact_id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    #[cfg(feature = "sqlite")]
+    if let Some(store) = &state.store {
+        let edits = store.edits(&id, &artifact_id).map_err(internal_error)?;
+        return Ok(Json(edits));
+    }
+    Ok(Json(artifact.metadata.revisions))
+}
+
+/// Artifacts of a scan set that the query includes
+fn load_artifacts(
+    state: &AppState,
+    id: &str,
+    query: &ArtifactQuery,
+) -> Result<Vec<PageArtifact>, StatusCode> {
+    let Some(dir) = state.scan_set_dir(id) else {
+        return Err(StatusCode::NOT_FOUND);
+    };
+    // A kind that does not exist matches nothing
+    let Some(filter) = query.filter() else {
+        return Ok(Vec::new());
+    };
+    scan_set::find_artifacts(&dir, &filter).map_err(|e| {
+        tracing::error!("Failed to load scan set {}: {}", dir.display(), e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Log an unexpected error, answering 500
+fn internal_error(e: anyhow::Error) -> StatusCode {
+    tracing::error!("{:#}", e);
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+async fn get_file(
+    State(state): State<Arc<AppState>>,
+    UrlPath((id, path)): UrlPath<(String, String)>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let dir = state.scan_set_dir(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let path = Path::new(&path);
+    // Only paths inside the scan set
+    if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let bytes = tokio::fs::read(dir.join(path))
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(([(header::CONTENT_TYPE, content_type(path))], bytes))
+}
+
+async fn analyze_scan_set(
+    State(state): State<Arc<AppState>>,
+    UrlPath(id): UrlPath<String>,
+    request: Option<Json<AnalyzeRequest>>,
+) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    let dir = state.scan_set_dir(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let options = request.options(&state.config).map_err(internal_error)?;
+    let job = state.jobs.enqueue_analyze(&id, dir, options);
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+async fn clean_scan_set(
+    State(state): State<Arc<AppState>>,
+    UrlPath(id): UrlPath<String>,
+    request: Option<Json<CleanRequest>>,
+) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    let dir = state.scan_set_dir(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let client = state
+        .config
+        .gemini_config()
+        .and_then(llm_bridge::GeminiClient::new)
+        .map_err(|e| {
+            tracing::error!("Failed to create Gemini client: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let options = request.options(&state.config);
+    let job = state.jobs.enqueue_clean(&id, dir, client, options);
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+async fn get_job(
+    State(state): State<Arc<AppState>>,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Json<Job>, StatusCode> {
+    state.jobs.get(&id).map(Json).ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn job_events_ws(
+    State(state): State<Arc<AppState>>,
+    UrlPath(id): UrlPath<String>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    let events = state.jobs.events(&id).ok_or(StatusCode::NOT_FOUND)?;
+    Ok(ws.on_upgrade(move |socket| send_events(socket, events)))
+}
+
+/// Send job events over a WebSocket as JSON text messages, closing it
+/// after the last
+async fn send_events(mut socket: WebSocket, events: impl Stream<Item = JobEvent>) {
+    let mut events = std::pin::pin!(events);
+    while let Some(event) = events.next().await {
+        let Ok(text) = serde_json::to_string(&event) else {
+            continue;
+        };
+        if socket.send(Message::Text(text)).await.is_err() {
+            // The client went away
+            return;
+        }
+    }
+    let _ = socket.send(Message::Close(None)).await;
+}
+
+/// Job events for clients whose proxies break WebSockets: each event is an
+/// unnamed (`message`) event with the JSON the WebSocket sends
+///
+/// The stream ends after the last event. `EventSource` reconnects when a
+/// stream ends, so clients close it on `finished`; a reconnect to a
+/// finished job gets its final status alone.
+async fn job_events_sse(
+    State(state): State<Arc<AppState>>,
+    UrlPath(id): UrlPath<String>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, StatusCode> {
+    let events = state.jobs.events(&id).ok_or(StatusCode::NOT_FOUND)?;
+    let events = events.map(|event| Event::default().json_data(event));
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// MIME type of a scan set file, by extension
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "tif" | "tiff" => "image/tiff",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "webp" => "image/webp",
+        "json" => "application/json",
+        "txt" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+#[derive(Serialize)]
+struct ScanSetInfo {
+    id: String,
+    name: String,
+}
+
+#[derive(Deserialize)]
+struct CreateScanSetRequest {
+    /// Scan set name (default: scan_set)
+    #[serde(default)]
+    name: Option<String>,
+}
+
+#[derive(Serialize)]
+struct CreateScanSetResponse {
+    id: String,
+    name: String,
+    /// Scan set directory
+    path: String,
+    /// Manifest file
+    manifest: String,
+    /// Directory of the scan set's images
+    images: String,
+}
+
+/// Optional stages of an analysis job; the rest comes from the
+/// configuration, as for `scan3data analyze`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct AnalyzeRequest {
+    /// Cross-check the classification with the text model
+    use_llm: bool,
+    /// Correct OCR text with this Ollama vision model
+    vision_model: Option<String>,
+    /// Auto-fix OCR confusions
+    autofix: bool,
+    /// Start from the Gemini-cleaned images
+    prefer_cleaned: bool,
+    /// Continue an interrupted analysis with the same options
+    resume: bool,
+}
+
+impl AnalyzeRequest {
+    /// Options of the run, completed from the configuration
+    fn options(self, config: &Config) -> anyhow::Result<AnalyzeOptions> {
+        let settings = &config.analyze;
+        let autofix_threshold = settings
+            .autofix_threshold
+            .unwrap_or(core_pipeline::autofix::DEFAULT_THRESHOLD);
+        Ok(AnalyzeOptions {
+            ollama: config.ollama_config(),
+            use_llm: self.use_llm,
+            text_model: config.models.text.clone(),
+            vision_model: self.vision_model,
+            vision_max_dimension: settings.vision_max_dimension,
+            autofix_threshold: self.autofix.then_some(autofix_threshold),
+            hooks: Default::default(),
+            jobs: settings.jobs.unwrap_or(1),
+            model_jobs: settings.model_jobs.unwrap_or(1),
+            prefer_cleaned: self.prefer_cleaned,
+            preprocess: config.preprocess_options(),
+            rules: config.rule_set(None)?,
+            resume: self.resume,
+        })
+    }
+}
+
+/// Options of a clean job; the budget defaults to the configured one, as
+/// for `scan3data clean`
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct CleanRequest {
+    /// Clean artifacts that already have a cleaned image again
+    force: bool,
+    /// Stop before the job spends more than this (USD)
+    max_cost_usd: Option<f64>,
+}
+
+impl CleanRequest {
+    /// Options of the run, completed from the configuration
+    fn options(self, config: &Config) -> CleanOptions {
+        CleanOptions {
+            force: self.force,
+            max_cost_usd: self.max_cost_usd.or(config.clean.max_cost_usd),
+        }
+    }
+}
+
+/// Which artifacts of a scan set to list; every filter is optional
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+struct ArtifactQuery {
+    /// Artifact kind, as listed (`ListingSource`), in any case
+    kind: Option<String>,
+    /// Lowest classification confidence (0.0-1.0)
+    min_confidence: Option<f32>,
+    /// Highest classification confidence (0.0-1.0)
+    max_confidence: Option<f32>,
+}
+
+impl ArtifactQuery {
+    /// The storage filter of the query; `None` when the kind is unknown
+    fn filter(&self) -> Option<ArtifactFilter> {
+        let kind = match self.kind.as_deref() {
+            Some(kind) => Some(ArtifactKind::parse(kind)?),
+            None => None,
+        };
+        Some(ArtifactFilter {
+            kind,
+            min_confidence: self.min_confidence,
+            max_confidence: self.max_confidence,
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct UploadResponse {
+    artifact_id: String,
+    status: String,
+}
+
+#[derive(Serialize)]
+struct ArtifactsResponse {
+    artifacts: Vec<ArtifactInfo>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArtifactInfo {
+    id: String,
+    kind: String,
+    /// Confidence of the classification (0.0-1.0)
+    confidence: f32,
+    /// Whether OCR found text
+    has_text: bool,
+    /// Detected page number
+    page_number: Option<u32>,
+    /// Files the scan was ingested from
+    original_filenames: Vec<String>,
+    /// URL of the raw image
+    image_url: String,
+    /// URL of the thumbnail, once generated
+    thumbnail_url: Option<String>,
+    /// URL of the cleaned image, once cleaned
+    cleaned_image_url: Option<String>,
+}
+
+impl ArtifactInfo {
+    /// Record of an artifact of the scan set with this ID
+    fn of_artifact(scan_set_id: &str, artifact: &PageArtifact) -> Self {
+        let file_url =
+            |path: &Path| format!("/api/scan_sets/{}/files/{}", scan_set_id, url_path(path));
+        let metadata = &artifact.metadata;
+        Self {
+            id: artifact.id.0.to_string(),
+            kind: format!("{:?}", artifact.layout_label),
+            confidence: metadata.confidence,
+            has_text: artifact
+                .content_text
+                .as_deref()
+                .is_some_and(|text| !text.trim().is_empty()),
+            page_number: metadata.page_number,
+            original_filenames: metadata.original_filenames.clone(),
+            image_url: file_url(&artifact.raw_image_path),
+            thumbnail_url: metadata.thumbnail_path.as_deref().map(file_url),
+            cleaned_image_url: metadata.cleaned_image_path.as_deref().map(file_url),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CleanImageRequest {
+    /// Base64-encoded image data
+    image_data: String,
+}
+
+#[derive(Serialize)]
+struct CleanImageResponse {
+    /// Base64-encoded cleaned image data
+    cleaned_image_data: String,
+}
+
+async fn clean_image(
+    State(state): State<Arc<AppState>>,
+    Json(payload): Json<CleanImageRequest>,
+) -> Result<Json<CleanImageResponse>, StatusCode> {
+    // Decode base64 image
+    let image_bytes = general_purpose::STANDARD
+        .decode(&payload.image_data)
+        .map_err(|e| {
+            tracing::error!("Failed to decode base64 image: {}", e);
+            StatusCode::BAD_REQUEST
+        })?;
+
+    // Create Gemini client from environment, with the configured model
+    let gemini_client = state
+        .config
+        .gemini_config()
+        .and_then(llm_bridge::GeminiClient::new)
+        .map_err(|e| {
+            tracing::error!("Failed to create Gemini client: {}", e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    // Clean the image
+    let cleaned_bytes = gemini_client.clean_image(&image_bytes).await.map_err(|e| {
+        tracing::error!("Failed to clean image: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    // Encode back to base64
+    let cleaned_b64 = general_purpose::STANDARD.encode(&cleaned_bytes);
+
+    Ok(Json(CleanImageResponse {
+        cleaned_image_data: cleaned_b64,
+    }))
+}
+
+#[derive(Deserialize)]
+struct OcrRequest {
+    /// Base64-encoded image data, or a `data:` URL
+    image_data: String,
+}
+
+#[derive(Serialize)]
+struct OcrResponse {
+    /// Page text, preserving layout
+    text: String,
+    /// Recognized lines, with their confidence
+    lines: Vec<ocr::OcrLine>,
+}
+
+async fn run_ocr(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+) -> Result<Json<OcrResponse>, StatusCode> {
+    let is_multipart = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("multipart/form-data"));
+    let image_bytes = if is_multipart {
+        let multipart = Multipart::from_request(request, &())
+            .await
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+        ocr::multipart_image(multipart)
+            .await
+            .map_err(|e| {
+                tracing::error!("Failed to read multipart body: {}", e);
+                StatusCode::BAD_REQUEST
+            })?
+            .ok_or(StatusCode::BAD_REQUEST)?
+            .to_vec()
+    } else {
+        let body = Bytes::from_request(request, &())
+            .await
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+        let request: OcrRequest =
+            serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+        general_purpose::STANDARD
+            .decode(ocr::strip_data_url(&request.image_data))
+            .map_err(|e| {
+                tracing::error!("Failed to decode base64 image: {}", e);
+                StatusCode::BAD_REQUEST
+            })?
+    };
+    let image = image::load_from_memory(&image_bytes).map_err(|e| {
+        tracing::error!("Failed to decode image: {}", e);
+        StatusCode::BAD_REQUEST
+    })?;
+
+    // Tesseract blocks
+    let preprocess = state.config.preprocess_options();
+    let output = tokio::task::spawn_blocking(move || ocr::recognize(&image, &preprocess))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(|e| {
+            tracing::error!("OCR failed: {}", e);
+            match e {
+                core_pipeline::Error::OcrEngineMissing { .. } => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })?;
+    Ok(Json(OcrResponse {
+        lines: ocr::ocr_lines(&output.words),
+        text: output.text,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core_pipeline::types::{PageId, PageMetadata, ScanSetId};
+
+    #[test]
+    fn test_clean_image_request_deserialize() {
+        let json = r#"{"image_data": "dGVzdA=="}"#;
+        let req: CleanImageRequest = serde_json::from_str(json).unwrap();
+        assert_eq!(req.image_data, "dGVzdA==");
+    }
+
+    #[test]
+    fn test_clean_image_response_serialize() {
+        let response = CleanImageResponse {
+            cleaned_image_data: "Y2xlYW5lZA==".to_string(),
+        };
+        let json = serde_json::to_string(&response).unwrap();
+        assert!(json.contains("cleaned_image_data"));
+        assert!(json.contains("Y2xlYW5lZA=="));
+    }
+
+    #[test]
+    fn test_create_scan_set_request_deserialize() {
+        let req: CreateScanSetRequest = serde_json::from_str(r#"{"name": "Box 3"}"#).unwrap();
+        assert_eq!(req.name.as_deref(), Some("Box 3"));
+        let req: CreateScanSetRequest = serde_json::from_str("{}").unwrap();
+        assert!(req.name.is_none());
+    }
+
+    #[test]
+    fn test_analyze_request_options() {
+        let config = Config::from_toml("[analyze]\njobs = 4\nautofix_threshold = 0.9").unwrap();
+        let request: AnalyzeRequest = serde_json::from_str(r#"{"autofix": true}"#).unwrap();
+        let options = request.options(&config).unwrap();
+        assert_eq!(options.jobs, 4);
+        assert_eq!(options.autofix_threshold, Some(0.9));
+        assert!(options.vision_model.is_none());
+    }
+
+    #[test]
+    fn test_scan_set_dir_rejects_paths() {
+        let state = AppState {
+            config: Config::default(),
+            scan_set: None,
+            data_dir: PathBuf::from("."),
+            jobs: JobQueue::default(),
+            #[cfg(feature = "sqlite")]
+            store: None,
+        };
+        assert!(state.scan_set_dir("../etc").is_none());
+        assert!(state
+            .scan_set_dir(&uuid::Uuid::new_v4().to_string())
+            .is_none());
+    }
+
+    #[test]
+    fn test_scan_set_dir_past_broken_served_set() {
+        let data = tempfile::tempdir().unwrap();
+        let (dir, manifest) = scan3data::create_scan_set(
+            data.path(),
+            "Box 3",
+            KeypunchModel::default(),
+            StorageBackend::Json,
+        )
+        .unwrap();
+        let state = AppState {
+            config: Config::default(),
+            scan_set: Some(data.path().join("missing")),
+            data_dir: data.path().to_path_buf(),
+            jobs: JobQueue::default(),
+            #[cfg(feature = "sqlite")]
+            store: None,
+        };
+        let id = manifest.scan_set_id.0.to_string();
+        assert_eq!(state.scan_set_dir(&id), Some(dir));
+    }
+
+    #[test]
+    fn test_artifact_info() {
+        let artifact = PageArtifact {
+            id: PageId::new(),
+            scan_set: ScanSetId::new(),
+            raw_image_path: PathBuf::from("images/0123456789abcdef.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: Some("      X = 1".to_string()),
+            metadata: PageMetadata {
+                confidence: 0.75,
+                ..PageMetadata::default()
+            },
+        };
+        let info = ArtifactInfo::of_artifact("abc", &artifact);
+        assert_eq!(info.kind, "ListingSource");
+        assert!(info.has_text);
+        assert_eq!(
+            info.image_url,
+            "/api/scan_sets/abc/files/images/0123456789abcdef.jpg"
+        );
+        assert!(info.thumbnail_url.is_none());
+        assert_eq!(content_type(Path::new("images/a.JPG")), "image/jpeg");
+
+        let query: ArtifactQuery =
+            serde_json::from_str(r#"{"kind": "listingsource", "min_confidence": 0.5}"#).unwrap();
+        assert!(query.filter().unwrap().includes(&artifact));
+        let query = ArtifactQuery {
+            max_confidence: Some(0.5),
+            ..query
+        };
+        assert!(!query.filter().unwrap().includes(&artifact));
+        let query: ArtifactQuery = serde_json::from_str(r#"{"kind": "deck"}"#).unwrap();
+        assert!(query.filter().is_none());
+    }
+
+    async fn call(app: &Router, method: &str, uri: &str) -> (StatusCode, String, Vec<u8>) {
+        use tower::ServiceExt;
+        let request = axum::http::Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app.clone().oneshot(request).await.unwrap();
+        let content_type = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, content_type, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_events_of_finished_job() {
+        let data = tempfile::tempdir().unwrap();
+        let app = router(ServerOptions {
+            config: Config::default(),
+            scan_set: None,
+            dist: data.path().join("dist"),
+            data_dir: data.path().to_path_buf(),
+            database: None,
+        })
+        .unwrap();
+
+        let (status, _, body) = call(&app, "POST", "/api/scan_sets").await;
+        assert_eq!(status, StatusCode::OK);
+        let scan_set: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let uri = format!(
+            "/api/scan_sets/{}/analyze",
+            scan_set["id"].as_str().unwrap()
+        );
+        let (status, _, body) = call(&app, "POST", &uri).await;
+        assert_eq!(status, StatusCode::ACCEPTED);
+        let job: Job = serde_json::from_slice(&body).unwrap();
+
+        let job_uri = format!("/api/jobs/{}", job.id);
+        let job = loop {
+            let (_, _, body) = call(&app, "GET", &job_uri).await;
+            let job: Job = serde_json::from_slice(&body).unwrap();
+            if job.is_finished() {
+                break job;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        };
+        assert_eq!(job.state, jobs::JobState::Completed);
+
+        // A finished job's stream holds its status, then ends
+        let (status, content_type, body) = call(&app, "GET", &format!("{}/events", job_uri)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(content_type.starts_with("text/event-stream"));
+        let body = String::from_utf8(body).unwrap();
+        let events: Vec<serde_json::Value> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|data| serde_json::from_str(data.trim()).unwrap())
+            .collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["event"], "status");
+        assert_eq!(events[0]["id"], job.id.as_str());
+        assert_eq!(events[0]["state"], "completed");
+
+        let (status, _, _) = call(&app, "GET", "/api/jobs/unknown/events").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_base64_roundtrip() {
+        let original = b"test image data";
+        let encoded = general_purpose::STANDARD.encode(original);
+        let decoded = general_purpose::STANDARD.decode(&encoded).unwrap();
+        assert_eq!(original, decoded.as_slice());
+    }
+}
@@ -0,0 +1,146 @@
+//! Server-side OCR of uploaded images
+//!
+//! `POST /api/ocr` runs Tesseract on one image, restricted to the IBM 1130
+//! character set, as `analyze` does for scan sets. The image comes as
+//! base64 in JSON (a `data:` URL too) or as the `image` part of a
+//! `multipart/form-data` body; the text comes back with each line's
+//! confidence.
+
+use axum::body::Bytes;
+use axum::extract::multipart::{Multipart, MultipartError};
+use core_pipeline::keypunch::KeypunchModel;
+use core_pipeline::ocr::{extract_ocr_tesseract, OcrOutput, OcrWord};
+use core_pipeline::preprocess::{preprocess_image, PreprocessOptions};
+use image::DynamicImage;
+use serde::Serialize;
+
+/// A recognized line of text
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OcrLine {
+    /// Words of the line, separated by single spaces
+    pub text: String,
+    /// Mean recognition confidence of the words (0-100)
+    pub confidence: f32,
+}
+
+/// Preprocess an image with the given steps and OCR it with the IBM 1130
+/// whitelist
+pub fn recognize(
+    image: &DynamicImage,
+    preprocess: &PreprocessOptions,
+) -> core_pipeline::Result<OcrOutput> {
+    let gray = preprocess_image(image, preprocess)?;
+    extract_ocr_tesseract(&gray, KeypunchModel::default())
+}
+
+/// Lines of the words, in reading order
+pub fn ocr_lines(words: &[OcrWord]) -> Vec<OcrLine> {
+    words
+        .chunk_by(|a, b| (a.block, a.paragraph, a.line) == (b.block, b.paragraph, b.line))
+        .map(|line| OcrLine {
+            text: line
+                .iter()
+                .map(|w| w.text.as_str())
+                .collect::<Vec<_>>()
+                .join(" "),
+            confidence: line.iter().map(|w| w.confidence).sum::<f32>() / line.len() as f32,
+        })
+        .collect()
+}
+
+/// Base64 image data, without the prefix of a `data:` URL
+pub fn strip_data_url(image_data: &str) -> &str {
+    match image_data.split_once(',') {
+        Some((prefix, data)) if prefix.starts_with("data:") => data,
+        _ => image_data,
+    }
+}
+
+/// The `image` field of a `multipart/form-data` body, else its first file
+pub async fn multipart_image(mut multipart: Multipart) -> Result<Option<Bytes>, MultipartError> {
+    let mut first_file = None;
+    while let Some(field) = multipart.next_field().await? {
+        if field.name() == Some("image") {
+            return Ok(Some(field.bytes().await?));
+        }
+        let is_file = field.file_name().is_some();
+        let bytes = field.bytes().await?;
+        if is_file && first_file.is_none() {
+            first_file = Some(bytes);
+        }
+    }
+    Ok(first_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, line: u32, confidence: f32) -> OcrWord {
+        OcrWord {
+            text: text.to_string(),
+            block: 1,
+            paragraph: 1,
+            line,
+            left: 0,
+            top: 0,
+            width: 10,
+            height: 10,
+            confidence,
+        }
+    }
+
+    #[test]
+    fn test_ocr_lines() {
+        let words = [
+            word("DO", 1, 90.0),
+            word("10", 1, 80.0),
+            word("END", 2, 50.0),
+        ];
+        let lines = ocr_lines(&words);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].text, "DO 10");
+        assert_eq!(lines[0].confidence, 85.0);
+        assert_eq!(lines[1].text, "END");
+        assert_eq!(strip_data_url("data:image/jpeg;base64,AAAA"), "AAAA");
+        assert_eq!(strip_data_url("AAAA"), "AAAA");
+    }
+
+    async fn image_of(content_type: &str, body: Vec<u8>) -> Option<Bytes> {
+        use axum::extract::FromRequest;
+        let request = axum::http::Request::builder()
+            .header("content-type", content_type)
+            .body(axum::body::Body::from(body))
+            .unwrap();
+        let multipart = Multipart::from_request(request, &()).await.ok()?;
+        multipart_image(multipart).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_multipart_image() {
+        let body = |name: &str, filename: &str| {
+            [
+                &b"preamble\r\n--XyZ\r\n\
+                Content-Disposition: form-data; name=\"note\"; filename=\"image\"\r\n\r\n\
+                hello\r\n--XyZ\r\n\
+                Content-Disposition: form-data; name=\""[..],
+                name.as_bytes(),
+                b"\"; filename=\"",
+                filename.as_bytes(),
+                b"\"\r\nContent-Type: image/png\r\n\r\n\x89PNG\r\n\x00\x01\r\n--XyZ--\r\n",
+            ]
+            .concat()
+        };
+        let content_type = "multipart/form-data; boundary=XyZ";
+        let png = &b"\x89PNG\r\n\x00\x01"[..];
+        let image = image_of(content_type, body("image", "card.png")).await;
+        assert_eq!(image.as_deref(), Some(png));
+        // Without a field named `image`, the first file, even one whose
+        // file name is "image"
+        let image = image_of(content_type, body("scan", "card.png")).await;
+        assert_eq!(image.as_deref(), Some(&b"hello"[..]));
+        assert!(image_of("multipart/form-data", body("image", "card.png"))
+            .await
+            .is_none());
+    }
+}
@@ -0,0 +1,332 @@
+//! SQLite store of the server's state
+//!
+//! With the `sqlite` feature and `server.database` configured, the server
+//! keeps the scan sets it knows, its jobs and the edits made to artifact
+//! text in one database, so it still knows them after a restart:
+//!
+//! ```text
+//! scan_sets (id, name, path, created_at)
+//! edits     (id, scan_set_id, artifact_id, source, line_number, ..., recorded_at)
+//! jobs      (id, scan_set_id, state, json)
+//! ```
+//!
+//! Artifacts are not copied here: they stay in each scan set's own store
+//! ([`core_pipeline::storage`]), which for scan sets the server creates is
+//! the SQLite backend. These tables only hold what belongs to the server.
+//! Edits are kept when a later run drops them from an artifact, so the
+//! history outlives a re-analysis.
+
+use crate::jobs::{Job, JobLog};
+use anyhow::{bail, Context, Result};
+use core_pipeline::scan_set;
+use core_pipeline::types::{PageArtifact, TextRevision};
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+/// Version of [`SCHEMA`], stored as the database's `user_version`
+const SCHEMA_VERSION: u32 = 2;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS scan_sets (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    path TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS edits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    scan_set_id TEXT NOT NULL,
+    artifact_id TEXT NOT NULL,
+    source TEXT NOT NULL,
+    line_number INTEGER NOT NULL,
+    col INTEGER NOT NULL,
+    before TEXT NOT NULL,
+    after TEXT NOT NULL,
+    reason TEXT NOT NULL,
+    confidence REAL NOT NULL,
+    recorded_at TEXT NOT NULL DEFAULT (datetime('now')),
+    UNIQUE (scan_set_id, artifact_id, source, line_number, col, before, after)
+);
+CREATE TABLE IF NOT EXISTS jobs (
+    id TEXT PRIMARY KEY,
+    scan_set_id TEXT NOT NULL,
+    state TEXT NOT NULL,
+    json TEXT NOT NULL
+);
+";
+
+/// A scan set recorded in the store
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredScanSet {
+    /// Scan set ID
+    pub id: String,
+    /// Scan set name
+    pub name: String,
+    /// Scan set directory
+    pub path: PathBuf,
+}
+
+/// The server's database
+#[derive(Debug)]
+pub struct ServerStore {
+    conn: Mutex<Connection>,
+}
+
+impl ServerStore {
+    /// Open the database, creating it if needed
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open database: {}", path.display()))?;
+        conn.execute_batch("PRAGMA foreign_keys = ON; PRAGMA journal_mode = WAL;")?;
+
+        let version: u32 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            bail!(
+                "Database {} has schema version {}, newer than this build supports ({})",
+                path.display(),
+                version,
+                SCHEMA_VERSION
+            );
+        }
+        // Version 1 copied the artifacts; they stay in the scan sets now
+        if version < 2 {
+            conn.execute_batch("DROP TABLE IF EXISTS artifacts")?;
+        }
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create server schema")?;
+        conn.execute_batch(&format!("PRAGMA user_version = {}", SCHEMA_VERSION))?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Record the scan set in `dir`, adding its artifacts' edits to the
+    /// history
+    pub fn add_scan_set(&self, dir: &Path) -> Result<()> {
+        let (manifest, artifacts) = scan_set::load(dir)?;
+        let id = manifest.scan_set_id.0.to_string();
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO scan_sets (id, name, path, created_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (id) DO UPDATE SET name = excluded.name, path = excluded.path",
+            params![
+                id,
+                manifest.name,
+                dir.to_string_lossy(),
+                manifest.created_at
+            ],
+        )?;
+        record_edits(&tx, &id, &artifacts)?;
+        tx.commit()
+            .with_context(|| format!("Failed to record scan set {}", dir.display()))
+    }
+
+    /// Recorded scan sets, oldest first
+    pub fn scan_sets(&self) -> Result<Vec<StoredScanSet>> {
+        let conn = self.conn();
+        let mut stmt =
+            conn.prepare("SELECT id, name, path FROM scan_sets ORDER BY created_at, id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok(StoredScanSet {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                path: PathBuf::from(row.get::<_, String>(2)?),
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Edits made to an artifact of a recorded scan set, oldest first
+    pub fn edits(&self, scan_set_id: &str, artifact_id: &str) -> Result<Vec<TextRevision>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT source, line_number, col, before, after, reason, confidence
+             FROM edits WHERE scan_set_id = ?1 AND artifact_id = ?2 ORDER BY id",
+        )?;
+        let rows = stmt.query_map([scan_set_id, artifact_id], |row| {
+            Ok(TextRevision {
+                source: row.get(0)?,
+                line_number: row.get::<_, i64>(1)? as usize,
+                column: row.get::<_, i64>(2)? as usize,
+                before: row.get(3)?,
+                after: row.get(4)?,
+                reason: row.get(5)?,
+                confidence: row.get(6)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Recorded jobs, oldest first
+    pub fn jobs(&self) -> Result<Vec<Job>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT id, json FROM jobs ORDER BY rowid")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut jobs = Vec::new();
+        for row in rows {
+            let (id, json) = row?;
+            jobs.push(
+                serde_json::from_str(&json)
+                    .with_context(|| format!("Failed to parse job {}", id))?,
+            );
+        }
+        Ok(jobs)
+    }
+
+    fn save_job(&self, job: &Job) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO jobs (id, scan_set_id, state, json) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (id) DO UPDATE SET state = excluded.state, json = excluded.json",
+            params![
+                job.id,
+                job.scan_set_id,
+                serde_json::to_value(job.state)?.as_str(),
+                serde_json::to_string(job)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Directory of a recorded scan set
+    pub fn scan_set_path(&self, scan_set_id: &str) -> Result<Option<PathBuf>> {
+        let path: Option<String> = self
+            .conn()
+            .query_row(
+                "SELECT path FROM scan_sets WHERE id = ?1",
+                [scan_set_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(path.map(PathBuf::from))
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().expect("database lock")
+    }
+}
+
+impl JobLog for ServerStore {
+    /// Save the job; once it finishes, read the scan set's artifacts again
+    fn record(&self, job: &Job) {
+        if let Err(e) = self.save_job(job) {
+            tracing::error!("Failed to record job {}: {:#}", job.id, e);
+        }
+        if !job.is_finished() {
+            return;
+        }
+        let refreshed = self
+            .scan_set_path(&job.scan_set_id)
+            .and_then(|path| path.map_or(Ok(()), |path| self.add_scan_set(&path)));
+        if let Err(e) = refreshed {
+            tracing::error!("Failed to record scan set {}: {:#}", job.scan_set_id, e);
+        }
+    }
+}
+
+/// Add the edits of a scan set's artifacts to the history
+fn record_edits(tx: &Transaction, scan_set_id: &str, artifacts: &[PageArtifact]) -> Result<()> {
+    for artifact in artifacts {
+        let id = artifact.id.0.to_string();
+        for r in &artifact.metadata.revisions {
+            tx.execute(
+                "INSERT OR IGNORE INTO edits
+                 (scan_set_id, artifact_id, source, line_number, col, before, after, reason, confidence)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                params![
+                    scan_set_id,
+                    id,
+                    r.source,
+                    r.line_number as i64,
+                    r.column as i64,
+                    r.before,
+                    r.after,
+                    r.reason,
+                    r.confidence,
+                ],
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jobs::JobKind;
+    use core_pipeline::keypunch::KeypunchModel;
+    use core_pipeline::storage::StorageBackend;
+    use core_pipeline::types::{ArtifactKind, PageId, PageMetadata};
+    use std::sync::Arc;
+
+    fn revision(after: &str) -> TextRevision {
+        TextRevision {
+            source: "manual".to_string(),
+            line_number: 1,
+            column: 7,
+            before: "O".to_string(),
+            after: after.to_string(),
+            reason: "typo".to_string(),
+            confidence: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_server_store() {
+        let data = tempfile::tempdir().unwrap();
+        let (dir, manifest) = scan3data::create_scan_set(
+            data.path(),
+            "Box 3",
+            KeypunchModel::default(),
+            StorageBackend::Sqlite,
+        )
+        .unwrap();
+        let id = manifest.scan_set_id.0.to_string();
+        let artifact = |revisions| PageArtifact {
+            id: PageId::new(),
+            scan_set: manifest.scan_set_id,
+            raw_image_path: PathBuf::from("images/a.jpg"),
+            processed_image_path: None,
+            layout_label: ArtifactKind::ListingSource,
+            content_text: None,
+            metadata: PageMetadata {
+                revisions,
+                ..PageMetadata::default()
+            },
+        };
+        let mut artifacts = vec![artifact(vec![revision("0")]), artifact(Vec::new())];
+        scan_set::save_artifacts(&dir, &artifacts).unwrap();
+
+        let db = data.path().join("server.sqlite");
+        let store = ServerStore::open(&db).unwrap();
+        store.add_scan_set(&dir).unwrap();
+        assert_eq!(store.scan_sets().unwrap()[0].name, "Box 3");
+        assert_eq!(store.scan_set_path(&id).unwrap(), Some(dir.clone()));
+        assert_eq!(store.scan_set_path("other").unwrap(), None);
+        // The artifacts stay in the scan set's own database
+        assert_eq!(StorageBackend::detect(&dir), StorageBackend::Sqlite);
+
+        // A later run replaced the edit; the history keeps both
+        artifacts[0].metadata.revisions = vec![revision("Q")];
+        scan_set::save_artifacts(&dir, &artifacts).unwrap();
+        drop(store);
+        let store = Arc::new(ServerStore::open(&db).unwrap());
+        let mut job = Job::new(JobKind::Analyze, &id);
+        job.state = crate::jobs::JobState::Completed;
+        store.record(&job);
+
+        let artifact_id = artifacts[0].id.0.to_string();
+        let edits = store.edits(&id, &artifact_id).unwrap();
+        let afters: Vec<&str> = edits.iter().map(|e| e.after.as_str()).collect();
+        assert_eq!(afters, ["0", "Q"]);
+        assert!(store.edits(&id, "unknown").unwrap().is_empty());
+        assert_eq!(store.jobs().unwrap(), vec![job]);
+    }
+}
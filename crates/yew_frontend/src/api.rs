@@ -0,0 +1,44 @@
+//! Requests to the scan3data API
+//!
+//! The API server also serves the frontend, so requests go to the page's
+//! own origin. A server with API keys needs one: open the page once with
+//! `?api_key=<key>`, and the key is kept in local storage for later visits.
+
+use gloo::storage::{LocalStorage, Storage};
+use gloo_net::http::RequestBuilder;
+
+/// Query parameter of the page URL carrying an API key
+const API_KEY_PARAM: &str = "api_key";
+
+/// Local storage entry keeping the API key
+const API_KEY_STORAGE: &str = "scan3data.api_key";
+
+/// Header the API key is sent in
+const API_KEY_HEADER: &str = "X-API-Key";
+
+/// API key from the page URL, else the one kept from an earlier visit
+fn api_key() -> Option<String> {
+    let search = gloo::utils::window().location().search().ok()?;
+    let from_url = web_sys::UrlSearchParams::new_with_str(&search)
+        .ok()
+        .and_then(|params| params.get(API_KEY_PARAM))
+        .filter(|key| !key.is_empty());
+    match from_url {
+        Some(key) => {
+            // Storage may be unavailable (private browsing); the key then
+            // lasts for this page
+            let _ = LocalStorage::set(API_KEY_STORAGE, &key);
+            Some(key)
+        }
+        None => LocalStorage::get(API_KEY_STORAGE).ok(),
+    }
+}
+
+/// POST to an API path, with the API key if there is one
+pub fn post(path: &str) -> RequestBuilder {
+    let request = gloo_net::http::Request::post(path);
+    match api_key() {
+        Some(key) => request.header(API_KEY_HEADER, &key),
+        None => request,
+    }
+}
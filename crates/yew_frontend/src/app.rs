@@ -1,5 +1,6 @@
 //! Main application component
 
+use crate::api;
 use crate::components::pipeline::{Pipeline, PipelineData, PipelineStage};
 use yew::prelude::*;
 
@@ -43,7 +44,7 @@ pub fn app() -> Html {
                             "image_data": base64_data
                         });
 
-                        match gloo_net::http::Request::post("http://localhost:7214/api/clean-image")
+                        match api::post("/api/clean-image")
                             .json(&request)
                             .unwrap()
                             .send()
@@ -81,12 +82,39 @@ pub fn app() -> Html {
         let pipeline_data = pipeline_data.clone();
         let current_stage = current_stage.clone();
         Callback::from(move |_| {
-            // TODO: Call OCR API
-            // For now, set placeholder text
-            let mut data = (*pipeline_data).clone();
-            data.raw_ocr_text = Some("OCR text will appear here...".to_string());
-            pipeline_data.set(data);
-            current_stage.set(PipelineStage::Validation);
+            let pipeline_data = pipeline_data.clone();
+            let current_stage = current_stage.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                // OCR the cleaned image shown next to the text; the API
+                // takes the data URL as is
+                let data = (*pipeline_data).clone();
+                let Some(data_url) = data.cleaned_image.or(data.original_image) else {
+                    return;
+                };
+                let request = serde_json::json!({
+                    "image_data": data_url
+                });
+
+                match api::post("/api/ocr").json(&request).unwrap().send().await {
+                    Ok(response) if response.ok() => {
+                        if let Ok(json) = response.json::<serde_json::Value>().await {
+                            if let Some(text) = json.get("text").and_then(|v| v.as_str()) {
+                                let mut new_data = (*pipeline_data).clone();
+                                new_data.raw_ocr_text = Some(text.to_string());
+                                pipeline_data.set(new_data);
+                                current_stage.set(PipelineStage::Validation);
+                            }
+                        }
+                    }
+                    Ok(response) => {
+                        gloo::console::error!("OCR failed with status", response.status());
+                    }
+                    Err(err) => {
+                        gloo::console::error!("Failed to run OCR:", err.to_string());
+                    }
+                }
+            });
         })
     };
 
@@ -1,5 +1,6 @@
 //! scan3data Yew frontend
 
+mod api;
 mod app;
 mod components;
 